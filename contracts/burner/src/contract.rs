@@ -1,10 +1,10 @@
-use cosmwasm_std::{
-    entry_point, BankMsg, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
-};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{BankMsg, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult};
 
 use crate::msg::{InstantiateMsg, MigrateMsg};
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     _deps: DepsMut,
     _env: Env,
@@ -16,7 +16,7 @@ pub fn instantiate(
     ))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
     // delete all state
     let keys: Vec<_> = deps
@@ -19,7 +19,7 @@
 
 use cosmwasm_std::{from_binary, Empty, Env, Response};
 use cosmwasm_vm::testing::{
-    execute, instantiate, mock_env, mock_info, mock_instance, mock_instance_with_gas_limit, query,
+    execute, instantiate, mock_env, mock_info, mock_instance, query, MockInstanceBuilder,
 };
 
 use cyberpunk::msg::{ExecuteMsg, QueryMsg};
@@ -28,7 +28,9 @@ static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/cy
 
 #[test]
 fn execute_argon2() {
-    let mut deps = mock_instance_with_gas_limit(WASM, 100_000_000_000_000);
+    let mut deps = MockInstanceBuilder::new(WASM)
+        .gas_limit(100_000_000_000_000)
+        .build();
 
     let init_info = mock_info("admin", &[]);
     let init_res: Response = instantiate(&mut deps, mock_env(), init_info, Empty {}).unwrap();
@@ -1,13 +1,15 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    entry_point, from_slice, to_binary, to_vec, AllBalanceResponse, BankMsg, Deps, DepsMut, Env,
-    Event, MessageInfo, QueryResponse, Response, StdError, StdResult,
+    from_slice, to_binary, to_vec, AllBalanceResponse, BankMsg, Deps, DepsMut, Env, Event,
+    MessageInfo, QueryResponse, Response, StdError, StdResult,
 };
 
 use crate::errors::HackError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, VerifierResponse};
 use crate::state::{State, CONFIG_KEY};
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
@@ -29,7 +31,7 @@ pub fn instantiate(
     Ok(Response::new().add_attribute("Let the", "hacking begin"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
     env: Env,
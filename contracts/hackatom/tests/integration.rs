@@ -24,8 +24,8 @@ use cosmwasm_std::{
 use cosmwasm_vm::{
     call_execute, from_slice,
     testing::{
-        execute, instantiate, migrate, mock_env, mock_info, mock_instance,
-        mock_instance_with_balances, query, sudo, test_io, MOCK_CONTRACT_ADDR,
+        execute, instantiate, migrate, mock_env, mock_info, mock_instance, query, sudo, test_io,
+        MockInstanceBuilder, MOCK_CONTRACT_ADDR,
     },
     Storage, VmError,
 };
@@ -35,6 +35,12 @@ use hackatom::state::{State, CONFIG_KEY};
 
 static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/hackatom.wasm");
 
+/// Size of the Wasm built by `cargo wasm` with Rust 1.95 when this was last updated. Update it
+/// when the contract or its dependencies change its size on purpose.
+const WASM_SIZE: usize = 225_660;
+/// Size differences between compiler versions that are tolerated
+const WASM_SIZE_TOLERANCE: usize = WASM_SIZE / 10;
+
 const DESERIALIZATION_LIMIT: usize = 20_000;
 
 fn make_init_msg() -> (InstantiateMsg, String) {
@@ -50,6 +56,17 @@ fn make_init_msg() -> (InstantiateMsg, String) {
     )
 }
 
+#[test]
+fn wasm_size_is_within_limit() {
+    assert!(
+        WASM.len() <= WASM_SIZE + WASM_SIZE_TOLERANCE,
+        "Wasm size of {} bytes exceeds the recorded size of {} bytes by more than {} bytes",
+        WASM.len(),
+        WASM_SIZE,
+        WASM_SIZE_TOLERANCE
+    );
+}
+
 #[test]
 fn proper_initialization() {
     let mut deps = mock_instance(WASM, &[]);
@@ -178,7 +195,9 @@ fn sudo_can_steal_tokens() {
 fn querier_callbacks_work() {
     let rich_addr = String::from("foobar");
     let rich_balance = coins(10000, "gold");
-    let mut deps = mock_instance_with_balances(WASM, &[(&rich_addr, &rich_balance)]);
+    let mut deps = MockInstanceBuilder::new(WASM)
+        .balances(&[(&rich_addr, &rich_balance)])
+        .build();
 
     // querying with balance gets the balance
     let query_msg = QueryMsg::OtherBalance { address: rich_addr };
@@ -1,5 +1,7 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    entry_point, from_slice, to_binary, DepsMut, Env, IbcBasicResponse, IbcChannelCloseMsg,
+    from_slice, to_binary, DepsMut, Env, IbcBasicResponse, IbcChannelCloseMsg,
     IbcChannelConnectMsg, IbcChannelOpenMsg, IbcMsg, IbcOrder, IbcPacketAckMsg,
     IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, StdError, StdResult,
 };
@@ -15,7 +17,7 @@ pub const IBC_APP_VERSION: &str = "ibc-reflect-v1";
 /// packets live one hour
 pub const PACKET_LIFETIME: u64 = 60 * 60;
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// enforces ordering and versioing constraints
 pub fn ibc_channel_open(_deps: DepsMut, _env: Env, msg: IbcChannelOpenMsg) -> StdResult<()> {
     let channel = msg.channel();
@@ -42,7 +44,7 @@ pub fn ibc_channel_open(_deps: DepsMut, _env: Env, msg: IbcChannelOpenMsg) -> St
     Ok(())
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// once it's established, we send a WhoAmI message
 pub fn ibc_channel_connect(
     deps: DepsMut,
@@ -71,7 +73,7 @@ pub fn ibc_channel_connect(
         .add_attribute("channel_id", channel_id))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// On closed channel, simply delete the account from our local store
 pub fn ibc_channel_close(
     deps: DepsMut,
@@ -89,7 +91,7 @@ pub fn ibc_channel_close(
         .add_attribute("channel_id", channel_id))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// never should be called as the other side never sends packets
 pub fn ibc_packet_receive(
     _deps: DepsMut,
@@ -101,7 +103,7 @@ pub fn ibc_packet_receive(
         .add_attribute("action", "ibc_packet_ack"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn ibc_packet_ack(
     deps: DepsMut,
     env: Env,
@@ -212,7 +214,7 @@ fn acknowledge_balances(
     Ok(IbcBasicResponse::new().add_attribute("action", "acknowledge_balances"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// we just ignore these now. shall we store some info?
 pub fn ibc_packet_timeout(
     _deps: DepsMut,
@@ -1,10 +1,11 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    entry_point, from_slice, to_binary, wasm_execute, BankMsg, Binary, CosmosMsg, Deps, DepsMut,
-    Empty, Env, Event, Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannelCloseMsg,
-    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg,
-    IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, Order,
-    QueryResponse, Reply, Response, StdError, StdResult, SubMsg, SubMsgResponse, SubMsgResult,
-    WasmMsg,
+    from_slice, to_binary, wasm_execute, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Empty, Env,
+    Event, Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg,
+    IbcChannelOpenMsg, IbcChannelOpenResponse, IbcOrder, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, MessageInfo, Order, QueryResponse, Reply, Response,
+    StdError, StdResult, SubMsg, SubMsgResponse, SubMsgResult, WasmMsg,
 };
 
 use crate::msg::{
@@ -17,7 +18,7 @@ pub const IBC_APP_VERSION: &str = "ibc-reflect-v1";
 pub const RECEIVE_DISPATCH_ID: u64 = 1234;
 pub const INIT_CALLBACK_ID: u64 = 7890;
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
@@ -33,7 +34,7 @@ pub fn instantiate(
     Ok(Response::new().add_attribute("action", "instantiate"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> StdResult<Response> {
     match (reply.id, reply.result) {
         (RECEIVE_DISPATCH_ID, SubMsgResult::Err(err)) => {
@@ -84,7 +85,7 @@ pub fn handle_init_callback(deps: DepsMut, response: SubMsgResponse) -> StdResul
     Ok(Response::new().add_attribute("action", "execute_init_callback"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
     match msg {
         QueryMsg::Account { channel_id } => to_binary(&query_account(deps, channel_id)?),
@@ -115,7 +116,7 @@ pub fn query_list_accounts(deps: Deps) -> StdResult<ListAccountsResponse> {
     })
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// enforces ordering and versioing constraints
 pub fn ibc_channel_open(
     _deps: DepsMut,
@@ -145,7 +146,7 @@ pub fn ibc_channel_open(
     }))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// once it's established, we create the reflect contract
 pub fn ibc_channel_connect(
     deps: DepsMut,
@@ -175,7 +176,7 @@ pub fn ibc_channel_connect(
         .add_event(Event::new("ibc").add_attribute("channel", "connect")))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// On closed channel, we take all tokens from reflect contract to this contract.
 /// We also delete the channel entry from accounts.
 pub fn ibc_channel_close(
@@ -214,7 +215,7 @@ pub fn ibc_channel_close(
 }
 
 /// this is a no-op just to test how this integrates with wasmd
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(_deps: DepsMut, _env: Env, _msg: Empty) -> StdResult<Response> {
     Ok(Response::default())
 }
@@ -225,7 +226,7 @@ fn encode_ibc_error(msg: impl Into<String>) -> Binary {
     to_binary(&AcknowledgementMsg::<()>::Err(msg.into())).unwrap()
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// we look for a the proper reflect contract to relay to and send the message
 /// We cannot return any meaningful response value as we do not know the response value
 /// of execution. We just return ok if we dispatched, error if we failed to dispatch
@@ -308,7 +309,7 @@ fn receive_dispatch(
         .add_attribute("action", "receive_dispatch"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// never should be called as we do not send packets
 pub fn ibc_packet_ack(
     _deps: DepsMut,
@@ -318,7 +319,7 @@ pub fn ibc_packet_ack(
     Ok(IbcBasicResponse::new().add_attribute("action", "ibc_packet_ack"))
 }
 
-#[entry_point]
+#[cfg_attr(not(feature = "library"), entry_point)]
 /// never should be called as we do not send packets
 pub fn ibc_packet_timeout(
     _deps: DepsMut,
@@ -20,8 +20,8 @@
 use cosmwasm_std::{from_binary, from_slice, MessageInfo, Response};
 use cosmwasm_vm::{
     testing::{
-        execute, instantiate, migrate, mock_env, mock_info, mock_instance_with_gas_limit, query,
-        MockApi, MockQuerier, MockStorage,
+        execute, instantiate, migrate, mock_env, mock_info, query, MockApi, MockInstanceBuilder,
+        MockQuerier, MockStorage,
     },
     Instance,
 };
@@ -34,10 +34,23 @@ use queue::state::Item;
 
 static WASM: &[u8] = include_bytes!("../target/wasm32-unknown-unknown/release/queue.wasm");
 
+/// The Wasm built without the size_optimized feature by `cargo wasm-unoptimized`.
+/// It is read at runtime, such that the other tests do not depend on this build.
+const UNOPTIMIZED_WASM_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/target/unoptimized/wasm32-unknown-unknown/release/queue.wasm"
+);
+
+/// Size of the Wasm built by `cargo wasm` with Rust 1.95 when this was last updated. Update it
+/// when the contract or its dependencies change its size on purpose.
+const WASM_SIZE: usize = 193_383;
+/// Size differences between compiler versions that are tolerated
+const WASM_SIZE_TOLERANCE: usize = WASM_SIZE / 10;
+
 /// Instantiates a contract with no elements
 fn create_contract() -> (Instance<MockApi, MockStorage, MockQuerier>, MessageInfo) {
     let gas_limit = 1_000_000_000_000; // ~1ms, enough for many executions within one instance
-    let mut deps = mock_instance_with_gas_limit(WASM, gas_limit);
+    let mut deps = MockInstanceBuilder::new(WASM).gas_limit(gas_limit).build();
     let creator = String::from("creator");
     let info = mock_info(&creator, &[]);
     let res: Response =
@@ -58,6 +71,33 @@ fn get_sum(deps: &mut Instance<MockApi, MockStorage, MockQuerier>) -> i32 {
     res.sum
 }
 
+#[test]
+fn wasm_size_is_within_limit() {
+    assert!(
+        WASM.len() <= WASM_SIZE + WASM_SIZE_TOLERANCE,
+        "Wasm size of {} bytes exceeds the recorded size of {} bytes by more than {} bytes",
+        WASM.len(),
+        WASM_SIZE,
+        WASM_SIZE_TOLERANCE
+    );
+}
+
+#[test]
+fn size_optimized_wasm_is_not_larger() {
+    let unoptimized = std::fs::read(UNOPTIMIZED_WASM_PATH).unwrap_or_else(|err| {
+        panic!(
+            "Error reading {}: {}. Build it using `cargo wasm-unoptimized`.",
+            UNOPTIMIZED_WASM_PATH, err
+        )
+    });
+    assert!(
+        WASM.len() <= unoptimized.len(),
+        "Wasm size of {} bytes exceeds the size of {} bytes without size_optimized",
+        WASM.len(),
+        unoptimized.len()
+    );
+}
+
 #[test]
 fn instantiate_and_query() {
     let (mut deps, _) = create_contract();
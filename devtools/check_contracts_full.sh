@@ -14,6 +14,8 @@ for contract_dir in contracts/*/; do
     cargo clippy --all-targets -- -D warnings
     cargo schema
     cargo wasm
+    # Contracts comparing their Wasm size to a build without the size_optimized feature
+    if grep -q "^wasm-unoptimized" .cargo/config; then cargo wasm-unoptimized; fi
     cargo integration-test
   )
 done
@@ -9,10 +9,15 @@ use serde::Deserialize;
 use digest::Digest;
 use k256::ecdsa::SigningKey; // type alias
 use k256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::ecdsa::signature::Signer; // trait
+use p256::elliptic_curve::rand_core::OsRng;
 use sha2::Sha256;
 
 use cosmwasm_crypto::{
-    ed25519_batch_verify, ed25519_verify, secp256k1_recover_pubkey, secp256k1_verify,
+    bls12_381_aggregate_verify, bls12_381_hash_to_g1, bls12_381_hash_to_g2,
+    bls12_381_pairing_equality, ed25519_batch_verify, ed25519_verify, poseidon_hash,
+    secp256k1_recover_pubkey, secp256k1_verify, secp256r1_recover_pubkey, secp256r1_verify,
+    POSEIDON_INPUT_LEN,
 };
 use std::cmp::min;
 
@@ -107,6 +112,41 @@ fn bench_crypto(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("secp256r1_verify", |b| {
+        let message_hash = Sha256::digest(b"Hello World!");
+        let secret_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let signature: p256::ecdsa::Signature = secret_key.sign(b"Hello World!");
+        let public_key = secret_key.verifying_key().to_encoded_point(false);
+        b.iter(|| {
+            assert!(
+                secp256r1_verify(&message_hash, signature.as_ref(), public_key.as_bytes()).unwrap()
+            );
+        });
+    });
+
+    group.bench_function("secp256r1_recover_pubkey", |b| {
+        let message_hash = Sha256::digest(b"Hello World!");
+        let secret_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let signature: p256::ecdsa::Signature = secret_key.sign(b"Hello World!");
+        let expected = secret_key
+            .verifying_key()
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec();
+        let recovery_param = (0..=1)
+            .find(|&param| {
+                secp256r1_recover_pubkey(&message_hash, signature.as_ref(), param).unwrap()
+                    == expected
+            })
+            .unwrap();
+        b.iter(|| {
+            let pubkey =
+                secp256r1_recover_pubkey(&message_hash, signature.as_ref(), recovery_param)
+                    .unwrap();
+            assert_eq!(pubkey, expected);
+        });
+    });
+
     group.bench_function("ed25519_verify", |b| {
         let message = hex::decode(COSMOS_ED25519_MSG_HEX).unwrap();
         let signature = hex::decode(COSMOS_ED25519_SIGNATURE_HEX).unwrap();
@@ -171,6 +211,118 @@ fn bench_crypto(c: &mut Criterion) {
         }
     }
 
+    // Poseidon hash of different input lengths
+    for n in [1, 2, 16] {
+        let inputs = vec![[7u8; POSEIDON_INPUT_LEN]; n];
+        let inputs: Vec<&[u8]> = inputs.iter().map(|i| i.as_slice()).collect();
+        group.bench_function(format!("poseidon_hash_{}", convert_no_fmt(n as i64)), |b| {
+            b.iter(|| {
+                poseidon_hash(&inputs).unwrap();
+            });
+        });
+    }
+
+    // BLS12-381 aggregate signatures of different sizes, with the same and distinct messages
+    {
+        const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+        let secrets: Vec<_> = (1..=16u64).map(bls12_381::Scalar::from).collect();
+        let messages: Vec<Vec<u8>> = (0..16u8).map(|i| vec![i; 32]).collect();
+        let messages: Vec<&[u8]> = messages.iter().map(|m| m.as_slice()).collect();
+        let pubkeys: Vec<u8> = secrets
+            .iter()
+            .flat_map(|secret| {
+                bls12_381::G1Affine::from(bls12_381::G1Affine::generator() * secret).to_compressed()
+            })
+            .collect();
+        let sign = |secret: &bls12_381::Scalar, message: &[u8]| {
+            let point =
+                bls12_381::G2Affine::from_compressed(&bls12_381_hash_to_g2(message, DST).unwrap())
+                    .unwrap();
+            bls12_381::G2Projective::from(point) * secret
+        };
+
+        for n in [1, 4, 16] {
+            let same_message = bls12_381::G2Affine::from(
+                secrets[..n]
+                    .iter()
+                    .map(|secret| sign(secret, messages[0]))
+                    .fold(bls12_381::G2Projective::identity(), |acc, sig| acc + sig),
+            )
+            .to_compressed();
+            let distinct_messages = bls12_381::G2Affine::from(
+                secrets[..n]
+                    .iter()
+                    .zip(&messages)
+                    .map(|(secret, message)| sign(secret, message))
+                    .fold(bls12_381::G2Projective::identity(), |acc, sig| acc + sig),
+            )
+            .to_compressed();
+            let pubkeys = &pubkeys[..n * 48];
+
+            group.bench_function(
+                format!(
+                    "bls12_381_aggregate_verify_same_message_{}",
+                    convert_no_fmt(n as i64)
+                ),
+                |b| {
+                    b.iter(|| {
+                        assert!(bls12_381_aggregate_verify(
+                            pubkeys,
+                            &messages[..1],
+                            &same_message,
+                            DST
+                        )
+                        .unwrap());
+                    });
+                },
+            );
+            group.bench_function(
+                format!(
+                    "bls12_381_aggregate_verify_distinct_messages_{}",
+                    convert_no_fmt(n as i64)
+                ),
+                |b| {
+                    b.iter(|| {
+                        assert!(bls12_381_aggregate_verify(
+                            pubkeys,
+                            &messages[..n],
+                            &distinct_messages,
+                            DST
+                        )
+                        .unwrap());
+                    });
+                },
+            );
+        }
+
+        let g2 = bls12_381::G2Affine::generator().to_compressed();
+        for n in [1, 4, 16] {
+            let sum = secrets[..n]
+                .iter()
+                .fold(bls12_381::Scalar::zero(), |acc, secret| acc + secret);
+            let r =
+                bls12_381::G1Affine::from(bls12_381::G1Affine::generator() * sum).to_compressed();
+            let qs = g2.repeat(n);
+            group.bench_function(
+                format!("bls12_381_pairing_equality_{}", convert_no_fmt(n as i64)),
+                |b| {
+                    b.iter(|| {
+                        assert!(
+                            bls12_381_pairing_equality(&pubkeys[..n * 48], &qs, &r, &g2).unwrap()
+                        );
+                    });
+                },
+            );
+        }
+
+        group.bench_function("bls12_381_hash_to_g1", |b| {
+            b.iter(|| bls12_381_hash_to_g1(&[7u8; 32], DST).unwrap());
+        });
+        group.bench_function("bls12_381_hash_to_g2", |b| {
+            b.iter(|| bls12_381_hash_to_g2(&[7u8; 32], DST).unwrap());
+        });
+    }
+
     group.finish();
 }
 
@@ -0,0 +1,353 @@
+use alloc::vec::Vec;
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt,
+};
+use sha2_v09::Sha256;
+
+use crate::errors::{CryptoError, CryptoResult};
+
+/// Length of a compressed G1 point, e.g. a public key in the Ethereum consensus layer or drand
+pub const BLS12_381_G1_POINT_LEN: usize = 48;
+
+/// Length of a compressed G2 point, e.g. a signature in the Ethereum consensus layer or drand
+pub const BLS12_381_G2_POINT_LEN: usize = 96;
+
+/// Max number of public keys of an aggregate signature and max number of pairs of a pairing
+/// equality check. This is the size of the Ethereum sync committee.
+pub const BLS12_381_MAX_AGGREGATE_SIZE: usize = 512;
+
+/// Verifies an aggregate BLS signature over the BLS12-381 curve with public keys in G1 and
+/// signatures in G2 (the "minimal-pubkey-size" variant used by the Ethereum consensus layer
+/// and drand).
+///
+/// - `pubkeys`: 1 to [`BLS12_381_MAX_AGGREGATE_SIZE`] concatenated compressed G1 points
+/// - `messages`: either a single message signed by all public keys (like `FastAggregateVerify`
+///   of the IETF BLS signature draft) or one message per public key (like `AggregateVerify`).
+///   Messages are hashed to G2 using `dst` as the domain separation tag.
+/// - `signature`: a compressed G2 point
+///
+/// The points must be valid and in the prime order subgroup. Public keys must not be the
+/// point at infinity. When multiple messages are used, they must be distinct unless the
+/// public keys come with a proof of possession.
+pub fn bls12_381_aggregate_verify(
+    pubkeys: &[u8],
+    messages: &[&[u8]],
+    signature: &[u8],
+    dst: &[u8],
+) -> CryptoResult<bool> {
+    check_dst(dst)?;
+    if pubkeys.is_empty() || pubkeys.len() % BLS12_381_G1_POINT_LEN != 0 {
+        return Err(CryptoError::invalid_pubkey_format());
+    }
+    let pubkey_count = pubkeys.len() / BLS12_381_G1_POINT_LEN;
+    if pubkey_count > BLS12_381_MAX_AGGREGATE_SIZE {
+        return Err(CryptoError::bls12_381_err("Too many public keys"));
+    }
+    if messages.len() != 1 && messages.len() != pubkey_count {
+        return Err(CryptoError::bls12_381_err(
+            "Number of messages must be 1 or match the number of public keys",
+        ));
+    }
+
+    let pubkeys = pubkeys
+        .chunks_exact(BLS12_381_G1_POINT_LEN)
+        .map(|pubkey| match read_g1(pubkey) {
+            Some(point) if !bool::from(point.is_identity()) => Ok(point),
+            _ => Err(CryptoError::invalid_pubkey_format()),
+        })
+        .collect::<CryptoResult<Vec<_>>>()?;
+    let signature = read_g2(signature).ok_or_else(CryptoError::invalid_signature_format)?;
+
+    // e(pk_1, H(m_1)) * ... * e(pk_n, H(m_n)) == e(g1, signature)
+    let mut terms: Vec<(G1Affine, G2Prepared)> = if let [message] = messages {
+        let aggregated = pubkeys
+            .iter()
+            .fold(G1Projective::identity(), |acc, pubkey| acc + pubkey);
+        Vec::from([(G1Affine::from(aggregated), hash_to_g2(message, dst))])
+    } else {
+        pubkeys
+            .into_iter()
+            .zip(messages)
+            .map(|(pubkey, message)| (pubkey, hash_to_g2(message, dst)))
+            .collect()
+    };
+    terms.push((-G1Affine::generator(), G2Prepared::from(signature)));
+
+    Ok(pairing_product_is_identity(&terms))
+}
+
+/// Checks the pairing equality `e(p_1, q_1) * ... * e(p_n, q_n) == e(r, s)` over the
+/// BLS12-381 curve.
+///
+/// - `ps`: 1 to [`BLS12_381_MAX_AGGREGATE_SIZE`] concatenated compressed G1 points
+/// - `qs`: the same number of concatenated compressed G2 points
+/// - `r`: a compressed G1 point
+/// - `s`: a compressed G2 point
+///
+/// All points must be valid and in the prime order subgroup.
+pub fn bls12_381_pairing_equality(ps: &[u8], qs: &[u8], r: &[u8], s: &[u8]) -> CryptoResult<bool> {
+    if ps.is_empty()
+        || ps.len() % BLS12_381_G1_POINT_LEN != 0
+        || qs.len() % BLS12_381_G2_POINT_LEN != 0
+        || ps.len() / BLS12_381_G1_POINT_LEN != qs.len() / BLS12_381_G2_POINT_LEN
+    {
+        return Err(CryptoError::bls12_381_err(
+            "Inputs must contain the same number of G1 and G2 points",
+        ));
+    }
+    if ps.len() / BLS12_381_G1_POINT_LEN > BLS12_381_MAX_AGGREGATE_SIZE {
+        return Err(CryptoError::bls12_381_err("Too many pairs"));
+    }
+
+    let mut terms = ps
+        .chunks_exact(BLS12_381_G1_POINT_LEN)
+        .zip(qs.chunks_exact(BLS12_381_G2_POINT_LEN))
+        .map(|(p, q)| Ok((read_g1_or_err(p)?, G2Prepared::from(read_g2_or_err(q)?))))
+        .collect::<CryptoResult<Vec<_>>>()?;
+    terms.push((-read_g1_or_err(r)?, G2Prepared::from(read_g2_or_err(s)?)));
+
+    Ok(pairing_product_is_identity(&terms))
+}
+
+/// Hashes a message to a G1 point using `BLS12381G1_XMD:SHA-256_SSWU_RO_` of RFC 9380
+/// with the domain separation tag `dst`. Returns the compressed point.
+pub fn bls12_381_hash_to_g1(message: &[u8], dst: &[u8]) -> CryptoResult<[u8; 48]> {
+    check_dst(dst)?;
+    let point = <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(message, dst);
+    Ok(G1Affine::from(point).to_compressed())
+}
+
+/// Hashes a message to a G2 point using `BLS12381G2_XMD:SHA-256_SSWU_RO_` of RFC 9380
+/// with the domain separation tag `dst`. Returns the compressed point.
+pub fn bls12_381_hash_to_g2(message: &[u8], dst: &[u8]) -> CryptoResult<[u8; 96]> {
+    check_dst(dst)?;
+    Ok(G2Affine::from(hash_to_g2_projective(message, dst)).to_compressed())
+}
+
+fn hash_to_g2_projective(message: &[u8], dst: &[u8]) -> G2Projective {
+    <G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(message, dst)
+}
+
+fn hash_to_g2(message: &[u8], dst: &[u8]) -> G2Prepared {
+    G2Prepared::from(G2Affine::from(hash_to_g2_projective(message, dst)))
+}
+
+fn pairing_product_is_identity(terms: &[(G1Affine, G2Prepared)]) -> bool {
+    let terms: Vec<(&G1Affine, &G2Prepared)> = terms.iter().map(|(p, q)| (p, q)).collect();
+    multi_miller_loop(&terms).final_exponentiation() == Gt::identity()
+}
+
+/// RFC 9380, section 3.1: Tags MUST have nonzero length
+fn check_dst(dst: &[u8]) -> CryptoResult<()> {
+    if dst.is_empty() {
+        return Err(CryptoError::bls12_381_err(
+            "Domain separation tag must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads a compressed G1 point, checking that it is on the curve and in the subgroup
+fn read_g1(data: &[u8]) -> Option<G1Affine> {
+    let data: &[u8; BLS12_381_G1_POINT_LEN] = data.try_into().ok()?;
+    G1Affine::from_compressed(data).into()
+}
+
+/// Reads a compressed G2 point, checking that it is on the curve and in the subgroup
+fn read_g2(data: &[u8]) -> Option<G2Affine> {
+    let data: &[u8; BLS12_381_G2_POINT_LEN] = data.try_into().ok()?;
+    G2Affine::from_compressed(data).into()
+}
+
+fn read_g1_or_err(data: &[u8]) -> CryptoResult<G1Affine> {
+    read_g1(data).ok_or_else(|| CryptoError::bls12_381_err("Invalid G1 point"))
+}
+
+fn read_g2_or_err(data: &[u8]) -> CryptoResult<G2Affine> {
+    read_g2(data).ok_or_else(|| CryptoError::bls12_381_err("Invalid G2 point"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::Scalar;
+
+    // The ciphersuite of the Ethereum consensus layer
+    const DST_POP: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+    fn keypair(seed: u64) -> (Scalar, [u8; 48]) {
+        let secret = Scalar::from(seed) * Scalar::from(0x9e3779b97f4a7c15);
+        let pubkey = G1Affine::from(G1Affine::generator() * secret).to_compressed();
+        (secret, pubkey)
+    }
+
+    fn sign(secret: &Scalar, message: &[u8]) -> G2Projective {
+        hash_to_g2_projective(message, DST_POP) * secret
+    }
+
+    fn compress(signature: G2Projective) -> [u8; 96] {
+        G2Affine::from(signature).to_compressed()
+    }
+
+    #[test]
+    fn bls12_381_aggregate_verify_works_for_single_signature() {
+        let (secret, pubkey) = keypair(1);
+        let signature = compress(sign(&secret, b"drand round 1"));
+
+        assert!(
+            bls12_381_aggregate_verify(&pubkey, &[b"drand round 1"], &signature, DST_POP).unwrap()
+        );
+        assert!(
+            !bls12_381_aggregate_verify(&pubkey, &[b"drand round 2"], &signature, DST_POP).unwrap()
+        );
+        // other DST
+        assert!(!bls12_381_aggregate_verify(
+            &pubkey,
+            &[b"drand round 1"],
+            &signature,
+            b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_"
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn bls12_381_aggregate_verify_works_for_same_message() {
+        let message: &[u8] = b"beacon block root";
+        let keypairs: Vec<_> = (1..=4).map(keypair).collect();
+        let pubkeys: Vec<u8> = keypairs.iter().flat_map(|(_, pubkey)| *pubkey).collect();
+        let signature = compress(
+            keypairs
+                .iter()
+                .map(|(secret, _)| sign(secret, message))
+                .fold(G2Projective::identity(), |acc, sig| acc + sig),
+        );
+
+        assert!(bls12_381_aggregate_verify(&pubkeys, &[message], &signature, DST_POP).unwrap());
+
+        // missing signer
+        let pubkeys = &pubkeys[..3 * BLS12_381_G1_POINT_LEN];
+        assert!(!bls12_381_aggregate_verify(pubkeys, &[message], &signature, DST_POP).unwrap());
+    }
+
+    #[test]
+    fn bls12_381_aggregate_verify_works_for_distinct_messages() {
+        let messages: [&[u8]; 3] = [b"one", b"two", b"three"];
+        let keypairs: Vec<_> = (1..=3).map(keypair).collect();
+        let pubkeys: Vec<u8> = keypairs.iter().flat_map(|(_, pubkey)| *pubkey).collect();
+        let signature = compress(
+            keypairs
+                .iter()
+                .zip(messages)
+                .map(|((secret, _), message)| sign(secret, message))
+                .fold(G2Projective::identity(), |acc, sig| acc + sig),
+        );
+
+        assert!(bls12_381_aggregate_verify(&pubkeys, &messages, &signature, DST_POP).unwrap());
+
+        let swapped: [&[u8]; 3] = [b"two", b"one", b"three"];
+        assert!(!bls12_381_aggregate_verify(&pubkeys, &swapped, &signature, DST_POP).unwrap());
+    }
+
+    #[test]
+    fn bls12_381_aggregate_verify_errs_for_invalid_inputs() {
+        let (secret, pubkey) = keypair(1);
+        let signature = compress(sign(&secret, b"msg"));
+
+        let err = bls12_381_aggregate_verify(&[], &[b"msg"], &signature, DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::InvalidPubkeyFormat { .. }));
+        let err =
+            bls12_381_aggregate_verify(&pubkey[1..], &[b"msg"], &signature, DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::InvalidPubkeyFormat { .. }));
+        // point at infinity
+        let identity = G1Affine::identity().to_compressed();
+        let err =
+            bls12_381_aggregate_verify(&identity, &[b"msg"], &signature, DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::InvalidPubkeyFormat { .. }));
+
+        let err =
+            bls12_381_aggregate_verify(&pubkey, &[b"msg"], &signature[1..], DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::InvalidSignatureFormat { .. }));
+        let mut invalid_signature = signature;
+        invalid_signature[95] ^= 0x01;
+        let err = bls12_381_aggregate_verify(&pubkey, &[b"msg"], &invalid_signature, DST_POP)
+            .unwrap_err();
+        assert!(matches!(err, CryptoError::InvalidSignatureFormat { .. }));
+
+        let err =
+            bls12_381_aggregate_verify(&pubkey, &[b"a", b"b"], &signature, DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+        let err = bls12_381_aggregate_verify(&pubkey, &[], &signature, DST_POP).unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+        let err = bls12_381_aggregate_verify(&pubkey, &[b"msg"], &signature, b"").unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+    }
+
+    #[test]
+    fn bls12_381_pairing_equality_works() {
+        let a = Scalar::from(7);
+        let b = Scalar::from(11);
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+
+        // e(a * g1, b * g2) * e(b * g1, a * g2) == e(2ab * g1, g2)
+        let ps = [
+            G1Affine::from(g1 * a).to_compressed(),
+            G1Affine::from(g1 * b).to_compressed(),
+        ]
+        .concat();
+        let qs = [
+            G2Affine::from(g2 * b).to_compressed(),
+            G2Affine::from(g2 * a).to_compressed(),
+        ]
+        .concat();
+        let r = G1Affine::from(g1 * (a * b + a * b)).to_compressed();
+        let s = g2.to_compressed();
+        assert!(bls12_381_pairing_equality(&ps, &qs, &r, &s).unwrap());
+
+        let r = G1Affine::from(g1 * (a * b)).to_compressed();
+        assert!(!bls12_381_pairing_equality(&ps, &qs, &r, &s).unwrap());
+
+        // mismatching number of points
+        let err = bls12_381_pairing_equality(&ps, &qs[..96], &r, &s).unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+        let err = bls12_381_pairing_equality(&[], &[], &r, &s).unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+        // invalid point
+        let err = bls12_381_pairing_equality(&ps, &qs, &[0u8; 48], &s).unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+    }
+
+    #[test]
+    fn bls12_381_hash_to_curve_works() {
+        // Test vectors from RFC 9380, appendix J.9.1 and J.10.1 (msg = "abc")
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let expected = G1Affine::from_uncompressed(&hex_literal::hex!(
+            "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903"
+            "0b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d"
+        ))
+        .unwrap();
+        assert_eq!(
+            bls12_381_hash_to_g1(b"abc", dst).unwrap(),
+            expected.to_compressed()
+        );
+
+        let dst = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+        let expected = G2Affine::from_uncompressed(&hex_literal::hex!(
+            "139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd8"
+            "02c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6"
+            "00aa65dae3c8d732d10ecd2c50f8a1baf3001578f71c694e03866e9f3d49ac1e1ce70dd94a733534f106d4cec0eddd16"
+            "1787327b68159716a37440985269cf584bcb1e621d3a7202be6ea05c4cfe244aeb197642555a0645fb87bf7466b2ba48"
+        ))
+        .unwrap();
+        assert_eq!(
+            bls12_381_hash_to_g2(b"abc", dst).unwrap(),
+            expected.to_compressed()
+        );
+
+        let err = bls12_381_hash_to_g1(b"abc", b"").unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+        let err = bls12_381_hash_to_g2(b"abc", b"").unwrap_err();
+        assert!(matches!(err, CryptoError::Bls12381Err { .. }));
+    }
+}
@@ -0,0 +1,27 @@
+use subtle::ConstantTimeEq; // trait
+
+/// Checks two byte strings for equality in constant time.
+///
+/// The time needed only depends on the lengths of the inputs but not on their contents,
+/// which makes this suitable for comparing secrets such as preimages of commitments.
+/// Inputs of different lengths are never equal.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_eq_works() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"foo", b"foo"));
+        assert!(ct_eq(&[0xAA; 1000], &[0xAA; 1000]));
+
+        assert!(!ct_eq(b"foo", b"bar"));
+        assert!(!ct_eq(b"foo", b"fop"));
+        assert!(!ct_eq(b"foo", b"fo"));
+        assert!(!ct_eq(b"", b"\0"));
+    }
+}
@@ -1,11 +1,17 @@
+use digest::Digest;
 use ed25519_zebra::{batch, Signature, VerificationKey};
-use rand_core::OsRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, RngCore, SeedableRng};
+use sha2::Sha256;
 
 use crate::errors::{CryptoError, CryptoResult};
 
 /// Length of a serialized public key
 pub const EDDSA_PUBKEY_LEN: usize = 32;
 
+/// Domain separator for deriving the batch verification RNG seed from the inputs
+const BATCH_SEED_DOMAIN: &[u8] = b"cosmwasm_ed25519_batch_verify";
+
 /// EdDSA ed25519 implementation.
 ///
 /// This function verifies messages against a signature, with the public key of the signer,
@@ -60,11 +66,115 @@ pub fn ed25519_verify(message: &[u8], signature: &[u8], public_key: &[u8]) -> Cr
 ///  - The "one-public key, with zero messages and zero signatures" case, is considered the empty
 /// case.
 ///  - The empty case (no messages, no signatures and no public keys) returns true.
+///
+/// The random coefficients of the batch equation are drawn from a ChaCha20 RNG seeded with a
+/// hash of all inputs. This makes the result independent of the environment, such that it
+/// can be used in consensus. Use [`ed25519_batch_verify_with_rng`] to provide a custom RNG.
 pub fn ed25519_batch_verify(
     messages: &[&[u8]],
     signatures: &[&[u8]],
     public_keys: &[&[u8]],
 ) -> CryptoResult<bool> {
+    let (messages, public_keys) = expand_batch_inputs(messages, signatures, public_keys)?;
+    let rng = ChaCha20Rng::from_seed(batch_seed(&messages, signatures, &public_keys));
+    verify_batch(rng, &messages, signatures, &public_keys)
+}
+
+/// Performs batch Ed25519 signature verification like [`ed25519_batch_verify`], using the given
+/// RNG for the random coefficients of the batch equation.
+///
+/// The RNG must be unpredictable for the creators of the signatures. Seeding it with a fixed
+/// value allows attackers to craft invalid signatures which pass batch verification.
+pub fn ed25519_batch_verify_with_rng<R: RngCore + CryptoRng>(
+    rng: R,
+    messages: &[&[u8]],
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+) -> CryptoResult<bool> {
+    let (messages, public_keys) = expand_batch_inputs(messages, signatures, public_keys)?;
+    verify_batch(rng, &messages, signatures, &public_keys)
+}
+
+/// The inputs of an Ed25519 batch verification, borrowed from a packed buffer.
+/// See [`ed25519_batch_decode_packed`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackedEd25519Batch<'a> {
+    pub messages: Vec<&'a [u8]>,
+    pub signatures: Vec<&'a [u8]>,
+    pub public_keys: Vec<&'a [u8]>,
+}
+
+/// Decodes the inputs of a batch verification from a single buffer without copying the items.
+///
+/// All numbers are big endian uint32. The buffer starts with the number of messages,
+/// signatures and public keys, followed by the end offset of each item relative to the
+/// start of the data, followed by the data of all items:
+///
+/// ```ignore
+/// message_count || signature_count || public_key_count || end_offset_1 || … || end_offset_n || data
+/// ```
+///
+/// Items are ordered messages first, then signatures, then public keys. Contracts create
+/// such buffers via the `encode_packed` function of cosmwasm-std.
+pub fn ed25519_batch_decode_packed(input: &[u8]) -> CryptoResult<PackedEd25519Batch<'_>> {
+    let invalid = || CryptoError::batch_err("Invalid packed batch input");
+    let read_u32 = |pos: usize| -> CryptoResult<usize> {
+        let bytes = input.get(pos..pos + 4).ok_or_else(invalid)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()) as usize)
+    };
+
+    let counts = [read_u32(0)?, read_u32(4)?, read_u32(8)?];
+    let item_count = counts
+        .iter()
+        .try_fold(0usize, |sum, count| sum.checked_add(*count))
+        .ok_or_else(invalid)?;
+    let data_start = item_count
+        .checked_add(3)
+        .and_then(|words| words.checked_mul(4))
+        .filter(|data_start| *data_start <= input.len())
+        .ok_or_else(invalid)?;
+    let data = &input[data_start..];
+
+    let mut items = Vec::with_capacity(item_count);
+    let mut start = 0usize;
+    for i in 0..item_count {
+        let end = read_u32(12 + 4 * i)?;
+        if end < start || end > data.len() {
+            return Err(invalid());
+        }
+        items.push(&data[start..end]);
+        start = end;
+    }
+    if start != data.len() {
+        return Err(invalid());
+    }
+
+    let public_keys = items.split_off(counts[0] + counts[1]);
+    let signatures = items.split_off(counts[0]);
+    Ok(PackedEd25519Batch {
+        messages: items,
+        signatures,
+        public_keys,
+    })
+}
+
+/// Performs batch Ed25519 signature verification like [`ed25519_batch_verify`] with inputs
+/// in the format of [`ed25519_batch_decode_packed`].
+pub fn ed25519_batch_verify_packed(input: &[u8]) -> CryptoResult<bool> {
+    let batch = ed25519_batch_decode_packed(input)?;
+    ed25519_batch_verify(&batch.messages, &batch.signatures, &batch.public_keys)
+}
+
+/// Messages and public keys of a batch, one per signature
+type ExpandedInputs<'a> = (Vec<&'a [u8]>, Vec<&'a [u8]>);
+
+/// Checks the number of inputs and replicates the message or the public key
+/// for the multisig and single signer variants, see [`ed25519_batch_verify`].
+fn expand_batch_inputs<'a>(
+    messages: &[&'a [u8]],
+    signatures: &[&[u8]],
+    public_keys: &[&'a [u8]],
+) -> CryptoResult<ExpandedInputs<'a>> {
     // Structural checks
     let messages_len = messages.len();
     let signatures_len = signatures.len();
@@ -86,7 +196,33 @@ pub fn ed25519_batch_verify(
     }
     debug_assert_eq!(messages.len(), signatures_len);
     debug_assert_eq!(messages.len(), public_keys.len());
+    Ok((messages, public_keys))
+}
 
+/// Derives a seed for the batch verification RNG from all inputs (after expansion).
+/// Lengths are included to make the encoding unambiguous.
+fn batch_seed(messages: &[&[u8]], signatures: &[&[u8]], public_keys: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(BATCH_SEED_DOMAIN);
+    for ((message, signature), public_key) in messages
+        .iter()
+        .zip(signatures.iter())
+        .zip(public_keys.iter())
+    {
+        for data in [message, signature, public_key] {
+            hasher.update((data.len() as u64).to_be_bytes());
+            hasher.update(data);
+        }
+    }
+    hasher.finalize().into()
+}
+
+fn verify_batch<R: RngCore + CryptoRng>(
+    rng: R,
+    messages: &[&[u8]],
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+) -> CryptoResult<bool> {
     let mut batch = batch::Verifier::new();
 
     for ((&message, &signature), &public_key) in messages
@@ -103,7 +239,7 @@ pub fn ed25519_batch_verify(
     }
 
     // Batch verification
-    match batch.verify(OsRng) {
+    match batch.verify(rng) {
         Ok(()) => Ok(true),
         Err(_) => Ok(false),
     }
@@ -139,6 +275,7 @@ fn read_pubkey(data: &[u8]) -> Result<[u8; 32], InvalidEd25519PubkeyFormat> {
 mod tests {
     use super::*;
     use ed25519_zebra::SigningKey;
+    use rand_core::OsRng;
     use serde::Deserialize;
 
     // For generic signature verification
@@ -284,6 +421,120 @@ mod tests {
 
         // ed25519_batch_verify() works
         assert!(ed25519_batch_verify(&messages, &signatures, &public_keys).unwrap());
+
+        // ed25519_batch_verify_with_rng() works
+        let rng = ChaCha20Rng::seed_from_u64(42);
+        assert!(ed25519_batch_verify_with_rng(rng, &messages, &signatures, &public_keys).unwrap());
+
+        // a single invalid signature fails the batch
+        let mut broken = signatures[1].to_vec();
+        broken[0] ^= 0x01;
+        let mut broken_signatures = signatures.clone();
+        broken_signatures[1] = &broken;
+        assert!(!ed25519_batch_verify(&messages, &broken_signatures, &public_keys).unwrap());
+        let rng = ChaCha20Rng::seed_from_u64(42);
+        assert!(
+            !ed25519_batch_verify_with_rng(rng, &messages, &broken_signatures, &public_keys)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ed25519_batch_decode_packed_works() {
+        let packed = [
+            &[0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1][..],
+            &[0, 0, 0, 5, 0, 0, 0, 5, 0, 0, 0, 9, 0, 0, 0, 13, 0, 0, 0, 16],
+            b"firstsig1sig2key",
+        ]
+        .concat();
+        let batch = ed25519_batch_decode_packed(&packed).unwrap();
+        assert_eq!(batch.messages, [b"first" as &[u8], b""]);
+        assert_eq!(batch.signatures, [b"sig1" as &[u8], b"sig2"]);
+        assert_eq!(batch.public_keys, [b"key" as &[u8]]);
+
+        let batch = ed25519_batch_decode_packed(&[0u8; 12]).unwrap();
+        assert!(batch.messages.is_empty());
+        assert!(batch.signatures.is_empty());
+        assert!(batch.public_keys.is_empty());
+    }
+
+    #[test]
+    fn test_ed25519_batch_decode_packed_fails_for_invalid_input() {
+        let packed = [
+            &[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1][..],
+            &[0, 0, 0, 3, 0, 0, 0, 6, 0, 0, 0, 9],
+            b"msgsigkey",
+        ]
+        .concat();
+        // too short
+        for len in [0, 11, 20, packed.len() - 1] {
+            let err = ed25519_batch_decode_packed(&packed[..len]).unwrap_err();
+            assert!(matches!(err, CryptoError::BatchErr { .. }), "{}", len);
+        }
+        // trailing data
+        let mut long = packed.clone();
+        long.push(0);
+        ed25519_batch_decode_packed(&long).unwrap_err();
+        // decreasing offsets
+        let mut decreasing = packed.clone();
+        decreasing[19] = 2;
+        ed25519_batch_decode_packed(&decreasing).unwrap_err();
+        // huge counts
+        let mut huge = packed;
+        huge[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        ed25519_batch_decode_packed(&huge).unwrap_err();
+    }
+
+    #[test]
+    fn test_cosmos_ed25519_batch_verify_packed() {
+        let code = &read_cosmos_sigs()[0];
+        let message = hex::decode(&code.message).unwrap();
+        let signature = hex::decode(&code.signature).unwrap();
+        let public_key = hex::decode(&code.public_key).unwrap();
+        // one message, signature and public key
+        let packed = |signature: &[u8]| {
+            let message_end = message.len() as u32;
+            [
+                &[0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1][..],
+                &message_end.to_be_bytes(),
+                &(message_end + 64).to_be_bytes(),
+                &(message_end + 96).to_be_bytes(),
+                &message,
+                signature,
+                &public_key,
+            ]
+            .concat()
+        };
+
+        assert!(ed25519_batch_verify_packed(&packed(&signature)).unwrap());
+
+        let mut broken = signature.clone();
+        broken[0] ^= 0x01;
+        assert!(!ed25519_batch_verify_packed(&packed(&broken)).unwrap());
+
+        // the same structural checks apply, here no message for the signature
+        let packed = [
+            &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1][..],
+            &[0, 0, 0, 64, 0, 0, 0, 96],
+            &signature,
+            &public_key,
+        ]
+        .concat();
+        let err = ed25519_batch_verify_packed(&packed).unwrap_err();
+        assert!(matches!(err, CryptoError::BatchErr { .. }));
+    }
+
+    #[test]
+    fn test_batch_seed_depends_on_all_inputs() {
+        let seed = batch_seed(&[b"msg"], &[b"sig"], &[b"key"]);
+        assert_eq!(seed, batch_seed(&[b"msg"], &[b"sig"], &[b"key"]));
+
+        assert_ne!(seed, batch_seed(&[b"msG"], &[b"sig"], &[b"key"]));
+        assert_ne!(seed, batch_seed(&[b"msg"], &[b"siG"], &[b"key"]));
+        assert_ne!(seed, batch_seed(&[b"msg"], &[b"sig"], &[b"keY"]));
+        // moving bytes between inputs changes the seed
+        assert_ne!(seed, batch_seed(&[b"ms"], &[b"gsig"], &[b"key"]));
+        assert_ne!(seed, batch_seed(&[], &[], &[]));
     }
 
     // structural tests
@@ -1,46 +1,99 @@
+use alloc::string::String;
+use core::fmt;
 #[cfg(feature = "backtraces")]
 use std::backtrace::Backtrace;
-use std::fmt::Debug;
-use thiserror::Error;
 
 pub type CryptoResult<T> = core::result::Result<T, CryptoError>;
 
-#[derive(Error, Debug)]
+#[derive(Debug)]
 pub enum CryptoError {
-    #[error("Batch verify error: {msg}")]
     BatchErr {
         msg: String,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Crypto error: {msg}")]
+    Bls12381Err {
+        msg: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
     GenericErr {
         msg: String,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Invalid hash format")]
+    Groth16Err {
+        msg: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    HashInputErr {
+        msg: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    Ics23Err {
+        msg: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    TendermintErr {
+        msg: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
     InvalidHashFormat {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Invalid public key format")]
+    InvalidHashFunction {
+        id: u32,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
     InvalidPubkeyFormat {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Invalid signature format")]
     InvalidSignatureFormat {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Invalid recovery parameter. Supported values: 0 and 1.")]
     InvalidRecoveryParam {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
 }
 
+// Implemented manually instead of via thiserror in order to support no_std builds
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::BatchErr { msg, .. } => write!(f, "Batch verify error: {}", msg),
+            CryptoError::Bls12381Err { msg, .. } => write!(f, "BLS12-381 error: {}", msg),
+            CryptoError::GenericErr { msg, .. } => write!(f, "Crypto error: {}", msg),
+            CryptoError::Groth16Err { msg, .. } => write!(f, "Groth16 error: {}", msg),
+            CryptoError::HashInputErr { msg, .. } => write!(f, "Invalid hash input: {}", msg),
+            CryptoError::Ics23Err { msg, .. } => write!(f, "ICS-23 proof error: {}", msg),
+            CryptoError::TendermintErr { msg, .. } => {
+                write!(f, "Tendermint header error: {}", msg)
+            }
+            CryptoError::InvalidHashFormat { .. } => f.write_str("Invalid hash format"),
+            CryptoError::InvalidHashFunction { id, .. } => {
+                write!(f, "Unknown hash function identifier: {}", id)
+            }
+            CryptoError::InvalidPubkeyFormat { .. } => f.write_str("Invalid public key format"),
+            CryptoError::InvalidSignatureFormat { .. } => f.write_str("Invalid signature format"),
+            CryptoError::InvalidRecoveryParam { .. } => {
+                f.write_str("Invalid recovery parameter. Supported values: 0 and 1.")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CryptoError {}
+
 impl CryptoError {
     pub fn batch_err(msg: impl Into<String>) -> Self {
         CryptoError::BatchErr {
@@ -50,6 +103,14 @@ impl CryptoError {
         }
     }
 
+    pub fn bls12_381_err(msg: impl Into<String>) -> Self {
+        CryptoError::Bls12381Err {
+            msg: msg.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
     pub fn generic_err(msg: impl Into<String>) -> Self {
         CryptoError::GenericErr {
             msg: msg.into(),
@@ -58,6 +119,38 @@ impl CryptoError {
         }
     }
 
+    pub fn groth16_err(msg: impl Into<String>) -> Self {
+        CryptoError::Groth16Err {
+            msg: msg.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn hash_input_err(msg: impl Into<String>) -> Self {
+        CryptoError::HashInputErr {
+            msg: msg.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn ics23_err(msg: impl Into<String>) -> Self {
+        CryptoError::Ics23Err {
+            msg: msg.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn tendermint_err(msg: impl Into<String>) -> Self {
+        CryptoError::TendermintErr {
+            msg: msg.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
     pub fn invalid_hash_format() -> Self {
         CryptoError::InvalidHashFormat {
             #[cfg(feature = "backtraces")]
@@ -65,6 +158,14 @@ impl CryptoError {
         }
     }
 
+    pub fn invalid_hash_function(id: u32) -> Self {
+        CryptoError::InvalidHashFunction {
+            id,
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
     pub fn invalid_pubkey_format() -> Self {
         CryptoError::InvalidPubkeyFormat {
             #[cfg(feature = "backtraces")]
@@ -95,7 +196,13 @@ impl CryptoError {
             CryptoError::InvalidPubkeyFormat { .. } => 5,
             CryptoError::InvalidRecoveryParam { .. } => 6,
             CryptoError::BatchErr { .. } => 7,
+            CryptoError::HashInputErr { .. } => 8,
+            CryptoError::Groth16Err { .. } => 9,
             CryptoError::GenericErr { .. } => 10,
+            CryptoError::InvalidHashFunction { .. } => 11,
+            CryptoError::Ics23Err { .. } => 12,
+            CryptoError::TendermintErr { .. } => 13,
+            CryptoError::Bls12381Err { .. } => 14,
         }
     }
 }
@@ -103,6 +210,18 @@ impl CryptoError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn display_works() {
+        let error = CryptoError::batch_err("wrong length");
+        assert_eq!(error.to_string(), "Batch verify error: wrong length");
+        let error = CryptoError::invalid_recovery_param();
+        assert_eq!(
+            error.to_string(),
+            "Invalid recovery parameter. Supported values: 0 and 1."
+        );
+    }
 
     // constructors
     #[test]
@@ -116,6 +235,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bls12_381_err_works() {
+        let error = CryptoError::bls12_381_err("invalid point");
+        match error {
+            CryptoError::Bls12381Err { msg, .. } => assert_eq!(msg, "invalid point"),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
     #[test]
     fn generic_err_works() {
         let error = CryptoError::generic_err("something went wrong in a general way");
@@ -127,6 +255,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn groth16_err_works() {
+        let error = CryptoError::groth16_err("invalid proof length");
+        match error {
+            CryptoError::Groth16Err { msg, .. } => assert_eq!(msg, "invalid proof length"),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
+    #[test]
+    fn hash_input_err_works() {
+        let error = CryptoError::hash_input_err("too many inputs");
+        match error {
+            CryptoError::HashInputErr { msg, .. } => assert_eq!(msg, "too many inputs"),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
+    #[test]
+    fn ics23_err_works() {
+        let error = CryptoError::ics23_err("unexpected end of proof");
+        match error {
+            CryptoError::Ics23Err { msg, .. } => assert_eq!(msg, "unexpected end of proof"),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
+    #[test]
+    fn tendermint_err_works() {
+        let error = CryptoError::tendermint_err("too many validators");
+        match error {
+            CryptoError::TendermintErr { msg, .. } => assert_eq!(msg, "too many validators"),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
     #[test]
     fn invalid_hash_format_works() {
         let error = CryptoError::invalid_hash_format();
@@ -136,6 +300,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn invalid_hash_function_works() {
+        let error = CryptoError::invalid_hash_function(42);
+        match error {
+            CryptoError::InvalidHashFunction { id, .. } => assert_eq!(id, 42),
+            _ => panic!("wrong error type!"),
+        }
+    }
+
     #[test]
     fn invalid_signature_format_works() {
         let error = CryptoError::invalid_signature_format();
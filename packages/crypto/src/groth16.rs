@@ -0,0 +1,328 @@
+use alloc::vec::Vec;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
+use ark_ff::{BigInteger256, One, PrimeField, Zero};
+
+use crate::errors::{CryptoError, CryptoResult};
+
+/// Length of a big-endian encoded BN254 base or scalar field element
+const FIELD_LEN: usize = 32;
+const G1_LEN: usize = 2 * FIELD_LEN;
+const G2_LEN: usize = 4 * FIELD_LEN;
+
+/// Length of a proof, i.e. the points A (G1), B (G2) and C (G1)
+pub const GROTH16_PROOF_LEN: usize = 2 * G1_LEN + G2_LEN;
+
+/// Length of a public input, i.e. a big-endian encoded BN254 scalar field element
+pub const GROTH16_PUBLIC_INPUT_LEN: usize = FIELD_LEN;
+
+/// Max number of public inputs of a verification key
+pub const GROTH16_MAX_PUBLIC_INPUTS: usize = 64;
+
+/// Length of a verification key without the points for the public inputs, i.e. the
+/// points alpha (G1), beta (G2), gamma (G2), delta (G2) and the constant term of IC (G1).
+const VK_BASE_LEN: usize = 2 * G1_LEN + 3 * G2_LEN;
+
+/// Max length of a verification key
+pub const GROTH16_VK_MAX_LEN: usize = VK_BASE_LEN + GROTH16_MAX_PUBLIC_INPUTS * G1_LEN;
+
+/// Verifies a Groth16 proof over the BN254 curve.
+///
+/// The encoding follows the one of the Ethereum precompiles (EIP-196/EIP-197) as used by
+/// snarkjs generated verifiers, i.e. all field elements are 32 bytes big-endian, G1 points
+/// are encoded as `x || y` and G2 points as `x.c1 || x.c0 || y.c1 || y.c0`. The point at
+/// infinity is encoded as all zeros.
+///
+/// - `vk`: `alpha (G1) || beta (G2) || gamma (G2) || delta (G2) || IC (G1 * (n + 1))`
+/// - `proof`: `A (G1) || B (G2) || C (G1)`
+/// - `public_inputs`: `n` concatenated scalar field elements
+pub fn zk_verify_groth16(vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> CryptoResult<bool> {
+    if vk.len() < VK_BASE_LEN || vk.len() > GROTH16_VK_MAX_LEN {
+        return Err(CryptoError::groth16_err("Invalid verification key length"));
+    }
+    let input_count = (vk.len() - VK_BASE_LEN) / G1_LEN;
+    if vk.len() != VK_BASE_LEN + input_count * G1_LEN {
+        return Err(CryptoError::groth16_err("Invalid verification key length"));
+    }
+    if proof.len() != GROTH16_PROOF_LEN {
+        return Err(CryptoError::groth16_err("Invalid proof length"));
+    }
+    if public_inputs.len() != input_count * GROTH16_PUBLIC_INPUT_LEN {
+        return Err(CryptoError::groth16_err(format!(
+            "Verification key requires {} public inputs",
+            input_count
+        )));
+    }
+
+    let (alpha, rest) = read_g1(vk)?;
+    let (beta, rest) = read_g2(rest)?;
+    let (gamma, rest) = read_g2(rest)?;
+    let (delta, mut rest) = read_g2(rest)?;
+    let mut ic = Vec::with_capacity(input_count + 1);
+    while !rest.is_empty() {
+        let (point, remaining) = read_g1(rest)?;
+        ic.push(point);
+        rest = remaining;
+    }
+
+    let (a, rest) = read_g1(proof)?;
+    let (b, rest) = read_g2(rest)?;
+    let (c, _) = read_g1(rest)?;
+
+    let mut vk_x: G1Projective = ic[0].into_projective();
+    for (input, point) in public_inputs
+        .chunks_exact(GROTH16_PUBLIC_INPUT_LEN)
+        .zip(&ic[1..])
+    {
+        let input: Fr =
+            read_field(input).ok_or_else(|| CryptoError::groth16_err("Invalid public input"))?;
+        vk_x += &point.mul(input.into_repr());
+    }
+
+    // e(A, B) = e(alpha, beta) * e(vk_x, gamma) * e(C, delta)
+    let pairings = [
+        (a.into(), b.into()),
+        ((-vk_x.into_affine()).into(), gamma.into()),
+        ((-c).into(), delta.into()),
+        ((-alpha).into(), beta.into()),
+    ];
+    Ok(Bn254::product_of_pairings(&pairings).is_one())
+}
+
+/// Reads a big-endian encoded field element. Returns None if it is not smaller than the modulus.
+fn read_field<F: PrimeField<BigInt = BigInteger256>>(data: &[u8]) -> Option<F> {
+    let mut limbs = [0u64; 4];
+    for (i, chunk) in data.chunks_exact(8).enumerate() {
+        limbs[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    F::from_repr(BigInteger256(limbs))
+}
+
+fn read_fq(data: &[u8]) -> CryptoResult<Fq> {
+    read_field(data).ok_or_else(|| CryptoError::groth16_err("Invalid field element"))
+}
+
+fn read_g1(data: &[u8]) -> CryptoResult<(G1Affine, &[u8])> {
+    let (point, rest) = data.split_at(G1_LEN);
+    let x = read_fq(&point[..FIELD_LEN])?;
+    let y = read_fq(&point[FIELD_LEN..])?;
+    let point = if x.is_zero() && y.is_zero() {
+        G1Affine::zero()
+    } else {
+        G1Affine::new(x, y, false)
+    };
+    // G1 has cofactor 1, so every point on the curve is in the correct subgroup
+    if !point.is_on_curve() {
+        return Err(CryptoError::groth16_err("Invalid G1 point"));
+    }
+    Ok((point, rest))
+}
+
+fn read_g2(data: &[u8]) -> CryptoResult<(G2Affine, &[u8])> {
+    let (point, rest) = data.split_at(G2_LEN);
+    let x = Fq2::new(
+        read_fq(&point[FIELD_LEN..2 * FIELD_LEN])?,
+        read_fq(&point[..FIELD_LEN])?,
+    );
+    let y = Fq2::new(
+        read_fq(&point[3 * FIELD_LEN..])?,
+        read_fq(&point[2 * FIELD_LEN..3 * FIELD_LEN])?,
+    );
+    let point = if x.is_zero() && y.is_zero() {
+        G2Affine::zero()
+    } else {
+        G2Affine::new(x, y, false)
+    };
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(CryptoError::groth16_err("Invalid G2 point"));
+    }
+    Ok((point, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::{create_random_proof, generate_random_parameters, Proof, VerifyingKey};
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_std::test_rng;
+    use hex_literal::hex;
+
+    /// Proves knowledge of `a` and `b` with `a * b = c` for a public `c`
+    struct MultiplyCircuit {
+        a: Option<Fr>,
+        b: Option<Fr>,
+    }
+
+    impl ConstraintSynthesizer<Fr> for MultiplyCircuit {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let a = cs.new_witness_variable(|| self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.new_witness_variable(|| self.b.ok_or(SynthesisError::AssignmentMissing))?;
+            let c = cs.new_input_variable(|| {
+                let mut a = self.a.ok_or(SynthesisError::AssignmentMissing)?;
+                let b = self.b.ok_or(SynthesisError::AssignmentMissing)?;
+                a *= &b;
+                Ok(a)
+            })?;
+            cs.enforce_constraint(lc!() + a, lc!() + b, lc!() + c)
+        }
+    }
+
+    fn write_field<F: PrimeField<BigInt = BigInteger256>>(out: &mut Vec<u8>, value: F) {
+        for limb in value.into_repr().0.iter().rev() {
+            out.extend_from_slice(&limb.to_be_bytes());
+        }
+    }
+
+    fn write_g1(out: &mut Vec<u8>, point: G1Affine) {
+        write_field(out, point.x);
+        write_field(out, point.y);
+    }
+
+    fn write_g2(out: &mut Vec<u8>, point: G2Affine) {
+        write_field(out, point.x.c1);
+        write_field(out, point.x.c0);
+        write_field(out, point.y.c1);
+        write_field(out, point.y.c0);
+    }
+
+    fn encode_vk(vk: &VerifyingKey<Bn254>) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_g1(&mut out, vk.alpha_g1);
+        write_g2(&mut out, vk.beta_g2);
+        write_g2(&mut out, vk.gamma_g2);
+        write_g2(&mut out, vk.delta_g2);
+        for point in &vk.gamma_abc_g1 {
+            write_g1(&mut out, *point);
+        }
+        out
+    }
+
+    fn encode_proof(proof: &Proof<Bn254>) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_g1(&mut out, proof.a);
+        write_g2(&mut out, proof.b);
+        write_g1(&mut out, proof.c);
+        out
+    }
+
+    fn encode_input(value: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_field(&mut out, Fr::from(value));
+        out
+    }
+
+    /// Returns an encoded verification key and a proof for `3 * 11 = 33`
+    fn setup() -> (Vec<u8>, Vec<u8>) {
+        let mut rng = test_rng();
+        let params = generate_random_parameters::<Bn254, _, _>(
+            MultiplyCircuit { a: None, b: None },
+            &mut rng,
+        )
+        .unwrap();
+        let circuit = MultiplyCircuit {
+            a: Some(Fr::from(3u64)),
+            b: Some(Fr::from(11u64)),
+        };
+        let proof = create_random_proof(circuit, &params, &mut rng).unwrap();
+        (encode_vk(&params.vk), encode_proof(&proof))
+    }
+
+    #[test]
+    fn read_g2_uses_eip197_encoding() {
+        // generator of G2 as specified in EIP-197, imaginary parts first
+        let encoded = hex!(
+            "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2"
+            "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed"
+            "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b"
+            "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa"
+        );
+        let (point, rest) = read_g2(&encoded).unwrap();
+        assert_eq!(point, G2Affine::prime_subgroup_generator());
+        assert!(rest.is_empty());
+
+        let mut out = Vec::new();
+        write_g2(&mut out, point);
+        assert_eq!(out, encoded);
+    }
+
+    #[test]
+    fn read_g1_works_for_point_at_infinity() {
+        let (point, _) = read_g1(&[0u8; G1_LEN]).unwrap();
+        assert!(point.is_zero());
+    }
+
+    #[test]
+    fn zk_verify_groth16_works() {
+        let (vk, proof) = setup();
+        assert_eq!(vk.len(), VK_BASE_LEN + G1_LEN);
+        assert_eq!(proof.len(), GROTH16_PROOF_LEN);
+
+        assert!(zk_verify_groth16(&vk, &proof, &encode_input(33)).unwrap());
+    }
+
+    #[test]
+    fn zk_verify_groth16_fails_for_wrong_input() {
+        let (vk, proof) = setup();
+        assert!(!zk_verify_groth16(&vk, &proof, &encode_input(34)).unwrap());
+    }
+
+    #[test]
+    fn zk_verify_groth16_fails_for_modified_proof() {
+        let (vk, mut proof) = setup();
+        // swap A and C, which are both valid G1 points
+        let a = proof[..G1_LEN].to_vec();
+        let c = proof[G1_LEN + G2_LEN..].to_vec();
+        proof[..G1_LEN].copy_from_slice(&c);
+        proof[G1_LEN + G2_LEN..].copy_from_slice(&a);
+        assert!(!zk_verify_groth16(&vk, &proof, &encode_input(33)).unwrap());
+    }
+
+    #[test]
+    fn zk_verify_groth16_errs_for_invalid_lengths() {
+        let (vk, proof) = setup();
+        let input = encode_input(33);
+
+        let err = zk_verify_groth16(&vk[1..], &proof, &input).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+        let err = zk_verify_groth16(&vk[..VK_BASE_LEN - G1_LEN], &proof, &[]).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+        let err = zk_verify_groth16(&vk, &proof[1..], &input).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+        let err = zk_verify_groth16(&vk, &proof, &[]).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+        let err = zk_verify_groth16(&vk, &proof, &[input.clone(), input].concat()).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+    }
+
+    #[test]
+    fn zk_verify_groth16_errs_for_invalid_points() {
+        let (vk, proof) = setup();
+        let input = encode_input(33);
+
+        // A not on curve
+        let mut modified = proof.clone();
+        modified[G1_LEN - 1] ^= 0x01;
+        let err = zk_verify_groth16(&vk, &modified, &input).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+
+        // B not on curve
+        let mut modified = proof.clone();
+        modified[G1_LEN + G2_LEN - 1] ^= 0x01;
+        let err = zk_verify_groth16(&vk, &modified, &input).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+
+        // coordinate not smaller than the modulus
+        let mut modified = proof;
+        modified[..FIELD_LEN].copy_from_slice(&[0xff; FIELD_LEN]);
+        let err = zk_verify_groth16(&vk, &modified, &input).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+    }
+
+    #[test]
+    fn zk_verify_groth16_errs_for_invalid_public_input() {
+        let (vk, proof) = setup();
+        let err = zk_verify_groth16(&vk, &proof, &[0xff; GROTH16_PUBLIC_INPUT_LEN]).unwrap_err();
+        assert!(matches!(err, CryptoError::Groth16Err { .. }));
+    }
+}
@@ -0,0 +1,68 @@
+use digest::Digest;
+use ripemd::Ripemd160;
+use sha2::Sha256;
+
+/// Length of a RIPEMD-160 hash in bytes
+pub const RIPEMD160_LEN: usize = 20;
+
+/// Length of a SHA-256 hash in bytes
+pub const SHA256_LEN: usize = 32;
+
+/// Calculates the RIPEMD-160 hash of the given data
+pub fn ripemd160(data: &[u8]) -> [u8; RIPEMD160_LEN] {
+    Ripemd160::digest(data).into()
+}
+
+/// Calculates the SHA-256 hash of the given data
+pub fn sha256(data: &[u8]) -> [u8; SHA256_LEN] {
+    Sha256::digest(data).into()
+}
+
+/// Calculates `ripemd160(sha256(data))`.
+///
+/// This is how Cosmos SDK and Bitcoin derive the address of a secp256k1 public key
+/// in compressed form. The result is the raw (canonical) address.
+pub fn hash160(data: &[u8]) -> [u8; RIPEMD160_LEN] {
+    ripemd160(&sha256(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn ripemd160_works() {
+        // test vectors from https://homes.esat.kuleuven.be/~bosselae/ripemd160.html
+        assert_eq!(
+            ripemd160(b""),
+            hex!("9c1185a5c5e9fc54612808977ee8f548b2258d31")
+        );
+        assert_eq!(
+            ripemd160(b"abc"),
+            hex!("8eb208f7e05d987a9b044a8e98c6b087f15a0bfc")
+        );
+        assert_eq!(
+            ripemd160(b"message digest"),
+            hex!("5d0689ef49d2fae572b881b123a85ffa21595f36")
+        );
+    }
+
+    #[test]
+    fn sha256_works() {
+        assert_eq!(
+            sha256(b"abc"),
+            hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+
+    #[test]
+    fn hash160_works() {
+        // example from https://en.bitcoin.it/wiki/Technical_background_of_version_1_Bitcoin_addresses
+        let pubkey = hex!("0250863ad64a87ae8a2fe83c1af1a8403cb53f53e486d8511dad8a04887e5b2352");
+        assert_eq!(
+            hash160(&pubkey),
+            hex!("f54a5851e9372b87810a8e60cdd2e7cfd80b6e31")
+        );
+    }
+}
@@ -0,0 +1,936 @@
+//! Verification of [ICS-23](https://github.com/cosmos/ics23) commitment proofs.
+//!
+//! Proofs are passed in the protobuf encoding of `cosmos.ics23.v1.CommitmentProof` as produced
+//! by the Cosmos SDK (e.g. via ABCI queries with `prove = true`). Only existence and
+//! non-existence proofs are supported. Batch and compressed proofs are rejected.
+use alloc::vec::Vec;
+use digest::Digest;
+use ripemd::Ripemd160;
+use sha2::{Sha256, Sha512, Sha512_256};
+
+use crate::errors::{CryptoError, CryptoResult};
+use crate::proto::{encode_varint, ProtoReader, ProtoValue};
+
+/// Identifier of the proof spec of IAVL trees (Cosmos SDK multistore substores)
+pub const ICS23_SPEC_IAVL: u32 = 1;
+/// Identifier of the proof spec of Tendermint simple Merkle trees (Cosmos SDK multistore root)
+pub const ICS23_SPEC_TENDERMINT: u32 = 2;
+
+/// Max length of an encoded commitment proof
+pub const ICS23_PROOF_MAX_LEN: usize = 64 * 1024;
+/// Max length of a commitment root
+pub const ICS23_ROOT_MAX_LEN: usize = 64;
+/// Max length of a key to be proven
+pub const ICS23_KEY_MAX_LEN: usize = 4 * 1024;
+/// Max length of a value to be proven
+pub const ICS23_VALUE_MAX_LEN: usize = 128 * 1024;
+
+/// Verifies that `proof` proves the existence of `key` with `value` in the tree with the
+/// given `root`, using the proof spec with the identifier `spec_id` ([`ICS23_SPEC_IAVL`]
+/// or [`ICS23_SPEC_TENDERMINT`]).
+///
+/// Returns an error for unknown spec identifiers and malformed proofs and `false` if the
+/// proof is well-formed but does not prove the claim.
+pub fn ics23_verify_membership(
+    spec_id: u32,
+    root: &[u8],
+    proof: &[u8],
+    key: &[u8],
+    value: &[u8],
+) -> CryptoResult<bool> {
+    let spec = ProofSpec::from_id(spec_id)?;
+    match decode_commitment_proof(proof)? {
+        CommitmentProof::Exist(proof) => Ok(verify_existence(&proof, &spec, root, key, value)),
+        CommitmentProof::Nonexist(_) => Err(CryptoError::ics23_err(
+            "Expected an existence proof, got a non-existence proof",
+        )),
+    }
+}
+
+/// Verifies that `proof` proves the absence of `key` in the tree with the given `root`,
+/// using the proof spec with the identifier `spec_id` ([`ICS23_SPEC_IAVL`] or
+/// [`ICS23_SPEC_TENDERMINT`]).
+///
+/// Returns an error for unknown spec identifiers and malformed proofs and `false` if the
+/// proof is well-formed but does not prove the claim.
+pub fn ics23_verify_non_membership(
+    spec_id: u32,
+    root: &[u8],
+    proof: &[u8],
+    key: &[u8],
+) -> CryptoResult<bool> {
+    let spec = ProofSpec::from_id(spec_id)?;
+    match decode_commitment_proof(proof)? {
+        CommitmentProof::Nonexist(proof) => Ok(verify_non_existence(&proof, &spec, root, key)),
+        CommitmentProof::Exist(_) => Err(CryptoError::ics23_err(
+            "Expected a non-existence proof, got an existence proof",
+        )),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HashOp {
+    NoHash,
+    Sha256,
+    Sha512,
+    Ripemd160,
+    /// `ripemd160(sha256(x))`
+    Bitcoin,
+    Sha512_256,
+}
+
+impl HashOp {
+    fn from_proto(value: u64) -> CryptoResult<Self> {
+        match value {
+            0 => Ok(HashOp::NoHash),
+            1 => Ok(HashOp::Sha256),
+            2 => Ok(HashOp::Sha512),
+            4 => Ok(HashOp::Ripemd160),
+            5 => Ok(HashOp::Bitcoin),
+            6 => Ok(HashOp::Sha512_256),
+            _ => Err(CryptoError::ics23_err("Unsupported hash operation")),
+        }
+    }
+
+    fn apply(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashOp::NoHash => data.to_vec(),
+            HashOp::Sha256 => Sha256::digest(data).to_vec(),
+            HashOp::Sha512 => Sha512::digest(data).to_vec(),
+            HashOp::Ripemd160 => Ripemd160::digest(data).to_vec(),
+            HashOp::Bitcoin => Ripemd160::digest(Sha256::digest(data)).to_vec(),
+            HashOp::Sha512_256 => Sha512_256::digest(data).to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LengthOp {
+    NoPrefix,
+    /// Protobuf style varint length prefix
+    VarProto,
+    Require32Bytes,
+    Require64Bytes,
+}
+
+impl LengthOp {
+    fn from_proto(value: u64) -> CryptoResult<Self> {
+        match value {
+            0 => Ok(LengthOp::NoPrefix),
+            1 => Ok(LengthOp::VarProto),
+            7 => Ok(LengthOp::Require32Bytes),
+            8 => Ok(LengthOp::Require64Bytes),
+            _ => Err(CryptoError::ics23_err("Unsupported length operation")),
+        }
+    }
+
+    fn apply(self, data: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            LengthOp::NoPrefix => Some(data.to_vec()),
+            LengthOp::VarProto => {
+                let mut out = Vec::with_capacity(data.len() + 10);
+                encode_varint(data.len() as u64, &mut out);
+                out.extend_from_slice(data);
+                Some(out)
+            }
+            LengthOp::Require32Bytes => (data.len() == 32).then(|| data.to_vec()),
+            LengthOp::Require64Bytes => (data.len() == 64).then(|| data.to_vec()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LeafOp {
+    hash: HashOp,
+    prehash_key: HashOp,
+    prehash_value: HashOp,
+    length: LengthOp,
+    prefix: Vec<u8>,
+}
+
+impl LeafOp {
+    fn apply(&self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        if key.is_empty() || value.is_empty() {
+            return None;
+        }
+        let key = self.length.apply(&self.prehash_key.apply(key))?;
+        let value = self.length.apply(&self.prehash_value.apply(value))?;
+        let mut data = Vec::with_capacity(self.prefix.len() + key.len() + value.len());
+        data.extend_from_slice(&self.prefix);
+        data.extend_from_slice(&key);
+        data.extend_from_slice(&value);
+        Some(self.hash.apply(&data))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct InnerOp {
+    hash: HashOp,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl InnerOp {
+    fn apply(&self, child: &[u8]) -> Option<Vec<u8>> {
+        if child.is_empty() {
+            return None;
+        }
+        let mut data = Vec::with_capacity(self.prefix.len() + child.len() + self.suffix.len());
+        data.extend_from_slice(&self.prefix);
+        data.extend_from_slice(child);
+        data.extend_from_slice(&self.suffix);
+        Some(self.hash.apply(&data))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ExistenceProof {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    leaf: Option<LeafOp>,
+    /// Inner nodes from the leaf to the root
+    path: Vec<InnerOp>,
+}
+
+impl ExistenceProof {
+    /// Calculates the root hash implied by this proof
+    fn calculate(&self) -> Option<Vec<u8>> {
+        let leaf = self.leaf.as_ref()?;
+        self.path
+            .iter()
+            .try_fold(leaf.apply(&self.key, &self.value)?, |hash, step| {
+                step.apply(&hash)
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct NonExistenceProof {
+    left: Option<ExistenceProof>,
+    right: Option<ExistenceProof>,
+}
+
+enum CommitmentProof {
+    Exist(ExistenceProof),
+    Nonexist(NonExistenceProof),
+}
+
+struct InnerSpec {
+    /// The position of the children in an inner node, e.g. `[0, 1]` for binary trees
+    child_order: Vec<usize>,
+    child_size: usize,
+    min_prefix_length: usize,
+    max_prefix_length: usize,
+    /// The hash of an empty child, if the tree has empty children
+    empty_child: Vec<u8>,
+    hash: HashOp,
+}
+
+struct ProofSpec {
+    leaf_spec: LeafOp,
+    inner_spec: InnerSpec,
+    max_depth: usize,
+    min_depth: usize,
+    /// Whether the prefixes of leaf and inner ops must have the layout of IAVL nodes
+    iavl: bool,
+}
+
+impl ProofSpec {
+    fn from_id(id: u32) -> CryptoResult<Self> {
+        let leaf_spec = LeafOp {
+            hash: HashOp::Sha256,
+            prehash_key: HashOp::NoHash,
+            prehash_value: HashOp::Sha256,
+            length: LengthOp::VarProto,
+            prefix: [0].to_vec(),
+        };
+        match id {
+            ICS23_SPEC_IAVL => Ok(ProofSpec {
+                leaf_spec,
+                inner_spec: InnerSpec {
+                    child_order: [0, 1].to_vec(),
+                    child_size: 33,
+                    min_prefix_length: 4,
+                    max_prefix_length: 12,
+                    empty_child: Vec::new(),
+                    hash: HashOp::Sha256,
+                },
+                max_depth: 0,
+                min_depth: 0,
+                iavl: true,
+            }),
+            ICS23_SPEC_TENDERMINT => Ok(ProofSpec {
+                leaf_spec,
+                inner_spec: InnerSpec {
+                    child_order: [0, 1].to_vec(),
+                    child_size: 32,
+                    min_prefix_length: 1,
+                    max_prefix_length: 1,
+                    empty_child: Vec::new(),
+                    hash: HashOp::Sha256,
+                },
+                max_depth: 0,
+                min_depth: 0,
+                iavl: false,
+            }),
+            _ => Err(CryptoError::ics23_err("Unknown proof spec identifier")),
+        }
+    }
+}
+
+fn verify_existence(
+    proof: &ExistenceProof,
+    spec: &ProofSpec,
+    root: &[u8],
+    key: &[u8],
+    value: &[u8],
+) -> bool {
+    proof.key == key
+        && proof.value == value
+        && check_against_spec(proof, spec)
+        && proof.calculate().as_deref() == Some(root)
+}
+
+fn verify_non_existence(
+    proof: &NonExistenceProof,
+    spec: &ProofSpec,
+    root: &[u8],
+    key: &[u8],
+) -> bool {
+    if let Some(left) = &proof.left {
+        if left.key.as_slice() >= key || !verify_existence(left, spec, root, &left.key, &left.value)
+        {
+            return false;
+        }
+    }
+    if let Some(right) = &proof.right {
+        if right.key.as_slice() <= key
+            || !verify_existence(right, spec, root, &right.key, &right.value)
+        {
+            return false;
+        }
+    }
+    let inner_spec = &spec.inner_spec;
+    match (&proof.left, &proof.right) {
+        (None, None) => false,
+        (None, Some(right)) => is_left_most(inner_spec, &right.path),
+        (Some(left), None) => is_right_most(inner_spec, &left.path),
+        (Some(left), Some(right)) => is_left_neighbor(inner_spec, &left.path, &right.path),
+    }
+}
+
+fn check_against_spec(proof: &ExistenceProof, spec: &ProofSpec) -> bool {
+    let leaf = match &proof.leaf {
+        Some(leaf) => leaf,
+        None => return false,
+    };
+    let leaf_spec = &spec.leaf_spec;
+    if leaf.hash != leaf_spec.hash
+        || leaf.prehash_key != leaf_spec.prehash_key
+        || leaf.prehash_value != leaf_spec.prehash_value
+        || leaf.length != leaf_spec.length
+        || !leaf.prefix.starts_with(&leaf_spec.prefix)
+    {
+        return false;
+    }
+    if spec.iavl && !is_iavl_prefix(&leaf.prefix, 0) {
+        return false;
+    }
+    if (spec.min_depth > 0 && proof.path.len() < spec.min_depth)
+        || (spec.max_depth > 0 && proof.path.len() > spec.max_depth)
+    {
+        return false;
+    }
+    proof.path.iter().zip(1..).all(|(step, layer)| {
+        check_inner_op(step, &leaf_spec.prefix, &spec.inner_spec)
+            && (!spec.iavl || is_iavl_prefix(&step.prefix, layer))
+    })
+}
+
+/// Checks that `prefix` has the layout of an IAVL node in the given layer (0 for leaves),
+/// like `validateIavlOps` of the reference implementation.
+///
+/// The prefix starts with the height, size and version of the node, none of which may be
+/// negative, and the height must be at least the layer. Leaves have nothing else in their
+/// prefix. Inner nodes continue with the length of the child hash, preceded by the left
+/// sibling if the child is on the right.
+fn is_iavl_prefix(prefix: &[u8], layer: i64) -> bool {
+    let mut rest = prefix;
+    let mut fields = [0i64; 3];
+    for field in &mut fields {
+        match read_signed_varint(&mut rest) {
+            Some(value) if value >= 0 => *field = value,
+            _ => return false,
+        }
+    }
+    let [height, _size, _version] = fields;
+    if height < layer {
+        return false;
+    }
+    if layer == 0 {
+        rest.is_empty()
+    } else {
+        rest.len() == 1 || rest.len() == 34
+    }
+}
+
+/// Reads a zigzag encoded varint from the start of `data` like `binary.ReadVarint` of Go
+fn read_signed_varint(data: &mut &[u8]) -> Option<i64> {
+    let mut value = 0u64;
+    for shift in (0..70).step_by(7) {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        if shift == 63 && byte > 1 {
+            // overflow
+            return None;
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Some((value >> 1) as i64 ^ -((value & 1) as i64));
+        }
+    }
+    None
+}
+
+fn check_inner_op(op: &InnerOp, leaf_prefix: &[u8], spec: &InnerSpec) -> bool {
+    // An inner node must not be confused with a leaf
+    if op.hash != spec.hash || op.prefix.starts_with(leaf_prefix) {
+        return false;
+    }
+    let max_left_child_bytes = (spec.child_order.len() - 1) * spec.child_size;
+    op.prefix.len() >= spec.min_prefix_length
+        && op.prefix.len() <= spec.max_prefix_length + max_left_child_bytes
+        && op.suffix.len() % spec.child_size == 0
+}
+
+/// The position of `branch` in the child order
+fn position(spec: &InnerSpec, branch: usize) -> usize {
+    spec.child_order
+        .iter()
+        .position(|b| *b == branch)
+        .unwrap_or(spec.child_order.len())
+}
+
+/// The expected prefix length range and suffix length of an inner node on the given branch
+fn padding(spec: &InnerSpec, branch: usize) -> (usize, usize, usize) {
+    let idx = position(spec, branch);
+    let prefix = idx * spec.child_size;
+    let suffix = (spec.child_order.len() - 1).saturating_sub(idx) * spec.child_size;
+    (
+        prefix + spec.min_prefix_length,
+        prefix + spec.max_prefix_length,
+        suffix,
+    )
+}
+
+fn has_padding(op: &InnerOp, (min_prefix, max_prefix, suffix): (usize, usize, usize)) -> bool {
+    op.prefix.len() >= min_prefix && op.prefix.len() <= max_prefix && op.suffix.len() == suffix
+}
+
+/// The branch the child of this inner node is on
+fn order_from_padding(spec: &InnerSpec, op: &InnerOp) -> Option<usize> {
+    (0..spec.child_order.len()).find(|branch| has_padding(op, padding(spec, *branch)))
+}
+
+fn left_branches_are_empty(spec: &InnerSpec, op: &InnerOp) -> bool {
+    let left_branches = match order_from_padding(spec, op) {
+        Some(0) | None => return false,
+        Some(idx) => idx,
+    };
+    let left_bytes = left_branches * spec.child_size;
+    if op.prefix.len() < left_bytes {
+        return false;
+    }
+    op.prefix[op.prefix.len() - left_bytes..]
+        .chunks(spec.child_size)
+        .all(|child| child == spec.empty_child.as_slice())
+}
+
+fn right_branches_are_empty(spec: &InnerSpec, op: &InnerOp) -> bool {
+    let idx = match order_from_padding(spec, op) {
+        Some(idx) => idx,
+        None => return false,
+    };
+    let right_branches = spec.child_order.len() - 1 - idx;
+    if right_branches == 0 || op.suffix.len() != right_branches * spec.child_size {
+        return false;
+    }
+    op.suffix
+        .chunks(spec.child_size)
+        .all(|child| child == spec.empty_child.as_slice())
+}
+
+/// Checks if the path leads to the left-most leaf of the tree
+fn is_left_most(spec: &InnerSpec, path: &[InnerOp]) -> bool {
+    let left = padding(spec, 0);
+    path.iter()
+        .all(|step| has_padding(step, left) || left_branches_are_empty(spec, step))
+}
+
+/// Checks if the path leads to the right-most leaf of the tree
+fn is_right_most(spec: &InnerSpec, path: &[InnerOp]) -> bool {
+    let right = padding(spec, spec.child_order.len() - 1);
+    path.iter()
+        .all(|step| has_padding(step, right) || right_branches_are_empty(spec, step))
+}
+
+/// Checks if the leaves of the two paths are direct neighbors
+fn is_left_neighbor(spec: &InnerSpec, mut left: &[InnerOp], mut right: &[InnerOp]) -> bool {
+    // Skip the common ancestors
+    loop {
+        match (left.split_last(), right.split_last()) {
+            (Some((top_left, rest_left)), Some((top_right, rest_right))) => {
+                if top_left.prefix == top_right.prefix && top_left.suffix == top_right.suffix {
+                    left = rest_left;
+                    right = rest_right;
+                } else {
+                    break;
+                }
+            }
+            _ => return false,
+        }
+    }
+    let (top_left, rest_left) = left.split_last().unwrap();
+    let (top_right, rest_right) = right.split_last().unwrap();
+    let is_left_step = match (
+        order_from_padding(spec, top_left),
+        order_from_padding(spec, top_right),
+    ) {
+        (Some(left_idx), Some(right_idx)) => right_idx == left_idx + 1,
+        _ => false,
+    };
+    is_left_step && is_right_most(spec, rest_left) && is_left_most(spec, rest_right)
+}
+
+fn invalid_field() -> CryptoError {
+    CryptoError::ics23_err("Invalid field type in proof")
+}
+
+fn decode_commitment_proof(data: &[u8]) -> CryptoResult<CommitmentProof> {
+    let mut reader = ProtoReader::new(data, CryptoError::ics23_err);
+    let mut proof = None;
+    while let Some((field, value)) = reader.next_field()? {
+        proof = match (field, value) {
+            (1, ProtoValue::Bytes(bytes)) => {
+                Some(CommitmentProof::Exist(decode_existence_proof(bytes)?))
+            }
+            (2, ProtoValue::Bytes(bytes)) => Some(CommitmentProof::Nonexist(
+                decode_non_existence_proof(bytes)?,
+            )),
+            (1 | 2, _) => return Err(invalid_field()),
+            (3 | 4, _) => {
+                return Err(CryptoError::ics23_err(
+                    "Batch and compressed proofs are not supported",
+                ))
+            }
+            _ => proof,
+        };
+    }
+    proof.ok_or_else(|| CryptoError::ics23_err("Empty commitment proof"))
+}
+
+fn decode_existence_proof(data: &[u8]) -> CryptoResult<ExistenceProof> {
+    let mut reader = ProtoReader::new(data, CryptoError::ics23_err);
+    let mut proof = ExistenceProof {
+        key: Vec::new(),
+        value: Vec::new(),
+        leaf: None,
+        path: Vec::new(),
+    };
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(bytes)) => proof.key = bytes.to_vec(),
+            (2, ProtoValue::Bytes(bytes)) => proof.value = bytes.to_vec(),
+            (3, ProtoValue::Bytes(bytes)) => proof.leaf = Some(decode_leaf_op(bytes)?),
+            (4, ProtoValue::Bytes(bytes)) => proof.path.push(decode_inner_op(bytes)?),
+            (1..=4, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(proof)
+}
+
+fn decode_non_existence_proof(data: &[u8]) -> CryptoResult<NonExistenceProof> {
+    let mut reader = ProtoReader::new(data, CryptoError::ics23_err);
+    let mut proof = NonExistenceProof {
+        left: None,
+        right: None,
+    };
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            // the key (field 1) is not needed since the verified key is passed explicitly
+            (1, ProtoValue::Bytes(_)) => {}
+            (2, ProtoValue::Bytes(bytes)) => proof.left = Some(decode_existence_proof(bytes)?),
+            (3, ProtoValue::Bytes(bytes)) => proof.right = Some(decode_existence_proof(bytes)?),
+            (1..=3, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(proof)
+}
+
+fn decode_leaf_op(data: &[u8]) -> CryptoResult<LeafOp> {
+    let mut reader = ProtoReader::new(data, CryptoError::ics23_err);
+    let mut op = LeafOp {
+        hash: HashOp::NoHash,
+        prehash_key: HashOp::NoHash,
+        prehash_value: HashOp::NoHash,
+        length: LengthOp::NoPrefix,
+        prefix: Vec::new(),
+    };
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Varint(v)) => op.hash = HashOp::from_proto(v)?,
+            (2, ProtoValue::Varint(v)) => op.prehash_key = HashOp::from_proto(v)?,
+            (3, ProtoValue::Varint(v)) => op.prehash_value = HashOp::from_proto(v)?,
+            (4, ProtoValue::Varint(v)) => op.length = LengthOp::from_proto(v)?,
+            (5, ProtoValue::Bytes(bytes)) => op.prefix = bytes.to_vec(),
+            (1..=5, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(op)
+}
+
+fn decode_inner_op(data: &[u8]) -> CryptoResult<InnerOp> {
+    let mut reader = ProtoReader::new(data, CryptoError::ics23_err);
+    let mut op = InnerOp {
+        hash: HashOp::NoHash,
+        prefix: Vec::new(),
+        suffix: Vec::new(),
+    };
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Varint(v)) => op.hash = HashOp::from_proto(v)?,
+            (2, ProtoValue::Bytes(bytes)) => op.prefix = bytes.to_vec(),
+            (3, ProtoValue::Bytes(bytes)) => op.suffix = bytes.to_vec(),
+            (1..=3, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::{encode_bytes_field as encode_bytes, encode_varint_field as encode_enum};
+
+    // A Tendermint style tree with the leaves a, c, e and g:
+    //
+    //          root
+    //        /      \
+    //      ac        eg
+    //     /  \      /  \
+    //    a    c    e    g
+
+    fn leaf_hash(key: &[u8]) -> Vec<u8> {
+        let spec = ProofSpec::from_id(ICS23_SPEC_TENDERMINT).unwrap();
+        spec.leaf_spec.apply(key, &value_of(key)).unwrap()
+    }
+
+    fn value_of(key: &[u8]) -> Vec<u8> {
+        [b"value of ", key].concat()
+    }
+
+    fn inner_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+        HashOp::Sha256.apply(&[&[1u8][..], left, right].concat())
+    }
+
+    fn root() -> Vec<u8> {
+        inner_hash(
+            &inner_hash(&leaf_hash(b"a"), &leaf_hash(b"c")),
+            &inner_hash(&leaf_hash(b"e"), &leaf_hash(b"g")),
+        )
+    }
+
+    /// Steps from the leaf to the root as (is left child, sibling hash)
+    fn steps(key: &[u8]) -> Vec<(bool, Vec<u8>)> {
+        let ac = inner_hash(&leaf_hash(b"a"), &leaf_hash(b"c"));
+        let eg = inner_hash(&leaf_hash(b"e"), &leaf_hash(b"g"));
+        match key {
+            b"a" => [(true, leaf_hash(b"c")), (true, eg)].to_vec(),
+            b"c" => [(false, leaf_hash(b"a")), (true, eg)].to_vec(),
+            b"e" => [(true, leaf_hash(b"g")), (false, ac)].to_vec(),
+            b"g" => [(false, leaf_hash(b"e")), (false, ac)].to_vec(),
+            _ => panic!("key not in tree"),
+        }
+    }
+
+    fn encode_existence_proof(key: &[u8]) -> Vec<u8> {
+        let mut leaf = Vec::new();
+        encode_enum(1, 1, &mut leaf); // sha256
+        encode_enum(3, 1, &mut leaf); // prehash value with sha256
+        encode_enum(4, 1, &mut leaf); // var proto length
+        encode_bytes(5, &[0], &mut leaf);
+
+        let mut out = Vec::new();
+        encode_bytes(1, key, &mut out);
+        encode_bytes(2, &value_of(key), &mut out);
+        encode_bytes(3, &leaf, &mut out);
+        for (is_left, sibling) in steps(key) {
+            let mut inner = Vec::new();
+            encode_enum(1, 1, &mut inner);
+            if is_left {
+                encode_bytes(2, &[1], &mut inner);
+                encode_bytes(3, &sibling, &mut inner);
+            } else {
+                encode_bytes(2, &[&[1u8][..], &sibling].concat(), &mut inner);
+            }
+            encode_bytes(4, &inner, &mut out);
+        }
+        out
+    }
+
+    fn membership_proof(key: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_bytes(1, &encode_existence_proof(key), &mut out);
+        out
+    }
+
+    fn non_membership_proof(key: &[u8], left: Option<&[u8]>, right: Option<&[u8]>) -> Vec<u8> {
+        let mut proof = Vec::new();
+        encode_bytes(1, key, &mut proof);
+        if let Some(left) = left {
+            encode_bytes(2, &encode_existence_proof(left), &mut proof);
+        }
+        if let Some(right) = right {
+            encode_bytes(3, &encode_existence_proof(right), &mut proof);
+        }
+        let mut out = Vec::new();
+        encode_bytes(2, &proof, &mut out);
+        out
+    }
+
+    #[test]
+    fn ics23_verify_membership_works() {
+        let root = root();
+        for key in [b"a", b"c", b"e", b"g"] {
+            let proof = membership_proof(key);
+            assert!(ics23_verify_membership(
+                ICS23_SPEC_TENDERMINT,
+                &root,
+                &proof,
+                key,
+                &value_of(key)
+            )
+            .unwrap());
+        }
+    }
+
+    #[test]
+    fn ics23_verify_membership_fails_for_wrong_claims() {
+        let root = root();
+        let proof = membership_proof(b"c");
+        let spec = ICS23_SPEC_TENDERMINT;
+        assert!(!ics23_verify_membership(spec, &root, &proof, b"c", b"other").unwrap());
+        assert!(!ics23_verify_membership(spec, &root, &proof, b"a", &value_of(b"a")).unwrap());
+        assert!(!ics23_verify_membership(spec, &[0; 32], &proof, b"c", &value_of(b"c")).unwrap());
+        // inner nodes do not match the IAVL spec
+        assert!(
+            !ics23_verify_membership(ICS23_SPEC_IAVL, &root, &proof, b"c", &value_of(b"c"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn ics23_verify_membership_errs_for_invalid_input() {
+        let root = root();
+        let proof = membership_proof(b"c");
+        let value = value_of(b"c");
+        match ics23_verify_membership(42, &root, &proof, b"c", &value).unwrap_err() {
+            CryptoError::Ics23Err { msg, .. } => {
+                assert_eq!(msg, "Unknown proof spec identifier")
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        let truncated = &proof[..proof.len() - 1];
+        match ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, truncated, b"c", &value)
+            .unwrap_err()
+        {
+            CryptoError::Ics23Err { msg, .. } => assert_eq!(msg, "Unexpected end of message"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        let proof = non_membership_proof(b"d", Some(b"c"), Some(b"e"));
+        ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, &proof, b"c", &value).unwrap_err();
+        ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, &[], b"c", &value).unwrap_err();
+    }
+
+    /// An IAVL style existence proof of `a` in a tree with the leaves a and c, using the given
+    /// prefixes for the leaf and the inner node. Returns the proof and the root it implies.
+    fn iavl_membership_proof(leaf_prefix: &[u8], inner_prefix: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let spec = ProofSpec::from_id(ICS23_SPEC_IAVL).unwrap();
+        // height 0, size 1, version 1
+        let sibling = LeafOp {
+            prefix: [0, 2, 2].to_vec(),
+            ..spec.leaf_spec.clone()
+        };
+        let leaf = LeafOp {
+            prefix: leaf_prefix.to_vec(),
+            ..spec.leaf_spec
+        };
+        let inner = InnerOp {
+            hash: HashOp::Sha256,
+            prefix: [inner_prefix, &[32]].concat(),
+            suffix: [&[32u8][..], &sibling.apply(b"c", &value_of(b"c")).unwrap()].concat(),
+        };
+        let root = inner
+            .apply(&leaf.apply(b"a", &value_of(b"a")).unwrap())
+            .unwrap();
+
+        let mut leaf_encoded = Vec::new();
+        encode_enum(1, 1, &mut leaf_encoded); // sha256
+        encode_enum(3, 1, &mut leaf_encoded); // prehash value with sha256
+        encode_enum(4, 1, &mut leaf_encoded); // var proto length
+        encode_bytes(5, &leaf.prefix, &mut leaf_encoded);
+        let mut inner_encoded = Vec::new();
+        encode_enum(1, 1, &mut inner_encoded);
+        encode_bytes(2, &inner.prefix, &mut inner_encoded);
+        encode_bytes(3, &inner.suffix, &mut inner_encoded);
+        let mut existence_proof = Vec::new();
+        encode_bytes(1, b"a", &mut existence_proof);
+        encode_bytes(2, &value_of(b"a"), &mut existence_proof);
+        encode_bytes(3, &leaf_encoded, &mut existence_proof);
+        encode_bytes(4, &inner_encoded, &mut existence_proof);
+        let mut proof = Vec::new();
+        encode_bytes(1, &existence_proof, &mut proof);
+        (proof, root)
+    }
+
+    #[test]
+    fn ics23_verify_membership_checks_iavl_prefixes() {
+        let verify = |leaf_prefix: &[u8], inner_prefix: &[u8]| {
+            let (proof, root) = iavl_membership_proof(leaf_prefix, inner_prefix);
+            ics23_verify_membership(ICS23_SPEC_IAVL, &root, &proof, b"a", &value_of(b"a")).unwrap()
+        };
+        // leaf with height 0, size 1, version 1 and inner node with height 1, size 2, version 1
+        assert!(verify(&[0, 2, 2], &[2, 4, 2]));
+        // large versions are fine
+        assert!(verify(&[0, 2, 0xc0, 0x84, 0x3d], &[2, 4, 0xc0, 0x84, 0x3d]));
+
+        // negative version
+        assert!(!verify(&[0, 2, 1], &[2, 4, 2]));
+        assert!(!verify(&[0, 2, 2], &[2, 4, 1]));
+        // truncated varint
+        assert!(!verify(&[0, 2, 0x80], &[2, 4, 2]));
+        // trailing bytes in the leaf prefix
+        assert!(!verify(&[0, 2, 2, 0], &[2, 4, 2]));
+        // trailing bytes in the inner prefix
+        assert!(!verify(&[0, 2, 2], &[2, 4, 2, 0]));
+        // the same proofs are fine for other specs
+        let (proof, root) = iavl_membership_proof(&[0, 2, 2, 0], &[2, 4, 2]);
+        let spec = ProofSpec::from_id(ICS23_SPEC_IAVL).unwrap();
+        let proof = match decode_commitment_proof(&proof).unwrap() {
+            CommitmentProof::Exist(proof) => proof,
+            CommitmentProof::Nonexist(_) => unreachable!(),
+        };
+        let spec = ProofSpec {
+            iavl: false,
+            ..spec
+        };
+        assert!(verify_existence(
+            &proof,
+            &spec,
+            &root,
+            b"a",
+            &value_of(b"a")
+        ));
+    }
+
+    #[test]
+    fn read_signed_varint_works() {
+        let read = |mut data: &[u8]| read_signed_varint(&mut data).map(|value| (value, data.len()));
+        assert_eq!(read(&[0]), Some((0, 0)));
+        assert_eq!(read(&[1]), Some((-1, 0)));
+        assert_eq!(read(&[2, 7]), Some((1, 1)));
+        assert_eq!(read(&[0xac, 0x02]), Some((150, 0)));
+        assert_eq!(
+            read(&[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Some((i64::MAX, 0))
+        );
+        assert_eq!(
+            read(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Some((i64::MIN, 0))
+        );
+        // empty, truncated and overflowing
+        assert_eq!(read(&[]), None);
+        assert_eq!(read(&[0x80]), None);
+        assert_eq!(
+            read(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]),
+            None
+        );
+    }
+
+    #[test]
+    fn ics23_verify_non_membership_works() {
+        let root = root();
+        let spec = ICS23_SPEC_TENDERMINT;
+        // between neighbors in different subtrees
+        let proof = non_membership_proof(b"d", Some(b"c"), Some(b"e"));
+        assert!(ics23_verify_non_membership(spec, &root, &proof, b"d").unwrap());
+        // between neighbors in the same subtree
+        let proof = non_membership_proof(b"b", Some(b"a"), Some(b"c"));
+        assert!(ics23_verify_non_membership(spec, &root, &proof, b"b").unwrap());
+        // before the left-most leaf
+        let proof = non_membership_proof(b"0", None, Some(b"a"));
+        assert!(ics23_verify_non_membership(spec, &root, &proof, b"0").unwrap());
+        // after the right-most leaf
+        let proof = non_membership_proof(b"h", Some(b"g"), None);
+        assert!(ics23_verify_non_membership(spec, &root, &proof, b"h").unwrap());
+    }
+
+    #[test]
+    fn ics23_verify_non_membership_fails_for_wrong_claims() {
+        let root = root();
+        let spec = ICS23_SPEC_TENDERMINT;
+        // not neighbors
+        let proof = non_membership_proof(b"d", Some(b"a"), Some(b"e"));
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"d").unwrap());
+        // key not between the neighbors
+        let proof = non_membership_proof(b"f", Some(b"c"), Some(b"e"));
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"f").unwrap());
+        // existing key
+        let proof = non_membership_proof(b"c", Some(b"a"), Some(b"c"));
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"c").unwrap());
+        // not the left-most leaf
+        let proof = non_membership_proof(b"b", None, Some(b"c"));
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"b").unwrap());
+        // not the right-most leaf
+        let proof = non_membership_proof(b"f", Some(b"e"), None);
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"f").unwrap());
+        // no neighbors
+        let proof = non_membership_proof(b"f", None, None);
+        assert!(!ics23_verify_non_membership(spec, &root, &proof, b"f").unwrap());
+        // wrong root
+        let proof = non_membership_proof(b"d", Some(b"c"), Some(b"e"));
+        assert!(!ics23_verify_non_membership(spec, &[0; 32], &proof, b"d").unwrap());
+    }
+
+    #[test]
+    fn ics23_verify_non_membership_errs_for_existence_proof() {
+        let proof = membership_proof(b"c");
+        match ics23_verify_non_membership(ICS23_SPEC_TENDERMINT, &root(), &proof, b"d").unwrap_err()
+        {
+            CryptoError::Ics23Err { msg, .. } => assert_eq!(
+                msg,
+                "Expected a non-existence proof, got an existence proof"
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn decoding_skips_unknown_fields() {
+        let mut proof = membership_proof(b"c");
+        encode_enum(15, 7, &mut proof);
+        encode_bytes(16, b"foo", &mut proof);
+        assert!(ics23_verify_membership(
+            ICS23_SPEC_TENDERMINT,
+            &root(),
+            &proof,
+            b"c",
+            &value_of(b"c")
+        )
+        .unwrap());
+    }
+}
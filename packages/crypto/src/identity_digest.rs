@@ -15,7 +15,7 @@ pub struct Identity256 {
 
 impl Update for Identity256 {
     fn update(&mut self, hash: &[u8]) {
-        assert_eq!(hash.as_ref().len(), 32);
+        assert_eq!(hash.len(), 32);
         self.array = *GenericArray::from_slice(hash);
     }
 }
@@ -2,20 +2,77 @@
 //! Please don't use any of these types directly, as
 //! they might change frequently, or be removed in the future.
 //! This crate does not adhere to semantic versioning.
+//!
+//! Without the default `std` feature, the crate builds in `no_std` environments with
+//! an allocator. Ed25519 verification, the Poseidon hash, Groth16 verification and Tendermint
+//! header verification require `std`.
 #![cfg_attr(feature = "backtraces", feature(backtrace))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+mod bls12_381;
+mod compare;
+#[cfg(feature = "std")]
 mod ed25519;
 mod errors;
+#[cfg(feature = "std")]
+mod groth16;
+mod hashing;
+mod ics23;
 mod identity_digest;
+#[cfg(feature = "std")]
+mod poseidon;
+mod proto;
 mod secp256k1;
+mod secp256r1;
+#[cfg(feature = "std")]
+mod tendermint;
 
+#[doc(hidden)]
+pub use crate::bls12_381::{
+    bls12_381_aggregate_verify, bls12_381_hash_to_g1, bls12_381_hash_to_g2,
+    bls12_381_pairing_equality, BLS12_381_G1_POINT_LEN, BLS12_381_G2_POINT_LEN,
+    BLS12_381_MAX_AGGREGATE_SIZE,
+};
+#[doc(hidden)]
+pub use crate::compare::ct_eq;
+#[cfg(feature = "std")]
 #[doc(hidden)]
 pub use crate::ed25519::EDDSA_PUBKEY_LEN;
+#[cfg(feature = "std")]
 #[doc(hidden)]
-pub use crate::ed25519::{ed25519_batch_verify, ed25519_verify};
+pub use crate::ed25519::{
+    ed25519_batch_decode_packed, ed25519_batch_verify, ed25519_batch_verify_packed,
+    ed25519_batch_verify_with_rng, ed25519_verify, PackedEd25519Batch,
+};
 #[doc(hidden)]
 pub use crate::errors::{CryptoError, CryptoResult};
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use crate::groth16::{
+    zk_verify_groth16, GROTH16_MAX_PUBLIC_INPUTS, GROTH16_PROOF_LEN, GROTH16_PUBLIC_INPUT_LEN,
+    GROTH16_VK_MAX_LEN,
+};
+#[doc(hidden)]
+pub use crate::hashing::{hash160, ripemd160, sha256, RIPEMD160_LEN, SHA256_LEN};
 #[doc(hidden)]
-pub use crate::secp256k1::{secp256k1_recover_pubkey, secp256k1_verify};
+pub use crate::ics23::{
+    ics23_verify_membership, ics23_verify_non_membership, ICS23_KEY_MAX_LEN, ICS23_PROOF_MAX_LEN,
+    ICS23_ROOT_MAX_LEN, ICS23_SPEC_IAVL, ICS23_SPEC_TENDERMINT, ICS23_VALUE_MAX_LEN,
+};
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use crate::poseidon::{poseidon_hash, POSEIDON_INPUT_LEN, POSEIDON_MAX_INPUTS};
+#[doc(hidden)]
+pub use crate::secp256k1::{
+    secp256k1_recover_pubkey, secp256k1_verify, secp256k1_verify_prehashed,
+    HASH_FUNCTION_KECCAK256, HASH_FUNCTION_SHA256,
+};
 #[doc(hidden)]
 pub use crate::secp256k1::{ECDSA_PUBKEY_MAX_LEN, ECDSA_SIGNATURE_LEN, MESSAGE_HASH_MAX_LEN};
+#[doc(hidden)]
+pub use crate::secp256r1::{secp256r1_recover_pubkey, secp256r1_verify};
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use crate::tendermint::{tendermint_verify_header, TENDERMINT_MAX_VALIDATORS};
@@ -0,0 +1,114 @@
+use alloc::vec::Vec;
+use ff_ce::{PrimeField, PrimeFieldRepr};
+use once_cell::sync::Lazy;
+use poseidon_rs::{Fr, FrRepr, Poseidon};
+
+use crate::errors::{CryptoError, CryptoResult};
+
+/// Length of a Poseidon input and output, i.e. a big-endian encoded BN254 scalar field element
+pub const POSEIDON_INPUT_LEN: usize = 32;
+
+/// Max number of inputs hashed in one call
+pub const POSEIDON_MAX_INPUTS: usize = 16;
+
+/// Loading the round constants is expensive, so this is done once per process
+static POSEIDON: Lazy<Poseidon> = Lazy::new(Poseidon::new);
+
+/// Calculates the Poseidon hash of 1 to [`POSEIDON_MAX_INPUTS`] elements of the
+/// BN254 scalar field.
+///
+/// Inputs and output are 32 byte big-endian encoded field elements. Inputs must be
+/// smaller than the field modulus. The parametrization is compatible with circomlib's
+/// `Poseidon(n)` template, such that commitments and merkle trees created in circom
+/// circuits can be verified on chain.
+pub fn poseidon_hash(inputs: &[&[u8]]) -> CryptoResult<[u8; POSEIDON_INPUT_LEN]> {
+    if inputs.is_empty() || inputs.len() > POSEIDON_MAX_INPUTS {
+        return Err(CryptoError::hash_input_err(format!(
+            "Number of inputs must be between 1 and {}",
+            POSEIDON_MAX_INPUTS
+        )));
+    }
+    let elements = inputs
+        .iter()
+        .map(|input| read_field_element(input))
+        .collect::<CryptoResult<Vec<Fr>>>()?;
+
+    let hash = POSEIDON.hash(elements).map_err(CryptoError::generic_err)?;
+
+    let mut out = [0u8; POSEIDON_INPUT_LEN];
+    hash.into_repr()
+        .write_be(&mut out[..])
+        .map_err(|e| CryptoError::generic_err(e.to_string()))?;
+    Ok(out)
+}
+
+fn read_field_element(input: &[u8]) -> CryptoResult<Fr> {
+    if input.len() != POSEIDON_INPUT_LEN {
+        return Err(CryptoError::hash_input_err(format!(
+            "Input must be {} bytes long",
+            POSEIDON_INPUT_LEN
+        )));
+    }
+    let mut repr = FrRepr::default();
+    repr.read_be(input)
+        .map_err(|e| CryptoError::generic_err(e.to_string()))?;
+    Fr::from_repr(repr)
+        .map_err(|_| CryptoError::hash_input_err("Input is not a valid field element"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    fn element(value: u8) -> [u8; POSEIDON_INPUT_LEN] {
+        let mut out = [0u8; POSEIDON_INPUT_LEN];
+        out[POSEIDON_INPUT_LEN - 1] = value;
+        out
+    }
+
+    #[test]
+    fn poseidon_hash_works() {
+        // test vectors from circomlibjs
+        assert_eq!(
+            poseidon_hash(&[&element(1)]).unwrap(),
+            hex!("29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133")
+        );
+        assert_eq!(
+            poseidon_hash(&[&element(1), &element(2)]).unwrap(),
+            hex!("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+
+        // max number of inputs
+        let inputs = vec![element(7); POSEIDON_MAX_INPUTS];
+        let inputs: Vec<&[u8]> = inputs.iter().map(|i| i.as_slice()).collect();
+        poseidon_hash(&inputs).unwrap();
+    }
+
+    #[test]
+    fn poseidon_hash_fails_for_wrong_number_of_inputs() {
+        let err = poseidon_hash(&[]).unwrap_err();
+        assert!(matches!(err, CryptoError::HashInputErr { .. }));
+
+        let inputs = vec![element(7); POSEIDON_MAX_INPUTS + 1];
+        let inputs: Vec<&[u8]> = inputs.iter().map(|i| i.as_slice()).collect();
+        let err = poseidon_hash(&inputs).unwrap_err();
+        assert!(matches!(err, CryptoError::HashInputErr { .. }));
+    }
+
+    #[test]
+    fn poseidon_hash_fails_for_invalid_inputs() {
+        // wrong length
+        let err = poseidon_hash(&[&[1u8; 31]]).unwrap_err();
+        assert!(matches!(err, CryptoError::HashInputErr { .. }));
+
+        // field modulus is not a valid element
+        let modulus = hex!("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001");
+        let err = poseidon_hash(&[&modulus]).unwrap_err();
+        assert!(matches!(err, CryptoError::HashInputErr { .. }));
+
+        // but the largest element is
+        let max = hex!("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000");
+        poseidon_hash(&[&max]).unwrap();
+    }
+}
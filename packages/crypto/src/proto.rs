@@ -0,0 +1,164 @@
+//! A minimal implementation of the protobuf wire format, sufficient for the messages of
+//! ICS-23 proofs and Tendermint light blocks. This avoids code generation and keeps the
+//! crate usable in no_std environments.
+use alloc::vec::Vec;
+
+use crate::errors::{CryptoError, CryptoResult};
+
+pub(crate) const WIRE_TYPE_VARINT: u64 = 0;
+const WIRE_TYPE_FIXED64: u64 = 1;
+pub(crate) const WIRE_TYPE_LEN: u64 = 2;
+const WIRE_TYPE_FIXED32: u64 = 5;
+
+pub(crate) fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Encodes a length-delimited field, even if `bytes` is empty
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) fn encode_bytes_field(field: u64, bytes: &[u8], out: &mut Vec<u8>) {
+    encode_varint(field << 3 | WIRE_TYPE_LEN, out);
+    encode_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+/// Encodes a varint field, even if `value` is 0
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) fn encode_varint_field(field: u64, value: u64, out: &mut Vec<u8>) {
+    encode_varint(field << 3 | WIRE_TYPE_VARINT, out);
+    encode_varint(value, out);
+}
+
+#[derive(Debug)]
+pub(crate) enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// A fixed size value, which none of the supported messages uses
+    Other,
+}
+
+/// Reads the fields of an encoded message one by one. Errors are created with `make_err`,
+/// such that every user of the reader reports its own error type.
+pub(crate) struct ProtoReader<'a> {
+    data: &'a [u8],
+    make_err: fn(&'static str) -> CryptoError,
+}
+
+impl<'a> ProtoReader<'a> {
+    pub fn new(data: &'a [u8], make_err: fn(&'static str) -> CryptoError) -> Self {
+        ProtoReader { data, make_err }
+    }
+
+    fn read_varint(&mut self) -> CryptoResult<u64> {
+        let mut value = 0u64;
+        for (i, byte) in self.data.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.data = &self.data[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err((self.make_err)("Invalid varint in message"))
+    }
+
+    fn read_slice(&mut self, len: usize) -> CryptoResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err((self.make_err)("Unexpected end of message"));
+        }
+        let (slice, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(slice)
+    }
+
+    /// Reads the next field number and value, or `None` at the end of the message
+    pub fn next_field(&mut self) -> CryptoResult<Option<(u64, ProtoValue<'a>)>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.read_varint()?;
+        let value = match tag & 0x7 {
+            WIRE_TYPE_VARINT => ProtoValue::Varint(self.read_varint()?),
+            WIRE_TYPE_LEN => {
+                let len = self.read_varint()?;
+                let len = usize::try_from(len)
+                    .map_err(|_| (self.make_err)("Unexpected end of message"))?;
+                ProtoValue::Bytes(self.read_slice(len)?)
+            }
+            WIRE_TYPE_FIXED64 => {
+                self.read_slice(8)?;
+                ProtoValue::Other
+            }
+            WIRE_TYPE_FIXED32 => {
+                self.read_slice(4)?;
+                ProtoValue::Other
+            }
+            _ => return Err((self.make_err)("Unsupported wire type in message")),
+        };
+        Ok(Some((tag >> 3, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(msg: &'static str) -> CryptoError {
+        CryptoError::generic_err(msg)
+    }
+
+    #[test]
+    fn encode_varint_works() {
+        let mut out = Vec::new();
+        encode_varint(1, &mut out);
+        encode_varint(300, &mut out);
+        encode_varint(u64::MAX, &mut out);
+        assert_eq!(
+            out,
+            [1, 0xAC, 0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+        );
+    }
+
+    #[test]
+    fn next_field_works() {
+        let mut data = Vec::new();
+        encode_varint_field(1, 300, &mut data);
+        encode_bytes_field(2, b"foo", &mut data);
+        data.extend_from_slice(&[3 << 3 | 5, 1, 2, 3, 4]);
+
+        let mut reader = ProtoReader::new(&data, err);
+        match reader.next_field().unwrap() {
+            Some((1, ProtoValue::Varint(300))) => {}
+            _ => panic!("Unexpected field"),
+        }
+        match reader.next_field().unwrap() {
+            Some((2, ProtoValue::Bytes(b"foo"))) => {}
+            _ => panic!("Unexpected field"),
+        }
+        match reader.next_field().unwrap() {
+            Some((3, ProtoValue::Other)) => {}
+            _ => panic!("Unexpected field"),
+        }
+        assert!(reader.next_field().unwrap().is_none());
+    }
+
+    #[test]
+    fn next_field_errs_for_truncated_message() {
+        let mut data = Vec::new();
+        encode_bytes_field(2, b"foo", &mut data);
+        let mut reader = ProtoReader::new(&data[..4], err);
+        match reader.next_field().unwrap_err() {
+            CryptoError::GenericErr { msg, .. } => assert_eq!(msg, "Unexpected end of message"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        let mut reader = ProtoReader::new(&[0x08, 0x80], err);
+        match reader.next_field().unwrap_err() {
+            CryptoError::GenericErr { msg, .. } => assert_eq!(msg, "Invalid varint in message"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+}
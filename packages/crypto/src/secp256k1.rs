@@ -1,3 +1,5 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
 use digest::{Digest, Update}; // trait
 use k256::{
     ecdsa::recoverable,
@@ -63,6 +65,29 @@ pub fn secp256k1_verify(
     }
 }
 
+/// Identifier of SHA-256 for [`secp256k1_verify_prehashed`].
+pub const HASH_FUNCTION_SHA256: u32 = 1;
+/// Identifier of Keccak-256 (as used by Ethereum) for [`secp256k1_verify_prehashed`].
+pub const HASH_FUNCTION_KECCAK256: u32 = 2;
+
+/// Like [`secp256k1_verify`], but the caller states which hash function produced the 32 byte
+/// `digest`. The digest is verified as is and never hashed again.
+///
+/// Returns an [`CryptoError::InvalidHashFunction`] error for identifiers other than
+/// [`HASH_FUNCTION_SHA256`] and [`HASH_FUNCTION_KECCAK256`].
+pub fn secp256k1_verify_prehashed(
+    digest: &[u8],
+    hash_function: u32,
+    signature: &[u8],
+    public_key: &[u8],
+) -> CryptoResult<bool> {
+    match hash_function {
+        HASH_FUNCTION_SHA256 | HASH_FUNCTION_KECCAK256 => {}
+        id => return Err(CryptoError::invalid_hash_function(id)),
+    }
+    secp256k1_verify(digest, signature, public_key)
+}
+
 /// Recovers a public key from a message hash and a signature.
 ///
 /// This is required when working with Ethereum where public keys
@@ -233,6 +258,64 @@ mod tests {
         .unwrap());
     }
 
+    #[test]
+    fn secp256k1_verify_prehashed_works() {
+        let message_digest = Sha256::new().chain(MSG);
+        let message_hash = message_digest.clone().finalize();
+        let secret_key = SigningKey::random(&mut OsRng);
+        let signature: Signature = secret_key.sign_digest(message_digest);
+        let public_key = VerifyingKey::from(&secret_key);
+        let public_key = public_key.to_encoded_point(true);
+
+        for hash_function in [HASH_FUNCTION_SHA256, HASH_FUNCTION_KECCAK256] {
+            assert!(secp256k1_verify_prehashed(
+                &message_hash,
+                hash_function,
+                signature.as_bytes(),
+                public_key.as_bytes()
+            )
+            .unwrap());
+        }
+
+        // The digest is not hashed again
+        let double_hash = Sha256::digest(message_hash);
+        assert!(!secp256k1_verify_prehashed(
+            &double_hash,
+            HASH_FUNCTION_SHA256,
+            signature.as_bytes(),
+            public_key.as_bytes()
+        )
+        .unwrap());
+
+        // Unknown identifiers
+        for hash_function in [0, 3, u32::MAX] {
+            match secp256k1_verify_prehashed(
+                &message_hash,
+                hash_function,
+                signature.as_bytes(),
+                public_key.as_bytes(),
+            )
+            .unwrap_err()
+            {
+                CryptoError::InvalidHashFunction { id, .. } => assert_eq!(id, hash_function),
+                err => panic!("Unexpected error: {}", err),
+            }
+        }
+
+        // Digest must have 32 bytes
+        match secp256k1_verify_prehashed(
+            &message_hash[..31],
+            HASH_FUNCTION_SHA256,
+            signature.as_bytes(),
+            public_key.as_bytes(),
+        )
+        .unwrap_err()
+        {
+            CryptoError::InvalidHashFormat { .. } => {}
+            err => panic!("Unexpected error: {}", err),
+        }
+    }
+
     #[test]
     fn test_cosmos_secp256k1_verify() {
         let public_key = base64::decode(COSMOS_SECP256K1_PUBKEY_BASE64).unwrap();
@@ -0,0 +1,310 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use digest::{Digest, Update}; // trait
+use p256::{
+    ecdsa::signature::{DigestVerifier, Signature as _}, // traits
+    ecdsa::{Signature, VerifyingKey},                   // type aliases
+    elliptic_curve::{
+        ops::{Invert, Reduce},
+        sec1::ToEncodedPoint,
+        subtle::Choice,
+        DecompressPoint, PrimeField,
+    },
+    AffinePoint,
+    FieldBytes,
+    ProjectivePoint,
+    PublicKey,
+    Scalar,
+    U256,
+};
+
+use crate::errors::{CryptoError, CryptoResult};
+use crate::identity_digest::Identity256;
+
+/// Length of a serialized compressed public key
+const ECDSA_COMPRESSED_PUBKEY_LEN: usize = 33;
+/// Length of a serialized uncompressed public key
+const ECDSA_UNCOMPRESSED_PUBKEY_LEN: usize = 65;
+
+/// ECDSA secp256r1 (also known as P-256 or prime256v1) implementation.
+///
+/// This function verifies message hashes (typically, hashed unsing SHA-256) against a signature,
+/// with the public key of the signer, using the secp256r1 elliptic curve digital signature
+/// parametrization / algorithm. This is the curve used by passkeys (WebAuthn) and most
+/// hardware security modules.
+///
+/// The signature and public key use the same encoding as in [`crate::secp256k1_verify`]:
+/// - signature:  Serialized "compact" signature (64 bytes).
+/// - public key: Serialized according to SEC 1 (33 or 65 bytes).
+pub fn secp256r1_verify(
+    message_hash: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> CryptoResult<bool> {
+    let message_hash = read_hash(message_hash)?;
+    let signature = read_signature(signature)?;
+    check_pubkey(public_key)?;
+
+    let mut signature =
+        Signature::from_bytes(&signature).map_err(|e| CryptoError::generic_err(e.to_string()))?;
+    // Non low-S signatures require normalization
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+    }
+
+    let public_key = VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| CryptoError::generic_err(e.to_string()))?;
+
+    let message_digest = Identity256::new().chain(message_hash);
+    match public_key.verify_digest(message_digest, &signature) {
+        Ok(_) => Ok(true),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Recovers a secp256r1 public key from a message hash and a signature.
+///
+/// `recovery_param` must be 0 or 1, just like for [`crate::secp256k1_recover_pubkey`].
+/// All other values are invalid.
+///
+/// Returns the recovered pubkey in uncompressed form, which can be used
+/// in secp256r1_verify directly.
+pub fn secp256r1_recover_pubkey(
+    message_hash: &[u8],
+    signature: &[u8],
+    recovery_param: u8,
+) -> Result<Vec<u8>, CryptoError> {
+    let message_hash = read_hash(message_hash)?;
+    let signature = read_signature(signature)?;
+
+    let mut y_odd = match recovery_param {
+        0 => false,
+        1 => true,
+        _ => return Err(CryptoError::invalid_recovery_param()),
+    };
+
+    let mut signature =
+        Signature::from_bytes(&signature).map_err(|e| CryptoError::generic_err(e.to_string()))?;
+    // Normalizing a high-S signature negates the nonce point, which flips the parity of its y coordinate
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        y_odd = !y_odd;
+    }
+    let (r, s) = signature.split_scalars();
+
+    // The nonce point R has the x coordinate r. Recovery of points with x coordinates >= n
+    // (which are very unlikely and not supported by the recovery param) is not implemented.
+    let big_r = Option::<AffinePoint>::from(AffinePoint::decompress(
+        &r.to_repr(),
+        Choice::from(y_odd as u8),
+    ))
+    .ok_or_else(|| CryptoError::generic_err("Nonce point not on curve"))?;
+
+    // pubkey = r^-1 * (s * R - z * G)
+    let z = <Scalar as Reduce<U256>>::from_be_bytes_reduced(FieldBytes::from(message_hash));
+    let r_inv = *r.invert();
+    let big_r = ProjectivePoint::from(big_r);
+    let point = (big_r * *s - ProjectivePoint::GENERATOR * z) * r_inv;
+    let pubkey = PublicKey::from_affine(point.to_affine())
+        .map_err(|e| CryptoError::generic_err(e.to_string()))?;
+    let encoded: Vec<u8> = pubkey.to_encoded_point(false).as_bytes().into();
+    Ok(encoded)
+}
+
+/// Error raised when hash is not 32 bytes long
+struct InvalidSecp256r1HashFormat;
+
+impl From<InvalidSecp256r1HashFormat> for CryptoError {
+    fn from(_original: InvalidSecp256r1HashFormat) -> Self {
+        CryptoError::invalid_hash_format()
+    }
+}
+
+fn read_hash(data: &[u8]) -> Result<[u8; 32], InvalidSecp256r1HashFormat> {
+    data.try_into().map_err(|_| InvalidSecp256r1HashFormat)
+}
+
+/// Error raised when signature is not 64 bytes long (32 bytes r, 32 bytes s)
+struct InvalidSecp256r1SignatureFormat;
+
+impl From<InvalidSecp256r1SignatureFormat> for CryptoError {
+    fn from(_original: InvalidSecp256r1SignatureFormat) -> Self {
+        CryptoError::invalid_signature_format()
+    }
+}
+
+fn read_signature(data: &[u8]) -> Result<[u8; 64], InvalidSecp256r1SignatureFormat> {
+    data.try_into().map_err(|_| InvalidSecp256r1SignatureFormat)
+}
+
+/// Error raised when public key is not in one of the two supported formats:
+/// 1. Uncompressed: 65 bytes starting with 0x04
+/// 2. Compressed: 33 bytes starting with 0x02 or 0x03
+struct InvalidSecp256r1PubkeyFormat;
+
+impl From<InvalidSecp256r1PubkeyFormat> for CryptoError {
+    fn from(_original: InvalidSecp256r1PubkeyFormat) -> Self {
+        CryptoError::invalid_pubkey_format()
+    }
+}
+
+fn check_pubkey(data: &[u8]) -> Result<(), InvalidSecp256r1PubkeyFormat> {
+    let ok = match data.first() {
+        Some(0x02) | Some(0x03) => data.len() == ECDSA_COMPRESSED_PUBKEY_LEN,
+        Some(0x04) => data.len() == ECDSA_UNCOMPRESSED_PUBKEY_LEN,
+        _ => false,
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(InvalidSecp256r1PubkeyFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use p256::{
+        ecdsa::signature::Signer, // trait
+        ecdsa::SigningKey,        // type alias
+        elliptic_curve::rand_core::OsRng,
+    };
+    use sha2::Sha256;
+
+    const MSG: &str = "Hello World!";
+
+    /// Signs the SHA-256 hash of `MSG`
+    fn sign(secret_key: &SigningKey) -> Signature {
+        secret_key.sign(MSG.as_bytes())
+    }
+
+    #[test]
+    fn secp256r1_verify_works() {
+        let message_hash = Sha256::digest(MSG);
+
+        let secret_key = SigningKey::random(&mut OsRng);
+        let signature = sign(&secret_key);
+        let public_key = VerifyingKey::from(&secret_key);
+
+        // Verification (uncompressed public key)
+        assert!(secp256r1_verify(
+            &message_hash,
+            signature.as_ref(),
+            public_key.to_encoded_point(false).as_bytes()
+        )
+        .unwrap());
+
+        // Verification (compressed public key)
+        assert!(secp256r1_verify(
+            &message_hash,
+            signature.as_ref(),
+            public_key.to_encoded_point(true).as_bytes()
+        )
+        .unwrap());
+
+        // Wrong message fails
+        let bad_message_hash = Sha256::new()
+            .chain_update(MSG)
+            .chain_update("\0")
+            .finalize();
+        assert!(!secp256r1_verify(
+            &bad_message_hash,
+            signature.as_ref(),
+            public_key.to_encoded_point(false).as_bytes()
+        )
+        .unwrap());
+
+        // Other pubkey fails
+        let other_public_key = VerifyingKey::from(&SigningKey::random(&mut OsRng));
+        assert!(!secp256r1_verify(
+            &message_hash,
+            signature.as_ref(),
+            other_public_key.to_encoded_point(false).as_bytes()
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn secp256r1_verify_accepts_high_s_signatures() {
+        let message_hash = Sha256::digest(MSG);
+        let secret_key = SigningKey::random(&mut OsRng);
+        let signature = sign(&secret_key);
+        let public_key = VerifyingKey::from(&secret_key);
+
+        let (r, s) = signature.split_scalars();
+        let high_s = Signature::from_scalars(r, -*s).unwrap();
+        assert!(secp256r1_verify(
+            &message_hash,
+            high_s.as_ref(),
+            public_key.to_encoded_point(false).as_bytes()
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn secp256r1_verify_fails_for_invalid_formats() {
+        let message_hash = Sha256::digest(MSG);
+        let secret_key = SigningKey::random(&mut OsRng);
+        let signature = sign(&secret_key);
+        let public_key = VerifyingKey::from(&secret_key).to_encoded_point(false);
+        let public_key = public_key.as_bytes();
+
+        match secp256r1_verify(&message_hash[1..], signature.as_ref(), public_key).unwrap_err() {
+            CryptoError::InvalidHashFormat { .. } => {}
+            err => panic!("Unexpected error: {}", err),
+        }
+        match secp256r1_verify(&message_hash, &signature.as_ref()[1..], public_key).unwrap_err() {
+            CryptoError::InvalidSignatureFormat { .. } => {}
+            err => panic!("Unexpected error: {}", err),
+        }
+        match secp256r1_verify(&message_hash, signature.as_ref(), &public_key[1..]).unwrap_err() {
+            CryptoError::InvalidPubkeyFormat { .. } => {}
+            err => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn secp256r1_recover_pubkey_works() {
+        for _ in 0..8 {
+            let message_hash = Sha256::digest(MSG);
+            let secret_key = SigningKey::random(&mut OsRng);
+            let signature = sign(&secret_key);
+            let expected = VerifyingKey::from(&secret_key)
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec();
+
+            // exactly one of the two recovery params recovers the signer
+            let recovered: Vec<_> = [0, 1]
+                .into_iter()
+                .map(|id| secp256r1_recover_pubkey(&message_hash, signature.as_ref(), id).unwrap())
+                .collect();
+            assert!(recovered.contains(&expected));
+            assert_ne!(recovered[0], recovered[1]);
+            let id = if recovered[0] == expected { 0u8 } else { 1u8 };
+
+            // The high-S version of the signature recovers the same key with the other parity
+            let (r, s) = signature.split_scalars();
+            let high_s = Signature::from_scalars(r, -*s).unwrap();
+            let pubkey = secp256r1_recover_pubkey(&message_hash, high_s.as_ref(), id ^ 1).unwrap();
+            assert_eq!(pubkey, expected);
+        }
+    }
+
+    #[test]
+    fn secp256r1_recover_pubkey_fails_for_invalid_recovery_param() {
+        let message_hash = Sha256::digest(MSG);
+        let secret_key = SigningKey::random(&mut OsRng);
+        let signature = sign(&secret_key);
+
+        for recovery_param in [2, 3, 4, 255] {
+            match secp256r1_recover_pubkey(&message_hash, signature.as_ref(), recovery_param)
+                .unwrap_err()
+            {
+                CryptoError::InvalidRecoveryParam { .. } => {}
+                err => panic!("Unexpected error: {}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,916 @@
+//! Verification of Tendermint (CometBFT) headers as done by light clients.
+//!
+//! All inputs use the protobuf encoding of the `tendermint.types` messages as returned by the
+//! RPC of a node and as used in the headers of IBC light client updates. Only Ed25519
+//! validator keys are supported.
+use alloc::vec::Vec;
+use ed25519_zebra::{Signature, VerificationKey};
+
+use crate::errors::{CryptoError, CryptoResult};
+use crate::hashing::sha256;
+use crate::proto::{
+    encode_bytes_field, encode_varint, encode_varint_field, ProtoReader, ProtoValue,
+};
+
+/// Max number of validators in a validator set
+pub const TENDERMINT_MAX_VALIDATORS: usize = 512;
+
+/// `BlockIDFlagCommit` of `CommitSig`, i.e. the validator signed the committed block
+const BLOCK_ID_FLAG_COMMIT: u64 = 2;
+/// `SignedMsgType` of precommit votes
+const PRECOMMIT_TYPE: u64 = 2;
+/// Length of a validator address
+const ADDRESS_LEN: usize = 20;
+
+/// Verifies an untrusted header against a trusted one, following the skipping verification of
+/// the Tendermint light client with the default trust level of 1/3.
+///
+/// - `trusted_header`: a `tendermint.types.Header` that was verified before
+/// - `trusted_next_validators`: the `tendermint.types.ValidatorSet` that the trusted header
+///   commits to in `next_validators_hash`
+/// - `untrusted_header`: the `tendermint.types.SignedHeader` (header and commit) to verify
+/// - `untrusted_validators`: the `tendermint.types.ValidatorSet` of the untrusted header
+///
+/// If the untrusted header directly follows the trusted one, its validators must be the
+/// trusted next validators. Otherwise, validators of the trusted next validator set holding
+/// more than 1/3 of its voting power must have signed the untrusted header. In both cases,
+/// validators holding more than 2/3 of the voting power of the untrusted validator set must
+/// have signed it.
+///
+/// Returns an error for malformed inputs and `false` if the inputs are well-formed but the
+/// untrusted header cannot be trusted. The trusting period and clock drift are not checked,
+/// since they depend on the current time. Callers are responsible for this.
+pub fn tendermint_verify_header(
+    trusted_header: &[u8],
+    trusted_next_validators: &[u8],
+    untrusted_header: &[u8],
+    untrusted_validators: &[u8],
+) -> CryptoResult<bool> {
+    let trusted = decode_header(trusted_header)?;
+    let trusted_next_validators = decode_validator_set(trusted_next_validators)?;
+    let (untrusted, commit) = decode_signed_header(untrusted_header)?;
+    let untrusted_validators = decode_validator_set(untrusted_validators)?;
+
+    if untrusted.chain_id != trusted.chain_id
+        || untrusted.height <= trusted.height
+        || untrusted.time <= trusted.time
+        || trusted_next_validators.hash != trusted.next_validators_hash
+        || untrusted_validators.hash != untrusted.validators_hash
+        || commit.height != untrusted.height
+        || commit.block_id.hash != untrusted.hash
+    {
+        return Ok(false);
+    }
+
+    let chain_id = &untrusted.chain_id;
+    if untrusted.height == trusted.height + 1 {
+        if untrusted.validators_hash != trusted.next_validators_hash {
+            return Ok(false);
+        }
+    } else if !has_signing_power(&trusted_next_validators, &commit, chain_id, false, 1, 3) {
+        return Ok(false);
+    }
+    Ok(has_signing_power(
+        &untrusted_validators,
+        &commit,
+        chain_id,
+        true,
+        2,
+        3,
+    ))
+}
+
+fn tendermint_err(msg: &'static str) -> CryptoError {
+    CryptoError::tendermint_err(msg)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Timestamp {
+    seconds: i64,
+    nanos: i32,
+}
+
+#[derive(Debug)]
+struct Header {
+    chain_id: Vec<u8>,
+    height: i64,
+    time: Timestamp,
+    validators_hash: Vec<u8>,
+    next_validators_hash: Vec<u8>,
+    /// The hash of the header, i.e. the block hash
+    hash: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct BlockId {
+    hash: Vec<u8>,
+    part_set_total: u32,
+    part_set_hash: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct CommitSig {
+    block_id_flag: u64,
+    validator_address: Vec<u8>,
+    /// The encoded timestamp, which is part of the signed vote
+    timestamp: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Commit {
+    height: i64,
+    round: i32,
+    block_id: BlockId,
+    signatures: Vec<CommitSig>,
+}
+
+#[derive(Debug)]
+struct Validator {
+    address: [u8; ADDRESS_LEN],
+    pub_key: VerificationKey,
+    voting_power: u64,
+}
+
+#[derive(Debug)]
+struct ValidatorSet {
+    validators: Vec<Validator>,
+    total_voting_power: u64,
+    hash: Vec<u8>,
+}
+
+/// Checks if validators of the set holding more than `numerator / denominator` of the total
+/// voting power signed the commit.
+///
+/// With `by_index`, the commit signatures must be in the order of the validator set, as it is
+/// the case for the validator set that created the commit. Otherwise, validators are looked
+/// up by address and signatures of unknown validators are ignored.
+fn has_signing_power(
+    validators: &ValidatorSet,
+    commit: &Commit,
+    chain_id: &[u8],
+    by_index: bool,
+    numerator: u64,
+    denominator: u64,
+) -> bool {
+    if by_index && commit.signatures.len() != validators.validators.len() {
+        return false;
+    }
+    let needed = u128::from(validators.total_voting_power) * u128::from(numerator);
+    let mut tallied = 0u128;
+    let mut seen = Vec::new();
+    for (index, sig) in commit.signatures.iter().enumerate() {
+        if sig.block_id_flag != BLOCK_ID_FLAG_COMMIT {
+            continue;
+        }
+        let position = if by_index {
+            Some(index)
+        } else {
+            validators
+                .validators
+                .iter()
+                .position(|v| v.address.as_slice() == sig.validator_address.as_slice())
+        };
+        let position = match position {
+            Some(position) => position,
+            None => continue,
+        };
+        if by_index
+            && validators.validators[position].address.as_slice()
+                != sig.validator_address.as_slice()
+        {
+            // a signature in the wrong slot must not count for another validator
+            return false;
+        }
+        if seen.contains(&position) {
+            // double votes are invalid
+            return false;
+        }
+        seen.push(position);
+
+        let validator = &validators.validators[position];
+        let signature = match <[u8; 64]>::try_from(sig.signature.as_slice()) {
+            Ok(signature) => Signature::from(signature),
+            Err(_) => return false,
+        };
+        let sign_bytes = vote_sign_bytes(chain_id, commit, sig);
+        if validator.pub_key.verify(&signature, &sign_bytes).is_err() {
+            return false;
+        }
+
+        tallied += u128::from(validator.voting_power);
+        if tallied * u128::from(denominator) > needed {
+            return true;
+        }
+    }
+    false
+}
+
+/// The length-prefixed encoding of the `CanonicalVote` signed by a validator
+fn vote_sign_bytes(chain_id: &[u8], commit: &Commit, sig: &CommitSig) -> Vec<u8> {
+    let mut part_set_header = Vec::new();
+    if commit.block_id.part_set_total != 0 {
+        encode_varint_field(
+            1,
+            commit.block_id.part_set_total.into(),
+            &mut part_set_header,
+        );
+    }
+    if !commit.block_id.part_set_hash.is_empty() {
+        encode_bytes_field(2, &commit.block_id.part_set_hash, &mut part_set_header);
+    }
+    let mut block_id = Vec::new();
+    if !commit.block_id.hash.is_empty() {
+        encode_bytes_field(1, &commit.block_id.hash, &mut block_id);
+    }
+    if !part_set_header.is_empty() {
+        encode_bytes_field(2, &part_set_header, &mut block_id);
+    }
+
+    let mut vote = Vec::new();
+    encode_varint_field(1, PRECOMMIT_TYPE, &mut vote);
+    // height and round are sfixed64
+    if commit.height != 0 {
+        vote.push(2 << 3 | 1);
+        vote.extend_from_slice(&commit.height.to_le_bytes());
+    }
+    if commit.round != 0 {
+        vote.push(3 << 3 | 1);
+        vote.extend_from_slice(&i64::from(commit.round).to_le_bytes());
+    }
+    // like `CanonicalizeBlockID`, a zero block ID (vote for nil) is omitted
+    if !block_id.is_empty() {
+        encode_bytes_field(4, &block_id, &mut vote);
+    }
+    encode_bytes_field(5, &sig.timestamp, &mut vote);
+    if !chain_id.is_empty() {
+        encode_bytes_field(6, chain_id, &mut vote);
+    }
+
+    let mut out = Vec::with_capacity(vote.len() + 2);
+    encode_varint(vote.len() as u64, &mut out);
+    out.extend_from_slice(&vote);
+    out
+}
+
+/// The root of a Tendermint simple Merkle tree with the given leaves
+fn merkle_root(leaves: &[Vec<u8>]) -> Vec<u8> {
+    match leaves {
+        [] => sha256(&[]).to_vec(),
+        [leaf] => sha256(&[&[0u8][..], leaf].concat()).to_vec(),
+        _ => {
+            // the largest power of 2 smaller than the number of leaves
+            let mut split = 1;
+            while split * 2 < leaves.len() {
+                split *= 2;
+            }
+            let left = merkle_root(&leaves[..split]);
+            let right = merkle_root(&leaves[split..]);
+            sha256(&[&[1u8][..], &left, &right].concat()).to_vec()
+        }
+    }
+}
+
+/// Encodes the value as the single field of a protobuf message, which is how the wrapper
+/// types `StringValue`, `BytesValue` and `Int64Value` are encoded in header hashes
+fn wrapped_bytes(value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if !value.is_empty() {
+        encode_bytes_field(1, value, &mut out);
+    }
+    out
+}
+
+fn wrapped_int(value: i64) -> Vec<u8> {
+    let mut out = Vec::new();
+    if value != 0 {
+        encode_varint_field(1, value as u64, &mut out);
+    }
+    out
+}
+
+fn decode_timestamp(data: &[u8]) -> CryptoResult<Timestamp> {
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    let mut timestamp = Timestamp::default();
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Varint(v)) => timestamp.seconds = v as i64,
+            (2, ProtoValue::Varint(v)) => timestamp.nanos = v as i64 as i32,
+            (1 | 2, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(timestamp)
+}
+
+fn invalid_field() -> CryptoError {
+    CryptoError::tendermint_err("Invalid field type in message")
+}
+
+fn decode_header(data: &[u8]) -> CryptoResult<Header> {
+    // The leaves of the header hash in field order. Message fields are hashed in their
+    // encoding, all other fields in the encoding of the respective wrapper type.
+    let mut leaves: [Vec<u8>; 14] = Default::default();
+    let mut chain_id = Vec::new();
+    let mut height = 0i64;
+    let mut time = Timestamp::default();
+    let mut validators_hash = Vec::new();
+    let mut next_validators_hash = Vec::new();
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1 | 4 | 5, ProtoValue::Bytes(bytes)) => {
+                if field == 4 {
+                    time = decode_timestamp(bytes)?;
+                }
+                leaves[field as usize - 1] = bytes.to_vec();
+            }
+            (2, ProtoValue::Bytes(bytes)) => {
+                chain_id = bytes.to_vec();
+                leaves[1] = wrapped_bytes(bytes);
+            }
+            (3, ProtoValue::Varint(v)) => {
+                height = v as i64;
+                leaves[2] = wrapped_int(height);
+            }
+            (6..=14, ProtoValue::Bytes(bytes)) => {
+                match field {
+                    8 => validators_hash = bytes.to_vec(),
+                    9 => next_validators_hash = bytes.to_vec(),
+                    _ => {}
+                }
+                leaves[field as usize - 1] = wrapped_bytes(bytes);
+            }
+            (1..=14, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    if chain_id.is_empty() || height <= 0 {
+        return Err(tendermint_err("Header requires a chain ID and a height"));
+    }
+    Ok(Header {
+        chain_id,
+        height,
+        time,
+        validators_hash,
+        next_validators_hash,
+        hash: merkle_root(&leaves),
+    })
+}
+
+fn decode_signed_header(data: &[u8]) -> CryptoResult<(Header, Commit)> {
+    let mut header = None;
+    let mut commit = None;
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(bytes)) => header = Some(decode_header(bytes)?),
+            (2, ProtoValue::Bytes(bytes)) => commit = Some(decode_commit(bytes)?),
+            (1 | 2, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    match (header, commit) {
+        (Some(header), Some(commit)) => Ok((header, commit)),
+        _ => Err(tendermint_err(
+            "Signed header requires a header and a commit",
+        )),
+    }
+}
+
+fn decode_commit(data: &[u8]) -> CryptoResult<Commit> {
+    let mut commit = Commit {
+        height: 0,
+        round: 0,
+        block_id: BlockId::default(),
+        signatures: Vec::new(),
+    };
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Varint(v)) => commit.height = v as i64,
+            (2, ProtoValue::Varint(v)) => commit.round = v as i64 as i32,
+            (3, ProtoValue::Bytes(bytes)) => commit.block_id = decode_block_id(bytes)?,
+            (4, ProtoValue::Bytes(bytes)) => {
+                if commit.signatures.len() == TENDERMINT_MAX_VALIDATORS {
+                    return Err(tendermint_err("Too many commit signatures"));
+                }
+                commit.signatures.push(decode_commit_sig(bytes)?);
+            }
+            (1..=4, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(commit)
+}
+
+fn decode_block_id(data: &[u8]) -> CryptoResult<BlockId> {
+    let mut block_id = BlockId::default();
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(bytes)) => block_id.hash = bytes.to_vec(),
+            (2, ProtoValue::Bytes(bytes)) => {
+                let mut reader = ProtoReader::new(bytes, tendermint_err);
+                while let Some((field, value)) = reader.next_field()? {
+                    match (field, value) {
+                        (1, ProtoValue::Varint(v)) => block_id.part_set_total = v as u32,
+                        (2, ProtoValue::Bytes(bytes)) => block_id.part_set_hash = bytes.to_vec(),
+                        (1 | 2, _) => return Err(invalid_field()),
+                        _ => {}
+                    }
+                }
+            }
+            (1 | 2, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(block_id)
+}
+
+fn decode_commit_sig(data: &[u8]) -> CryptoResult<CommitSig> {
+    let mut sig = CommitSig {
+        block_id_flag: 0,
+        validator_address: Vec::new(),
+        timestamp: Vec::new(),
+        signature: Vec::new(),
+    };
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Varint(v)) => sig.block_id_flag = v,
+            (2, ProtoValue::Bytes(bytes)) => sig.validator_address = bytes.to_vec(),
+            (3, ProtoValue::Bytes(bytes)) => sig.timestamp = bytes.to_vec(),
+            (4, ProtoValue::Bytes(bytes)) => sig.signature = bytes.to_vec(),
+            (1..=4, _) => return Err(invalid_field()),
+            _ => {}
+        }
+    }
+    Ok(sig)
+}
+
+fn decode_validator_set(data: &[u8]) -> CryptoResult<ValidatorSet> {
+    let mut validators = Vec::new();
+    // The leaves of the validator set hash, i.e. the encoded `SimpleValidator`s
+    let mut leaves = Vec::new();
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(bytes)) => {
+                if validators.len() == TENDERMINT_MAX_VALIDATORS {
+                    return Err(tendermint_err("Too many validators"));
+                }
+                let (validator, leaf) = decode_validator(bytes)?;
+                validators.push(validator);
+                leaves.push(leaf);
+            }
+            (1, _) => return Err(invalid_field()),
+            // proposer and total voting power are not needed
+            _ => {}
+        }
+    }
+    let total_voting_power = validators
+        .iter()
+        .try_fold(0u64, |sum, v: &Validator| sum.checked_add(v.voting_power))
+        .filter(|total| *total <= i64::MAX as u64)
+        .ok_or_else(|| tendermint_err("Total voting power overflow"))?;
+    if total_voting_power == 0 {
+        return Err(tendermint_err("Validator set has no voting power"));
+    }
+    Ok(ValidatorSet {
+        validators,
+        total_voting_power,
+        hash: merkle_root(&leaves),
+    })
+}
+
+fn decode_validator(data: &[u8]) -> CryptoResult<(Validator, Vec<u8>)> {
+    let mut pub_key = None;
+    let mut pub_key_encoded = &[][..];
+    let mut voting_power = 0u64;
+    let mut reader = ProtoReader::new(data, tendermint_err);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (2, ProtoValue::Bytes(bytes)) => {
+                pub_key_encoded = bytes;
+                let mut reader = ProtoReader::new(bytes, tendermint_err);
+                while let Some((field, value)) = reader.next_field()? {
+                    match (field, value) {
+                        (1, ProtoValue::Bytes(key)) => pub_key = Some(key),
+                        _ => {
+                            return Err(tendermint_err("Only Ed25519 validator keys are supported"))
+                        }
+                    }
+                }
+            }
+            (3, ProtoValue::Varint(v)) => {
+                if v as i64 <= 0 {
+                    return Err(tendermint_err("Validator voting power must be positive"));
+                }
+                voting_power = v;
+            }
+            (2 | 3, _) => return Err(invalid_field()),
+            // The address is derived from the public key.
+            _ => {}
+        }
+    }
+    let pub_key = pub_key.ok_or_else(|| tendermint_err("Validator requires a public key"))?;
+    let address = sha256(pub_key)[..ADDRESS_LEN].try_into().unwrap();
+    let pub_key = VerificationKey::try_from(pub_key)
+        .map_err(|_| tendermint_err("Invalid validator public key"))?;
+
+    let mut leaf = Vec::new();
+    encode_bytes_field(1, pub_key_encoded, &mut leaf);
+    encode_varint_field(2, voting_power, &mut leaf);
+    Ok((
+        Validator {
+            address,
+            pub_key,
+            voting_power,
+        },
+        leaf,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_zebra::SigningKey;
+
+    const CHAIN_ID: &[u8] = b"testing-1";
+
+    fn signing_key(index: u8) -> SigningKey {
+        SigningKey::from([index + 1; 32])
+    }
+
+    fn encode_validator_set(powers: &[(u8, u64)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (index, power) in powers {
+            let pub_key: [u8; 32] = VerificationKey::from(&signing_key(*index)).into();
+            let mut pub_key_msg = Vec::new();
+            encode_bytes_field(1, &pub_key, &mut pub_key_msg);
+            let mut validator = Vec::new();
+            encode_bytes_field(1, &sha256(&pub_key)[..ADDRESS_LEN], &mut validator);
+            encode_bytes_field(2, &pub_key_msg, &mut validator);
+            encode_varint_field(3, *power, &mut validator);
+            encode_bytes_field(1, &validator, &mut out);
+        }
+        out
+    }
+
+    fn validators_hash(powers: &[(u8, u64)]) -> Vec<u8> {
+        decode_validator_set(&encode_validator_set(powers))
+            .unwrap()
+            .hash
+    }
+
+    fn encode_timestamp(seconds: i64) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode_varint_field(1, seconds as u64, &mut out);
+        out
+    }
+
+    fn encode_header(height: i64, validators: &[(u8, u64)], next: &[(u8, u64)]) -> Vec<u8> {
+        let mut version = Vec::new();
+        encode_varint_field(1, 11, &mut version);
+        let mut header = Vec::new();
+        encode_bytes_field(1, &version, &mut header);
+        encode_bytes_field(2, CHAIN_ID, &mut header);
+        encode_varint_field(3, height as u64, &mut header);
+        encode_bytes_field(4, &encode_timestamp(1_700_000_000 + height), &mut header);
+        encode_bytes_field(8, &validators_hash(validators), &mut header);
+        encode_bytes_field(9, &validators_hash(next), &mut header);
+        encode_bytes_field(11, b"app hash of the block", &mut header);
+        header
+    }
+
+    /// Creates a signed header, where the validators with the given indices sign
+    fn encode_signed_header(
+        height: i64,
+        validators: &[(u8, u64)],
+        next: &[(u8, u64)],
+        signers: &[u8],
+    ) -> Vec<u8> {
+        let header = encode_header(height, validators, next);
+        let mut commit = Commit {
+            height,
+            round: 1,
+            block_id: BlockId {
+                hash: decode_header(&header).unwrap().hash,
+                part_set_total: 1,
+                part_set_hash: sha256(b"parts").to_vec(),
+            },
+            signatures: Vec::new(),
+        };
+        for (index, _) in validators {
+            let pub_key: [u8; 32] = VerificationKey::from(&signing_key(*index)).into();
+            let mut sig = CommitSig {
+                block_id_flag: 1, // absent
+                validator_address: sha256(&pub_key)[..ADDRESS_LEN].to_vec(),
+                timestamp: encode_timestamp(1_700_000_000 + height),
+                signature: Vec::new(),
+            };
+            if signers.contains(index) {
+                sig.block_id_flag = BLOCK_ID_FLAG_COMMIT;
+                let signature = signing_key(*index).sign(&vote_sign_bytes(CHAIN_ID, &commit, &sig));
+                sig.signature = <[u8; 64]>::from(signature).to_vec();
+            }
+            commit.signatures.push(sig);
+        }
+
+        let mut part_set_header = Vec::new();
+        encode_varint_field(1, 1, &mut part_set_header);
+        encode_bytes_field(2, &commit.block_id.part_set_hash, &mut part_set_header);
+        let mut block_id = Vec::new();
+        encode_bytes_field(1, &commit.block_id.hash, &mut block_id);
+        encode_bytes_field(2, &part_set_header, &mut block_id);
+        let mut commit_encoded = Vec::new();
+        encode_varint_field(1, height as u64, &mut commit_encoded);
+        encode_varint_field(2, 1, &mut commit_encoded);
+        encode_bytes_field(3, &block_id, &mut commit_encoded);
+        for sig in commit.signatures {
+            let mut sig_encoded = Vec::new();
+            encode_varint_field(1, sig.block_id_flag, &mut sig_encoded);
+            encode_bytes_field(2, &sig.validator_address, &mut sig_encoded);
+            encode_bytes_field(3, &sig.timestamp, &mut sig_encoded);
+            if !sig.signature.is_empty() {
+                encode_bytes_field(4, &sig.signature, &mut sig_encoded);
+            }
+            encode_bytes_field(4, &sig_encoded, &mut commit_encoded);
+        }
+
+        let mut out = Vec::new();
+        encode_bytes_field(1, &header, &mut out);
+        encode_bytes_field(2, &commit_encoded, &mut out);
+        out
+    }
+
+    const VALS: &[(u8, u64)] = &[(0, 10), (1, 10), (2, 10), (3, 10)];
+    const NEXT_VALS: &[(u8, u64)] = &[(2, 10), (3, 10), (4, 10), (5, 10)];
+
+    #[test]
+    fn merkle_root_works() {
+        // empty tree and single leaf
+        assert_eq!(merkle_root(&[]), sha256(&[]).to_vec());
+        assert_eq!(merkle_root(&[b"a".to_vec()]), sha256(&[0, b'a']).to_vec());
+        // the split point is the largest power of 2 smaller than the number of leaves
+        let leaves = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        let left = merkle_root(&leaves[..2]);
+        let right = merkle_root(&leaves[2..]);
+        assert_eq!(
+            merkle_root(&leaves),
+            sha256(&[&[1u8][..], &left, &right].concat()).to_vec()
+        );
+    }
+
+    #[test]
+    fn merkle_root_matches_cometbft() {
+        // test vectors from `TestHashFromByteSlices` of CometBFT's crypto/merkle
+        let root = |leaves: &[&[u8]]| {
+            hex::encode(merkle_root(
+                &leaves.iter().map(|leaf| leaf.to_vec()).collect::<Vec<_>>(),
+            ))
+        };
+        assert_eq!(
+            root(&[]),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            root(&[&[1, 2, 3]]),
+            "054edec1d0211f624fed0cbca9d4f9400b0e491c43742af2c5b0abebf0c990d8"
+        );
+        assert_eq!(
+            root(&[&[1, 2, 3], &[4, 5, 6]]),
+            "82e6cfce00453804379b53962939eaa7906b39904be0813fcadd31b100773c4b"
+        );
+        assert_eq!(
+            root(&[&[1, 2], &[3, 4], &[5, 6], &[7, 8], &[9, 10]]),
+            "f326493eceab4f2d9ffbc78c59432a0a005d6ea98392045c74df5d14a113be18"
+        );
+    }
+
+    #[test]
+    fn decode_header_hash_matches_cometbft() {
+        // the "generates hash" case of `TestHeaderHash` in CometBFT's types/block_test.go
+        let mut version = Vec::new();
+        encode_varint_field(1, 1, &mut version);
+        encode_varint_field(2, 2, &mut version);
+        let mut part_set_header = Vec::new();
+        encode_varint_field(1, 6, &mut part_set_header);
+        encode_bytes_field(2, &[0; 32], &mut part_set_header);
+        let mut last_block_id = Vec::new();
+        encode_bytes_field(1, &[0; 32], &mut last_block_id);
+        encode_bytes_field(2, &part_set_header, &mut last_block_id);
+
+        let mut header = Vec::new();
+        encode_bytes_field(1, &version, &mut header);
+        encode_bytes_field(2, b"chainId", &mut header);
+        encode_varint_field(3, 3, &mut header);
+        // 2019-10-13T16:14:44Z
+        encode_bytes_field(4, &encode_timestamp(1_570_983_284), &mut header);
+        encode_bytes_field(5, &last_block_id, &mut header);
+        let hashed_fields: [&[u8]; 8] = [
+            b"last_commit_hash",
+            b"data_hash",
+            b"validators_hash",
+            b"next_validators_hash",
+            b"consensus_hash",
+            b"app_hash",
+            b"last_results_hash",
+            b"evidence_hash",
+        ];
+        for (field, value) in (6..).zip(hashed_fields) {
+            encode_bytes_field(field, &sha256(value), &mut header);
+        }
+        encode_bytes_field(14, &sha256(b"proposer_address")[..ADDRESS_LEN], &mut header);
+
+        assert_eq!(
+            hex::encode_upper(decode_header(&header).unwrap().hash),
+            "F740121F553B5418C3EFBD343C2DBFE9E007BB67B0D020A0741374BAB65242A4"
+        );
+    }
+
+    #[test]
+    fn vote_sign_bytes_matches_cometbft() {
+        // the precommit case of `TestVoteSignBytesTestVectors` in CometBFT's types/vote_test.go:
+        // height 1, round 1, a nil block ID and the zero time of Go
+        let commit = Commit {
+            height: 1,
+            round: 1,
+            block_id: BlockId {
+                hash: Vec::new(),
+                part_set_total: 0,
+                part_set_hash: Vec::new(),
+            },
+            signatures: Vec::new(),
+        };
+        let sig = CommitSig {
+            block_id_flag: BLOCK_ID_FLAG_COMMIT,
+            validator_address: Vec::new(),
+            timestamp: vec![
+                0x08, 0x80, 0x92, 0xb8, 0xc3, 0x98, 0xfe, 0xff, 0xff, 0xff, 0x01,
+            ],
+            signature: Vec::new(),
+        };
+        assert_eq!(
+            vote_sign_bytes(b"", &commit, &sig),
+            [
+                0x21, // length
+                0x08, 0x02, // type
+                0x11, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // height
+                0x19, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // round
+                0x2a, 0x0b, 0x08, 0x80, 0x92, 0xb8, 0xc3, 0x98, 0xfe, 0xff, 0xff, 0xff,
+                0x01, // timestamp
+            ]
+        );
+    }
+
+    #[test]
+    fn tendermint_verify_header_works_for_adjacent_headers() {
+        let trusted = encode_header(10, VALS, VALS);
+        let untrusted = encode_signed_header(11, VALS, NEXT_VALS, &[0, 1, 2]);
+        assert!(tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn tendermint_verify_header_works_for_non_adjacent_headers() {
+        let trusted = encode_header(10, VALS, VALS);
+        // 2 of 4 trusted validators signed
+        let untrusted = encode_signed_header(20, NEXT_VALS, NEXT_VALS, &[2, 3, 4]);
+        assert!(tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(NEXT_VALS)
+        )
+        .unwrap());
+
+        // 1 of 4 trusted validators signed
+        let untrusted = encode_signed_header(20, NEXT_VALS, NEXT_VALS, &[3, 4, 5]);
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(NEXT_VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn tendermint_verify_header_requires_two_thirds_of_validators() {
+        let trusted = encode_header(10, VALS, VALS);
+        let untrusted = encode_signed_header(11, VALS, VALS, &[0, 1]);
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn tendermint_verify_header_fails_for_wrong_validators() {
+        let trusted = encode_header(10, VALS, VALS);
+        // adjacent header with validators other than the trusted next validators
+        let untrusted = encode_signed_header(11, NEXT_VALS, NEXT_VALS, &[2, 3, 4, 5]);
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(NEXT_VALS)
+        )
+        .unwrap());
+
+        // validator set does not match the header
+        let untrusted = encode_signed_header(11, VALS, VALS, &[0, 1, 2, 3]);
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(NEXT_VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn tendermint_verify_header_fails_for_old_headers() {
+        let trusted = encode_header(10, VALS, VALS);
+        let untrusted = encode_signed_header(10, VALS, VALS, &[0, 1, 2, 3]);
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn tendermint_verify_header_fails_for_modified_header() {
+        let trusted = encode_header(10, VALS, VALS);
+        let mut untrusted = encode_signed_header(11, VALS, VALS, &[0, 1, 2, 3]);
+        // flip a byte in the app hash
+        let pos = untrusted.windows(8).position(|w| w == b"app hash").unwrap();
+        untrusted[pos] ^= 1;
+        assert!(!tendermint_verify_header(
+            &trusted,
+            &encode_validator_set(VALS),
+            &untrusted,
+            &encode_validator_set(VALS)
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn has_signing_power_checks_addresses_by_index() {
+        let (header, mut commit) =
+            decode_signed_header(&encode_signed_header(11, VALS, VALS, &[0, 1, 2, 3])).unwrap();
+        let validators = decode_validator_set(&encode_validator_set(VALS)).unwrap();
+        let chain_id = header.chain_id.as_slice();
+        assert!(has_signing_power(
+            &validators,
+            &commit,
+            chain_id,
+            true,
+            2,
+            3
+        ));
+
+        // the signature is still valid for the validator of the slot, but claims to be from another one
+        commit.signatures[0].validator_address = commit.signatures[1].validator_address.clone();
+        assert!(!has_signing_power(
+            &validators,
+            &commit,
+            chain_id,
+            true,
+            2,
+            3
+        ));
+    }
+
+    #[test]
+    fn tendermint_verify_header_errs_for_malformed_input() {
+        let trusted = encode_header(10, VALS, VALS);
+        let untrusted = encode_signed_header(11, VALS, VALS, &[0, 1, 2, 3]);
+        let vals = encode_validator_set(VALS);
+        match tendermint_verify_header(&trusted, &vals, &untrusted[..10], &vals).unwrap_err() {
+            CryptoError::TendermintErr { msg, .. } => {
+                assert_eq!(msg, "Unexpected end of message")
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        match tendermint_verify_header(&trusted, &[], &untrusted, &vals).unwrap_err() {
+            CryptoError::TendermintErr { msg, .. } => {
+                assert_eq!(msg, "Validator set has no voting power")
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+}
@@ -51,6 +51,31 @@ use std::str::FromStr;
 ///
 /// where `InstantiateMsg`, `ExecuteMsg`, and `QueryMsg` are contract defined
 /// types that implement `DeserializeOwned + JsonSchema`.
+///
+/// In order to allow other contracts to depend on this contract, e.g. to use its
+/// message types, the exports can be disabled with a `library` feature of the contract.
+/// Otherwise the Wasm builds of the depending contracts contain duplicate entry points.
+///
+/// ```
+/// # use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError};
+/// # type InstantiateMsg = ();
+/// #[cfg(not(feature = "library"))]
+/// use cosmwasm_std::entry_point;
+///
+/// #[cfg_attr(not(feature = "library"), entry_point)]
+/// pub fn instantiate(
+///     deps: DepsMut,
+///     env: Env,
+///     info: MessageInfo,
+///     msg: InstantiateMsg,
+/// ) -> Result<Response, StdError> {
+/// #   Ok(Default::default())
+/// }
+/// ```
+///
+/// This needs to be a feature of the contract rather than of cosmwasm-std. Since Cargo
+/// unifies the features of a dependency, a cosmwasm-std feature enabled by one contract
+/// would disable the entry points of all contracts in the build.
 #[proc_macro_attribute]
 pub fn entry_point(_attr: TokenStream, mut item: TokenStream) -> TokenStream {
     let cloned = item.clone();
@@ -8,7 +8,7 @@ use std::borrow::Cow;
 use std::fmt;
 use std::ops::Deref;
 
-use crate::{binary::Binary, HexBinary};
+use crate::{binary::Binary, HexBinary, StdError, StdResult};
 
 /// A human readable address.
 ///
@@ -154,7 +154,9 @@ impl<'a> From<&'a Addr> for Cow<'a, Addr> {
 /// addition to that there are many unsafe ways to convert any binary data into an instance.
 /// So the type shoud be treated as a marker to express the intended data type, not as
 /// a validity guarantee of any sort.
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, JsonSchema)]
+#[derive(
+    Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema,
+)]
 pub struct CanonicalAddr(pub Binary);
 
 /// Implement `CanonicalAddr == Binary`
@@ -260,13 +262,64 @@ impl Deref for CanonicalAddr {
     }
 }
 
+impl AsRef<[u8]> for CanonicalAddr {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
 impl CanonicalAddr {
     pub fn as_slice(&self) -> &[u8] {
         self.0.as_slice()
     }
+
+    /// Copies the address into a fixed size array, e.g. to use it as a compact storage key.
+    /// Returns an error if the address does not have the expected length.
+    ///
+    /// ```
+    /// # use cosmwasm_std::CanonicalAddr;
+    /// let addr = CanonicalAddr::from([0x14, 0xd3, 0xcc, 0x81]);
+    /// let array: [u8; 4] = addr.to_array().unwrap();
+    /// assert_eq!(array, [0x14, 0xd3, 0xcc, 0x81]);
+    /// assert!(addr.to_array::<20>().is_err());
+    /// ```
+    pub fn to_array<const LENGTH: usize>(&self) -> StdResult<[u8; LENGTH]> {
+        self.0.to_array()
+    }
+}
+
+impl<const LENGTH: usize> TryFrom<&CanonicalAddr> for [u8; LENGTH] {
+    type Error = StdError;
+
+    fn try_from(source: &CanonicalAddr) -> StdResult<Self> {
+        source.to_array()
+    }
+}
+
+impl<const LENGTH: usize> TryFrom<CanonicalAddr> for [u8; LENGTH] {
+    type Error = StdError;
+
+    fn try_from(source: CanonicalAddr) -> StdResult<Self> {
+        source.to_array()
+    }
 }
 
 impl fmt::Display for CanonicalAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(self, f)
+    }
+}
+
+impl fmt::LowerHex for CanonicalAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.as_slice() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::UpperHex for CanonicalAddr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for byte in self.0.as_slice() {
             write!(f, "{:02X}", byte)?;
@@ -587,6 +640,59 @@ mod tests {
         assert_eq!(address.to_string(), "1203AB00FF");
     }
 
+    #[test]
+    fn canonical_addr_implements_hex_formatting() {
+        let address = CanonicalAddr::from([0x12, 0x03, 0xab, 0x00, 0xff]);
+        assert_eq!(format!("{:x}", address), "1203ab00ff");
+        assert_eq!(format!("{:X}", address), "1203AB00FF");
+    }
+
+    #[test]
+    fn canonical_addr_supports_slicing() {
+        let address = CanonicalAddr::from([0x12, 0x03, 0xab, 0x00, 0xff]);
+        assert_eq!(&address[1..3], &[0x03, 0xab]);
+        assert_eq!(address[4], 0xff);
+        assert_eq!(address.as_ref(), &[0x12, 0x03, 0xab, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn canonical_addr_to_array_works() {
+        let address = CanonicalAddr::from([0x12, 0x03, 0xab]);
+        assert_eq!(address.to_array::<3>().unwrap(), [0x12, 0x03, 0xab]);
+
+        let array: [u8; 3] = (&address).try_into().unwrap();
+        assert_eq!(array, [0x12, 0x03, 0xab]);
+        let array: [u8; 3] = address.clone().try_into().unwrap();
+        assert_eq!(array, [0x12, 0x03, 0xab]);
+
+        match address.to_array::<20>().unwrap_err() {
+            StdError::InvalidDataSize {
+                expected, actual, ..
+            } => {
+                assert_eq!(expected, 20);
+                assert_eq!(actual, 3);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        let result: StdResult<[u8; 2]> = address.try_into();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn canonical_addr_implements_ord() {
+        let a = CanonicalAddr::from([0x00, 0xff]);
+        let b = CanonicalAddr::from([0x01]);
+        let c = CanonicalAddr::from([0x01, 0x00]);
+        assert!(a < b);
+        assert!(b < c);
+
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(c.clone(), 3);
+        map.insert(a.clone(), 1);
+        map.insert(b.clone(), 2);
+        assert_eq!(map.into_keys().collect::<Vec<_>>(), vec![a, b, c]);
+    }
+
     #[test]
     fn canonical_addr_implements_deref() {
         // Dereference to [u8]
@@ -26,8 +26,29 @@
 /// # Ok(())
 /// # }
 /// ```
+///
+/// When the error argument is omitted, a [`StdError`](crate::StdError) containing
+/// the condition is returned. This is a drop-in replacement for `assert!`, which
+/// panics and only results in an unhelpful "unreachable" error in Wasm.
+///
+/// ```
+/// # use cosmwasm_std::StdError;
+/// # fn body(amount: u128) -> Result<(), StdError> {
+/// use cosmwasm_std::ensure;
+/// ensure!(amount > 0);
+/// # Ok(())
+/// # }
+/// let err = body(0).unwrap_err();
+/// assert_eq!(err.to_string(), "Generic error: Condition failed: amount > 0");
+/// ```
 #[macro_export]
 macro_rules! ensure {
+    ($cond:expr $(,)?) => {
+        $crate::ensure!(
+            $cond,
+            $crate::StdError::generic_err(concat!("Condition failed: ", stringify!($cond)))
+        )
+    };
     ($cond:expr, $e:expr) => {
         if !($cond) {
             return Err(std::convert::From::from($e));
@@ -63,8 +84,28 @@ macro_rules! ensure {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// When the error argument is omitted, a [`StdError`](crate::StdError) containing
+/// both expressions and their `Debug` representations is returned.
 #[macro_export]
 macro_rules! ensure_eq {
+    ($a:expr, $b:expr $(,)?) => {
+        match (&$a, &$b) {
+            (left, right) => {
+                $crate::ensure_eq!(
+                    left,
+                    right,
+                    $crate::StdError::generic_err(format!(
+                        "Equality check failed: {} == {} (left: {:?}, right: {:?})",
+                        stringify!($a),
+                        stringify!($b),
+                        left,
+                        right
+                    ))
+                )
+            }
+        }
+    };
     ($a:expr, $b:expr, $e:expr) => {
         // Not implemented via `ensure!` because the caller would have to import both macros.
         if !($a == $b) {
@@ -95,8 +136,27 @@ macro_rules! ensure_eq {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// When the error argument is omitted, a [`StdError`](crate::StdError) containing
+/// both expressions and the `Debug` representation of their value is returned.
 #[macro_export]
 macro_rules! ensure_ne {
+    ($a:expr, $b:expr $(,)?) => {
+        match (&$a, &$b) {
+            (left, right) => {
+                $crate::ensure_ne!(
+                    left,
+                    right,
+                    $crate::StdError::generic_err(format!(
+                        "Inequality check failed: {} != {} (both: {:?})",
+                        stringify!($a),
+                        stringify!($b),
+                        left
+                    ))
+                )
+            }
+        }
+    };
     ($a:expr, $b:expr, $e:expr) => {
         // Not implemented via `ensure!` because the caller would have to import both macros.
         if !($a != $b) {
@@ -157,6 +217,18 @@ mod tests {
         check(5, 5).unwrap();
     }
 
+    #[test]
+    fn ensure_without_error_works() {
+        fn check(a: usize, b: usize) -> Result<(), StdError> {
+            ensure!(a == b);
+            Ok(())
+        }
+
+        let err = check(5, 6).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Condition failed: a == b"));
+        check(5, 5).unwrap();
+    }
+
     #[test]
     fn ensure_eq_works() {
         let check = |a, b| {
@@ -183,6 +255,23 @@ mod tests {
         let _err = check().unwrap_err();
     }
 
+    #[test]
+    fn ensure_eq_without_error_works() {
+        fn check(a: &str, b: &str) -> Result<(), StdError> {
+            ensure_eq!(a, b.to_lowercase());
+            Ok(())
+        }
+
+        let err = check("123", "456").unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(
+                r#"Equality check failed: a == b.to_lowercase() (left: "123", right: "456")"#
+            )
+        );
+        check("abc", "ABC").unwrap();
+    }
+
     #[test]
     fn ensure_ne_works() {
         let check = |a, b| {
@@ -208,4 +297,19 @@ mod tests {
 
         check().unwrap();
     }
+
+    #[test]
+    fn ensure_ne_without_error_works() {
+        fn check(a: u32, b: u32) -> Result<(), StdError> {
+            ensure_ne!(a, b + 1);
+            Ok(())
+        }
+
+        let err = check(3, 2).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Inequality check failed: a != b + 1 (both: 3)")
+        );
+        check(3, 3).unwrap();
+    }
 }
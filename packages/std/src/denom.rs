@@ -0,0 +1,291 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::errors::DenomError;
+
+/// The prefix of denoms of tokens received via IBC (ICS-20)
+const IBC_PREFIX: &str = "ibc/";
+
+/// The length of the hex encoded SHA-256 hash of the trace in an IBC denom
+const IBC_HASH_LENGTH: usize = 64;
+
+/// Checks that the denom has the format accepted by the Cosmos SDK by default,
+/// i.e. it matches `[a-zA-Z][a-zA-Z0-9/:._-]{2,127}`. IBC denoms must have the
+/// form `ibc/{hash}` with a hex encoded SHA-256 hash of the denom trace.
+///
+/// Chains can configure a different format, so this is a sanity check for the common case
+/// rather than a guarantee that a denom exists.
+///
+/// ## Examples
+///
+/// ```
+/// # use cosmwasm_std::{validate_denom, DenomError};
+/// validate_denom("uatom").unwrap();
+/// validate_denom("factory/cosmos1creator/token").unwrap();
+/// validate_denom("ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2").unwrap();
+///
+/// assert_eq!(validate_denom("ab"), Err(DenomError::InvalidLength { length: 2 }));
+/// assert_eq!(validate_denom("ibc/123"), Err(DenomError::InvalidIbcHash));
+/// ```
+pub fn validate_denom(denom: &str) -> Result<(), DenomError> {
+    // All valid characters are ASCII, so the number of characters is only relevant for valid denoms
+    let length = denom.chars().count();
+    if !(3..=128).contains(&length) {
+        return Err(DenomError::InvalidLength { length });
+    }
+
+    let mut chars = denom.chars();
+    let first = chars.next().unwrap(); // ok since the length was checked above
+    if !first.is_ascii_alphabetic() {
+        return Err(DenomError::InvalidFirstCharacter { character: first });
+    }
+    for (position, character) in chars.enumerate() {
+        if !(character.is_ascii_alphanumeric() || "/:._-".contains(character)) {
+            return Err(DenomError::InvalidCharacter {
+                character,
+                position: position + 1,
+            });
+        }
+    }
+
+    if let Some(hash) = denom.strip_prefix(IBC_PREFIX) {
+        if hash.len() != IBC_HASH_LENGTH || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(DenomError::InvalidIbcHash);
+        }
+    }
+    Ok(())
+}
+
+/// A denom that has the format accepted by the Cosmos SDK by default (see [`validate_denom`]).
+///
+/// Deserializing a `Denom` validates it, so it can be used in messages sent by users.
+///
+/// ## Examples
+///
+/// ```
+/// # use cosmwasm_std::{from_slice, Denom, DenomError};
+/// let denom = Denom::new("uatom").unwrap();
+/// assert_eq!(denom, "uatom");
+///
+/// let err = Denom::new("1atom").unwrap_err();
+/// assert_eq!(err, DenomError::InvalidFirstCharacter { character: '1' });
+///
+/// let denom: Denom = from_slice(br#""ujuno""#).unwrap();
+/// assert!(from_slice::<Denom>(br#""u""#).is_err());
+/// ```
+#[derive(
+    Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema,
+)]
+#[serde(try_from = "String")]
+pub struct Denom(String);
+
+impl Denom {
+    /// Creates a new `Denom` after validating the input
+    pub fn new(input: impl Into<String>) -> Result<Self, DenomError> {
+        let input = input.into();
+        validate_denom(&input)?;
+        Ok(Denom(input))
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Utility for explicit conversion to `String`.
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Returns the hash of an IBC denom (`ibc/{hash}`) and `None` for all other denoms
+    pub fn ibc_hash(&self) -> Option<&str> {
+        self.0.strip_prefix(IBC_PREFIX)
+    }
+}
+
+impl fmt::Display for Denom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl AsRef<str> for Denom {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl TryFrom<String> for Denom {
+    type Error = DenomError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Denom::new(input)
+    }
+}
+
+impl TryFrom<&str> for Denom {
+    type Error = DenomError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        Denom::new(input)
+    }
+}
+
+impl From<Denom> for String {
+    fn from(denom: Denom) -> Self {
+        denom.0
+    }
+}
+
+/// Implement `Denom == &str`
+impl PartialEq<&str> for Denom {
+    fn eq(&self, rhs: &&str) -> bool {
+        self.0 == *rhs
+    }
+}
+
+/// Implement `&str == Denom`
+impl PartialEq<Denom> for &str {
+    fn eq(&self, rhs: &Denom) -> bool {
+        *self == rhs.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::{from_slice, to_vec};
+
+    const IBC_DENOM: &str = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+    #[test]
+    fn validate_denom_works() {
+        for denom in [
+            "abc",
+            "uatom",
+            "stake",
+            "factory/osmo1creator/sub.denom_1",
+            "gamm/pool/1",
+            "cw20:juno1contract",
+            "Xy-z",
+            IBC_DENOM,
+            &IBC_DENOM.to_lowercase(),
+            &"a".repeat(128),
+        ] {
+            validate_denom(denom).unwrap_or_else(|e| panic!("{denom}: {e}"));
+        }
+    }
+
+    #[test]
+    fn validate_denom_checks_length() {
+        assert_eq!(
+            validate_denom(""),
+            Err(DenomError::InvalidLength { length: 0 })
+        );
+        assert_eq!(
+            validate_denom("ab"),
+            Err(DenomError::InvalidLength { length: 2 })
+        );
+        assert_eq!(
+            validate_denom(&"a".repeat(129)),
+            Err(DenomError::InvalidLength { length: 129 })
+        );
+        // characters, not bytes
+        assert_eq!(
+            validate_denom("ää"),
+            Err(DenomError::InvalidLength { length: 2 })
+        );
+    }
+
+    #[test]
+    fn validate_denom_checks_characters() {
+        assert_eq!(
+            validate_denom("1abc"),
+            Err(DenomError::InvalidFirstCharacter { character: '1' })
+        );
+        assert_eq!(
+            validate_denom("/abc"),
+            Err(DenomError::InvalidFirstCharacter { character: '/' })
+        );
+        assert_eq!(
+            validate_denom("ab c"),
+            Err(DenomError::InvalidCharacter {
+                character: ' ',
+                position: 2
+            })
+        );
+        assert_eq!(
+            validate_denom("abcä"),
+            Err(DenomError::InvalidCharacter {
+                character: 'ä',
+                position: 3
+            })
+        );
+    }
+
+    #[test]
+    fn validate_denom_checks_ibc_hash() {
+        for denom in [
+            "ibc/",
+            "ibc/123",
+            &IBC_DENOM[..IBC_DENOM.len() - 1],
+            &format!("{IBC_DENOM}0"),
+            &IBC_DENOM.replace('F', "G"),
+            "ibc/transfer/channel-0/uatom",
+        ] {
+            assert_eq!(
+                validate_denom(denom),
+                Err(DenomError::InvalidIbcHash),
+                "{denom}"
+            );
+        }
+        // prefix is case-sensitive like in ibc-go
+        validate_denom("IBC/123").unwrap();
+    }
+
+    #[test]
+    fn denom_new_works() {
+        let denom = Denom::new("uatom").unwrap();
+        assert_eq!(denom.as_str(), "uatom");
+        assert_eq!(denom.to_string(), "uatom");
+        assert_eq!(denom, "uatom");
+        assert_eq!("uatom", denom);
+        assert_eq!(denom.ibc_hash(), None);
+        assert_eq!(String::from(denom), "uatom");
+
+        let denom = Denom::try_from(IBC_DENOM).unwrap();
+        assert_eq!(denom.ibc_hash(), Some(&IBC_DENOM[4..]));
+
+        assert_eq!(
+            Denom::new("u").unwrap_err(),
+            DenomError::InvalidLength { length: 1 }
+        );
+    }
+
+    #[test]
+    fn denom_serialization_validates() {
+        let denom = Denom::new("uatom").unwrap();
+        let serialized = to_vec(&denom).unwrap();
+        assert_eq!(serialized, br#""uatom""#);
+        assert_eq!(from_slice::<Denom>(&serialized).unwrap(), denom);
+
+        let err = from_slice::<Denom>(br#""ibc/123""#).unwrap_err();
+        assert!(
+            err.to_string().contains("IBC denom must be 'ibc/'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn denom_error_converts_to_std_error() {
+        let err: crate::StdError = Denom::new("u").unwrap_err().into();
+        assert_eq!(
+            err.to_string(),
+            "Invalid denom: Denom must be between 3 and 128 characters long but has 1"
+        );
+    }
+}
@@ -5,8 +5,8 @@ mod verification_error;
 
 pub use recover_pubkey_error::RecoverPubkeyError;
 pub use std_error::{
-    CheckedFromRatioError, CheckedMultiplyRatioError, ConversionOverflowError, DivideByZeroError,
-    OverflowError, OverflowOperation, RoundUpOverflowError, StdError, StdResult,
+    CheckedFromRatioError, CheckedMultiplyRatioError, ConversionOverflowError, DenomError,
+    DivideByZeroError, OverflowError, OverflowOperation, RoundUpOverflowError, StdError, StdResult,
 };
 pub use system_error::SystemError;
 pub use verification_error::VerificationError;
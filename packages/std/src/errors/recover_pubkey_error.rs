@@ -70,6 +70,12 @@ impl From<CryptoError> for RecoverPubkeyError {
             CryptoError::GenericErr { .. } => RecoverPubkeyError::unknown_err(original.code()),
             CryptoError::InvalidRecoveryParam { .. } => RecoverPubkeyError::InvalidRecoveryParam,
             CryptoError::BatchErr { .. } => panic!("Conversion not supported"),
+            CryptoError::Groth16Err { .. } => panic!("Conversion not supported"),
+            CryptoError::Ics23Err { .. } => panic!("Conversion not supported"),
+            CryptoError::TendermintErr { .. } => panic!("Conversion not supported"),
+            CryptoError::Bls12381Err { .. } => panic!("Conversion not supported"),
+            CryptoError::HashInputErr { .. } => panic!("Conversion not supported"),
+            CryptoError::InvalidHashFunction { .. } => panic!("Conversion not supported"),
         }
     }
 }
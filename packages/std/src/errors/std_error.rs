@@ -108,6 +108,12 @@ pub enum StdError {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
+    #[error("Invalid denom: {source}")]
+    InvalidDenom {
+        source: DenomError,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
 }
 
 impl StdError {
@@ -210,6 +216,14 @@ impl StdError {
             backtrace: Backtrace::capture(),
         }
     }
+
+    pub fn invalid_denom(source: DenomError) -> Self {
+        StdError::InvalidDenom {
+            source,
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
 }
 
 impl PartialEq<StdError> for StdError {
@@ -429,6 +443,22 @@ impl PartialEq<StdError> for StdError {
                     false
                 }
             }
+            StdError::InvalidDenom {
+                source,
+                #[cfg(feature = "backtraces")]
+                    backtrace: _,
+            } => {
+                if let StdError::InvalidDenom {
+                    source: rhs_source,
+                    #[cfg(feature = "backtraces")]
+                        backtrace: _,
+                } = rhs
+                {
+                    source == rhs_source
+                } else {
+                    false
+                }
+            }
         }
     }
 }
@@ -469,6 +499,12 @@ impl From<DivideByZeroError> for StdError {
     }
 }
 
+impl From<DenomError> for StdError {
+    fn from(source: DenomError) -> Self {
+        Self::invalid_denom(source)
+    }
+}
+
 /// The return type for init, execute and query. Since the error type cannot be serialized to JSON,
 /// this is only available within the contract and its unit tests.
 ///
@@ -578,6 +614,23 @@ pub enum CheckedFromRatioError {
 #[error("Round up operation failed because of overflow")]
 pub struct RoundUpOverflowError;
 
+/// A denom that does not follow the format accepted by the Cosmos SDK,
+/// see [`validate_denom`](crate::validate_denom)
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DenomError {
+    #[error("Denom must be between 3 and 128 characters long but has {length}")]
+    InvalidLength { length: usize },
+
+    #[error("Denom must start with a letter but starts with '{character}'")]
+    InvalidFirstCharacter { character: char },
+
+    #[error("Denom must not contain '{character}' (at position {position})")]
+    InvalidCharacter { character: char, position: usize },
+
+    #[error("IBC denom must be 'ibc/' followed by a 64 character hex encoded hash")]
+    InvalidIbcHash,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -28,6 +28,12 @@ pub enum SystemError {
         /// The address that was attempted to query
         addr: String,
     },
+    /// A contract queried itself via `WasmQuery::Smart`, which is rejected to protect
+    /// against reentrancy through queries
+    Reentrancy {
+        /// The address of the executing contract
+        contract_addr: String,
+    },
     Unknown {},
     UnsupportedRequest {
         kind: String,
@@ -52,6 +58,9 @@ impl std::fmt::Display for SystemError {
                 String::from_utf8_lossy(response)
             ),
             SystemError::NoSuchContract { addr } => write!(f, "No such contract: {}", addr),
+            SystemError::Reentrancy { contract_addr } => {
+                write!(f, "Contract must not query itself: {}", contract_addr)
+            }
             SystemError::Unknown {} => write!(f, "Unknown system error"),
             SystemError::UnsupportedRequest { kind } => {
                 write!(f, "Unsupported query type: {}", kind)
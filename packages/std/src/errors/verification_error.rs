@@ -12,14 +12,26 @@ pub enum VerificationError {
     BatchErr,
     #[error("Generic error")]
     GenericErr,
+    #[error("Groth16 error")]
+    Groth16Err,
+    #[error("ICS-23 proof error")]
+    Ics23Err,
     #[error("Invalid hash format")]
     InvalidHashFormat,
+    #[error("Unknown hash function identifier")]
+    InvalidHashFunction,
+    #[error("Invalid hash input")]
+    InvalidHashInput,
     #[error("Invalid signature format")]
     InvalidSignatureFormat,
     #[error("Invalid public key format")]
     InvalidPubkeyFormat,
     #[error("Invalid recovery parameter. Supported values: 0 and 1.")]
     InvalidRecoveryParam,
+    #[error("Tendermint header error")]
+    TendermintErr,
+    #[error("BLS12-381 error")]
+    Bls12381Err,
     #[error("Unknown error: {error_code}")]
     UnknownErr {
         error_code: u32,
@@ -43,9 +55,17 @@ impl PartialEq<VerificationError> for VerificationError {
         match self {
             VerificationError::BatchErr => matches!(rhs, VerificationError::BatchErr),
             VerificationError::GenericErr => matches!(rhs, VerificationError::GenericErr),
+            VerificationError::Groth16Err => matches!(rhs, VerificationError::Groth16Err),
+            VerificationError::Ics23Err => matches!(rhs, VerificationError::Ics23Err),
             VerificationError::InvalidHashFormat => {
                 matches!(rhs, VerificationError::InvalidHashFormat)
             }
+            VerificationError::InvalidHashFunction => {
+                matches!(rhs, VerificationError::InvalidHashFunction)
+            }
+            VerificationError::InvalidHashInput => {
+                matches!(rhs, VerificationError::InvalidHashInput)
+            }
             VerificationError::InvalidPubkeyFormat => {
                 matches!(rhs, VerificationError::InvalidPubkeyFormat)
             }
@@ -55,6 +75,8 @@ impl PartialEq<VerificationError> for VerificationError {
             VerificationError::InvalidRecoveryParam => {
                 matches!(rhs, VerificationError::InvalidRecoveryParam)
             }
+            VerificationError::TendermintErr => matches!(rhs, VerificationError::TendermintErr),
+            VerificationError::Bls12381Err => matches!(rhs, VerificationError::Bls12381Err),
             VerificationError::UnknownErr { error_code, .. } => {
                 if let VerificationError::UnknownErr {
                     error_code: rhs_error_code,
@@ -75,11 +97,17 @@ impl From<CryptoError> for VerificationError {
     fn from(original: CryptoError) -> Self {
         match original {
             CryptoError::InvalidHashFormat { .. } => VerificationError::InvalidHashFormat,
+            CryptoError::InvalidHashFunction { .. } => VerificationError::InvalidHashFunction,
             CryptoError::InvalidPubkeyFormat { .. } => VerificationError::InvalidPubkeyFormat,
             CryptoError::InvalidSignatureFormat { .. } => VerificationError::InvalidSignatureFormat,
             CryptoError::GenericErr { .. } => VerificationError::GenericErr,
             CryptoError::InvalidRecoveryParam { .. } => VerificationError::InvalidRecoveryParam,
             CryptoError::BatchErr { .. } => VerificationError::BatchErr,
+            CryptoError::Groth16Err { .. } => VerificationError::Groth16Err,
+            CryptoError::HashInputErr { .. } => VerificationError::InvalidHashInput,
+            CryptoError::Ics23Err { .. } => VerificationError::Ics23Err,
+            CryptoError::TendermintErr { .. } => VerificationError::TendermintErr,
+            CryptoError::Bls12381Err { .. } => VerificationError::Bls12381Err,
         }
     }
 }
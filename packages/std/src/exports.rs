@@ -11,6 +11,7 @@ use std::marker::PhantomData;
 use std::vec::Vec;
 
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use crate::deps::OwnedDeps;
 #[cfg(feature = "stargate")]
@@ -19,6 +20,8 @@ use crate::ibc::{
     IbcChannelOpenResponse, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
     IbcReceiveResponse,
 };
+#[cfg(feature = "stargate")]
+use crate::ibc_callbacks::{IbcDestinationCallbackMsg, IbcSourceCallbackMsg};
 use crate::imports::{ExternalApi, ExternalQuerier, ExternalStorage};
 use crate::memory::{alloc, consume_region, release_buffer, Region};
 #[cfg(feature = "abort")]
@@ -49,6 +52,22 @@ extern "C" fn requires_cosmwasm_1_1() -> () {}
 #[no_mangle]
 extern "C" fn requires_cosmwasm_1_2() -> () {}
 
+#[cfg(feature = "secp256r1")]
+#[no_mangle]
+extern "C" fn requires_secp256r1() -> () {}
+
+#[cfg(feature = "zk")]
+#[no_mangle]
+extern "C" fn requires_zk() -> () {}
+
+#[cfg(feature = "tendermint")]
+#[no_mangle]
+extern "C" fn requires_tendermint() -> () {}
+
+#[cfg(feature = "bls12_381")]
+#[no_mangle]
+extern "C" fn requires_bls12_381() -> () {}
+
 /// interface_version_* exports mark which Wasm VM interface level this contract is compiled for.
 /// They can be checked by cosmwasm_vm.
 /// Update this whenever the Wasm VM interface breaks.
@@ -71,6 +90,21 @@ extern "C" fn deallocate(pointer: u32) {
     let _ = unsafe { consume_region(pointer as *mut Region) };
 }
 
+/// Serializes the result of an entry point for the host
+#[cfg(not(feature = "size_optimized"))]
+fn serialize_result<T: Serialize>(result: &T) -> Vec<u8> {
+    to_vec(result).unwrap()
+}
+
+/// Serializes the result of an entry point for the host.
+///
+/// Unlike `unwrap`, this does not format a serialization error, which keeps the
+/// formatting code of the error type out of the contract.
+#[cfg(feature = "size_optimized")]
+fn serialize_result<T: Serialize>(result: &T) -> Vec<u8> {
+    to_vec(result).unwrap_or_else(|_| panic!("Error serializing the result"))
+}
+
 // TODO: replace with https://doc.rust-lang.org/std/ops/trait.Try.html once stabilized
 macro_rules! r#try_into_contract_result {
     ($expr:expr) => {
@@ -112,7 +146,7 @@ where
         info_ptr as *mut Region,
         msg_ptr as *mut Region,
     );
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -142,7 +176,7 @@ where
         info_ptr as *mut Region,
         msg_ptr as *mut Region,
     );
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -166,7 +200,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_migrate(migrate_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -190,7 +224,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_sudo(sudo_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -213,7 +247,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_reply(reply_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -235,7 +269,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_query(query_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -258,7 +292,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_channel_open(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -283,7 +317,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_channel_connect(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -308,7 +342,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_channel_close(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -334,7 +368,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_packet_receive(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -360,7 +394,7 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_packet_ack(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -387,7 +421,65 @@ where
     #[cfg(feature = "abort")]
     install_panic_handler();
     let res = _do_ibc_packet_timeout(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
-    let v = to_vec(&res).unwrap();
+    let v = serialize_result(&res);
+    release_buffer(v) as u32
+}
+
+/// do_ibc_source_callback is designed for use with #[entry_point] to make a "C" extern
+///
+/// contract_fn is called by the callbacks middleware when a packet sent by the contract
+/// via another IBC application (e.g. an ICS-20 transfer with a callback requested in the memo)
+/// was acknowledged or timed out.
+///
+/// - `Q`: custom query type (see QueryRequest)
+/// - `C`: custom response message type (see CosmosMsg)
+/// - `E`: error type for responses
+#[cfg(feature = "stargate")]
+pub fn do_ibc_source_callback<Q, C, E>(
+    contract_fn: &dyn Fn(DepsMut<Q>, Env, IbcSourceCallbackMsg) -> Result<IbcBasicResponse<C>, E>,
+    env_ptr: u32,
+    msg_ptr: u32,
+) -> u32
+where
+    Q: CustomQuery,
+    C: CustomMsg,
+    E: ToString,
+{
+    #[cfg(feature = "abort")]
+    install_panic_handler();
+    let res = _do_ibc_source_callback(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
+    let v = serialize_result(&res);
+    release_buffer(v) as u32
+}
+
+/// do_ibc_destination_callback is designed for use with #[entry_point] to make a "C" extern
+///
+/// contract_fn is called by the callbacks middleware on the destination chain after a packet
+/// requesting a callback for this contract in its memo was received and acknowledged.
+///
+/// - `Q`: custom query type (see QueryRequest)
+/// - `C`: custom response message type (see CosmosMsg)
+/// - `E`: error type for responses
+#[cfg(feature = "stargate")]
+pub fn do_ibc_destination_callback<Q, C, E>(
+    contract_fn: &dyn Fn(
+        DepsMut<Q>,
+        Env,
+        IbcDestinationCallbackMsg,
+    ) -> Result<IbcBasicResponse<C>, E>,
+    env_ptr: u32,
+    msg_ptr: u32,
+) -> u32
+where
+    Q: CustomQuery,
+    C: CustomMsg,
+    E: ToString,
+{
+    #[cfg(feature = "abort")]
+    install_panic_handler();
+    let res =
+        _do_ibc_destination_callback(contract_fn, env_ptr as *mut Region, msg_ptr as *mut Region);
+    let v = serialize_result(&res);
     release_buffer(v) as u32
 }
 
@@ -646,6 +738,52 @@ where
     contract_fn(deps.as_mut(), env, msg).into()
 }
 
+#[cfg(feature = "stargate")]
+fn _do_ibc_source_callback<Q, C, E>(
+    contract_fn: &dyn Fn(DepsMut<Q>, Env, IbcSourceCallbackMsg) -> Result<IbcBasicResponse<C>, E>,
+    env_ptr: *mut Region,
+    msg_ptr: *mut Region,
+) -> ContractResult<IbcBasicResponse<C>>
+where
+    Q: CustomQuery,
+    C: CustomMsg,
+    E: ToString,
+{
+    let env: Vec<u8> = unsafe { consume_region(env_ptr) };
+    let msg: Vec<u8> = unsafe { consume_region(msg_ptr) };
+
+    let env: Env = try_into_contract_result!(from_slice(&env));
+    let msg: IbcSourceCallbackMsg = try_into_contract_result!(from_slice(&msg));
+
+    let mut deps = make_dependencies();
+    contract_fn(deps.as_mut(), env, msg).into()
+}
+
+#[cfg(feature = "stargate")]
+fn _do_ibc_destination_callback<Q, C, E>(
+    contract_fn: &dyn Fn(
+        DepsMut<Q>,
+        Env,
+        IbcDestinationCallbackMsg,
+    ) -> Result<IbcBasicResponse<C>, E>,
+    env_ptr: *mut Region,
+    msg_ptr: *mut Region,
+) -> ContractResult<IbcBasicResponse<C>>
+where
+    Q: CustomQuery,
+    C: CustomMsg,
+    E: ToString,
+{
+    let env: Vec<u8> = unsafe { consume_region(env_ptr) };
+    let msg: Vec<u8> = unsafe { consume_region(msg_ptr) };
+
+    let env: Env = try_into_contract_result!(from_slice(&env));
+    let msg: IbcDestinationCallbackMsg = try_into_contract_result!(from_slice(&msg));
+
+    let mut deps = make_dependencies();
+    contract_fn(deps.as_mut(), env, msg).into()
+}
+
 /// Makes all bridges to external dependencies (i.e. Wasm imports) that are injected by the VM
 pub(crate) fn make_dependencies<Q>() -> OwnedDeps<ExternalStorage, ExternalApi, ExternalQuerier, Q>
 where
@@ -3,6 +3,7 @@
 // The rest of the IBC related functionality is defined here
 
 use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::cmp::{Ord, Ordering, PartialOrd};
 
@@ -12,7 +13,7 @@ use crate::binary::Binary;
 use crate::coin::Coin;
 use crate::errors::StdResult;
 use crate::results::{Attribute, CosmosMsg, Empty, Event, SubMsg};
-use crate::serde::to_binary;
+use crate::serde::{from_binary, to_binary};
 use crate::timestamp::Timestamp;
 
 /// These are messages in the IBC lifecycle. Only usable by IBC-enabled contracts
@@ -768,6 +769,34 @@ impl<T> IbcReceiveResponse<T> {
         self.events.extend(events.into_iter());
         self
     }
+
+    /// Deserializes the acknowledgement bytes from JSON.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use cosmwasm_std::{to_binary, IbcReceiveResponse};
+    ///
+    /// let res: IbcReceiveResponse = IbcReceiveResponse::new().set_ack(to_binary(&7u32).unwrap());
+    /// assert_eq!(res.acknowledgement_as::<u32>().unwrap(), 7);
+    /// ```
+    pub fn acknowledgement_as<A: DeserializeOwned>(&self) -> StdResult<A> {
+        from_binary(&self.acknowledgement)
+    }
+
+    /// Returns the value of the first attribute of the main `wasm` event with the given key.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.as_str())
+    }
+
+    /// Returns all custom events of the given type, without the `wasm-` prefix
+    /// added by the runtime.
+    pub fn events_of_type<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = &'a Event> + 'a {
+        self.events.iter().filter(move |event| event.ty == ty)
+    }
 }
 
 #[cfg(test)]
@@ -895,4 +924,34 @@ mod tests {
         let expected = r#"{"data":"Zm9v","src":{"port_id":"their-port","channel_id":"channel-1234"},"dest":{"port_id":"our-port","channel_id":"chan33"},"sequence":27,"timeout":{"block":{"revision":1,"height":12345678},"timestamp":null}}"#;
         assert_eq!(to_string(&no_timestamp).unwrap(), expected);
     }
+
+    #[test]
+    fn ibc_receive_response_accessors_work() {
+        let res: IbcReceiveResponse = IbcReceiveResponse::new()
+            .set_ack(br#"{"result":"AQ=="}"#)
+            .add_attribute("action", "receive")
+            .add_attribute("success", "true")
+            .add_event(Event::new("ibc").add_attribute("packet", "1"))
+            .add_event(Event::new("transfer"))
+            .add_event(Event::new("ibc").add_attribute("packet", "2"));
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "snake_case")]
+        enum Ack {
+            Result(Binary),
+            Error(String),
+        }
+        assert_eq!(
+            res.acknowledgement_as::<Ack>().unwrap(),
+            Ack::Result(Binary::from([1]))
+        );
+        assert!(res.acknowledgement_as::<u64>().is_err());
+
+        assert_eq!(res.attribute("success"), Some("true"));
+        assert_eq!(res.attribute("missing"), None);
+
+        let ibc_events: Vec<_> = res.events_of_type("ibc").collect();
+        assert_eq!(ibc_events, [&res.events[0], &res.events[2]]);
+        assert_eq!(res.events_of_type("wasm-ibc").count(), 0);
+    }
 }
@@ -0,0 +1,285 @@
+// Types for the callbacks middleware of ibc-go (ADR-8), which informs contracts about the
+// lifecycle of packets of other IBC applications, e.g. ICS-20 transfers sent by the contract.
+// See https://github.com/cosmos/ibc-go/blob/main/docs/architecture/adr-008-app-caller-cbs.md
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::addresses::Addr;
+use crate::errors::StdResult;
+use crate::ibc::{IbcAcknowledgement, IbcPacket};
+use crate::ibc_packet_data::Ics20PacketData;
+use crate::math::Uint64;
+use crate::serde::to_vec;
+
+/// The callbacks requested in the memo of a packet, e.g. the `memo` of an ICS-20 transfer.
+///
+/// The JSON form of this type is what the callbacks middleware expects as the memo.
+/// Use [`IbcCallbackRequest::to_memo`] to create it.
+///
+/// ## Examples
+///
+/// ```
+/// # use cosmwasm_std::{Addr, IbcCallbackRequest, IbcSrcCallback};
+/// let request = IbcCallbackRequest::source(IbcSrcCallback {
+///     address: Addr::unchecked("contract"),
+///     gas_limit: None,
+/// });
+/// assert_eq!(request.to_memo().unwrap(), r#"{"src_callback":{"address":"contract"}}"#);
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IbcCallbackRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src_callback: Option<IbcSrcCallback>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dest_callback: Option<IbcDstCallback>,
+}
+
+impl IbcCallbackRequest {
+    /// Requests a callback on the source chain only
+    pub fn source(src_callback: IbcSrcCallback) -> Self {
+        IbcCallbackRequest {
+            src_callback: Some(src_callback),
+            dest_callback: None,
+        }
+    }
+
+    /// Requests a callback on the destination chain only
+    pub fn destination(dest_callback: IbcDstCallback) -> Self {
+        IbcCallbackRequest {
+            src_callback: None,
+            dest_callback: Some(dest_callback),
+        }
+    }
+
+    /// Requests callbacks on both the source and the destination chain
+    pub fn both(src_callback: IbcSrcCallback, dest_callback: IbcDstCallback) -> Self {
+        IbcCallbackRequest {
+            src_callback: Some(src_callback),
+            dest_callback: Some(dest_callback),
+        }
+    }
+
+    /// Serializes the request to the JSON memo understood by the callbacks middleware
+    pub fn to_memo(&self) -> StdResult<String> {
+        Ok(String::from_utf8(to_vec(self)?)?)
+    }
+}
+
+/// A callback on the source chain, i.e. the chain the packet was sent from.
+///
+/// The callbacks middleware only calls the contract if it is also the sender of the packet.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IbcSrcCallback {
+    /// The contract that receives the [`IbcSourceCallbackMsg`] via `ibc_source_callback`
+    pub address: Addr,
+    /// The gas limit of the callback. The chain applies its own maximum if this is unset or higher.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<Uint64>,
+}
+
+/// A callback on the destination chain, i.e. the chain receiving the packet.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IbcDstCallback {
+    /// The address of the contract on the destination chain that receives the
+    /// [`IbcDestinationCallbackMsg`] via `ibc_destination_callback`
+    pub address: String,
+    /// The gas limit of the callback. The chain applies its own maximum if this is unset or higher.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<Uint64>,
+}
+
+/// The message that is passed into `ibc_source_callback` when the packet sent by the
+/// contract was acknowledged or timed out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum IbcSourceCallbackMsg {
+    Acknowledgement(IbcAckCallbackMsg),
+    Timeout(IbcTimeoutCallbackMsg),
+}
+
+impl IbcSourceCallbackMsg {
+    /// The packet sent by the contract
+    pub fn packet(&self) -> &IbcPacket {
+        match self {
+            IbcSourceCallbackMsg::Acknowledgement(msg) => &msg.original_packet,
+            IbcSourceCallbackMsg::Timeout(msg) => &msg.packet,
+        }
+    }
+
+    /// Deserializes and validates the data of the packet as an ICS-20 transfer
+    pub fn ics20_packet_data(&self) -> StdResult<Ics20PacketData> {
+        Ics20PacketData::from_packet_data(&self.packet().data)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
+pub struct IbcAckCallbackMsg {
+    pub acknowledgement: IbcAcknowledgement,
+    pub original_packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+impl IbcAckCallbackMsg {
+    pub fn new(
+        acknowledgement: IbcAcknowledgement,
+        original_packet: IbcPacket,
+        relayer: Addr,
+    ) -> Self {
+        Self {
+            acknowledgement,
+            original_packet,
+            relayer,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
+pub struct IbcTimeoutCallbackMsg {
+    pub packet: IbcPacket,
+    pub relayer: Addr,
+}
+
+impl IbcTimeoutCallbackMsg {
+    pub fn new(packet: IbcPacket, relayer: Addr) -> Self {
+        Self { packet, relayer }
+    }
+}
+
+/// The message that is passed into `ibc_destination_callback` after the packet was
+/// received on the destination chain and acknowledged by the receiving application.
+///
+/// The packet was not necessarily processed successfully, so the acknowledgement must be
+/// checked before relying on e.g. the tokens of an ICS-20 transfer to be available.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[non_exhaustive]
+pub struct IbcDestinationCallbackMsg {
+    pub packet: IbcPacket,
+    pub ack: IbcAcknowledgement,
+}
+
+impl IbcDestinationCallbackMsg {
+    pub fn new(packet: IbcPacket, ack: IbcAcknowledgement) -> Self {
+        Self { packet, ack }
+    }
+
+    /// Deserializes and validates the data of the packet as an ICS-20 transfer
+    pub fn ics20_packet_data(&self) -> StdResult<Ics20PacketData> {
+        Ics20PacketData::from_packet_data(&self.packet.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibc::{IbcEndpoint, IbcTimeout};
+    use crate::math::Uint256;
+    use crate::serde::from_slice;
+    use crate::timestamp::Timestamp;
+    use serde_json_wasm::to_string;
+
+    fn transfer_packet() -> IbcPacket {
+        let data = Ics20PacketData {
+            denom: "ujuno".to_string(),
+            amount: Uint256::from(100u128),
+            sender: "contract".to_string(),
+            receiver: "cosmos1receiver".to_string(),
+            memo: Some(r#"{"src_callback":{"address":"contract"}}"#.to_string()),
+        };
+        IbcPacket::new(
+            data.to_packet_data().unwrap(),
+            IbcEndpoint {
+                port_id: "transfer".to_string(),
+                channel_id: "channel-0".to_string(),
+            },
+            IbcEndpoint {
+                port_id: "transfer".to_string(),
+                channel_id: "channel-7".to_string(),
+            },
+            1,
+            IbcTimeout::with_timestamp(Timestamp::from_seconds(1700000000)),
+        )
+    }
+
+    #[test]
+    fn ibc_callback_request_to_memo_works() {
+        let src = IbcSrcCallback {
+            address: Addr::unchecked("contract"),
+            gas_limit: Some(Uint64::new(500_000)),
+        };
+        let dest = IbcDstCallback {
+            address: "juno1contract".to_string(),
+            gas_limit: None,
+        };
+        assert_eq!(
+            IbcCallbackRequest::source(src.clone()).to_memo().unwrap(),
+            r#"{"src_callback":{"address":"contract","gas_limit":"500000"}}"#
+        );
+        assert_eq!(
+            IbcCallbackRequest::destination(dest.clone())
+                .to_memo()
+                .unwrap(),
+            r#"{"dest_callback":{"address":"juno1contract"}}"#
+        );
+        assert_eq!(
+            IbcCallbackRequest::both(src, dest).to_memo().unwrap(),
+            r#"{"src_callback":{"address":"contract","gas_limit":"500000"},"dest_callback":{"address":"juno1contract"}}"#
+        );
+    }
+
+    #[test]
+    fn ibc_source_callback_msg_serializes_to_correct_json() {
+        let msg = IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg::new(
+            transfer_packet(),
+            Addr::unchecked("relayer"),
+        ));
+        let json = to_string(&msg).unwrap();
+        assert!(
+            json.starts_with(r#"{"timeout":{"packet":{"data":""#),
+            "{}",
+            json
+        );
+        assert!(json.ends_with(r#""relayer":"relayer"}}"#), "{}", json);
+
+        let msg = IbcSourceCallbackMsg::Acknowledgement(IbcAckCallbackMsg::new(
+            IbcAcknowledgement::new(br#"{"result":"AQ=="}"#),
+            transfer_packet(),
+            Addr::unchecked("relayer"),
+        ));
+        let json = to_string(&msg).unwrap();
+        assert!(
+            json.starts_with(r#"{"acknowledgement":{"acknowledgement":{"data":"#),
+            "{}",
+            json
+        );
+        assert_eq!(
+            from_slice::<IbcSourceCallbackMsg>(json.as_bytes()).unwrap(),
+            msg
+        );
+    }
+
+    #[test]
+    fn ics20_packet_data_works() {
+        let msg = IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg::new(
+            transfer_packet(),
+            Addr::unchecked("relayer"),
+        ));
+        let data = msg.ics20_packet_data().unwrap();
+        assert_eq!(data.denom, "ujuno");
+        assert_eq!(data.amount, Uint256::from(100u128));
+
+        let msg = IbcDestinationCallbackMsg::new(
+            transfer_packet(),
+            IbcAcknowledgement::new(br#"{"result":"AQ=="}"#),
+        );
+        assert_eq!(msg.ics20_packet_data().unwrap(), data);
+
+        let mut packet = transfer_packet();
+        packet.data = br#"{"foo":"bar"}"#.into();
+        let msg = IbcDestinationCallbackMsg::new(packet, IbcAcknowledgement::new(b""));
+        msg.ics20_packet_data().unwrap_err();
+    }
+}
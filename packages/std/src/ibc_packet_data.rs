@@ -0,0 +1,276 @@
+// Packet data types of the standard IBC applications, for contracts that speak
+// these protocols over their own channels.
+// See https://github.com/cosmos/ibc/tree/main/spec/app
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::binary::Binary;
+use crate::errors::{StdError, StdResult};
+use crate::math::Uint256;
+use crate::serde::{from_binary, to_binary};
+
+/// The packet data of an ICS-20 fungible token transfer.
+///
+/// The JSON form uses snake_case field names, like ibc-go's `FungibleTokenPacketData`.
+/// See https://github.com/cosmos/ibc/tree/main/spec/app/ics-020-fungible-token-transfer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Ics20PacketData {
+    /// The denomination of the tokens, including the trace for tokens
+    /// that are not native to the sending chain (e.g. `transfer/channel-0/uatom`).
+    pub denom: String,
+    /// The amount of tokens. This is encoded as a string in JSON.
+    pub amount: Uint256,
+    /// The sender address on the sending chain
+    pub sender: String,
+    /// The receiver address on the receiving chain
+    pub receiver: String,
+    /// An optional memo. This is omitted from the JSON if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+impl Ics20PacketData {
+    /// The channel version of ICS-20 this packet data belongs to
+    pub const VERSION: &'static str = "ics20-1";
+
+    /// Checks the basic validity of the packet data as done by ibc-go.
+    pub fn validate(&self) -> StdResult<()> {
+        if self.denom.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-20 denom must not be empty"));
+        }
+        if self.amount.is_zero() {
+            return Err(StdError::generic_err("ICS-20 amount must not be zero"));
+        }
+        if self.sender.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-20 sender must not be empty"));
+        }
+        if self.receiver.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-20 receiver must not be empty"));
+        }
+        Ok(())
+    }
+
+    /// Deserializes and validates the data of a received packet.
+    pub fn from_packet_data(data: &Binary) -> StdResult<Self> {
+        let packet_data: Self = from_binary(data)?;
+        packet_data.validate()?;
+        Ok(packet_data)
+    }
+
+    /// Validates and serializes the packet data for sending.
+    pub fn to_packet_data(&self) -> StdResult<Binary> {
+        self.validate()?;
+        to_binary(self)
+    }
+}
+
+/// The packet data of an ICS-721 non-fungible token transfer.
+///
+/// The JSON form uses camelCase field names, like ibc-go's `NonFungibleTokenPacketData`.
+/// See https://github.com/cosmos/ibc/tree/main/spec/app/ics-721-nft-transfer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Ics721PacketData {
+    /// The class ID of the tokens, including the trace for classes
+    /// that are not native to the sending chain.
+    pub class_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_data: Option<Binary>,
+    /// The IDs of the transferred tokens. Must not be empty.
+    pub token_ids: Vec<String>,
+    /// If set, this must contain one URI per token ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_uris: Option<Vec<String>>,
+    /// If set, this must contain one entry per token ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_data: Option<Vec<Binary>>,
+    /// The sender address on the sending chain
+    pub sender: String,
+    /// The receiver address on the receiving chain
+    pub receiver: String,
+    /// An optional memo. This is omitted from the JSON if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+impl Ics721PacketData {
+    /// The channel version of ICS-721 this packet data belongs to
+    pub const VERSION: &'static str = "ics721-1";
+
+    /// Checks the basic validity of the packet data as done by the ICS-721 reference implementation.
+    pub fn validate(&self) -> StdResult<()> {
+        if self.class_id.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-721 class ID must not be empty"));
+        }
+        if self.token_ids.is_empty() {
+            return Err(StdError::generic_err("ICS-721 token IDs must not be empty"));
+        }
+        if self.token_ids.iter().any(|id| id.trim().is_empty()) {
+            return Err(StdError::generic_err("ICS-721 token ID must not be empty"));
+        }
+        if let Some(token_uris) = &self.token_uris {
+            if token_uris.len() != self.token_ids.len() {
+                return Err(StdError::generic_err(
+                    "ICS-721 token URIs must have the same length as token IDs",
+                ));
+            }
+        }
+        if let Some(token_data) = &self.token_data {
+            if token_data.len() != self.token_ids.len() {
+                return Err(StdError::generic_err(
+                    "ICS-721 token data must have the same length as token IDs",
+                ));
+            }
+        }
+        if self.sender.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-721 sender must not be empty"));
+        }
+        if self.receiver.trim().is_empty() {
+            return Err(StdError::generic_err("ICS-721 receiver must not be empty"));
+        }
+        Ok(())
+    }
+
+    /// Deserializes and validates the data of a received packet.
+    pub fn from_packet_data(data: &Binary) -> StdResult<Self> {
+        let packet_data: Self = from_binary(data)?;
+        packet_data.validate()?;
+        Ok(packet_data)
+    }
+
+    /// Validates and serializes the packet data for sending.
+    pub fn to_packet_data(&self) -> StdResult<Binary> {
+        self.validate()?;
+        to_binary(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json_wasm::to_string;
+
+    fn ics20_data() -> Ics20PacketData {
+        Ics20PacketData {
+            denom: "transfer/channel-0/uatom".to_string(),
+            amount: Uint256::from(1234u128),
+            sender: "cosmos1sender".to_string(),
+            receiver: "juno1receiver".to_string(),
+            memo: None,
+        }
+    }
+
+    fn ics721_data() -> Ics721PacketData {
+        Ics721PacketData {
+            class_id: "wasm.juno1collection/channel-5/punks".to_string(),
+            class_uri: None,
+            class_data: None,
+            token_ids: vec!["1".to_string(), "2".to_string()],
+            token_uris: Some(vec!["ipfs://1".to_string(), "ipfs://2".to_string()]),
+            token_data: None,
+            sender: "juno1sender".to_string(),
+            receiver: "stars1receiver".to_string(),
+            memo: Some("hello".to_string()),
+        }
+    }
+
+    #[test]
+    fn ics20_packet_data_serializes_to_correct_json() {
+        let data = ics20_data();
+        assert_eq!(
+            to_string(&data).unwrap(),
+            r#"{"denom":"transfer/channel-0/uatom","amount":"1234","sender":"cosmos1sender","receiver":"juno1receiver"}"#
+        );
+
+        let with_memo = Ics20PacketData {
+            memo: Some("hello".to_string()),
+            ..data
+        };
+        assert_eq!(
+            to_string(&with_memo).unwrap(),
+            r#"{"denom":"transfer/channel-0/uatom","amount":"1234","sender":"cosmos1sender","receiver":"juno1receiver","memo":"hello"}"#
+        );
+    }
+
+    #[test]
+    fn ics20_packet_data_roundtrips() {
+        let data = ics20_data();
+        let binary = data.to_packet_data().unwrap();
+        assert_eq!(Ics20PacketData::from_packet_data(&binary).unwrap(), data);
+    }
+
+    #[test]
+    fn ics20_packet_data_validate_works() {
+        ics20_data().validate().unwrap();
+
+        let empty_denom = Ics20PacketData {
+            denom: "".to_string(),
+            ..ics20_data()
+        };
+        assert!(empty_denom.validate().is_err());
+        let zero_amount = Ics20PacketData {
+            amount: Uint256::zero(),
+            ..ics20_data()
+        };
+        assert!(zero_amount.validate().is_err());
+        assert!(zero_amount.to_packet_data().is_err());
+        let empty_receiver = Ics20PacketData {
+            receiver: " ".to_string(),
+            ..ics20_data()
+        };
+        assert!(empty_receiver.validate().is_err());
+
+        let invalid = br#"{"denom":"uatom","amount":"0","sender":"a","receiver":"b"}"#;
+        assert!(Ics20PacketData::from_packet_data(&Binary::from(&invalid[..])).is_err());
+    }
+
+    #[test]
+    fn ics721_packet_data_serializes_to_correct_json() {
+        let data = ics721_data();
+        assert_eq!(
+            to_string(&data).unwrap(),
+            r#"{"classId":"wasm.juno1collection/channel-5/punks","tokenIds":["1","2"],"tokenUris":["ipfs://1","ipfs://2"],"sender":"juno1sender","receiver":"stars1receiver","memo":"hello"}"#
+        );
+    }
+
+    #[test]
+    fn ics721_packet_data_roundtrips() {
+        let data = Ics721PacketData {
+            class_data: Some(Binary::from(b"class")),
+            token_data: Some(vec![Binary::from(b"one"), Binary::from(b"two")]),
+            ..ics721_data()
+        };
+        let binary = data.to_packet_data().unwrap();
+        assert_eq!(Ics721PacketData::from_packet_data(&binary).unwrap(), data);
+    }
+
+    #[test]
+    fn ics721_packet_data_validate_works() {
+        ics721_data().validate().unwrap();
+
+        let no_tokens = Ics721PacketData {
+            token_ids: vec![],
+            token_uris: None,
+            ..ics721_data()
+        };
+        assert!(no_tokens.validate().is_err());
+        let uri_mismatch = Ics721PacketData {
+            token_uris: Some(vec!["ipfs://1".to_string()]),
+            ..ics721_data()
+        };
+        assert!(uri_mismatch.validate().is_err());
+        let data_mismatch = Ics721PacketData {
+            token_data: Some(vec![Binary::from(b"one")]),
+            ..ics721_data()
+        };
+        assert!(data_mismatch.validate().is_err());
+        let empty_class = Ics721PacketData {
+            class_id: "".to_string(),
+            ..ics721_data()
+        };
+        assert!(empty_class.validate().is_err());
+    }
+}
@@ -7,6 +7,14 @@ use crate::memory::{alloc, build_region, consume_region, Region};
 use crate::results::SystemResult;
 #[cfg(feature = "iterator")]
 use crate::sections::decode_sections2;
+#[cfg(feature = "cosmwasm_1_2")]
+use crate::sections::encode_packed;
+#[cfg(any(
+    not(feature = "cosmwasm_1_2"),
+    feature = "zk",
+    feature = "tendermint",
+    feature = "bls12_381"
+))]
 use crate::sections::encode_sections;
 use crate::serde::from_slice;
 use crate::traits::{Api, Querier, QuerierResult, Storage};
@@ -37,6 +45,9 @@ extern "C" {
     fn db_scan(start_ptr: u32, end_ptr: u32, order: i32) -> u32;
     #[cfg(feature = "iterator")]
     fn db_next(iterator_id: u32) -> u32;
+    // like db_scan but the iterator returns at most `limit` elements
+    #[cfg(all(feature = "iterator", feature = "cosmwasm_1_2"))]
+    fn db_scan_with_limit(start_ptr: u32, end_ptr: u32, order: i32, limit: u32) -> u32;
 
     fn addr_validate(source_ptr: u32) -> u32;
     fn addr_canonicalize(source_ptr: u32, destination_ptr: u32) -> u32;
@@ -48,12 +59,38 @@ extern "C" {
     /// greater than 1 in case of error.
     fn secp256k1_verify(message_hash_ptr: u32, signature_ptr: u32, public_key_ptr: u32) -> u32;
 
+    /// Same as `secp256k1_verify`, but with an explicit identifier of the hash function
+    /// that produced the digest.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn secp256k1_verify_prehashed(
+        digest_ptr: u32,
+        hash_function: u32,
+        signature_ptr: u32,
+        public_key_ptr: u32,
+    ) -> u32;
+
     fn secp256k1_recover_pubkey(
         message_hash_ptr: u32,
         signature_ptr: u32,
         recovery_param: u32,
     ) -> u64;
 
+    /// Verifies message hashes against a signature with a public key, using the
+    /// secp256r1 ECDSA parametrization.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "secp256r1")]
+    fn secp256r1_verify(message_hash_ptr: u32, signature_ptr: u32, public_key_ptr: u32) -> u32;
+
+    #[cfg(feature = "secp256r1")]
+    fn secp256r1_recover_pubkey(
+        message_hash_ptr: u32,
+        signature_ptr: u32,
+        recovery_param: u32,
+    ) -> u64;
+
     /// Verifies a message against a signature with a public key, using the
     /// ed25519 EdDSA scheme.
     /// Returns 0 on verification success, 1 on verification failure, and values
@@ -64,8 +101,90 @@ extern "C" {
     /// ed25519 EdDSA scheme.
     /// Returns 0 on verification success, 1 on verification failure, and values
     /// greater than 1 in case of error.
+    #[cfg(not(feature = "cosmwasm_1_2"))]
     fn ed25519_batch_verify(messages_ptr: u32, signatures_ptr: u32, public_keys_ptr: u32) -> u32;
 
+    /// Same as `ed25519_batch_verify`, but with messages, signatures and public keys
+    /// packed into a single region.
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ed25519_batch_verify_packed(input_ptr: u32) -> u32;
+
+    /// Calculates the Poseidon hash of the sections encoded field elements.
+    /// Returns the error code in the high half and a pointer to the 32 byte
+    /// hash in the low half. The error code is 0 on success.
+    #[cfg(feature = "zk")]
+    fn poseidon_hash(inputs_ptr: u32) -> u64;
+
+    /// Verifies a Groth16 proof over the BN254 curve.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "zk")]
+    fn zk_verify_groth16(vk_ptr: u32, proof_ptr: u32, public_inputs_ptr: u32) -> u32;
+
+    /// Verifies an untrusted Tendermint header against a trusted one.
+    /// Takes a pointer to the sections encoded trusted header, trusted next validators,
+    /// untrusted signed header and untrusted validators.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "tendermint")]
+    fn tendermint_verify_header(input_ptr: u32) -> u32;
+
+    /// Verifies a BLS12-381 aggregate signature (in G2) of the sections encoded messages
+    /// against the concatenated compressed public keys (in G1).
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_aggregate_verify(
+        pubkeys_ptr: u32,
+        messages_ptr: u32,
+        signature_ptr: u32,
+        dst_ptr: u32,
+    ) -> u32;
+
+    /// Checks whether the product of the pairings of the concatenated G1 points `ps`
+    /// and G2 points `qs` equals the pairing of `r` and `s`.
+    /// Returns 0 if it does, 1 if it does not, and values greater than 1 in case of error.
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_pairing_equality(ps_ptr: u32, qs_ptr: u32, r_ptr: u32, s_ptr: u32) -> u32;
+
+    /// Hashes a message to a compressed G1 point with the given domain separation tag.
+    /// Returns the error code in the high half and a pointer to the point in the low half.
+    /// The error code is 0 on success.
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_hash_to_g1(message_ptr: u32, dst_ptr: u32) -> u64;
+
+    /// Same as `bls12_381_hash_to_g1`, but returns a compressed G2 point.
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_hash_to_g2(message_ptr: u32, dst_ptr: u32) -> u64;
+
+    /// Verifies an ICS-23 existence proof of a key and value against a commitment root.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ics23_verify_membership(
+        spec_id: u32,
+        root_ptr: u32,
+        proof_ptr: u32,
+        key_ptr: u32,
+        value_ptr: u32,
+    ) -> u32;
+
+    /// Verifies an ICS-23 non-existence proof of a key against a commitment root.
+    /// Returns 0 on verification success, 1 on verification failure, and values
+    /// greater than 1 in case of error.
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ics23_verify_non_membership(
+        spec_id: u32,
+        root_ptr: u32,
+        proof_ptr: u32,
+        key_ptr: u32,
+    ) -> u32;
+
+    /// Compares two byte strings in constant time.
+    /// Returns 0 if they are equal and 1 otherwise.
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ct_eq(a_ptr: u32, b_ptr: u32) -> u32;
+
     /// Writes a debug message (UFT-8 encoded) to the host for debugging purposes.
     /// The host is free to log or process this in any way it considers appropriate.
     /// In production environments it is expected that those messages are discarded.
@@ -139,6 +258,24 @@ impl Storage for ExternalStorage {
         let iter = ExternalIterator { iterator_id };
         Box::new(iter)
     }
+
+    #[cfg(all(feature = "iterator", feature = "cosmwasm_1_2"))]
+    fn range_with_limit(
+        &self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+    ) -> Box<dyn Iterator<Item = Record>> {
+        let start_region = start.map(build_region);
+        let end_region = end.map(build_region);
+        let start_region_addr = get_optional_region_address(&start_region.as_ref());
+        let end_region_addr = get_optional_region_address(&end_region.as_ref());
+        let iterator_id =
+            unsafe { db_scan_with_limit(start_region_addr, end_region_addr, order as i32, limit) };
+        let iter = ExternalIterator { iterator_id };
+        Box::new(iter)
+    }
 }
 
 #[cfg(feature = "iterator")]
@@ -270,6 +407,41 @@ impl Api for ExternalApi {
         }
     }
 
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn secp256k1_verify_prehashed(
+        &self,
+        digest: &[u8],
+        hash_function: u32,
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let digest_send = build_region(digest);
+        let digest_send_ptr = &*digest_send as *const Region as u32;
+        let sig_send = build_region(signature);
+        let sig_send_ptr = &*sig_send as *const Region as u32;
+        let pubkey_send = build_region(public_key);
+        let pubkey_send_ptr = &*pubkey_send as *const Region as u32;
+
+        let result = unsafe {
+            secp256k1_verify_prehashed(
+                digest_send_ptr,
+                hash_function,
+                sig_send_ptr,
+                pubkey_send_ptr,
+            )
+        };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            3 => Err(VerificationError::InvalidHashFormat),
+            4 => Err(VerificationError::InvalidSignatureFormat),
+            5 => Err(VerificationError::InvalidPubkeyFormat),
+            10 => Err(VerificationError::GenericErr),
+            11 => Err(VerificationError::InvalidHashFunction),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
     fn secp256k1_recover_pubkey(
         &self,
         message_hash: &[u8],
@@ -298,6 +470,62 @@ impl Api for ExternalApi {
         }
     }
 
+    #[cfg(feature = "secp256r1")]
+    fn secp256r1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let hash_send = build_region(message_hash);
+        let hash_send_ptr = &*hash_send as *const Region as u32;
+        let sig_send = build_region(signature);
+        let sig_send_ptr = &*sig_send as *const Region as u32;
+        let pubkey_send = build_region(public_key);
+        let pubkey_send_ptr = &*pubkey_send as *const Region as u32;
+
+        let result = unsafe { secp256r1_verify(hash_send_ptr, sig_send_ptr, pubkey_send_ptr) };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            2 => panic!("MessageTooLong must not happen. This is a bug in the VM."),
+            3 => Err(VerificationError::InvalidHashFormat),
+            4 => Err(VerificationError::InvalidSignatureFormat),
+            5 => Err(VerificationError::InvalidPubkeyFormat),
+            10 => Err(VerificationError::GenericErr),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "secp256r1")]
+    fn secp256r1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recover_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        let hash_send = build_region(message_hash);
+        let hash_send_ptr = &*hash_send as *const Region as u32;
+        let sig_send = build_region(signature);
+        let sig_send_ptr = &*sig_send as *const Region as u32;
+
+        let result =
+            unsafe { secp256r1_recover_pubkey(hash_send_ptr, sig_send_ptr, recover_param.into()) };
+        let error_code = from_high_half(result);
+        let pubkey_ptr = from_low_half(result);
+        match error_code {
+            0 => {
+                let pubkey = unsafe { consume_region(pubkey_ptr as *mut Region) };
+                Ok(pubkey)
+            }
+            2 => panic!("MessageTooLong must not happen. This is a bug in the VM."),
+            3 => Err(RecoverPubkeyError::InvalidHashFormat),
+            4 => Err(RecoverPubkeyError::InvalidSignatureFormat),
+            6 => Err(RecoverPubkeyError::InvalidRecoveryParam),
+            error_code => Err(RecoverPubkeyError::unknown_err(error_code)),
+        }
+    }
+
     fn ed25519_verify(
         &self,
         message: &[u8],
@@ -330,32 +558,299 @@ impl Api for ExternalApi {
         signatures: &[&[u8]],
         public_keys: &[&[u8]],
     ) -> Result<bool, VerificationError> {
-        let msgs_encoded = encode_sections(messages);
-        let msgs_send = build_region(&msgs_encoded);
-        let msgs_send_ptr = &*msgs_send as *const Region as u32;
+        #[cfg(feature = "cosmwasm_1_2")]
+        let result = {
+            let input = encode_packed(&[messages, signatures, public_keys]);
+            let input_send = build_region(&input);
+            let input_send_ptr = &*input_send as *const Region as u32;
+            unsafe { ed25519_batch_verify_packed(input_send_ptr) }
+        };
+        #[cfg(not(feature = "cosmwasm_1_2"))]
+        let result = {
+            let msgs_encoded = encode_sections(messages);
+            let msgs_send = build_region(&msgs_encoded);
+            let msgs_send_ptr = &*msgs_send as *const Region as u32;
+
+            let sigs_encoded = encode_sections(signatures);
+            let sig_sends = build_region(&sigs_encoded);
+            let sigs_send_ptr = &*sig_sends as *const Region as u32;
+
+            let pubkeys_encoded = encode_sections(public_keys);
+            let pubkeys_send = build_region(&pubkeys_encoded);
+            let pubkeys_send_ptr = &*pubkeys_send as *const Region as u32;
+
+            unsafe { ed25519_batch_verify(msgs_send_ptr, sigs_send_ptr, pubkeys_send_ptr) }
+        };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            2 => panic!("Error code 2 unused since CosmWasm 0.15. This is a bug in the VM."),
+            3 => panic!("InvalidHashFormat must not happen. This is a bug in the VM."),
+            4 => Err(VerificationError::InvalidSignatureFormat),
+            5 => Err(VerificationError::InvalidPubkeyFormat),
+            10 => Err(VerificationError::GenericErr),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
 
-        let sigs_encoded = encode_sections(signatures);
-        let sig_sends = build_region(&sigs_encoded);
-        let sigs_send_ptr = &*sig_sends as *const Region as u32;
+    #[cfg(feature = "zk")]
+    fn poseidon_hash(&self, inputs: &[&[u8]]) -> Result<[u8; 32], VerificationError> {
+        let inputs_encoded = encode_sections(inputs);
+        let inputs_send = build_region(&inputs_encoded);
+        let inputs_send_ptr = &*inputs_send as *const Region as u32;
 
-        let pubkeys_encoded = encode_sections(public_keys);
-        let pubkeys_send = build_region(&pubkeys_encoded);
-        let pubkeys_send_ptr = &*pubkeys_send as *const Region as u32;
+        let result = unsafe { poseidon_hash(inputs_send_ptr) };
+        let error_code = from_high_half(result);
+        let hash_ptr = from_low_half(result);
+        match error_code {
+            0 => {
+                let hash = unsafe { consume_region(hash_ptr as *mut Region) };
+                Ok(hash
+                    .try_into()
+                    .expect("Poseidon hash must be 32 bytes long. This is a bug in the VM."))
+            }
+            8 => Err(VerificationError::InvalidHashInput),
+            10 => Err(VerificationError::GenericErr),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
 
-        let result =
-            unsafe { ed25519_batch_verify(msgs_send_ptr, sigs_send_ptr, pubkeys_send_ptr) };
+    #[cfg(feature = "zk")]
+    fn zk_verify_groth16(
+        &self,
+        vk: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let vk_send = build_region(vk);
+        let vk_send_ptr = &*vk_send as *const Region as u32;
+        let proof_send = build_region(proof);
+        let proof_send_ptr = &*proof_send as *const Region as u32;
+        let inputs_send = build_region(public_inputs);
+        let inputs_send_ptr = &*inputs_send as *const Region as u32;
+
+        let result = unsafe { zk_verify_groth16(vk_send_ptr, proof_send_ptr, inputs_send_ptr) };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            9 => Err(VerificationError::Groth16Err),
+            10 => Err(VerificationError::GenericErr),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "tendermint")]
+    fn tendermint_verify_header(
+        &self,
+        trusted_header: &[u8],
+        trusted_next_validators: &[u8],
+        untrusted_header: &[u8],
+        untrusted_validators: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let input_encoded = encode_sections(&[
+            trusted_header,
+            trusted_next_validators,
+            untrusted_header,
+            untrusted_validators,
+        ]);
+        let input_send = build_region(&input_encoded);
+        let input_send_ptr = &*input_send as *const Region as u32;
+
+        let result = unsafe { tendermint_verify_header(input_send_ptr) };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            10 => Err(VerificationError::GenericErr),
+            13 => Err(VerificationError::TendermintErr),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_aggregate_verify(
+        &self,
+        pubkeys: &[u8],
+        messages: &[&[u8]],
+        signature: &[u8],
+        dst: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let pubkeys_send = build_region(pubkeys);
+        let pubkeys_send_ptr = &*pubkeys_send as *const Region as u32;
+        let messages_encoded = encode_sections(messages);
+        let messages_send = build_region(&messages_encoded);
+        let messages_send_ptr = &*messages_send as *const Region as u32;
+        let signature_send = build_region(signature);
+        let signature_send_ptr = &*signature_send as *const Region as u32;
+        let dst_send = build_region(dst);
+        let dst_send_ptr = &*dst_send as *const Region as u32;
+
+        let result = unsafe {
+            bls12_381_aggregate_verify(
+                pubkeys_send_ptr,
+                messages_send_ptr,
+                signature_send_ptr,
+                dst_send_ptr,
+            )
+        };
         match result {
             0 => Ok(true),
             1 => Ok(false),
-            2 => panic!("Error code 2 unused since CosmWasm 0.15. This is a bug in the VM."),
-            3 => panic!("InvalidHashFormat must not happen. This is a bug in the VM."),
             4 => Err(VerificationError::InvalidSignatureFormat),
             5 => Err(VerificationError::InvalidPubkeyFormat),
             10 => Err(VerificationError::GenericErr),
+            14 => Err(VerificationError::Bls12381Err),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_pairing_equality(
+        &self,
+        ps: &[u8],
+        qs: &[u8],
+        r: &[u8],
+        s: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let ps_send = build_region(ps);
+        let ps_send_ptr = &*ps_send as *const Region as u32;
+        let qs_send = build_region(qs);
+        let qs_send_ptr = &*qs_send as *const Region as u32;
+        let r_send = build_region(r);
+        let r_send_ptr = &*r_send as *const Region as u32;
+        let s_send = build_region(s);
+        let s_send_ptr = &*s_send as *const Region as u32;
+
+        let result =
+            unsafe { bls12_381_pairing_equality(ps_send_ptr, qs_send_ptr, r_send_ptr, s_send_ptr) };
+        match result {
+            0 => Ok(true),
+            1 => Ok(false),
+            10 => Err(VerificationError::GenericErr),
+            14 => Err(VerificationError::Bls12381Err),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_hash_to_g1(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 48], VerificationError> {
+        let message_send = build_region(message);
+        let message_send_ptr = &*message_send as *const Region as u32;
+        let dst_send = build_region(dst);
+        let dst_send_ptr = &*dst_send as *const Region as u32;
+
+        let result = unsafe { bls12_381_hash_to_g1(message_send_ptr, dst_send_ptr) };
+        let error_code = from_high_half(result);
+        let point_ptr = from_low_half(result);
+        match error_code {
+            0 => {
+                let point = unsafe { consume_region(point_ptr as *mut Region) };
+                Ok(point
+                    .try_into()
+                    .expect("G1 point must be 48 bytes long. This is a bug in the VM."))
+            }
+            10 => Err(VerificationError::GenericErr),
+            14 => Err(VerificationError::Bls12381Err),
+            error_code => Err(VerificationError::unknown_err(error_code)),
+        }
+    }
+
+    #[cfg(feature = "bls12_381")]
+    fn bls12_381_hash_to_g2(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 96], VerificationError> {
+        let message_send = build_region(message);
+        let message_send_ptr = &*message_send as *const Region as u32;
+        let dst_send = build_region(dst);
+        let dst_send_ptr = &*dst_send as *const Region as u32;
+
+        let result = unsafe { bls12_381_hash_to_g2(message_send_ptr, dst_send_ptr) };
+        let error_code = from_high_half(result);
+        let point_ptr = from_low_half(result);
+        match error_code {
+            0 => {
+                let point = unsafe { consume_region(point_ptr as *mut Region) };
+                Ok(point
+                    .try_into()
+                    .expect("G2 point must be 96 bytes long. This is a bug in the VM."))
+            }
+            10 => Err(VerificationError::GenericErr),
+            14 => Err(VerificationError::Bls12381Err),
             error_code => Err(VerificationError::unknown_err(error_code)),
         }
     }
 
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ics23_verify_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let root_send = build_region(root);
+        let root_send_ptr = &*root_send as *const Region as u32;
+        let proof_send = build_region(proof);
+        let proof_send_ptr = &*proof_send as *const Region as u32;
+        let key_send = build_region(key);
+        let key_send_ptr = &*key_send as *const Region as u32;
+        let value_send = build_region(value);
+        let value_send_ptr = &*value_send as *const Region as u32;
+
+        let result = unsafe {
+            ics23_verify_membership(
+                spec,
+                root_send_ptr,
+                proof_send_ptr,
+                key_send_ptr,
+                value_send_ptr,
+            )
+        };
+        ics23_result(result)
+    }
+
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ics23_verify_non_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let root_send = build_region(root);
+        let root_send_ptr = &*root_send as *const Region as u32;
+        let proof_send = build_region(proof);
+        let proof_send_ptr = &*proof_send as *const Region as u32;
+        let key_send = build_region(key);
+        let key_send_ptr = &*key_send as *const Region as u32;
+
+        let result = unsafe {
+            ics23_verify_non_membership(spec, root_send_ptr, proof_send_ptr, key_send_ptr)
+        };
+        ics23_result(result)
+    }
+
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn ct_eq(&self, a: &[u8], b: &[u8]) -> bool {
+        let a_send = build_region(a);
+        let a_send_ptr = &*a_send as *const Region as u32;
+        let b_send = build_region(b);
+        let b_send_ptr = &*b_send as *const Region as u32;
+
+        let result = unsafe { ct_eq(a_send_ptr, b_send_ptr) };
+        match result {
+            0 => true,
+            1 => false,
+            other => panic!("Unexpected result from ct_eq: {}", other),
+        }
+    }
+
     fn debug(&self, message: &str) {
         // keep the boxes in scope, so we free it at the end (don't cast to pointers same line as build_region)
         let region = build_region(message.as_bytes());
@@ -364,6 +859,17 @@ impl Api for ExternalApi {
     }
 }
 
+#[cfg(feature = "cosmwasm_1_2")]
+fn ics23_result(result: u32) -> Result<bool, VerificationError> {
+    match result {
+        0 => Ok(true),
+        1 => Ok(false),
+        10 => Err(VerificationError::GenericErr),
+        12 => Err(VerificationError::Ics23Err),
+        error_code => Err(VerificationError::unknown_err(error_code)),
+    }
+}
+
 /// Takes a pointer to a Region and reads the data into a String.
 /// This is for trusted string sources only.
 unsafe fn consume_string_region_written_by_vm(from: *mut Region) -> String {
@@ -3,14 +3,21 @@
 // Exposed on all platforms
 
 mod addresses;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
 mod assertions;
 mod binary;
 mod coin;
 mod conversion;
+mod denom;
 mod deps;
 mod errors;
 mod hex_binary;
 mod ibc;
+#[cfg(feature = "stargate")]
+mod ibc_callbacks;
+#[cfg(feature = "stargate")]
+mod ibc_packet_data;
 mod import_helpers;
 #[cfg(feature = "iterator")]
 mod iterator;
@@ -28,11 +35,12 @@ mod types;
 pub use crate::addresses::{instantiate2_address, Addr, CanonicalAddr};
 pub use crate::binary::Binary;
 pub use crate::coin::{coin, coins, has_coins, Coin};
+pub use crate::denom::{validate_denom, Denom};
 pub use crate::deps::{Deps, DepsMut, OwnedDeps};
 pub use crate::errors::{
-    CheckedFromRatioError, CheckedMultiplyRatioError, ConversionOverflowError, DivideByZeroError,
-    OverflowError, OverflowOperation, RecoverPubkeyError, StdError, StdResult, SystemError,
-    VerificationError,
+    CheckedFromRatioError, CheckedMultiplyRatioError, ConversionOverflowError, DenomError,
+    DivideByZeroError, OverflowError, OverflowOperation, RecoverPubkeyError, StdError, StdResult,
+    SystemError, VerificationError,
 };
 pub use crate::hex_binary::HexBinary;
 #[cfg(feature = "stargate")]
@@ -42,6 +50,13 @@ pub use crate::ibc::{
     IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse,
     IbcTimeout, IbcTimeoutBlock,
 };
+#[cfg(feature = "stargate")]
+pub use crate::ibc_callbacks::{
+    IbcAckCallbackMsg, IbcCallbackRequest, IbcDestinationCallbackMsg, IbcDstCallback,
+    IbcSourceCallbackMsg, IbcSrcCallback, IbcTimeoutCallbackMsg,
+};
+#[cfg(feature = "stargate")]
+pub use crate::ibc_packet_data::{Ics20PacketData, Ics721PacketData};
 #[cfg(feature = "iterator")]
 pub use crate::iterator::{Order, Record};
 pub use crate::math::{
@@ -77,7 +92,10 @@ pub use crate::results::{GovMsg, VoteOption};
 pub use crate::serde::{from_binary, from_slice, to_binary, to_vec};
 pub use crate::storage::MemoryStorage;
 pub use crate::timestamp::Timestamp;
-pub use crate::traits::{Api, Querier, QuerierResult, QuerierWrapper, Storage};
+pub use crate::traits::{
+    Api, Querier, QuerierResult, QuerierWrapper, Storage, HASH_FUNCTION_KECCAK256,
+    HASH_FUNCTION_SHA256, ICS23_SPEC_IAVL, ICS23_SPEC_TENDERMINT,
+};
 pub use crate::types::{BlockInfo, ContractInfo, Env, MessageInfo, TransactionInfo};
 
 // Exposed in wasm build only
@@ -93,8 +111,8 @@ mod memory; // Used by exports and imports only. This assumes pointers are 32 bi
 pub use crate::exports::{do_execute, do_instantiate, do_migrate, do_query, do_reply, do_sudo};
 #[cfg(all(feature = "stargate", target_arch = "wasm32"))]
 pub use crate::exports::{
-    do_ibc_channel_close, do_ibc_channel_connect, do_ibc_channel_open, do_ibc_packet_ack,
-    do_ibc_packet_receive, do_ibc_packet_timeout,
+    do_ibc_channel_close, do_ibc_channel_connect, do_ibc_channel_open, do_ibc_destination_callback,
+    do_ibc_packet_ack, do_ibc_packet_receive, do_ibc_packet_timeout, do_ibc_source_callback,
 };
 #[cfg(target_arch = "wasm32")]
 pub use crate::imports::{ExternalApi, ExternalQuerier, ExternalStorage};
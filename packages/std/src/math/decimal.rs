@@ -12,6 +12,7 @@ use crate::errors::{
     OverflowOperation, RoundUpOverflowError, StdError,
 };
 
+use super::decimal_str::DecimalStr;
 use super::Fraction;
 use super::Isqrt;
 use super::{Uint128, Uint256};
@@ -389,50 +390,42 @@ impl FromStr for Decimal {
     type Err = StdError;
 
     /// Converts the decimal string to a Decimal
-    /// Possible inputs: "1.23", "1", "000012", "1.123000000"
-    /// Disallowed: "", ".23"
+    /// Possible inputs: "1.23", "1", "000012", "1.123000000", "1_000.5", "1.234e-5", "5E+3"
+    /// Disallowed: "", ".23", "1.", "_1", "1__0", "1e"
     ///
     /// This never performs any kind of rounding.
-    /// More than DECIMAL_PLACES fractional digits, even zeros, result in an error.
+    /// More than DECIMAL_PLACES fractional digits after applying the exponent, even zeros,
+    /// result in an error.
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let mut parts_iter = input.split('.');
+        let DecimalStr {
+            digits,
+            fractional_digits,
+        } = DecimalStr::parse(input)?;
+
+        if fractional_digits > Self::DECIMAL_PLACES as i64 {
+            return Err(StdError::generic_err(format!(
+                "Cannot parse more than {} fractional digits",
+                Self::DECIMAL_PLACES
+            )));
+        }
 
-        let whole_part = parts_iter.next().unwrap(); // split always returns at least one element
-        let whole = whole_part
+        // The digits are valid, so parsing can only fail due to an overflow
+        let value = digits
             .parse::<Uint128>()
-            .map_err(|_| StdError::generic_err("Error parsing whole"))?;
-        let mut atomics = whole
-            .checked_mul(Self::DECIMAL_FRACTIONAL)
             .map_err(|_| StdError::generic_err("Value too big"))?;
-
-        if let Some(fractional_part) = parts_iter.next() {
-            let fractional = fractional_part
-                .parse::<Uint128>()
-                .map_err(|_| StdError::generic_err("Error parsing fractional"))?;
-            let exp = (Self::DECIMAL_PLACES.checked_sub(fractional_part.len() as u32)).ok_or_else(
-                || {
-                    StdError::generic_err(format!(
-                        "Cannot parse more than {} fractional digits",
-                        Self::DECIMAL_PLACES
-                    ))
-                },
-            )?;
-            debug_assert!(exp <= Self::DECIMAL_PLACES);
-            let fractional_factor = Uint128::from(10u128.pow(exp));
-            atomics = atomics
-                .checked_add(
-                    // The inner multiplication can't overflow because
-                    // fractional < 10^DECIMAL_PLACES && fractional_factor <= 10^DECIMAL_PLACES
-                    fractional.checked_mul(fractional_factor).unwrap(),
-                )
-                .map_err(|_| StdError::generic_err("Value too big"))?;
+        if value.is_zero() {
+            return Ok(Self::zero());
         }
 
-        if parts_iter.next().is_some() {
-            return Err(StdError::generic_err("Unexpected number of dots"));
-        }
+        // Moves the decimal point such that there are exactly DECIMAL_PLACES fractional digits
+        let shift =
+            u32::try_from(Self::DECIMAL_PLACES as i64 - fractional_digits).unwrap_or(u32::MAX);
+        let atomics = Uint128::new(10)
+            .checked_pow(shift)
+            .and_then(|factor| value.checked_mul(factor))
+            .map_err(|_| StdError::generic_err("Value too big"))?;
 
-        Ok(Decimal(atomics))
+        Ok(Self(atomics))
     }
 }
 
@@ -909,6 +902,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decimal_from_str_works_for_digit_separators() {
+        assert_eq!(
+            Decimal::from_str("1_000").unwrap(),
+            Decimal::percent(100_000)
+        );
+        assert_eq!(
+            Decimal::from_str("1_0.0_5").unwrap(),
+            Decimal::permille(10_050)
+        );
+        assert_eq!(
+            Decimal::from_str("0.000_000_000_000_000_001").unwrap(),
+            Decimal::raw(1)
+        );
+
+        for input in ["_1", "1_", "1__0", "1_.5", "1._5", "1.5_", "1e_1"] {
+            Decimal::from_str(input).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn decimal_from_str_works_for_scientific_notation() {
+        assert_eq!(
+            Decimal::from_str("1.2e3").unwrap(),
+            Decimal::percent(120_000)
+        );
+        assert_eq!(
+            Decimal::from_str("1.2E3").unwrap(),
+            Decimal::percent(120_000)
+        );
+        assert_eq!(
+            Decimal::from_str("1.2e+3").unwrap(),
+            Decimal::percent(120_000)
+        );
+        assert_eq!(Decimal::from_str("15e-1").unwrap(), Decimal::percent(150));
+        assert_eq!(
+            Decimal::from_str("1.234e-5").unwrap(),
+            Decimal::raw(12_340_000_000_000)
+        );
+        assert_eq!(Decimal::from_str("5e0").unwrap(), Decimal::percent(500));
+        assert_eq!(Decimal::from_str("1e-18").unwrap(), Decimal::raw(1));
+        assert_eq!(Decimal::from_str("1_000e-3").unwrap(), Decimal::one());
+
+        // Zero is zero, no matter the exponent
+        assert_eq!(Decimal::from_str("0e99999999999").unwrap(), Decimal::zero());
+        assert_eq!(Decimal::from_str("0.0e-17").unwrap(), Decimal::zero());
+
+        // Works for max value
+        assert_eq!(
+            Decimal::from_str("340282366920938463463374607431768211455e-18").unwrap(),
+            Decimal::MAX
+        );
+    }
+
+    #[test]
+    fn decimal_from_str_errors_for_invalid_scientific_notation() {
+        for input in ["1e", "1e+", "1e-", "1e1.5", "1e1e1", "1ee1", "1e 1"] {
+            match Decimal::from_str(input).unwrap_err() {
+                StdError::GenericErr { msg, .. } => assert_eq!(msg, "Error parsing exponent"),
+                e => panic!("Unexpected error: {:?}", e),
+            }
+        }
+
+        // No rounding
+        for input in ["1e-19", "1.5e-18", "1.000e-16"] {
+            match Decimal::from_str(input).unwrap_err() {
+                StdError::GenericErr { msg, .. } => {
+                    assert_eq!(msg, "Cannot parse more than 18 fractional digits")
+                }
+                e => panic!("Unexpected error: {:?}", e),
+            }
+        }
+        match Decimal::from_str("1e-99999999999").unwrap_err() {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "Cannot parse more than 18 fractional digits")
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // Overflow
+        for input in [
+            "340282366920938463464",
+            "1e21",
+            "1e4294967295",
+            "1e99999999999",
+        ] {
+            match Decimal::from_str(input).unwrap_err() {
+                StdError::GenericErr { msg, .. } => assert_eq!(msg, "Value too big"),
+                e => panic!("Unexpected error: {:?}", e),
+            }
+        }
+    }
+
     #[test]
     fn decimal_from_str_errors_for_broken_whole_part() {
         match Decimal::from_str("").unwrap_err() {
@@ -944,7 +1030,7 @@ mod tests {
             e => panic!("Unexpected error: {:?}", e),
         }
 
-        match Decimal::from_str("1.2e3").unwrap_err() {
+        match Decimal::from_str("1._2").unwrap_err() {
             StdError::GenericErr { msg, .. } => assert_eq!(msg, "Error parsing fractional"),
             e => panic!("Unexpected error: {:?}", e),
         }
@@ -0,0 +1,155 @@
+use crate::errors::{StdError, StdResult};
+
+/// A decimal number string split into its digits and the position of the decimal point
+pub(crate) struct DecimalStr {
+    /// All digits of the number without decimal point and digit separators
+    pub digits: String,
+    /// The number of `digits` after the decimal point once the exponent is applied.
+    /// This is negative if the exponent moves the decimal point past the last digit.
+    pub fractional_digits: i64,
+}
+
+impl DecimalStr {
+    /// Parses strings like "1.23", "1_000.5", "1.234e-5" or "5E+3".
+    ///
+    /// Digits can be separated by single underscores. The exponent is optional,
+    /// its digits follow the same rules as the digits of the whole and fractional part.
+    pub fn parse(input: &str) -> StdResult<Self> {
+        let (number, exponent) = match input.find(['e', 'E']) {
+            Some(index) => (&input[..index], Some(&input[index + 1..])),
+            None => (input, None),
+        };
+
+        let mut parts_iter = number.split('.');
+
+        let whole_part = parts_iter.next().unwrap(); // split always returns at least one element
+        let mut digits = strip_digit_separators(whole_part)
+            .ok_or_else(|| StdError::generic_err("Error parsing whole"))?;
+        let mut fractional_digits = 0i64;
+
+        if let Some(fractional_part) = parts_iter.next() {
+            let fractional = strip_digit_separators(fractional_part)
+                .ok_or_else(|| StdError::generic_err("Error parsing fractional"))?;
+            fractional_digits = fractional.len() as i64;
+            digits.push_str(&fractional);
+        }
+
+        if parts_iter.next().is_some() {
+            return Err(StdError::generic_err("Unexpected number of dots"));
+        }
+
+        if let Some(exponent) = exponent {
+            let exponent = parse_exponent(exponent)
+                .ok_or_else(|| StdError::generic_err("Error parsing exponent"))?;
+            fractional_digits -= exponent;
+        }
+
+        Ok(DecimalStr {
+            digits,
+            fractional_digits,
+        })
+    }
+}
+
+/// Parses an exponent with optional sign. Exponents that do not fit in an u32 are saturated,
+/// which leads to an overflow or too many fractional digits for any non-zero number.
+fn parse_exponent(input: &str) -> Option<i64> {
+    let (is_negative, magnitude) = match input.as_bytes().first() {
+        Some(b'-') => (true, &input[1..]),
+        Some(b'+') => (false, &input[1..]),
+        _ => (false, input),
+    };
+    let magnitude = strip_digit_separators(magnitude)?
+        .parse::<u32>()
+        .unwrap_or(u32::MAX) as i64;
+    Some(if is_negative { -magnitude } else { magnitude })
+}
+
+/// Returns the ASCII digits of the input without underscores. The input must be non-empty and
+/// underscores are only allowed between two digits.
+fn strip_digit_separators(input: &str) -> Option<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut previous_is_digit = false;
+    for c in input.chars() {
+        match c {
+            '0'..='9' => {
+                out.push(c);
+                previous_is_digit = true;
+            }
+            '_' if previous_is_digit => previous_is_digit = false,
+            _ => return None,
+        }
+    }
+    // This rejects empty inputs as well as trailing underscores
+    if previous_is_digit {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (String, i64) {
+        let parsed = DecimalStr::parse(input).unwrap();
+        (parsed.digits, parsed.fractional_digits)
+    }
+
+    fn parse_err(input: &str) -> String {
+        match DecimalStr::parse(input) {
+            Err(StdError::GenericErr { msg, .. }) => msg,
+            Err(e) => panic!("Unexpected error: {:?}", e),
+            Ok(parsed) => panic!("Unexpectedly parsed to {}", parsed.digits),
+        }
+    }
+
+    #[test]
+    fn decimal_str_parse_works() {
+        assert_eq!(parse("0"), ("0".to_string(), 0));
+        assert_eq!(parse("0042"), ("0042".to_string(), 0));
+        assert_eq!(parse("1.23"), ("123".to_string(), 2));
+        assert_eq!(parse("1_000.000_5"), ("10000005".to_string(), 4));
+        assert_eq!(parse("1.234e-5"), ("1234".to_string(), 8));
+        assert_eq!(parse("1.234E5"), ("1234".to_string(), -2));
+        assert_eq!(parse("5e+3"), ("5".to_string(), -3));
+        assert_eq!(parse("5e0_3"), ("5".to_string(), -3));
+        assert_eq!(parse("5e-0"), ("5".to_string(), 0));
+
+        // exponents are saturated
+        assert_eq!(
+            parse("5e99999999999"),
+            ("5".to_string(), -(u32::MAX as i64))
+        );
+        assert_eq!(parse("5e-99999999999"), ("5".to_string(), u32::MAX as i64));
+    }
+
+    #[test]
+    fn decimal_str_parse_errors_for_invalid_input() {
+        for input in [
+            "", " ", "-1", "+1", ".5", "_1", "1_", "1__0", "1_.5", "1 ", "e5",
+        ] {
+            assert_eq!(parse_err(input), "Error parsing whole", "input: {}", input);
+        }
+        for input in ["1.", "1._5", "1.5_", "1.e5", "1.5 "] {
+            assert_eq!(
+                parse_err(input),
+                "Error parsing fractional",
+                "input: {}",
+                input
+            );
+        }
+        for input in [
+            "1e", "1e+", "1e-", "1e_1", "1e1_", "1e--1", "1e1.5", "1e1e1", "1e 1",
+        ] {
+            assert_eq!(
+                parse_err(input),
+                "Error parsing exponent",
+                "input: {}",
+                input
+            );
+        }
+        assert_eq!(parse_err("1.2.3"), "Unexpected number of dots");
+    }
+}
@@ -1,5 +1,6 @@
 mod decimal;
 mod decimal256;
+mod decimal_str;
 mod fraction;
 mod isqrt;
 mod uint128;
@@ -1,5 +1,6 @@
 /// Installs a panic handler that aborts the contract execution
-/// and sends the panic message and location to the host.
+/// and sends the panic message and location to the host. With the `size_optimized`
+/// feature, only the message is sent.
 ///
 /// This overrides any previous panic handler. See <https://doc.rust-lang.org/std/panic/fn.set_hook.html>
 /// for details.
@@ -7,8 +8,19 @@
 pub fn install_panic_handler() {
     use super::imports::handle_panic;
     std::panic::set_hook(Box::new(|info| {
-        // E.g. "panicked at 'oh no (a = 3)', src/contract.rs:51:5"
-        let full_message = info.to_string();
-        handle_panic(&full_message);
+        // The message with its location, e.g. "panicked at 'oh no (a = 3)', src/contract.rs:51:5"
+        #[cfg(not(feature = "size_optimized"))]
+        handle_panic(&info.to_string());
+        // Only the message, e.g. "oh no (a = 3)", which avoids formatting the location
+        #[cfg(feature = "size_optimized")]
+        {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+            handle_panic(message);
+        }
     }));
 }
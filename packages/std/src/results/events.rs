@@ -25,19 +25,26 @@ pub struct Event {
 
 impl Event {
     /// Create a new event with the given type and an empty list of attributes.
+    ///
+    /// In debug builds or with the `strict_events` feature, this panics if the type is
+    /// empty or only consists of whitespace, since the chain would reject the event.
     pub fn new(ty: impl Into<String>) -> Self {
+        let ty = ty.into();
+
+        #[cfg(any(debug_assertions, feature = "strict_events"))]
+        if ty.trim().is_empty() {
+            panic!("event type `{}` is invalid - types must not be empty", ty);
+        }
+
         Event {
-            ty: ty.into(),
+            ty,
             attributes: Vec::with_capacity(10),
         }
     }
 
     /// Add an attribute to the event.
     pub fn add_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
-        self.attributes.push(Attribute {
-            key: key.into(),
-            value: value.into(),
-        });
+        self.attributes.push(Attribute::new(key, value));
         self
     }
 
@@ -63,15 +70,27 @@ pub struct Attribute {
 
 impl Attribute {
     /// Creates a new Attribute. `attr` is just an alias for this.
+    ///
+    /// In debug builds or with the `strict_events` feature, this panics if the key is
+    /// empty, only consists of whitespace or starts with the reserved `_` prefix, since
+    /// the chain would reject the attribute.
     pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
         let key = key.into();
 
-        #[cfg(debug_assertions)]
-        if key.starts_with('_') {
-            panic!(
-                "attribute key `{}` is invalid - keys starting with an underscore are reserved",
-                key
-            );
+        #[cfg(any(debug_assertions, feature = "strict_events"))]
+        {
+            if key.trim().is_empty() {
+                panic!(
+                    "attribute key `{}` is invalid - keys must not be empty",
+                    key
+                );
+            }
+            if key.starts_with('_') {
+                panic!(
+                    "attribute key `{}` is invalid - keys starting with an underscore are reserved",
+                    key
+                );
+            }
         }
 
         Self {
@@ -157,6 +176,36 @@ mod tests {
         Attribute::new("_", "value");
     }
 
+    #[test]
+    #[should_panic(expected = "keys must not be empty")]
+    fn attribute_new_empty_key_panics() {
+        Attribute::new("", "value");
+    }
+
+    #[test]
+    #[should_panic(expected = "keys must not be empty")]
+    fn attribute_new_whitespace_key_panics() {
+        Attribute::new(" \t", "value");
+    }
+
+    #[test]
+    #[should_panic(expected = "types must not be empty")]
+    fn event_new_empty_type_panics() {
+        Event::new("");
+    }
+
+    #[test]
+    #[should_panic(expected = "types must not be empty")]
+    fn event_new_whitespace_type_panics() {
+        Event::new("  ");
+    }
+
+    #[test]
+    #[should_panic(expected = "keys starting with an underscore are reserved")]
+    fn event_add_attribute_reserved_key_panics() {
+        Event::new("test").add_attribute("_contract_address", "value");
+    }
+
     #[test]
     fn attr_works_for_different_types() {
         let expected = ("foo", "42");
@@ -49,6 +49,18 @@ impl<S> SystemResult<S> {
     pub fn unwrap(self) -> S {
         self.into_result().unwrap()
     }
+
+    /// Returns `true` if the result is `SystemResult::Ok`, i.e. the query was executed.
+    /// The contained result of the queried contract can still be an error.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, SystemResult::Ok(_))
+    }
+
+    /// Returns `true` if the result is `SystemResult::Err`, i.e. the query could not be
+    /// executed by the chain.
+    pub fn is_err(&self) -> bool {
+        matches!(self, SystemResult::Err(_))
+    }
 }
 
 impl<S: fmt::Debug> SystemResult<S> {
@@ -74,3 +86,66 @@ impl<S> From<SystemResult<S>> for Result<S, SystemError> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_slice, to_vec, Binary, ContractResult};
+
+    #[test]
+    fn system_result_serialization_works() {
+        let result = SystemResult::Ok(ContractResult::Ok(Binary::from(b"foo")));
+        assert_eq!(&to_vec(&result).unwrap(), br#"{"ok":{"ok":"Zm9v"}}"#);
+
+        let result: SystemResult<ContractResult<Binary>> =
+            SystemResult::Ok(ContractResult::Err("broken".to_string()));
+        assert_eq!(&to_vec(&result).unwrap(), br#"{"ok":{"error":"broken"}}"#);
+
+        let result: SystemResult<ContractResult<Binary>> =
+            SystemResult::Err(SystemError::NoSuchContract {
+                addr: "foo".to_string(),
+            });
+        assert_eq!(
+            &to_vec(&result).unwrap(),
+            br#"{"error":{"no_such_contract":{"addr":"foo"}}}"#
+        );
+    }
+
+    #[test]
+    fn system_result_deserialization_works() {
+        let result: SystemResult<ContractResult<Binary>> =
+            from_slice(br#"{"ok":{"error":"broken"}}"#).unwrap();
+        assert_eq!(
+            result,
+            SystemResult::Ok(ContractResult::Err("broken".to_string()))
+        );
+
+        let result: SystemResult<ContractResult<Binary>> =
+            from_slice(br#"{"error":{"unknown":{}}}"#).unwrap();
+        assert_eq!(result, SystemResult::Err(SystemError::Unknown {}));
+    }
+
+    #[test]
+    fn is_ok_and_is_err_work() {
+        let ok: SystemResult<u8> = SystemResult::Ok(1);
+        assert!(ok.is_ok());
+        assert!(!ok.is_err());
+
+        let err: SystemResult<u8> = SystemResult::Err(SystemError::Unknown {});
+        assert!(!err.is_ok());
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn can_convert_from_and_to_core_result() {
+        let original: Result<u8, SystemError> = Ok(5);
+        let converted: SystemResult<u8> = original.into();
+        assert_eq!(converted, SystemResult::Ok(5));
+        assert_eq!(converted.into_result(), Ok(5));
+
+        let original: Result<u8, SystemError> = Err(SystemError::Unknown {});
+        let converted: SystemResult<u8> = original.into();
+        assert_eq!(converted, SystemResult::Err(SystemError::Unknown {}));
+        assert_eq!(converted.into_result(), Err(SystemError::Unknown {}));
+    }
+}
@@ -35,6 +35,40 @@ pub fn encode_sections(sections: &[&[u8]]) -> Vec<u8> {
     out_data
 }
 
+/// Encodes groups of items into one vector, such that all items can be read without copying.
+///
+/// All numbers are big endian uint32. The vector starts with the number of items of each
+/// group, followed by the end offset of each item relative to the start of the data,
+/// followed by the data of all items:
+///
+/// ```ignore
+/// group1_count || group2_count || … || end_offset_1 || … || end_offset_n || data
+/// ```
+#[cfg(feature = "cosmwasm_1_2")]
+#[allow(dead_code)] // used in Wasm and tests only
+pub fn encode_packed(groups: &[&[&[u8]]]) -> Vec<u8> {
+    let item_count: usize = groups.iter().map(|group| group.len()).sum();
+    let data_len: usize = groups
+        .iter()
+        .flat_map(|group| group.iter())
+        .map(|item| item.len())
+        .sum();
+    let mut out_data = Vec::with_capacity(4 * (groups.len() + item_count) + data_len);
+    for group in groups {
+        out_data.extend_from_slice(&force_to_u32(group.len()).to_be_bytes());
+    }
+    let mut end = 0usize;
+    for item in groups.iter().flat_map(|group| group.iter()) {
+        end += item.len();
+        out_data.extend_from_slice(&force_to_u32(end).to_be_bytes());
+    }
+    for item in groups.iter().flat_map(|group| group.iter()) {
+        out_data.extend_from_slice(item);
+    }
+    debug_assert_eq!(out_data.len(), out_data.capacity());
+    out_data
+}
+
 /// Splits data into the last section ("tail") and the rest.
 /// The tail's length information is cut off, such that it is ready to use.
 /// The rest is basically unparsed and contails the lengths of the remaining sections.
@@ -141,4 +175,31 @@ mod tests {
         let enc = encode_sections(&[&[0xAA], &[0xDE, 0xDE], &[], &[0xFF; 19]]);
         assert_eq!(enc, b"\xAA\0\0\0\x01\xDE\xDE\0\0\0\x02\0\0\0\0\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\0\0\0\x13" as &[u8]);
     }
+
+    #[test]
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn encode_packed_works() {
+        let enc = encode_packed(&[]);
+        assert_eq!(enc, b"" as &[u8]);
+        let enc = encode_packed(&[&[], &[]]);
+        assert_eq!(enc, b"\0\0\0\0\0\0\0\0" as &[u8]);
+        let enc = encode_packed(&[&[&[0xAA], &[]], &[&[0xBB, 0xCC]]]);
+        assert_eq!(
+            enc,
+            b"\0\0\0\x02\0\0\0\x01\0\0\0\x01\0\0\0\x01\0\0\0\x03\xAA\xBB\xCC" as &[u8]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "cosmwasm_1_2")]
+    fn encode_packed_matches_ed25519_batch_decode_packed() {
+        let messages: [&[u8]; 2] = [b"first", b""];
+        let signatures: [&[u8]; 2] = [b"sig1", b"sig2"];
+        let public_keys: [&[u8]; 1] = [b"key"];
+        let enc = encode_packed(&[&messages, &signatures, &public_keys]);
+        let batch = cosmwasm_crypto::ed25519_batch_decode_packed(&enc).unwrap();
+        assert_eq!(batch.messages, messages);
+        assert_eq!(batch.signatures, signatures);
+        assert_eq!(batch.public_keys, public_keys);
+    }
 }
@@ -139,6 +139,38 @@ mod tests {
         assert_eq!(store.get(b"food"), Some(b"bank".to_vec()));
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_with_limit_works() {
+        let mut store = MemoryStorage::new();
+        store.set(b"ant", b"hill");
+        store.set(b"foo", b"bar");
+        store.set(b"ze", b"bra");
+
+        let elements: Vec<Record> = store
+            .range_with_limit(None, None, Order::Descending, 2)
+            .collect();
+        assert_eq!(
+            elements,
+            vec![
+                (b"ze".to_vec(), b"bra".to_vec()),
+                (b"foo".to_vec(), b"bar".to_vec()),
+            ]
+        );
+
+        let elements: Vec<Record> = store
+            .range_with_limit(Some(b"b"), None, Order::Ascending, 5)
+            .collect();
+        assert_eq!(elements.len(), 2);
+
+        assert_eq!(
+            store
+                .range_with_limit(None, None, Order::Ascending, 0)
+                .count(),
+            0
+        );
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn iterator() {
@@ -0,0 +1,263 @@
+use bech32::{FromBase32, ToBase32, Variant};
+use sha3::{Digest, Keccak256};
+
+use crate::addresses::{Addr, CanonicalAddr};
+use crate::errors::{StdError, StdResult};
+
+/// Length of an Ethereum address in bytes
+const EVM_ADDRESS_LENGTH: usize = 20;
+/// Max length of canonical addresses as enforced by the Cosmos SDK
+const MAX_BECH32_ADDRESS_LENGTH: usize = 255;
+
+/// Address formats used by chains. These can be plugged into [`MockApi`](super::MockApi)
+/// and `cosmwasm_vm::testing::MockApi` in order to test contracts with realistic addresses
+/// instead of the default mock format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AddressCodec {
+    /// Bech32 addresses with the given prefix (human readable part) as used by Cosmos SDK
+    /// chains, e.g. `juno1...` for the prefix `juno`.
+    Bech32 { prefix: &'static str },
+    /// Hex encoded 20 byte addresses with `0x` prefix as used by EVM compatible chains.
+    /// Humanized addresses use the mixed case checksum encoding of
+    /// [EIP-55](https://eips.ethereum.org/EIPS/eip-55).
+    EvmHex,
+}
+
+impl AddressCodec {
+    /// Decodes a human readable address into its canonical form.
+    ///
+    /// For [`AddressCodec::EvmHex`], all lowercase and all uppercase addresses are accepted
+    /// as well as mixed case addresses with a valid checksum. Note that `addr_validate`
+    /// only accepts the normalized (i.e. checksummed) form.
+    pub fn canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        match self {
+            AddressCodec::Bech32 { prefix } => bech32_canonicalize(prefix, human),
+            AddressCodec::EvmHex => evm_canonicalize(human),
+        }
+    }
+
+    /// Encodes a canonical address into its normalized human readable form.
+    pub fn humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        match self {
+            AddressCodec::Bech32 { prefix } => bech32_humanize(prefix, canonical),
+            AddressCodec::EvmHex => evm_humanize(canonical),
+        }
+    }
+}
+
+fn bech32_canonicalize(prefix: &str, human: &str) -> StdResult<CanonicalAddr> {
+    let (hrp, data, variant) = bech32::decode(human)
+        .map_err(|e| StdError::generic_err(format!("Invalid input: {}", e)))?;
+    if hrp != prefix {
+        return Err(StdError::generic_err(format!(
+            "Invalid input: wrong bech32 prefix, expected {}",
+            prefix
+        )));
+    }
+    if variant != Variant::Bech32 {
+        return Err(StdError::generic_err(
+            "Invalid input: expected bech32 but got bech32m",
+        ));
+    }
+    let canonical = Vec::<u8>::from_base32(&data)
+        .map_err(|e| StdError::generic_err(format!("Invalid input: {}", e)))?;
+    check_bech32_length(&canonical)?;
+    Ok(canonical.into())
+}
+
+fn bech32_humanize(prefix: &str, canonical: &CanonicalAddr) -> StdResult<Addr> {
+    check_bech32_length(canonical.as_slice())?;
+    let human = bech32::encode(prefix, canonical.as_slice().to_base32(), Variant::Bech32)
+        .map_err(|e| StdError::generic_err(format!("Invalid input: {}", e)))?;
+    Ok(Addr::unchecked(human))
+}
+
+fn check_bech32_length(canonical: &[u8]) -> StdResult<()> {
+    if canonical.is_empty() || canonical.len() > MAX_BECH32_ADDRESS_LENGTH {
+        return Err(StdError::generic_err(
+            "Invalid input: canonical address length not correct",
+        ));
+    }
+    Ok(())
+}
+
+fn evm_canonicalize(human: &str) -> StdResult<CanonicalAddr> {
+    let digits = human
+        .strip_prefix("0x")
+        .ok_or_else(|| StdError::generic_err("Invalid input: address must start with 0x"))?;
+    if digits.len() != 2 * EVM_ADDRESS_LENGTH {
+        return Err(StdError::generic_err(
+            "Invalid input: address must contain 40 hex digits",
+        ));
+    }
+    let canonical = hex::decode(digits)
+        .map_err(|_| StdError::generic_err("Invalid input: address is not hex encoded"))?;
+
+    let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+        && digits.chars().any(|c| c.is_ascii_uppercase());
+    if is_mixed_case && digits != eip55_checksum_digits(&canonical) {
+        return Err(StdError::generic_err(
+            "Invalid input: address checksum not correct",
+        ));
+    }
+    Ok(canonical.into())
+}
+
+fn evm_humanize(canonical: &CanonicalAddr) -> StdResult<Addr> {
+    if canonical.len() != EVM_ADDRESS_LENGTH {
+        return Err(StdError::generic_err(
+            "Invalid input: canonical address length not correct",
+        ));
+    }
+    Ok(Addr::unchecked(format!(
+        "0x{}",
+        eip55_checksum_digits(canonical.as_slice())
+    )))
+}
+
+/// Returns the 40 hex digits of the address with EIP-55 mixed case checksum
+fn eip55_checksum_digits(address: &[u8]) -> String {
+    let lower = hex::encode(address);
+    let hash = Keccak256::digest(lower.as_bytes());
+    lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    const JUNO: AddressCodec = AddressCodec::Bech32 { prefix: "juno" };
+
+    #[test]
+    fn bech32_roundtrip_works() {
+        let canonical = CanonicalAddr::from(hex!("cd9cf4e5e7f9b2b1b7e3b9f0e8a7c1e0a2b3c4d5"));
+        let human = JUNO.humanize(&canonical).unwrap();
+        assert!(human.as_str().starts_with("juno1"));
+        assert_eq!(JUNO.canonicalize(human.as_str()).unwrap(), canonical);
+
+        // 32 byte addresses (e.g. contracts)
+        let canonical = CanonicalAddr::from([0xab; 32]);
+        let human = JUNO.humanize(&canonical).unwrap();
+        assert_eq!(JUNO.canonicalize(human.as_str()).unwrap(), canonical);
+    }
+
+    #[test]
+    fn bech32_canonicalize_works() {
+        // https://github.com/cosmos/cosmos-sdk/blob/v0.46.0/types/address_test.go
+        let cosmos = AddressCodec::Bech32 { prefix: "cosmos" };
+        let canonical = cosmos
+            .canonicalize("cosmos1fl48vsnmsdzcv85q5d2q4z5ajdha8yu34mf0eh")
+            .unwrap();
+        assert_eq!(
+            canonical.as_slice(),
+            hex!("4fea76427b8345861e80a3540a8a9d936fd39391")
+        );
+
+        // uppercase is a valid bech32 encoding of the same address
+        let upper = cosmos
+            .canonicalize("COSMOS1FL48VSNMSDZCV85Q5D2Q4Z5AJDHA8YU34MF0EH")
+            .unwrap();
+        assert_eq!(upper, canonical);
+    }
+
+    #[test]
+    fn bech32_canonicalize_fails_for_invalid_input() {
+        let cosmos = AddressCodec::Bech32 { prefix: "cosmos" };
+
+        // wrong prefix
+        let err = JUNO
+            .canonicalize("cosmos1fl48vsnmsdzcv85q5d2q4z5ajdha8yu34mf0eh")
+            .unwrap_err();
+        assert!(err.to_string().contains("wrong bech32 prefix"), "{}", err);
+
+        // wrong checksum
+        cosmos
+            .canonicalize("cosmos1fl48vsnmsdzcv85q5d2q4z5ajdha8yu34mf0ek")
+            .unwrap_err();
+
+        // not bech32 at all
+        cosmos.canonicalize("foobar").unwrap_err();
+        cosmos.canonicalize("").unwrap_err();
+
+        // bech32m
+        let bech32m = bech32::encode("cosmos", [0x12; 20].to_base32(), Variant::Bech32m).unwrap();
+        let err = cosmos.canonicalize(&bech32m).unwrap_err();
+        assert!(err.to_string().contains("bech32m"), "{}", err);
+    }
+
+    #[test]
+    fn bech32_humanize_fails_for_empty_address() {
+        JUNO.humanize(&CanonicalAddr::from(vec![])).unwrap_err();
+    }
+
+    #[test]
+    fn evm_humanize_works() {
+        // test vectors from https://eips.ethereum.org/EIPS/eip-55
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0xde709f2102306220921060314715629080e2fb77",
+        ] {
+            let canonical = CanonicalAddr::from(hex::decode(&address[2..]).unwrap());
+            let human = AddressCodec::EvmHex.humanize(&canonical).unwrap();
+            assert_eq!(human.as_str(), address);
+        }
+    }
+
+    #[test]
+    fn evm_canonicalize_works() {
+        let expected = hex!("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED",
+        ] {
+            let canonical = AddressCodec::EvmHex.canonicalize(address).unwrap();
+            assert_eq!(canonical.as_slice(), expected);
+        }
+    }
+
+    #[test]
+    fn evm_canonicalize_fails_for_invalid_input() {
+        // wrong checksum
+        let err = AddressCodec::EvmHex
+            .canonicalize("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD")
+            .unwrap_err();
+        assert!(err.to_string().contains("checksum"), "{}", err);
+
+        // missing prefix
+        AddressCodec::EvmHex
+            .canonicalize("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")
+            .unwrap_err();
+        // wrong length
+        AddressCodec::EvmHex
+            .canonicalize("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea")
+            .unwrap_err();
+        // not hex
+        AddressCodec::EvmHex
+            .canonicalize("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg")
+            .unwrap_err();
+    }
+
+    #[test]
+    fn evm_humanize_fails_for_wrong_length() {
+        AddressCodec::EvmHex
+            .humanize(&CanonicalAddr::from([0x12; 32]))
+            .unwrap_err();
+    }
+}
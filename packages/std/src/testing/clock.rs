@@ -0,0 +1,179 @@
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "stargate")]
+use crate::ibc::{IbcTimeout, IbcTimeoutBlock};
+use crate::types::{BlockInfo, Env};
+
+use super::mock::mock_env;
+
+/// The block time used by [`MockClock::advance_blocks`]
+pub const MOCK_BLOCK_TIME_SECONDS: u64 = 5;
+
+/// The current block of a test scenario, shared by all mocked subsystems that depend on it.
+///
+/// Clones of a clock share the same state, such that advancing time in one place is seen
+/// by the [`Env`]s created via [`MockClock::env`], the staking rewards simulated by
+/// [`MockQuerier::simulate_staking_rewards`](super::MockQuerier::simulate_staking_rewards)
+/// and the evaluation of IBC timeouts via [`MockClock::is_timed_out`].
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    block: Arc<RwLock<BlockInfo>>,
+}
+
+impl MockClock {
+    /// Creates a clock starting at the block of [`mock_env`]
+    pub fn new() -> Self {
+        Self::with_block(mock_env().block)
+    }
+
+    pub fn with_block(block: BlockInfo) -> Self {
+        MockClock {
+            block: Arc::new(RwLock::new(block)),
+        }
+    }
+
+    /// The current block
+    pub fn block(&self) -> BlockInfo {
+        self.block.read().unwrap().clone()
+    }
+
+    pub fn height(&self) -> u64 {
+        self.block.read().unwrap().height
+    }
+
+    /// Returns [`mock_env`] with the current block of this clock
+    pub fn env(&self) -> Env {
+        Env {
+            block: self.block(),
+            ..mock_env()
+        }
+    }
+
+    /// Advances the clock by the given number of blocks. Each block takes
+    /// [`MOCK_BLOCK_TIME_SECONDS`].
+    pub fn advance_blocks(&self, blocks: u64) {
+        let mut block = self.block.write().unwrap();
+        block.height += blocks;
+        block.time = block.time.plus_seconds(blocks * MOCK_BLOCK_TIME_SECONDS);
+    }
+
+    /// Advances the time of the current block without changing the height
+    pub fn advance_seconds(&self, seconds: u64) {
+        let mut block = self.block.write().unwrap();
+        block.time = block.time.plus_seconds(seconds);
+    }
+
+    /// Replaces the current block, e.g. to jump to a specific height or time
+    pub fn set_block(&self, block: BlockInfo) {
+        *self.block.write().unwrap() = block;
+    }
+
+    /// Returns true if a packet with the given timeout can no longer be received on this
+    /// chain, i.e. if the current block reached the timeout height or timestamp.
+    ///
+    /// The chain is assumed to be at IBC revision 1, like the timeouts of the mock IBC packets.
+    #[cfg(feature = "stargate")]
+    pub fn is_timed_out(&self, timeout: &IbcTimeout) -> bool {
+        let block = self.block();
+        let current = IbcTimeoutBlock {
+            revision: 1,
+            height: block.height,
+        };
+        let height_reached = timeout.block().map_or(false, |height| current >= height);
+        let time_reached = timeout
+            .timestamp()
+            .map_or(false, |timestamp| block.time >= timestamp);
+        height_reached || time_reached
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_starts_at_mock_env() {
+        let clock = MockClock::new();
+        assert_eq!(clock.block(), mock_env().block);
+        assert_eq!(clock.env(), mock_env());
+    }
+
+    #[test]
+    fn advance_blocks_works() {
+        let clock = MockClock::new();
+        let start = clock.block();
+        clock.advance_blocks(3);
+        assert_eq!(clock.height(), start.height + 3);
+        assert_eq!(clock.block().time, start.time.plus_seconds(15));
+        assert_eq!(clock.block().chain_id, start.chain_id);
+    }
+
+    #[test]
+    fn advance_seconds_works() {
+        let clock = MockClock::new();
+        let start = clock.block();
+        clock.advance_seconds(42);
+        assert_eq!(clock.height(), start.height);
+        assert_eq!(clock.block().time, start.time.plus_seconds(42));
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let clock = MockClock::new();
+        let other = clock.clone();
+        other.advance_blocks(1);
+        assert_eq!(clock.height(), mock_env().block.height + 1);
+        assert_eq!(clock.env().block, other.block());
+
+        let mut block = mock_env().block;
+        block.height = 1;
+        clock.set_block(block.clone());
+        assert_eq!(other.block(), block);
+    }
+
+    #[test]
+    #[cfg(feature = "stargate")]
+    fn is_timed_out_works() {
+        use crate::Timestamp;
+
+        let clock = MockClock::new();
+        let start = clock.block();
+        let at_height = |height| IbcTimeoutBlock {
+            revision: 1,
+            height,
+        };
+
+        let by_height = IbcTimeout::with_block(at_height(start.height + 2));
+        let by_time = IbcTimeout::with_timestamp(start.time.plus_seconds(6));
+        let both = IbcTimeout::with_both(at_height(start.height + 10), start.time.plus_seconds(6));
+        assert!(!clock.is_timed_out(&by_height));
+        assert!(!clock.is_timed_out(&by_time));
+        assert!(!clock.is_timed_out(&both));
+
+        clock.advance_blocks(1);
+        assert!(!clock.is_timed_out(&by_height));
+        assert!(!clock.is_timed_out(&by_time));
+
+        clock.advance_seconds(1);
+        assert!(!clock.is_timed_out(&by_height));
+        assert!(clock.is_timed_out(&by_time));
+        assert!(clock.is_timed_out(&both));
+
+        clock.advance_blocks(1);
+        assert!(clock.is_timed_out(&by_height));
+
+        // later revisions are never reached
+        let later_revision = IbcTimeout::with_block(IbcTimeoutBlock {
+            revision: 2,
+            height: 1,
+        });
+        assert!(!clock.is_timed_out(&later_revision));
+        assert!(!clock.is_timed_out(&IbcTimeout::with_timestamp(Timestamp::from_nanos(u64::MAX))));
+    }
+}
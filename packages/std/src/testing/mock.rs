@@ -15,6 +15,12 @@ use crate::ibc::{
     IbcEndpoint, IbcOrder, IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
     IbcTimeoutBlock,
 };
+#[cfg(feature = "stargate")]
+use crate::ibc_callbacks::{
+    IbcAckCallbackMsg, IbcDestinationCallbackMsg, IbcSourceCallbackMsg, IbcTimeoutCallbackMsg,
+};
+#[cfg(feature = "staking")]
+use crate::math::Decimal;
 use crate::math::Uint128;
 #[cfg(feature = "cosmwasm_1_1")]
 use crate::query::SupplyResponse;
@@ -34,6 +40,10 @@ use crate::traits::{Api, Querier, QuerierResult};
 use crate::types::{BlockInfo, ContractInfo, Env, MessageInfo, TransactionInfo};
 use crate::Attribute;
 
+use super::address_codec::AddressCodec;
+#[cfg(feature = "staking")]
+use super::clock::MockClock;
+
 pub const MOCK_CONTRACT_ADDR: &str = "cosmos2contract";
 
 /// Creates all external requirements that can be injected for unit tests.
@@ -91,12 +101,36 @@ pub struct MockApi {
     /// Length of canonical addresses created with this API. Contracts should not make any assumtions
     /// what this value is.
     canonical_length: usize,
+    /// When set, addresses are encoded in this format instead of the mock format
+    address_codec: Option<AddressCodec>,
+}
+
+impl MockApi {
+    /// Uses the given address format instead of the default mock format, e.g.
+    /// bech32 addresses with a chain specific prefix or EVM hex addresses.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use cosmwasm_std::Api;
+    /// # use cosmwasm_std::testing::{AddressCodec, MockApi};
+    /// let api = MockApi::default().with_address_codec(AddressCodec::EvmHex);
+    /// let addr = api.addr_validate("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap();
+    /// assert_eq!(api.addr_canonicalize(addr.as_str()).unwrap().len(), 20);
+    /// ```
+    pub fn with_address_codec(self, address_codec: AddressCodec) -> Self {
+        MockApi {
+            address_codec: Some(address_codec),
+            ..self
+        }
+    }
 }
 
 impl Default for MockApi {
     fn default() -> Self {
         MockApi {
             canonical_length: CANONICAL_LENGTH,
+            address_codec: None,
         }
     }
 }
@@ -115,6 +149,10 @@ impl Api for MockApi {
     }
 
     fn addr_canonicalize(&self, input: &str) -> StdResult<CanonicalAddr> {
+        if let Some(address_codec) = self.address_codec {
+            return address_codec.canonicalize(input);
+        }
+
         // Dummy input validation. This is more sophisticated for formats like bech32, where format and checksum are validated.
         if input.len() < 3 {
             return Err(StdError::generic_err(
@@ -145,6 +183,10 @@ impl Api for MockApi {
     }
 
     fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        if let Some(address_codec) = self.address_codec {
+            return address_codec.humanize(canonical);
+        }
+
         if canonical.len() != self.canonical_length {
             return Err(StdError::generic_err(
                 "Invalid input: canonical address length not correct",
@@ -179,6 +221,21 @@ impl Api for MockApi {
         )?)
     }
 
+    fn secp256k1_verify_prehashed(
+        &self,
+        digest: &[u8],
+        hash_function: u32,
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::secp256k1_verify_prehashed(
+            digest,
+            hash_function,
+            signature,
+            public_key,
+        )?)
+    }
+
     fn secp256k1_recover_pubkey(
         &self,
         message_hash: &[u8],
@@ -190,6 +247,30 @@ impl Api for MockApi {
         Ok(pubkey.to_vec())
     }
 
+    fn secp256r1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::secp256r1_verify(
+            message_hash,
+            signature,
+            public_key,
+        )?)
+    }
+
+    fn secp256r1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        let pubkey =
+            cosmwasm_crypto::secp256r1_recover_pubkey(message_hash, signature, recovery_param)?;
+        Ok(pubkey)
+    }
+
     fn ed25519_verify(
         &self,
         message: &[u8],
@@ -214,6 +295,105 @@ impl Api for MockApi {
         )?)
     }
 
+    fn poseidon_hash(&self, inputs: &[&[u8]]) -> Result<[u8; 32], VerificationError> {
+        Ok(cosmwasm_crypto::poseidon_hash(inputs)?)
+    }
+
+    fn zk_verify_groth16(
+        &self,
+        vk: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::zk_verify_groth16(
+            vk,
+            proof,
+            public_inputs,
+        )?)
+    }
+
+    fn tendermint_verify_header(
+        &self,
+        trusted_header: &[u8],
+        trusted_next_validators: &[u8],
+        untrusted_header: &[u8],
+        untrusted_validators: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::tendermint_verify_header(
+            trusted_header,
+            trusted_next_validators,
+            untrusted_header,
+            untrusted_validators,
+        )?)
+    }
+
+    fn bls12_381_aggregate_verify(
+        &self,
+        pubkeys: &[u8],
+        messages: &[&[u8]],
+        signature: &[u8],
+        dst: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::bls12_381_aggregate_verify(
+            pubkeys, messages, signature, dst,
+        )?)
+    }
+
+    fn bls12_381_pairing_equality(
+        &self,
+        ps: &[u8],
+        qs: &[u8],
+        r: &[u8],
+        s: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::bls12_381_pairing_equality(ps, qs, r, s)?)
+    }
+
+    fn bls12_381_hash_to_g1(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 48], VerificationError> {
+        Ok(cosmwasm_crypto::bls12_381_hash_to_g1(message, dst)?)
+    }
+
+    fn bls12_381_hash_to_g2(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 96], VerificationError> {
+        Ok(cosmwasm_crypto::bls12_381_hash_to_g2(message, dst)?)
+    }
+
+    fn ics23_verify_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::ics23_verify_membership(
+            spec, root, proof, key, value,
+        )?)
+    }
+
+    fn ics23_verify_non_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Ok(cosmwasm_crypto::ics23_verify_non_membership(
+            spec, root, proof, key,
+        )?)
+    }
+
+    fn ct_eq(&self, a: &[u8], b: &[u8]) -> bool {
+        cosmwasm_crypto::ct_eq(a, b)
+    }
+
     fn debug(&self, message: &str) {
         println!("{}", message);
     }
@@ -415,6 +595,164 @@ pub fn mock_ibc_packet_timeout(
     ))
 }
 
+/// Creates an acknowledgement IbcSourceCallbackMsg for testing ibc_source_callback, e.g. with
+/// the [`Ics20PacketData`](crate::Ics20PacketData) of a transfer sent by the contract.
+/// The packet is the same as in mock_ibc_packet_ack.
+#[cfg(feature = "stargate")]
+pub fn mock_ibc_source_callback_ack(
+    my_channel_id: &str,
+    data: &impl Serialize,
+    ack: IbcAcknowledgement,
+) -> StdResult<IbcSourceCallbackMsg> {
+    let packet = mock_ibc_packet(my_channel_id, data)?;
+    Ok(IbcSourceCallbackMsg::Acknowledgement(
+        IbcAckCallbackMsg::new(ack, packet, Addr::unchecked("relayer")),
+    ))
+}
+
+/// Creates a timeout IbcSourceCallbackMsg for testing ibc_source_callback.
+/// The packet is the same as in mock_ibc_packet_timeout.
+#[cfg(feature = "stargate")]
+pub fn mock_ibc_source_callback_timeout(
+    my_channel_id: &str,
+    data: &impl Serialize,
+) -> StdResult<IbcSourceCallbackMsg> {
+    let packet = mock_ibc_packet(my_channel_id, data)?;
+    Ok(IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg::new(
+        packet,
+        Addr::unchecked("relayer"),
+    )))
+}
+
+/// Creates an IbcDestinationCallbackMsg for testing ibc_destination_callback.
+/// The packet is the same as in mock_ibc_packet_recv.
+#[cfg(feature = "stargate")]
+pub fn mock_ibc_destination_callback(
+    my_channel_id: &str,
+    data: &impl Serialize,
+    ack: IbcAcknowledgement,
+) -> StdResult<IbcDestinationCallbackMsg> {
+    let packet = mock_ibc_packet_recv(my_channel_id, data)?.packet;
+    Ok(IbcDestinationCallbackMsg::new(packet, ack))
+}
+
+/// Tracks the packet sequences of mock IBC channels and creates packet messages
+/// with consecutive sequences, like a relayer would deliver them.
+///
+/// As on a real chain, the sequences of a channel start at 1 and the send and receive
+/// directions are counted independently. Gaps can be created with
+/// [`MockIbcChannelState::skip_recv_sequences`] and [`MockIbcChannelState::skip_send_sequences`],
+/// e.g. to simulate timed out packets on unordered channels.
+#[cfg(feature = "stargate")]
+#[derive(Clone, Debug, Default)]
+pub struct MockIbcChannelState {
+    channels: HashMap<String, MockIbcChannelSequences>,
+}
+
+#[cfg(feature = "stargate")]
+#[derive(Clone, Copy, Debug)]
+struct MockIbcChannelSequences {
+    next_send: u64,
+    next_recv: u64,
+}
+
+#[cfg(feature = "stargate")]
+impl Default for MockIbcChannelSequences {
+    fn default() -> Self {
+        MockIbcChannelSequences {
+            next_send: 1,
+            next_recv: 1,
+        }
+    }
+}
+
+#[cfg(feature = "stargate")]
+impl MockIbcChannelState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sequence of the next packet sent by the contract on the given channel
+    pub fn next_send_sequence(&self, my_channel_id: &str) -> u64 {
+        self.sequences(my_channel_id).next_send
+    }
+
+    /// The sequence of the next packet received by the contract on the given channel
+    pub fn next_recv_sequence(&self, my_channel_id: &str) -> u64 {
+        self.sequences(my_channel_id).next_recv
+    }
+
+    /// Skips `count` sequences of packets sent by the contract on the given channel
+    pub fn skip_send_sequences(&mut self, my_channel_id: &str, count: u64) {
+        self.sequences_mut(my_channel_id).next_send += count;
+    }
+
+    /// Skips `count` sequences of packets received by the contract on the given channel
+    pub fn skip_recv_sequences(&mut self, my_channel_id: &str, count: u64) {
+        self.sequences_mut(my_channel_id).next_recv += count;
+    }
+
+    /// Creates a IbcPacketReceiveMsg like [`mock_ibc_packet_recv`] with the next
+    /// receive sequence of the channel.
+    pub fn packet_recv(
+        &mut self,
+        my_channel_id: &str,
+        data: &impl Serialize,
+    ) -> StdResult<IbcPacketReceiveMsg> {
+        let mut msg = mock_ibc_packet_recv(my_channel_id, data)?;
+        msg.packet.sequence = self.take_recv_sequence(my_channel_id);
+        Ok(msg)
+    }
+
+    /// Creates a IbcPacketAckMsg like [`mock_ibc_packet_ack`] for the next
+    /// packet sent by the contract on the channel.
+    pub fn packet_ack(
+        &mut self,
+        my_channel_id: &str,
+        data: &impl Serialize,
+        ack: IbcAcknowledgement,
+    ) -> StdResult<IbcPacketAckMsg> {
+        let mut msg = mock_ibc_packet_ack(my_channel_id, data, ack)?;
+        msg.original_packet.sequence = self.take_send_sequence(my_channel_id);
+        Ok(msg)
+    }
+
+    /// Creates a IbcPacketTimeoutMsg like [`mock_ibc_packet_timeout`] for the next
+    /// packet sent by the contract on the channel.
+    pub fn packet_timeout(
+        &mut self,
+        my_channel_id: &str,
+        data: &impl Serialize,
+    ) -> StdResult<IbcPacketTimeoutMsg> {
+        let mut msg = mock_ibc_packet_timeout(my_channel_id, data)?;
+        msg.packet.sequence = self.take_send_sequence(my_channel_id);
+        Ok(msg)
+    }
+
+    fn sequences(&self, my_channel_id: &str) -> MockIbcChannelSequences {
+        self.channels
+            .get(my_channel_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn sequences_mut(&mut self, my_channel_id: &str) -> &mut MockIbcChannelSequences {
+        self.channels.entry(my_channel_id.to_string()).or_default()
+    }
+
+    fn take_send_sequence(&mut self, my_channel_id: &str) -> u64 {
+        let sequences = self.sequences_mut(my_channel_id);
+        sequences.next_send += 1;
+        sequences.next_send - 1
+    }
+
+    fn take_recv_sequence(&mut self, my_channel_id: &str) -> u64 {
+        let sequences = self.sequences_mut(my_channel_id);
+        sequences.next_recv += 1;
+        sequences.next_recv - 1
+    }
+}
+
 /// The same type as cosmwasm-std's QuerierResult, but easier to reuse in
 /// cosmwasm-vm. It might diverge from QuerierResult at some point.
 pub type MockQuerierCustomHandlerResult = SystemResult<ContractResult<Binary>>;
@@ -468,6 +806,13 @@ impl<C: DeserializeOwned> MockQuerier<C> {
         self.staking = StakingQuerier::new(denom, validators, delegations);
     }
 
+    /// Lets the accumulated rewards of all delegations grow with the height of the given clock.
+    /// See [`StakingQuerier::simulate_rewards`].
+    #[cfg(feature = "staking")]
+    pub fn simulate_staking_rewards(&mut self, clock: &MockClock, rate_per_block: Decimal) {
+        self.staking.simulate_rewards(clock, rate_per_block);
+    }
+
     pub fn update_wasm<WH: 'static>(&mut self, handler: WH)
     where
         WH: Fn(&WasmQuery) -> QuerierResult,
@@ -551,8 +896,17 @@ impl WasmQuerier {
 }
 
 impl Default for WasmQuerier {
+    /// By default, smart queries of [`MOCK_CONTRACT_ADDR`] are rejected as reentrant,
+    /// like the VM does. All other contracts do not exist.
     fn default() -> Self {
         let handler = Box::from(|request: &WasmQuery| -> QuerierResult {
+            if let WasmQuery::Smart { contract_addr, .. } = request {
+                if contract_addr == MOCK_CONTRACT_ADDR {
+                    return SystemResult::Err(SystemError::Reentrancy {
+                        contract_addr: contract_addr.clone(),
+                    });
+                }
+            }
             let addr = match request {
                 WasmQuery::Smart { contract_addr, .. } => contract_addr,
                 WasmQuery::Raw { contract_addr, .. } => contract_addr,
@@ -663,6 +1017,15 @@ pub struct StakingQuerier {
     denom: String,
     validators: Vec<Validator>,
     delegations: Vec<FullDelegation>,
+    rewards: Option<RewardSimulation>,
+}
+
+#[cfg(feature = "staking")]
+#[derive(Clone)]
+struct RewardSimulation {
+    clock: MockClock,
+    rate_per_block: Decimal,
+    start_height: u64,
 }
 
 #[cfg(feature = "staking")]
@@ -672,9 +1035,43 @@ impl StakingQuerier {
             denom: denom.to_string(),
             validators: validators.to_vec(),
             delegations: delegations.to_vec(),
+            rewards: None,
         }
     }
 
+    /// Simulates staking rewards starting at the current height of the given clock.
+    ///
+    /// For every block the clock advances, each delegation accumulates
+    /// `rate_per_block` times its delegated amount in rewards of the bonded denom,
+    /// on top of the `accumulated_rewards` it was created with.
+    pub fn simulate_rewards(&mut self, clock: &MockClock, rate_per_block: Decimal) {
+        self.rewards = Some(RewardSimulation {
+            clock: clock.clone(),
+            rate_per_block,
+            start_height: clock.height(),
+        });
+    }
+
+    fn with_rewards(&self, mut delegation: FullDelegation) -> FullDelegation {
+        if let Some(rewards) = &self.rewards {
+            let blocks = rewards.clock.height().saturating_sub(rewards.start_height);
+            let reward = delegation.amount.amount * rewards.rate_per_block * Uint128::from(blocks);
+            if !reward.is_zero() {
+                match delegation
+                    .accumulated_rewards
+                    .iter_mut()
+                    .find(|coin| coin.denom == self.denom)
+                {
+                    Some(coin) => coin.amount += reward,
+                    None => delegation
+                        .accumulated_rewards
+                        .push(Coin::new(reward.u128(), &self.denom)),
+                }
+            }
+        }
+        delegation
+    }
+
     pub fn query(&self, request: &StakingQuery) -> QuerierResult {
         let contract_result: ContractResult<Binary> = match request {
             StakingQuery::BondedDenom {} => {
@@ -718,7 +1115,7 @@ impl StakingQuerier {
                     .iter()
                     .find(|d| d.delegator.as_str() == delegator && d.validator == *validator);
                 let res = DelegationResponse {
-                    delegation: delegation.cloned(),
+                    delegation: delegation.cloned().map(|d| self.with_rewards(d)),
                 };
                 to_binary(&res).into()
             }
@@ -806,7 +1203,10 @@ pub fn mock_wasmd_attr(key: impl Into<String>, value: impl Into<String>) -> Attr
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{coin, coins, from_binary, to_binary, ContractInfoResponse, Response};
+    use crate::{
+        coin, coins, from_binary, to_binary, ContractInfoResponse, Response, HASH_FUNCTION_SHA256,
+        ICS23_SPEC_TENDERMINT,
+    };
     #[cfg(feature = "staking")]
     use crate::{Decimal, Delegation};
     use hex_literal::hex;
@@ -817,6 +1217,11 @@ mod tests {
     const SECP256K1_SIG_HEX: &str = "207082eb2c3dfa0b454e0906051270ba4074ac93760ba9e7110cd9471475111151eb0dbbc9920e72146fb564f99d039802bf6ef2561446eb126ef364d21ee9c4";
     const SECP256K1_PUBKEY_HEX: &str = "04051c1ee2190ecfb174bfe4f90763f2b4ff7517b70a2aec1876ebcfd644c4633fb03f3cfbd94b1f376e34592d9d41ccaf640bb751b00a1fadeb0c01157769eb73";
 
+    const SECP256R1_MSG_HASH_HEX: &str =
+        "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069";
+    const SECP256R1_SIG_HEX: &str = "ba45c3fbeade9e6c15979e7963fcd871a1c0183d53735f83c8ddd111a82730d6d7ef6299307bd722c7a15da66c657e182f9e73a9f69c70c97108940c0db099e0";
+    const SECP256R1_PUBKEY_HEX: &str = "04fdaebc61f24907a8e5ded06e01565bc6a0cfab998b80dc24ec4ad5b233347d2017c4a267e17c41a7b3db37e75ae9ea86800d55ba3735c8f30d5b019b1ad9353d";
+
     const ED25519_MSG_HEX: &str = "72";
     const ED25519_SIG_HEX: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
     const ED25519_PUBKEY_HEX: &str =
@@ -864,6 +1269,31 @@ mod tests {
         assert_eq!(data1, data2);
     }
 
+    #[test]
+    fn addr_canonicalize_with_length_works() {
+        let api = MockApi::default();
+
+        let canonical = api.addr_canonicalize_with_length("foobar123", 54).unwrap();
+        assert_eq!(canonical, api.addr_canonicalize("foobar123").unwrap());
+
+        match api
+            .addr_canonicalize_with_length("foobar123", 20)
+            .unwrap_err()
+        {
+            StdError::InvalidDataSize {
+                expected, actual, ..
+            } => {
+                assert_eq!(expected, 20);
+                assert_eq!(actual, 54);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // errors from addr_canonicalize are passed through
+        let err = api.addr_canonicalize_with_length("1", 54).unwrap_err();
+        assert!(matches!(err, StdError::GenericErr { .. }));
+    }
+
     #[test]
     fn canonicalize_and_humanize_restores_original() {
         let api = MockApi::default();
@@ -906,6 +1336,41 @@ mod tests {
         api.addr_humanize(&input).unwrap();
     }
 
+    #[test]
+    fn addr_validate_works_with_bech32_codec() {
+        let api = MockApi::default().with_address_codec(AddressCodec::Bech32 { prefix: "cosmos" });
+
+        let addr = "cosmos1fl48vsnmsdzcv85q5d2q4z5ajdha8yu34mf0eh";
+        assert_eq!(api.addr_validate(addr).unwrap(), addr);
+        let canonical = api.addr_canonicalize(addr).unwrap();
+        assert_eq!(canonical.len(), 20);
+        assert_eq!(api.addr_humanize(&canonical).unwrap(), addr);
+
+        // not normalized
+        api.addr_validate(&addr.to_uppercase()).unwrap_err();
+        // other chain
+        let juno = AddressCodec::Bech32 { prefix: "juno" }
+            .humanize(&canonical)
+            .unwrap();
+        api.addr_validate(juno.as_str()).unwrap_err();
+        // mock format
+        api.addr_validate("foobar123").unwrap_err();
+    }
+
+    #[test]
+    fn addr_validate_works_with_evm_codec() {
+        let api = MockApi::default().with_address_codec(AddressCodec::EvmHex);
+
+        let addr = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(api.addr_validate(addr).unwrap(), addr);
+        let canonical = api.addr_canonicalize(&addr.to_lowercase()).unwrap();
+        assert_eq!(api.addr_humanize(&canonical).unwrap(), addr);
+
+        // not checksummed
+        api.addr_validate(&addr.to_lowercase()).unwrap_err();
+        api.addr_validate("foobar123").unwrap_err();
+    }
+
     // Basic "works" test. Exhaustive tests on VM's side (packages/vm/src/imports.rs)
     #[test]
     fn secp256k1_verify_works() {
@@ -949,6 +1414,22 @@ mod tests {
         assert_eq!(res.unwrap_err(), VerificationError::InvalidPubkeyFormat);
     }
 
+    #[test]
+    fn secp256k1_verify_prehashed_works() {
+        let api = MockApi::default();
+
+        let hash = hex::decode(SECP256K1_MSG_HASH_HEX).unwrap();
+        let signature = hex::decode(SECP256K1_SIG_HEX).unwrap();
+        let public_key = hex::decode(SECP256K1_PUBKEY_HEX).unwrap();
+
+        assert!(api
+            .secp256k1_verify_prehashed(&hash, HASH_FUNCTION_SHA256, &signature, &public_key)
+            .unwrap());
+
+        let res = api.secp256k1_verify_prehashed(&hash, 0, &signature, &public_key);
+        assert_eq!(res.unwrap_err(), VerificationError::InvalidHashFunction);
+    }
+
     #[test]
     fn secp256k1_recover_pubkey_works() {
         let api = MockApi::default();
@@ -1017,6 +1498,48 @@ mod tests {
         }
     }
 
+    // Basic "works" test. Exhaustive tests on VM's side (packages/vm/src/imports.rs)
+    #[test]
+    fn secp256r1_verify_works() {
+        let api = MockApi::default();
+
+        let mut hash = hex::decode(SECP256R1_MSG_HASH_HEX).unwrap();
+        let signature = hex::decode(SECP256R1_SIG_HEX).unwrap();
+        let public_key = hex::decode(SECP256R1_PUBKEY_HEX).unwrap();
+
+        assert!(api
+            .secp256r1_verify(&hash, &signature, &public_key)
+            .unwrap());
+
+        // Wrong hash
+        hash[0] ^= 0x01;
+        assert!(!api
+            .secp256r1_verify(&hash, &signature, &public_key)
+            .unwrap());
+
+        // Malformed pubkey
+        let res = api.secp256r1_verify(&hash, &signature, &public_key[1..]);
+        assert_eq!(res.unwrap_err(), VerificationError::InvalidPubkeyFormat);
+    }
+
+    #[test]
+    fn secp256r1_recover_pubkey_works() {
+        let api = MockApi::default();
+
+        let hash = hex::decode(SECP256R1_MSG_HASH_HEX).unwrap();
+        let signature = hex::decode(SECP256R1_SIG_HEX).unwrap();
+        let expected = hex::decode(SECP256R1_PUBKEY_HEX).unwrap();
+
+        let pubkey = api.secp256r1_recover_pubkey(&hash, &signature, 0).unwrap();
+        assert_eq!(pubkey, expected);
+
+        let result = api.secp256r1_recover_pubkey(&hash, &signature, 2);
+        assert_eq!(
+            result.unwrap_err(),
+            RecoverPubkeyError::InvalidRecoveryParam
+        );
+    }
+
     // Basic "works" test. Exhaustive tests on VM's side (packages/vm/src/imports.rs)
     #[test]
     fn ed25519_verify_works() {
@@ -1111,6 +1634,200 @@ mod tests {
         assert_eq!(res.unwrap_err(), VerificationError::InvalidPubkeyFormat);
     }
 
+    #[test]
+    fn poseidon_hash_works() {
+        let api = MockApi::default();
+
+        let one = hex!("0000000000000000000000000000000000000000000000000000000000000001");
+        let two = hex!("0000000000000000000000000000000000000000000000000000000000000002");
+        let hash = api.poseidon_hash(&[&one, &two]).unwrap();
+        assert_eq!(
+            hash,
+            hex!("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a")
+        );
+    }
+
+    #[test]
+    fn poseidon_hash_errs() {
+        let api = MockApi::default();
+
+        // field modulus
+        let input = hex!("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001");
+        let res = api.poseidon_hash(&[&input]);
+        assert_eq!(res.unwrap_err(), VerificationError::InvalidHashInput);
+
+        let res = api.poseidon_hash(&[]);
+        assert_eq!(res.unwrap_err(), VerificationError::InvalidHashInput);
+    }
+
+    // Proof of knowledge of two factors of 33 (a * b = c with public c),
+    // generated with ark-groth16
+    const GROTH16_VK_HEX: &str = "04076665b8ea10d1f1088c2a5bc3fde6fa595c862b70ca7fb1a0d40e335a7e6d1add777c0a2833fc260f2d2efa5b83a1c573bae542a2fc70814d1f1247d342332a2a6503854c930b239693b403489ed1304008fa7d4367e370f25f633a9630ed1762ea18ff102a72b33fa1b145f5779fe9ceb1df0b4b4901f58b7d0abbf05438147b50868be5efb739888f132c1feb0c8f033781cfe15057f91213bde0f32df72a03b2a2637568b73ee71b3631ca82e5022933f13ee221338968bacf5d7fe0ea29aa2b10fbd31334cdb0ca4dfbcef670447aaef714f5d691dc1c49cd44129568221b1fc591601528e30eb7f2da312117378422d513021ef893a36152e62efb3e12cedf3d4637d61208117b8c0af66a5ddd29699536660253266f9e63ef26b2b91b86474753f31ae70e979c81f48b51ad43e43e42cfcf2a85c9cc85e79fcc83101f12aa8b550f4b2bcab999a2613480bf015410a18c93090aadfc520db8685eb8071671eb3a1c2c455de52368634685add354398fef807ff78586467100ecd0200add326cc141d0d9c752d6a8590d3f9123b67f32b8d7da5db189002e38de266c09c85676193171bb585953b1a7a2c94573aee4988c734e461c791b72facb8a3e126864fa09c9a802ecfde1b0db54f475e2341becf2ce4bb226533cc8ef2c03242246500ab9fd7578eaac0e9167dae9948f9b8d64f6ed59e4ff988a38006e02c60673104bd60d32774677c91aa5be53aa79a8ea18c8ef833b68032d6cf778a28b28ee3cba3c48edcffd6c354d0c96f7d3892bd68493835897ad12d9b49df4b81b";
+    const GROTH16_PROOF_HEX: &str = "230828863050148d0714fd793d1026bc9497f3e123a16334b127ff487d8a1ddc2d6926a6d63f0b78c6af0ae1898515a76f9db3c05703da06bc13e6643eab7dc72d1ed3b81a1287dd0d10691e7e461986115b919cc5b8f257ef5e98ce5f9c6ff72b27f2c8b9c12af71745a6587def58d614aeb6e9f7d98a5ddf466df2a3dc757610f2f06c5917c736a912ef2937a77cc43706eb633a7ef1a04121cdaf5651726023b5d84bebf24d9a2e174f58667b08f6db60c32a899803601d0bdb40a61a5512034838d658e5832b4ad43d67f092977eb68bac494d8c860d7b1211213e2b8a8416d26d2fc675c7854de03dbb4a1932be9a9febca09fcd3e90293ebcbb6b35788";
+    const GROTH16_INPUT_HEX: &str =
+        "0000000000000000000000000000000000000000000000000000000000000021";
+
+    #[test]
+    fn zk_verify_groth16_works() {
+        let api = MockApi::default();
+
+        let vk = hex::decode(GROTH16_VK_HEX).unwrap();
+        let proof = hex::decode(GROTH16_PROOF_HEX).unwrap();
+        let input = hex::decode(GROTH16_INPUT_HEX).unwrap();
+        assert!(api.zk_verify_groth16(&vk, &proof, &input).unwrap());
+
+        // 34
+        let mut input = input;
+        input[31] += 1;
+        assert!(!api.zk_verify_groth16(&vk, &proof, &input).unwrap());
+    }
+
+    #[test]
+    fn zk_verify_groth16_errs() {
+        let api = MockApi::default();
+
+        let vk = hex::decode(GROTH16_VK_HEX).unwrap();
+        let proof = hex::decode(GROTH16_PROOF_HEX).unwrap();
+        let res = api.zk_verify_groth16(&vk, &proof, &[]);
+        assert_eq!(res.unwrap_err(), VerificationError::Groth16Err);
+    }
+
+    #[test]
+    fn tendermint_verify_header_works() {
+        let api = MockApi::default();
+
+        // a chain with a single validator
+        let trusted_header = hex!("0a02080b120974657374696e672d31180a2206088ae2cfaa064220a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4974a20a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4975a156170702068617368206f662074686520626c6f636b");
+        let validators = hex!("0a3c0a1434750f98bd59fcfc946da45aaabe933be154a4b512220a208a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c180a");
+        let untrusted_header = hex!("0a740a02080b120974657374696e672d31180b2206088be2cfaa064220a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4974a20a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4975a156170702068617368206f662074686520626c6f636b12b201080b10011a480a200011289ad8eb5f811e66cf0d9bb5c04897d970310ec979c8a794c012e69ec1ed122408011220d887db09649dab0d83951d8d5d69b2e7d8bb70e79daa2a3a279b4fd6b8346cea22620802121434750f98bd59fcfc946da45aaabe933be154a4b51a06088be2cfaa06224075c59ecb3457e109c1a17bf80b48662e82af79c65b40cfcb4f646862b0585b9436c84e3fd57b6c7b10668ac70e557959edf819ef6dc9fbedd2aedb4c96eafe05");
+        assert!(api
+            .tendermint_verify_header(&trusted_header, &validators, &untrusted_header, &validators)
+            .unwrap());
+
+        // truncated validator set
+        let res = api.tendermint_verify_header(
+            &trusted_header,
+            &validators,
+            &untrusted_header,
+            &validators[..10],
+        );
+        assert_eq!(res.unwrap_err(), VerificationError::TendermintErr);
+    }
+
+    const BLS12_381_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+    #[test]
+    fn bls12_381_aggregate_verify_works() {
+        let api = MockApi::default();
+
+        // two keys signing the same message
+        let pubkey1 = hex!("86d4e01c75c1331328bda6bee6724fd4b25f25725e7b55d27ae17f257cfba31c75715ff595399b194cb0fa68b890a8d2");
+        let pubkey2 = hex!("aabf0ec575019e6c8280db3c675e7981bb55275716154a9c74a84739ccc6b539397f88627915d24da0536a52547efcac");
+        let signature = hex!("986228fe79837c7d35ddf628345cae60689eb300d3bd7038cbbf7fc304ae4d92ed23b57408816f9bdda5a6e224a7b27214086d80275564fc8ae531a6f664f112b8663f2d3e1c798253ba1fc26be71d35646a1bb0fa1ec73c32380df8b1676c4e");
+        let pubkeys = [pubkey1, pubkey2].concat();
+        assert!(api
+            .bls12_381_aggregate_verify(&pubkeys, &[b"hello bls"], &signature, BLS12_381_DST)
+            .unwrap());
+        assert!(!api
+            .bls12_381_aggregate_verify(&pubkeys, &[b"hello BLS"], &signature, BLS12_381_DST)
+            .unwrap());
+        assert!(!api
+            .bls12_381_aggregate_verify(&pubkey1, &[b"hello bls"], &signature, BLS12_381_DST)
+            .unwrap());
+
+        let res = api.bls12_381_aggregate_verify(
+            &pubkeys[1..],
+            &[b"hello bls"],
+            &signature,
+            BLS12_381_DST,
+        );
+        assert_eq!(res.unwrap_err(), VerificationError::InvalidPubkeyFormat);
+        let res = api.bls12_381_aggregate_verify(&pubkeys, &[b"hello bls"], &signature, b"");
+        assert_eq!(res.unwrap_err(), VerificationError::Bls12381Err);
+    }
+
+    #[test]
+    fn bls12_381_pairing_equality_works() {
+        let api = MockApi::default();
+
+        let g1 = hex!("97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb");
+        let g2 = hex!("93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8");
+        let other_g1 = hex!("86d4e01c75c1331328bda6bee6724fd4b25f25725e7b55d27ae17f257cfba31c75715ff595399b194cb0fa68b890a8d2");
+        assert!(api.bls12_381_pairing_equality(&g1, &g2, &g1, &g2).unwrap());
+        assert!(!api
+            .bls12_381_pairing_equality(&other_g1, &g2, &g1, &g2)
+            .unwrap());
+
+        let res = api.bls12_381_pairing_equality(&g1, &g2[1..], &g1, &g2);
+        assert_eq!(res.unwrap_err(), VerificationError::Bls12381Err);
+    }
+
+    #[test]
+    fn bls12_381_hash_to_curve_works() {
+        let api = MockApi::default();
+
+        // test vectors from RFC 9380, appendix J.9.1 and J.10.1
+        let point = api
+            .bls12_381_hash_to_g1(
+                b"abc",
+                b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_",
+            )
+            .unwrap();
+        assert_eq!(point, hex!("83567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903"));
+        let point = api
+            .bls12_381_hash_to_g2(
+                b"abc",
+                b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_",
+            )
+            .unwrap();
+        assert_eq!(point, hex!("939cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6"));
+
+        let res = api.bls12_381_hash_to_g1(b"abc", b"");
+        assert_eq!(res.unwrap_err(), VerificationError::Bls12381Err);
+    }
+
+    #[test]
+    fn ics23_verify_membership_works() {
+        let api = MockApi::default();
+
+        // a tree with the single leaf foo -> bar
+        let root = hex!("2d6e9a3e3928b84ea41ebc047c06d8b416d5855983a04921bd48adde9c4aa714");
+        let proof = hex!("0a150a03666f6f12036261721a090801180120012a0100");
+        assert!(api
+            .ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, &proof, b"foo", b"bar")
+            .unwrap());
+        assert!(!api
+            .ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, &proof, b"foo", b"baz")
+            .unwrap());
+
+        let res =
+            api.ics23_verify_membership(ICS23_SPEC_TENDERMINT, &root, &[0x0a], b"foo", b"bar");
+        assert_eq!(res.unwrap_err(), VerificationError::Ics23Err);
+    }
+
+    #[test]
+    fn ics23_verify_non_membership_works() {
+        let api = MockApi::default();
+
+        let root = hex!("2d6e9a3e3928b84ea41ebc047c06d8b416d5855983a04921bd48adde9c4aa714");
+        let proof = hex!("121c0a03676f6f12150a03666f6f12036261721a090801180120012a0100");
+        assert!(api
+            .ics23_verify_non_membership(ICS23_SPEC_TENDERMINT, &root, &proof, b"goo")
+            .unwrap());
+        assert!(!api
+            .ics23_verify_non_membership(ICS23_SPEC_TENDERMINT, &root, &proof, b"eoo")
+            .unwrap());
+    }
+
+    #[test]
+    fn ct_eq_works() {
+        let api = MockApi::default();
+
+        assert!(api.ct_eq(b"commitment", b"commitment"));
+        assert!(!api.ct_eq(b"commitment", b"commitmenT"));
+        assert!(!api.ct_eq(b"commitment", b"commit"));
+    }
+
     #[cfg(feature = "cosmwasm_1_1")]
     #[test]
     fn bank_querier_supply() {
@@ -1417,6 +2134,44 @@ mod tests {
         assert_eq!(dels, Some(del2c));
     }
 
+    #[cfg(feature = "staking")]
+    #[test]
+    fn staking_querier_simulates_rewards() {
+        let delegator = Addr::unchecked("investor");
+        let del1 = FullDelegation {
+            delegator: delegator.clone(),
+            validator: "validator-one".to_string(),
+            amount: coin(1000, "ustake"),
+            can_redelegate: coin(1000, "ustake"),
+            accumulated_rewards: coins(5, "ustake"),
+        };
+        let del2 = FullDelegation {
+            delegator: delegator.clone(),
+            validator: "validator-two".to_string(),
+            amount: coin(300, "ustake"),
+            can_redelegate: coin(300, "ustake"),
+            accumulated_rewards: vec![],
+        };
+        let clock = MockClock::new();
+        let mut staking = StakingQuerier::new("ustake", &[], &[del1.clone(), del2.clone()]);
+        staking.simulate_rewards(&clock, Decimal::percent(1));
+
+        // nothing happens until the clock advances
+        let dels = get_delegator(&staking, delegator.clone(), "validator-one");
+        assert_eq!(dels, Some(del1.clone()));
+
+        clock.advance_blocks(3);
+        let dels = get_delegator(&staking, delegator.clone(), "validator-one").unwrap();
+        assert_eq!(dels.accumulated_rewards, coins(35, "ustake"));
+        let dels = get_delegator(&staking, delegator.clone(), "validator-two").unwrap();
+        assert_eq!(dels.accumulated_rewards, coins(9, "ustake"));
+
+        // time alone does not produce rewards
+        clock.advance_seconds(3600);
+        let dels = get_delegator(&staking, delegator, "validator-one").unwrap();
+        assert_eq!(dels.accumulated_rewards, coins(35, "ustake"));
+    }
+
     #[test]
     fn wasm_querier_works() {
         let mut querier = WasmQuerier::default();
@@ -1458,6 +2213,20 @@ mod tests {
             err => panic!("Unexpected error: {:?}", err),
         }
 
+        // Smart queries of the contract itself are reentrant
+        let system_err = querier
+            .query(&WasmQuery::Smart {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                msg: b"{}".into(),
+            })
+            .unwrap_err();
+        match system_err {
+            SystemError::Reentrancy { contract_addr } => {
+                assert_eq!(contract_addr, MOCK_CONTRACT_ADDR)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
         querier.update_handler(|request| {
             let constract1 = Addr::unchecked("contract1");
             let mut storage1 = HashMap::<Binary, Binary>::default();
@@ -1616,4 +2385,75 @@ mod tests {
 
         assert_eq!(digit_sum(&[255, 1]), 256);
     }
+
+    #[test]
+    #[cfg(feature = "stargate")]
+    fn mock_ibc_callbacks_work() {
+        let data = crate::Ics20PacketData {
+            denom: "ujuno".to_string(),
+            amount: crate::Uint256::from(100u128),
+            sender: MOCK_CONTRACT_ADDR.to_string(),
+            receiver: "cosmos1receiver".to_string(),
+            memo: None,
+        };
+
+        let ack = IbcAcknowledgement::new(br#"{"result":"AQ=="}"#);
+        let msg = mock_ibc_source_callback_ack("channel-0", &data, ack.clone()).unwrap();
+        assert_eq!(msg.packet().src.channel_id, "channel-0");
+        assert_eq!(msg.ics20_packet_data().unwrap(), data);
+        match msg {
+            IbcSourceCallbackMsg::Acknowledgement(msg) => assert_eq!(msg.acknowledgement, ack),
+            _ => panic!("expected acknowledgement"),
+        }
+
+        let msg = mock_ibc_source_callback_timeout("channel-0", &data).unwrap();
+        assert!(matches!(msg, IbcSourceCallbackMsg::Timeout(_)));
+        assert_eq!(msg.ics20_packet_data().unwrap(), data);
+
+        let msg = mock_ibc_destination_callback("channel-7", &data, ack.clone()).unwrap();
+        assert_eq!(msg.packet.dest.channel_id, "channel-7");
+        assert_eq!(msg.ack, ack);
+        assert_eq!(msg.ics20_packet_data().unwrap(), data);
+    }
+
+    #[test]
+    #[cfg(feature = "stargate")]
+    fn mock_ibc_channel_state_tracks_sequences() {
+        let mut state = MockIbcChannelState::new();
+        assert_eq!(state.next_send_sequence("channel-1"), 1);
+        assert_eq!(state.next_recv_sequence("channel-1"), 1);
+
+        let recv1 = state.packet_recv("channel-1", &"foo").unwrap();
+        let recv2 = state.packet_recv("channel-1", &"bar").unwrap();
+        assert_eq!(recv1.packet.sequence, 1);
+        assert_eq!(recv2.packet.sequence, 2);
+        assert_eq!(recv2.packet.dest.channel_id, "channel-1");
+
+        // send and receive sequences are independent, as are channels
+        let ack = state
+            .packet_ack("channel-1", &"foo", IbcAcknowledgement::new(b"ok"))
+            .unwrap();
+        assert_eq!(ack.original_packet.sequence, 1);
+        let timeout = state.packet_timeout("channel-1", &"foo").unwrap();
+        assert_eq!(timeout.packet.sequence, 2);
+        let other = state.packet_recv("channel-2", &"foo").unwrap();
+        assert_eq!(other.packet.sequence, 1);
+
+        assert_eq!(state.next_send_sequence("channel-1"), 3);
+        assert_eq!(state.next_recv_sequence("channel-1"), 3);
+        assert_eq!(state.next_recv_sequence("channel-2"), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "stargate")]
+    fn mock_ibc_channel_state_can_skip_sequences() {
+        let mut state = MockIbcChannelState::new();
+        state.skip_recv_sequences("channel-1", 3);
+        state.skip_send_sequences("channel-1", 10);
+
+        let recv = state.packet_recv("channel-1", &"foo").unwrap();
+        assert_eq!(recv.packet.sequence, 4);
+        let timeout = state.packet_timeout("channel-1", &"foo").unwrap();
+        assert_eq!(timeout.packet.sequence, 11);
+    }
 }
@@ -3,10 +3,14 @@
 // Exposed for testing only
 // Both unit tests and integration tests are compiled to native code, so everything in here does not need to compile to Wasm.
 
+mod address_codec;
 mod assertions;
+mod clock;
 mod mock;
 
+pub use address_codec::AddressCodec;
 pub use assertions::assert_approx_eq_impl;
+pub use clock::{MockClock, MOCK_BLOCK_TIME_SECONDS};
 
 #[cfg(feature = "staking")]
 pub use mock::StakingQuerier;
@@ -19,5 +23,7 @@ pub use mock::{
 pub use mock::{
     mock_ibc_channel, mock_ibc_channel_close_confirm, mock_ibc_channel_close_init,
     mock_ibc_channel_connect_ack, mock_ibc_channel_connect_confirm, mock_ibc_channel_open_init,
-    mock_ibc_channel_open_try, mock_ibc_packet_ack, mock_ibc_packet_recv, mock_ibc_packet_timeout,
+    mock_ibc_channel_open_try, mock_ibc_destination_callback, mock_ibc_packet_ack,
+    mock_ibc_packet_recv, mock_ibc_packet_timeout, mock_ibc_source_callback_ack,
+    mock_ibc_source_callback_timeout, MockIbcChannelState,
 };
@@ -22,6 +22,18 @@ use crate::results::{ContractResult, Empty, SystemResult};
 use crate::serde::{from_binary, to_binary, to_vec};
 use crate::ContractInfoResponse;
 
+/// Identifier of SHA-256 for [`Api::secp256k1_verify_prehashed`].
+pub const HASH_FUNCTION_SHA256: u32 = 1;
+/// Identifier of Keccak-256 (as used by Ethereum) for [`Api::secp256k1_verify_prehashed`].
+pub const HASH_FUNCTION_KECCAK256: u32 = 2;
+
+/// Identifier of the ICS-23 proof spec of IAVL trees (Cosmos SDK multistore substores) for
+/// [`Api::ics23_verify_membership`] and [`Api::ics23_verify_non_membership`].
+pub const ICS23_SPEC_IAVL: u32 = 1;
+/// Identifier of the ICS-23 proof spec of Tendermint simple Merkle trees (Cosmos SDK multistore
+/// root) for [`Api::ics23_verify_membership`] and [`Api::ics23_verify_non_membership`].
+pub const ICS23_SPEC_TENDERMINT: u32 = 2;
+
 /// Storage provides read and write access to a persistent storage.
 /// If you only want to provide read access, provide `&Storage`
 pub trait Storage {
@@ -45,6 +57,22 @@ pub trait Storage {
         order: Order,
     ) -> Box<dyn Iterator<Item = Record> + 'a>;
 
+    #[cfg(feature = "iterator")]
+    /// Like [`Storage::range`] but returns at most `limit` key/value pairs.
+    ///
+    /// Implementations can push the limit down into the underlying database iterator, which
+    /// avoids loading elements that are never used. In contracts this requires the
+    /// `cosmwasm_1_2` feature, otherwise the limit is only applied in the contract.
+    fn range_with_limit<'a>(
+        &'a self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+    ) -> Box<dyn Iterator<Item = Record> + 'a> {
+        Box::new(self.range(start, end, order).take(limit as usize))
+    }
+
     fn set(&mut self, key: &[u8], value: &[u8]);
 
     /// Removes a database entry at `key`.
@@ -68,6 +96,8 @@ pub trait Storage {
 ///
 /// We can use feature flags to opt-in to non-essential methods
 /// for backwards compatibility in systems that don't have them all.
+/// Methods that were added later have default implementations returning
+/// an unknown error, such that existing implementors keep compiling.
 pub trait Api {
     /// Takes a human readable address and validates if it is valid.
     /// If it the validation succeeds, a `Addr` containing the same data as the input is returned.
@@ -102,6 +132,34 @@ pub trait Api {
     /// But that might change. So your contract should not make assumptions on the size.
     fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr>;
 
+    /// Like [`addr_canonicalize`] but ensures the resulting canonical address has the
+    /// given length in bytes. Use this if your contract relies on a fixed address size,
+    /// e.g. when converting to an array using [`CanonicalAddr::to_array`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use cosmwasm_std::Api;
+    /// # use cosmwasm_std::testing::MockApi;
+    /// # let api = MockApi::default();
+    /// let canonical = api.addr_canonicalize_with_length("foobar123", 54).unwrap();
+    /// assert_eq!(canonical.len(), 54);
+    /// assert!(api.addr_canonicalize_with_length("foobar123", 20).is_err());
+    /// ```
+    ///
+    /// [`addr_canonicalize`]: Api::addr_canonicalize
+    fn addr_canonicalize_with_length(
+        &self,
+        human: &str,
+        length: usize,
+    ) -> StdResult<CanonicalAddr> {
+        let canonical = self.addr_canonicalize(human)?;
+        if canonical.len() != length {
+            return Err(StdError::invalid_data_size(length, canonical.len()));
+        }
+        Ok(canonical)
+    }
+
     /// Takes a canonical address and returns a human readble address.
     /// This is the inverse of [`addr_canonicalize`].
     ///
@@ -115,6 +173,25 @@ pub trait Api {
         public_key: &[u8],
     ) -> Result<bool, VerificationError>;
 
+    /// Like [`secp256k1_verify`], but for a 32 byte `digest` produced by the hash function
+    /// with the identifier `hash_function` ([`HASH_FUNCTION_SHA256`] or [`HASH_FUNCTION_KECCAK256`]).
+    /// The digest is verified as is and never hashed again. Unknown identifiers result in
+    /// [`VerificationError::InvalidHashFunction`].
+    ///
+    /// In contracts, this requires the `cosmwasm_1_2` feature.
+    ///
+    /// [`secp256k1_verify`]: Api::secp256k1_verify
+    #[allow(unused_variables)]
+    fn secp256k1_verify_prehashed(
+        &self,
+        digest: &[u8],
+        hash_function: u32,
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
     fn secp256k1_recover_pubkey(
         &self,
         message_hash: &[u8],
@@ -122,6 +199,40 @@ pub trait Api {
         recovery_param: u8,
     ) -> Result<Vec<u8>, RecoverPubkeyError>;
 
+    /// Like [`secp256k1_verify`], but for the secp256r1 curve (also known as P-256 or prime256v1),
+    /// which is used e.g. by passkeys (WebAuthn) and hardware security modules.
+    ///
+    /// In contracts, this requires the `secp256r1` feature and a chain that supports the
+    /// `secp256r1` capability.
+    ///
+    /// [`secp256k1_verify`]: Api::secp256k1_verify
+    #[allow(unused_variables)]
+    fn secp256r1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Like [`secp256k1_recover_pubkey`], but for the secp256r1 curve.
+    /// Returns the recovered public key in uncompressed form.
+    ///
+    /// In contracts, this requires the `secp256r1` feature and a chain that supports the
+    /// `secp256r1` capability.
+    ///
+    /// [`secp256k1_recover_pubkey`]: Api::secp256k1_recover_pubkey
+    #[allow(unused_variables)]
+    fn secp256r1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        Err(RecoverPubkeyError::unknown_err(0))
+    }
+
     fn ed25519_verify(
         &self,
         message: &[u8],
@@ -136,6 +247,170 @@ pub trait Api {
         public_keys: &[&[u8]],
     ) -> Result<bool, VerificationError>;
 
+    /// Calculates the circomlib compatible Poseidon hash of 1 to 16 elements of the BN254
+    /// scalar field. Inputs and output are 32 byte big-endian encoded field elements.
+    ///
+    /// In contracts, this requires the `zk` feature and a chain that supports the `zk` capability.
+    #[allow(unused_variables)]
+    fn poseidon_hash(&self, inputs: &[&[u8]]) -> Result<[u8; 32], VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Verifies a Groth16 proof over the BN254 curve. All inputs use the encoding of the
+    /// Ethereum precompiles (EIP-196/EIP-197) as produced by snarkjs:
+    ///
+    /// - `vk`: `alpha (G1) || beta (G2) || gamma (G2) || delta (G2) || IC (G1 * (n + 1))`
+    /// - `proof`: `A (G1) || B (G2) || C (G1)`
+    /// - `public_inputs`: `n` concatenated 32 byte big-endian scalar field elements
+    ///
+    /// In contracts, this requires the `zk` feature and a chain that supports the `zk` capability.
+    #[allow(unused_variables)]
+    fn zk_verify_groth16(
+        &self,
+        vk: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Verifies an untrusted Tendermint header against a trusted one, following the skipping
+    /// verification of the Tendermint light client with a trust level of 1/3. All inputs are
+    /// protobuf encoded `tendermint.types` messages:
+    ///
+    /// - `trusted_header`: the `Header` verified before
+    /// - `trusted_next_validators`: the `ValidatorSet` the trusted header commits to in
+    ///   `next_validators_hash`
+    /// - `untrusted_header`: the `SignedHeader` to verify
+    /// - `untrusted_validators`: the `ValidatorSet` of the untrusted header
+    ///
+    /// Returns `Ok(false)` if the untrusted header cannot be trusted and
+    /// [`VerificationError::TendermintErr`] for malformed inputs. The trusting period is not
+    /// checked, contracts need to compare the time of the trusted header with the block time.
+    ///
+    /// In contracts, this requires the `tendermint` feature and a chain that supports the
+    /// `tendermint` capability.
+    #[allow(unused_variables)]
+    fn tendermint_verify_header(
+        &self,
+        trusted_header: &[u8],
+        trusted_next_validators: &[u8],
+        untrusted_header: &[u8],
+        untrusted_validators: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Verifies a BLS12-381 aggregate signature in the "minimal pubkey size" variant, i.e.
+    /// with public keys in G1 and signatures in G2. `pubkeys` is the concatenation of
+    /// compressed public keys (48 bytes each) and `signature` is a compressed G2 point (96 bytes).
+    /// `messages` contains either a single message signed by all keys or one message per key.
+    /// `dst` is the domain separation tag used to hash the messages to G2, e.g.
+    /// `BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_`.
+    ///
+    /// In contracts, this requires the `bls12_381` feature and a chain that supports the
+    /// `bls12_381` capability.
+    #[allow(unused_variables)]
+    fn bls12_381_aggregate_verify(
+        &self,
+        pubkeys: &[u8],
+        messages: &[&[u8]],
+        signature: &[u8],
+        dst: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Checks the BLS12-381 pairing equation `e(p_1, q_1) * ... * e(p_n, q_n) == e(r, s)`.
+    /// `ps` and `r` are compressed G1 points (48 bytes each), `qs` and `s` are compressed
+    /// G2 points (96 bytes each). `ps` and `qs` must contain the same number of points.
+    ///
+    /// In contracts, this requires the `bls12_381` feature and a chain that supports the
+    /// `bls12_381` capability.
+    #[allow(unused_variables)]
+    fn bls12_381_pairing_equality(
+        &self,
+        ps: &[u8],
+        qs: &[u8],
+        r: &[u8],
+        s: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Hashes a message to a compressed BLS12-381 G1 point as specified in RFC 9380,
+    /// using the `BLS12381G1_XMD:SHA-256_SSWU_RO_` suite with the given domain separation tag.
+    ///
+    /// In contracts, this requires the `bls12_381` feature and a chain that supports the
+    /// `bls12_381` capability.
+    #[allow(unused_variables)]
+    fn bls12_381_hash_to_g1(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 48], VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Like [`bls12_381_hash_to_g1`](Api::bls12_381_hash_to_g1), but hashes to a compressed
+    /// G2 point using the `BLS12381G2_XMD:SHA-256_SSWU_RO_` suite.
+    ///
+    /// In contracts, this requires the `bls12_381` feature and a chain that supports the
+    /// `bls12_381` capability.
+    #[allow(unused_variables)]
+    fn bls12_381_hash_to_g2(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<[u8; 96], VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Verifies that the protobuf encoded ICS-23 `CommitmentProof` proves the existence of
+    /// `key` with `value` in the tree with the given commitment `root`. `spec` is the identifier
+    /// of the tree's proof spec ([`ICS23_SPEC_IAVL`] or [`ICS23_SPEC_TENDERMINT`]).
+    ///
+    /// Returns `Ok(false)` if the proof is well-formed but does not prove the claim and
+    /// [`VerificationError::Ics23Err`] for malformed proofs and unknown specs.
+    ///
+    /// In contracts, this requires the `cosmwasm_1_2` feature.
+    #[allow(unused_variables)]
+    fn ics23_verify_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Like [`ics23_verify_membership`], but verifies that the proof proves the absence of `key`.
+    ///
+    /// In contracts, this requires the `cosmwasm_1_2` feature.
+    ///
+    /// [`ics23_verify_membership`]: Api::ics23_verify_membership
+    #[allow(unused_variables)]
+    fn ics23_verify_non_membership(
+        &self,
+        spec: u32,
+        root: &[u8],
+        proof: &[u8],
+        key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        Err(VerificationError::unknown_err(0))
+    }
+
+    /// Checks two byte strings for equality in constant time, i.e. the time needed does not
+    /// depend on the contents of the inputs. Use this instead of `==` when comparing secrets,
+    /// e.g. the preimage of a commitment.
+    ///
+    /// In contracts, this requires the `cosmwasm_1_2` feature.
+    fn ct_eq(&self, a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
     /// Emits a debugging message that is handled depending on the environment (typically printed to console or ignored).
     /// Those messages are not persisted to chain.
     fn debug(&self, message: &str);
@@ -368,6 +643,73 @@ mod tests {
         2
     }
 
+    /// An Api that only implements the required methods
+    struct MinimalApi;
+
+    impl Api for MinimalApi {
+        fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+            Ok(Addr::unchecked(human))
+        }
+        fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+            Ok(human.as_bytes().into())
+        }
+        fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+            Ok(Addr::unchecked(String::from_utf8(canonical.to_vec())?))
+        }
+        fn secp256k1_verify(
+            &self,
+            _: &[u8],
+            _: &[u8],
+            _: &[u8],
+        ) -> Result<bool, VerificationError> {
+            Ok(false)
+        }
+        fn secp256k1_recover_pubkey(
+            &self,
+            _: &[u8],
+            _: &[u8],
+            _: u8,
+        ) -> Result<Vec<u8>, RecoverPubkeyError> {
+            Ok(vec![])
+        }
+        fn ed25519_verify(&self, _: &[u8], _: &[u8], _: &[u8]) -> Result<bool, VerificationError> {
+            Ok(false)
+        }
+        fn ed25519_batch_verify(
+            &self,
+            _: &[&[u8]],
+            _: &[&[u8]],
+            _: &[&[u8]],
+        ) -> Result<bool, VerificationError> {
+            Ok(false)
+        }
+        fn debug(&self, _: &str) {}
+    }
+
+    #[test]
+    fn api_default_methods_do_not_panic() {
+        let api = MinimalApi;
+        let unknown = VerificationError::unknown_err(0);
+        assert_eq!(api.secp256r1_verify(b"", b"", b"").unwrap_err(), unknown);
+        assert_eq!(
+            api.secp256r1_recover_pubkey(b"", b"", 0).unwrap_err(),
+            RecoverPubkeyError::unknown_err(0)
+        );
+        assert_eq!(api.poseidon_hash(&[b"a"]).unwrap_err(), unknown);
+        assert_eq!(api.zk_verify_groth16(b"", b"", b"").unwrap_err(), unknown);
+        assert_eq!(api.bls12_381_hash_to_g1(b"", b"").unwrap_err(), unknown);
+        assert_eq!(
+            api.ics23_verify_non_membership(0, b"", b"", b"")
+                .unwrap_err(),
+            unknown
+        );
+
+        assert!(api.ct_eq(b"secret", b"secret"));
+        assert!(!api.ct_eq(b"secret", b"secreT"));
+        assert!(!api.ct_eq(b"secret", b"secret!"));
+        assert!(api.ct_eq(b"", b""));
+    }
+
     // this just needs to compile to prove we can use it
     #[test]
     fn use_querier_wrapper_as_querier() {
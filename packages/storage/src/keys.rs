@@ -0,0 +1,391 @@
+//! Order preserving encodings for storage keys.
+//!
+//! All encodings in here are self-delimiting and preserve the natural ordering of the
+//! values in the byte-wise ordering of the encoded keys. This makes it possible to combine
+//! multiple components into composite keys (tuples) and to do range queries over them.
+//!
+//! - Unsigned integers are encoded as fixed size big endian values.
+//! - Signed integers are encoded as fixed size big endian values with the sign bit flipped,
+//!   such that negative values sort before positive ones.
+//! - Strings, addresses and byte vectors are encoded with every 0x00 byte escaped as
+//!   0x00 0xFF, followed by the terminator 0x00 0x00.
+//! - Tuples are encoded as the concatenation of their components. The encoding of the
+//!   first component(s) of a tuple is a prefix of the encoding of the tuple, which can be
+//!   used to iterate over all keys sharing those components.
+
+use std::any::type_name;
+
+use cosmwasm_std::{Addr, StdError, StdResult};
+
+/// A value that can be encoded as (part of) a storage key
+pub trait EncodeKey {
+    /// Appends the encoding of this value to `out`
+    fn encode_key_into(&self, out: &mut Vec<u8>);
+}
+
+/// A value that can be decoded from (part of) a storage key
+pub trait DecodeKey: Sized {
+    /// Decodes a value from the beginning of `data`.
+    /// Returns the value and the remaining data.
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])>;
+}
+
+/// Encodes a key, which can be a single value or a tuple of up to three components
+pub fn encode_key<K: EncodeKey + ?Sized>(key: &K) -> Vec<u8> {
+    let mut out = Vec::new();
+    key.encode_key_into(&mut out);
+    out
+}
+
+/// Decodes a key created by [`encode_key`]. Fails if there is unconsumed data left.
+pub fn decode_key<K: DecodeKey>(data: &[u8]) -> StdResult<K> {
+    let (key, rest) = K::decode_key_from(data)?;
+    if !rest.is_empty() {
+        return Err(StdError::parse_err(
+            type_name::<K>(),
+            format!("{} unexpected trailing bytes", rest.len()),
+        ));
+    }
+    Ok(key)
+}
+
+impl<T: EncodeKey + ?Sized> EncodeKey for &T {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        (*self).encode_key_into(out)
+    }
+}
+
+fn split_fixed<const LENGTH: usize>(data: &[u8]) -> StdResult<([u8; LENGTH], &[u8])> {
+    if data.len() < LENGTH {
+        return Err(StdError::invalid_data_size(LENGTH, data.len()));
+    }
+    let (head, rest) = data.split_at(LENGTH);
+    let mut out = [0u8; LENGTH];
+    out.copy_from_slice(head);
+    Ok((out, rest))
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key_into(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+                let (bytes, rest) = split_fixed(data)?;
+                Ok((<$t>::from_be_bytes(bytes), rest))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl EncodeKey for $t {
+            fn encode_key_into(&self, out: &mut Vec<u8>) {
+                let flipped = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                out.extend_from_slice(&flipped.to_be_bytes());
+            }
+        }
+
+        impl DecodeKey for $t {
+            fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+                let (bytes, rest) = split_fixed(data)?;
+                let flipped = <$u>::from_be_bytes(bytes) ^ (1 << (<$u>::BITS - 1));
+                Ok((flipped as $t, rest))
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64, u128);
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x00;
+
+fn encode_bytes(data: &[u8], out: &mut Vec<u8>) {
+    for &byte in data {
+        out.push(byte);
+        if byte == ESCAPE {
+            out.push(ESCAPED_ZERO);
+        }
+    }
+    out.extend_from_slice(&[ESCAPE, TERMINATOR]);
+}
+
+fn decode_bytes<'a>(data: &'a [u8], target: &str) -> StdResult<(Vec<u8>, &'a [u8])> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let byte = data[pos];
+        if byte != ESCAPE {
+            out.push(byte);
+            pos += 1;
+            continue;
+        }
+        match data.get(pos + 1) {
+            Some(&TERMINATOR) => return Ok((out, &data[pos + 2..])),
+            Some(&ESCAPED_ZERO) => {
+                out.push(ESCAPE);
+                pos += 2;
+            }
+            _ => return Err(StdError::parse_err(target, "invalid escape sequence")),
+        }
+    }
+    Err(StdError::parse_err(target, "missing terminator"))
+}
+
+impl EncodeKey for [u8] {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self, out)
+    }
+}
+
+impl EncodeKey for Vec<u8> {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self, out)
+    }
+}
+
+impl DecodeKey for Vec<u8> {
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+        decode_bytes(data, type_name::<Self>())
+    }
+}
+
+impl EncodeKey for str {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out)
+    }
+}
+
+impl EncodeKey for String {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out)
+    }
+}
+
+impl DecodeKey for String {
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+        let (bytes, rest) = decode_bytes(data, type_name::<Self>())?;
+        let string = String::from_utf8(bytes).map_err(StdError::invalid_utf8)?;
+        Ok((string, rest))
+    }
+}
+
+impl EncodeKey for Addr {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        encode_bytes(self.as_bytes(), out)
+    }
+}
+
+/// Addresses read from storage are assumed to be validated before they were stored
+impl DecodeKey for Addr {
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+        let (string, rest) = String::decode_key_from(data)?;
+        Ok((Addr::unchecked(string), rest))
+    }
+}
+
+impl<A: EncodeKey, B: EncodeKey> EncodeKey for (A, B) {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        self.0.encode_key_into(out);
+        self.1.encode_key_into(out);
+    }
+}
+
+impl<A: DecodeKey, B: DecodeKey> DecodeKey for (A, B) {
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+        let (a, rest) = A::decode_key_from(data)?;
+        let (b, rest) = B::decode_key_from(rest)?;
+        Ok(((a, b), rest))
+    }
+}
+
+impl<A: EncodeKey, B: EncodeKey, C: EncodeKey> EncodeKey for (A, B, C) {
+    fn encode_key_into(&self, out: &mut Vec<u8>) {
+        self.0.encode_key_into(out);
+        self.1.encode_key_into(out);
+        self.2.encode_key_into(out);
+    }
+}
+
+impl<A: DecodeKey, B: DecodeKey, C: DecodeKey> DecodeKey for (A, B, C) {
+    fn decode_key_from(data: &[u8]) -> StdResult<(Self, &[u8])> {
+        let (a, rest) = A::decode_key_from(data)?;
+        let (b, rest) = B::decode_key_from(rest)?;
+        let (c, rest) = C::decode_key_from(rest)?;
+        Ok(((a, b, c), rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+
+    /// Checks that the values (sorted by their natural order) are encoded in the same
+    /// order and can be decoded again
+    fn assert_order_preserving<K: EncodeKey + DecodeKey + PartialEq + Debug>(sorted: &[K]) {
+        let encoded: Vec<Vec<u8>> = sorted.iter().map(encode_key).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+        for (value, key) in sorted.iter().zip(encoded) {
+            assert_eq!(&decode_key::<K>(&key).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn unsigned_encoding_works() {
+        assert_eq!(encode_key(&0x12u8), b"\x12");
+        assert_eq!(encode_key(&0x1234u16), b"\x12\x34");
+        assert_eq!(encode_key(&1u32), b"\x00\x00\x00\x01");
+        assert_eq!(encode_key(&1u64), b"\x00\x00\x00\x00\x00\x00\x00\x01");
+        assert_eq!(encode_key(&1u128).len(), 16);
+
+        assert_order_preserving(&[0u8, 1, 127, 128, 255]);
+        assert_order_preserving(&[0u32, 1, 255, 256, 65536, u32::MAX]);
+        assert_order_preserving(&[0u64, 1, 256, u64::MAX / 2, u64::MAX]);
+        assert_order_preserving(&[0u128, 1, u128::MAX]);
+    }
+
+    #[test]
+    fn signed_encoding_works() {
+        assert_eq!(encode_key(&0i8), b"\x80");
+        assert_eq!(encode_key(&-1i8), b"\x7f");
+        assert_eq!(encode_key(&i8::MIN), b"\x00");
+        assert_eq!(encode_key(&i8::MAX), b"\xff");
+        assert_eq!(encode_key(&-1i64), b"\x7f\xff\xff\xff\xff\xff\xff\xff");
+
+        assert_order_preserving(&[i8::MIN, -100, -1, 0, 1, 100, i8::MAX]);
+        assert_order_preserving(&[i16::MIN, -256, -1, 0, 1, 256, i16::MAX]);
+        assert_order_preserving(&[i32::MIN, -65536, -1, 0, 1, 65536, i32::MAX]);
+        assert_order_preserving(&[i64::MIN, -(1 << 40), -1, 0, 1, 1 << 40, i64::MAX]);
+        assert_order_preserving(&[i128::MIN, -1, 0, 1, i128::MAX]);
+    }
+
+    #[test]
+    fn string_encoding_works() {
+        assert_eq!(encode_key("abc"), b"abc\x00\x00");
+        assert_eq!(encode_key(""), b"\x00\x00");
+        assert_eq!(encode_key("a\0b"), b"a\x00\xffb\x00\x00");
+        assert_eq!(encode_key(&String::from("abc")), encode_key("abc"));
+
+        assert_order_preserving(&[
+            String::from(""),
+            String::from("\0"),
+            String::from("\0\0"),
+            String::from("a"),
+            String::from("a\0"),
+            String::from("a\u{1}"),
+            String::from("ab"),
+            String::from("b"),
+        ]);
+    }
+
+    #[test]
+    fn bytes_encoding_works() {
+        assert_eq!(encode_key(&b"\x00\x01"[..]), b"\x00\xff\x01\x00\x00");
+        assert_eq!(encode_key(&vec![0u8, 1]), encode_key(&b"\x00\x01"[..]));
+
+        assert_order_preserving(&[
+            vec![],
+            vec![0u8],
+            vec![0, 0],
+            vec![0, 1],
+            vec![1],
+            vec![255],
+        ]);
+    }
+
+    #[test]
+    fn addr_encoding_works() {
+        let addr = Addr::unchecked("cosmwasm1abc");
+        assert_eq!(encode_key(&addr), encode_key("cosmwasm1abc"));
+
+        assert_order_preserving(&[
+            Addr::unchecked("alice"),
+            Addr::unchecked("bob"),
+            Addr::unchecked("bobby"),
+        ]);
+    }
+
+    #[test]
+    fn composite_encoding_works() {
+        assert_eq!(encode_key(&("a", 1u8)), b"a\x00\x00\x01");
+        assert_eq!(encode_key(&(1u8, "a", -1i8)), b"\x01a\x00\x00\x7f");
+
+        // string components must not bleed into each other
+        assert_order_preserving(&[
+            (String::from("a"), String::from("z")),
+            (String::from("ab"), String::from("a")),
+            (String::from("b"), String::from("")),
+        ]);
+        assert_order_preserving(&[
+            (Addr::unchecked("alice"), -5i64),
+            (Addr::unchecked("alice"), 3i64),
+            (Addr::unchecked("bob"), i64::MIN),
+        ]);
+        assert_order_preserving(&[
+            (1u32, String::from("x"), 0u64),
+            (1u32, String::from("x"), 1u64),
+            (1u32, String::from("y"), 0u64),
+            (2u32, String::from(""), 0u64),
+        ]);
+    }
+
+    #[test]
+    fn composite_prefix_works() {
+        let prefix = encode_key(&Addr::unchecked("alice"));
+        assert!(encode_key(&(Addr::unchecked("alice"), 7u64)).starts_with(&prefix));
+        assert!(!encode_key(&(Addr::unchecked("alice2"), 7u64)).starts_with(&prefix));
+
+        let prefix = encode_key(&(1u32, "x"));
+        assert!(encode_key(&(1u32, "x", 0u64)).starts_with(&prefix));
+    }
+
+    #[test]
+    fn decode_key_fails_for_invalid_data() {
+        // too short
+        match decode_key::<u32>(b"\x00\x01").unwrap_err() {
+            StdError::InvalidDataSize {
+                expected, actual, ..
+            } => {
+                assert_eq!(expected, 4);
+                assert_eq!(actual, 2);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // trailing data
+        match decode_key::<u8>(b"\x00\x01").unwrap_err() {
+            StdError::ParseErr { msg, .. } => assert_eq!(msg, "1 unexpected trailing bytes"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // missing terminator
+        match decode_key::<String>(b"abc").unwrap_err() {
+            StdError::ParseErr { msg, .. } => assert_eq!(msg, "missing terminator"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        match decode_key::<String>(b"abc\x00").unwrap_err() {
+            StdError::ParseErr { msg, .. } => assert_eq!(msg, "invalid escape sequence"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // invalid UTF-8
+        match decode_key::<String>(b"\xc3\x28\x00\x00").unwrap_err() {
+            StdError::InvalidUtf8 { .. } => {}
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // composite with missing component
+        assert!(decode_key::<(String, u64)>(b"abc\x00\x00").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 mod bucket;
+mod keys;
 mod length_prefixed;
 mod namespace_helpers;
 mod prefixed_storage;
@@ -7,6 +8,7 @@ mod singleton;
 mod type_helpers;
 
 pub use bucket::{bucket, bucket_read, Bucket, ReadonlyBucket};
+pub use keys::{decode_key, encode_key, DecodeKey, EncodeKey};
 pub use length_prefixed::{to_length_prefixed, to_length_prefixed_nested};
 pub use prefixed_storage::{prefixed, prefixed_read, PrefixedStorage, ReadonlyPrefixedStorage};
 pub use sequence::{currval, nextval, sequence};
@@ -11,8 +11,9 @@ use cosmwasm_vm::testing::{
     mock_backend, mock_env, mock_info, mock_instance_options, MockApi, MockQuerier, MockStorage,
 };
 use cosmwasm_vm::{
-    call_execute, call_instantiate, capabilities_from_csv, Cache, CacheOptions, Checksum, Instance,
-    InstanceOptions, Size,
+    call_execute, call_instantiate, AllowedEntryPoints, Cache, CacheOptions, CallLimits,
+    Capabilities, Checksum, DebugLimits, GasConfig, GasMultiplier, Instance, InstanceOptions, Size,
+    StorageLimits, StorageReadCache,
 };
 
 // Instance
@@ -21,11 +22,21 @@ const DEFAULT_GAS_LIMIT: u64 = 1_000_000_000_000; // ~1ms
 const DEFAULT_INSTANCE_OPTIONS: InstanceOptions = InstanceOptions {
     gas_limit: DEFAULT_GAS_LIMIT,
     print_debug: false,
+    gas_config: GasConfig::DEFAULT,
+    storage_limits: StorageLimits::DEFAULT,
+    storage_read_cache: StorageReadCache::DEFAULT,
+    debug_limits: DebugLimits::DEFAULT,
+    call_limits: CallLimits::DEFAULT,
+    allow_reentrant_queries: true,
+    query_depth: 0,
+    validate_msg_addresses: false,
+    gas_multiplier: GasMultiplier::ONE,
 };
 const HIGH_GAS_LIMIT: u64 = 20_000_000_000_000_000; // ~20s, allows many calls on one instance
 
 // Cache
 const MEMORY_CACHE_SIZE: Size = Size::mebi(200);
+const MAX_DECOMPRESSED_WASM_SIZE: Size = Size::mebi(3);
 
 // Multi-threaded get_instance benchmark
 const INSTANTIATION_THREADS: usize = 128;
@@ -123,9 +134,12 @@ fn bench_cache(c: &mut Criterion) {
 
     let options = CacheOptions {
         base_dir: TempDir::new().unwrap().into_path(),
-        available_capabilities: capabilities_from_csv("iterator,staking"),
+        available_capabilities: Capabilities::from_csv("iterator,staking").unwrap(),
         memory_cache_size: MEMORY_CACHE_SIZE,
         instance_memory_limit: DEFAULT_MEMORY_LIMIT,
+        fallback_base_dir: None,
+        max_decompressed_wasm_size: MAX_DECOMPRESSED_WASM_SIZE,
+        allowed_entry_points: AllowedEntryPoints::DEFAULT,
     };
 
     group.bench_function("save wasm", |b| {
@@ -163,9 +177,12 @@ fn bench_cache(c: &mut Criterion) {
     group.bench_function("instantiate from fs", |b| {
         let non_memcache = CacheOptions {
             base_dir: TempDir::new().unwrap().into_path(),
-            available_capabilities: capabilities_from_csv("iterator,staking"),
+            available_capabilities: Capabilities::from_csv("iterator,staking").unwrap(),
             memory_cache_size: Size(0),
             instance_memory_limit: DEFAULT_MEMORY_LIMIT,
+            fallback_base_dir: None,
+            max_decompressed_wasm_size: MAX_DECOMPRESSED_WASM_SIZE,
+            allowed_entry_points: AllowedEntryPoints::DEFAULT,
         };
         let cache: Cache<MockApi, MockStorage, MockQuerier> =
             unsafe { Cache::new(non_memcache).unwrap() };
@@ -229,9 +246,12 @@ pub fn bench_instance_threads(c: &mut Criterion) {
     c.bench_function("multi-threaded get_instance", |b| {
         let options = CacheOptions {
             base_dir: TempDir::new().unwrap().into_path(),
-            available_capabilities: capabilities_from_csv("iterator,staking"),
+            available_capabilities: Capabilities::from_csv("iterator,staking").unwrap(),
             memory_cache_size: MEMORY_CACHE_SIZE,
             instance_memory_limit: DEFAULT_MEMORY_LIMIT,
+            fallback_base_dir: None,
+            max_decompressed_wasm_size: MAX_DECOMPRESSED_WASM_SIZE,
+            allowed_entry_points: AllowedEntryPoints::DEFAULT,
         };
 
         let cache: Cache<MockApi, MockStorage, MockQuerier> =
@@ -1,9 +1,56 @@
+//! Checks a local Wasm file before uploading it. This runs the static checks of the chain
+//! and a trial instantiation against the mock backend (see `cosmwasm_vm::testing::check_contract`).
+//!
+//! Usage: cargo run --example check_contract -- <WASM> [AVAILABLE_CAPABILITIES]
+//!
+//! For checking many contracts at once, see `cosmwasm-check` (https://crates.io/crates/cosmwasm-check).
+
+use std::process::exit;
+
+use cosmwasm_vm::capabilities_from_csv;
+use cosmwasm_vm::testing::check_contract;
+
+const DEFAULT_AVAILABLE_CAPABILITIES: &str = "iterator,staking,stargate,cosmwasm_1_1";
+
 pub fn main() {
-    eprintln!("`check_contract` has been removed from `cosmwasm-vm` examples - please use `cosmwasm-check` instead.");
-    eprintln!("See https://crates.io/crates/cosmwasm-check");
-    eprintln!();
-    eprintln!("> cargo install cosmwasm-check");
-    eprintln!("> cosmwasm-check --help");
-    eprintln!();
-    std::process::exit(74);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (path, available_capabilities_csv) = match args.as_slice() {
+        [path] => (path, DEFAULT_AVAILABLE_CAPABILITIES),
+        [path, capabilities] => (path, capabilities.as_str()),
+        _ => {
+            eprintln!("Usage: check_contract <WASM> [AVAILABLE_CAPABILITIES]");
+            exit(2);
+        }
+    };
+    let available_capabilities = capabilities_from_csv(available_capabilities_csv);
+
+    let wasm = match std::fs::read(path) {
+        Ok(wasm) => wasm,
+        Err(err) => {
+            eprintln!("Error reading {}: {}", path, err);
+            exit(1);
+        }
+    };
+
+    println!("File: {}", path);
+    let mut available: Vec<_> = available_capabilities.iter().collect();
+    available.sort();
+    println!("Available capabilities: {:?}", available);
+    match check_contract(&wasm, &available_capabilities) {
+        Ok(report) => {
+            let mut required: Vec<_> = report.required_capabilities.iter().collect();
+            required.sort();
+            println!("Checksum: {}", report.checksum);
+            println!("Wasm size: {} bytes", report.wasm_size);
+            println!("Required capabilities: {:?}", required);
+            println!("IBC entry points: {:?}", report.ibc_entry_points);
+            println!("Memory after instantiation: {} pages", report.memory_pages);
+            println!("Result: pass");
+        }
+        Err(err) => {
+            println!("Result: failure");
+            println!("{}", err);
+            exit(1);
+        }
+    }
 }
@@ -5,8 +5,9 @@ use tempfile::TempDir;
 use cosmwasm_std::{coins, Empty};
 use cosmwasm_vm::testing::{mock_backend, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
 use cosmwasm_vm::{
-    call_execute, call_instantiate, capabilities_from_csv, Cache, CacheOptions, InstanceOptions,
-    Size,
+    call_execute, call_instantiate, AllowedEntryPoints, Cache, CacheOptions, CallLimits,
+    Capabilities, DebugLimits, GasConfig, GasMultiplier, InstanceOptions, Size, StorageLimits,
+    StorageReadCache,
 };
 
 // Instance
@@ -15,9 +16,19 @@ const DEFAULT_GAS_LIMIT: u64 = 400_000 * 150_000;
 const DEFAULT_INSTANCE_OPTIONS: InstanceOptions = InstanceOptions {
     gas_limit: DEFAULT_GAS_LIMIT,
     print_debug: false,
+    gas_config: GasConfig::DEFAULT,
+    storage_limits: StorageLimits::DEFAULT,
+    storage_read_cache: StorageReadCache::DEFAULT,
+    debug_limits: DebugLimits::DEFAULT,
+    call_limits: CallLimits::DEFAULT,
+    allow_reentrant_queries: true,
+    query_depth: 0,
+    validate_msg_addresses: false,
+    gas_multiplier: GasMultiplier::ONE,
 };
 // Cache
 const MEMORY_CACHE_SIZE: Size = Size::mebi(200);
+const MAX_DECOMPRESSED_WASM_SIZE: Size = Size::mebi(3);
 
 static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
 
@@ -28,9 +39,12 @@ const THREADS: usize = SAVE_WASM_THREADS + INSTANTIATION_THREADS;
 pub fn main() {
     let options = CacheOptions {
         base_dir: TempDir::new().unwrap().into_path(),
-        available_capabilities: capabilities_from_csv("iterator,staking"),
+        available_capabilities: Capabilities::from_csv("iterator,staking").unwrap(),
         memory_cache_size: MEMORY_CACHE_SIZE,
         instance_memory_limit: DEFAULT_MEMORY_LIMIT,
+        fallback_base_dir: None,
+        max_decompressed_wasm_size: MAX_DECOMPRESSED_WASM_SIZE,
+        allowed_entry_points: AllowedEntryPoints::DEFAULT,
     };
 
     let cache: Cache<MockApi, MockStorage, MockQuerier> = unsafe { Cache::new(options).unwrap() };
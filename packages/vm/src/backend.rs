@@ -3,7 +3,9 @@ use std::ops::AddAssign;
 use std::string::FromUtf8Error;
 use thiserror::Error;
 
-use cosmwasm_std::{Binary, ContractResult, SystemResult};
+use cosmwasm_std::{Binary, ContractResult, StdError, SystemResult};
+
+use crate::versioned_storage::HeightPinnedStorage;
 #[cfg(feature = "iterator")]
 use cosmwasm_std::{Order, Record};
 
@@ -74,6 +76,18 @@ pub struct Backend<A: BackendApi, S: Storage, Q: Querier> {
     pub querier: Q,
 }
 
+impl<A: BackendApi, S: VersionedStorage, Q: Querier> Backend<A, S, Q> {
+    /// Returns a backend whose storage reads the state at the given block height.
+    /// Writes to this storage fail.
+    pub fn at_height(self, height: u64) -> Backend<A, HeightPinnedStorage<S>, Q> {
+        Backend {
+            api: self.api,
+            storage: HeightPinnedStorage::new(self.storage, height),
+            querier: self.querier,
+        }
+    }
+}
+
 /// Access to the VM's backend storage, i.e. the chain
 pub trait Storage {
     /// Returns Err on error.
@@ -112,6 +126,59 @@ pub trait Storage {
     #[cfg(feature = "iterator")]
     fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>>;
 
+    /// Like [`Storage::scan`] but the iterator returns at most `limit` elements.
+    ///
+    /// Backends should override this to push the limit down into the underlying database
+    /// iterator. The default implementation ignores the limit and calls [`Storage::scan`].
+    /// The VM enforces the limit in either case.
+    #[cfg(feature = "iterator")]
+    fn range_with_limit(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        _limit: u32,
+    ) -> BackendResult<u32> {
+        self.scan(start, end, order)
+    }
+
+    /// Releases the iterator with the given ID. It must not be used afterwards.
+    ///
+    /// The VM calls this for iterators it creates for itself, e.g. in [`Storage::range_count`].
+    /// Iterators created by contracts are not released by the VM. The default implementation
+    /// does nothing.
+    #[cfg(feature = "iterator")]
+    fn close_iterator(&mut self, _iterator_id: u32) -> BackendResult<()> {
+        (Ok(()), GasInfo::free())
+    }
+
+    /// Returns the number of entries in the given range, using the same bounds as
+    /// [`Storage::scan`].
+    ///
+    /// The default implementation iterates over the range and releases the iterator
+    /// afterwards. Backends can override this if they can count more efficiently.
+    #[cfg(feature = "iterator")]
+    fn range_count(&mut self, start: Option<&[u8]>, end: Option<&[u8]>) -> BackendResult<usize> {
+        let (result, mut gas_info) = self.scan(start, end, Order::Ascending);
+        let iterator_id = match result {
+            Ok(id) => id,
+            Err(err) => return (Err(err), gas_info),
+        };
+        let mut count = 0;
+        let result = loop {
+            let (result, info) = self.next(iterator_id);
+            gas_info += info;
+            match result {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => break Ok(count),
+                Err(err) => break Err(err),
+            }
+        };
+        let (closed, info) = self.close_iterator(iterator_id);
+        gas_info += info;
+        (result.and_then(|count| closed.map(|_| count)), gas_info)
+    }
+
     fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()>;
 
     /// Removes a database entry at `key`.
@@ -121,6 +188,79 @@ pub trait Storage {
     fn remove(&mut self, key: &[u8]) -> BackendResult<()>;
 }
 
+/// Allows using trait objects like `Box<dyn Storage>` as a storage, such that
+/// backends can be composed at runtime without creating a new `Instance` type for each combination.
+impl<S: Storage + ?Sized> Storage for Box<S> {
+    fn get(&self, key: &[u8]) -> BackendResult<Option<Vec<u8>>> {
+        (**self).get(key)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn scan(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> BackendResult<u32> {
+        (**self).scan(start, end, order)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>> {
+        (**self).next(iterator_id)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range_with_limit(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+    ) -> BackendResult<u32> {
+        (**self).range_with_limit(start, end, order, limit)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn close_iterator(&mut self, iterator_id: u32) -> BackendResult<()> {
+        (**self).close_iterator(iterator_id)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range_count(&mut self, start: Option<&[u8]>, end: Option<&[u8]>) -> BackendResult<usize> {
+        (**self).range_count(start, end)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()> {
+        (**self).set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> BackendResult<()> {
+        (**self).remove(key)
+    }
+}
+
+/// An extension of [`Storage`] for backends that keep historical state, e.g. on archive nodes.
+///
+/// Use [`Backend::at_height`] to create a backend whose storage reads state at a fixed
+/// height, which can be used to construct an [`Instance`](crate::Instance) that serves
+/// queries against historical state.
+pub trait VersionedStorage: Storage {
+    /// Like [`Storage::get`] but returns the value as of the given block height.
+    fn get_at_height(&self, key: &[u8], height: u64) -> BackendResult<Option<Vec<u8>>>;
+
+    /// Like [`Storage::scan`] but iterates over the state as of the given block height.
+    /// Elements are read from the returned iterator using [`Storage::next`].
+    #[cfg(feature = "iterator")]
+    fn range_at_height(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        height: u64,
+    ) -> BackendResult<u32>;
+}
+
 /// Callbacks to system functions defined outside of the wasm modules.
 /// This is a trait to allow Mocks in the test code.
 ///
@@ -130,9 +270,19 @@ pub trait Storage {
 ///
 /// We can use feature flags to opt-in to non-essential methods
 /// for backwards compatibility in systems that don't have them all.
-pub trait BackendApi: Copy + Clone + Send {
+pub trait BackendApi: Clone + Send {
     fn canonical_address(&self, human: &str) -> BackendResult<Vec<u8>>;
     fn human_address(&self, canonical: &[u8]) -> BackendResult<String>;
+
+    /// Validates a `CosmosMsg::Custom` payload returned by a contract.
+    ///
+    /// This is called by the VM for every custom message in a contract response,
+    /// allowing the chain to reject malformed messages at contract return time.
+    /// Rejections are reported via `BackendError::UserErr`.
+    /// The default implementation accepts all messages free of charge.
+    fn validate_custom_msg(&self, _msg: Binary) -> BackendResult<()> {
+        (Ok(()), GasInfo::free())
+    }
 }
 
 pub trait Querier {
@@ -150,6 +300,43 @@ pub trait Querier {
         request: &[u8],
         gas_limit: u64,
     ) -> BackendResult<SystemResult<ContractResult<Binary>>>;
+
+    /// Like [`Querier::query_raw`] but additionally receives the query depth of the
+    /// contracts executed for this query, i.e. the depth of the querying instance plus one.
+    /// This is what the VM calls for queries of a contract.
+    ///
+    /// A querier executing a smart query must create the queried instance with this as
+    /// [`InstanceOptions::query_depth`](crate::InstanceOptions::query_depth), such that the
+    /// maximum query depth applies to nested queries. The default implementation ignores the depth.
+    fn query_raw_at_depth(
+        &self,
+        request: &[u8],
+        gas_limit: u64,
+        query_depth: u32,
+    ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
+        let _ = query_depth;
+        self.query_raw(request, gas_limit)
+    }
+}
+
+/// Allows using trait objects like `Box<dyn Querier>` as a querier
+impl<Q: Querier + ?Sized> Querier for Box<Q> {
+    fn query_raw(
+        &self,
+        request: &[u8],
+        gas_limit: u64,
+    ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
+        (**self).query_raw(request, gas_limit)
+    }
+
+    fn query_raw_at_depth(
+        &self,
+        request: &[u8],
+        gas_limit: u64,
+        query_depth: u32,
+    ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
+        (**self).query_raw_at_depth(request, gas_limit, query_depth)
+    }
 }
 
 /// A result type for calling into the backend. Such a call can cause
@@ -160,8 +347,14 @@ pub type BackendResult<T> = (core::result::Result<T, BackendError>, GasInfo);
 #[derive(Error, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum BackendError {
-    #[error("Panic in FFI call")]
-    ForeignPanic {},
+    #[error(
+        "Panic in FFI call{}",
+        msg.as_ref().map(|msg| format!(": {}", msg)).unwrap_or_default()
+    )]
+    ForeignPanic {
+        /// The panic message, if known
+        msg: Option<String>,
+    },
     #[error("Bad argument")]
     BadArgument {},
     #[error("VM received invalid UTF-8 data from backend")]
@@ -172,6 +365,15 @@ pub enum BackendError {
     OutOfGas {},
     #[error("Unknown error during call into backend: {msg}")]
     Unknown { msg: String },
+    /// An error specific to the backend implementation. The meaning of `code` is defined by the
+    /// backend, which allows embedders to tell apart e.g. a full disk from a key missing in an
+    /// archive node. `retryable` is set if the same call may succeed when repeated later.
+    #[error("Backend error with code {code}: {msg}")]
+    Coded {
+        code: u32,
+        msg: String,
+        retryable: bool,
+    },
     // This is the only error case of BackendError that is reported back to the contract.
     #[error("User error during call into backend: {msg}")]
     UserErr { msg: String },
@@ -179,7 +381,14 @@ pub enum BackendError {
 
 impl BackendError {
     pub fn foreign_panic() -> Self {
-        BackendError::ForeignPanic {}
+        BackendError::ForeignPanic { msg: None }
+    }
+
+    /// Creates a foreign panic error carrying the panic message.
+    pub fn foreign_panic_with_msg(msg: impl Into<String>) -> Self {
+        BackendError::ForeignPanic {
+            msg: Some(msg.into()),
+        }
     }
 
     pub fn bad_argument() -> Self {
@@ -201,6 +410,58 @@ impl BackendError {
     pub fn user_err(msg: impl Into<String>) -> Self {
         BackendError::UserErr { msg: msg.into() }
     }
+
+    /// Creates a backend specific error with the given code that is not retryable.
+    pub fn coded(code: u32, msg: impl Into<String>) -> Self {
+        BackendError::Coded {
+            code,
+            msg: msg.into(),
+            retryable: false,
+        }
+    }
+
+    /// Creates a backend specific error with the given code that may succeed when retried.
+    pub fn coded_retryable(code: u32, msg: impl Into<String>) -> Self {
+        BackendError::Coded {
+            code,
+            msg: msg.into(),
+            retryable: true,
+        }
+    }
+
+    /// The backend specific error code, if set by the backend.
+    pub fn code(&self) -> Option<u32> {
+        match self {
+            BackendError::Coded { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the backend marked this error as retryable.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            BackendError::Coded {
+                retryable: true,
+                ..
+            }
+        )
+    }
+
+    /// Converts this error into the error a contract gets from `cosmwasm_std` when the API
+    /// import with the given name (e.g. `addr_validate`) fails, such that tests using a mock
+    /// backend can assert on the same text as on a real chain.
+    ///
+    /// Only user errors are reported to the contract. All other errors abort the execution
+    /// in the VM and are converted like in `From<BackendError> for StdError`.
+    pub fn into_contract_error(self, import: &str) -> StdError {
+        match self {
+            BackendError::UserErr { msg } => {
+                StdError::generic_err(format!("{} errored: {}", import, msg))
+            }
+            err => err.into(),
+        }
+    }
 }
 
 impl From<FromUtf8Error> for BackendError {
@@ -209,6 +470,29 @@ impl From<FromUtf8Error> for BackendError {
     }
 }
 
+/// Converts errors returned by `cosmwasm_std` implementations, e.g. of `cosmwasm_std::testing::MockApi`,
+/// into user errors, which are reported back to the contract. Generic errors keep their message
+/// and all other errors use their display text.
+impl From<StdError> for BackendError {
+    fn from(original: StdError) -> Self {
+        match original {
+            StdError::GenericErr { msg, .. } => BackendError::user_err(msg),
+            err => BackendError::user_err(err.to_string()),
+        }
+    }
+}
+
+/// Converts backend errors into generic errors. The message of user errors is kept and
+/// all other errors use their display text.
+impl From<BackendError> for StdError {
+    fn from(original: BackendError) -> Self {
+        match original {
+            BackendError::UserErr { msg } => StdError::generic_err(msg),
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,6 +592,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn backend_err_foreign_panic_with_msg() {
+        let error = BackendError::foreign_panic_with_msg("trigger failure");
+        match &error {
+            BackendError::ForeignPanic { msg } => {
+                assert_eq!(msg.as_deref(), Some("trigger failure"))
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(error.to_string(), "Panic in FFI call: trigger failure");
+        assert_eq!(
+            BackendError::foreign_panic().to_string(),
+            "Panic in FFI call"
+        );
+    }
+
     #[test]
     fn backend_err_bad_argument() {
         let error = BackendError::bad_argument();
@@ -353,6 +653,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn backend_err_coded() {
+        let error = BackendError::coded(28, "disk full");
+        match error {
+            BackendError::Coded {
+                code,
+                ref msg,
+                retryable,
+            } => {
+                assert_eq!(code, 28);
+                assert_eq!(msg, "disk full");
+                assert!(!retryable);
+            }
+            ref e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(error.code(), Some(28));
+        assert!(!error.is_retryable());
+        assert_eq!(error.to_string(), "Backend error with code 28: disk full");
+
+        let error = BackendError::coded_retryable(404, "key not found in archive node");
+        assert_eq!(error.code(), Some(404));
+        assert!(error.is_retryable());
+
+        let error = BackendError::unknown("broken");
+        assert_eq!(error.code(), None);
+        assert!(!error.is_retryable());
+    }
+
     // conversions
 
     #[test]
@@ -363,4 +691,50 @@ mod tests {
             e => panic!("Unexpected error: {:?}", e),
         }
     }
+
+    #[test]
+    fn convert_from_std_error() {
+        let error: BackendError = StdError::generic_err("Invalid input").into();
+        assert_eq!(error, BackendError::user_err("Invalid input"));
+
+        let error: BackendError = StdError::not_found("Config").into();
+        assert_eq!(error, BackendError::user_err("Config not found"));
+    }
+
+    #[test]
+    fn convert_to_std_error() {
+        let error: StdError = BackendError::user_err("Invalid input").into();
+        match error {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg, "Invalid input"),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        let error: StdError = BackendError::out_of_gas().into();
+        match error {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "Ran out of gas during call into backend")
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // roundtrip
+        let error: StdError = BackendError::from(StdError::generic_err("Invalid input")).into();
+        assert_eq!(error, StdError::generic_err("Invalid input"));
+    }
+
+    #[test]
+    fn into_contract_error_works() {
+        let error = BackendError::user_err("Invalid input: human address too short")
+            .into_contract_error("addr_validate");
+        assert_eq!(
+            error.to_string(),
+            "Generic error: addr_validate errored: Invalid input: human address too short"
+        );
+
+        let error = BackendError::unknown("broken").into_contract_error("addr_humanize");
+        assert_eq!(
+            error.to_string(),
+            "Generic error: Unknown error during call into backend: broken"
+        );
+    }
 }
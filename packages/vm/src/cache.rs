@@ -1,21 +1,36 @@
-use std::collections::HashSet;
-use std::fs::{File, OpenOptions};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::marker::PhantomData;
-use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+
+use serde::de::DeserializeOwned;
+
+use cosmwasm_std::{ContractResult, CustomMsg, Env, MessageInfo, QueryResponse, Reply, Response};
 
 use crate::backend::{Backend, BackendApi, Querier, Storage};
-use crate::capabilities::required_capabilities_from_module;
+use crate::calls;
+use crate::capabilities::{required_capabilities_from_module, Capabilities};
 use crate::checksum::Checksum;
-use crate::compatibility::check_wasm;
+use crate::compatibility::{
+    check_wasm_with_extra_imports, AllowedEntryPoints, DEFAULT_IMPORT_NAMESPACE, SUPPORTED_IMPORTS,
+};
+use crate::compression::decompress_wasm;
+use crate::environment::panic_payload_message;
 use crate::errors::{VmError, VmResult};
 use crate::filesystem::mkdir_p;
-use crate::instance::{Instance, InstanceOptions};
-use crate::modules::{FileSystemCache, InMemoryCache, PinnedMemoryCache};
+use crate::instance::{GasReport, Instance, InstanceOptions, InstanceParams, InstantiationFailure};
+use crate::libraries::Library;
+use crate::modules::{module_size_estimate, FileSystemCache, InMemoryCache, PinnedMemoryCache};
 use crate::size::Size;
-use crate::static_analysis::{deserialize_wasm, has_ibc_entry_points};
-use crate::wasm_backend::{compile, make_runtime_store};
+use crate::static_analysis::{
+    deserialize_wasm, entry_points, ibc_entry_points, imported_functions, non_determinism_sources,
+    uses_floats, IbcEntryPoints, NonDeterminismSource,
+};
+use crate::wasm_backend::{compile, compile_with_fingerprint, make_runtime_store};
 
 const STATE_DIR: &str = "state";
 // Things related to the state of the blockchain.
@@ -25,12 +40,24 @@ const CACHE_DIR: &str = "cache";
 // Cacheable things.
 const MODULES_DIR: &str = "modules";
 
+/// Counters of the cache lookups and compilations since the cache was created.
+///
+/// Lookups happen when getting an instance and when pinning a module. A miss in one
+/// cache is followed by a lookup in the next one, i.e. pinned memory cache, memory cache
+/// and file system cache.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Stats {
     pub hits_pinned_memory_cache: u32,
     pub hits_memory_cache: u32,
     pub hits_fs_cache: u32,
+    /// The number of times a module for an instance was not found in any cache
     pub misses: u32,
+    pub misses_pinned_memory_cache: u32,
+    pub misses_memory_cache: u32,
+    pub misses_fs_cache: u32,
+    /// The number of compilations performed, e.g. when saving Wasm or after a miss in
+    /// the file system cache
+    pub compiles: u32,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -38,7 +65,10 @@ pub struct Metrics {
     pub stats: Stats,
     pub elements_pinned_memory_cache: usize,
     pub elements_memory_cache: usize,
+    /// Estimated memory usage of the pinned memory cache in bytes.
+    /// See `memory_cache_size` in [`CacheOptions`] for how modules are measured.
     pub size_pinned_memory_cache: usize,
+    /// Estimated memory usage of the memory cache in bytes, which is at most `memory_cache_size`.
     pub size_memory_cache: usize,
 }
 
@@ -49,41 +79,115 @@ pub struct CacheOptions {
     /// If this does not exist, it will be created. Not sure if this behaviour
     /// is desired but wasmd relies on it.
     pub base_dir: PathBuf,
-    pub available_capabilities: HashSet<String>,
+    /// The capabilities of the chain, which contracts can require via `requires_*` exports
+    /// or by using capability gated imports.
+    pub available_capabilities: Capabilities,
+    /// Memory limit for the memory cache, in bytes. When it is exceeded, the least recently
+    /// used modules are removed from the memory cache.
+    ///
+    /// The size of a compiled module is estimated as a multiple of its Wasm bytecode size,
+    /// which is deterministic across machines. Modules bigger than this limit are not
+    /// kept in memory.
     pub memory_cache_size: Size,
     /// Memory limit for instances, in bytes. Use a value that is divisible by the Wasm page size 65536,
     /// e.g. full MiBs.
     pub instance_memory_limit: Size,
+    /// The base directory of another cache, e.g. on a shared file system, whose compiled modules
+    /// are used when they are not found in `base_dir`. Such modules are copied to `base_dir`.
+    ///
+    /// This directory is never written to and only modules are read from it.
+    /// Wasm files are always stored in and loaded from `base_dir`.
+    pub fallback_base_dir: Option<PathBuf>,
+    /// Maximum size of a gzip or zstd compressed Wasm blob after decompression, in bytes.
+    /// Decompression in [`Cache::save_wasm`] is aborted once this is exceeded.
+    /// Uncompressed Wasm is not affected by this limit.
+    pub max_decompressed_wasm_size: Size,
+    /// The optional entry points contracts may export. Contracts exporting other ones
+    /// cannot be stored via [`Cache::save_wasm`].
+    pub allowed_entry_points: AllowedEntryPoints,
 }
 
-pub struct CacheInner {
+/// A cache for Wasm code and compiled modules that can be shared between threads, e.g. using an `Arc`.
+///
+/// Each of the caches is guarded by its own lock, which is never held while another one is acquired.
+/// This way the lookups of modules in the pinned memory cache, which are the hot path of a query server,
+/// only need a shared read lock and do not wait for compilations or writes to disk.
+pub struct Cache<A: BackendApi, S: Storage, Q: Querier> {
+    /// Available capabilities are immutable for the lifetime of the cache,
+    /// i.e. any number of read-only references is allowed to access it concurrently.
+    available_capabilities: Capabilities,
+    /// Immutable for the lifetime of the cache
     wasm_path: PathBuf,
     /// Instances memory limit in bytes. Use a value that is divisible by the Wasm page size 65536,
     /// e.g. full MiBs.
     instance_memory_limit: Size,
-    pinned_memory_cache: PinnedMemoryCache,
-    memory_cache: InMemoryCache,
-    fs_cache: FileSystemCache,
-    stats: Stats,
-}
-
-pub struct Cache<A: BackendApi, S: Storage, Q: Querier> {
-    /// Available capabilities are immutable for the lifetime of the cache,
-    /// i.e. any number of read-only references is allowed to access it concurrently.
-    available_capabilities: HashSet<String>,
-    inner: Mutex<CacheInner>,
+    /// Immutable for the lifetime of the cache
+    max_decompressed_wasm_size: Size,
+    /// Immutable for the lifetime of the cache
+    allowed_entry_points: AllowedEntryPoints,
+    /// Pinned modules are only added and removed on `pin`/`unpin`, such that loads
+    /// can happen concurrently.
+    pinned_memory_cache: RwLock<PinnedMemoryCache>,
+    /// An LRU cache, i.e. every load modifies the cache
+    memory_cache: Mutex<InMemoryCache>,
+    /// Guards all file system access, i.e. the Wasm files in `wasm_path` as well as the
+    /// file system cache. This ensures no file is read while it is being written.
+    fs_cache: RwLock<FileSystemCache>,
+    stats: Mutex<Stats>,
+    /// Libraries by the namespace contracts import their functions from
+    libraries: RwLock<HashMap<String, Library>>,
     // Those two don't store data but only fix type information
     type_api: PhantomData<A>,
     type_storage: PhantomData<S>,
     type_querier: PhantomData<Q>,
-    /// To prevent concurrent access to `WasmerInstance::new`
-    instantiation_lock: Mutex<()>,
+    /// To prevent concurrent access to `WasmerInstance::new` for the same module.
+    /// Different modules can be instantiated concurrently.
+    instantiation_locks: Mutex<HashMap<Checksum, Arc<Mutex<()>>>>,
+}
+
+/// The outcome of running an entry point on a cached contract using
+/// [`Cache::call_with_instance`] or one of the `call_*` helpers.
+pub struct CallOutcome<T, A: BackendApi, S: Storage, Q: Querier> {
+    /// The result of the call
+    pub result: VmResult<T>,
+    /// The gas report at the end of the call
+    pub gas_report: GasReport,
+    /// The external dependencies for reuse. This is `None` if they could not be recovered
+    /// from the instance.
+    pub backend: Option<Backend<A, S, Q>>,
+}
+
+/// The progress of [`Cache::precompile_all`]
+#[derive(Debug)]
+pub struct PrecompileProgress {
+    /// The checksum that was processed last
+    pub checksum: Checksum,
+    /// The number of processed checksums, including this one
+    pub done: usize,
+    /// The total number of checksums
+    pub total: usize,
+    /// The error if precompiling this checksum failed
+    pub error: Option<VmError>,
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct AnalysisReport {
+    /// True if and only if all IBC entry points are exported
     pub has_ibc_entry_points: bool,
+    /// Which IBC entry points are exported. In contrast to `has_ibc_entry_points`
+    /// this lists the missing ones for contracts with partial IBC support.
+    pub ibc_entry_points: IbcEntryPoints,
     pub required_capabilities: HashSet<String>,
+    /// True if the contract uses floats in function signatures, locals, globals or instructions
+    pub uses_floats: bool,
+    /// The full names (`module.field`) of all imported host functions
+    pub imports: BTreeSet<String>,
+    /// The exported entry points along with their number of parameters.
+    /// Memory management and marker exports are not included.
+    pub entry_points: BTreeMap<String, usize>,
+    /// Potential sources of non-determinism. Imports provided by libraries of this cache
+    /// are considered known.
+    pub non_determinism: BTreeSet<NonDeterminismSource>,
 }
 
 impl<A, S, Q> Cache<A, S, Q>
@@ -105,6 +209,9 @@ where
             available_capabilities,
             memory_cache_size,
             instance_memory_limit,
+            fallback_base_dir,
+            max_decompressed_wasm_size,
+            allowed_entry_points,
         } = options;
 
         let state_path = base_dir.join(STATE_DIR);
@@ -117,47 +224,101 @@ where
         mkdir_p(&cache_path).map_err(|_e| VmError::cache_err("Error creating cache directory"))?;
         mkdir_p(&wasm_path).map_err(|_e| VmError::cache_err("Error creating wasm directory"))?;
 
-        let fs_cache = FileSystemCache::new(cache_path.join(MODULES_DIR))
+        let mut fs_cache = FileSystemCache::new(cache_path.join(MODULES_DIR))
             .map_err(|e| VmError::cache_err(format!("Error file system cache: {}", e)))?;
+        if let Some(fallback_base_dir) = fallback_base_dir {
+            fs_cache = fs_cache.with_fallback(fallback_base_dir.join(CACHE_DIR).join(MODULES_DIR));
+        }
         Ok(Cache {
             available_capabilities,
-            inner: Mutex::new(CacheInner {
-                wasm_path,
-                instance_memory_limit,
-                pinned_memory_cache: PinnedMemoryCache::new(),
-                memory_cache: InMemoryCache::new(memory_cache_size),
-                fs_cache,
-                stats: Stats::default(),
-            }),
+            wasm_path,
+            instance_memory_limit,
+            max_decompressed_wasm_size,
+            allowed_entry_points,
+            pinned_memory_cache: RwLock::new(PinnedMemoryCache::new()),
+            memory_cache: Mutex::new(InMemoryCache::new(memory_cache_size)),
+            fs_cache: RwLock::new(fs_cache),
+            stats: Mutex::new(Stats::default()),
             type_storage: PhantomData::<S>,
             type_api: PhantomData::<A>,
             type_querier: PhantomData::<Q>,
-            instantiation_lock: Mutex::new(()),
+            libraries: RwLock::new(HashMap::new()),
+            instantiation_locks: Mutex::new(HashMap::new()),
         })
     }
 
     pub fn stats(&self) -> Stats {
-        self.inner.lock().unwrap().stats
+        *self.stats.lock().unwrap()
     }
 
+    /// Returns the stats and the state of the memory caches.
+    ///
+    /// The caches are locked one after another, so the values are not guaranteed to be
+    /// consistent with each other when the cache is used concurrently.
     pub fn metrics(&self) -> Metrics {
-        let cache = self.inner.lock().unwrap();
+        let stats = self.stats();
+        let (elements_pinned_memory_cache, size_pinned_memory_cache) = {
+            let pinned_memory_cache = self.pinned_memory_cache.read().unwrap();
+            (pinned_memory_cache.len(), pinned_memory_cache.size())
+        };
+        let (elements_memory_cache, size_memory_cache) = {
+            let memory_cache = self.memory_cache.lock().unwrap();
+            (memory_cache.len(), memory_cache.size())
+        };
         Metrics {
-            stats: cache.stats,
-            elements_pinned_memory_cache: cache.pinned_memory_cache.len(),
-            elements_memory_cache: cache.memory_cache.len(),
-            size_pinned_memory_cache: cache.pinned_memory_cache.size(),
-            size_memory_cache: cache.memory_cache.size(),
+            stats,
+            elements_pinned_memory_cache,
+            elements_memory_cache,
+            size_pinned_memory_cache,
+            size_memory_cache,
+        }
+    }
+
+    /// Registers a library whose functions contracts can import from the given namespace.
+    ///
+    /// Libraries should be added before any contract that uses them is saved or instantiated.
+    /// The namespace must not be used by the VM or another library.
+    pub fn add_library(&self, namespace: impl Into<String>, library: Library) -> VmResult<()> {
+        let namespace = namespace.into();
+        if namespace == DEFAULT_IMPORT_NAMESPACE {
+            return Err(VmError::generic_err(format!(
+                "Namespace \"{}\" is reserved for the VM",
+                namespace
+            )));
+        }
+        let mut libraries = self.libraries.write().unwrap();
+        if libraries.contains_key(&namespace) {
+            return Err(VmError::generic_err(format!(
+                "A library with namespace \"{}\" exists already",
+                namespace
+            )));
         }
+        libraries.insert(namespace, library);
+        Ok(())
     }
 
+    /// Checks, compiles and stores the given Wasm. Returns the checksum of the Wasm, which
+    /// is used to refer to it later on.
+    ///
+    /// The Wasm can be gzip or zstd compressed, like the code in a StoreCode transaction.
+    /// It is decompressed up to `max_decompressed_wasm_size` bytes (see [`CacheOptions`])
+    /// and the decompressed Wasm is stored. Its checksum does not depend on the compression.
     pub fn save_wasm(&self, wasm: &[u8]) -> VmResult<Checksum> {
-        check_wasm(wasm, &self.available_capabilities)?;
+        let wasm = decompress_wasm(wasm, self.max_decompressed_wasm_size.0)?;
+        let wasm = wasm.as_ref();
+        let library_imports = self.library_imports();
+        check_wasm_with_extra_imports(
+            wasm,
+            &self.available_capabilities,
+            &library_imports,
+            &self.allowed_entry_points,
+        )?;
         let module = compile(wasm, None, &[])?;
+        self.stats.lock().unwrap().compiles += 1;
 
-        let mut cache = self.inner.lock().unwrap();
-        let checksum = save_wasm_to_disk(&cache.wasm_path, wasm)?;
-        cache.fs_cache.store(&checksum, &module)?;
+        let mut fs_cache = self.fs_cache.write().unwrap();
+        let checksum = save_wasm_to_disk(&self.wasm_path, wasm)?;
+        fs_cache.store(&checksum, &module)?;
         Ok(checksum)
     }
 
@@ -167,11 +328,10 @@ where
     ///
     /// If the given ID is not found or the content does not match the hash (=ID), an error is returned.
     pub fn load_wasm(&self, checksum: &Checksum) -> VmResult<Vec<u8>> {
-        self.load_wasm_with_path(&self.inner.lock().unwrap().wasm_path, checksum)
-    }
-
-    fn load_wasm_with_path(&self, wasm_path: &Path, checksum: &Checksum) -> VmResult<Vec<u8>> {
-        let code = load_wasm_from_disk(wasm_path, checksum)?;
+        let code = {
+            let _fs_cache = self.fs_cache.read().unwrap();
+            load_wasm_from_disk(&self.wasm_path, checksum)?
+        };
         // verify hash matches (integrity check)
         if Checksum::generate(&code) != *checksum {
             Err(VmError::integrity_err())
@@ -188,12 +348,53 @@ where
         // Here we could use a streaming deserializer to slightly improve performance. However, this way it is DRYer.
         let wasm = self.load_wasm(checksum)?;
         let module = deserialize_wasm(&wasm)?;
+        let ibc_entry_points = ibc_entry_points(&module);
+        let library_imports = self.library_imports();
+        let known_imports: Vec<&str> = SUPPORTED_IMPORTS
+            .iter()
+            .copied()
+            .chain(library_imports.iter().map(String::as_str))
+            .collect();
         Ok(AnalysisReport {
-            has_ibc_entry_points: has_ibc_entry_points(&module),
+            has_ibc_entry_points: ibc_entry_points == IbcEntryPoints::Complete,
+            ibc_entry_points,
             required_capabilities: required_capabilities_from_module(&module),
+            uses_floats: uses_floats(&module),
+            imports: imported_functions(&module),
+            entry_points: entry_points(&module),
+            non_determinism: non_determinism_sources(&module, &known_imports),
         })
     }
 
+    /// Returns the full names (`namespace.name`) of all library functions
+    fn library_imports(&self) -> Vec<String> {
+        let libraries = self.libraries.read().unwrap();
+        libraries
+            .iter()
+            .flat_map(|(namespace, library)| {
+                library
+                    .function_names()
+                    .into_iter()
+                    .map(move |name| format!("{}.{}", namespace, name))
+            })
+            .collect()
+    }
+
+    /// Returns a fingerprint of the instrumented code of a previously saved Wasm, i.e. a hash
+    /// of the code after the metering and validation passes of this VM were applied.
+    ///
+    /// Validators can compare fingerprints, e.g. after a VM upgrade, to ensure they all run
+    /// identical instrumented code. The fingerprint is deterministic for a given Wasm and VM version
+    /// but not comparable to the [`Checksum`] of the Wasm.
+    ///
+    /// The Wasm is compiled for this, independent of the caches. The result is not stored.
+    pub fn instrumented_fingerprint(&self, checksum: &Checksum) -> VmResult<Checksum> {
+        let wasm = self.load_wasm(checksum)?;
+        let (_module, fingerprint) = compile_with_fingerprint(&wasm, None)?;
+        self.stats.lock().unwrap().compiles += 1;
+        Ok(fingerprint)
+    }
+
     /// Pins a Module that was previously stored via save_wasm.
     ///
     /// The module is lookup first in the memory cache, and then in the file system cache.
@@ -201,37 +402,40 @@ where
     /// pinned cache.
     /// If the given ID is not found, or the content does not match the hash (=ID), an error is returned.
     pub fn pin(&self, checksum: &Checksum) -> VmResult<()> {
-        let mut cache = self.inner.lock().unwrap();
-        if cache.pinned_memory_cache.has(checksum) {
+        if self.pinned_memory_cache.read().unwrap().has(checksum) {
             return Ok(());
         }
 
         // Try to get module from the memory cache
-        if let Some(module) = cache.memory_cache.load(checksum)? {
-            cache.stats.hits_memory_cache += 1;
-            return cache
-                .pinned_memory_cache
-                .store(checksum, module.module, module.size);
+        let cached = self.memory_cache.lock().unwrap().load(checksum)?;
+        if let Some(module) = cached {
+            self.stats.lock().unwrap().hits_memory_cache += 1;
+            return self.pinned_memory_cache.write().unwrap().store(
+                checksum,
+                module.module,
+                module.size,
+            );
         }
+        self.stats.lock().unwrap().misses_memory_cache += 1;
 
         // Try to get module from file system cache
-        let store = make_runtime_store(Some(cache.instance_memory_limit));
-        if let Some(module) = cache.fs_cache.load(checksum, &store)? {
-            cache.stats.hits_fs_cache += 1;
-            let module_size = loupe::size_of_val(&module);
-            return cache
-                .pinned_memory_cache
-                .store(checksum, module, module_size);
-        }
-
-        // Re-compile from original Wasm bytecode
-        let code = self.load_wasm_with_path(&cache.wasm_path, checksum)?;
-        let module = compile(&code, Some(cache.instance_memory_limit), &[])?;
-        // Store into the fs cache too
-        cache.fs_cache.store(checksum, &module)?;
-        let module_size = loupe::size_of_val(&module);
-        cache
-            .pinned_memory_cache
+        let store = make_runtime_store(Some(self.instance_memory_limit));
+        let cached = self.fs_cache.read().unwrap().load(checksum, &store)?;
+        let (module, wasm_size) = match cached {
+            Some(module) => {
+                self.stats.lock().unwrap().hits_fs_cache += 1;
+                (module, self.wasm_size(checksum)?)
+            }
+            // Re-compile from original Wasm bytecode
+            None => {
+                self.stats.lock().unwrap().misses_fs_cache += 1;
+                self.compile_and_store(checksum)?
+            }
+        };
+        let module_size = module_size_estimate(wasm_size);
+        self.pinned_memory_cache
+            .write()
+            .unwrap()
             .store(checksum, module, module_size)
     }
 
@@ -240,77 +444,347 @@ where
     /// Not found IDs are silently ignored, and no integrity check (checksum validation) is done
     /// on the removed value.
     pub fn unpin(&self, checksum: &Checksum) -> VmResult<()> {
-        self.inner
-            .lock()
-            .unwrap()
-            .pinned_memory_cache
-            .remove(checksum)
+        self.pinned_memory_cache.write().unwrap().remove(checksum)
+    }
+
+    /// Compiles previously saved Wasm blobs and stores the modules in the file system cache,
+    /// e.g. to warm up the cache after state sync before serving blocks. Modules that are already
+    /// in the file system cache are skipped.
+    ///
+    /// The checksums are processed by `concurrency` worker threads. After each checksum,
+    /// `on_progress` is called on the calling thread. Returns the checksums that failed,
+    /// or an error naming the worker if a worker thread panicked.
+    pub fn precompile_all<F>(
+        self: &Arc<Self>,
+        checksums: &[Checksum],
+        concurrency: usize,
+        mut on_progress: F,
+    ) -> VmResult<Vec<Checksum>>
+    where
+        F: FnMut(PrecompileProgress),
+    {
+        let total = checksums.len();
+        let queue = Arc::new(checksums.to_vec());
+        let next_index = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = mpsc::channel();
+
+        let workers: Vec<_> = (0..concurrency.max(1).min(total))
+            .map(|_| {
+                let cache = Arc::clone(self);
+                let queue = Arc::clone(&queue);
+                let next_index = Arc::clone(&next_index);
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    while let Some(checksum) = queue.get(next_index.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let result = cache.precompile(checksum);
+                        if sender.send((*checksum, result)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        // Ensure the receiver stops once all workers are done
+        drop(sender);
+
+        let mut failed = Vec::new();
+        for (done, (checksum, result)) in receiver.iter().enumerate() {
+            let error = result.err();
+            if error.is_some() {
+                failed.push(checksum);
+            }
+            on_progress(PrecompileProgress {
+                checksum,
+                done: done + 1,
+                total,
+                error,
+            });
+        }
+        for (index, worker) in workers.into_iter().enumerate() {
+            worker.join().map_err(|panic| {
+                VmError::generic_err(format!(
+                    "Precompile worker {} panicked: {}",
+                    index,
+                    panic_payload_message(&*panic)
+                ))
+            })?;
+        }
+        Ok(failed)
+    }
+
+    /// Compiles a previously saved Wasm and stores it in the file system cache if needed.
+    fn precompile(&self, checksum: &Checksum) -> VmResult<()> {
+        if self.fs_cache.read().unwrap().has(checksum) {
+            return Ok(());
+        }
+        self.compile_and_store(checksum)?;
+        Ok(())
     }
 
     /// Returns an Instance tied to a previously saved Wasm.
     ///
     /// It takes a module from cache or Wasm code and instantiates it.
+    /// Gas multipliers configured for the available capabilities replace the corresponding
+    /// multipliers of `options.gas_config` (see [`Capabilities::apply_gas_multipliers`]).
     pub fn get_instance(
         &self,
         checksum: &Checksum,
         backend: Backend<A, S, Q>,
         options: InstanceOptions,
     ) -> VmResult<Instance<A, S, Q>> {
-        let module = self.get_module(checksum)?;
-        let instance = Instance::from_module(
+        self.try_get_instance(checksum, backend, options)
+            .map_err(|failure| failure.error)
+    }
+
+    /// Like [`Cache::get_instance`] but returns the backend along with the error if the
+    /// instance cannot be created, such that it can be reused by the caller.
+    pub fn try_get_instance(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+    ) -> Result<Instance<A, S, Q>, InstantiationFailure<A, S, Q>> {
+        let module = match self.get_module(checksum) {
+            Ok(module) => module,
+            Err(error) => return Err(InstantiationFailure { error, backend }),
+        };
+        let libraries = self.libraries.read().unwrap();
+        let instantiation_lock = self.instantiation_lock(checksum);
+        let options = InstanceOptions {
+            gas_config: self
+                .available_capabilities
+                .apply_gas_multipliers(options.gas_config),
+            ..options
+        };
+        let instance = Instance::try_from_module(
             &module,
             backend,
-            options.gas_limit,
-            options.print_debug,
-            None,
-            Some(&self.instantiation_lock),
-        )?;
-        Ok(instance)
+            options,
+            InstanceParams {
+                instantiation_lock: Some(&instantiation_lock),
+                available_capabilities: Some(&self.available_capabilities),
+                libraries: Some(&libraries),
+                ..Default::default()
+            },
+        );
+        self.release_instantiation_lock(checksum, instantiation_lock);
+        instance
+    }
+
+    /// Returns the lock guarding the instantiation of the given module.
+    /// It must be passed to [`Cache::release_instantiation_lock`] when it is no longer needed.
+    fn instantiation_lock(&self, checksum: &Checksum) -> Arc<Mutex<()>> {
+        let mut locks = self.instantiation_locks.lock().unwrap();
+        Arc::clone(locks.entry(*checksum).or_default())
+    }
+
+    /// Drops a lock obtained from [`Cache::instantiation_lock`] and removes it from the map
+    /// if no other instantiation holds it, such that the map does not grow with every module.
+    fn release_instantiation_lock(&self, checksum: &Checksum, lock: Arc<Mutex<()>>) {
+        let mut locks = self.instantiation_locks.lock().unwrap();
+        // Dropped while holding the map lock, such that no clone can be created concurrently
+        drop(lock);
+        if matches!(locks.get(checksum), Some(lock) if Arc::strong_count(lock) == 1) {
+            locks.remove(checksum);
+        }
+    }
+
+    /// Gets an instance for a previously saved Wasm, runs `call` on it and recycles the instance.
+    ///
+    /// Errors that occur before the call, such as a missing Wasm or a failed instantiation, are
+    /// returned directly. The result of the call itself is part of the outcome, such that the
+    /// backend and gas report are available even if the call failed.
+    pub fn call_with_instance<T, F>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        call: F,
+    ) -> VmResult<CallOutcome<T, A, S, Q>>
+    where
+        F: FnOnce(&mut Instance<A, S, Q>) -> VmResult<T>,
+    {
+        let mut instance = self.get_instance(checksum, backend, options)?;
+        let result = call(&mut instance)
+            .map_err(|err| err.context(format!("of checksum {}…", &checksum.to_hex()[..8])));
+        let (gas_report, _, backend) = instance.into_parts();
+        Ok(CallOutcome {
+            result,
+            gas_report,
+            backend,
+        })
+    }
+
+    /// Calls `instantiate` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_instantiate<U>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        info: &MessageInfo,
+        msg: &[u8],
+    ) -> VmResult<CallOutcome<ContractResult<Response<U>>, A, S, Q>>
+    where
+        U: DeserializeOwned + CustomMsg,
+    {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_instantiate(instance, env, info, msg)
+        })
+    }
+
+    /// Calls `execute` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_execute<U>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        info: &MessageInfo,
+        msg: &[u8],
+    ) -> VmResult<CallOutcome<ContractResult<Response<U>>, A, S, Q>>
+    where
+        U: DeserializeOwned + CustomMsg,
+    {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_execute(instance, env, info, msg)
+        })
+    }
+
+    /// Calls `migrate` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_migrate<U>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        msg: &[u8],
+    ) -> VmResult<CallOutcome<ContractResult<Response<U>>, A, S, Q>>
+    where
+        U: DeserializeOwned + CustomMsg,
+    {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_migrate(instance, env, msg)
+        })
+    }
+
+    /// Calls `sudo` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_sudo<U>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        msg: &[u8],
+    ) -> VmResult<CallOutcome<ContractResult<Response<U>>, A, S, Q>>
+    where
+        U: DeserializeOwned + CustomMsg,
+    {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_sudo(instance, env, msg)
+        })
+    }
+
+    /// Calls `reply` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_reply<U>(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        msg: &Reply,
+    ) -> VmResult<CallOutcome<ContractResult<Response<U>>, A, S, Q>>
+    where
+        U: DeserializeOwned + CustomMsg,
+    {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_reply(instance, env, msg)
+        })
+    }
+
+    /// Calls `query` on a previously saved Wasm. See [`Cache::call_with_instance`].
+    pub fn call_query(
+        &self,
+        checksum: &Checksum,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        env: &Env,
+        msg: &[u8],
+    ) -> VmResult<CallOutcome<ContractResult<QueryResponse>, A, S, Q>> {
+        self.call_with_instance(checksum, backend, options, |instance| {
+            calls::call_query(instance, env, msg)
+        })
     }
 
     /// Returns a module tied to a previously saved Wasm.
     /// Depending on availability, this is either generated from a memory cache, file system cache or Wasm code.
     /// This is part of `get_instance` but pulled out to reduce the locking time.
     fn get_module(&self, checksum: &Checksum) -> VmResult<wasmer::Module> {
-        let mut cache = self.inner.lock().unwrap();
         // Try to get module from the pinned memory cache
-        if let Some(module) = cache.pinned_memory_cache.load(checksum)? {
-            cache.stats.hits_pinned_memory_cache += 1;
+        let cached = self.pinned_memory_cache.read().unwrap().load(checksum)?;
+        if let Some(module) = cached {
+            self.stats.lock().unwrap().hits_pinned_memory_cache += 1;
             return Ok(module);
         }
+        self.stats.lock().unwrap().misses_pinned_memory_cache += 1;
 
         // Get module from memory cache
-        if let Some(module) = cache.memory_cache.load(checksum)? {
-            cache.stats.hits_memory_cache += 1;
+        let cached = self.memory_cache.lock().unwrap().load(checksum)?;
+        if let Some(module) = cached {
+            self.stats.lock().unwrap().hits_memory_cache += 1;
             return Ok(module.module);
         }
+        self.stats.lock().unwrap().misses_memory_cache += 1;
 
         // Get module from file system cache
-        let store = make_runtime_store(Some(cache.instance_memory_limit));
-        if let Some(module) = cache.fs_cache.load(checksum, &store)? {
-            cache.stats.hits_fs_cache += 1;
-            let module_size = loupe::size_of_val(&module);
-            cache
-                .memory_cache
-                .store(checksum, module.clone(), module_size)?;
-            return Ok(module);
-        }
-
-        // Re-compile module from wasm
-        //
-        // This is needed for chains that upgrade their node software in a way that changes the module
-        // serialization format. If you do not replay all transactions, previous calls of `save_wasm`
-        // stored the old module format.
-        let wasm = self.load_wasm_with_path(&cache.wasm_path, checksum)?;
-        cache.stats.misses += 1;
-        let module = compile(&wasm, Some(cache.instance_memory_limit), &[])?;
-        cache.fs_cache.store(checksum, &module)?;
-        let module_size = loupe::size_of_val(&module);
-        cache
-            .memory_cache
+        let store = make_runtime_store(Some(self.instance_memory_limit));
+        let cached = self.fs_cache.read().unwrap().load(checksum, &store)?;
+        let (module, wasm_size) = match cached {
+            Some(module) => {
+                self.stats.lock().unwrap().hits_fs_cache += 1;
+                (module, self.wasm_size(checksum)?)
+            }
+            // Re-compile module from wasm
+            //
+            // This is needed for chains that upgrade their node software in a way that changes the module
+            // serialization format. If you do not replay all transactions, previous calls of `save_wasm`
+            // stored the old module format.
+            None => {
+                {
+                    let mut stats = self.stats.lock().unwrap();
+                    stats.misses += 1;
+                    stats.misses_fs_cache += 1;
+                }
+                self.compile_and_store(checksum)?
+            }
+        };
+        let module_size = module_size_estimate(wasm_size);
+        self.memory_cache
+            .lock()
+            .unwrap()
             .store(checksum, module.clone(), module_size)?;
         Ok(module)
     }
+
+    /// Compiles a previously saved Wasm and stores the module in the file system cache.
+    /// Returns the module and the size of the Wasm bytecode.
+    ///
+    /// No lock is held during compilation. Concurrent calls for the same checksum may compile
+    /// the module multiple times, which is harmless since the results are equal.
+    fn compile_and_store(&self, checksum: &Checksum) -> VmResult<(wasmer::Module, usize)> {
+        let wasm = self.load_wasm(checksum)?;
+        let module = compile(&wasm, Some(self.instance_memory_limit), &[])?;
+        self.stats.lock().unwrap().compiles += 1;
+        self.fs_cache.write().unwrap().store(checksum, &module)?;
+        Ok((module, wasm.len()))
+    }
+
+    /// Returns the size of a previously saved Wasm in bytes without reading it.
+    fn wasm_size(&self, checksum: &Checksum) -> VmResult<usize> {
+        let _fs_cache = self.fs_cache.read().unwrap();
+        wasm_size_on_disk(&self.wasm_path, checksum)
+    }
 }
 
 unsafe impl<A, S, Q> Sync for Cache<A, S, Q>
@@ -329,6 +803,12 @@ where
 {
 }
 
+/// Marks a Wasm file as compressed. It is followed by the size of the uncompressed Wasm
+/// (u64, little endian) and the zstd compressed Wasm. Files without this marker contain the
+/// uncompressed Wasm, as written by older versions. Those are still supported.
+const COMPRESSED_WASM_MAGIC: &[u8; 4] = b"\0cwz";
+const COMPRESSED_WASM_HEADER_LENGTH: usize = 12;
+
 /// save stores the wasm code in the given directory and returns an ID for lookup.
 /// It will create the directory if it doesn't exist.
 /// Saving the same byte code multiple times is allowed.
@@ -344,36 +824,89 @@ fn save_wasm_to_disk(dir: impl Into<PathBuf>, wasm: &[u8]) -> VmResult<Checksum>
     let mut file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(filepath)
         .map_err(|e| VmError::cache_err(format!("Error opening Wasm file for writing: {}", e)))?;
-    file.write_all(wasm)
+    file.write_all(COMPRESSED_WASM_MAGIC)
+        .and_then(|_| file.write_all(&(wasm.len() as u64).to_le_bytes()))
+        .and_then(|_| zstd::stream::copy_encode(wasm, &mut file, 0))
         .map_err(|e| VmError::cache_err(format!("Error writing Wasm file: {}", e)))?;
 
     Ok(checksum)
 }
 
+/// Reads the header of a compressed Wasm file. For uncompressed files, this returns `None`
+/// along with the bytes read so far.
+fn read_compressed_wasm_header(file: &mut File) -> VmResult<(Option<usize>, Vec<u8>)> {
+    let mut header = Vec::with_capacity(COMPRESSED_WASM_HEADER_LENGTH);
+    file.take(COMPRESSED_WASM_HEADER_LENGTH as u64)
+        .read_to_end(&mut header)
+        .map_err(|_e| VmError::cache_err("Error reading Wasm file"))?;
+    if header.len() == COMPRESSED_WASM_HEADER_LENGTH && header.starts_with(COMPRESSED_WASM_MAGIC) {
+        let size = u64::from_le_bytes(header[4..].try_into().unwrap());
+        Ok((Some(size as usize), header))
+    } else {
+        Ok((None, header))
+    }
+}
+
+/// Returns the size of the uncompressed Wasm
+fn wasm_size_on_disk(dir: impl Into<PathBuf>, checksum: &Checksum) -> VmResult<usize> {
+    // this requires the directory and file to exist
+    let path = dir.into().join(checksum.to_hex());
+    let mut file = File::open(&path)
+        .map_err(|_e| VmError::cache_err("Error opening Wasm file for reading"))?;
+    match read_compressed_wasm_header(&mut file)? {
+        (Some(size), _) => Ok(size),
+        (None, _) => {
+            let metadata = fs::metadata(path)
+                .map_err(|_e| VmError::cache_err("Error reading Wasm file metadata"))?;
+            Ok(metadata.len() as usize)
+        }
+    }
+}
+
 fn load_wasm_from_disk(dir: impl Into<PathBuf>, checksum: &Checksum) -> VmResult<Vec<u8>> {
     // this requires the directory and file to exist
     let path = dir.into().join(checksum.to_hex());
     let mut file =
         File::open(path).map_err(|_e| VmError::cache_err("Error opening Wasm file for reading"))?;
 
-    let mut wasm = Vec::<u8>::new();
-    file.read_to_end(&mut wasm)
-        .map_err(|_e| VmError::cache_err("Error reading Wasm file"))?;
-    Ok(wasm)
+    match read_compressed_wasm_header(&mut file)? {
+        (Some(size), _) => {
+            // The size is only checked after decompression. Don't preallocate it, since the
+            // header might be corrupted.
+            let mut wasm = Vec::<u8>::new();
+            zstd::stream::read::Decoder::new(file)
+                .and_then(|mut decoder| decoder.read_to_end(&mut wasm))
+                .map_err(|_e| VmError::cache_err("Error decompressing Wasm file"))?;
+            if wasm.len() != size {
+                return Err(VmError::cache_err("Wasm file has an invalid size"));
+            }
+            Ok(wasm)
+        }
+        (None, mut wasm) => {
+            file.read_to_end(&mut wasm)
+                .map_err(|_e| VmError::cache_err("Error reading Wasm file"))?;
+            Ok(wasm)
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::calls::{call_execute, call_instantiate};
-    use crate::capabilities::capabilities_from_csv;
+    use crate::environment::{
+        CallLimits, DebugLimits, GasConfig, GasMultiplier, StorageLimits, StorageReadCache,
+    };
     use crate::errors::VmError;
     use crate::testing::{mock_backend, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
     use cosmwasm_std::{coins, Empty};
     use std::fs::{create_dir_all, OpenOptions};
     use std::io::Write;
+    use std::sync::Arc;
+    use std::thread;
     use tempfile::TempDir;
 
     const TESTING_GAS_LIMIT: u64 = 500_000_000_000; // ~0.5ms
@@ -381,14 +914,24 @@ mod tests {
     const TESTING_OPTIONS: InstanceOptions = InstanceOptions {
         gas_limit: TESTING_GAS_LIMIT,
         print_debug: false,
+        gas_config: GasConfig::DEFAULT,
+        storage_limits: StorageLimits::DEFAULT,
+        storage_read_cache: StorageReadCache::DEFAULT,
+        debug_limits: DebugLimits::DEFAULT,
+        call_limits: CallLimits::DEFAULT,
+        allow_reentrant_queries: false,
+        query_depth: 0,
+        validate_msg_addresses: false,
+        gas_multiplier: GasMultiplier::ONE,
     };
     const TESTING_MEMORY_CACHE_SIZE: Size = Size::mebi(200);
+    const TESTING_MAX_DECOMPRESSED_WASM_SIZE: Size = Size::mebi(3);
 
     static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
     static IBC_CONTRACT: &[u8] = include_bytes!("../testdata/ibc_reflect.wasm");
 
-    fn default_capabilities() -> HashSet<String> {
-        capabilities_from_csv("iterator,staking")
+    fn default_capabilities() -> Capabilities {
+        Capabilities::from_csv("iterator,staking").unwrap()
     }
 
     fn make_testing_options() -> CacheOptions {
@@ -397,17 +940,23 @@ mod tests {
             available_capabilities: default_capabilities(),
             memory_cache_size: TESTING_MEMORY_CACHE_SIZE,
             instance_memory_limit: TESTING_MEMORY_LIMIT,
+            fallback_base_dir: None,
+            max_decompressed_wasm_size: TESTING_MAX_DECOMPRESSED_WASM_SIZE,
+            allowed_entry_points: AllowedEntryPoints::DEFAULT,
         }
     }
 
     fn make_stargate_testing_options() -> CacheOptions {
         let mut capabilities = default_capabilities();
-        capabilities.insert("stargate".into());
+        capabilities.register("stargate").unwrap();
         CacheOptions {
             base_dir: TempDir::new().unwrap().into_path(),
             available_capabilities: capabilities,
             memory_cache_size: TESTING_MEMORY_CACHE_SIZE,
             instance_memory_limit: TESTING_MEMORY_LIMIT,
+            fallback_base_dir: None,
+            max_decompressed_wasm_size: TESTING_MAX_DECOMPRESSED_WASM_SIZE,
+            allowed_entry_points: AllowedEntryPoints::DEFAULT,
         }
     }
 
@@ -442,6 +991,60 @@ mod tests {
         cache.save_wasm(CONTRACT).unwrap();
     }
 
+    #[test]
+    fn save_wasm_rejects_forbidden_entry_points() {
+        let options = CacheOptions {
+            allowed_entry_points: AllowedEntryPoints {
+                migrate: false,
+                ..AllowedEntryPoints::DEFAULT
+            },
+            ..make_testing_options()
+        };
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(options).unwrap() };
+        match cache.save_wasm(CONTRACT).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract exports entry points that are not allowed on this chain: [\"migrate\"]"
+            ),
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn save_wasm_decompresses_zstd() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        let compressed = zstd::stream::encode_all(CONTRACT, 0).unwrap();
+        let checksum = cache.save_wasm(&compressed).unwrap();
+        assert_eq!(checksum, Checksum::generate(CONTRACT));
+        assert_eq!(cache.load_wasm(&checksum).unwrap(), CONTRACT);
+    }
+
+    #[test]
+    fn save_wasm_limits_decompressed_size() {
+        let options = CacheOptions {
+            max_decompressed_wasm_size: Size(CONTRACT.len() - 1),
+            ..make_testing_options()
+        };
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(options).unwrap() };
+        let compressed = zstd::stream::encode_all(CONTRACT, 0).unwrap();
+        match cache.save_wasm(&compressed).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                format!(
+                    "Decompressed Wasm exceeds the limit of {} bytes",
+                    CONTRACT.len() - 1
+                )
+            ),
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        // uncompressed Wasm is not limited
+        cache.save_wasm(CONTRACT).unwrap();
+    }
+
     #[test]
     fn save_wasm_rejects_invalid_contract() {
         // Invalid because it doesn't contain required memory and exports
@@ -467,6 +1070,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn libraries_can_be_used_by_contracts() {
+        let contract = wat::parse_str(
+            r#"(module
+            (import "math" "add" (func $add (param i64 i64) (result i64)))
+            (memory 1)
+            (func $alloc (param i32) (result i32) i32.const 0)
+            (func $dealloc (param i32))
+            (func $instantiate (param i32 i32 i32) (result i32) i32.const 0)
+            (func $double (param i64) (result i64)
+                local.get 0
+                local.get 0
+                call $add)
+            (func $version)
+            (export "memory" (memory 0))
+            (export "allocate" (func $alloc))
+            (export "deallocate" (func $dealloc))
+            (export "instantiate" (func $instantiate))
+            (export "interface_version_8" (func $version))
+            (export "double" (func $double))
+            )"#,
+        )
+        .unwrap();
+        let library = wat::parse_str(
+            r#"(module
+            (func (export "add") (param i64 i64) (result i64)
+                local.get 0
+                local.get 1
+                i64.add)
+            )"#,
+        )
+        .unwrap();
+        let library = Library::new(&library, TESTING_MEMORY_LIMIT).unwrap();
+
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        match cache.save_wasm(&contract).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert!(msg.starts_with("Wasm contract requires unsupported import: \"math.add\""))
+            }
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        cache.add_library("math", library.clone()).unwrap();
+        let checksum = cache.save_wasm(&contract).unwrap();
+        let instance = cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        let result = instance
+            .call_function1("double", &[wasmer::Val::I64(21)])
+            .unwrap();
+        assert_eq!(result.unwrap_i64(), 42);
+
+        // namespaces cannot be used twice or shadow the VM's imports
+        match cache.add_library("math", library.clone()).unwrap_err() {
+            VmError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "A library with namespace \"math\" exists already")
+            }
+            e => panic!("Unexpected error {:?}", e),
+        }
+        match cache.add_library("env", library).unwrap_err() {
+            VmError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "Namespace \"env\" is reserved for the VM")
+            }
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     #[test]
     fn save_wasm_fills_file_system_but_not_memory_cache() {
         // Who knows if and when the uploaded contract will be executed. Don't pollute
@@ -506,6 +1177,9 @@ mod tests {
                 available_capabilities: default_capabilities(),
                 memory_cache_size: TESTING_MEMORY_CACHE_SIZE,
                 instance_memory_limit: TESTING_MEMORY_LIMIT,
+                fallback_base_dir: None,
+                max_decompressed_wasm_size: TESTING_MAX_DECOMPRESSED_WASM_SIZE,
+                allowed_entry_points: AllowedEntryPoints::DEFAULT,
             };
             let cache1: Cache<MockApi, MockStorage, MockQuerier> =
                 unsafe { Cache::new(options1).unwrap() };
@@ -518,6 +1192,9 @@ mod tests {
                 available_capabilities: default_capabilities(),
                 memory_cache_size: TESTING_MEMORY_CACHE_SIZE,
                 instance_memory_limit: TESTING_MEMORY_LIMIT,
+                fallback_base_dir: None,
+                max_decompressed_wasm_size: TESTING_MAX_DECOMPRESSED_WASM_SIZE,
+                allowed_entry_points: AllowedEntryPoints::DEFAULT,
             };
             let cache2: Cache<MockApi, MockStorage, MockQuerier> =
                 unsafe { Cache::new(options2).unwrap() };
@@ -526,6 +1203,102 @@ mod tests {
         }
     }
 
+    #[test]
+    fn get_instance_loads_modules_from_fallback_base_dir() {
+        let shared_dir = TempDir::new().unwrap();
+        let local_dir = TempDir::new().unwrap();
+
+        let shared_options = CacheOptions {
+            base_dir: shared_dir.path().to_path_buf(),
+            ..make_testing_options()
+        };
+        let shared_cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(shared_options).unwrap() };
+        let checksum = shared_cache.save_wasm(CONTRACT).unwrap();
+
+        let local_options = CacheOptions {
+            base_dir: local_dir.path().to_path_buf(),
+            fallback_base_dir: Some(shared_dir.path().to_path_buf()),
+            ..make_testing_options()
+        };
+        let local_cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(local_options).unwrap() };
+
+        // The Wasm is only stored locally, e.g. after state sync
+        let wasm_path = local_dir.path().join(STATE_DIR).join(WASM_DIR);
+        let mut file = File::create(wasm_path.join(checksum.to_hex())).unwrap();
+        file.write_all(CONTRACT).unwrap();
+        let local_modules_path = local_dir.path().join(CACHE_DIR).join(MODULES_DIR);
+        assert_eq!(
+            fs::read_dir(&local_modules_path).unwrap().count(),
+            0,
+            "no local modules before loading"
+        );
+
+        let instance = local_cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        instance.recycle().unwrap();
+        assert_eq!(local_cache.stats().hits_fs_cache, 1);
+        assert_eq!(local_cache.stats().misses, 0);
+
+        // The module was copied to the local directory
+        let version_dir = fs::read_dir(&local_modules_path)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(version_dir.join(checksum.to_hex()).is_file());
+    }
+
+    #[test]
+    fn precompile_all_works() {
+        let tmp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            base_dir: tmp_dir.path().to_path_buf(),
+            ..make_stargate_testing_options()
+        };
+        let cache: Arc<Cache<MockApi, MockStorage, MockQuerier>> =
+            Arc::new(unsafe { Cache::new(options).unwrap() });
+        let checksum1 = cache.save_wasm(CONTRACT).unwrap();
+        let checksum2 = cache.save_wasm(IBC_CONTRACT).unwrap();
+        let unknown = Checksum::generate(b"non-existent");
+
+        // Remove the modules, e.g. after state sync
+        let modules_path = tmp_dir.path().join(CACHE_DIR).join(MODULES_DIR);
+        fs::remove_dir_all(&modules_path).unwrap();
+
+        let mut progress = Vec::new();
+        let failed = cache
+            .precompile_all(&[checksum1, unknown, checksum2], 2, |p| {
+                progress.push((p.checksum, p.done, p.total, p.error.is_some()))
+            })
+            .unwrap();
+        assert_eq!(failed, vec![unknown]);
+        assert_eq!(progress.len(), 3);
+        for (i, (checksum, done, total, is_err)) in progress.into_iter().enumerate() {
+            assert_eq!(done, i + 1);
+            assert_eq!(total, 3);
+            assert_eq!(is_err, checksum == unknown);
+        }
+
+        // Modules are loaded from the file system cache
+        for checksum in [checksum1, checksum2] {
+            cache
+                .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+                .unwrap();
+        }
+        assert_eq!(cache.stats().hits_fs_cache, 2);
+        assert_eq!(cache.stats().misses, 0);
+
+        // Nothing to do for empty input
+        let failed = cache
+            .precompile_all(&[], 4, |_| panic!("Unexpected progress"))
+            .unwrap();
+        assert_eq!(failed, vec![]);
+    }
+
     #[test]
     fn load_wasm_errors_for_non_existent_id() {
         let cache: Cache<MockApi, MockStorage, MockQuerier> =
@@ -551,6 +1324,9 @@ mod tests {
             available_capabilities: default_capabilities(),
             memory_cache_size: TESTING_MEMORY_CACHE_SIZE,
             instance_memory_limit: TESTING_MEMORY_LIMIT,
+            fallback_base_dir: None,
+            max_decompressed_wasm_size: TESTING_MAX_DECOMPRESSED_WASM_SIZE,
+            allowed_entry_points: AllowedEntryPoints::DEFAULT,
         };
         let cache: Cache<MockApi, MockStorage, MockQuerier> =
             unsafe { Cache::new(options).unwrap() };
@@ -807,6 +1583,352 @@ mod tests {
         }
     }
 
+    #[test]
+    fn metrics_report_estimated_module_sizes() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_stargate_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+        let pinned_checksum = cache.save_wasm(IBC_CONTRACT).unwrap();
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.size_memory_cache, 0);
+        assert_eq!(metrics.size_pinned_memory_cache, 0);
+
+        // from file system
+        cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        cache.pin(&pinned_checksum).unwrap();
+        let metrics = cache.metrics();
+        assert_eq!(metrics.elements_memory_cache, 1);
+        assert_eq!(
+            metrics.size_memory_cache,
+            module_size_estimate(CONTRACT.len())
+        );
+        assert_eq!(metrics.elements_pinned_memory_cache, 1);
+        assert_eq!(
+            metrics.size_pinned_memory_cache,
+            module_size_estimate(IBC_CONTRACT.len())
+        );
+    }
+
+    #[test]
+    fn modules_bigger_than_memory_cache_are_not_cached() {
+        let options = CacheOptions {
+            memory_cache_size: Size::kibi(1),
+            ..make_testing_options()
+        };
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(options).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        for _ in 0..2 {
+            cache
+                .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+                .unwrap();
+        }
+        assert_eq!(cache.stats().hits_memory_cache, 0);
+        assert_eq!(cache.stats().hits_fs_cache, 2);
+        assert_eq!(cache.metrics().elements_memory_cache, 0);
+    }
+
+    #[test]
+    fn cache_can_be_shared_between_threads() {
+        let cache: Arc<Cache<MockApi, MockStorage, MockQuerier>> =
+            Arc::new(unsafe { Cache::new(make_stargate_testing_options()).unwrap() });
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+        let pinned_checksum = cache.save_wasm(IBC_CONTRACT).unwrap();
+        cache.pin(&pinned_checksum).unwrap();
+        let before = cache.stats();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                thread::spawn(move || {
+                    for _ in 0..3 {
+                        let instance = cache
+                            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+                            .unwrap();
+                        instance.recycle().unwrap();
+                        let instance = cache
+                            .get_instance(&pinned_checksum, mock_backend(&[]), TESTING_OPTIONS)
+                            .unwrap();
+                        instance.recycle().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits_pinned_memory_cache, 12);
+        // Threads can load the module from disk concurrently before it is in the memory cache
+        assert_eq!(
+            stats.hits_memory_cache + stats.hits_fs_cache - before.hits_fs_cache,
+            12
+        );
+        assert_eq!(stats.misses, 0);
+        assert_eq!(cache.metrics().elements_memory_cache, 1);
+    }
+
+    #[test]
+    fn try_get_instance_returns_backend_on_failure() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = Checksum::generate(b"unknown");
+
+        let mut backend = mock_backend(&[]);
+        backend.storage.set(b"foo", b"bar").0.unwrap();
+        let failure = cache
+            .try_get_instance(&checksum, backend, TESTING_OPTIONS)
+            .err()
+            .unwrap();
+        assert!(matches!(failure.error, VmError::CacheErr { .. }));
+        assert_eq!(
+            failure.backend.storage.get(b"foo").0.unwrap(),
+            Some(b"bar".to_vec())
+        );
+
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+        let instance = cache
+            .try_get_instance(&checksum, failure.backend, TESTING_OPTIONS)
+            .unwrap();
+        let backend = instance.recycle().unwrap().backend;
+        assert_eq!(
+            backend.storage.get(b"foo").0.unwrap(),
+            Some(b"bar".to_vec())
+        );
+    }
+
+    #[test]
+    fn instantiation_locks_are_per_module() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_stargate_testing_options()).unwrap() };
+        let checksum1 = cache.save_wasm(CONTRACT).unwrap();
+        let checksum2 = cache.save_wasm(IBC_CONTRACT).unwrap();
+
+        let lock1 = cache.instantiation_lock(&checksum1);
+        assert!(Arc::ptr_eq(&lock1, &cache.instantiation_lock(&checksum1)));
+        assert!(!Arc::ptr_eq(&lock1, &cache.instantiation_lock(&checksum2)));
+
+        // Holding the lock of one module does not block instantiating another one
+        let _guard = lock1.lock().unwrap();
+        cache
+            .get_instance(&checksum2, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+    }
+
+    #[test]
+    fn instantiation_locks_are_removed_when_released() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        assert!(cache.instantiation_locks.lock().unwrap().is_empty());
+
+        // A lock that is still held is kept
+        let lock1 = cache.instantiation_lock(&checksum);
+        let lock2 = cache.instantiation_lock(&checksum);
+        cache.release_instantiation_lock(&checksum, lock1);
+        assert_eq!(cache.instantiation_locks.lock().unwrap().len(), 1);
+        cache.release_instantiation_lock(&checksum, lock2);
+        assert!(cache.instantiation_locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn call_helpers_work() {
+        let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        // init
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        let outcome = cache
+            .call_instantiate::<Empty>(
+                &checksum,
+                mock_backend(&[]),
+                TESTING_OPTIONS,
+                &mock_env(),
+                &info,
+                msg,
+            )
+            .unwrap();
+        let response = outcome.result.unwrap().unwrap();
+        assert_eq!(response.messages.len(), 0);
+        assert!(outcome.gas_report.used_internally > 0);
+        let backend = outcome.backend.unwrap();
+
+        // execute with the same storage
+        let info = mock_info("verifies", &coins(15, "earth"));
+        let msg = br#"{"release":{}}"#;
+        let outcome = cache
+            .call_execute::<Empty>(&checksum, backend, TESTING_OPTIONS, &mock_env(), &info, msg)
+            .unwrap();
+        let response = outcome.result.unwrap().unwrap();
+        assert_eq!(response.messages.len(), 1);
+        let backend = outcome.backend.unwrap();
+
+        // query
+        let outcome = cache
+            .call_query(
+                &checksum,
+                backend,
+                TESTING_OPTIONS,
+                &mock_env(),
+                br#"{"verifier":{}}"#,
+            )
+            .unwrap();
+        let response = outcome.result.unwrap().unwrap();
+        assert_eq!(response.as_slice(), br#"{"verifier":"verifies"}"#);
+
+        assert_eq!(cache.stats().hits_fs_cache, 1);
+        assert_eq!(cache.stats().hits_memory_cache, 2);
+    }
+
+    #[test]
+    fn call_with_instance_returns_call_errors_in_outcome() {
+        let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        // out of gas
+        let options = InstanceOptions {
+            gas_limit: 10,
+            ..TESTING_OPTIONS
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        let outcome = cache
+            .call_instantiate::<Empty>(
+                &checksum,
+                mock_backend(&[]),
+                options,
+                &mock_env(),
+                &info,
+                msg,
+            )
+            .unwrap();
+        match outcome.result.unwrap_err() {
+            VmError::GasDepletion { .. } => {}
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(outcome.gas_report.remaining, 0);
+        assert!(outcome.backend.is_some());
+    }
+
+    #[test]
+    fn get_instance_applies_capability_gas_multipliers() {
+        let capabilities = Capabilities::from_config(
+            r#"
+            [[capability]]
+            name = "iterator"
+            gas_multiplier = { numerator = 3, denominator = 1 }
+
+            [[capability]]
+            name = "staking"
+            "#,
+        )
+        .unwrap();
+        let options = CacheOptions {
+            available_capabilities: capabilities,
+            ..make_testing_options()
+        };
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(options).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        let instance = cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        let gas_config = instance.shared_env().gas_config;
+        assert_eq!(
+            gas_config.iterator_multiplier,
+            GasMultiplier::new(3, 1).unwrap()
+        );
+        assert_eq!(
+            gas_config.stargate_query_multiplier,
+            GasConfig::DEFAULT.stargate_query_multiplier
+        );
+    }
+
+    #[test]
+    fn call_with_instance_applies_gas_multiplier() {
+        let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+
+        let mut reports = vec![];
+        for gas_multiplier in [GasMultiplier::ONE, GasMultiplier::new(1, 3).unwrap()] {
+            let options = InstanceOptions {
+                gas_multiplier,
+                ..TESTING_OPTIONS
+            };
+            let outcome = cache
+                .call_instantiate::<Empty>(
+                    &checksum,
+                    mock_backend(&[]),
+                    options,
+                    &mock_env(),
+                    &info,
+                    msg,
+                )
+                .unwrap();
+            outcome.result.unwrap().unwrap();
+            reports.push(outcome.gas_report);
+        }
+
+        let (full, discounted) = (&reports[0], &reports[1]);
+        assert_eq!(discounted.limit, full.limit);
+        assert_eq!(discounted.used_externally, full.used_externally);
+        assert_eq!(discounted.used_internally, full.used_internally / 3);
+        assert_eq!(
+            discounted.remaining,
+            TESTING_GAS_LIMIT - discounted.used_externally - discounted.used_internally
+        );
+    }
+
+    #[test]
+    fn call_with_instance_adds_checksum_to_call_errors() {
+        let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        let outcome = cache
+            .call_with_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS, |_instance| {
+                Err::<(), _>(VmError::generic_err("something went wrong"))
+            })
+            .unwrap();
+        let err = outcome.result.unwrap_err();
+        assert_eq!(
+            err.breadcrumbs(),
+            [format!("of checksum {}…", &checksum.to_hex()[..8])]
+        );
+        assert!(matches!(err, VmError::GenericErr { .. }));
+    }
+
+    #[test]
+    fn call_with_instance_fails_for_unknown_checksum() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = Checksum::generate(b"non-existent");
+
+        let result =
+            cache.call_with_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS, |_instance| {
+                Ok(())
+            });
+        match result {
+            Err(VmError::CacheErr { msg, .. }) => {
+                assert_eq!(msg, "Error opening Wasm file for reading")
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+            Ok(_) => panic!("Expected error"),
+        }
+    }
+
     #[test]
     fn use_multiple_cached_instances_of_same_contract() {
         let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
@@ -826,7 +1948,7 @@ mod tests {
             call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg).unwrap();
         let msgs = res.unwrap().messages;
         assert_eq!(msgs.len(), 0);
-        let backend1 = instance.recycle().unwrap();
+        let backend1 = instance.recycle().unwrap().backend;
 
         // init instance 2
         let mut instance = cache
@@ -838,7 +1960,7 @@ mod tests {
             call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg).unwrap();
         let msgs = res.unwrap().messages;
         assert_eq!(msgs.len(), 0);
-        let backend2 = instance.recycle().unwrap();
+        let backend2 = instance.recycle().unwrap().backend;
 
         // run contract 2 - just sanity check - results validate in contract unit tests
         let mut instance = cache
@@ -910,6 +2032,15 @@ mod tests {
         let options = InstanceOptions {
             gas_limit: 10,
             print_debug: false,
+            gas_config: GasConfig::DEFAULT,
+            storage_limits: StorageLimits::DEFAULT,
+            storage_read_cache: StorageReadCache::DEFAULT,
+            debug_limits: DebugLimits::DEFAULT,
+            call_limits: CallLimits::DEFAULT,
+            allow_reentrant_queries: false,
+            query_depth: 0,
+            validate_msg_addresses: false,
+            gas_multiplier: GasMultiplier::ONE,
         };
         let mut instance1 = cache.get_instance(&checksum, backend1, options).unwrap();
         assert_eq!(cache.stats().hits_fs_cache, 1);
@@ -930,6 +2061,15 @@ mod tests {
         let options = InstanceOptions {
             gas_limit: TESTING_GAS_LIMIT,
             print_debug: false,
+            gas_config: GasConfig::DEFAULT,
+            storage_limits: StorageLimits::DEFAULT,
+            storage_read_cache: StorageReadCache::DEFAULT,
+            debug_limits: DebugLimits::DEFAULT,
+            call_limits: CallLimits::DEFAULT,
+            allow_reentrant_queries: false,
+            query_depth: 0,
+            validate_msg_addresses: false,
+            gas_multiplier: GasMultiplier::ONE,
         };
         let mut instance2 = cache.get_instance(&checksum, backend2, options).unwrap();
         assert_eq!(cache.stats().hits_pinned_memory_cache, 0);
@@ -988,8 +2128,114 @@ mod tests {
         assert_eq!(code, loaded);
     }
 
+    #[test]
+    fn save_wasm_to_disk_compresses() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path();
+        let checksum = save_wasm_to_disk(path, CONTRACT).unwrap();
+
+        let on_disk = fs::read(path.join(checksum.to_hex())).unwrap();
+        assert!(on_disk.starts_with(COMPRESSED_WASM_MAGIC));
+        assert!(on_disk.len() < CONTRACT.len() * 3 / 4);
+        assert_eq!(wasm_size_on_disk(path, &checksum).unwrap(), CONTRACT.len());
+        assert_eq!(load_wasm_from_disk(path, &checksum).unwrap(), CONTRACT);
+    }
+
+    #[test]
+    fn load_wasm_from_disk_works_for_uncompressed_files() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path();
+        let checksum = Checksum::generate(CONTRACT);
+        fs::write(path.join(checksum.to_hex()), CONTRACT).unwrap();
+
+        assert_eq!(wasm_size_on_disk(path, &checksum).unwrap(), CONTRACT.len());
+        assert_eq!(load_wasm_from_disk(path, &checksum).unwrap(), CONTRACT);
+
+        // short files
+        let code = vec![7u8; 3];
+        let checksum = Checksum::generate(&code);
+        fs::write(path.join(checksum.to_hex()), &code).unwrap();
+        assert_eq!(wasm_size_on_disk(path, &checksum).unwrap(), 3);
+        assert_eq!(load_wasm_from_disk(path, &checksum).unwrap(), code);
+
+        // saving again replaces the uncompressed file
+        save_wasm_to_disk(path, CONTRACT).unwrap();
+        let checksum = Checksum::generate(CONTRACT);
+        assert_eq!(load_wasm_from_disk(path, &checksum).unwrap(), CONTRACT);
+    }
+
+    #[test]
+    fn load_wasm_from_disk_fails_for_corrupted_files() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path();
+        let checksum = save_wasm_to_disk(path, CONTRACT).unwrap();
+        let filepath = path.join(checksum.to_hex());
+        let mut on_disk = fs::read(&filepath).unwrap();
+        on_disk.truncate(on_disk.len() / 2);
+        fs::write(&filepath, on_disk).unwrap();
+
+        let err = load_wasm_from_disk(path, &checksum).unwrap_err();
+        assert!(matches!(err, VmError::CacheErr { .. }));
+    }
+
+    #[test]
+    fn load_wasm_from_disk_fails_for_invalid_size_header() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path();
+        let checksum = save_wasm_to_disk(path, CONTRACT).unwrap();
+        let filepath = path.join(checksum.to_hex());
+        let mut on_disk = fs::read(&filepath).unwrap();
+        on_disk[4..COMPRESSED_WASM_HEADER_LENGTH].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&filepath, on_disk).unwrap();
+
+        let err = load_wasm_from_disk(path, &checksum).unwrap_err();
+        assert!(matches!(err, VmError::CacheErr { .. }));
+    }
+
+    #[test]
+    fn instrumented_fingerprint_works() {
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+
+        let fingerprint = cache.instrumented_fingerprint(&checksum).unwrap();
+        assert_ne!(fingerprint, checksum);
+        assert_eq!(
+            cache.instrumented_fingerprint(&checksum).unwrap(),
+            fingerprint
+        );
+
+        // independent of caches
+        cache.pin(&checksum).unwrap();
+        assert_eq!(
+            cache.instrumented_fingerprint(&checksum).unwrap(),
+            fingerprint
+        );
+
+        // same for other cache instances
+        let other: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(make_testing_options()).unwrap() };
+        other.save_wasm(CONTRACT).unwrap();
+        assert_eq!(
+            other.instrumented_fingerprint(&checksum).unwrap(),
+            fingerprint
+        );
+
+        // unknown checksum
+        let unknown = Checksum::generate(b"unknown");
+        cache.instrumented_fingerprint(&unknown).unwrap_err();
+    }
+
     #[test]
     fn analyze_works() {
+        let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        let arities = |entry_points: &[(&str, usize)]| {
+            entry_points
+                .iter()
+                .map(|(name, arity)| (name.to_string(), *arity))
+                .collect()
+        };
+
         let cache: Cache<MockApi, MockStorage, MockQuerier> =
             unsafe { Cache::new(make_stargate_testing_options()).unwrap() };
 
@@ -999,7 +2245,31 @@ mod tests {
             report1,
             AnalysisReport {
                 has_ibc_entry_points: false,
+                ibc_entry_points: IbcEntryPoints::None,
                 required_capabilities: HashSet::new(),
+                uses_floats: false,
+                imports: strings(&[
+                    "env.addr_canonicalize",
+                    "env.addr_humanize",
+                    "env.addr_validate",
+                    "env.db_read",
+                    "env.db_remove",
+                    "env.db_write",
+                    "env.debug",
+                    "env.ed25519_batch_verify",
+                    "env.ed25519_verify",
+                    "env.query_chain",
+                    "env.secp256k1_recover_pubkey",
+                    "env.secp256k1_verify",
+                ]),
+                entry_points: arities(&[
+                    ("execute", 3),
+                    ("instantiate", 3),
+                    ("migrate", 2),
+                    ("query", 2),
+                    ("sudo", 2),
+                ]),
+                non_determinism: BTreeSet::new(),
             }
         );
 
@@ -1009,15 +2279,87 @@ mod tests {
             report2,
             AnalysisReport {
                 has_ibc_entry_points: true,
+                ibc_entry_points: IbcEntryPoints::Complete,
                 required_capabilities: HashSet::from_iter(vec![
                     "iterator".to_string(),
                     "staking".to_string(),
                     "stargate".to_string()
                 ]),
+                uses_floats: false,
+                imports: strings(&[
+                    "env.addr_canonicalize",
+                    "env.addr_humanize",
+                    "env.addr_validate",
+                    "env.db_next",
+                    "env.db_read",
+                    "env.db_remove",
+                    "env.db_scan",
+                    "env.db_write",
+                    "env.debug",
+                    "env.ed25519_batch_verify",
+                    "env.ed25519_verify",
+                    "env.query_chain",
+                    "env.secp256k1_recover_pubkey",
+                    "env.secp256k1_verify",
+                ]),
+                entry_points: arities(&[
+                    ("ibc_channel_close", 2),
+                    ("ibc_channel_connect", 2),
+                    ("ibc_channel_open", 2),
+                    ("ibc_packet_ack", 2),
+                    ("ibc_packet_receive", 2),
+                    ("ibc_packet_timeout", 2),
+                    ("instantiate", 3),
+                    ("migrate", 2),
+                    ("query", 2),
+                    ("reply", 2),
+                ]),
+                non_determinism: BTreeSet::new(),
             }
         );
     }
 
+    #[test]
+    fn stats_counts_misses_and_compiles() {
+        let tmp_dir = TempDir::new().unwrap();
+        let options = CacheOptions {
+            base_dir: tmp_dir.path().to_path_buf(),
+            ..make_testing_options()
+        };
+        let cache: Cache<MockApi, MockStorage, MockQuerier> =
+            unsafe { Cache::new(options).unwrap() };
+        let checksum = cache.save_wasm(CONTRACT).unwrap();
+        assert_eq!(cache.stats().compiles, 1);
+
+        // Remove the module to enforce a miss in all caches
+        let modules_path = tmp_dir.path().join(CACHE_DIR).join(MODULES_DIR);
+        fs::remove_dir_all(&modules_path).unwrap();
+        cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        let stats = cache.stats();
+        assert_eq!(stats.misses_pinned_memory_cache, 1);
+        assert_eq!(stats.misses_memory_cache, 1);
+        assert_eq!(stats.misses_fs_cache, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.compiles, 2);
+
+        // The memory cache now contains the module
+        cache
+            .get_instance(&checksum, mock_backend(&[]), TESTING_OPTIONS)
+            .unwrap();
+        let stats = cache.stats();
+        assert_eq!(stats.hits_memory_cache, 1);
+        assert_eq!(stats.misses_pinned_memory_cache, 2);
+        assert_eq!(stats.misses_memory_cache, 1);
+        assert_eq!(stats.misses_fs_cache, 1);
+        assert_eq!(stats.compiles, 2);
+
+        // Fingerprints are computed from a fresh compilation
+        cache.instrumented_fingerprint(&checksum).unwrap();
+        assert_eq!(cache.stats().compiles, 3);
+    }
+
     #[test]
     fn pin_unpin_works() {
         let cache = unsafe { Cache::new(make_testing_options()).unwrap() };
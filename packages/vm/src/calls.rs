@@ -1,12 +1,18 @@
+use std::borrow::Cow;
+
 use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use wasmer::Val;
 
-use cosmwasm_std::{ContractResult, CustomMsg, Env, MessageInfo, QueryResponse, Reply, Response};
+use cosmwasm_std::{
+    BankMsg, Binary, ContractResult, CosmosMsg, CustomMsg, Env, MessageInfo, QueryResponse, Reply,
+    Response, SubMsg, WasmMsg,
+};
 #[cfg(feature = "stargate")]
 use cosmwasm_std::{
     Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg,
-    IbcChannelOpenMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
-    IbcReceiveResponse,
+    IbcChannelOpenMsg, IbcDestinationCallbackMsg, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, IbcSourceCallbackMsg,
 };
 
 use crate::backend::{BackendApi, Querier, Storage};
@@ -54,6 +60,12 @@ mod read_limits {
     /// Max length (in bytes) of the result data from a ibc_packet_timeout call.
     #[cfg(feature = "stargate")]
     pub const RESULT_IBC_PACKET_TIMEOUT: usize = 64 * MI;
+    /// Max length (in bytes) of the result data from a ibc_source_callback call.
+    #[cfg(feature = "stargate")]
+    pub const RESULT_IBC_SOURCE_CALLBACK: usize = 64 * MI;
+    /// Max length (in bytes) of the result data from a ibc_destination_callback call.
+    #[cfg(feature = "stargate")]
+    pub const RESULT_IBC_DESTINATION_CALLBACK: usize = 64 * MI;
 }
 
 /// The limits for the JSON deserialization.
@@ -93,6 +105,12 @@ mod deserialization_limits {
     /// Max length (in bytes) of the result data from a ibc_packet_timeout call.
     #[cfg(feature = "stargate")]
     pub const RESULT_IBC_PACKET_TIMEOUT: usize = 256 * KI;
+    /// Max length (in bytes) of the result data from a ibc_source_callback call.
+    #[cfg(feature = "stargate")]
+    pub const RESULT_IBC_SOURCE_CALLBACK: usize = 256 * KI;
+    /// Max length (in bytes) of the result data from a ibc_destination_callback call.
+    #[cfg(feature = "stargate")]
+    pub const RESULT_IBC_DESTINATION_CALLBACK: usize = 256 * KI;
 }
 
 pub fn call_instantiate<A, S, Q, U>(
@@ -109,9 +127,15 @@ where
 {
     let env = to_vec(env)?;
     let info = to_vec(info)?;
+    instance.charge_serialization(env.len() + info.len())?;
     let data = call_instantiate_raw(instance, &env, &info, msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Response<U>> =
         from_slice(&data, deserialization_limits::RESULT_INSTANTIATE)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -129,9 +153,15 @@ where
 {
     let env = to_vec(env)?;
     let info = to_vec(info)?;
+    instance.charge_serialization(env.len() + info.len())?;
     let data = call_execute_raw(instance, &env, &info, msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Response<U>> =
         from_slice(&data, deserialization_limits::RESULT_EXECUTE)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -147,9 +177,15 @@ where
     U: DeserializeOwned + CustomMsg,
 {
     let env = to_vec(env)?;
+    instance.charge_serialization(env.len())?;
     let data = call_migrate_raw(instance, &env, msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Response<U>> =
         from_slice(&data, deserialization_limits::RESULT_MIGRATE)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -165,9 +201,15 @@ where
     U: DeserializeOwned + CustomMsg,
 {
     let env = to_vec(env)?;
+    instance.charge_serialization(env.len())?;
     let data = call_sudo_raw(instance, &env, msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Response<U>> =
         from_slice(&data, deserialization_limits::RESULT_SUDO)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -184,9 +226,15 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_reply_raw(instance, &env, &msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Response<U>> =
         from_slice(&data, deserialization_limits::RESULT_REPLY)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -201,7 +249,9 @@ where
     Q: Querier + 'static,
 {
     let env = to_vec(env)?;
+    instance.charge_serialization(env.len())?;
     let data = call_query_raw(instance, &env, msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<QueryResponse> =
         from_slice(&data, deserialization_limits::RESULT_QUERY)?;
     // Ensure query response is valid JSON
@@ -227,7 +277,9 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_channel_open_raw(instance, &env, &msg)?;
+    instance.charge_serialization(data.len())?;
     let result: ContractResult<Option<Ibc3ChannelOpenResponse>> =
         from_slice(&data, deserialization_limits::RESULT_IBC_CHANNEL_OPEN)?;
     Ok(result)
@@ -247,8 +299,15 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_channel_connect_raw(instance, &env, &msg)?;
-    let result = from_slice(&data, deserialization_limits::RESULT_IBC_CHANNEL_CONNECT)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_CHANNEL_CONNECT)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -266,8 +325,15 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_channel_close_raw(instance, &env, &msg)?;
-    let result = from_slice(&data, deserialization_limits::RESULT_IBC_CHANNEL_CLOSE)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_CHANNEL_CLOSE)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -285,8 +351,15 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_packet_receive_raw(instance, &env, &msg)?;
-    let result = from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_RECEIVE)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcReceiveResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_RECEIVE)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -304,8 +377,15 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_packet_ack_raw(instance, &env, &msg)?;
-    let result = from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_ACK)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_ACK)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -323,8 +403,69 @@ where
 {
     let env = to_vec(env)?;
     let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
     let data = call_ibc_packet_timeout_raw(instance, &env, &msg)?;
-    let result = from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_TIMEOUT)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_PACKET_TIMEOUT)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
+    Ok(result)
+}
+
+#[cfg(feature = "stargate")]
+pub fn call_ibc_source_callback<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: &Env,
+    msg: &IbcSourceCallbackMsg,
+) -> VmResult<ContractResult<IbcBasicResponse<U>>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    let env = to_vec(env)?;
+    let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
+    let data = call_ibc_source_callback_raw(instance, &env, &msg)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> =
+        from_slice(&data, deserialization_limits::RESULT_IBC_SOURCE_CALLBACK)?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
+    Ok(result)
+}
+
+#[cfg(feature = "stargate")]
+pub fn call_ibc_destination_callback<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: &Env,
+    msg: &IbcDestinationCallbackMsg,
+) -> VmResult<ContractResult<IbcBasicResponse<U>>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    let env = to_vec(env)?;
+    let msg = to_vec(msg)?;
+    instance.charge_serialization(env.len() + msg.len())?;
+    let data = call_ibc_destination_callback_raw(instance, &env, &msg)?;
+    instance.charge_serialization(data.len())?;
+    let result: ContractResult<IbcBasicResponse<U>> = from_slice(
+        &data,
+        deserialization_limits::RESULT_IBC_DESTINATION_CALLBACK,
+    )?;
+    if let ContractResult::Ok(response) = &result {
+        validate_custom_msgs(instance, &response.messages)?;
+        validate_msg_addresses(instance, &response.messages)?;
+    }
     Ok(result)
 }
 
@@ -341,6 +482,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -363,6 +505,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -384,6 +527,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -405,6 +549,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(instance, "sudo", &[env, msg], read_limits::RESULT_SUDO)
 }
@@ -421,6 +566,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(instance, "reply", &[env, msg], read_limits::RESULT_REPLY)
 }
@@ -437,6 +583,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(true);
     call_raw(instance, "query", &[env, msg], read_limits::RESULT_QUERY)
 }
@@ -452,6 +599,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -472,6 +620,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -492,6 +641,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -512,6 +662,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -532,6 +683,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -552,6 +704,7 @@ where
     S: Storage + 'static,
     Q: Querier + 'static,
 {
+    instance.check_msg_size(msg.len())?;
     instance.set_storage_readonly(false);
     call_raw(
         instance,
@@ -561,6 +714,105 @@ where
     )
 }
 
+#[cfg(feature = "stargate")]
+pub fn call_ibc_source_callback_raw<A, S, Q>(
+    instance: &mut Instance<A, S, Q>,
+    env: &[u8],
+    msg: &[u8],
+) -> VmResult<Vec<u8>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    instance.check_msg_size(msg.len())?;
+    instance.set_storage_readonly(false);
+    call_raw(
+        instance,
+        "ibc_source_callback",
+        &[env, msg],
+        read_limits::RESULT_IBC_SOURCE_CALLBACK,
+    )
+}
+
+#[cfg(feature = "stargate")]
+pub fn call_ibc_destination_callback_raw<A, S, Q>(
+    instance: &mut Instance<A, S, Q>,
+    env: &[u8],
+    msg: &[u8],
+) -> VmResult<Vec<u8>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    instance.check_msg_size(msg.len())?;
+    instance.set_storage_readonly(false);
+    call_raw(
+        instance,
+        "ibc_destination_callback",
+        &[env, msg],
+        read_limits::RESULT_IBC_DESTINATION_CALLBACK,
+    )
+}
+
+/// Passes all `CosmosMsg::Custom` messages of a contract response to the backend
+/// for validation (see [`BackendApi::validate_custom_msg`]).
+fn validate_custom_msgs<A, S, Q, U>(
+    instance: &Instance<A, S, Q>,
+    messages: &[SubMsg<U>],
+) -> VmResult<()>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: CustomMsg,
+{
+    for sub_msg in messages {
+        if let CosmosMsg::Custom(custom) = &sub_msg.msg {
+            instance.validate_custom_msg(Binary(to_vec(custom)?))?;
+        }
+    }
+    Ok(())
+}
+
+/// Validates the recipients of bank messages and the contract and admin addresses of
+/// wasm messages in a contract response if enabled via
+/// [`InstanceOptions::validate_msg_addresses`](crate::InstanceOptions::validate_msg_addresses).
+fn validate_msg_addresses<A, S, Q, U>(
+    instance: &Instance<A, S, Q>,
+    messages: &[SubMsg<U>],
+) -> VmResult<()>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    if !instance.validates_msg_addresses() {
+        return Ok(());
+    }
+    for sub_msg in messages {
+        let addresses: Vec<&str> = match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => vec![to_address],
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })
+            | CosmosMsg::Wasm(WasmMsg::Migrate { contract_addr, .. })
+            | CosmosMsg::Wasm(WasmMsg::ClearAdmin { contract_addr }) => vec![contract_addr],
+            CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+                contract_addr,
+                admin,
+            }) => vec![contract_addr, admin],
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: Some(admin), ..
+            }) => vec![admin],
+            _ => vec![],
+        };
+        for addr in addresses {
+            instance.validate_address(addr)?;
+        }
+    }
+    Ok(())
+}
+
 /// Calls a function with the given arguments.
 /// The exported function must return exactly one result (an offset to the result Region).
 pub(crate) fn call_raw<A, S, Q>(
@@ -569,6 +821,52 @@ pub(crate) fn call_raw<A, S, Q>(
     args: &[&[u8]],
     result_max_length: usize,
 ) -> VmResult<Vec<u8>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    // the read cache must not outlive a single call
+    instance.clear_storage_read_cache();
+    // the debug message limit applies per call
+    instance.reset_debug_messages();
+    // the write limit applies per call
+    instance.reset_storage_writes();
+    // all entry points take the env as first argument, which is only parsed if needed
+    if !instance.allows_reentrant_queries() {
+        instance.set_contract_address(args.first().and_then(|env| contract_address(env)));
+    }
+
+    let gas_before = instance.create_gas_report();
+    let result = call_raw_inner(instance, name, args, result_max_length);
+    instance.record_call(name, &gas_before);
+    result
+}
+
+/// Extracts the address of the executing contract from a serialized [`Env`].
+/// Returns `None` if the env cannot be parsed.
+fn contract_address(env: &[u8]) -> Option<String> {
+    #[derive(Deserialize)]
+    struct PartialEnv<'a> {
+        #[serde(borrow)]
+        contract: PartialContractInfo<'a>,
+    }
+    #[derive(Deserialize)]
+    struct PartialContractInfo<'a> {
+        #[serde(borrow)]
+        address: Cow<'a, str>,
+    }
+    serde_json::from_slice::<PartialEnv>(env)
+        .ok()
+        .map(|env| env.contract.address.into_owned())
+}
+
+fn call_raw_inner<A, S, Q>(
+    instance: &mut Instance<A, S, Q>,
+    name: &str,
+    args: &[&[u8]],
+    result_max_length: usize,
+) -> VmResult<Vec<u8>>
 where
     A: BackendApi + 'static,
     S: Storage + 'static,
@@ -580,7 +878,9 @@ where
         instance.write_memory(region_ptr, arg)?;
         arg_region_ptrs.push(region_ptr.into());
     }
-    let result = instance.call_function1(name, &arg_region_ptrs)?;
+    let result = instance
+        .call_function1(name, &arg_region_ptrs)
+        .map_err(|err| err.context(format!("in call_{}", name)))?;
     let res_region_ptr = ref_to_u32(&result)?;
     let data = instance.read_memory(res_region_ptr, result_max_length)?;
     // free return value in wasm (arguments were freed in wasm code)
@@ -591,8 +891,14 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testing::{mock_env, mock_info, mock_instance};
-    use cosmwasm_std::{coins, Empty};
+    use crate::backend::{Backend, BackendError, BackendResult, GasInfo};
+    use crate::environment::{GasConfig, StorageLimits};
+    use crate::size::Size;
+    use crate::testing::{
+        mock_env, mock_info, mock_instance, mock_instance_options, MockApi, MockInstanceBuilder,
+        MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{coins, BankMsg, Empty};
 
     static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
     static CYBERPUNK: &[u8] = include_bytes!("../testdata/cyberpunk.wasm");
@@ -609,6 +915,132 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn call_instantiate_reports_api_errors_like_contracts() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "x", "beneficiary": "benefits"}"#;
+        let err = call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap_err();
+
+        let (result, _gas_info) = MockApi::default().canonical_address("x");
+        let expected = result.unwrap_err().into_contract_error("addr_validate");
+        assert_eq!(err, expected.to_string());
+    }
+
+    #[test]
+    fn call_instantiate_charges_serialization() {
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        let instantiate = |serialization_per_byte_cost| {
+            let mut instance = MockInstanceBuilder::new(CONTRACT)
+                .gas_config(GasConfig {
+                    serialization_per_byte_cost,
+                    ..GasConfig::default()
+                })
+                .build();
+            let gas_before = instance.get_gas_left();
+            call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+                .unwrap()
+                .unwrap();
+            gas_before - instance.get_gas_left()
+        };
+
+        let env = to_vec(&mock_env()).unwrap();
+        let info = to_vec(&info).unwrap();
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let result = call_instantiate_raw(&mut instance, &env, &info, msg).unwrap();
+        let serialized = env.len() + info.len() + result.len();
+        assert_eq!(instantiate(1000) - instantiate(0), 1000 * serialized as u64);
+    }
+
+    #[test]
+    fn call_instantiate_raw_rejects_large_msg() {
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        let env = to_vec(&mock_env()).unwrap();
+        let info = to_vec(&mock_info("creator", &[])).unwrap();
+
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .max_msg_size(Size(msg.len() - 1))
+            .build();
+        let gas_before = instance.get_gas_left();
+        match call_instantiate_raw(&mut instance, &env, &info, msg).unwrap_err() {
+            VmError::InputTooLarge { size, max, .. } => {
+                assert_eq!(size, msg.len());
+                assert_eq!(max, msg.len() - 1);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        // the contract was not called
+        assert_eq!(instance.get_gas_left(), gas_before);
+
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .max_msg_size(Size(msg.len()))
+            .build();
+        call_instantiate_raw(&mut instance, &env, &info, msg).unwrap();
+    }
+
+    #[test]
+    fn contract_address_works() {
+        let env = to_vec(&mock_env()).unwrap();
+        assert_eq!(
+            contract_address(&env),
+            Some(mock_env().contract.address.to_string())
+        );
+        assert_eq!(contract_address(b"{}"), None);
+    }
+
+    #[test]
+    fn validate_msg_addresses_works() {
+        let valid: Vec<SubMsg> = vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(1, "earth"),
+            }),
+            SubMsg::new(WasmMsg::UpdateAdmin {
+                contract_addr: "contract".to_string(),
+                admin: "admin".to_string(),
+            }),
+        ];
+        let invalid: Vec<SubMsg> = vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "Contract".to_string(),
+            msg: Binary::from(b"{}"),
+            funds: vec![],
+        })];
+
+        // disabled by default
+        let instance = mock_instance(CONTRACT, &[]);
+        validate_msg_addresses(&instance, &invalid).unwrap();
+
+        let instance = MockInstanceBuilder::new(CONTRACT)
+            .validate_msg_addresses(true)
+            .build();
+        let gas_before = instance.get_gas_left();
+        validate_msg_addresses(&instance, &valid).unwrap();
+        // canonicalize and humanize for each of the three addresses
+        assert!(instance.get_gas_left() < gas_before);
+        match validate_msg_addresses(&instance, &invalid).unwrap_err() {
+            VmError::InvalidMsgAddress { addr, msg, .. } => {
+                assert_eq!(addr, "Contract");
+                assert_eq!(msg, "Address is not normalized");
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        let too_short: Vec<SubMsg> = vec![SubMsg::new(BankMsg::Send {
+            to_address: "ab".to_string(),
+            amount: coins(1, "earth"),
+        })];
+        match validate_msg_addresses(&instance, &too_short).unwrap_err() {
+            VmError::InvalidMsgAddress { addr, msg, .. } => {
+                assert_eq!(addr, "ab");
+                assert_eq!(msg, "Invalid input: human address too short");
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
     #[test]
     fn call_execute_works() {
         let mut instance = mock_instance(CONTRACT, &[]);
@@ -628,6 +1060,116 @@ mod tests {
             .unwrap();
     }
 
+    /// An API that rejects all custom messages
+    #[derive(Clone)]
+    struct RejectingApi(MockApi);
+
+    impl BackendApi for RejectingApi {
+        fn canonical_address(&self, human: &str) -> BackendResult<Vec<u8>> {
+            self.0.canonical_address(human)
+        }
+
+        fn human_address(&self, canonical: &[u8]) -> BackendResult<String> {
+            self.0.human_address(canonical)
+        }
+
+        fn validate_custom_msg(&self, msg: Binary) -> BackendResult<()> {
+            let err = BackendError::user_err(format!("unsupported message {}", msg.to_base64()));
+            (Err(err), GasInfo::with_cost(1000))
+        }
+    }
+
+    fn rejecting_instance() -> Instance<RejectingApi, MockStorage, MockQuerier> {
+        let backend = Backend {
+            api: RejectingApi(MockApi::default()),
+            storage: MockStorage::default(),
+            querier: MockQuerier::new(&[]),
+        };
+        let (options, memory_limit) = mock_instance_options();
+        Instance::from_code(CONTRACT, backend, options, memory_limit).unwrap()
+    }
+
+    #[test]
+    fn validate_custom_msgs_works() {
+        let instance = rejecting_instance();
+
+        // no custom messages
+        let messages: Vec<SubMsg<Empty>> = vec![SubMsg::new(BankMsg::Burn {
+            amount: coins(12, "earth"),
+        })];
+        validate_custom_msgs(&instance, &messages).unwrap();
+
+        // custom message is rejected and charged
+        let gas_before = instance.get_gas_left();
+        let messages: Vec<SubMsg<Empty>> = vec![SubMsg::new(CosmosMsg::Custom(Empty {}))];
+        let err = validate_custom_msgs(&instance, &messages).unwrap_err();
+        match err {
+            VmError::CustomMsgRejected { msg, .. } => {
+                assert_eq!(msg, "unsupported message e30=") // base64 of `{}`
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(gas_before - instance.get_gas_left(), 1000);
+    }
+
+    #[test]
+    fn call_execute_validates_custom_msgs_only() {
+        let mut instance = rejecting_instance();
+
+        // hackatom only emits bank messages, which are not validated
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        let info = mock_info("verifies", &coins(15, "earth"));
+        let msg = br#"{"release":{}}"#;
+        let response = call_execute::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.messages.len(), 1);
+    }
+
+    #[test]
+    fn call_instantiate_enforces_max_writes_per_call() {
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+
+        // hackatom writes its config once during instantiation
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .storage_limits(StorageLimits {
+                max_writes_per_call: Some(0),
+                ..StorageLimits::default()
+            })
+            .build();
+        let err =
+            call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg).unwrap_err();
+        match &err {
+            VmError::RuntimeErr { msg, .. } => {
+                assert!(msg.contains("Too many storage writes in a single call. Limit: 0"))
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(
+            err.breadcrumbs(),
+            ["during db_write", "in call_instantiate"]
+        );
+
+        // the limit applies to each call separately
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .storage_limits(StorageLimits {
+                max_writes_per_call: Some(1),
+                ..StorageLimits::default()
+            })
+            .build();
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+    }
+
     #[test]
     fn call_execute_runs_out_of_gas() {
         let mut instance = mock_instance(CYBERPUNK, &[]);
@@ -643,7 +1185,7 @@ mod tests {
         let msg = br#"{"cpu_loop":{}}"#;
         let err =
             call_execute::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg).unwrap_err();
-        assert!(matches!(err, VmError::GasDepletion {}));
+        assert!(matches!(err, VmError::GasDepletion { .. }));
     }
 
     #[test]
@@ -694,14 +1236,16 @@ mod tests {
         use super::*;
         use crate::calls::{call_instantiate, call_reply};
         use crate::testing::{
-            mock_env, mock_info, mock_instance, MockApi, MockQuerier, MockStorage,
+            mock_backend, mock_env, mock_info, mock_instance, mock_instance_options, MockApi,
+            MockQuerier, MockStorage,
         };
         use cosmwasm_std::testing::{
             mock_ibc_channel_close_init, mock_ibc_channel_connect_ack, mock_ibc_channel_open_init,
-            mock_ibc_packet_ack, mock_ibc_packet_recv, mock_ibc_packet_timeout, mock_wasmd_attr,
+            mock_ibc_destination_callback, mock_ibc_packet_ack, mock_ibc_packet_recv,
+            mock_ibc_packet_timeout, mock_ibc_source_callback_ack, mock_wasmd_attr,
         };
         use cosmwasm_std::{
-            Empty, Event, IbcAcknowledgement, IbcOrder, Reply, ReplyOn, SubMsgResponse,
+            Attribute, Empty, Event, IbcAcknowledgement, IbcOrder, Reply, ReplyOn, SubMsgResponse,
             SubMsgResult,
         };
         static CONTRACT: &[u8] = include_bytes!("../testdata/ibc_reflect.wasm");
@@ -790,6 +1334,114 @@ mod tests {
                 .unwrap()
                 .unwrap();
         }
+        #[test]
+        fn call_ibc_callbacks_call_the_right_exports() {
+            // ibc_reflect does not implement the callbacks, so the calls fail with the export names
+            let mut instance = mock_instance(CONTRACT, &[]);
+            setup(&mut instance, CHANNEL_ID, ACCOUNT);
+            let msg =
+                mock_ibc_source_callback_ack(CHANNEL_ID, br#"{}"#, IbcAcknowledgement::new(b"{}"))
+                    .unwrap();
+            let err = call_ibc_source_callback::<_, _, _, Empty>(&mut instance, &mock_env(), &msg)
+                .unwrap_err();
+            assert!(err.to_string().contains("ibc_source_callback"), "{}", err);
+
+            let msg =
+                mock_ibc_destination_callback(CHANNEL_ID, br#"{}"#, IbcAcknowledgement::new(b"{}"))
+                    .unwrap();
+            let err =
+                call_ibc_destination_callback::<_, _, _, Empty>(&mut instance, &mock_env(), &msg)
+                    .unwrap_err();
+            assert!(
+                err.to_string().contains("ibc_destination_callback"),
+                "{}",
+                err
+            );
+        }
+
+        /// A contract implementing the IBC callbacks, which respond with an attribute
+        /// naming the called entry point
+        fn ibc_callbacks_contract() -> Vec<u8> {
+            let response = |entry_point: &str| {
+                format!(
+                    r#"{{"ok":{{"messages":[],"attributes":[{{"key":"action","value":"{}"}}],"events":[]}}}}"#,
+                    entry_point
+                )
+            };
+            let source = response("ibc_source_callback");
+            let destination = response("ibc_destination_callback");
+            // The responses are stored at 1024 and 2048. The region of the returned
+            // response is written to 16. allocate is a bump allocator starting at 4096
+            // which keeps regions 4 byte aligned.
+            let wat = format!(
+                r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 4096))
+                (data (i32.const 1024) "{}")
+                (data (i32.const 2048) "{}")
+                (func $respond (param $offset i32) (param $length i32) (result i32)
+                    (i32.store (i32.const 16) (local.get $offset))
+                    (i32.store (i32.const 20) (local.get $length))
+                    (i32.store (i32.const 24) (local.get $length))
+                    (i32.const 16))
+                (func (export "allocate") (param $size i32) (result i32)
+                    (local $region i32)
+                    (local.set $region (global.get $next))
+                    (i32.store (local.get $region) (i32.add (local.get $region) (i32.const 12)))
+                    (i32.store offset=4 (local.get $region) (local.get $size))
+                    (i32.store offset=8 (local.get $region) (i32.const 0))
+                    (global.set $next
+                        (i32.and
+                            (i32.add (local.get $region) (i32.add (local.get $size) (i32.const 15)))
+                            (i32.const -4)))
+                    (local.get $region))
+                (func (export "deallocate") (param i32))
+                (func (export "interface_version_8"))
+                (func (export "ibc_source_callback") (param i32 i32) (result i32)
+                    (call $respond (i32.const 1024) (i32.const {})))
+                (func (export "ibc_destination_callback") (param i32 i32) (result i32)
+                    (call $respond (i32.const 2048) (i32.const {})))
+                )"#,
+                source.replace('"', "\\\""),
+                destination.replace('"', "\\\""),
+                source.len(),
+                destination.len(),
+            );
+            wat::parse_str(wat).unwrap()
+        }
+
+        #[test]
+        fn call_ibc_callbacks_works() {
+            let wasm = ibc_callbacks_contract();
+            let (options, memory_limit) = mock_instance_options();
+            let mut instance =
+                Instance::from_code(&wasm, mock_backend(&[]), options, memory_limit).unwrap();
+
+            let msg =
+                mock_ibc_source_callback_ack(CHANNEL_ID, br#"{}"#, IbcAcknowledgement::new(b"{}"))
+                    .unwrap();
+            let res = call_ibc_source_callback::<_, _, _, Empty>(&mut instance, &mock_env(), &msg)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                res.attributes,
+                [Attribute::new("action", "ibc_source_callback")]
+            );
+
+            let msg =
+                mock_ibc_destination_callback(CHANNEL_ID, br#"{}"#, IbcAcknowledgement::new(b"{}"))
+                    .unwrap();
+            let res =
+                call_ibc_destination_callback::<_, _, _, Empty>(&mut instance, &mock_env(), &msg)
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(
+                res.attributes,
+                [Attribute::new("action", "ibc_destination_callback")]
+            );
+            assert_eq!(res.messages, []);
+        }
+
         #[test]
         fn call_ibc_packet_receive_works() {
             let mut instance = mock_instance(CONTRACT, &[]);
@@ -800,5 +1452,23 @@ mod tests {
                 .unwrap()
                 .unwrap();
         }
+
+        #[test]
+        fn call_ibc_packet_receive_returns_ack_and_events_separately() {
+            let mut instance = mock_instance(CONTRACT, &[]);
+            setup(&mut instance, CHANNEL_ID, ACCOUNT);
+            // an unknown packet is turned into an error acknowledgement by the contract
+            let msg = mock_ibc_packet_recv(CHANNEL_ID, br#"{"unknown":{}}"#).unwrap();
+            let res = call_ibc_packet_receive::<_, _, _, Empty>(&mut instance, &mock_env(), &msg)
+                .unwrap()
+                .unwrap();
+            let ack: serde_json::Value = res.acknowledgement_as().unwrap();
+            assert!(ack["error"].as_str().unwrap().starts_with("invalid packet"));
+            assert_eq!(res.events_of_type("ibc").count(), 1);
+            assert_eq!(
+                res.events[0].attributes,
+                [Attribute::new("packet", "receive")]
+            );
+        }
     }
 }
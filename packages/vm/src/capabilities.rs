@@ -1,8 +1,64 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
 
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::environment::{GasConfig, GasMultiplier};
 use crate::static_analysis::ExportInfo;
 
-const REQUIRES_PREFIX: &str = "requires_";
+pub(crate) const REQUIRES_PREFIX: &str = "requires_";
+
+/// Imports that may only be used on chains with the given capability, in addition
+/// to the capabilities the contract requires explicitly via `requires_*` exports.
+/// Those imports are only provided to instances if the capability is available.
+pub(crate) const CAPABILITY_GATED_IMPORTS: &[(&str, &str)] = &[
+    ("env.secp256r1_verify", "secp256r1"),
+    ("env.secp256r1_recover_pubkey", "secp256r1"),
+    ("env.bls12_381_aggregate_verify", "bls12_381"),
+    ("env.bls12_381_pairing_equality", "bls12_381"),
+    ("env.bls12_381_hash_to_g1", "bls12_381"),
+    ("env.bls12_381_hash_to_g2", "bls12_381"),
+    ("env.poseidon_hash", "zk"),
+    ("env.zk_verify_groth16", "zk"),
+    ("env.tendermint_verify_header", "tendermint"),
+    ("env.secp256k1_verify_prehashed", "cosmwasm_1_2"),
+    ("env.ed25519_batch_verify_packed", "cosmwasm_1_2"),
+    ("env.ct_eq", "cosmwasm_1_2"),
+    ("env.ics23_verify_membership", "cosmwasm_1_2"),
+    ("env.ics23_verify_non_membership", "cosmwasm_1_2"),
+    #[cfg(feature = "iterator")]
+    ("env.db_scan", "iterator"),
+    #[cfg(feature = "iterator")]
+    ("env.db_scan_with_limit", "iterator"),
+    #[cfg(feature = "iterator")]
+    ("env.db_next", "iterator"),
+];
+
+/// Returns the capability needed for using the given import (in the format `module.name`),
+/// if there is any.
+pub(crate) fn import_capability(full_name: &str) -> Option<&'static str> {
+    CAPABILITY_GATED_IMPORTS
+        .iter()
+        .find(|(name, _)| *name == full_name)
+        .map(|(_, capability)| *capability)
+}
+
+/// Returns the multiplier of the gas config that prices the gas reported by the backend for
+/// the given capability, if there is one.
+fn gas_config_multiplier<'a>(
+    gas_config: &'a mut GasConfig,
+    capability: &str,
+) -> Option<&'a mut GasMultiplier> {
+    match capability {
+        "iterator" => Some(&mut gas_config.iterator_multiplier),
+        "stargate" => Some(&mut gas_config.stargate_query_multiplier),
+        _ => None,
+    }
+}
 
 /// Takes a comma-separated string, splits it by commas, removes empty elements and returns a set of capabilities.
 /// This can be used e.g. to initialize the cache.
@@ -13,6 +69,257 @@ pub fn capabilities_from_csv(csv: &str) -> HashSet<String> {
         .collect()
 }
 
+/// The capabilities a chain provides to contracts.
+///
+/// In contrast to a plain set of strings, every capability is validated when it is registered.
+/// A capability consists of one or more segments separated by `.` (e.g. `iterator`,
+/// `token_factory` or `osmosis.v1`). Each segment is made of lowercase ASCII letters,
+/// digits and `_` and starts with a letter.
+///
+/// This dereferences to the underlying set, such that it can be used wherever
+/// a `&HashSet<String>` of available capabilities is expected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    set: HashSet<String>,
+    /// Gas multipliers configured for individual capabilities
+    gas_multipliers: HashMap<String, GasMultiplier>,
+}
+
+/// Error raised when loading capabilities from a config using [`Capabilities::from_config`]
+/// or [`Capabilities::from_config_file`]
+#[derive(Error, Debug)]
+pub enum CapabilitiesConfigError {
+    #[error("Error reading capabilities config file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error parsing capabilities config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error(transparent)]
+    InvalidCapability(#[from] InvalidCapabilityError),
+    #[error("Capability {capability:?} is configured more than once")]
+    Duplicate { capability: String },
+    #[error("Gas multiplier of capability {capability:?} must have a non-zero denominator")]
+    ZeroDenominator { capability: String },
+    #[error("Capability {capability:?} does not support a gas multiplier")]
+    UnsupportedGasMultiplier { capability: String },
+}
+
+/// The structure of a capabilities config. See [`Capabilities::from_config`].
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CapabilitiesConfig {
+    #[serde(default)]
+    capability: Vec<CapabilityConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CapabilityConfig {
+    name: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    gas_multiplier: Option<GasMultiplierConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GasMultiplierConfig {
+    numerator: u64,
+    denominator: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Error raised when registering a capability that is not syntactically valid
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("Invalid capability {capability:?}: {reason}")]
+pub struct InvalidCapabilityError {
+    pub capability: String,
+    pub reason: &'static str,
+}
+
+impl Capabilities {
+    /// Creates an empty set of capabilities
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a comma-separated list of capabilities, ignoring whitespace around
+    /// the elements as well as empty elements.
+    pub fn from_csv(csv: &str) -> Result<Self, InvalidCapabilityError> {
+        let mut out = Self::new();
+        for capability in capabilities_from_csv(csv) {
+            out.register(capability)?;
+        }
+        Ok(out)
+    }
+
+    /// Parses capabilities from a TOML config like this:
+    ///
+    /// ```toml
+    /// [[capability]]
+    /// name = "iterator"
+    ///
+    /// [[capability]]
+    /// name = "stargate"
+    /// enabled = false
+    ///
+    /// [[capability]]
+    /// name = "iterator"
+    /// gas_multiplier = { numerator = 3, denominator = 2 }
+    ///
+    /// [[capability]]
+    /// name = "osmosis.v1"
+    /// ```
+    ///
+    /// `enabled` defaults to `true`. Disabled capabilities are validated but not added to the set.
+    ///
+    /// The optional `gas_multiplier` prices the gas reported by the backend for the capability.
+    /// It is supported for `iterator` (see [`GasConfig::iterator_multiplier`]) and `stargate`
+    /// (see [`GasConfig::stargate_query_multiplier`]) and applied using
+    /// [`Capabilities::apply_gas_multipliers`].
+    pub fn from_config(toml: &str) -> Result<Self, CapabilitiesConfigError> {
+        let config: CapabilitiesConfig = toml::from_str(toml)?;
+        let mut out = Self::new();
+        let mut seen = HashSet::new();
+        for CapabilityConfig {
+            name,
+            enabled,
+            gas_multiplier,
+        } in config.capability
+        {
+            if let Err(reason) = validate_capability(&name) {
+                return Err(InvalidCapabilityError {
+                    capability: name,
+                    reason,
+                }
+                .into());
+            }
+            if !seen.insert(name.clone()) {
+                return Err(CapabilitiesConfigError::Duplicate { capability: name });
+            }
+            if let Some(GasMultiplierConfig {
+                numerator,
+                denominator,
+            }) = gas_multiplier
+            {
+                if gas_config_multiplier(&mut GasConfig::default(), &name).is_none() {
+                    return Err(CapabilitiesConfigError::UnsupportedGasMultiplier {
+                        capability: name,
+                    });
+                }
+                let multiplier = match GasMultiplier::new(numerator, denominator) {
+                    Some(multiplier) => multiplier,
+                    None => {
+                        return Err(CapabilitiesConfigError::ZeroDenominator { capability: name })
+                    }
+                };
+                if enabled {
+                    out.gas_multipliers.insert(name.clone(), multiplier);
+                }
+            }
+            if !enabled {
+                continue;
+            }
+            out.set.insert(name);
+        }
+        Ok(out)
+    }
+
+    /// Like [`Capabilities::from_config`] but reads the config from the file at `path`.
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, CapabilitiesConfigError> {
+        let toml = fs::read_to_string(path)?;
+        Self::from_config(&toml)
+    }
+
+    /// Validates the capability and adds it to the set.
+    /// Registering an existing capability again has no effect.
+    pub fn register(
+        &mut self,
+        capability: impl Into<String>,
+    ) -> Result<(), InvalidCapabilityError> {
+        let capability = capability.into();
+        if let Err(reason) = validate_capability(&capability) {
+            return Err(InvalidCapabilityError { capability, reason });
+        }
+        self.set.insert(capability);
+        Ok(())
+    }
+
+    /// Returns the gas multiplier configured for the given capability, if any
+    pub fn gas_multiplier(&self, capability: &str) -> Option<GasMultiplier> {
+        self.gas_multipliers.get(capability).copied()
+    }
+
+    /// Returns the given gas config with the multipliers of all capabilities that have one
+    /// configured. They replace the corresponding multipliers of the gas config.
+    pub fn apply_gas_multipliers(&self, mut gas_config: GasConfig) -> GasConfig {
+        for (capability, multiplier) in &self.gas_multipliers {
+            if let Some(target) = gas_config_multiplier(&mut gas_config, capability) {
+                *target = *multiplier;
+            }
+        }
+        gas_config
+    }
+
+    /// Returns the underlying set of capabilities
+    pub fn as_set(&self) -> &HashSet<String> {
+        &self.set
+    }
+}
+
+impl Deref for Capabilities {
+    type Target = HashSet<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.set
+    }
+}
+
+impl TryFrom<HashSet<String>> for Capabilities {
+    type Error = InvalidCapabilityError;
+
+    fn try_from(set: HashSet<String>) -> Result<Self, Self::Error> {
+        let mut out = Self::new();
+        for capability in set {
+            out.register(capability)?;
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    /// Writes the capabilities as a sorted, comma-separated list
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sorted: BTreeSet<_> = self.set.iter().collect();
+        let list: Vec<_> = sorted.into_iter().map(String::as_str).collect();
+        f.write_str(&list.join(","))
+    }
+}
+
+fn validate_capability(capability: &str) -> Result<(), &'static str> {
+    if capability.is_empty() {
+        return Err("must not be empty");
+    }
+    for segment in capability.split('.') {
+        match segment.chars().next() {
+            None => return Err("must not contain empty segments"),
+            Some(first) if !first.is_ascii_lowercase() => {
+                return Err("segments must start with a lowercase letter")
+            }
+            _ => {}
+        }
+        if !segment
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err("only lowercase letters, digits, '_' and '.' are allowed");
+        }
+    }
+    Ok(())
+}
+
 /// Implementation for check_wasm, based on static analysis of the bytecode.
 /// This is used for code upload, to perform check before compiling the Wasm.
 pub fn required_capabilities_from_module(module: &impl ExportInfo) -> HashSet<String> {
@@ -58,6 +365,199 @@ mod tests {
         assert!(set.contains("b"));
     }
 
+    #[test]
+    fn capabilities_register_works() {
+        let mut capabilities = Capabilities::new();
+        capabilities.register("iterator").unwrap();
+        capabilities.register("token_factory").unwrap();
+        capabilities.register("osmosis.v1").unwrap();
+        capabilities.register("cosmwasm_1_2").unwrap();
+        capabilities.register("iterator").unwrap();
+        assert_eq!(capabilities.len(), 4);
+        assert!(capabilities.contains("osmosis.v1"));
+    }
+
+    #[test]
+    fn capabilities_register_fails_for_invalid_syntax() {
+        let mut capabilities = Capabilities::new();
+        for (capability, reason) in [
+            ("", "must not be empty"),
+            ("osmosis.", "must not contain empty segments"),
+            (".v1", "must not contain empty segments"),
+            ("a..b", "must not contain empty segments"),
+            ("Water", "segments must start with a lowercase letter"),
+            ("osmosis.1", "segments must start with a lowercase letter"),
+            ("_private", "segments must start with a lowercase letter"),
+            (
+                "token-factory",
+                "only lowercase letters, digits, '_' and '.' are allowed",
+            ),
+            (
+                "zk ",
+                "only lowercase letters, digits, '_' and '.' are allowed",
+            ),
+        ] {
+            let err = capabilities.register(capability).unwrap_err();
+            assert_eq!(
+                err,
+                InvalidCapabilityError {
+                    capability: capability.to_string(),
+                    reason
+                }
+            );
+        }
+        assert!(capabilities.is_empty());
+    }
+
+    #[test]
+    fn capabilities_from_csv_validates() {
+        let capabilities = Capabilities::from_csv(" staking,iterator ,,osmosis.v1").unwrap();
+        assert_eq!(capabilities.to_string(), "iterator,osmosis.v1,staking");
+
+        let err = Capabilities::from_csv("staking,Iterator").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid capability \"Iterator\": segments must start with a lowercase letter"
+        );
+    }
+
+    #[test]
+    fn capabilities_try_from_set_works() {
+        let set = capabilities_from_csv("iterator,staking");
+        let capabilities = Capabilities::try_from(set.clone()).unwrap();
+        assert_eq!(capabilities.as_set(), &set);
+
+        let set = capabilities_from_csv("iterator,Staking");
+        Capabilities::try_from(set).unwrap_err();
+    }
+
+    #[test]
+    fn capabilities_from_config_works() {
+        let capabilities = Capabilities::from_config(
+            r#"
+            [[capability]]
+            name = "iterator"
+            gas_multiplier = { numerator = 3, denominator = 2 }
+
+            [[capability]]
+            name = "staking"
+            enabled = true
+
+            [[capability]]
+            name = "stargate"
+            enabled = false
+            gas_multiplier = { numerator = 2, denominator = 1 }
+
+            [[capability]]
+            name = "osmosis.v1"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(capabilities.to_string(), "iterator,osmosis.v1,staking");
+        assert_eq!(
+            capabilities.gas_multiplier("iterator"),
+            GasMultiplier::new(3, 2)
+        );
+        assert_eq!(capabilities.gas_multiplier("staking"), None);
+        // not available
+        assert_eq!(capabilities.gas_multiplier("stargate"), None);
+
+        // empty config
+        let capabilities = Capabilities::from_config("").unwrap();
+        assert!(capabilities.is_empty());
+    }
+
+    #[test]
+    fn capabilities_from_config_fails_for_invalid_config() {
+        let err = Capabilities::from_config("[[capability]]\nname = \"Iterator\"").unwrap_err();
+        assert!(matches!(
+            err,
+            CapabilitiesConfigError::InvalidCapability(InvalidCapabilityError { capability, .. }) if capability == "Iterator"
+        ));
+
+        // disabled capabilities are validated as well
+        let err = Capabilities::from_config("[[capability]]\nname = \"a-b\"\nenabled = false")
+            .unwrap_err();
+        assert!(matches!(err, CapabilitiesConfigError::InvalidCapability(_)));
+
+        let err = Capabilities::from_config(
+            "[[capability]]\nname = \"zk\"\n[[capability]]\nname = \"zk\"\nenabled = false",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Capability \"zk\" is configured more than once"
+        );
+
+        let err = Capabilities::from_config(
+            "[[capability]]\nname = \"iterator\"\ngas_multiplier = { numerator = 1, denominator = 0 }",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CapabilitiesConfigError::ZeroDenominator { capability } if capability == "iterator"
+        ));
+
+        let err = Capabilities::from_config(
+            "[[capability]]\nname = \"zk\"\ngas_multiplier = { numerator = 2, denominator = 1 }",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Capability \"zk\" does not support a gas multiplier"
+        );
+
+        // unknown fields
+        let err =
+            Capabilities::from_config("[[capability]]\nname = \"zk\"\nenable = true").unwrap_err();
+        assert!(matches!(err, CapabilitiesConfigError::Parse(_)));
+    }
+
+    #[test]
+    fn capabilities_apply_gas_multipliers_works() {
+        let gas_config = GasConfig {
+            iterator_multiplier: GasMultiplier::new(5, 1).unwrap(),
+            ..GasConfig::DEFAULT
+        };
+
+        // nothing configured
+        let capabilities = Capabilities::from_csv("iterator,stargate").unwrap();
+        assert_eq!(capabilities.apply_gas_multipliers(gas_config), gas_config);
+
+        let capabilities = Capabilities::from_config(
+            r#"
+            [[capability]]
+            name = "iterator"
+            gas_multiplier = { numerator = 3, denominator = 2 }
+
+            [[capability]]
+            name = "stargate"
+            gas_multiplier = { numerator = 1, denominator = 4 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            capabilities.apply_gas_multipliers(gas_config),
+            GasConfig {
+                iterator_multiplier: GasMultiplier::new(3, 2).unwrap(),
+                stargate_query_multiplier: GasMultiplier::new(1, 4).unwrap(),
+                ..GasConfig::DEFAULT
+            }
+        );
+    }
+
+    #[test]
+    fn capabilities_from_config_file_works() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("capabilities.toml");
+        fs::write(&path, "[[capability]]\nname = \"iterator\"\n").unwrap();
+        let capabilities = Capabilities::from_config_file(&path).unwrap();
+        assert_eq!(capabilities.to_string(), "iterator");
+
+        let err = Capabilities::from_config_file(dir.path().join("missing.toml")).unwrap_err();
+        assert!(matches!(err, CapabilitiesConfigError::Io(_)));
+    }
+
     #[test]
     fn required_capabilities_from_module_works() {
         let wasm = wat::parse_str(
@@ -1,15 +1,18 @@
-use parity_wasm::elements::{External, ImportEntry, Module};
+use parity_wasm::elements::{External, ImportEntry, Module, ValueType};
 use std::collections::BTreeSet;
 use std::collections::HashSet;
 
-use crate::capabilities::required_capabilities_from_module;
+use crate::capabilities::{import_capability, required_capabilities_from_module};
 use crate::errors::{VmError, VmResult};
 use crate::limited::LimitedDisplay;
-use crate::static_analysis::{deserialize_wasm, ExportInfo};
+use crate::static_analysis::{
+    deserialize_wasm, exported_function_type, ibc_entry_points, ExportInfo, IbcEntryPoints,
+    IBC_CALLBACK_EXPORTS, REQUIRED_IBC_EXPORTS,
+};
 
 /// Lists all imports we provide upon instantiating the instance in Instance::from_module()
 /// This should be updated when new imports are added
-const SUPPORTED_IMPORTS: &[&str] = &[
+pub(crate) const SUPPORTED_IMPORTS: &[&str] = &[
     "env.abort",
     "env.db_read",
     "env.db_write",
@@ -18,15 +21,31 @@ const SUPPORTED_IMPORTS: &[&str] = &[
     "env.addr_canonicalize",
     "env.addr_humanize",
     "env.secp256k1_verify",
+    "env.secp256k1_verify_prehashed",
     "env.secp256k1_recover_pubkey",
+    "env.secp256r1_verify",
+    "env.secp256r1_recover_pubkey",
     "env.ed25519_verify",
     "env.ed25519_batch_verify",
+    "env.ed25519_batch_verify_packed",
+    "env.ics23_verify_membership",
+    "env.ics23_verify_non_membership",
+    "env.ct_eq",
     "env.debug",
     "env.query_chain",
+    "env.bls12_381_aggregate_verify",
+    "env.bls12_381_pairing_equality",
+    "env.bls12_381_hash_to_g1",
+    "env.bls12_381_hash_to_g2",
+    "env.poseidon_hash",
+    "env.zk_verify_groth16",
+    "env.tendermint_verify_header",
     #[cfg(feature = "iterator")]
     "env.db_scan",
     #[cfg(feature = "iterator")]
     "env.db_next",
+    #[cfg(feature = "iterator")]
+    "env.db_scan_with_limit",
 ];
 
 /// Lists all entry points we expect to be present when calling a contract.
@@ -41,7 +60,53 @@ const REQUIRED_EXPORTS: &[&str] = &[
     "instantiate",
 ];
 
-const INTERFACE_VERSION_PREFIX: &str = "interface_version_";
+/// Signatures (params, results) of all exports the VM calls, including optional ones
+/// and the interface_version_* marker export (see [`expected_signature`]).
+/// Pointers to regions are passed as i32.
+const ENTRY_POINT_SIGNATURES: &[(&str, &[ValueType], &[ValueType])] = &[
+    ("allocate", &[ValueType::I32], &[ValueType::I32]),
+    ("deallocate", &[ValueType::I32], &[]),
+    ("instantiate", &[ValueType::I32; 3], &[ValueType::I32]),
+    ("execute", &[ValueType::I32; 3], &[ValueType::I32]),
+    ("migrate", &[ValueType::I32; 2], &[ValueType::I32]),
+    ("sudo", &[ValueType::I32; 2], &[ValueType::I32]),
+    ("reply", &[ValueType::I32; 2], &[ValueType::I32]),
+    ("query", &[ValueType::I32; 2], &[ValueType::I32]),
+    ("ibc_channel_open", &[ValueType::I32; 2], &[ValueType::I32]),
+    (
+        "ibc_channel_connect",
+        &[ValueType::I32; 2],
+        &[ValueType::I32],
+    ),
+    ("ibc_channel_close", &[ValueType::I32; 2], &[ValueType::I32]),
+    (
+        "ibc_packet_receive",
+        &[ValueType::I32; 2],
+        &[ValueType::I32],
+    ),
+    ("ibc_packet_ack", &[ValueType::I32; 2], &[ValueType::I32]),
+    (
+        "ibc_packet_timeout",
+        &[ValueType::I32; 2],
+        &[ValueType::I32],
+    ),
+    (
+        "ibc_source_callback",
+        &[ValueType::I32; 2],
+        &[ValueType::I32],
+    ),
+    (
+        "ibc_destination_callback",
+        &[ValueType::I32; 2],
+        &[ValueType::I32],
+    ),
+];
+
+pub(crate) const INTERFACE_VERSION_PREFIX: &str = "interface_version_";
+
+/// The namespace in which the VM provides its imports to contracts
+pub(crate) const DEFAULT_IMPORT_NAMESPACE: &str = "env";
+
 const SUPPORTED_INTERFACE_VERSIONS: &[&str] = &[
     "interface_version_8",
     #[cfg(feature = "allow_interface_version_7")]
@@ -50,13 +115,88 @@ const SUPPORTED_INTERFACE_VERSIONS: &[&str] = &[
 
 const MEMORY_LIMIT: u32 = 512; // in pages
 
+/// The optional entry points a chain allows contracts to export. Contracts exporting
+/// other ones are rejected when they are stored, e.g. to forbid migrations entirely
+/// on a permissioned chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllowedEntryPoints {
+    pub migrate: bool,
+    pub sudo: bool,
+    pub reply: bool,
+    /// The IBC entry points ([`REQUIRED_IBC_EXPORTS`]), which are allowed or forbidden as a set,
+    /// and the optional IBC callback entry points ([`IBC_CALLBACK_EXPORTS`])
+    pub ibc: bool,
+}
+
+impl AllowedEntryPoints {
+    /// Allows all entry points
+    pub const DEFAULT: AllowedEntryPoints = AllowedEntryPoints {
+        migrate: true,
+        sudo: true,
+        reply: true,
+        ibc: true,
+    };
+
+    /// Returns the exports that are forbidden by this configuration
+    fn forbidden_exports(&self) -> Vec<&'static str> {
+        let mut forbidden = Vec::new();
+        if !self.migrate {
+            forbidden.push("migrate");
+        }
+        if !self.sudo {
+            forbidden.push("sudo");
+        }
+        if !self.reply {
+            forbidden.push("reply");
+        }
+        if !self.ibc {
+            forbidden.extend_from_slice(REQUIRED_IBC_EXPORTS);
+            forbidden.extend_from_slice(IBC_CALLBACK_EXPORTS);
+        }
+        forbidden
+    }
+}
+
+impl Default for AllowedEntryPoints {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Checks if the data is valid wasm and compatibility with the CosmWasm API (imports and exports)
 pub fn check_wasm(wasm_code: &[u8], available_capabilities: &HashSet<String>) -> VmResult<()> {
+    check_wasm_with_extra_imports(
+        wasm_code,
+        available_capabilities,
+        &[],
+        &AllowedEntryPoints::DEFAULT,
+    )
+}
+
+/// Like [`check_wasm`] but allows the contract to use the given imports (in the format
+/// `module.name`) in addition to the ones provided by the VM, e.g. the functions of libraries.
+/// Contracts must only export the optional entry points allowed by `allowed_entry_points`.
+pub(crate) fn check_wasm_with_extra_imports(
+    wasm_code: &[u8],
+    available_capabilities: &HashSet<String>,
+    extra_imports: &[String],
+    allowed_entry_points: &AllowedEntryPoints,
+) -> VmResult<()> {
     let module = deserialize_wasm(wasm_code)?;
     check_wasm_memories(&module)?;
     check_interface_version(&module)?;
     check_wasm_exports(&module)?;
-    check_wasm_imports(&module, SUPPORTED_IMPORTS)?;
+    check_wasm_entry_points(&module, allowed_entry_points)?;
+    if extra_imports.is_empty() {
+        check_wasm_imports(&module, SUPPORTED_IMPORTS)?;
+    } else {
+        let supported_imports: Vec<&str> = SUPPORTED_IMPORTS
+            .iter()
+            .copied()
+            .chain(extra_imports.iter().map(String::as_str))
+            .collect();
+        check_wasm_imports(&module, &supported_imports)?;
+    }
     check_wasm_capabilities(&module, available_capabilities)?;
     Ok(())
 }
@@ -89,6 +229,12 @@ fn check_wasm_memories(module: &Module) -> VmResult<()> {
         )));
     }
 
+    if limits.shared() {
+        return Err(VmError::static_validation_err(
+            "Wasm contract memory must not be shared",
+        ));
+    }
+
     if limits.maximum().is_some() {
         return Err(VmError::static_validation_err(
             "Wasm contract memory's maximum must be unset. The host will set it for you.",
@@ -127,17 +273,104 @@ fn check_interface_version(module: &Module) -> VmResult<()> {
     }
 }
 
+/// Checks presence and signatures of the exports called by the VM.
+///
+/// In contrast to the other checks, this does not stop at the first problem but
+/// reports all of them in one error, one problem per line.
 fn check_wasm_exports(module: &Module) -> VmResult<()> {
+    let problems = export_problems(module);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(VmError::static_validation_err(problems.join("\n")))
+    }
+}
+
+fn check_wasm_entry_points(
+    module: &Module,
+    allowed_entry_points: &AllowedEntryPoints,
+) -> VmResult<()> {
+    let available_exports = module.exported_function_names(None);
+    let forbidden: Vec<&str> = allowed_entry_points
+        .forbidden_exports()
+        .into_iter()
+        .filter(|name| available_exports.contains(*name))
+        .collect();
+    if forbidden.is_empty() {
+        Ok(())
+    } else {
+        Err(VmError::static_validation_err(format!(
+            "Wasm contract exports entry points that are not allowed on this chain: {:?}",
+            forbidden
+        )))
+    }
+}
+
+fn export_problems(module: &Module) -> Vec<String> {
     let available_exports: HashSet<String> = module.exported_function_names(None);
+    let mut problems = Vec::<String>::new();
+
     for required_export in REQUIRED_EXPORTS {
         if !available_exports.contains(*required_export) {
-            return Err(VmError::static_validation_err(format!(
+            problems.push(format!(
                 "Wasm contract doesn't have required export: \"{}\". Exports required by VM: {:?}.",
                 required_export, REQUIRED_EXPORTS
-            )));
+            ));
         }
     }
-    Ok(())
+
+    if let IbcEntryPoints::Partial(missing) = ibc_entry_points(module) {
+        problems.push(format!(
+            "Wasm contract has an incomplete set of IBC entry points. Missing: {:?}. Either all or none of {:?} must be exported.",
+            missing, REQUIRED_IBC_EXPORTS
+        ));
+    }
+
+    // Sorted for a deterministic error message
+    let sorted_exports: BTreeSet<&String> = available_exports.iter().collect();
+    for name in sorted_exports {
+        let (params, results) = match expected_signature(name) {
+            Some(signature) => signature,
+            None => continue,
+        };
+        match exported_function_type(module, name) {
+            Some(actual) if actual.params() == params && actual.results() == results => {}
+            Some(actual) => problems.push(format!(
+                "Wasm contract export \"{}\" has wrong signature. Expected: {}, actual: {}.",
+                name,
+                format_signature(params, results),
+                format_signature(actual.params(), actual.results())
+            )),
+            None => problems.push(format!(
+                "Wasm contract export \"{}\" has no valid function type.",
+                name
+            )),
+        }
+    }
+
+    problems
+}
+
+/// Returns the expected (params, results) for exports known to the VM
+fn expected_signature(name: &str) -> Option<(&'static [ValueType], &'static [ValueType])> {
+    if name.starts_with(INTERFACE_VERSION_PREFIX) {
+        return Some((&[], &[]));
+    }
+    ENTRY_POINT_SIGNATURES
+        .iter()
+        .find(|(entry_point, ..)| *entry_point == name)
+        .map(|(_, params, results)| (*params, *results))
+}
+
+fn format_signature(params: &[ValueType], results: &[ValueType]) -> String {
+    let join = |types: &[ValueType]| {
+        types
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", join(params), join(results))
 }
 
 /// Checks if the import requirements of the contract are satisfied.
@@ -178,15 +411,23 @@ fn check_wasm_capabilities(
     module: &Module,
     available_capabilities: &HashSet<String>,
 ) -> VmResult<()> {
-    let required_capabilities = required_capabilities_from_module(module);
+    let mut required_capabilities = required_capabilities_from_module(module);
+    if let Some(import_section) = module.import_section() {
+        for import in import_section.entries() {
+            if let Some(capability) = import_capability(&full_import_name(import)) {
+                required_capabilities.insert(capability.to_string());
+            }
+        }
+    }
     if !required_capabilities.is_subset(available_capabilities) {
         // We switch to BTreeSet to get a sorted error message
         let unavailable: BTreeSet<_> = required_capabilities
             .difference(available_capabilities)
             .collect();
         return Err(VmError::static_validation_err(format!(
-            "Wasm contract requires unavailable capabilities: {}",
-            unavailable.to_string_limited(200)
+            "Wasm contract requires unavailable capabilities: {}. Available capabilities: {}",
+            unavailable.to_string_limited(200),
+            available_capabilities.to_string_limited(200)
         )));
     }
     Ok(())
@@ -342,6 +583,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_wasm_memories_shared() {
+        let wasm_shared = wat::parse_str("(module (memory 1 5 shared))").unwrap();
+        match check_wasm_memories(&deserialize_wasm(&wasm_shared).unwrap()) {
+            Err(VmError::StaticValidationErr { msg, .. }) => {
+                assert_eq!(msg, "Wasm contract memory must not be shared");
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Didn't reject wasm with shared memory"),
+        }
+    }
+
     #[test]
     fn check_interface_version_works() {
         // valid
@@ -477,10 +730,13 @@ mod tests {
             r#"(module
                 (type (func))
                 (func (type 0) nop)
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32))
+                (func (param i32 i32 i32) (result i32) i32.const 0)
                 (export "add_one" (func 0))
-                (export "allocate" (func 0))
-                (export "deallocate" (func 0))
-                (export "instantiate" (func 0))
+                (export "allocate" (func 1))
+                (export "deallocate" (func 2))
+                (export "instantiate" (func 3))
             )"#,
         )
         .unwrap();
@@ -527,6 +783,204 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_wasm_exports_reports_all_problems() {
+        // wrong signatures of allocate and interface_version_8, missing instantiate
+        let wasm = wat::parse_str(
+            r#"(module
+                (type (func))
+                (func (type 0) nop)
+                (func (param i32))
+                (func (param i32) (result i32) i32.const 0)
+                (export "interface_version_8" (func 2))
+                (export "allocate" (func 0))
+                (export "deallocate" (func 1))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        match check_wasm_exports(&module) {
+            Err(VmError::StaticValidationErr { msg, .. }) => {
+                let lines: Vec<&str> = msg.lines().collect();
+                assert_eq!(lines.len(), 3, "{}", msg);
+                assert!(lines[0]
+                    .starts_with("Wasm contract doesn't have required export: \"instantiate\""));
+                assert_eq!(lines[1], "Wasm contract export \"allocate\" has wrong signature. Expected: (i32) -> (i32), actual: () -> ().");
+                assert_eq!(lines[2], "Wasm contract export \"interface_version_8\" has wrong signature. Expected: () -> (), actual: (i32) -> (i32).");
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Didn't reject wasm with invalid api"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_exports_checks_optional_exports() {
+        // migrate must take (env, msg)
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32))
+                (func (param i32 i32 i32) (result i32) i32.const 0)
+                (export "allocate" (func 0))
+                (export "deallocate" (func 1))
+                (export "instantiate" (func 2))
+                (export "migrate" (func 2))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        match check_wasm_exports(&module) {
+            Err(VmError::StaticValidationErr { msg, .. }) => assert_eq!(
+                msg,
+                "Wasm contract export \"migrate\" has wrong signature. Expected: (i32, i32) -> (i32), actual: (i32, i32, i32) -> (i32)."
+            ),
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Didn't reject wasm with invalid api"),
+        }
+
+        // IBC callbacks take (env, msg) and do not require the other IBC entry points
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32))
+                (func (param i32 i32 i32) (result i32) i32.const 0)
+                (func (param i32 i32) (result i32) i32.const 0)
+                (export "allocate" (func 0))
+                (export "deallocate" (func 1))
+                (export "instantiate" (func 2))
+                (export "ibc_source_callback" (func 3))
+                (export "ibc_destination_callback" (func 2))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        match check_wasm_exports(&module) {
+            Err(VmError::StaticValidationErr { msg, .. }) => assert_eq!(
+                msg,
+                "Wasm contract export \"ibc_destination_callback\" has wrong signature. Expected: (i32, i32) -> (i32), actual: (i32, i32, i32) -> (i32)."
+            ),
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Didn't reject wasm with invalid api"),
+        }
+
+        // imported functions can be exported too
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "debug" (func (param i32)))
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32 i32 i32) (result i32) i32.const 0)
+                (export "allocate" (func 1))
+                (export "deallocate" (func 0))
+                (export "instantiate" (func 2))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        check_wasm_exports(&module).unwrap();
+    }
+
+    #[test]
+    fn check_wasm_exports_requires_complete_ibc_set() {
+        let ibc_exports = |names: &[&str]| {
+            let exports: String = names
+                .iter()
+                .map(|name| format!(r#"(export "{}" (func 3))"#, name))
+                .collect();
+            let wasm = wat::parse_str(format!(
+                r#"(module
+                    (func (param i32) (result i32) i32.const 0)
+                    (func (param i32))
+                    (func (param i32 i32 i32) (result i32) i32.const 0)
+                    (func (param i32 i32) (result i32) i32.const 0)
+                    (export "allocate" (func 0))
+                    (export "deallocate" (func 1))
+                    (export "instantiate" (func 2))
+                    {}
+                )"#,
+                exports
+            ))
+            .unwrap();
+            deserialize_wasm(&wasm).unwrap()
+        };
+
+        check_wasm_exports(&ibc_exports(&[])).unwrap();
+        check_wasm_exports(&ibc_exports(REQUIRED_IBC_EXPORTS)).unwrap();
+        match check_wasm_exports(&ibc_exports(&REQUIRED_IBC_EXPORTS[..4])) {
+            Err(VmError::StaticValidationErr { msg, .. }) => {
+                assert!(msg.starts_with(
+                    "Wasm contract has an incomplete set of IBC entry points. Missing: [\"ibc_packet_ack\", \"ibc_packet_timeout\"]."
+                ), "{}", msg);
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Didn't reject wasm with partial IBC support"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_entry_points_works() {
+        let module = |names: &[&str]| {
+            let exports: String = names
+                .iter()
+                .map(|name| format!(r#"(export "{}" (func 0))"#, name))
+                .collect();
+            let wasm = wat::parse_str(format!(
+                r#"(module
+                    (func (param i32 i32) (result i32) i32.const 0)
+                    (export "instantiate" (func 0))
+                    {}
+                )"#,
+                exports
+            ))
+            .unwrap();
+            deserialize_wasm(&wasm).unwrap()
+        };
+        let all = module(&["execute", "migrate", "sudo", "reply"]);
+        let ibc = module(REQUIRED_IBC_EXPORTS);
+
+        check_wasm_entry_points(&all, &AllowedEntryPoints::DEFAULT).unwrap();
+        check_wasm_entry_points(&ibc, &AllowedEntryPoints::DEFAULT).unwrap();
+
+        // forbidden entry points not exported by the contract are fine
+        let no_ibc = AllowedEntryPoints {
+            ibc: false,
+            ..AllowedEntryPoints::DEFAULT
+        };
+        check_wasm_entry_points(&all, &no_ibc).unwrap();
+        match check_wasm_entry_points(&ibc, &no_ibc).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract exports entry points that are not allowed on this chain: [\"ibc_channel_open\", \"ibc_channel_connect\", \"ibc_channel_close\", \"ibc_packet_receive\", \"ibc_packet_ack\", \"ibc_packet_timeout\"]"
+            ),
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        // IBC callbacks can be exported without the other IBC entry points
+        let callbacks = module(IBC_CALLBACK_EXPORTS);
+        check_wasm_entry_points(&callbacks, &AllowedEntryPoints::DEFAULT).unwrap();
+        match check_wasm_entry_points(&module(&["ibc_source_callback"]), &no_ibc).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract exports entry points that are not allowed on this chain: [\"ibc_source_callback\"]"
+            ),
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        let no_migrate_and_sudo = AllowedEntryPoints {
+            migrate: false,
+            sudo: false,
+            ..AllowedEntryPoints::DEFAULT
+        };
+        check_wasm_entry_points(&ibc, &no_migrate_and_sudo).unwrap();
+        check_wasm_entry_points(&module(&["execute", "reply"]), &no_migrate_and_sudo).unwrap();
+        match check_wasm_entry_points(&all, &no_migrate_and_sudo).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract exports entry points that are not allowed on this chain: [\"migrate\", \"sudo\"]"
+            ),
+            e => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     #[test]
     fn check_wasm_exports_of_old_contract() {
         let module = deserialize_wasm(CONTRACT_0_7).unwrap();
@@ -685,7 +1139,7 @@ mod tests {
         match check_wasm_capabilities(&module, &available).unwrap_err() {
             VmError::StaticValidationErr { msg, .. } => assert_eq!(
                 msg,
-                "Wasm contract requires unavailable capabilities: {\"sun\"}"
+                "Wasm contract requires unavailable capabilities: {\"sun\"}. Available capabilities: {\"freedom\", \"nutrients\", \"water\"}"
             ),
             _ => panic!("Got unexpected error"),
         }
@@ -701,7 +1155,7 @@ mod tests {
         match check_wasm_capabilities(&module, &available).unwrap_err() {
             VmError::StaticValidationErr { msg, .. } => assert_eq!(
                 msg,
-                "Wasm contract requires unavailable capabilities: {\"sun\", \"water\"}"
+                "Wasm contract requires unavailable capabilities: {\"sun\", \"water\"}. Available capabilities: {\"Water\", \"freedom\", \"nutrients\"}"
             ),
             _ => panic!("Got unexpected error"),
         }
@@ -711,7 +1165,7 @@ mod tests {
         match check_wasm_capabilities(&module, &available).unwrap_err() {
             VmError::StaticValidationErr { msg, .. } => assert_eq!(
                 msg,
-                "Wasm contract requires unavailable capabilities: {\"nutrients\", \"sun\", \"water\"}"
+                "Wasm contract requires unavailable capabilities: {\"nutrients\", \"sun\", \"water\"}. Available capabilities: {\"freedom\"}"
             ),
             _ => panic!("Got unexpected error"),
         }
@@ -721,7 +1175,156 @@ mod tests {
         match check_wasm_capabilities(&module, &available).unwrap_err() {
             VmError::StaticValidationErr { msg, .. } => assert_eq!(
                 msg,
-                "Wasm contract requires unavailable capabilities: {\"nutrients\", \"sun\", \"water\"}"
+                "Wasm contract requires unavailable capabilities: {\"nutrients\", \"sun\", \"water\"}. Available capabilities: {}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_capabilities_checks_gated_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "poseidon_hash" (func (param i32) (result i64)))
+            (import "env" "zk_verify_groth16" (func (param i32 i32 i32) (result i32)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["zk".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["staking".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"zk\"}. Available capabilities: {\"staking\"}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_capabilities_checks_tendermint_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "tendermint_verify_header" (func (param i32) (result i32)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["tendermint".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["zk".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"tendermint\"}. Available capabilities: {\"zk\"}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_capabilities_checks_cosmwasm_1_2_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "secp256k1_verify_prehashed" (func (param i32 i32 i32 i32) (result i32)))
+            (import "env" "ed25519_batch_verify_packed" (func (param i32) (result i32)))
+            (import "env" "ct_eq" (func (param i32 i32) (result i32)))
+            (import "env" "ics23_verify_membership" (func (param i32 i32 i32 i32 i32) (result i32)))
+            (import "env" "ics23_verify_non_membership" (func (param i32 i32 i32 i32) (result i32)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["cosmwasm_1_2".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["cosmwasm_1_1".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"cosmwasm_1_2\"}. Available capabilities: {\"cosmwasm_1_1\"}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_capabilities_checks_secp256r1_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "secp256r1_verify" (func (param i32 i32 i32) (result i32)))
+            (import "env" "secp256r1_recover_pubkey" (func (param i32 i32 i32) (result i64)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["secp256r1".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["zk".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"secp256r1\"}. Available capabilities: {\"zk\"}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    fn check_wasm_capabilities_checks_bls12_381_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "bls12_381_aggregate_verify" (func (param i32 i32 i32 i32) (result i32)))
+            (import "env" "bls12_381_pairing_equality" (func (param i32 i32 i32 i32) (result i32)))
+            (import "env" "bls12_381_hash_to_g1" (func (param i32 i32) (result i64)))
+            (import "env" "bls12_381_hash_to_g2" (func (param i32 i32) (result i64)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["bls12_381".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["zk".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"bls12_381\"}. Available capabilities: {\"zk\"}"
+            ),
+            _ => panic!("Got unexpected error"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn check_wasm_capabilities_checks_iterator_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "db_scan" (func (param i32 i32 i32) (result i32)))
+            (import "env" "db_next" (func (param i32) (result i32)))
+        )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+
+        let available = ["iterator".to_string()].into_iter().collect();
+        check_wasm_capabilities(&module, &available).unwrap();
+
+        let available = ["staking".to_string()].into_iter().collect();
+        match check_wasm_capabilities(&module, &available).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Wasm contract requires unavailable capabilities: {\"iterator\"}. Available capabilities: {\"staking\"}"
             ),
             _ => panic!("Got unexpected error"),
         }
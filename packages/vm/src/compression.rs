@@ -0,0 +1,151 @@
+//! Decompression of Wasm blobs uploaded in compressed form.
+use std::borrow::Cow;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use crate::errors::{VmError, VmResult};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Returns the Wasm contained in the given blob, which is either uncompressed Wasm,
+/// gzip or zstd compressed data. The format is detected by its magic bytes.
+///
+/// Decompression stops as soon as the output exceeds `max_size` bytes, such that
+/// small blobs cannot expand into huge allocations. Uncompressed blobs are returned
+/// as they are, whatever their size. Anything that is not compressed is treated as
+/// uncompressed Wasm and left to Wasm validation.
+pub fn decompress_wasm(data: &[u8], max_size: usize) -> VmResult<Cow<'_, [u8]>> {
+    if data.starts_with(GZIP_MAGIC) {
+        decompress_gzip(data, max_size).map(Cow::Owned)
+    } else if data.starts_with(ZSTD_MAGIC) {
+        decompress_zstd(data, max_size).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(data))
+    }
+}
+
+fn size_exceeded(max_size: usize) -> VmError {
+    VmError::static_validation_err(format!(
+        "Decompressed Wasm exceeds the limit of {} bytes",
+        max_size
+    ))
+}
+
+fn decompress_zstd(data: &[u8], max_size: usize) -> VmResult<Vec<u8>> {
+    let mut wasm = Vec::new();
+    zstd::stream::read::Decoder::new(data)
+        .and_then(|decoder| decoder.take(max_size as u64 + 1).read_to_end(&mut wasm))
+        .map_err(|e| VmError::static_validation_err(format!("Invalid zstd data: {}", e)))?;
+    if wasm.len() > max_size {
+        return Err(size_exceeded(max_size));
+    }
+    Ok(wasm)
+}
+
+fn decompress_gzip(data: &[u8], max_size: usize) -> VmResult<Vec<u8>> {
+    let mut wasm = Vec::new();
+    GzDecoder::new(data)
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut wasm)
+        .map_err(|e| VmError::static_validation_err(format!("Invalid gzip data: {}", e)))?;
+    if wasm.len() > max_size {
+        return Err(size_exceeded(max_size));
+    }
+    Ok(wasm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
+
+    /// Creates a gzip file with a file name in the header, like the gzip command line tool
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::GzBuilder::new()
+            .filename("contract.wasm")
+            .write(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 0).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn decompress_wasm_passes_through_uncompressed_data() {
+        let wasm = decompress_wasm(CONTRACT, 10).unwrap();
+        assert!(matches!(wasm, Cow::Borrowed(_)));
+        assert_eq!(wasm, CONTRACT);
+
+        let wasm = decompress_wasm(b"not wasm", 10).unwrap();
+        assert_eq!(wasm, &b"not wasm"[..]);
+    }
+
+    #[test]
+    fn decompress_wasm_works_for_gzip() {
+        let compressed = gzip(CONTRACT);
+        let wasm = decompress_wasm(&compressed, CONTRACT.len()).unwrap();
+        assert_eq!(wasm, CONTRACT);
+    }
+
+    #[test]
+    fn decompress_wasm_works_for_zstd() {
+        let compressed = zstd(CONTRACT);
+        let wasm = decompress_wasm(&compressed, CONTRACT.len()).unwrap();
+        assert_eq!(wasm, CONTRACT);
+    }
+
+    #[test]
+    fn decompress_wasm_enforces_limit() {
+        let max_size = CONTRACT.len() - 1;
+        let bomb = vec![0u8; 10 * 1024 * 1024];
+        for compressed in [gzip(CONTRACT), zstd(CONTRACT), gzip(&bomb), zstd(&bomb)] {
+            match decompress_wasm(&compressed, max_size).unwrap_err() {
+                VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                    msg,
+                    format!("Decompressed Wasm exceeds the limit of {} bytes", max_size)
+                ),
+                e => panic!("Unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn decompress_wasm_rejects_corrupt_gzip() {
+        let mut corrupt = gzip(CONTRACT);
+        let len = corrupt.len();
+        corrupt[len - 5] ^= 0x01;
+        match decompress_wasm(&corrupt, CONTRACT.len()).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert!(msg.starts_with("Invalid gzip data"))
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        match decompress_wasm(&[0x1f, 0x8b, 8, 0], CONTRACT.len()).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert!(msg.starts_with("Invalid gzip data"))
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn decompress_wasm_rejects_corrupt_zstd() {
+        let mut corrupt = zstd(CONTRACT);
+        corrupt.truncate(corrupt.len() / 2);
+        match decompress_wasm(&corrupt, CONTRACT.len()).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert!(msg.starts_with("Invalid zstd data"))
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+}
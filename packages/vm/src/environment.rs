@@ -1,55 +1,445 @@
 //! Internal details to be used by instance.rs only
+use std::any::Any;
 use std::borrow::{Borrow, BorrowMut};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::ptr::NonNull;
 use std::sync::{Arc, RwLock};
 
 use wasmer::{HostEnvInitError, Instance as WasmerInstance, Memory, Val, WasmerEnv};
 use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
 
-use crate::backend::{BackendApi, GasInfo, Querier, Storage};
+use crate::backend::{BackendApi, BackendError, GasInfo, Querier, Storage};
 use crate::errors::{VmError, VmResult};
+use crate::size::Size;
+use crate::wasm_backend::GAS_PER_OPERATION;
 
 /// Never can never be instantiated.
 /// Replace this with the [never primitive type](https://doc.rust-lang.org/std/primitive.never.html) when stable.
 #[derive(Debug)]
 pub enum Never {}
 
+/// Runs a callback provided by the embedder and converts a panic in it into a
+/// [`BackendError::ForeignPanic`] carrying the panic message. This avoids poisoning
+/// the context data lock that is held while the callback runs.
+fn catch_callback_panic<C, T>(callback: C) -> VmResult<T>
+where
+    C: FnOnce() -> VmResult<T>,
+{
+    catch_unwind(AssertUnwindSafe(callback)).unwrap_or_else(|payload| {
+        Err(VmError::backend_err(BackendError::foreign_panic_with_msg(
+            panic_payload_message(payload.as_ref()),
+        )))
+    })
+}
+
+/// Extracts the message of a panic caught by `catch_unwind` or `JoinHandle::join`
+pub(crate) fn panic_payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
 /** gas config data */
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct GasConfig {
     /// Gas costs of VM (not Backend) provided functionality
     /// secp256k1 signature verification cost
     pub secp256k1_verify_cost: u64,
     /// secp256k1 public key recovery cost
     pub secp256k1_recover_pubkey_cost: u64,
+    /// secp256r1 signature verification cost
+    pub secp256r1_verify_cost: u64,
+    /// secp256r1 public key recovery cost
+    pub secp256r1_recover_pubkey_cost: u64,
     /// ed25519 signature verification cost
     pub ed25519_verify_cost: u64,
     /// ed25519 batch signature verification cost
     pub ed25519_batch_verify_cost: u64,
     /// ed25519 batch signature verification cost (single public key)
     pub ed25519_batch_verify_one_pubkey_cost: u64,
+    /// Cost per byte of the input of the packed ed25519 batch signature verification,
+    /// which is charged before the input is decoded
+    pub ed25519_batch_verify_packed_per_byte_cost: u64,
+    /// BLS12-381 aggregate signature verification cost
+    pub bls12_381_aggregate_verify_cost: u64,
+    /// BLS12-381 aggregate signature verification cost per public key
+    pub bls12_381_aggregate_verify_per_pubkey_cost: u64,
+    /// BLS12-381 aggregate signature verification cost per message
+    pub bls12_381_aggregate_verify_per_message_cost: u64,
+    /// BLS12-381 pairing equality check cost
+    pub bls12_381_pairing_equality_cost: u64,
+    /// BLS12-381 pairing equality check cost per pair of points
+    pub bls12_381_pairing_equality_per_pair_cost: u64,
+    /// BLS12-381 hash to G1 cost
+    pub bls12_381_hash_to_g1_cost: u64,
+    /// BLS12-381 hash to G2 cost
+    pub bls12_381_hash_to_g2_cost: u64,
+    /// Poseidon hash cost per squared state width, where the state width is the number
+    /// of inputs plus one
+    pub poseidon_hash_cost: u64,
+    /// Groth16 proof verification cost
+    pub groth16_verify_cost: u64,
+    /// Groth16 proof verification cost per public input
+    pub groth16_verify_per_input_cost: u64,
+    /// ICS-23 proof verification cost
+    pub ics23_verify_cost: u64,
+    /// ICS-23 proof verification cost per byte of the proof, key and value
+    pub ics23_verify_per_byte_cost: u64,
+    /// Tendermint header verification cost
+    pub tendermint_verify_header_cost: u64,
+    /// Tendermint header verification cost per byte of the input
+    pub tendermint_verify_header_per_byte_cost: u64,
+    /// Constant-time comparison cost per byte of the longer input
+    pub ct_eq_per_byte_cost: u64,
+    /// Cost per byte of the JSON serialization of call arguments (env, info and msg) and
+    /// deserialization of call results performed by the host in the `call_*` functions.
+    /// This is 0 by default, such that chains opt in to charging it.
+    pub serialization_per_byte_cost: u64,
+    /// Multiplier for the gas reported by the backend for storage access
+    /// (db_read, db_write, db_remove)
+    pub storage_multiplier: GasMultiplier,
+    /// Multiplier for the gas reported by the backend for storage iteration
+    /// (db_scan, db_next)
+    pub iterator_multiplier: GasMultiplier,
+    /// Multiplier for the gas reported by the backend for queries (query_chain),
+    /// except for the ones covered by `stargate_query_multiplier`
+    pub query_multiplier: GasMultiplier,
+    /// Multiplier for the gas reported by the backend for `stargate` and `ibc` queries
+    pub stargate_query_multiplier: GasMultiplier,
 }
 
-impl Default for GasConfig {
-    fn default() -> Self {
+impl GasConfig {
+    /// The default configuration. This is the same as `GasConfig::default()` but
+    /// can be used in const contexts.
+    pub const DEFAULT: GasConfig = {
         // Target is 10^12 per millisecond (see GAS.md), i.e. 10^9 gas per µ second.
         const GAS_PER_US: u64 = 1_000_000_000;
-        Self {
+        GasConfig {
             // ~154 us in crypto benchmarks
             secp256k1_verify_cost: 154 * GAS_PER_US,
             // ~162 us in crypto benchmarks
             secp256k1_recover_pubkey_cost: 162 * GAS_PER_US,
+            // ~2.2 times the secp256k1_verify time in crypto benchmarks
+            secp256r1_verify_cost: 333 * GAS_PER_US,
+            // ~4.3 times the secp256k1_recover_pubkey time in crypto benchmarks
+            secp256r1_recover_pubkey_cost: 700 * GAS_PER_US,
             // ~63 us in crypto benchmarks
             ed25519_verify_cost: 63 * GAS_PER_US,
             // Gas cost factors, relative to ed25519_verify cost
             // From https://docs.rs/ed25519-zebra/2.2.0/ed25519_zebra/batch/index.html
             ed25519_batch_verify_cost: 63 * GAS_PER_US / 2,
             ed25519_batch_verify_one_pubkey_cost: 63 * GAS_PER_US / 4,
+            // ~1 ns per byte for copying and decoding the packed input
+            ed25519_batch_verify_packed_per_byte_cost: GAS_PER_US / 1000,
+            // ~3.5 ms for the final exponentiation and the pairing of the signature in crypto
+            // benchmarks, plus ~190 us for decoding each public key and ~1.6 ms for hashing each
+            // message to G2 and pairing it
+            bls12_381_aggregate_verify_cost: 3500 * GAS_PER_US,
+            bls12_381_aggregate_verify_per_pubkey_cost: 190 * GAS_PER_US,
+            bls12_381_aggregate_verify_per_message_cost: 1600 * GAS_PER_US,
+            // ~2.8 ms for the final exponentiation in crypto benchmarks, plus ~950 us
+            // for decoding and pairing each pair of points
+            bls12_381_pairing_equality_cost: 2800 * GAS_PER_US,
+            bls12_381_pairing_equality_per_pair_cost: 950 * GAS_PER_US,
+            // ~250 us and ~1 ms in crypto benchmarks
+            bls12_381_hash_to_g1_cost: 250 * GAS_PER_US,
+            bls12_381_hash_to_g2_cost: 1000 * GAS_PER_US,
+            // ~26 us for 1 input and ~1.4 ms for 16 inputs in crypto benchmarks
+            poseidon_hash_cost: 5 * GAS_PER_US,
+            // ~4.3 ms for the pairing check and point validation in crypto benchmarks,
+            // plus a scalar multiplication of ~155 us per public input
+            groth16_verify_cost: 4300 * GAS_PER_US,
+            groth16_verify_per_input_cost: 155 * GAS_PER_US,
+            // ~10 us for the parsing and spec checks of a proof, plus ~10 ns per byte for
+            // hashing the nodes on the path
+            ics23_verify_cost: 10 * GAS_PER_US,
+            ics23_verify_per_byte_cost: GAS_PER_US / 100,
+            // Dominated by the Ed25519 signature verifications (~63 us each). A commit signature
+            // takes ~110 bytes of the input and is verified up to twice, which gives ~1 us per byte.
+            tendermint_verify_header_cost: 100 * GAS_PER_US,
+            tendermint_verify_header_per_byte_cost: GAS_PER_US,
+            // ~1 ns per byte
+            ct_eq_per_byte_cost: GAS_PER_US / 1000,
+            // ~5 ns per byte for serde_json serialization and deserialization
+            serialization_per_byte_cost: 0,
+            storage_multiplier: GasMultiplier::ONE,
+            iterator_multiplier: GasMultiplier::ONE,
+            query_multiplier: GasMultiplier::ONE,
+            stargate_query_multiplier: GasMultiplier::ONE,
         }
+    };
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self::DEFAULT
     }
 }
 
+/// A rational factor that is applied to gas amounts reported by the backend.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct GasMultiplier {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl GasMultiplier {
+    /// The neutral multiplier, i.e. gas amounts remain unchanged
+    pub const ONE: GasMultiplier = GasMultiplier {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    /// Creates a multiplier `numerator / denominator`.
+    ///
+    /// Returns None if the denominator is zero.
+    pub const fn new(numerator: u64, denominator: u64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        Some(GasMultiplier {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Returns true if the multiplier is zero, i.e. if it maps all amounts to zero
+    pub const fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    /// Multiplies the given amount, rounding down. Saturates at `u64::MAX`.
+    pub fn apply(&self, amount: u64) -> u64 {
+        let product = amount as u128 * self.numerator as u128 / self.denominator as u128;
+        product.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// Divides the given amount by the multiplier, rounding down. Saturates at `u64::MAX`,
+    /// which is also the result for a multiplier of zero.
+    pub fn apply_inverse(&self, amount: u64) -> u64 {
+        if self.numerator == 0 {
+            return u64::MAX;
+        }
+        let quotient = amount as u128 * self.denominator as u128 / self.numerator as u128;
+        quotient.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// Applies the multiplier to the total gas of a backend operation.
+    ///
+    /// Gas that was used externally has been charged already. Thus it remains unchanged
+    /// and only the cost is adjusted such that the sum of both matches the multiplied total.
+    /// As a consequence, the result can never be lower than the externally used gas.
+    pub fn apply_to_gas_info(&self, info: GasInfo) -> GasInfo {
+        if *self == Self::ONE {
+            return info;
+        }
+        let total = self.apply(info.cost.saturating_add(info.externally_used));
+        GasInfo {
+            cost: total.saturating_sub(info.externally_used),
+            externally_used: info.externally_used,
+        }
+    }
+}
+
+impl Default for GasMultiplier {
+    fn default() -> Self {
+        Self::ONE
+    }
+}
+
+/// Limits for data that is written to or read from storage by the contract.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct StorageLimits {
+    /// Max key length for db_write/db_read/db_remove/db_scan, in bytes
+    pub max_key_length: usize,
+    /// Max value length for db_write, in bytes
+    pub max_value_length: usize,
+    /// Max number of db_write and db_remove calls per contract call. `None` means unlimited.
+    pub max_writes_per_call: Option<u32>,
+}
+
+impl StorageLimits {
+    /// The default limits. This is the same as `StorageLimits::default()` but
+    /// can be used in const contexts.
+    pub const DEFAULT: StorageLimits = StorageLimits {
+        max_key_length: 64 * 1024,
+        max_value_length: 128 * 1024,
+        max_writes_per_call: None,
+    };
+}
+
+impl Default for StorageLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Limits for the input of contract calls and for nested queries.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CallLimits {
+    /// The maximum size of the message passed to a call. Larger messages are rejected
+    /// with [`VmError::InputTooLarge`] before the contract is called.
+    pub max_msg_size: Size,
+    /// The maximum depth of nested contract queries. Creating an instance with a larger
+    /// [`InstanceOptions::query_depth`](crate::InstanceOptions::query_depth) fails with
+    /// [`VmError::MaxQueryDepthExceeded`].
+    pub max_query_depth: u32,
+}
+
+impl CallLimits {
+    /// The default limits. This is the same as `CallLimits::default()` but
+    /// can be used in const contexts.
+    pub const DEFAULT: CallLimits = CallLimits {
+        max_msg_size: Size::mebi(2),
+        max_query_depth: 10,
+    };
+}
+
+impl Default for CallLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Limits for the debug messages of a contract, which are only printed when `print_debug` is enabled.
+///
+/// Exceeding these limits never fails the contract call. Longer messages are truncated
+/// and further messages are dropped.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DebugLimits {
+    /// Max length of a single debug message, in bytes
+    pub max_message_length: usize,
+    /// Max number of debug messages printed per contract call
+    pub max_messages_per_call: u32,
+}
+
+impl DebugLimits {
+    /// The default limits. This is the same as `DebugLimits::default()` but
+    /// can be used in const contexts.
+    pub const DEFAULT: DebugLimits = DebugLimits {
+        max_message_length: 64 * 1024,
+        max_messages_per_call: 1000,
+    };
+}
+
+impl Default for DebugLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Controls a cache for storage reads that lives for the duration of a single contract call.
+///
+/// When enabled, repeated `db_read`s of the same key are served from the cache without
+/// calling into the backend again. Writes and removals of a key invalidate its cache entry.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StorageReadCache {
+    /// Every read is forwarded to the backend
+    Disabled,
+    /// Cache hits are charged the full gas of the original read (internal and externally used),
+    /// such that gas usage is the same as without the cache.
+    ChargeOriginalCost,
+    /// Cache hits are charged the given amount of gas only
+    ChargeFixedCost(u64),
+}
+
+impl StorageReadCache {
+    /// The default setting. This is the same as `StorageReadCache::default()` but
+    /// can be used in const contexts.
+    pub const DEFAULT: StorageReadCache = StorageReadCache::Disabled;
+
+    /// Returns the gas to be charged for serving a read from the cache,
+    /// given the gas info of the original read.
+    /// Returns `None` if the cache is disabled.
+    pub fn cache_hit_gas(&self, original: GasInfo) -> Option<GasInfo> {
+        match self {
+            StorageReadCache::Disabled => None,
+            StorageReadCache::ChargeOriginalCost => Some(GasInfo::with_cost(
+                original.cost.saturating_add(original.externally_used),
+            )),
+            StorageReadCache::ChargeFixedCost(cost) => Some(GasInfo::with_cost(*cost)),
+        }
+    }
+}
+
+impl Default for StorageReadCache {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Statistics accumulated across all calls of an instance while a session is active.
+/// See [`Instance::start_session`](crate::Instance::start_session).
+///
+/// Unlike a [`GasReport`](crate::GasReport), a session can be carried over to a new instance
+/// created from a recycled one (see [`Instance::resume_session`](crate::Instance::resume_session)),
+/// such that it covers all calls of an integration test.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SessionReport {
+    /// Number of contract calls, including failed ones
+    pub calls: u64,
+    /// Gas used by all calls, internally and externally. This includes the gas charged by
+    /// the host for JSON serialization of call inputs and outputs, which is not part of any entry.
+    pub gas_used: u64,
+    /// The part of `gas_used` that was metered externally
+    pub gas_used_externally: u64,
+    /// Number of host function calls made by the contract
+    pub host_calls: u64,
+    /// Bytes read from storage, i.e. the values returned by `db_read` as well as
+    /// keys and values returned by iterators
+    pub storage_bytes_read: u64,
+    /// Bytes written to storage, i.e. keys and values of `db_write` as well as
+    /// keys of `db_remove`
+    pub storage_bytes_written: u64,
+    /// The gas used by each call in the order they were made
+    pub entries: Vec<SessionEntry>,
+}
+
+impl SessionReport {
+    /// The calls of the given entry point in the order they were made
+    pub fn entries_for<'a>(
+        &'a self,
+        entry_point: &'a str,
+    ) -> impl Iterator<Item = &'a SessionEntry> + 'a {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.entry_point == entry_point)
+    }
+
+    /// The gas used by all calls of the given entry point
+    pub fn total_for(&self, entry_point: &str) -> u64 {
+        self.entries_for(entry_point)
+            .map(|entry| entry.gas_used)
+            .sum()
+    }
+
+    /// The gas used by all calls, summed up by entry point
+    pub fn totals_by_entry_point(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for entry in &self.entries {
+            *totals.entry(entry.entry_point.clone()).or_default() += entry.gas_used;
+        }
+        totals
+    }
+}
+
+/// The gas used by a single contract call, see [`SessionReport::entries`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SessionEntry {
+    /// The name of the called entry point, e.g. `execute`
+    pub entry_point: String,
+    /// Gas used by the contract during the call, internally and externally
+    pub gas_used: u64,
+    /// The part of `gas_used` that was metered externally
+    pub gas_used_externally: u64,
+}
+
 /** context data **/
 
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
@@ -61,6 +451,9 @@ pub struct GasState {
     pub gas_limit: u64,
     /// Tracking the gas used in the Cosmos SDK, in CosmWasm gas units.
     pub externally_used_gas: u64,
+    /// The gas deducted from the gas available to Wasm for `externally_used_gas`. This differs
+    /// from `externally_used_gas` if a gas multiplier is used.
+    pub externally_used_wasm_gas: u64,
 }
 
 impl GasState {
@@ -68,6 +461,7 @@ impl GasState {
         Self {
             gas_limit,
             externally_used_gas: 0,
+            externally_used_wasm_gas: 0,
         }
     }
 }
@@ -76,8 +470,20 @@ impl GasState {
 /// The environment is clonable but clones access the same underlying data.
 pub struct Environment<A: BackendApi, S: Storage, Q: Querier> {
     pub api: A,
-    pub print_debug: bool,
     pub gas_config: GasConfig,
+    pub storage_limits: StorageLimits,
+    pub storage_read_cache: StorageReadCache,
+    pub debug_limits: DebugLimits,
+    pub call_limits: CallLimits,
+    pub allow_reentrant_queries: bool,
+    /// The number of contract queries the instance is nested in
+    pub query_depth: u32,
+    pub validate_msg_addresses: bool,
+    /// Extra imports (as `namespace.name`) that charge gas for their own call overhead.
+    /// Those may claim a refund of the gas charged by the Wasm-level metering for calling them.
+    pub metering_exempt_imports: BTreeSet<String>,
+    /// The gas left is divided by this multiplier, see `InstanceOptions::gas_multiplier`
+    pub gas_multiplier: GasMultiplier,
     data: Arc<RwLock<ContextData<S, Q>>>,
 }
 
@@ -88,9 +494,17 @@ unsafe impl<A: BackendApi, S: Storage, Q: Querier> Sync for Environment<A, S, Q>
 impl<A: BackendApi, S: Storage, Q: Querier> Clone for Environment<A, S, Q> {
     fn clone(&self) -> Self {
         Environment {
-            api: self.api,
-            print_debug: self.print_debug,
-            gas_config: self.gas_config.clone(),
+            api: self.api.clone(),
+            gas_config: self.gas_config,
+            storage_limits: self.storage_limits,
+            storage_read_cache: self.storage_read_cache,
+            debug_limits: self.debug_limits,
+            call_limits: self.call_limits,
+            allow_reentrant_queries: self.allow_reentrant_queries,
+            query_depth: self.query_depth,
+            validate_msg_addresses: self.validate_msg_addresses,
+            metering_exempt_imports: self.metering_exempt_imports.clone(),
+            gas_multiplier: self.gas_multiplier,
             data: self.data.clone(),
         }
     }
@@ -104,11 +518,21 @@ impl<A: BackendApi, S: Storage, Q: Querier> WasmerEnv for Environment<A, S, Q> {
 
 impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
     pub fn new(api: A, gas_limit: u64, print_debug: bool) -> Self {
+        let mut data = ContextData::new(gas_limit);
+        data.print_debug = print_debug;
         Environment {
             api,
-            print_debug,
             gas_config: GasConfig::default(),
-            data: Arc::new(RwLock::new(ContextData::new(gas_limit))),
+            storage_limits: StorageLimits::default(),
+            storage_read_cache: StorageReadCache::default(),
+            debug_limits: DebugLimits::default(),
+            call_limits: CallLimits::default(),
+            allow_reentrant_queries: true,
+            query_depth: 0,
+            validate_msg_addresses: false,
+            metering_exempt_imports: BTreeSet::new(),
+            gas_multiplier: GasMultiplier::ONE,
+            data: Arc::new(RwLock::new(data)),
         }
     }
 
@@ -170,7 +594,23 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
         func.call(args).map_err(|runtime_err| -> VmError {
             self.with_wasmer_instance::<_, Never>(|instance| {
                 let err: VmError = match get_remaining_points(instance) {
-                    MeteringPoints::Remaining(_) => VmError::from(runtime_err),
+                    MeteringPoints::Remaining(_) => match runtime_err.downcast::<VmError>() {
+                        // An import failed. Keep its breadcrumbs structured instead of
+                        // flattening them into the runtime error message. Backend errors are
+                        // kept as they are such that embedders can inspect their codes.
+                        Ok(mut import_err) => {
+                            let breadcrumbs = import_err.take_breadcrumbs();
+                            let err = match import_err {
+                                err @ VmError::BackendErr { .. } => err,
+                                err => VmError::runtime_err(format!(
+                                    "Wasmer runtime error: RuntimeError: {}",
+                                    err
+                                )),
+                            };
+                            breadcrumbs.into_iter().fold(err, VmError::context)
+                        }
+                        Err(runtime_err) => VmError::from(runtime_err),
+                    },
                     MeteringPoints::Exhausted => VmError::gas_depletion(),
                 };
                 Err(err)
@@ -204,7 +644,7 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
         C: FnOnce(&mut S) -> VmResult<T>,
     {
         self.with_context_data_mut(|context_data| match context_data.storage.as_mut() {
-            Some(data) => callback(data),
+            Some(data) => catch_callback_panic(|| callback(data)),
             None => Err(VmError::uninitialized_context_data("storage")),
         })
     }
@@ -214,11 +654,67 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
         C: FnOnce(&mut Q) -> VmResult<T>,
     {
         self.with_context_data_mut(|context_data| match context_data.querier.as_mut() {
-            Some(querier) => callback(querier),
+            Some(querier) => catch_callback_panic(|| callback(querier)),
             None => Err(VmError::uninitialized_context_data("querier")),
         })
     }
 
+    /// Returns the cached result and gas info of a previous read of the given key,
+    /// if any.
+    pub fn get_cached_read(&self, key: &[u8]) -> Option<(Option<Vec<u8>>, GasInfo)> {
+        self.with_context_data(|context_data| context_data.read_cache.get(key).cloned())
+    }
+
+    /// Stores the result of a storage read in the read cache.
+    /// This is a no-op if the read cache is disabled.
+    pub fn cache_read(&self, key: &[u8], value: Option<Vec<u8>>, gas_info: GasInfo) {
+        if self.storage_read_cache == StorageReadCache::Disabled {
+            return;
+        }
+        self.with_context_data_mut(|context_data| {
+            context_data
+                .read_cache
+                .insert(key.to_vec(), (value, gas_info));
+        })
+    }
+
+    /// Removes the given key from the read cache. Must be called whenever the key is modified.
+    pub fn invalidate_cached_read(&self, key: &[u8]) {
+        self.with_context_data_mut(|context_data| {
+            context_data.read_cache.remove(key);
+        })
+    }
+
+    /// Removes all entries from the read cache. This is done at the beginning of every call.
+    pub fn clear_read_cache(&self) {
+        self.with_context_data_mut(|context_data| context_data.read_cache.clear())
+    }
+
+    /// Limits the number of elements the given iterator returns to the contract,
+    /// see `db_scan_with_limit`.
+    #[cfg(feature = "iterator")]
+    pub fn set_iterator_limit(&self, iterator_id: u32, limit: u32) {
+        self.with_context_data_mut(|context_data| {
+            context_data.iterator_limits.insert(iterator_id, limit);
+        })
+    }
+
+    /// Counts one element towards the limit of the given iterator.
+    /// Returns false if the limit is already reached and no more elements must be returned.
+    #[cfg(feature = "iterator")]
+    pub fn take_from_iterator_limit(&self, iterator_id: u32) -> bool {
+        self.with_context_data_mut(|context_data| {
+            match context_data.iterator_limits.get_mut(&iterator_id) {
+                Some(0) => false,
+                Some(remaining) => {
+                    *remaining -= 1;
+                    true
+                }
+                None => true,
+            }
+        })
+    }
+
     /// Creates a back reference from a contact to its partent instance
     pub fn set_wasmer_instance(&self, wasmer_instance: Option<NonNull<WasmerInstance>>) {
         self.with_context_data_mut(|context_data| {
@@ -237,6 +733,75 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
         })
     }
 
+    /// Returns true iff debug messages of the contract are printed
+    pub fn print_debug(&self) -> bool {
+        self.with_context_data(|context_data| context_data.print_debug)
+    }
+
+    pub fn set_print_debug(&self, new_value: bool) {
+        self.with_context_data_mut(|context_data| {
+            context_data.print_debug = new_value;
+        })
+    }
+
+    /// Counts a debug message of the current call and returns the number of
+    /// debug messages in this call so far, including this one.
+    pub fn count_debug_message(&self) -> u32 {
+        self.with_context_data_mut(|context_data| {
+            context_data.debug_messages = context_data.debug_messages.saturating_add(1);
+            context_data.debug_messages
+        })
+    }
+
+    /// Resets the number of debug messages. Must be called at the start of every call.
+    pub fn reset_debug_messages(&self) {
+        self.with_context_data_mut(|context_data| context_data.debug_messages = 0)
+    }
+
+    /// Counts a storage write or removal of the current call and fails if this exceeds
+    /// [`StorageLimits::max_writes_per_call`].
+    pub fn count_storage_write(&self) -> VmResult<()> {
+        let writes = self.with_context_data_mut(|context_data| {
+            context_data.storage_writes = context_data.storage_writes.saturating_add(1);
+            context_data.storage_writes
+        });
+        match self.storage_limits.max_writes_per_call {
+            Some(limit) if writes > limit => Err(VmError::storage_write_limit_exceeded(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resets the number of storage writes. Must be called at the start of every call.
+    pub fn reset_storage_writes(&self) {
+        self.with_context_data_mut(|context_data| context_data.storage_writes = 0)
+    }
+
+    pub fn contract_address(&self) -> Option<String> {
+        self.with_context_data(|context_data| context_data.contract_address.clone())
+    }
+
+    pub fn set_contract_address(&self, contract_address: Option<String>) {
+        self.with_context_data_mut(|context_data| context_data.contract_address = contract_address)
+    }
+
+    pub fn session(&self) -> Option<SessionReport> {
+        self.with_context_data(|context_data| context_data.session.clone())
+    }
+
+    /// Starts (`Some`) or ends (`None`) accumulating a session report
+    pub fn set_session(&self, session: Option<SessionReport>) {
+        self.with_context_data_mut(|context_data| context_data.session = session)
+    }
+
+    /// Updates the session report if a session is active
+    pub fn update_session<F: FnOnce(&mut SessionReport)>(&self, update: F) {
+        self.with_context_data_mut(|context_data| {
+            if let Some(session) = context_data.session.as_mut() {
+                update(session);
+            }
+        })
+    }
+
     pub fn get_gas_left(&self) -> u64 {
         self.with_wasmer_instance(|instance| {
             Ok(match get_remaining_points(instance) {
@@ -248,11 +813,49 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
     }
 
     pub fn set_gas_left(&self, new_value: u64) {
+        let gas_left = self.get_gas_left();
         self.with_wasmer_instance(|instance| {
             set_remaining_points(instance, new_value);
             Ok(())
         })
-        .expect("Wasmer instance is not set. This is a bug in the lifecycle.")
+        .expect("Wasmer instance is not set. This is a bug in the lifecycle.");
+        // Keep track of the refund as long as no Wasm code was executed
+        self.with_context_data_mut(|context_data| {
+            if context_data.call_overhead_refunded_at == Some(gas_left) {
+                context_data.call_overhead_refunded_at = Some(new_value);
+            } else {
+                context_data.call_overhead_refunded_at = None;
+            }
+        })
+    }
+
+    /// Returns true if the given extra import is listed in
+    /// [`Environment::metering_exempt_imports`]
+    pub fn is_metering_exempt(&self, namespace: &str, name: &str) -> bool {
+        self.metering_exempt_imports
+            .contains(&format!("{}.{}", namespace, name))
+    }
+
+    /// Refunds the gas the Wasm-level metering charged for a call operation.
+    /// This is used for calls of imports that are metered internally.
+    ///
+    /// Only one refund is granted per call: Wasm code consumes gas between two calls of
+    /// an import, so no refund is granted as long as the gas left was only changed by the host
+    /// since the last refund. Returns whether gas was refunded.
+    pub fn refund_call_overhead(&self) -> bool {
+        let gas_left = self.get_gas_left();
+        let refunded = self.with_context_data(|context_data| {
+            context_data.call_overhead_refunded_at == Some(gas_left)
+        });
+        if refunded {
+            return false;
+        }
+        let new_value = gas_left.saturating_add(GAS_PER_OPERATION);
+        self.set_gas_left(new_value);
+        self.with_context_data_mut(|context_data| {
+            context_data.call_overhead_refunded_at = Some(new_value)
+        });
+        true
     }
 
     /// Decreases gas left by the given amount.
@@ -300,6 +903,9 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
         self.with_context_data_mut(|context_data| {
             context_data.storage = Some(storage);
             context_data.querier = Some(querier);
+            context_data.read_cache.clear();
+            #[cfg(feature = "iterator")]
+            context_data.iterator_limits.clear();
         });
     }
 
@@ -307,6 +913,9 @@ impl<A: BackendApi, S: Storage, Q: Querier> Environment<A, S, Q> {
     /// iterators. This is meant to be called when recycling the instance.
     pub fn move_out(&self) -> (Option<S>, Option<Q>) {
         self.with_context_data_mut(|context_data| {
+            context_data.read_cache.clear();
+            #[cfg(feature = "iterator")]
+            context_data.iterator_limits.clear();
             (context_data.storage.take(), context_data.querier.take())
         })
     }
@@ -316,7 +925,26 @@ pub struct ContextData<S: Storage, Q: Querier> {
     gas_state: GasState,
     storage: Option<S>,
     storage_readonly: bool,
+    /// Stored here instead of in [`Environment`] such that changes are visible to the imports,
+    /// which hold clones of the environment
+    print_debug: bool,
+    /// Number of debug messages in the current call, see [`DebugLimits`]
+    debug_messages: u32,
+    /// Number of storage writes and removals in the current call, see [`StorageLimits`]
+    storage_writes: u32,
+    /// Statistics across calls, only accumulated while a session is active
+    session: Option<SessionReport>,
+    /// The address of the contract executing the current call, if known
+    contract_address: Option<String>,
+    /// The gas left after the last refund of a call overhead, as long as only the host
+    /// changed the gas left since, see [`Environment::refund_call_overhead`]
+    call_overhead_refunded_at: Option<u64>,
     querier: Option<Q>,
+    /// Results of storage reads in the current call, see [`StorageReadCache`]
+    read_cache: HashMap<Vec<u8>, (Option<Vec<u8>>, GasInfo)>,
+    /// Remaining number of elements of iterators created with a limit, by iterator ID
+    #[cfg(feature = "iterator")]
+    iterator_limits: HashMap<u32, u32>,
     /// A non-owning link to the wasmer instance
     wasmer_instance: Option<NonNull<WasmerInstance>>,
 }
@@ -327,7 +955,16 @@ impl<S: Storage, Q: Querier> ContextData<S, Q> {
             gas_state: GasState::with_limit(gas_limit),
             storage: None,
             storage_readonly: true,
+            print_debug: false,
+            debug_messages: 0,
+            storage_writes: 0,
+            session: None,
+            contract_address: None,
+            call_overhead_refunded_at: None,
             querier: None,
+            read_cache: HashMap::new(),
+            #[cfg(feature = "iterator")]
+            iterator_limits: HashMap::new(),
             wasmer_instance: None,
         }
     }
@@ -338,20 +975,24 @@ pub fn process_gas_info<A: BackendApi, S: Storage, Q: Querier>(
     info: GasInfo,
 ) -> VmResult<()> {
     let gas_left = env.get_gas_left();
+    // Externally used gas is charged by the chain without the multiplier. Dividing it
+    // makes it count fully towards the gas limit.
+    let externally_used = env.gas_multiplier.apply_inverse(info.externally_used);
 
     let new_limit = env.with_gas_state_mut(|gas_state| {
         gas_state.externally_used_gas += info.externally_used;
+        gas_state.externally_used_wasm_gas += externally_used;
         // These lines reduce the amount of gas available to wasmer
         // so it can not consume gas that was consumed externally.
         gas_left
-            .saturating_sub(info.externally_used)
+            .saturating_sub(externally_used)
             .saturating_sub(info.cost)
     });
 
     // This tells wasmer how much more gas it can consume from this point in time.
     env.set_gas_left(new_limit);
 
-    if info.externally_used + info.cost > gas_left {
+    if externally_used.saturating_add(info.cost) > gas_left {
         Err(VmError::gas_depletion())
     } else {
         Ok(())
@@ -409,9 +1050,14 @@ mod tests {
                 "addr_canonicalize" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "addr_humanize" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "secp256k1_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
+                "secp256k1_verify_prehashed" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32| -> u32 { 0 }),
                 "secp256k1_recover_pubkey" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u64 { 0 }),
                 "ed25519_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
                 "ed25519_batch_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
+                "ed25519_batch_verify_packed" => Function::new_native(store, |_a: u32| -> u32 { 0 }),
+                "ics23_verify_membership" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32, _e: u32| -> u32 { 0 }),
+                "ics23_verify_non_membership" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32| -> u32 { 0 }),
+                "ct_eq" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "debug" => Function::new_native(store, |_a: u32| {}),
             },
         };
@@ -436,6 +1082,73 @@ mod tests {
         env.move_in(storage, querier);
     }
 
+    #[test]
+    fn gas_multiplier_apply_works() {
+        assert_eq!(GasMultiplier::ONE.apply(0), 0);
+        assert_eq!(GasMultiplier::ONE.apply(123), 123);
+        assert_eq!(GasMultiplier::ONE.apply(u64::MAX), u64::MAX);
+
+        let triple = GasMultiplier::new(3, 1).unwrap();
+        assert_eq!(triple.apply(0), 0);
+        assert_eq!(triple.apply(7), 21);
+        assert_eq!(triple.apply(u64::MAX), u64::MAX);
+
+        // rounds down
+        let half = GasMultiplier::new(1, 2).unwrap();
+        assert_eq!(half.apply(10), 5);
+        assert_eq!(half.apply(11), 5);
+        assert_eq!(half.apply(u64::MAX), u64::MAX / 2);
+    }
+
+    #[test]
+    fn gas_multiplier_apply_inverse_works() {
+        assert_eq!(GasMultiplier::ONE.apply_inverse(0), 0);
+        assert_eq!(GasMultiplier::ONE.apply_inverse(123), 123);
+        assert_eq!(GasMultiplier::ONE.apply_inverse(u64::MAX), u64::MAX);
+
+        // rounds down
+        let triple = GasMultiplier::new(3, 1).unwrap();
+        assert_eq!(triple.apply_inverse(21), 7);
+        assert_eq!(triple.apply_inverse(23), 7);
+        assert_eq!(triple.apply_inverse(u64::MAX), u64::MAX / 3);
+
+        let half = GasMultiplier::new(1, 2).unwrap();
+        assert_eq!(half.apply_inverse(5), 10);
+        assert_eq!(half.apply_inverse(u64::MAX), u64::MAX);
+
+        // saturates for zero multiplier
+        assert!(GasMultiplier::new(0, 1).unwrap().is_zero());
+        assert!(!GasMultiplier::ONE.is_zero());
+        assert_eq!(GasMultiplier::new(0, 1).unwrap().apply_inverse(0), u64::MAX);
+        assert_eq!(GasMultiplier::new(0, 1).unwrap().apply_inverse(5), u64::MAX);
+    }
+
+    #[test]
+    fn gas_multiplier_new_works() {
+        assert_eq!(GasMultiplier::new(1, 1), Some(GasMultiplier::ONE));
+        assert!(GasMultiplier::new(0, 1).unwrap().is_zero());
+        assert_eq!(GasMultiplier::new(1, 0), None);
+        assert_eq!(GasMultiplier::new(0, 0), None);
+    }
+
+    #[test]
+    fn gas_multiplier_apply_to_gas_info_works() {
+        let info = GasInfo::new(30, 70);
+        assert_eq!(GasMultiplier::ONE.apply_to_gas_info(info), info);
+
+        // additional gas is charged as cost
+        let double = GasMultiplier::new(2, 1).unwrap();
+        assert_eq!(double.apply_to_gas_info(info), GasInfo::new(130, 70));
+
+        // cost can be reduced but externally used gas remains
+        let half = GasMultiplier::new(1, 2).unwrap();
+        assert_eq!(half.apply_to_gas_info(info), GasInfo::new(0, 70));
+        assert_eq!(
+            half.apply_to_gas_info(GasInfo::with_cost(30)),
+            GasInfo::with_cost(15)
+        );
+    }
+
     #[test]
     fn move_out_works() {
         let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
@@ -461,6 +1174,26 @@ mod tests {
         assert!(endq.is_none());
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn take_from_iterator_limit_works() {
+        let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
+
+        // iterators without limit
+        assert!(env.take_from_iterator_limit(1));
+        assert!(env.take_from_iterator_limit(1));
+
+        env.set_iterator_limit(2, 2);
+        assert!(env.take_from_iterator_limit(2));
+        assert!(env.take_from_iterator_limit(2));
+        assert!(!env.take_from_iterator_limit(2));
+        assert!(!env.take_from_iterator_limit(2));
+
+        // limits are reset between calls
+        leave_default_data(&env);
+        assert!(env.take_from_iterator_limit(2));
+    }
+
     #[test]
     fn process_gas_info_works_for_cost() {
         let (env, _instance) = make_instance(100);
@@ -505,6 +1238,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn process_gas_info_applies_gas_multiplier() {
+        let (mut env, _instance) = make_instance(100);
+        env.gas_multiplier = GasMultiplier::new(1, 2).unwrap();
+        env.set_gas_left(200);
+
+        // externally used gas is divided, cost is not
+        process_gas_info(&env, GasInfo::new(10, 20)).unwrap();
+        assert_eq!(env.get_gas_left(), 150);
+        let gas_state = env.with_gas_state(|gas_state| gas_state.clone());
+        assert_eq!(gas_state.gas_limit, 100);
+        assert_eq!(gas_state.externally_used_gas, 20);
+        assert_eq!(gas_state.externally_used_wasm_gas, 40);
+
+        // the externally used gas must not exceed the limit
+        match process_gas_info(&env, GasInfo::with_externally_used(76)).unwrap_err() {
+            VmError::GasDepletion { .. } => {}
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(env.get_gas_left(), 0);
+    }
+
     #[test]
     fn process_gas_info_works_for_cost_and_externally_used() {
         let (env, _instance) = make_instance(100);
@@ -636,6 +1391,21 @@ mod tests {
         assert!(env.is_storage_readonly());
     }
 
+    #[test]
+    fn set_print_debug_is_visible_in_clones() {
+        let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
+        let clone = env.clone();
+        assert!(!env.print_debug());
+        assert!(!clone.print_debug());
+
+        env.set_print_debug(true);
+        assert!(env.print_debug());
+        assert!(clone.print_debug());
+
+        clone.set_print_debug(false);
+        assert!(!env.print_debug());
+    }
+
     #[test]
     fn call_function_works() {
         let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
@@ -783,6 +1553,31 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn with_storage_from_context_converts_panics() {
+        let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
+        leave_default_data(&env);
+
+        let err = env
+            .with_storage_from_context::<_, ()>(|_store| panic!("trigger failure {}", 42))
+            .unwrap_err();
+        match err {
+            VmError::BackendErr {
+                source: BackendError::ForeignPanic { msg },
+                ..
+            } => assert_eq!(msg.as_deref(), Some("trigger failure 42")),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // the storage can still be used afterwards
+        let val = env
+            .with_storage_from_context::<_, _>(|store| {
+                Ok(store.get(INIT_KEY).0.expect("error getting value"))
+            })
+            .unwrap();
+        assert_eq!(val, Some(INIT_VALUE.to_vec()));
+    }
+
     #[test]
     fn with_querier_from_context_works() {
         let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
@@ -816,4 +1611,23 @@ mod tests {
         })
         .unwrap();
     }
+
+    #[test]
+    fn with_querier_from_context_converts_panics() {
+        let (env, _instance) = make_instance(TESTING_GAS_LIMIT);
+        leave_default_data(&env);
+
+        let err = env
+            .with_querier_from_context::<_, ()>(|_querier| {
+                std::panic::panic_any(17u8);
+            })
+            .unwrap_err();
+        match err {
+            VmError::BackendErr {
+                source: BackendError::ForeignPanic { msg },
+                ..
+            } => assert_eq!(msg.as_deref(), Some("<non-string panic payload>")),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
 }
@@ -4,7 +4,7 @@ mod vm_error;
 
 pub use communication_error::CommunicationError;
 pub use region_validation_error::RegionValidationError;
-pub use vm_error::VmError;
+pub use vm_error::{Breadcrumbs, VmError};
 
 pub type CommunicationResult<T> = core::result::Result<T, CommunicationError>;
 pub type RegionValidationResult<T> = core::result::Result<T, RegionValidationError>;
@@ -17,6 +17,28 @@ pub enum RegionValidationError {
         capacity
     )]
     OutOfRange { offset: u32, capacity: u32 },
+    #[error(
+        "Region exceeds Wasm memory. Offset {}, capacity {}, memory size {}",
+        offset,
+        capacity,
+        memory_size
+    )]
+    OutOfMemoryBounds {
+        offset: u32,
+        capacity: u32,
+        memory_size: usize,
+    },
+    #[error(
+        "Region data overlaps with the Region struct at {}. Offset {}, capacity {}",
+        region_ptr,
+        offset,
+        capacity
+    )]
+    OverlapsRegionStruct {
+        region_ptr: u32,
+        offset: u32,
+        capacity: u32,
+    },
     #[error("Got a zero Wasm address in the offset")]
     ZeroOffset {},
 }
@@ -30,6 +52,22 @@ impl RegionValidationError {
         RegionValidationError::OutOfRange { offset, capacity }
     }
 
+    pub(crate) fn out_of_memory_bounds(offset: u32, capacity: u32, memory_size: usize) -> Self {
+        RegionValidationError::OutOfMemoryBounds {
+            offset,
+            capacity,
+            memory_size,
+        }
+    }
+
+    pub(crate) fn overlaps_region_struct(region_ptr: u32, offset: u32, capacity: u32) -> Self {
+        RegionValidationError::OverlapsRegionStruct {
+            region_ptr,
+            offset,
+            capacity,
+        }
+    }
+
     pub(crate) fn zero_offset() -> Self {
         RegionValidationError::ZeroOffset {}
     }
@@ -69,6 +107,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn out_of_memory_bounds_works() {
+        let error = RegionValidationError::out_of_memory_bounds(65530, 7, 65536);
+        match error {
+            RegionValidationError::OutOfMemoryBounds {
+                offset,
+                capacity,
+                memory_size,
+                ..
+            } => {
+                assert_eq!(offset, 65530);
+                assert_eq!(capacity, 7);
+                assert_eq!(memory_size, 65536);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn overlaps_region_struct_works() {
+        let error = RegionValidationError::overlaps_region_struct(100, 90, 20);
+        match error {
+            RegionValidationError::OverlapsRegionStruct {
+                region_ptr,
+                offset,
+                capacity,
+                ..
+            } => {
+                assert_eq!(region_ptr, 100);
+                assert_eq!(offset, 90);
+                assert_eq!(capacity, 20);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn zero_offset() {
         let error = RegionValidationError::zero_offset();
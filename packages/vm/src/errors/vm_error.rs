@@ -11,137 +11,217 @@ use crate::backend::BackendError;
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum VmError {
-    #[error("Aborted: {}", msg)]
+    #[error("Aborted: {}{}", msg, breadcrumbs)]
     Aborted {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error calling into the VM's backend: {}", source)]
+    #[error("Custom message rejected by the chain: {msg}{breadcrumbs}")]
+    CustomMsgRejected {
+        msg: String,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Error calling into the VM's backend: {}{}", source, breadcrumbs)]
     BackendErr {
         source: BackendError,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Cache error: {msg}")]
+    #[error("Cache error: {msg}{breadcrumbs}")]
     CacheErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error in guest/host communication: {source}")]
+    #[error("Error in guest/host communication: {source}{breadcrumbs}")]
     CommunicationErr {
-        #[from]
         source: CommunicationError,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error compiling Wasm: {msg}")]
+    #[error("Error compiling Wasm: {msg}{breadcrumbs}")]
     CompileErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Couldn't convert from {} to {}. Input: {}", from_type, to_type, input)]
+    #[error(
+        "Couldn't convert from {} to {}. Input: {}{}",
+        from_type,
+        to_type,
+        input,
+        breadcrumbs
+    )]
     ConversionErr {
         from_type: String,
         to_type: String,
         input: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Crypto error: {}", source)]
+    #[error("Crypto error: {}{}", source, breadcrumbs)]
     CryptoErr {
         source: CryptoError,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Ran out of gas during contract execution")]
+    #[error("Ran out of gas during contract execution{breadcrumbs}")]
     GasDepletion {
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
     /// Whenever there is no specific error type available
-    #[error("Generic error: {msg}")]
+    #[error("Generic error: {msg}{breadcrumbs}")]
     GenericErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error instantiating a Wasm module: {msg}")]
+    #[error("Input too large. Got: {size} bytes; limit: {max} bytes{breadcrumbs}")]
+    InputTooLarge {
+        size: usize,
+        max: usize,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Maximum query depth exceeded. Got: {depth}; limit: {max}{breadcrumbs}")]
+    MaxQueryDepthExceeded {
+        depth: u32,
+        max: u32,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Invalid address '{addr}' in a message returned by the contract: {msg}{breadcrumbs}")]
+    InvalidMsgAddress {
+        addr: String,
+        msg: String,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Error instantiating a Wasm module: {msg}{breadcrumbs}")]
     InstantiationErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Hash doesn't match stored data")]
+    #[error("Hash doesn't match stored data{breadcrumbs}")]
     IntegrityErr {
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error parsing into type {target_type}: {msg}")]
+    #[error("Error parsing into type {target_type}: {msg}{breadcrumbs}")]
     ParseErr {
         /// the target type that was attempted
         target_type: String,
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Data too long for deserialization. Got: {length} bytes; limit: {max_length} bytes")]
+    #[error("Data too long for deserialization. Got: {length} bytes; limit: {max_length} bytes{breadcrumbs}")]
     DeserializationLimitExceeded {
         /// the target type that was attempted
         length: usize,
         max_length: usize,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error serializing type {source_type}: {msg}")]
+    #[error("Error serializing type {source_type}: {msg}{breadcrumbs}")]
     SerializeErr {
         /// the source type that was attempted
         source_type: String,
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error resolving Wasm function: {}", msg)]
+    #[error("Error resolving Wasm function: {}{}", msg, breadcrumbs)]
     ResolveErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
     #[error(
-        "Unexpected number of result values when calling '{}'. Expected: {}, actual: {}.",
+        "Unexpected number of result values when calling '{}'. Expected: {}, actual: {}.{}",
         function_name,
         expected,
-        actual
+        actual,
+        breadcrumbs
     )]
     ResultMismatch {
         function_name: String,
         expected: usize,
         actual: usize,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error executing Wasm: {}", msg)]
+    #[error("Error executing Wasm: {}{}", msg, breadcrumbs)]
     RuntimeErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Error during static Wasm validation: {}", msg)]
+    #[error("Error during static Wasm validation: {}{}", msg, breadcrumbs)]
     StaticValidationErr {
         msg: String,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Storage key too long. Got: {length} bytes; limit: {max_length} bytes{breadcrumbs}")]
+    StorageKeyTooLong {
+        length: usize,
+        max_length: usize,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Storage value too long. Got: {length} bytes; limit: {max_length} bytes{breadcrumbs}")]
+    StorageValueTooLong {
+        length: usize,
+        max_length: usize,
+        breadcrumbs: Breadcrumbs,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Too many storage writes in a single call. Limit: {limit}{breadcrumbs}")]
+    StorageWriteLimitExceeded {
+        limit: u32,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Uninitialized Context Data: {}", kind)]
+    #[error("Uninitialized Context Data: {}{}", kind, breadcrumbs)]
     UninitializedContextData {
         kind: String,
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
-    #[error("Must not call a writing storage function in this context.")]
+    #[error("Must not call a writing storage function in this context.{breadcrumbs}")]
     WriteAccessDenied {
+        breadcrumbs: Breadcrumbs,
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
@@ -151,6 +231,7 @@ impl VmError {
     pub(crate) fn aborted(msg: impl Into<String>) -> Self {
         VmError::Aborted {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -159,6 +240,16 @@ impl VmError {
     pub(crate) fn backend_err(original: BackendError) -> Self {
         VmError::BackendErr {
             source: original,
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn custom_msg_rejected(msg: impl Into<String>) -> Self {
+        VmError::CustomMsgRejected {
+            msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -167,6 +258,7 @@ impl VmError {
     pub(crate) fn cache_err(msg: impl Into<String>) -> Self {
         VmError::CacheErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -175,6 +267,7 @@ impl VmError {
     pub(crate) fn compile_err(msg: impl Into<String>) -> Self {
         VmError::CompileErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -189,6 +282,7 @@ impl VmError {
             from_type: from_type.into(),
             to_type: to_type.into(),
             input: input.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -197,6 +291,7 @@ impl VmError {
     pub(crate) fn crypto_err(original: CryptoError) -> Self {
         VmError::CryptoErr {
             source: original,
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -204,6 +299,37 @@ impl VmError {
 
     pub(crate) fn gas_depletion() -> Self {
         VmError::GasDepletion {
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn input_too_large(size: usize, max: usize) -> Self {
+        VmError::InputTooLarge {
+            size,
+            max,
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn max_query_depth_exceeded(depth: u32, max: u32) -> Self {
+        VmError::MaxQueryDepthExceeded {
+            depth,
+            max,
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn invalid_msg_address(addr: impl Into<String>, msg: impl Into<String>) -> Self {
+        VmError::InvalidMsgAddress {
+            addr: addr.into(),
+            msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -212,6 +338,7 @@ impl VmError {
     pub(crate) fn generic_err(msg: impl Into<String>) -> Self {
         VmError::GenericErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -220,6 +347,7 @@ impl VmError {
     pub(crate) fn instantiation_err(msg: impl Into<String>) -> Self {
         VmError::InstantiationErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -227,6 +355,7 @@ impl VmError {
 
     pub(crate) fn integrity_err() -> Self {
         VmError::IntegrityErr {
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -236,6 +365,7 @@ impl VmError {
         VmError::ParseErr {
             target_type: target.into(),
             msg: msg.to_string(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -245,6 +375,7 @@ impl VmError {
         VmError::DeserializationLimitExceeded {
             length,
             max_length,
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -254,6 +385,7 @@ impl VmError {
         VmError::SerializeErr {
             source_type: source.into(),
             msg: msg.to_string(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -262,6 +394,7 @@ impl VmError {
     pub(crate) fn resolve_err(msg: impl Into<String>) -> Self {
         VmError::ResolveErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -276,6 +409,7 @@ impl VmError {
             function_name: function_name.into(),
             expected,
             actual,
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -284,9 +418,10 @@ impl VmError {
     // Creates a runtime error with the given message.
     // This is private since it is only needed when converting wasmer::RuntimeError
     // to VmError.
-    fn runtime_err(msg: impl Into<String>) -> Self {
+    pub(crate) fn runtime_err(msg: impl Into<String>) -> Self {
         VmError::RuntimeErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -295,6 +430,36 @@ impl VmError {
     pub(crate) fn static_validation_err(msg: impl Into<String>) -> Self {
         VmError::StaticValidationErr {
             msg: msg.into(),
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn storage_key_too_long(length: usize, max_length: usize) -> Self {
+        VmError::StorageKeyTooLong {
+            length,
+            max_length,
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn storage_value_too_long(length: usize, max_length: usize) -> Self {
+        VmError::StorageValueTooLong {
+            length,
+            max_length,
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub(crate) fn storage_write_limit_exceeded(limit: u32) -> Self {
+        VmError::StorageWriteLimitExceeded {
+            limit,
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -303,6 +468,7 @@ impl VmError {
     pub(crate) fn uninitialized_context_data(kind: impl Into<String>) -> Self {
         VmError::UninitializedContextData {
             kind: kind.into(),
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -310,6 +476,132 @@ impl VmError {
 
     pub(crate) fn write_access_denied() -> Self {
         VmError::WriteAccessDenied {
+            breadcrumbs: Breadcrumbs::default(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
+impl VmError {
+    /// Adds a breadcrumb like "during db_read" or "in call_execute" to the error.
+    ///
+    /// The error variant is kept as it is, such that callers can still match on it.
+    /// The breadcrumbs are appended to the error message.
+    pub fn context(mut self, breadcrumb: impl Into<String>) -> Self {
+        self.breadcrumbs_mut().0.push(breadcrumb.into());
+        self
+    }
+
+    /// The breadcrumbs attached via [`VmError::context`], innermost first.
+    /// This is empty for errors without context.
+    pub fn breadcrumbs(&self) -> &[String] {
+        match self {
+            VmError::Aborted { breadcrumbs, .. }
+            | VmError::CustomMsgRejected { breadcrumbs, .. }
+            | VmError::BackendErr { breadcrumbs, .. }
+            | VmError::CacheErr { breadcrumbs, .. }
+            | VmError::CommunicationErr { breadcrumbs, .. }
+            | VmError::CompileErr { breadcrumbs, .. }
+            | VmError::ConversionErr { breadcrumbs, .. }
+            | VmError::CryptoErr { breadcrumbs, .. }
+            | VmError::GasDepletion { breadcrumbs, .. }
+            | VmError::GenericErr { breadcrumbs, .. }
+            | VmError::InputTooLarge { breadcrumbs, .. }
+            | VmError::MaxQueryDepthExceeded { breadcrumbs, .. }
+            | VmError::InvalidMsgAddress { breadcrumbs, .. }
+            | VmError::InstantiationErr { breadcrumbs, .. }
+            | VmError::IntegrityErr { breadcrumbs, .. }
+            | VmError::ParseErr { breadcrumbs, .. }
+            | VmError::DeserializationLimitExceeded { breadcrumbs, .. }
+            | VmError::SerializeErr { breadcrumbs, .. }
+            | VmError::ResolveErr { breadcrumbs, .. }
+            | VmError::ResultMismatch { breadcrumbs, .. }
+            | VmError::RuntimeErr { breadcrumbs, .. }
+            | VmError::StaticValidationErr { breadcrumbs, .. }
+            | VmError::StorageKeyTooLong { breadcrumbs, .. }
+            | VmError::StorageValueTooLong { breadcrumbs, .. }
+            | VmError::StorageWriteLimitExceeded { breadcrumbs, .. }
+            | VmError::UninitializedContextData { breadcrumbs, .. }
+            | VmError::WriteAccessDenied { breadcrumbs, .. } => &breadcrumbs.0,
+        }
+    }
+
+    /// Removes all breadcrumbs from the error and returns them, innermost first.
+    pub(crate) fn take_breadcrumbs(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.breadcrumbs_mut().0)
+    }
+
+    fn breadcrumbs_mut(&mut self) -> &mut Breadcrumbs {
+        match self {
+            VmError::Aborted { breadcrumbs, .. }
+            | VmError::CustomMsgRejected { breadcrumbs, .. }
+            | VmError::BackendErr { breadcrumbs, .. }
+            | VmError::CacheErr { breadcrumbs, .. }
+            | VmError::CommunicationErr { breadcrumbs, .. }
+            | VmError::CompileErr { breadcrumbs, .. }
+            | VmError::ConversionErr { breadcrumbs, .. }
+            | VmError::CryptoErr { breadcrumbs, .. }
+            | VmError::GasDepletion { breadcrumbs, .. }
+            | VmError::GenericErr { breadcrumbs, .. }
+            | VmError::InputTooLarge { breadcrumbs, .. }
+            | VmError::MaxQueryDepthExceeded { breadcrumbs, .. }
+            | VmError::InvalidMsgAddress { breadcrumbs, .. }
+            | VmError::InstantiationErr { breadcrumbs, .. }
+            | VmError::IntegrityErr { breadcrumbs, .. }
+            | VmError::ParseErr { breadcrumbs, .. }
+            | VmError::DeserializationLimitExceeded { breadcrumbs, .. }
+            | VmError::SerializeErr { breadcrumbs, .. }
+            | VmError::ResolveErr { breadcrumbs, .. }
+            | VmError::ResultMismatch { breadcrumbs, .. }
+            | VmError::RuntimeErr { breadcrumbs, .. }
+            | VmError::StaticValidationErr { breadcrumbs, .. }
+            | VmError::StorageKeyTooLong { breadcrumbs, .. }
+            | VmError::StorageValueTooLong { breadcrumbs, .. }
+            | VmError::StorageWriteLimitExceeded { breadcrumbs, .. }
+            | VmError::UninitializedContextData { breadcrumbs, .. }
+            | VmError::WriteAccessDenied { breadcrumbs, .. } => breadcrumbs,
+        }
+    }
+
+    /// The backend specific error code of the underlying [`BackendError`], if any.
+    /// See [`BackendError::code`].
+    pub fn backend_error_code(&self) -> Option<u32> {
+        match self {
+            VmError::BackendErr { source, .. } => source.code(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the underlying [`BackendError`] was marked as retryable by the backend.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            VmError::BackendErr { source, .. } => source.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+/// Breadcrumbs describing the operations that were in progress when a [`VmError`]
+/// occurred, innermost first. See [`VmError::context`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Breadcrumbs(Vec<String>);
+
+impl Display for Breadcrumbs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " ({})", self.0.join(" "))
+        }
+    }
+}
+
+impl From<CommunicationError> for VmError {
+    fn from(original: CommunicationError) -> Self {
+        VmError::CommunicationErr {
+            source: original,
+            breadcrumbs: Breadcrumbs::default(),
             #[cfg(feature = "backtraces")]
             backtrace: Backtrace::capture(),
         }
@@ -404,6 +696,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn custom_msg_rejected_works() {
+        let error = VmError::custom_msg_rejected("unknown denom");
+        match error {
+            VmError::CustomMsgRejected { msg, .. } => assert_eq!(msg, "unknown denom"),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn cache_err_works() {
         let error = VmError::cache_err("something went wrong");
@@ -452,6 +753,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn context_works() {
+        let error = VmError::generic_err("something went wrong").context("during db_read");
+        assert_eq!(error.breadcrumbs(), ["during db_read"]);
+
+        // further context is appended and the variant is kept
+        let error = error.context("in call_execute");
+        assert_eq!(error.breadcrumbs(), ["during db_read", "in call_execute"]);
+        assert_eq!(
+            error.to_string(),
+            "Generic error: something went wrong (during db_read in call_execute)"
+        );
+        match error {
+            VmError::GenericErr { msg, .. } => assert_eq!(msg, "something went wrong"),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        let error = VmError::gas_depletion().context("during db_read");
+        assert!(matches!(error, VmError::GasDepletion { .. }));
+        assert_eq!(error.breadcrumbs(), ["during db_read"]);
+
+        // no breadcrumbs
+        let error = VmError::generic_err("something went wrong");
+        assert!(error.breadcrumbs().is_empty());
+        assert_eq!(error.to_string(), "Generic error: something went wrong");
+    }
+
+    #[test]
+    fn backend_error_code_works() {
+        let error = VmError::backend_err(BackendError::coded_retryable(7, "timeout"))
+            .context("during db_read");
+        assert_eq!(error.backend_error_code(), Some(7));
+        assert!(error.is_retryable());
+
+        let error = VmError::backend_err(BackendError::coded(28, "disk full"));
+        assert_eq!(error.backend_error_code(), Some(28));
+        assert!(!error.is_retryable());
+
+        let error = VmError::backend_err(BackendError::unknown("broken"));
+        assert_eq!(error.backend_error_code(), None);
+        assert!(!error.is_retryable());
+
+        let error = VmError::generic_err("something went wrong");
+        assert_eq!(error.backend_error_code(), None);
+        assert!(!error.is_retryable());
+    }
+
     #[test]
     fn gas_depletion_works() {
         let error = VmError::gas_depletion();
@@ -461,6 +809,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn input_too_large_works() {
+        let error = VmError::input_too_large(42, 20);
+        match error {
+            VmError::InputTooLarge { size, max, .. } => {
+                assert_eq!(size, 42);
+                assert_eq!(max, 20);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn max_query_depth_exceeded_works() {
+        let error = VmError::max_query_depth_exceeded(11, 10);
+        match error {
+            VmError::MaxQueryDepthExceeded { depth, max, .. } => {
+                assert_eq!(depth, 11);
+                assert_eq!(max, 10);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn invalid_msg_address_works() {
+        let error = VmError::invalid_msg_address("foo", "Invalid input: too short");
+        match error {
+            VmError::InvalidMsgAddress { addr, msg, .. } => {
+                assert_eq!(addr, "foo");
+                assert_eq!(msg, "Invalid input: too short");
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn generic_err_works() {
         let guess = 7;
@@ -564,6 +948,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn storage_key_too_long_works() {
+        let error = VmError::storage_key_too_long(300, 200);
+        match error {
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 300);
+                assert_eq!(max_length, 200);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn storage_value_too_long_works() {
+        let error = VmError::storage_value_too_long(300, 200);
+        match error {
+            VmError::StorageValueTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 300);
+                assert_eq!(max_length, 200);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn storage_write_limit_exceeded_works() {
+        let error = VmError::storage_write_limit_exceeded(100);
+        match error {
+            VmError::StorageWriteLimitExceeded { limit, .. } => assert_eq!(limit, 100),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn uninitialized_context_data_works() {
         let error = VmError::uninitialized_context_data("foo");
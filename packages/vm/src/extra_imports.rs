@@ -0,0 +1,577 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+
+use wasmer::{Exports, Function, FunctionType, Memory, Store, Type, Val, WasmerEnv};
+
+use crate::backend::{BackendApi, GasInfo, Querier, Storage};
+use crate::compatibility::DEFAULT_IMPORT_NAMESPACE;
+use crate::environment::{process_gas_info, Environment};
+use crate::errors::{VmError, VmResult};
+use crate::imports::{to_high_half, to_low_half, write_to_contract};
+use crate::memory::{read_region, read_region_truncated, write_region};
+
+/// The operations host functions can perform on the instance they are bound to
+trait InstanceAccess: Send + Sync {
+    fn process_gas_info(&self, info: GasInfo) -> VmResult<()>;
+    fn memory(&self) -> Memory;
+    fn write_to_contract(&self, data: &[u8]) -> VmResult<u32>;
+    fn refund_call_overhead(&self, namespace: &str, name: &str) -> bool;
+}
+
+impl<A, S, Q> InstanceAccess for Environment<A, S, Q>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    fn process_gas_info(&self, info: GasInfo) -> VmResult<()> {
+        process_gas_info(self, info)
+    }
+
+    fn memory(&self) -> Memory {
+        Environment::memory(self)
+    }
+
+    fn write_to_contract(&self, data: &[u8]) -> VmResult<u32> {
+        write_to_contract(self, data)
+    }
+
+    fn refund_call_overhead(&self, namespace: &str, name: &str) -> bool {
+        self.is_metering_exempt(namespace, name) && Environment::refund_call_overhead(self)
+    }
+}
+
+/// A handle to the instance for host functions provided by the embedder via [`ExtraImports`].
+/// It gives access to the gas state and memory of the instance.
+///
+/// The handle is created before the instance and bound to it when the instance is created.
+/// It implements `WasmerEnv`, such that it can be used as the environment of a host function.
+#[derive(Clone, Default)]
+pub struct HostEnv {
+    instance: Arc<RwLock<Option<Arc<dyn InstanceAccess>>>>,
+}
+
+impl HostEnv {
+    /// Charges gas in the same way as the imports of the VM, i.e. `cost` and
+    /// `externally_used` are deducted from the gas left and `externally_used`
+    /// shows up in the gas report.
+    ///
+    /// Returns a `VmError::GasDepletion` if the instance ran out of gas. The host function
+    /// should return this error, which aborts the execution.
+    pub fn process_gas_info(&self, info: GasInfo) -> VmResult<()> {
+        self.bound()?.process_gas_info(info)
+    }
+
+    /// Refunds the gas the Wasm-level metering charged for calling the host function
+    /// `namespace.name` if it was marked via [`ExtraImports::exempt_from_metering`].
+    /// Returns whether gas was refunded.
+    ///
+    /// Internally metered host functions call this first and then charge for their call
+    /// overhead themselves, such that they are not charged twice. Gas is refunded at most
+    /// once per call of the host function.
+    pub fn refund_call_overhead(&self, namespace: &str, name: &str) -> VmResult<bool> {
+        Ok(self.bound()?.refund_call_overhead(namespace, name))
+    }
+
+    /// Reads the data of the region at `ptr` in the contract's memory. Errors if the
+    /// region is invalid or the data is longer than `max_length`.
+    pub fn read_region(&self, ptr: u32, max_length: usize) -> VmResult<Vec<u8>> {
+        read_region(&self.memory()?, ptr, max_length)
+    }
+
+    /// Like [`HostEnv::read_region`] but reads only the first `max_length` bytes of longer
+    /// regions instead of erroring. Returns the data along with the full length of the region.
+    pub fn read_region_truncated(&self, ptr: u32, max_length: usize) -> VmResult<(Vec<u8>, usize)> {
+        read_region_truncated(&self.memory()?, ptr, max_length)
+    }
+
+    /// Writes the data to the region at `ptr`, which must have been allocated by the contract
+    /// and be large enough. Sets the length of the region to the length of the data.
+    pub fn write_region(&self, ptr: u32, data: &[u8]) -> VmResult<()> {
+        write_region(&self.memory()?, ptr, data)
+    }
+
+    /// Allocates a region in the contract's memory, writes the data to it and returns
+    /// the pointer to the region. Ownership of the region is transferred to the contract.
+    pub fn write_to_contract(&self, data: &[u8]) -> VmResult<u32> {
+        self.bound()?.write_to_contract(data)
+    }
+
+    /// The memory of the contract. Prefer the region based methods above, which perform the
+    /// same bounds checks as the imports of the VM.
+    pub fn memory(&self) -> VmResult<Memory> {
+        Ok(self.bound()?.memory())
+    }
+
+    /// Binds the handle to the instance that owns `env`
+    pub(crate) fn bind<A, S, Q>(&self, env: &Environment<A, S, Q>)
+    where
+        A: BackendApi + 'static,
+        S: Storage + 'static,
+        Q: Querier + 'static,
+    {
+        let instance: Arc<dyn InstanceAccess> = Arc::new(env.clone());
+        *self.instance.write().unwrap() = Some(instance);
+    }
+
+    fn bound(&self) -> VmResult<Arc<dyn InstanceAccess>> {
+        // Clone the instance access to not hold the lock during the operation
+        self.instance
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| VmError::uninitialized_context_data("host env"))
+    }
+}
+
+impl WasmerEnv for HostEnv {}
+
+/// Creates a host function for [`ExtraImports`] in the style of the VM's imports.
+///
+/// The function takes a pointer to a region containing the input of at most `max_input_length`
+/// bytes, which is passed to `func`. Ownership of the input region is not transferred to the host.
+/// It returns a u64 with the error code in the high half and a pointer to a region in the low half:
+/// - If `func` returns `Ok(Ok(output))`, the error code is 0 and the region contains the output.
+/// - If `func` returns `Ok(Err(message))`, the error code is 1 and the region contains the message.
+///
+/// In both cases ownership of the region is transferred to the contract. Errors of type `VmError`
+/// returned by `func` (e.g. from [`HostEnv::process_gas_info`]) abort the execution.
+pub fn make_host_function<F>(
+    store: &Store,
+    env: HostEnv,
+    max_input_length: usize,
+    func: F,
+) -> Function
+where
+    F: Fn(&HostEnv, Vec<u8>) -> VmResult<Result<Vec<u8>, String>> + Send + Sync + 'static,
+{
+    // Native functions cannot capture an environment in Wasmer 2, so we use a dynamic one
+    let signature = FunctionType::new([Type::I32], [Type::I64]);
+    Function::new_with_env(store, signature, env, move |env: &HostEnv, args: &[Val]| {
+        let input_ptr = args[0].unwrap_i32() as u32;
+        let input = env.read_region(input_ptr, max_input_length)?;
+        let (code, data) = match func(env, input)? {
+            Ok(output) => (0, output),
+            Err(message) => (1, message.into_bytes()),
+        };
+        let ptr = env.write_to_contract(&data)?;
+        Ok(vec![Val::I64(
+            (to_high_half(code) | to_low_half(ptr)) as i64,
+        )])
+    })
+}
+
+/// Host functions provided by the embedder in addition to the imports of the VM,
+/// grouped by namespace. Namespaces are registered in the order they were inserted.
+#[derive(Default)]
+pub struct ExtraImports<'a> {
+    namespaces: Vec<(&'a str, Exports)>,
+    host_env: HostEnv,
+    metering_exempt: BTreeSet<String>,
+}
+
+impl<'a> ExtraImports<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given functions in the given namespace.
+    ///
+    /// Errors if the namespace was added before or is reserved for the imports of the VM,
+    /// such that extra imports never shadow other imports.
+    pub fn insert(&mut self, namespace: &'a str, exports: Exports) -> VmResult<()> {
+        if namespace == DEFAULT_IMPORT_NAMESPACE {
+            return Err(VmError::generic_err(format!(
+                "Namespace \"{}\" is reserved for the VM",
+                namespace
+            )));
+        }
+        if self
+            .namespaces
+            .iter()
+            .any(|(existing, _)| *existing == namespace)
+        {
+            return Err(VmError::generic_err(format!(
+                "Extra imports with namespace \"{}\" exist already",
+                namespace
+            )));
+        }
+        self.namespaces.push((namespace, exports));
+        Ok(())
+    }
+
+    /// Marks a function added via [`ExtraImports::insert`] as internally metered.
+    ///
+    /// Such a function charges gas for its call overhead itself, e.g. via
+    /// [`HostEnv::process_gas_info`], and claims a refund of the gas charged by the Wasm-level
+    /// metering for the call via [`HostEnv::refund_call_overhead`]. This way high-frequency
+    /// utilities are not charged twice.
+    pub fn exempt_from_metering(&mut self, namespace: &str, name: &str) -> VmResult<()> {
+        let exports = self
+            .namespaces
+            .iter()
+            .find(|(existing, _)| *existing == namespace)
+            .map(|(_, exports)| exports)
+            .ok_or_else(|| {
+                VmError::generic_err(format!(
+                    "Extra imports with namespace \"{}\" not found",
+                    namespace
+                ))
+            })?;
+        if exports.get_function(name).is_err() {
+            return Err(VmError::generic_err(format!(
+                "Extra import function \"{}.{}\" not found",
+                namespace, name
+            )));
+        }
+        self.metering_exempt
+            .insert(format!("{}.{}", namespace, name));
+        Ok(())
+    }
+
+    /// The functions marked via [`ExtraImports::exempt_from_metering`] as `namespace.name`
+    pub(crate) fn metering_exempt(&self) -> &BTreeSet<String> {
+        &self.metering_exempt
+    }
+
+    /// Returns the handle to the instance these imports are provided to.
+    /// This is meant to be used as (part of) the environment of the host functions.
+    pub fn host_env(&self) -> HostEnv {
+        self.host_env.clone()
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<(&'a str, Exports)>, HostEnv) {
+        (self.namespaces, self.host_env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::Function;
+
+    use crate::instance::{instance_from_module, Instance};
+    use crate::testing::{mock_backend, MockApi, MockQuerier, MockStorage};
+    use crate::wasm_backend::{compile, make_runtime_store, GAS_PER_OPERATION};
+    use crate::Size;
+
+    const GAS_LIMIT: u64 = 1_000_000_000;
+
+    fn make_instance() -> Instance<MockApi, MockStorage, MockQuerier> {
+        make_instance_with_exemption(false)
+    }
+
+    /// Like [`make_instance`] but optionally exempts `chain.charge` from metering
+    fn make_instance_with_exemption(exempt: bool) -> Instance<MockApi, MockStorage, MockQuerier> {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "chain" "charge" (func $charge (param i64 i64)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "main") (param i64 i64) (result i32)
+                local.get 0
+                local.get 1
+                call $charge
+                i32.const 0)
+            )"#,
+        )
+        .unwrap();
+        let module = compile(&wasm, Some(Size::mebi(16)), &[]).unwrap();
+
+        let mut extra_imports = ExtraImports::new();
+        let charge = Function::new_native_with_env(
+            module.store(),
+            extra_imports.host_env(),
+            |env: &HostEnv, cost: u64, externally_used: u64| -> VmResult<()> {
+                env.refund_call_overhead("chain", "charge")?;
+                env.process_gas_info(GasInfo::new(cost, externally_used))
+            },
+        );
+        let mut exports = Exports::new();
+        exports.insert("charge", charge);
+        extra_imports.insert("chain", exports).unwrap();
+        if exempt {
+            extra_imports
+                .exempt_from_metering("chain", "charge")
+                .unwrap();
+        }
+
+        instance_from_module(
+            &module,
+            mock_backend(&[]),
+            GAS_LIMIT,
+            false,
+            Some(extra_imports),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn host_env_charges_gas_of_the_instance() {
+        let instance = make_instance();
+        let before = instance.create_gas_report();
+        instance
+            .call_function1("main", &[Val::I64(1_000_000), Val::I64(2_000_000)])
+            .unwrap();
+        let report = instance.create_gas_report();
+        assert_eq!(report.used_externally, 2_000_000);
+        assert!(before.remaining - report.remaining > 3_000_000);
+    }
+
+    #[test]
+    fn host_env_reports_gas_depletion() {
+        let instance = make_instance();
+        let err = instance
+            .call_function1("main", &[Val::I64(GAS_LIMIT as i64), Val::I64(0)])
+            .unwrap_err();
+        assert!(err.to_string().contains("Ran out of gas"), "{}", err);
+        assert_eq!(instance.create_gas_report().remaining, 0);
+    }
+
+    #[test]
+    fn metering_exempt_imports_get_call_overhead_refunded() {
+        let args = [Val::I64(1_000_000), Val::I64(0)];
+
+        let instance = make_instance_with_exemption(false);
+        instance.call_function1("main", &args).unwrap();
+        let metered = instance.create_gas_report();
+
+        let instance = make_instance_with_exemption(true);
+        instance.call_function1("main", &args).unwrap();
+        let exempt = instance.create_gas_report();
+
+        assert_eq!(exempt.remaining, metered.remaining + GAS_PER_OPERATION);
+        assert_eq!(exempt.used_externally, metered.used_externally);
+    }
+
+    #[test]
+    fn call_overhead_is_refunded_once_per_call() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "chain" "refund" (func $refund (result i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "main") (result i32)
+                call $refund
+                call $refund
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let module = compile(&wasm, Some(Size::mebi(16)), &[]).unwrap();
+
+        let mut extra_imports = ExtraImports::new();
+        let refund = Function::new_native_with_env(
+            module.store(),
+            extra_imports.host_env(),
+            |env: &HostEnv| -> VmResult<u32> {
+                let first = env.refund_call_overhead("chain", "refund")?;
+                env.process_gas_info(GasInfo::with_cost(1))?;
+                let second = env.refund_call_overhead("chain", "refund")?;
+                Ok(first as u32 + second as u32)
+            },
+        );
+        let mut exports = Exports::new();
+        exports.insert("refund", refund);
+        extra_imports.insert("chain", exports).unwrap();
+        extra_imports
+            .exempt_from_metering("chain", "refund")
+            .unwrap();
+        let instance = instance_from_module(
+            &module,
+            mock_backend(&[]),
+            GAS_LIMIT,
+            false,
+            Some(extra_imports),
+        )
+        .unwrap();
+
+        // one refund for each of the two calls
+        let refunds = instance.call_function1("main", &[]).unwrap();
+        assert_eq!(refunds.unwrap_i32(), 2);
+    }
+
+    #[test]
+    fn refund_call_overhead_errors_when_not_bound() {
+        let env = HostEnv::default();
+        match env.refund_call_overhead("chain", "charge").unwrap_err() {
+            VmError::UninitializedContextData { kind, .. } => assert_eq!(kind, "host env"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn exempt_from_metering_requires_existing_function() {
+        let store = make_runtime_store(None);
+        let mut extra_imports = ExtraImports::new();
+        let mut exports = Exports::new();
+        exports.insert("nop", Function::new_native(&store, || {}));
+        extra_imports.insert("chain", exports).unwrap();
+
+        extra_imports.exempt_from_metering("chain", "nop").unwrap();
+        let err = extra_imports
+            .exempt_from_metering("chain", "other")
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("\"chain.other\" not found"),
+            "{}",
+            err
+        );
+        let err = extra_imports
+            .exempt_from_metering("other", "nop")
+            .unwrap_err();
+        assert!(err.to_string().contains("\"other\" not found"), "{}", err);
+        assert_eq!(
+            extra_imports.metering_exempt().iter().collect::<Vec<_>>(),
+            ["chain.nop"]
+        );
+    }
+
+    #[test]
+    fn host_env_errors_when_not_bound() {
+        let env = HostEnv::default();
+        match env.process_gas_info(GasInfo::with_cost(1)).unwrap_err() {
+            VmError::UninitializedContextData { kind, .. } => assert_eq!(kind, "host env"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    /// An instance of a contract with a bump allocator that calls the host function
+    /// `chain.reverse` in `run`
+    fn make_reverse_instance() -> (Instance<MockApi, MockStorage, MockQuerier>, HostEnv) {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "chain" "reverse" (func $reverse (param i32) (result i64)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (global $next (mut i32) (i32.const 1024))
+            (func (export "allocate") (param $size i32) (result i32)
+                (local $region i32)
+                global.get $next
+                local.set $region
+                ;; offset, capacity and length of the region
+                local.get $region
+                local.get $region
+                i32.const 12
+                i32.add
+                i32.store
+                local.get $region
+                local.get $size
+                i32.store offset=4
+                local.get $region
+                i32.const 0
+                i32.store offset=8
+                ;; keep regions 4 byte aligned
+                local.get $region
+                i32.const 15
+                i32.add
+                local.get $size
+                i32.add
+                i32.const -4
+                i32.and
+                global.set $next
+                local.get $region)
+            (func (export "deallocate") (param i32))
+            (func (export "run") (param i32) (result i64)
+                local.get 0
+                call $reverse)
+            )"#,
+        )
+        .unwrap();
+        let module = compile(&wasm, Some(Size::mebi(16)), &[]).unwrap();
+
+        let mut extra_imports = ExtraImports::new();
+        let reverse = make_host_function(
+            module.store(),
+            extra_imports.host_env(),
+            8,
+            |env: &HostEnv, mut input: Vec<u8>| {
+                env.process_gas_info(GasInfo::with_externally_used(input.len() as u64))?;
+                if input.is_empty() {
+                    return Ok(Err("Input must not be empty".to_string()));
+                }
+                input.reverse();
+                Ok(Ok(input))
+            },
+        );
+        let mut exports = Exports::new();
+        exports.insert("reverse", reverse);
+        extra_imports.insert("chain", exports).unwrap();
+        let host_env = extra_imports.host_env();
+        let instance = instance_from_module(
+            &module,
+            mock_backend(&[]),
+            GAS_LIMIT,
+            false,
+            Some(extra_imports),
+        )
+        .unwrap();
+        (instance, host_env)
+    }
+
+    #[test]
+    fn make_host_function_works() {
+        let (mut instance, _) = make_reverse_instance();
+
+        let mut run = |input: &[u8]| -> VmResult<(u64, Vec<u8>)> {
+            let input_ptr = instance.allocate(input.len())?;
+            instance.write_memory(input_ptr, input)?;
+            let result = instance.call_function1("run", &[input_ptr.into()])?;
+            let result = result.unwrap_i64() as u64;
+            let output = instance.read_memory(result as u32, 100)?;
+            Ok((result >> 32, output))
+        };
+
+        assert_eq!(run(b"abc").unwrap(), (0, b"cba".to_vec()));
+        assert_eq!(run(b"").unwrap(), (1, b"Input must not be empty".to_vec()));
+        // input too long
+        let err = run(b"123456789").unwrap_err();
+        assert!(err.to_string().contains("Region length too big"), "{}", err);
+
+        assert_eq!(instance.create_gas_report().used_externally, 3);
+    }
+
+    #[test]
+    fn host_env_region_access_works() {
+        let (mut instance, host_env) = make_reverse_instance();
+
+        let ptr = instance.allocate(5).unwrap();
+        host_env.write_region(ptr, b"hello").unwrap();
+        assert_eq!(instance.read_memory(ptr, 5).unwrap(), b"hello");
+        assert_eq!(host_env.read_region(ptr, 5).unwrap(), b"hello");
+        assert_eq!(
+            host_env.read_region_truncated(ptr, 2).unwrap(),
+            (b"he".to_vec(), 5)
+        );
+
+        // same bounds checks as the imports of the VM
+        let err = host_env.read_region(ptr, 4).unwrap_err();
+        assert!(err.to_string().contains("Region length too big"), "{}", err);
+        let err = host_env.write_region(ptr, b"hello world").unwrap_err();
+        assert!(err.to_string().contains("Region too small"), "{}", err);
+        let too_far = host_env.memory().unwrap().size().bytes().0 as u32;
+        host_env.read_region(too_far, 5).unwrap_err();
+    }
+
+    #[test]
+    fn extra_imports_insert_checks_namespaces() {
+        let mut extra_imports = ExtraImports::new();
+        extra_imports.insert("chain", Exports::new()).unwrap();
+        extra_imports.insert("math", Exports::new()).unwrap();
+
+        let err = extra_imports.insert("chain", Exports::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Extra imports with namespace \"chain\" exist already"
+        );
+        let err = extra_imports.insert("env", Exports::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Namespace \"env\" is reserved for the VM"
+        );
+
+        let (namespaces, _) = extra_imports.into_parts();
+        let names: Vec<_> = namespaces.iter().map(|(namespace, _)| *namespace).collect();
+        assert_eq!(names, ["chain", "math"]);
+    }
+}
@@ -1,24 +1,37 @@
 //! Import implementations
 
+use std::borrow::Cow;
 use std::cmp::max;
+use std::collections::HashMap;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
 
 use cosmwasm_crypto::{
-    ed25519_batch_verify, ed25519_verify, secp256k1_recover_pubkey, secp256k1_verify, CryptoError,
+    bls12_381_aggregate_verify, bls12_381_hash_to_g1, bls12_381_hash_to_g2,
+    bls12_381_pairing_equality, ct_eq, ed25519_batch_decode_packed, ed25519_batch_verify,
+    ed25519_verify, ics23_verify_membership, ics23_verify_non_membership, poseidon_hash,
+    secp256k1_recover_pubkey, secp256k1_verify, secp256k1_verify_prehashed,
+    secp256r1_recover_pubkey, secp256r1_verify, tendermint_verify_header, zk_verify_groth16,
+    CryptoError, CryptoResult,
 };
 use cosmwasm_crypto::{
-    ECDSA_PUBKEY_MAX_LEN, ECDSA_SIGNATURE_LEN, EDDSA_PUBKEY_LEN, MESSAGE_HASH_MAX_LEN,
+    BLS12_381_G1_POINT_LEN, BLS12_381_G2_POINT_LEN, BLS12_381_MAX_AGGREGATE_SIZE,
+    ECDSA_PUBKEY_MAX_LEN, ECDSA_SIGNATURE_LEN, EDDSA_PUBKEY_LEN, GROTH16_MAX_PUBLIC_INPUTS,
+    GROTH16_PROOF_LEN, GROTH16_PUBLIC_INPUT_LEN, GROTH16_VK_MAX_LEN, ICS23_KEY_MAX_LEN,
+    ICS23_PROOF_MAX_LEN, ICS23_ROOT_MAX_LEN, ICS23_VALUE_MAX_LEN, MESSAGE_HASH_MAX_LEN,
+    POSEIDON_INPUT_LEN, POSEIDON_MAX_INPUTS,
 };
 
 #[cfg(feature = "iterator")]
 use cosmwasm_std::Order;
+use cosmwasm_std::{Binary, ContractResult, SystemError, SystemResult};
 
 use crate::backend::{BackendApi, BackendError, Querier, Storage};
 use crate::conversion::{ref_to_u32, to_u32};
 use crate::environment::{process_gas_info, Environment};
 use crate::errors::{CommunicationError, VmError, VmResult};
-#[cfg(feature = "iterator")]
-use crate::memory::maybe_read_region;
-use crate::memory::{read_region, write_region};
+use crate::memory::{read_region, read_region_truncated, write_region};
 use crate::sections::decode_sections;
 #[allow(unused_imports)]
 use crate::sections::encode_sections;
@@ -29,10 +42,6 @@ use crate::GasInfo;
 const KI: usize = 1024;
 /// A mibi (mega binary)
 const MI: usize = 1024 * 1024;
-/// Max key length for db_write/db_read/db_remove/db_scan (when VM reads the key argument from Wasm memory)
-const MAX_LENGTH_DB_KEY: usize = 64 * KI;
-/// Max value length for db_write (when VM reads the value argument from Wasm memory)
-const MAX_LENGTH_DB_VALUE: usize = 128 * KI;
 /// Typically 20 (Cosmos SDK, Ethereum), 32 (Nano, Substrate) or 54 (MockApi)
 const MAX_LENGTH_CANONICAL_ADDRESS: usize = 64;
 /// The max length of human address inputs (in bytes).
@@ -50,9 +59,22 @@ const MAX_LENGTH_ED25519_MESSAGE: usize = 128 * 1024;
 /// This is an arbitrary value, for performance / memory contraints. If you need to batch-verify a
 /// larger number of signatures, let us know.
 const MAX_COUNT_ED25519_BATCH: usize = 256;
-
-/// Max length for a debug message
-const MAX_LENGTH_DEBUG: usize = 2 * MI;
+/// Max length of each input of a constant-time comparison in bytes.
+/// This is an arbitrary value, large enough for keys, hashes and commitment preimages.
+const MAX_LENGTH_CT_EQ: usize = 64 * KI;
+
+/// Max length of the sections encoded input of a Tendermint header verification, i.e. the
+/// trusted header, the trusted next validators, the untrusted signed header and the
+/// untrusted validators. This is enough for validator sets of a few hundred validators.
+const MAX_LENGTH_TENDERMINT_INPUT: usize = 512 * KI;
+
+/// Max length of a message hashed to a BLS12-381 curve point, i.e. the input of a hash-to-curve
+/// call or one message of an aggregate signature. Messages signed with BLS are typically
+/// 32 byte hashes such as Ethereum signing roots or drand round digests.
+const MAX_LENGTH_BLS12_381_MESSAGE: usize = 4 * KI;
+/// Max length of a domain separation tag for hashing to BLS12-381 curve points,
+/// see RFC 9380, section 5.3.3.
+const MAX_LENGTH_BLS12_381_DST: usize = 255;
 
 /// Max length for an abort message
 const MAX_LENGTH_ABORT: usize = 2 * MI;
@@ -69,19 +91,91 @@ pub fn do_db_read<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
     key_ptr: u32,
 ) -> VmResult<u32> {
-    let key = read_region(&env.memory(), key_ptr, MAX_LENGTH_DB_KEY)?;
-
-    let (result, gas_info) = env.with_storage_from_context::<_, _>(|store| Ok(store.get(&key)))?;
-    process_gas_info::<A, S, Q>(env, gas_info)?;
-    let value = result?;
+    let key = read_db_key(env, key_ptr)?;
+
+    let cached = env
+        .get_cached_read(&key)
+        .and_then(|(value, original_gas_info)| {
+            env.storage_read_cache
+                .cache_hit_gas(original_gas_info)
+                .map(|gas_info| (value, gas_info))
+        });
+    let value = match cached {
+        Some((value, gas_info)) => {
+            process_gas_info::<A, S, Q>(env, gas_info)?;
+            value
+        }
+        None => {
+            let (result, gas_info) =
+                env.with_storage_from_context::<_, _>(|store| Ok(store.get(&key)))?;
+            let gas_info = env
+                .gas_config
+                .storage_multiplier
+                .apply_to_gas_info(gas_info);
+            process_gas_info::<A, S, Q>(env, gas_info)?;
+            let value = result?;
+            env.cache_read(&key, value.clone(), gas_info);
+            value
+        }
+    };
 
     let out_data = match value {
         Some(data) => data,
         None => return Ok(0),
     };
+    env.update_session(|session| session.storage_bytes_read += out_data.len() as u64);
     write_to_contract::<A, S, Q>(env, &out_data)
 }
 
+/// Reads a storage key from Wasm memory, enforcing the key length limit of the environment
+fn read_db_key<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    key_ptr: u32,
+) -> VmResult<Vec<u8>> {
+    read_region(&env.memory(), key_ptr, env.storage_limits.max_key_length).map_err(
+        |err| match err {
+            VmError::CommunicationErr {
+                source: CommunicationError::RegionLengthTooBig { length, max_length },
+                ..
+            } => VmError::storage_key_too_long(length, max_length),
+            err => err,
+        },
+    )
+}
+
+/// Like [`read_db_key`] but gracefully handles null pointer (0) by returning None.
+/// This is meant to be used where the key is optional (like scan).
+#[cfg(feature = "iterator")]
+fn maybe_read_db_key<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    key_ptr: u32,
+) -> VmResult<Option<Vec<u8>>> {
+    if key_ptr == 0 {
+        Ok(None)
+    } else {
+        read_db_key(env, key_ptr).map(Some)
+    }
+}
+
+/// Reads a storage value from Wasm memory, enforcing the value length limit of the environment
+fn read_db_value<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    value_ptr: u32,
+) -> VmResult<Vec<u8>> {
+    read_region(
+        &env.memory(),
+        value_ptr,
+        env.storage_limits.max_value_length,
+    )
+    .map_err(|err| match err {
+        VmError::CommunicationErr {
+            source: CommunicationError::RegionLengthTooBig { length, max_length },
+            ..
+        } => VmError::storage_value_too_long(length, max_length),
+        err => err,
+    })
+}
+
 /// Writes a storage entry from Wasm memory into the VM's storage
 pub fn do_db_write<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
@@ -92,13 +186,21 @@ pub fn do_db_write<A: BackendApi, S: Storage, Q: Querier>(
         return Err(VmError::write_access_denied());
     }
 
-    let key = read_region(&env.memory(), key_ptr, MAX_LENGTH_DB_KEY)?;
-    let value = read_region(&env.memory(), value_ptr, MAX_LENGTH_DB_VALUE)?;
+    env.count_storage_write()?;
+
+    let key = read_db_key(env, key_ptr)?;
+    let value = read_db_value(env, value_ptr)?;
+    env.invalidate_cached_read(&key);
 
     let (result, gas_info) =
         env.with_storage_from_context::<_, _>(|store| Ok(store.set(&key, &value)))?;
+    let gas_info = env
+        .gas_config
+        .storage_multiplier
+        .apply_to_gas_info(gas_info);
     process_gas_info::<A, S, Q>(env, gas_info)?;
     result?;
+    env.update_session(|session| session.storage_bytes_written += (key.len() + value.len()) as u64);
 
     Ok(())
 }
@@ -111,12 +213,20 @@ pub fn do_db_remove<A: BackendApi, S: Storage, Q: Querier>(
         return Err(VmError::write_access_denied());
     }
 
-    let key = read_region(&env.memory(), key_ptr, MAX_LENGTH_DB_KEY)?;
+    env.count_storage_write()?;
+
+    let key = read_db_key(env, key_ptr)?;
+    env.invalidate_cached_read(&key);
 
     let (result, gas_info) =
         env.with_storage_from_context::<_, _>(|store| Ok(store.remove(&key)))?;
+    let gas_info = env
+        .gas_config
+        .storage_multiplier
+        .apply_to_gas_info(gas_info);
     process_gas_info(env, gas_info)?;
     result?;
+    env.update_session(|session| session.storage_bytes_written += key.len() as u64);
 
     Ok(())
 }
@@ -231,7 +341,51 @@ pub fn do_secp256k1_verify<A: BackendApi, S: Storage, Q: Querier>(
             | CryptoError::InvalidPubkeyFormat { .. }
             | CryptoError::InvalidSignatureFormat { .. }
             | CryptoError::GenericErr { .. } => err.code(),
-            CryptoError::BatchErr { .. } | CryptoError::InvalidRecoveryParam { .. } => {
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
+}
+
+/// Like [`do_secp256k1_verify`] but with an explicit identifier of the hash function that
+/// produced the digest. See [`secp256k1_verify_prehashed`] for the supported identifiers.
+pub fn do_secp256k1_verify_prehashed<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    digest_ptr: u32,
+    hash_function: u32,
+    signature_ptr: u32,
+    pubkey_ptr: u32,
+) -> VmResult<u32> {
+    let digest = read_region(&env.memory(), digest_ptr, MESSAGE_HASH_MAX_LEN)?;
+    let signature = read_region(&env.memory(), signature_ptr, ECDSA_SIGNATURE_LEN)?;
+    let pubkey = read_region(&env.memory(), pubkey_ptr, ECDSA_PUBKEY_MAX_LEN)?;
+
+    let gas_info = GasInfo::with_cost(env.gas_config.secp256k1_verify_cost);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = secp256k1_verify_prehashed(&digest, hash_function, &signature, &pubkey);
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidHashFunction { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidRecoveryParam { .. } => {
                 panic!("Error must not happen for this call")
             }
         },
@@ -265,7 +419,88 @@ pub fn do_secp256k1_recover_pubkey<A: BackendApi, S: Storage, Q: Querier>(
             | CryptoError::InvalidSignatureFormat { .. }
             | CryptoError::InvalidRecoveryParam { .. }
             | CryptoError::GenericErr { .. } => Ok(to_high_half(err.code())),
-            CryptoError::BatchErr { .. } | CryptoError::InvalidPubkeyFormat { .. } => {
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+    }
+}
+
+pub fn do_secp256r1_verify<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    hash_ptr: u32,
+    signature_ptr: u32,
+    pubkey_ptr: u32,
+) -> VmResult<u32> {
+    let hash = read_region(&env.memory(), hash_ptr, MESSAGE_HASH_MAX_LEN)?;
+    let signature = read_region(&env.memory(), signature_ptr, ECDSA_SIGNATURE_LEN)?;
+    let pubkey = read_region(&env.memory(), pubkey_ptr, ECDSA_PUBKEY_MAX_LEN)?;
+
+    let gas_info = GasInfo::with_cost(env.gas_config.secp256r1_verify_cost);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = secp256r1_verify(&hash, &signature, &pubkey);
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
+}
+
+pub fn do_secp256r1_recover_pubkey<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    hash_ptr: u32,
+    signature_ptr: u32,
+    recover_param: u32,
+) -> VmResult<u64> {
+    let hash = read_region(&env.memory(), hash_ptr, MESSAGE_HASH_MAX_LEN)?;
+    let signature = read_region(&env.memory(), signature_ptr, ECDSA_SIGNATURE_LEN)?;
+    let recover_param: u8 = match recover_param.try_into() {
+        Ok(rp) => rp,
+        Err(_) => return Ok((CryptoError::invalid_recovery_param().code() as u64) << 32),
+    };
+
+    let gas_info = GasInfo::with_cost(env.gas_config.secp256r1_recover_pubkey_cost);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = secp256r1_recover_pubkey(&hash, &signature, recover_param);
+    match result {
+        Ok(pubkey) => {
+            let pubkey_ptr = write_to_contract::<A, S, Q>(env, pubkey.as_ref())?;
+            Ok(to_low_half(pubkey_ptr))
+        }
+        Err(err) => match err {
+            CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::GenericErr { .. } => Ok(to_high_half(err.code())),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
                 panic!("Error must not happen for this call")
             }
         },
@@ -291,8 +526,14 @@ pub fn do_ed25519_verify<A: BackendApi, S: Storage, Q: Querier>(
             | CryptoError::InvalidSignatureFormat { .. }
             | CryptoError::GenericErr { .. } => err.code(),
             CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::HashInputErr { .. }
             | CryptoError::InvalidHashFormat { .. }
-            | CryptoError::InvalidRecoveryParam { .. } => {
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
                 panic!("Error must not happen for this call")
             }
         },
@@ -326,6 +567,41 @@ pub fn do_ed25519_batch_verify<A: BackendApi, S: Storage, Q: Querier>(
     let signatures = decode_sections(&signatures);
     let public_keys = decode_sections(&public_keys);
 
+    ed25519_batch_verify_with_gas(env, &messages, &signatures, &public_keys)
+}
+
+/// Like [`do_ed25519_batch_verify`] but with all inputs in a single region
+/// using the packed format of [`ed25519_batch_decode_packed`].
+pub fn do_ed25519_batch_verify_packed<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    input_ptr: u32,
+) -> VmResult<u32> {
+    let input = read_region(
+        &env.memory(),
+        input_ptr,
+        (MAX_LENGTH_ED25519_MESSAGE + MAX_LENGTH_ED25519_SIGNATURE + EDDSA_PUBKEY_LEN + 12)
+            * MAX_COUNT_ED25519_BATCH
+            + 12,
+    )?;
+    // Charged before decoding, such that malformed input is not free
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.ed25519_batch_verify_packed_per_byte_cost * input.len() as u64,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let batch = match ed25519_batch_decode_packed(&input) {
+        Ok(batch) => batch,
+        Err(err) => return Ok(err.code()),
+    };
+
+    ed25519_batch_verify_with_gas(env, &batch.messages, &batch.signatures, &batch.public_keys)
+}
+
+fn ed25519_batch_verify_with_gas<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    messages: &[&[u8]],
+    signatures: &[&[u8]],
+    public_keys: &[&[u8]],
+) -> VmResult<u32> {
     let gas_cost = if public_keys.len() == 1 {
         env.gas_config.ed25519_batch_verify_one_pubkey_cost
     } else {
@@ -333,14 +609,21 @@ pub fn do_ed25519_batch_verify<A: BackendApi, S: Storage, Q: Querier>(
     } * signatures.len() as u64;
     let gas_info = GasInfo::with_cost(max(gas_cost, env.gas_config.ed25519_verify_cost));
     process_gas_info::<A, S, Q>(env, gas_info)?;
-    let result = ed25519_batch_verify(&messages, &signatures, &public_keys);
+    let result = ed25519_batch_verify(messages, signatures, public_keys);
     Ok(result.map_or_else(
         |err| match err {
             CryptoError::BatchErr { .. }
             | CryptoError::InvalidPubkeyFormat { .. }
             | CryptoError::InvalidSignatureFormat { .. }
             | CryptoError::GenericErr { .. } => err.code(),
-            CryptoError::InvalidHashFormat { .. } | CryptoError::InvalidRecoveryParam { .. } => {
+            CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
                 panic!("Error must not happen for this call")
             }
         },
@@ -348,103 +631,632 @@ pub fn do_ed25519_batch_verify<A: BackendApi, S: Storage, Q: Querier>(
     ))
 }
 
-/// Prints a debug message to console.
-/// This does not charge gas, so debug printing should be disabled when used in a blockchain module.
-pub fn do_debug<A: BackendApi, S: Storage, Q: Querier>(
+pub fn do_poseidon_hash<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    message_ptr: u32,
-) -> VmResult<()> {
-    if env.print_debug {
-        let message_data = read_region(&env.memory(), message_ptr, MAX_LENGTH_DEBUG)?;
-        let msg = String::from_utf8_lossy(&message_data);
-        println!("{}", msg);
+    inputs_ptr: u32,
+) -> VmResult<u64> {
+    let inputs = read_region(
+        &env.memory(),
+        inputs_ptr,
+        (POSEIDON_INPUT_LEN + 4) * POSEIDON_MAX_INPUTS,
+    )?;
+    let inputs = decode_sections(&inputs);
+
+    let width = inputs.len() as u64 + 1;
+    let gas_info = GasInfo::with_cost(env.gas_config.poseidon_hash_cost * width * width);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = poseidon_hash(&inputs);
+    match result {
+        Ok(hash) => {
+            let hash_ptr = write_to_contract::<A, S, Q>(env, &hash)?;
+            Ok(to_low_half(hash_ptr))
+        }
+        Err(err) => match err {
+            CryptoError::HashInputErr { .. } | CryptoError::GenericErr { .. } => {
+                Ok(to_high_half(err.code()))
+            }
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
     }
-    Ok(())
 }
 
-/// Aborts the contract and shows the given error message
-pub fn do_abort<A: BackendApi, S: Storage, Q: Querier>(
+pub fn do_zk_verify_groth16<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    message_ptr: u32,
-) -> VmResult<()> {
-    let message_data = read_region(&env.memory(), message_ptr, MAX_LENGTH_ABORT)?;
-    let msg = String::from_utf8_lossy(&message_data);
-    Err(VmError::aborted(msg))
+    vk_ptr: u32,
+    proof_ptr: u32,
+    public_inputs_ptr: u32,
+) -> VmResult<u32> {
+    let vk = read_region(&env.memory(), vk_ptr, GROTH16_VK_MAX_LEN)?;
+    let proof = read_region(&env.memory(), proof_ptr, GROTH16_PROOF_LEN)?;
+    let public_inputs = read_region(
+        &env.memory(),
+        public_inputs_ptr,
+        GROTH16_PUBLIC_INPUT_LEN * GROTH16_MAX_PUBLIC_INPUTS,
+    )?;
+
+    let input_count = (public_inputs.len() / GROTH16_PUBLIC_INPUT_LEN) as u64;
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.groth16_verify_cost
+            + env.gas_config.groth16_verify_per_input_cost * input_count,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = zk_verify_groth16(&vk, &proof, &public_inputs);
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::Groth16Err { .. } | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
 }
 
-/// Creates a Region in the contract, writes the given data to it and returns the memory location
-fn write_to_contract<A: BackendApi, S: Storage, Q: Querier>(
+/// Verifies an ICS-23 existence proof of `key` with `value` against a commitment root.
+/// Returns 0 on verification success, 1 on verification failure, and values
+/// greater than 1 in case of error.
+pub fn do_ics23_verify_membership<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    input: &[u8],
+    spec_id: u32,
+    root_ptr: u32,
+    proof_ptr: u32,
+    key_ptr: u32,
+    value_ptr: u32,
 ) -> VmResult<u32> {
-    let out_size = to_u32(input.len())?;
-    let result = env.call_function1("allocate", &[out_size.into()])?;
-    let target_ptr = ref_to_u32(&result)?;
-    if target_ptr == 0 {
-        return Err(CommunicationError::zero_address().into());
-    }
-    write_region(&env.memory(), target_ptr, input)?;
-    Ok(target_ptr)
+    let root = read_region(&env.memory(), root_ptr, ICS23_ROOT_MAX_LEN)?;
+    let proof = read_region(&env.memory(), proof_ptr, ICS23_PROOF_MAX_LEN)?;
+    let key = read_region(&env.memory(), key_ptr, ICS23_KEY_MAX_LEN)?;
+    let value = read_region(&env.memory(), value_ptr, ICS23_VALUE_MAX_LEN)?;
+
+    let len = (proof.len() + key.len() + value.len()) as u64;
+    process_ics23_gas(env, len)?;
+    let result = ics23_verify_membership(spec_id, &root, &proof, &key, &value);
+    Ok(ics23_result_code(result))
 }
 
-pub fn do_query_chain<A: BackendApi, S: Storage, Q: Querier>(
+/// Verifies an ICS-23 non-existence proof of `key` against a commitment root.
+/// Returns 0 on verification success, 1 on verification failure, and values
+/// greater than 1 in case of error.
+pub fn do_ics23_verify_non_membership<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    request_ptr: u32,
+    spec_id: u32,
+    root_ptr: u32,
+    proof_ptr: u32,
+    key_ptr: u32,
 ) -> VmResult<u32> {
-    let request = read_region(&env.memory(), request_ptr, MAX_LENGTH_QUERY_CHAIN_REQUEST)?;
-
-    let gas_remaining = env.get_gas_left();
-    let (result, gas_info) = env.with_querier_from_context::<_, _>(|querier| {
-        Ok(querier.query_raw(&request, gas_remaining))
-    })?;
-    process_gas_info::<A, S, Q>(env, gas_info)?;
-    let serialized = to_vec(&result?)?;
-    write_to_contract::<A, S, Q>(env, &serialized)
+    let root = read_region(&env.memory(), root_ptr, ICS23_ROOT_MAX_LEN)?;
+    let proof = read_region(&env.memory(), proof_ptr, ICS23_PROOF_MAX_LEN)?;
+    let key = read_region(&env.memory(), key_ptr, ICS23_KEY_MAX_LEN)?;
+
+    let len = (proof.len() + key.len()) as u64;
+    process_ics23_gas(env, len)?;
+    let result = ics23_verify_non_membership(spec_id, &root, &proof, &key);
+    Ok(ics23_result_code(result))
 }
 
-#[cfg(feature = "iterator")]
-pub fn do_db_scan<A: BackendApi, S: Storage, Q: Querier>(
+fn process_ics23_gas<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    start_ptr: u32,
-    end_ptr: u32,
-    order: i32,
-) -> VmResult<u32> {
-    let start = maybe_read_region(&env.memory(), start_ptr, MAX_LENGTH_DB_KEY)?;
-    let end = maybe_read_region(&env.memory(), end_ptr, MAX_LENGTH_DB_KEY)?;
-    let order: Order = order
-        .try_into()
-        .map_err(|_| CommunicationError::invalid_order(order))?;
+    len: u64,
+) -> VmResult<()> {
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.ics23_verify_cost + env.gas_config.ics23_verify_per_byte_cost * len,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)
+}
 
-    let (result, gas_info) = env.with_storage_from_context::<_, _>(|store| {
-        Ok(store.scan(start.as_deref(), end.as_deref(), order))
-    })?;
-    process_gas_info::<A, S, Q>(env, gas_info)?;
-    let iterator_id = result?;
-    Ok(iterator_id)
+fn ics23_result_code(result: CryptoResult<bool>) -> u32 {
+    result.map_or_else(
+        |err| match err {
+            CryptoError::Ics23Err { .. } | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    )
 }
 
-#[cfg(feature = "iterator")]
-pub fn do_db_next<A: BackendApi, S: Storage, Q: Querier>(
+/// Verifies an untrusted Tendermint header against a trusted one, like a light client.
+/// Takes a pointer to the sections encoded trusted header, trusted next validators,
+/// untrusted signed header and untrusted validators.
+/// Returns 0 on verification success, 1 on verification failure, and values
+/// greater than 1 in case of error.
+pub fn do_tendermint_verify_header<A: BackendApi, S: Storage, Q: Querier>(
     env: &Environment<A, S, Q>,
-    iterator_id: u32,
+    input_ptr: u32,
 ) -> VmResult<u32> {
-    let (result, gas_info) =
-        env.with_storage_from_context::<_, _>(|store| Ok(store.next(iterator_id)))?;
-    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let input = read_region(&env.memory(), input_ptr, MAX_LENGTH_TENDERMINT_INPUT)?;
 
-    // Empty key will later be treated as _no more element_.
-    let (key, value) = result?.unwrap_or_else(|| (Vec::<u8>::new(), Vec::<u8>::new()));
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.tendermint_verify_header_cost
+            + env.gas_config.tendermint_verify_header_per_byte_cost * input.len() as u64,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)?;
 
-    let out_data = encode_sections(&[key, value])?;
-    write_to_contract::<A, S, Q>(env, &out_data)
+    let sections = decode_sections(&input);
+    let result = match sections.as_slice() {
+        [trusted_header, trusted_next_validators, untrusted_header, untrusted_validators] => {
+            tendermint_verify_header(
+                trusted_header,
+                trusted_next_validators,
+                untrusted_header,
+                untrusted_validators,
+            )
+        }
+        _ => Err(CryptoError::tendermint_err(
+            "Input must consist of four sections",
+        )),
+    };
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::TendermintErr { .. } | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Bls12381Err { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
 }
 
-/// Returns the data shifted by 32 bits towards the most significant bit.
-///
-/// This is independent of endianness. But to get the idea, it would be
+/// Verifies an aggregate BLS signature over the BLS12-381 curve. Takes pointers to the
+/// concatenated public keys (G1), the sections encoded messages, the signature (G2) and
+/// the domain separation tag. See [`bls12_381_aggregate_verify`] for details.
+/// Returns 0 on verification success, 1 on verification failure, and values
+/// greater than 1 in case of error.
+pub fn do_bls12_381_aggregate_verify<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    pubkeys_ptr: u32,
+    messages_ptr: u32,
+    signature_ptr: u32,
+    dst_ptr: u32,
+) -> VmResult<u32> {
+    let pubkeys = read_region(
+        &env.memory(),
+        pubkeys_ptr,
+        BLS12_381_G1_POINT_LEN * BLS12_381_MAX_AGGREGATE_SIZE,
+    )?;
+    let messages = read_region(
+        &env.memory(),
+        messages_ptr,
+        (MAX_LENGTH_BLS12_381_MESSAGE + 4) * BLS12_381_MAX_AGGREGATE_SIZE,
+    )?;
+    let signature = read_region(&env.memory(), signature_ptr, BLS12_381_G2_POINT_LEN)?;
+    let dst = read_region(&env.memory(), dst_ptr, MAX_LENGTH_BLS12_381_DST)?;
+    let messages = decode_sections(&messages);
+
+    let pubkey_count = (pubkeys.len() / BLS12_381_G1_POINT_LEN) as u64;
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.bls12_381_aggregate_verify_cost
+            + env.gas_config.bls12_381_aggregate_verify_per_pubkey_cost * pubkey_count
+            + env.gas_config.bls12_381_aggregate_verify_per_message_cost * messages.len() as u64,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+
+    let result = if messages
+        .iter()
+        .any(|message| message.len() > MAX_LENGTH_BLS12_381_MESSAGE)
+    {
+        Err(CryptoError::bls12_381_err("Message too long"))
+    } else {
+        bls12_381_aggregate_verify(&pubkeys, &messages, &signature, &dst)
+    };
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::Bls12381Err { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
+}
+
+/// Checks the pairing equality `e(p_1, q_1) * ... * e(p_n, q_n) == e(r, s)` over the
+/// BLS12-381 curve. Takes pointers to the concatenated G1 points `ps`, the concatenated
+/// G2 points `qs` and the points `r` (G1) and `s` (G2).
+/// Returns 0 if the equality holds, 1 if it does not hold, and values
+/// greater than 1 in case of error.
+pub fn do_bls12_381_pairing_equality<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    ps_ptr: u32,
+    qs_ptr: u32,
+    r_ptr: u32,
+    s_ptr: u32,
+) -> VmResult<u32> {
+    let ps = read_region(
+        &env.memory(),
+        ps_ptr,
+        BLS12_381_G1_POINT_LEN * BLS12_381_MAX_AGGREGATE_SIZE,
+    )?;
+    let qs = read_region(
+        &env.memory(),
+        qs_ptr,
+        BLS12_381_G2_POINT_LEN * BLS12_381_MAX_AGGREGATE_SIZE,
+    )?;
+    let r = read_region(&env.memory(), r_ptr, BLS12_381_G1_POINT_LEN)?;
+    let s = read_region(&env.memory(), s_ptr, BLS12_381_G2_POINT_LEN)?;
+
+    let pair_count = (ps.len() / BLS12_381_G1_POINT_LEN) as u64;
+    let gas_info = GasInfo::with_cost(
+        env.gas_config.bls12_381_pairing_equality_cost
+            + env.gas_config.bls12_381_pairing_equality_per_pair_cost * pair_count,
+    );
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+
+    let result = bls12_381_pairing_equality(&ps, &qs, &r, &s);
+    Ok(result.map_or_else(
+        |err| match err {
+            CryptoError::Bls12381Err { .. } | CryptoError::GenericErr { .. } => err.code(),
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+        |valid| if valid { 0 } else { 1 },
+    ))
+}
+
+/// Hashes a message to a BLS12-381 G1 point with the given domain separation tag.
+/// Returns the error code in the high half and a pointer to the compressed point
+/// in the low half. The error code is 0 on success.
+pub fn do_bls12_381_hash_to_g1<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    message_ptr: u32,
+    dst_ptr: u32,
+) -> VmResult<u64> {
+    let message = read_region(&env.memory(), message_ptr, MAX_LENGTH_BLS12_381_MESSAGE)?;
+    let dst = read_region(&env.memory(), dst_ptr, MAX_LENGTH_BLS12_381_DST)?;
+
+    let gas_info = GasInfo::with_cost(env.gas_config.bls12_381_hash_to_g1_cost);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = bls12_381_hash_to_g1(&message, &dst);
+    bls12_381_write_point(env, result.as_ref().map(|point| point.as_slice()))
+}
+
+/// Hashes a message to a BLS12-381 G2 point with the given domain separation tag.
+/// Returns the error code in the high half and a pointer to the compressed point
+/// in the low half. The error code is 0 on success.
+pub fn do_bls12_381_hash_to_g2<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    message_ptr: u32,
+    dst_ptr: u32,
+) -> VmResult<u64> {
+    let message = read_region(&env.memory(), message_ptr, MAX_LENGTH_BLS12_381_MESSAGE)?;
+    let dst = read_region(&env.memory(), dst_ptr, MAX_LENGTH_BLS12_381_DST)?;
+
+    let gas_info = GasInfo::with_cost(env.gas_config.bls12_381_hash_to_g2_cost);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let result = bls12_381_hash_to_g2(&message, &dst);
+    bls12_381_write_point(env, result.as_ref().map(|point| point.as_slice()))
+}
+
+fn bls12_381_write_point<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    result: Result<&[u8], &CryptoError>,
+) -> VmResult<u64> {
+    match result {
+        Ok(point) => {
+            let point_ptr = write_to_contract::<A, S, Q>(env, point)?;
+            Ok(to_low_half(point_ptr))
+        }
+        Err(err) => match err {
+            CryptoError::Bls12381Err { .. } | CryptoError::GenericErr { .. } => {
+                Ok(to_high_half(err.code()))
+            }
+            CryptoError::BatchErr { .. }
+            | CryptoError::Groth16Err { .. }
+            | CryptoError::HashInputErr { .. }
+            | CryptoError::Ics23Err { .. }
+            | CryptoError::TendermintErr { .. }
+            | CryptoError::InvalidHashFormat { .. }
+            | CryptoError::InvalidPubkeyFormat { .. }
+            | CryptoError::InvalidSignatureFormat { .. }
+            | CryptoError::InvalidRecoveryParam { .. }
+            | CryptoError::InvalidHashFunction { .. } => {
+                panic!("Error must not happen for this call")
+            }
+        },
+    }
+}
+
+/// Compares two regions in constant time. Returns 0 if they are equal and 1 otherwise.
+pub fn do_ct_eq<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    a_ptr: u32,
+    b_ptr: u32,
+) -> VmResult<u32> {
+    let a = read_region(&env.memory(), a_ptr, MAX_LENGTH_CT_EQ)?;
+    let b = read_region(&env.memory(), b_ptr, MAX_LENGTH_CT_EQ)?;
+
+    let len = max(a.len(), b.len()) as u64;
+    let gas_info = GasInfo::with_cost(env.gas_config.ct_eq_per_byte_cost * len);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    Ok(if ct_eq(&a, &b) { 0 } else { 1 })
+}
+
+/// Prints a debug message to console.
+/// This does not charge gas, so debug printing should be disabled when used in a blockchain module.
+///
+/// This never errors, such that enabling debug printing cannot change the result of a call.
+/// See [`DebugLimits`](crate::DebugLimits) for how long and frequent messages are handled.
+pub fn do_debug<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    message_ptr: u32,
+) -> VmResult<()> {
+    if !env.print_debug() {
+        return Ok(());
+    }
+
+    // Debug output must not change the result of the call, since it is only enabled on some nodes.
+    // So instead of erroring, we drop or truncate messages exceeding the limits.
+    let limits = env.debug_limits;
+    let count = env.count_debug_message();
+    if count > limits.max_messages_per_call {
+        if count == limits.max_messages_per_call + 1 {
+            println!(
+                "Reached the limit of {} debug messages per call. Dropping further messages.",
+                limits.max_messages_per_call
+            );
+        }
+        return Ok(());
+    }
+
+    match read_region_truncated(&env.memory(), message_ptr, limits.max_message_length) {
+        Ok((message_data, full_length)) => {
+            let msg = String::from_utf8_lossy(&message_data);
+            if full_length > message_data.len() {
+                println!("{} [truncated from {} bytes]", msg, full_length);
+            } else {
+                println!("{}", msg);
+            }
+        }
+        Err(err) => println!("Error reading debug message: {}", err),
+    }
+    Ok(())
+}
+
+/// Aborts the contract and shows the given error message
+pub fn do_abort<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    message_ptr: u32,
+) -> VmResult<()> {
+    let message_data = read_region(&env.memory(), message_ptr, MAX_LENGTH_ABORT)?;
+    let msg = String::from_utf8_lossy(&message_data);
+    Err(VmError::aborted(msg))
+}
+
+/// Creates a Region in the contract, writes the given data to it and returns the memory location
+pub(crate) fn write_to_contract<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    input: &[u8],
+) -> VmResult<u32> {
+    let out_size = to_u32(input.len())?;
+    let result = env.call_function1("allocate", &[out_size.into()])?;
+    let target_ptr = ref_to_u32(&result)?;
+    if target_ptr == 0 {
+        return Err(CommunicationError::zero_address().into());
+    }
+    write_region(&env.memory(), target_ptr, input)?;
+    Ok(target_ptr)
+}
+
+pub fn do_query_chain<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    request_ptr: u32,
+) -> VmResult<u32> {
+    let request = read_region(&env.memory(), request_ptr, MAX_LENGTH_QUERY_CHAIN_REQUEST)?;
+
+    if !env.allow_reentrant_queries {
+        if let Some(contract_addr) = env.contract_address() {
+            if smart_query_target(&request).as_deref() == Some(contract_addr.as_str()) {
+                let result: SystemResult<ContractResult<Binary>> =
+                    SystemResult::Err(SystemError::Reentrancy { contract_addr });
+                return write_to_contract::<A, S, Q>(env, &to_vec(&result)?);
+            }
+        }
+    }
+
+    // The querier expects the remaining gas in CosmWasm gas
+    let gas_remaining = env.gas_multiplier.apply(env.get_gas_left());
+    let (result, gas_info) = env.with_querier_from_context::<_, _>(|querier| {
+        // Contracts executed for this query run one level deeper
+        let query_depth = env.query_depth.saturating_add(1);
+        Ok(querier.query_raw_at_depth(&request, gas_remaining, query_depth))
+    })?;
+    let multiplier = if env.gas_config.stargate_query_multiplier != env.gas_config.query_multiplier
+        && is_stargate_query(&request)
+    {
+        env.gas_config.stargate_query_multiplier
+    } else {
+        env.gas_config.query_multiplier
+    };
+    let gas_info = multiplier.apply_to_gas_info(gas_info);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let serialized = to_vec(&result?)?;
+    write_to_contract::<A, S, Q>(env, &serialized)
+}
+
+/// Returns true if the given query request is a `stargate` or `ibc` query.
+///
+/// Only the top level key of the JSON object is inspected, such that this works
+/// for all custom query types. Requests that cannot be parsed are not considered
+/// stargate queries.
+fn is_stargate_query(request: &[u8]) -> bool {
+    match serde_json::from_slice::<HashMap<String, IgnoredAny>>(request) {
+        Ok(map) => map.contains_key("stargate") || map.contains_key("ibc"),
+        Err(_) => false,
+    }
+}
+
+/// Returns the contract address of a `wasm.smart` query request or `None` for
+/// all other requests, including the ones that cannot be parsed.
+///
+/// This runs for every query without being metered. Thus other requests are rejected
+/// at their first key and the rest of a smart query is skipped without allocating.
+fn smart_query_target(request: &[u8]) -> Option<String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Request<'a> {
+        #[serde(borrow)]
+        Wasm(WasmRequest<'a>),
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum WasmRequest<'a> {
+        Smart {
+            #[serde(borrow)]
+            contract_addr: Cow<'a, str>,
+        },
+    }
+    match serde_json::from_slice(request).ok()? {
+        Request::Wasm(WasmRequest::Smart { contract_addr }) => Some(contract_addr.into_owned()),
+    }
+}
+
+#[cfg(feature = "iterator")]
+pub fn do_db_scan<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    start_ptr: u32,
+    end_ptr: u32,
+    order: i32,
+) -> VmResult<u32> {
+    let start = maybe_read_db_key(env, start_ptr)?;
+    let end = maybe_read_db_key(env, end_ptr)?;
+    let order: Order = order
+        .try_into()
+        .map_err(|_| CommunicationError::invalid_order(order))?;
+
+    let (result, gas_info) = env.with_storage_from_context::<_, _>(|store| {
+        Ok(store.scan(start.as_deref(), end.as_deref(), order))
+    })?;
+    let gas_info = env
+        .gas_config
+        .iterator_multiplier
+        .apply_to_gas_info(gas_info);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let iterator_id = result?;
+    Ok(iterator_id)
+}
+
+/// Like [`do_db_scan`] but the iterator returns at most `limit` elements.
+/// The limit is passed to the backend, which can push it down to the database.
+#[cfg(feature = "iterator")]
+pub fn do_db_scan_with_limit<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    start_ptr: u32,
+    end_ptr: u32,
+    order: i32,
+    limit: u32,
+) -> VmResult<u32> {
+    let start = maybe_read_db_key(env, start_ptr)?;
+    let end = maybe_read_db_key(env, end_ptr)?;
+    let order: Order = order
+        .try_into()
+        .map_err(|_| CommunicationError::invalid_order(order))?;
+
+    let (result, gas_info) = env.with_storage_from_context::<_, _>(|store| {
+        Ok(store.range_with_limit(start.as_deref(), end.as_deref(), order, limit))
+    })?;
+    let gas_info = env
+        .gas_config
+        .iterator_multiplier
+        .apply_to_gas_info(gas_info);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+    let iterator_id = result?;
+    // Enforce the limit independently of the backend implementation
+    env.set_iterator_limit(iterator_id, limit);
+    Ok(iterator_id)
+}
+
+#[cfg(feature = "iterator")]
+pub fn do_db_next<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    iterator_id: u32,
+) -> VmResult<u32> {
+    if !env.take_from_iterator_limit(iterator_id) {
+        // Limit reached, so we do not ask the backend but signal the end of the iterator
+        let out_data = encode_sections(&[Vec::<u8>::new(), Vec::<u8>::new()])?;
+        return write_to_contract::<A, S, Q>(env, &out_data);
+    }
+
+    let (result, gas_info) =
+        env.with_storage_from_context::<_, _>(|store| Ok(store.next(iterator_id)))?;
+    let gas_info = env
+        .gas_config
+        .iterator_multiplier
+        .apply_to_gas_info(gas_info);
+    process_gas_info::<A, S, Q>(env, gas_info)?;
+
+    // Empty key will later be treated as _no more element_.
+    let (key, value) = result?.unwrap_or_else(|| (Vec::<u8>::new(), Vec::<u8>::new()));
+    env.update_session(|session| session.storage_bytes_read += (key.len() + value.len()) as u64);
+
+    let out_data = encode_sections(&[key, value])?;
+    write_to_contract::<A, S, Q>(env, &out_data)
+}
+
+/// Returns the data shifted by 32 bits towards the most significant bit.
+///
+/// This is independent of endianness. But to get the idea, it would be
 /// `data || 0x00000000` in big endian representation.
 #[inline]
-fn to_high_half(data: u32) -> u64 {
+pub(crate) fn to_high_half(data: u32) -> u64 {
     // See https://stackoverflow.com/a/58956419/2013738 to understand
     // why this is endianness agnostic.
     (data as u64) << 32
@@ -455,23 +1267,31 @@ fn to_high_half(data: u32) -> u64 {
 /// This is independent of endianness. But to get the idea, it would be
 /// `0x00000000 || data` in big endian representation.
 #[inline]
-fn to_low_half(data: u32) -> u64 {
+pub(crate) fn to_low_half(data: u32) -> u64 {
     data.into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmwasm_crypto::{HASH_FUNCTION_SHA256, ICS23_SPEC_TENDERMINT};
     use cosmwasm_std::{
         coins, from_binary, AllBalanceResponse, BankQuery, Binary, Empty, QueryRequest,
         SystemError, SystemResult, WasmQuery,
     };
     use hex_literal::hex;
     use std::ptr::NonNull;
+    use std::sync::Mutex;
     use wasmer::{imports, Function, Instance as WasmerInstance};
 
-    use crate::backend::{BackendError, Storage};
+    use crate::backend::{BackendError, BackendResult, Storage};
+    use crate::environment::{
+        DebugLimits, GasConfig, GasMultiplier, StorageLimits, StorageReadCache,
+    };
+    use crate::errors::RegionValidationError;
     use crate::size::Size;
+    #[cfg(feature = "iterator")]
+    use crate::testing::{expected_keys, prefix_range};
     use crate::testing::{MockApi, MockQuerier, MockStorage};
     use crate::wasm_backend::compile;
 
@@ -495,6 +1315,12 @@ mod tests {
     const ECDSA_SIG_HEX: &str = "207082eb2c3dfa0b454e0906051270ba4074ac93760ba9e7110cd9471475111151eb0dbbc9920e72146fb564f99d039802bf6ef2561446eb126ef364d21ee9c4";
     const ECDSA_PUBKEY_HEX: &str = "04051c1ee2190ecfb174bfe4f90763f2b4ff7517b70a2aec1876ebcfd644c4633fb03f3cfbd94b1f376e34592d9d41ccaf640bb751b00a1fadeb0c01157769eb73";
 
+    const SECP256R1_HASH_HEX: &str =
+        "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069";
+    const SECP256R1_SIG_HEX: &str = "ba45c3fbeade9e6c15979e7963fcd871a1c0183d53735f83c8ddd111a82730d6d7ef6299307bd722c7a15da66c657e182f9e73a9f69c70c97108940c0db099e0";
+    const SECP256R1_PUBKEY_HEX: &str = "04fdaebc61f24907a8e5ded06e01565bc6a0cfab998b80dc24ec4ad5b233347d2017c4a267e17c41a7b3db37e75ae9ea86800d55ba3735c8f30d5b019b1ad9353d";
+    const SECP256R1_GAS_LIMIT: u64 = 10_000_000_000_000; // ~10ms
+
     const EDDSA_MSG_HEX: &str = "";
     const EDDSA_SIG_HEX: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
     const EDDSA_PUBKEY_HEX: &str =
@@ -524,9 +1350,14 @@ mod tests {
                 "addr_canonicalize" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "addr_humanize" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "secp256k1_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
+                "secp256k1_verify_prehashed" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32| -> u32 { 0 }),
                 "secp256k1_recover_pubkey" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u64 { 0 }),
                 "ed25519_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
                 "ed25519_batch_verify" => Function::new_native(store, |_a: u32, _b: u32, _c: u32| -> u32 { 0 }),
+                "ed25519_batch_verify_packed" => Function::new_native(store, |_a: u32| -> u32 { 0 }),
+                "ics23_verify_membership" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32, _e: u32| -> u32 { 0 }),
+                "ics23_verify_non_membership" => Function::new_native(store, |_a: u32, _b: u32, _c: u32, _d: u32| -> u32 { 0 }),
+                "ct_eq" => Function::new_native(store, |_a: u32, _b: u32| -> u32 { 0 }),
                 "debug" => Function::new_native(store, |_a: u32| {}),
             },
         };
@@ -570,6 +1401,47 @@ mod tests {
         ref_to_u32(&result[0]).expect("error converting result")
     }
 
+    /// Writes a Region struct with arbitrary values into Wasm memory and returns its address.
+    /// This allows simulating malformed Regions crafted by the contract.
+    fn write_raw_region(
+        env: &Environment<MockApi, MockStorage, MockQuerier>,
+        offset: u32,
+        capacity: u32,
+        length: u32,
+    ) -> u32 {
+        let holder_ptr = write_data(env, &[0u8; 12]);
+        // The first field of the holder Region points to the space for the raw Region
+        let memory = env.memory();
+        let view = memory.view::<u8>();
+        let start = holder_ptr as usize;
+        let bytes: Vec<u8> = view[start..start + 4]
+            .iter()
+            .map(|cell| cell.get())
+            .collect();
+        let region_ptr = u32::from_le_bytes(bytes.try_into().unwrap());
+        set_raw_region(env, region_ptr, offset, capacity, length);
+        region_ptr
+    }
+
+    /// Overrides the Region struct at `region_ptr` without any validation
+    fn set_raw_region(
+        env: &Environment<MockApi, MockStorage, MockQuerier>,
+        region_ptr: u32,
+        offset: u32,
+        capacity: u32,
+        length: u32,
+    ) {
+        let raw = [offset, capacity, length]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let memory = env.memory();
+        let view = memory.view::<u8>();
+        for (i, byte) in raw.into_iter().enumerate() {
+            view[region_ptr as usize + i].set(byte);
+        }
+    }
+
     /// A Region reader that is just good enough for the tests in this file
     fn force_read(
         env: &Environment<MockApi, MockStorage, MockQuerier>,
@@ -578,6 +1450,52 @@ mod tests {
         read_region(&env.memory(), region_ptr, 5000).unwrap()
     }
 
+    /// Replaces the default data with the given entries
+    #[cfg(feature = "iterator")]
+    fn leave_data(env: &Environment<MockApi, MockStorage, MockQuerier>, entries: &[&[u8]]) {
+        let mut storage = MockStorage::new();
+        for key in entries {
+            storage.set(key, b"value").0.expect("error setting");
+        }
+        env.move_in(storage, MockQuerier::new(&[]));
+    }
+
+    /// Creates an iterator via `do_db_scan` and reads all keys via `do_db_next`
+    #[cfg(feature = "iterator")]
+    fn scan_keys_via_imports(
+        env: &Environment<MockApi, MockStorage, MockQuerier>,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Vec<Vec<u8>> {
+        let start_ptr = start.map_or(0, |start| write_data(env, start));
+        let end_ptr = end.map_or(0, |end| write_data(env, end));
+        let id = do_db_scan(env, start_ptr, end_ptr, order.into()).unwrap();
+        let mut keys = vec![];
+        loop {
+            let kv_region_ptr = do_db_next(env, id).unwrap();
+            let kv = force_read(env, kv_region_ptr);
+            let key = decode_sections(&kv)[0].to_vec();
+            if key.is_empty() {
+                return keys;
+            }
+            keys.push(key);
+        }
+    }
+
+    #[cfg(feature = "iterator")]
+    const SORTED_KEYS: &[&[u8]] = &[
+        b"a",
+        b"a/1",
+        b"a/2",
+        b"a/3",
+        b"a0",
+        b"b",
+        b"b/1",
+        &[0xFF],
+        &[0xFF, 0xFF],
+    ];
+
     #[test]
     fn do_db_read_works() {
         let api = MockApi::default();
@@ -611,20 +1529,139 @@ mod tests {
         let key_ptr = write_data(&env, &vec![7u8; 300 * 1024]);
         let result = do_db_read(&env, key_ptr);
         match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source: CommunicationError::RegionLengthTooBig { length, .. },
-                ..
-            } => assert_eq!(length, 300 * 1024),
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 300 * 1024);
+                assert_eq!(max_length, 64 * 1024);
+            }
             e => panic!("Unexpected error: {:?}", e),
         }
     }
 
     #[test]
-    fn do_db_write_works() {
+    fn do_db_read_applies_storage_multiplier() {
+        let key = b"I do not exist in storage";
+
         let api = MockApi::default();
-        let (env, _instance) = make_instance(api);
+        let (env, _instance) = make_instance(api.clone());
+        leave_default_data(&env);
+        let key_ptr = write_data(&env, key);
+        let gas_before = env.get_gas_left();
+        do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(gas_before - env.get_gas_left(), key.len() as u64);
 
-        let key_ptr = write_data(&env, b"new storage key");
+        let (mut env, _instance) = make_instance(api);
+        env.gas_config.storage_multiplier = GasMultiplier::new(3, 1).unwrap();
+        leave_default_data(&env);
+        let key_ptr = write_data(&env, key);
+        let gas_before = env.get_gas_left();
+        do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(gas_before - env.get_gas_left(), 3 * key.len() as u64);
+        // only the original amount is tracked as externally used
+        let externally_used = env.with_gas_state(|gas_state| gas_state.externally_used_gas);
+        assert_eq!(externally_used, key.len() as u64);
+    }
+
+    #[test]
+    fn do_db_read_uses_read_cache() {
+        let api = MockApi::default();
+
+        // disabled by default
+        let (env, _instance) = make_instance(api.clone());
+        leave_default_data(&env);
+        let key_ptr = write_data(&env, KEY1);
+        do_db_read(&env, key_ptr).unwrap();
+        env.with_storage_from_context::<_, _>(|store| Ok(store.set(KEY1, b"changed").0))
+            .unwrap()
+            .unwrap();
+        let value_ptr = do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(force_read(&env, value_ptr), b"changed");
+
+        // enabled: second read does not reach the backend
+        let (mut env, _instance) = make_instance(api);
+        env.storage_read_cache = StorageReadCache::ChargeOriginalCost;
+        leave_default_data(&env);
+        let key_ptr = write_data(&env, KEY1);
+        do_db_read(&env, key_ptr).unwrap();
+        env.with_storage_from_context::<_, _>(|store| Ok(store.set(KEY1, b"changed").0))
+            .unwrap()
+            .unwrap();
+        let value_ptr = do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(force_read(&env, value_ptr), VALUE1);
+
+        // non-existent keys are cached too
+        let missing_ptr = write_data(&env, b"I do not exist in storage");
+        assert_eq!(do_db_read(&env, missing_ptr).unwrap(), 0);
+        env.with_storage_from_context::<_, _>(|store| {
+            Ok(store.set(b"I do not exist in storage", b"now I do").0)
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(do_db_read(&env, missing_ptr).unwrap(), 0);
+
+        // clearing the cache
+        env.clear_read_cache();
+        let value_ptr = do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(force_read(&env, value_ptr), b"changed");
+    }
+
+    #[test]
+    fn do_db_read_cache_hit_gas_works() {
+        let api = MockApi::default();
+
+        let (mut env, _instance) = make_instance(api.clone());
+        env.gas_config.storage_multiplier = GasMultiplier::new(2, 1).unwrap();
+        env.storage_read_cache = StorageReadCache::ChargeOriginalCost;
+        leave_default_data(&env);
+        let key_ptr = write_data(&env, KEY1);
+        let gas_before = env.get_gas_left();
+        do_db_read(&env, key_ptr).unwrap();
+        let miss_cost = gas_before - env.get_gas_left();
+        let gas_before = env.get_gas_left();
+        do_db_read(&env, key_ptr).unwrap();
+        let hit_cost = gas_before - env.get_gas_left();
+        assert_eq!(hit_cost, miss_cost);
+        // the backend was only involved once
+        let externally_used = env.with_gas_state(|gas_state| gas_state.externally_used_gas);
+        assert_eq!(externally_used, KEY1.len() as u64);
+
+        let (mut env, _instance) = make_instance(api);
+        env.storage_read_cache = StorageReadCache::ChargeFixedCost(7);
+        leave_default_data(&env);
+        // use a non-existent key to avoid allocations in Wasm
+        let key_ptr = write_data(&env, b"I do not exist in storage");
+        do_db_read(&env, key_ptr).unwrap();
+        let gas_before = env.get_gas_left();
+        do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(gas_before - env.get_gas_left(), 7);
+    }
+
+    #[test]
+    fn do_db_write_and_remove_invalidate_read_cache() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.storage_read_cache = StorageReadCache::ChargeOriginalCost;
+        leave_default_data(&env);
+
+        let key_ptr = write_data(&env, KEY1);
+        let new_value_ptr = write_data(&env, b"new value");
+
+        do_db_read(&env, key_ptr).unwrap();
+        do_db_write(&env, key_ptr, new_value_ptr).unwrap();
+        let value_ptr = do_db_read(&env, key_ptr).unwrap();
+        assert_eq!(force_read(&env, value_ptr), b"new value");
+
+        do_db_remove(&env, key_ptr).unwrap();
+        assert_eq!(do_db_read(&env, key_ptr).unwrap(), 0);
+    }
+
+    #[test]
+    fn do_db_write_works() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+
+        let key_ptr = write_data(&env, b"new storage key");
         let value_ptr = write_data(&env, b"new value");
 
         leave_default_data(&env);
@@ -697,15 +1734,11 @@ mod tests {
 
         let result = do_db_write(&env, key_ptr, value_ptr);
         match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source:
-                    CommunicationError::RegionLengthTooBig {
-                        length, max_length, ..
-                    },
-                ..
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
             } => {
                 assert_eq!(length, 300 * 1024);
-                assert_eq!(max_length, MAX_LENGTH_DB_KEY);
+                assert_eq!(max_length, 64 * 1024);
             }
             err => panic!("unexpected error: {:?}", err),
         };
@@ -723,20 +1756,157 @@ mod tests {
 
         let result = do_db_write(&env, key_ptr, value_ptr);
         match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source:
-                    CommunicationError::RegionLengthTooBig {
-                        length, max_length, ..
-                    },
-                ..
+            VmError::StorageValueTooLong {
+                length, max_length, ..
             } => {
                 assert_eq!(length, 300 * 1024);
-                assert_eq!(max_length, MAX_LENGTH_DB_VALUE);
+                assert_eq!(max_length, 128 * 1024);
             }
             err => panic!("unexpected error: {:?}", err),
         };
     }
 
+    #[test]
+    fn do_db_write_enforces_configured_storage_limits() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.storage_limits = StorageLimits {
+            max_key_length: 4,
+            max_value_length: 6,
+            max_writes_per_call: None,
+        };
+
+        let key_ptr = write_data(&env, b"key");
+        let long_key_ptr = write_data(&env, b"long key");
+        let value_ptr = write_data(&env, b"value");
+        let long_value_ptr = write_data(&env, b"long value");
+        leave_default_data(&env);
+
+        do_db_write(&env, key_ptr, value_ptr).unwrap();
+
+        let result = do_db_write(&env, long_key_ptr, value_ptr);
+        match result.unwrap_err() {
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 8);
+                assert_eq!(max_length, 4);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        let result = do_db_write(&env, key_ptr, long_value_ptr);
+        match result.unwrap_err() {
+            VmError::StorageValueTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 10);
+                assert_eq!(max_length, 6);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // limits apply to reading as well
+        let result = do_db_read(&env, long_key_ptr);
+        match result.unwrap_err() {
+            VmError::StorageKeyTooLong { length, .. } => assert_eq!(length, 8),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn do_db_write_and_remove_enforce_max_writes_per_call() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.storage_limits = StorageLimits {
+            max_writes_per_call: Some(2),
+            ..StorageLimits::default()
+        };
+
+        let key_ptr = write_data(&env, b"key");
+        let value_ptr = write_data(&env, b"value");
+        leave_default_data(&env);
+
+        do_db_write(&env, key_ptr, value_ptr).unwrap();
+        do_db_remove(&env, key_ptr).unwrap();
+
+        let result = do_db_write(&env, key_ptr, value_ptr);
+        match result.unwrap_err() {
+            VmError::StorageWriteLimitExceeded { limit, .. } => assert_eq!(limit, 2),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        let result = do_db_remove(&env, key_ptr);
+        match result.unwrap_err() {
+            VmError::StorageWriteLimitExceeded { limit, .. } => assert_eq!(limit, 2),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // the count starts over in the next call
+        env.reset_storage_writes();
+        do_db_write(&env, key_ptr, value_ptr).unwrap();
+    }
+
+    #[test]
+    fn do_db_write_fails_for_malformed_regions() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        let key_ptr = write_data(&env, b"new storage key");
+        leave_default_data(&env);
+
+        // length exceeds capacity
+        let value_ptr = write_raw_region(&env, 1000, 4, 5);
+        let result = do_db_write(&env, key_ptr, value_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source:
+                    CommunicationError::InvalidRegion {
+                        source: RegionValidationError::LengthExceedsCapacity { .. },
+                        ..
+                    },
+                ..
+            } => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        let value = env
+            .with_storage_from_context::<_, _>(|store| Ok(store.get(b"new storage key").0.unwrap()))
+            .unwrap();
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn do_db_write_ignores_capacity_of_regions_it_reads() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        let key_ptr = write_data(&env, b"new storage key");
+        let value_ptr = write_data(&env, b"new value");
+        leave_default_data(&env);
+
+        // The capacity exceeds the Wasm memory but only `length` bytes are read
+        let memory = env.memory();
+        let memory_size = memory.size().bytes().0;
+        let view = memory.view::<u8>();
+        let start = value_ptr as usize;
+        let bytes: Vec<u8> = view[start..start + 4]
+            .iter()
+            .map(|cell| cell.get())
+            .collect();
+        let value_offset = u32::from_le_bytes(bytes.try_into().unwrap());
+        set_raw_region(
+            &env,
+            value_ptr,
+            value_offset,
+            memory_size as u32 - value_offset + 100,
+            9,
+        );
+
+        do_db_write(&env, key_ptr, value_ptr).unwrap();
+        let value = env
+            .with_storage_from_context::<_, _>(|store| Ok(store.get(b"new storage key").0.unwrap()))
+            .unwrap();
+        assert_eq!(value, Some(b"new value".to_vec()));
+    }
+
     #[test]
     fn do_db_write_is_prohibited_in_readonly_contexts() {
         let api = MockApi::default();
@@ -819,15 +1989,11 @@ mod tests {
 
         let result = do_db_remove(&env, key_ptr);
         match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source:
-                    CommunicationError::RegionLengthTooBig {
-                        length, max_length, ..
-                    },
-                ..
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
             } => {
                 assert_eq!(length, 300 * 1024);
-                assert_eq!(max_length, MAX_LENGTH_DB_KEY);
+                assert_eq!(max_length, 64 * 1024);
             }
             err => panic!("unexpected error: {:?}", err),
         };
@@ -850,6 +2016,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn do_debug_never_fails() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.debug_limits = DebugLimits {
+            max_message_length: 5,
+            max_messages_per_call: 3,
+        };
+        env.set_print_debug(true);
+
+        // too long
+        let message_ptr = write_data(&env, b"very long debug message");
+        do_debug(&env, message_ptr).unwrap();
+        // invalid UTF-8
+        let message_ptr = write_data(&env, &[0xff, 0xfe, 0x61]);
+        do_debug(&env, message_ptr).unwrap();
+        // invalid region
+        let message_ptr = write_raw_region(&env, 1000, 4, 5);
+        do_debug(&env, message_ptr).unwrap();
+        assert_eq!(env.count_debug_message(), 4);
+
+        // exceeding the per-call limit
+        let message_ptr = write_data(&env, b"dropped");
+        do_debug(&env, message_ptr).unwrap();
+        do_debug(&env, message_ptr).unwrap();
+        assert_eq!(env.count_debug_message(), 7);
+
+        env.reset_debug_messages();
+        assert_eq!(env.count_debug_message(), 1);
+    }
+
+    #[test]
+    fn do_debug_does_not_count_messages_when_disabled() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+
+        let message_ptr = write_data(&env, b"hello");
+        do_debug(&env, message_ptr).unwrap();
+        assert_eq!(env.count_debug_message(), 1);
+    }
+
     #[test]
     fn do_addr_validate_works() {
         let api = MockApi::default();
@@ -957,6 +2164,46 @@ mod tests {
         assert_eq!(data.len(), api.canonical_length());
     }
 
+    #[test]
+    fn do_addr_canonicalize_fails_for_malformed_destination_regions() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        let source_ptr = write_data(&env, b"foo");
+        let dest_ptr = write_raw_region(&env, 1, 0, 0);
+        leave_default_data(&env);
+
+        // capacity exceeds Wasm memory
+        let memory_size = env.memory().size().bytes().0;
+        set_raw_region(&env, dest_ptr, memory_size as u32 - 4, 100, 0);
+        let result = do_addr_canonicalize(&env, source_ptr, dest_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source:
+                    CommunicationError::InvalidRegion {
+                        source: RegionValidationError::OutOfMemoryBounds { memory_size: m, .. },
+                        ..
+                    },
+                ..
+            } => assert_eq!(m, memory_size),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // data overlaps with its own Region struct
+        set_raw_region(&env, dest_ptr, dest_ptr, 100, 0);
+        let result = do_addr_canonicalize(&env, source_ptr, dest_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source:
+                    CommunicationError::InvalidRegion {
+                        source: RegionValidationError::OverlapsRegionStruct { .. },
+                        ..
+                    },
+                ..
+            } => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn do_addr_canonicalize_reports_invalid_input_back_to_contract() {
         let api = MockApi::default();
@@ -1034,7 +2281,7 @@ mod tests {
     #[test]
     fn do_addr_canonicalize_fails_for_small_destination_region() {
         let api = MockApi::default();
-        let (env, mut instance) = make_instance(api);
+        let (env, mut instance) = make_instance(api.clone());
 
         let source_ptr = write_data(&env, b"foo");
         let dest_ptr = create_empty(&mut instance, 7);
@@ -1176,6 +2423,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn do_secp256k1_verify_prehashed_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let hash = hex::decode(ECDSA_HASH_HEX).unwrap();
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(ECDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = hex::decode(ECDSA_PUBKEY_HEX).unwrap();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_secp256k1_verify_prehashed(
+                &env,
+                hash_ptr,
+                HASH_FUNCTION_SHA256,
+                sig_ptr,
+                pubkey_ptr
+            )
+            .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn do_secp256k1_verify_prehashed_fails_for_unknown_hash_function() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let hash = hex::decode(ECDSA_HASH_HEX).unwrap();
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(ECDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = hex::decode(ECDSA_PUBKEY_HEX).unwrap();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_secp256k1_verify_prehashed(&env, hash_ptr, 0, sig_ptr, pubkey_ptr).unwrap(),
+            11 // mapped InvalidHashFunction
+        );
+    }
+
     #[test]
     fn do_secp256k1_verify_wrong_hash_verify_fails() {
         let api = MockApi::default();
@@ -1445,75 +2735,194 @@ mod tests {
     }
 
     #[test]
-    fn do_ed25519_verify_works() {
+    fn do_secp256r1_verify_works() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let hash = hex::decode(SECP256R1_HASH_HEX).unwrap();
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(SECP256R1_SIG_HEX).unwrap();
         let sig_ptr = write_data(&env, &sig);
-        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey = hex::decode(SECP256R1_PUBKEY_HEX).unwrap();
         let pubkey_ptr = write_data(&env, &pubkey);
 
+        let gas_before = env.get_gas_left();
         assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            do_secp256r1_verify(&env, hash_ptr, sig_ptr, pubkey_ptr).unwrap(),
             0
         );
+        let gas_used = gas_before - env.get_gas_left();
+        assert_eq!(gas_used, GasConfig::default().secp256r1_verify_cost);
     }
 
     #[test]
-    fn do_ed25519_verify_wrong_msg_verify_fails() {
+    fn do_secp256r1_verify_wrong_hash_verify_fails() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let mut msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        // alter msg
-        msg.push(0x01);
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let mut hash = hex::decode(SECP256R1_HASH_HEX).unwrap();
+        // alter hash
+        hash[0] ^= 0x01;
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(SECP256R1_SIG_HEX).unwrap();
         let sig_ptr = write_data(&env, &sig);
-        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey = hex::decode(SECP256R1_PUBKEY_HEX).unwrap();
         let pubkey_ptr = write_data(&env, &pubkey);
 
         assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            do_secp256r1_verify(&env, hash_ptr, sig_ptr, pubkey_ptr).unwrap(),
             1
         );
     }
 
     #[test]
-    fn do_ed25519_verify_larger_msg_fails() {
+    fn do_secp256r1_verify_secp256k1_signature_fails() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let mut msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        // extend / break msg
-        msg.extend_from_slice(&[0x00; MAX_LENGTH_ED25519_MESSAGE + 1]);
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let hash = hex::decode(ECDSA_HASH_HEX).unwrap();
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(ECDSA_SIG_HEX).unwrap();
         let sig_ptr = write_data(&env, &sig);
-        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey = hex::decode(ECDSA_PUBKEY_HEX).unwrap();
         let pubkey_ptr = write_data(&env, &pubkey);
 
-        let result = do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr);
-        match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source: CommunicationError::RegionLengthTooBig { length, .. },
-                ..
-            } => assert_eq!(length, msg.len()),
-            e => panic!("Unexpected error: {:?}", e),
-        }
+        // The secp256k1 public key is not a point on the secp256r1 curve
+        assert_eq!(
+            do_secp256r1_verify(&env, hash_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            10 // generic error
+        );
     }
 
     #[test]
-    fn do_ed25519_verify_wrong_sig_verify_fails() {
+    fn do_secp256r1_verify_wrong_pubkey_format_fails() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let mut sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let hash = hex::decode(SECP256R1_HASH_HEX).unwrap();
+        let hash_ptr = write_data(&env, &hash);
+        let sig = hex::decode(SECP256R1_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let mut pubkey = hex::decode(SECP256R1_PUBKEY_HEX).unwrap();
+        // alter pubkey format
+        pubkey[0] ^= 0x01;
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_secp256r1_verify(&env, hash_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            5 // mapped InvalidPubkeyFormat
+        );
+    }
+
+    #[test]
+    fn do_secp256r1_recover_pubkey_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(SECP256R1_GAS_LIMIT);
+
+        let hash_ptr = write_data(&env, &hex::decode(SECP256R1_HASH_HEX).unwrap());
+        let sig_ptr = write_data(&env, &hex::decode(SECP256R1_SIG_HEX).unwrap());
+        let result = do_secp256r1_recover_pubkey(&env, hash_ptr, sig_ptr, 0).unwrap();
+        let error = result >> 32;
+        let pubkey_ptr: u32 = (result & 0xFFFFFFFF).try_into().unwrap();
+        assert_eq!(error, 0);
+        assert_eq!(
+            force_read(&env, pubkey_ptr),
+            hex::decode(SECP256R1_PUBKEY_HEX).unwrap()
+        );
+
+        // The other recovery param gives a different key
+        let result = do_secp256r1_recover_pubkey(&env, hash_ptr, sig_ptr, 1).unwrap();
+        let pubkey_ptr: u32 = (result & 0xFFFFFFFF).try_into().unwrap();
+        assert_eq!(result >> 32, 0);
+        assert_ne!(
+            force_read(&env, pubkey_ptr),
+            hex::decode(SECP256R1_PUBKEY_HEX).unwrap()
+        );
+
+        // Invalid recovery params are rejected
+        let result = do_secp256r1_recover_pubkey(&env, hash_ptr, sig_ptr, 2).unwrap();
+        assert_eq!(
+            result >> 32,
+            CryptoError::invalid_recovery_param().code() as u64
+        );
+        let result = do_secp256r1_recover_pubkey(&env, hash_ptr, sig_ptr, 256).unwrap();
+        assert_eq!(
+            result >> 32,
+            CryptoError::invalid_recovery_param().code() as u64
+        );
+    }
+
+    #[test]
+    fn do_ed25519_verify_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn do_ed25519_verify_wrong_msg_verify_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let mut msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        // alter msg
+        msg.push(0x01);
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn do_ed25519_verify_larger_msg_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let mut msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        // extend / break msg
+        msg.extend_from_slice(&[0x00; MAX_LENGTH_ED25519_MESSAGE + 1]);
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        let result = do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source: CommunicationError::RegionLengthTooBig { length, .. },
+                ..
+            } => assert_eq!(length, msg.len()),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn do_ed25519_verify_wrong_sig_verify_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let mut sig = hex::decode(EDDSA_SIG_HEX).unwrap();
         // alter sig
         sig[0] ^= 0x01;
         let sig_ptr = write_data(&env, &sig);
@@ -1571,103 +2980,704 @@ mod tests {
     }
 
     #[test]
-    fn do_ed25519_verify_wrong_pubkey_verify_fails() {
+    fn do_ed25519_verify_wrong_pubkey_verify_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        // alter pubkey
+        pubkey[1] ^= 0x01;
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn do_ed25519_verify_larger_pubkey_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        // extend / break pubkey
+        pubkey.push(0x00);
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        let result = do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source: CommunicationError::RegionLengthTooBig { length, .. },
+                ..
+            } => assert_eq!(length, EDDSA_PUBKEY_LEN + 1),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn do_ed25519_verify_shorter_pubkey_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        // reduce / break pubkey
+        pubkey.pop();
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            5 // mapped InvalidPubkeyFormat
+        )
+    }
+
+    #[test]
+    fn do_ed25519_verify_empty_pubkey_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let msg_ptr = write_data(&env, &msg);
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = vec![];
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            5 // mapped InvalidPubkeyFormat
+        )
+    }
+
+    #[test]
+    fn do_ed25519_verify_wrong_data_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = vec![0x22; MESSAGE_HASH_MAX_LEN];
+        let msg_ptr = write_data(&env, &msg);
+        let sig = vec![0x22; MAX_LENGTH_ED25519_SIGNATURE];
+        let sig_ptr = write_data(&env, &sig);
+        let pubkey = vec![0x04; EDDSA_PUBKEY_LEN];
+        let pubkey_ptr = write_data(&env, &pubkey);
+
+        assert_eq!(
+            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
+            1 // verification failure
+        )
+    }
+
+    #[test]
+    fn do_ed25519_batch_verify_packed_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        // two empty messages, two signatures and one public key
+        let input = [
+            &[0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1][..],
+            &[
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0, 128, 0, 0, 0, 160,
+            ],
+            &msg,
+            &sig,
+            &sig,
+            &pubkey,
+        ]
+        .concat();
+        let input_ptr = write_data(&env, &input);
+
+        assert_eq!(do_ed25519_batch_verify_packed(&env, input_ptr).unwrap(), 0);
+
+        // wrong message
+        let input = [
+            &[0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1][..],
+            &[
+                0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 65, 0, 0, 0, 129, 0, 0, 0, 161,
+            ],
+            &[0x01],
+            &sig,
+            &sig,
+            &pubkey,
+        ]
+        .concat();
+        let input_ptr = write_data(&env, &input);
+        assert_eq!(do_ed25519_batch_verify_packed(&env, input_ptr).unwrap(), 1);
+    }
+
+    #[test]
+    fn do_ed25519_batch_verify_packed_fails_for_invalid_input() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let input_ptr = write_data(&env, &[0x00, 0x00, 0x00, 0x01]);
+        let gas_before = env.get_gas_left();
+        assert_eq!(
+            do_ed25519_batch_verify_packed(&env, input_ptr).unwrap(),
+            7 // mapped BatchErr
+        );
+        // the input is charged even though it cannot be decoded
+        assert_eq!(
+            gas_before - env.get_gas_left(),
+            4 * env.gas_config.ed25519_batch_verify_packed_per_byte_cost
+        );
+
+        // counts mismatch
+        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
+        let input = [
+            &[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1][..],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0, 96],
+            &sig,
+            &pubkey,
+        ]
+        .concat();
+        let input_ptr = write_data(&env, &input);
+        assert_eq!(
+            do_ed25519_batch_verify_packed(&env, input_ptr).unwrap(),
+            7 // mapped BatchErr
+        );
+    }
+
+    #[test]
+    fn do_poseidon_hash_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let one = hex!("0000000000000000000000000000000000000000000000000000000000000001");
+        let two = hex!("0000000000000000000000000000000000000000000000000000000000000002");
+        let expected = hex!("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a");
+
+        let inputs_ptr = write_data(
+            &env,
+            &encode_sections(&[one.to_vec(), two.to_vec()]).unwrap(),
+        );
+        let result = do_poseidon_hash(&env, inputs_ptr).unwrap();
+        let error = result >> 32;
+        let hash_ptr: u32 = (result & 0xFFFFFFFF).try_into().unwrap();
+        assert_eq!(error, 0);
+        assert_eq!(force_read(&env, hash_ptr), expected);
+    }
+
+    #[test]
+    fn do_poseidon_hash_fails_for_invalid_input() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        // field modulus
+        let input = hex!("30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001");
+        let inputs_ptr = write_data(&env, &encode_sections(&[input.to_vec()]).unwrap());
+        let result = do_poseidon_hash(&env, inputs_ptr).unwrap();
+        assert_eq!(result >> 32, 8); // mapped HashInputErr
+    }
+
+    // Proof of knowledge of two factors of 33 (a * b = c with public c),
+    // generated with ark-groth16
+    const GROTH16_VK_HEX: &str = "04076665b8ea10d1f1088c2a5bc3fde6fa595c862b70ca7fb1a0d40e335a7e6d1add777c0a2833fc260f2d2efa5b83a1c573bae542a2fc70814d1f1247d342332a2a6503854c930b239693b403489ed1304008fa7d4367e370f25f633a9630ed1762ea18ff102a72b33fa1b145f5779fe9ceb1df0b4b4901f58b7d0abbf05438147b50868be5efb739888f132c1feb0c8f033781cfe15057f91213bde0f32df72a03b2a2637568b73ee71b3631ca82e5022933f13ee221338968bacf5d7fe0ea29aa2b10fbd31334cdb0ca4dfbcef670447aaef714f5d691dc1c49cd44129568221b1fc591601528e30eb7f2da312117378422d513021ef893a36152e62efb3e12cedf3d4637d61208117b8c0af66a5ddd29699536660253266f9e63ef26b2b91b86474753f31ae70e979c81f48b51ad43e43e42cfcf2a85c9cc85e79fcc83101f12aa8b550f4b2bcab999a2613480bf015410a18c93090aadfc520db8685eb8071671eb3a1c2c455de52368634685add354398fef807ff78586467100ecd0200add326cc141d0d9c752d6a8590d3f9123b67f32b8d7da5db189002e38de266c09c85676193171bb585953b1a7a2c94573aee4988c734e461c791b72facb8a3e126864fa09c9a802ecfde1b0db54f475e2341becf2ce4bb226533cc8ef2c03242246500ab9fd7578eaac0e9167dae9948f9b8d64f6ed59e4ff988a38006e02c60673104bd60d32774677c91aa5be53aa79a8ea18c8ef833b68032d6cf778a28b28ee3cba3c48edcffd6c354d0c96f7d3892bd68493835897ad12d9b49df4b81b";
+    const GROTH16_PROOF_HEX: &str = "230828863050148d0714fd793d1026bc9497f3e123a16334b127ff487d8a1ddc2d6926a6d63f0b78c6af0ae1898515a76f9db3c05703da06bc13e6643eab7dc72d1ed3b81a1287dd0d10691e7e461986115b919cc5b8f257ef5e98ce5f9c6ff72b27f2c8b9c12af71745a6587def58d614aeb6e9f7d98a5ddf466df2a3dc757610f2f06c5917c736a912ef2937a77cc43706eb633a7ef1a04121cdaf5651726023b5d84bebf24d9a2e174f58667b08f6db60c32a899803601d0bdb40a61a5512034838d658e5832b4ad43d67f092977eb68bac494d8c860d7b1211213e2b8a8416d26d2fc675c7854de03dbb4a1932be9a9febca09fcd3e90293ebcbb6b35788";
+    const GROTH16_INPUT_HEX: &str =
+        "0000000000000000000000000000000000000000000000000000000000000021";
+    /// A verification costs more than TESTING_GAS_LIMIT
+    const GROTH16_GAS_LIMIT: u64 = 10_000_000_000_000; // ~10ms
+
+    #[test]
+    fn do_zk_verify_groth16_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(GROTH16_GAS_LIMIT);
+
+        let vk_ptr = write_data(&env, &hex::decode(GROTH16_VK_HEX).unwrap());
+        let proof_ptr = write_data(&env, &hex::decode(GROTH16_PROOF_HEX).unwrap());
+        let input_ptr = write_data(&env, &hex::decode(GROTH16_INPUT_HEX).unwrap());
+
+        let gas_before = env.get_gas_left();
+        assert_eq!(
+            do_zk_verify_groth16(&env, vk_ptr, proof_ptr, input_ptr).unwrap(),
+            0
+        );
+        let gas_used = gas_before - env.get_gas_left();
+        let gas_config = GasConfig::default();
+        assert_eq!(
+            gas_used,
+            gas_config.groth16_verify_cost + gas_config.groth16_verify_per_input_cost
+        );
+    }
+
+    #[test]
+    fn do_zk_verify_groth16_wrong_input_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(GROTH16_GAS_LIMIT);
+
+        let vk_ptr = write_data(&env, &hex::decode(GROTH16_VK_HEX).unwrap());
+        let proof_ptr = write_data(&env, &hex::decode(GROTH16_PROOF_HEX).unwrap());
+        let mut input = hex::decode(GROTH16_INPUT_HEX).unwrap();
+        input[31] += 1;
+        let input_ptr = write_data(&env, &input);
+
+        assert_eq!(
+            do_zk_verify_groth16(&env, vk_ptr, proof_ptr, input_ptr).unwrap(),
+            1 // verification failure
+        );
+    }
+
+    #[test]
+    fn do_zk_verify_groth16_invalid_proof_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(GROTH16_GAS_LIMIT);
+
+        let vk_ptr = write_data(&env, &hex::decode(GROTH16_VK_HEX).unwrap());
+        let proof_ptr = write_data(&env, &[0x01; GROTH16_PROOF_LEN]);
+        let input_ptr = write_data(&env, &hex::decode(GROTH16_INPUT_HEX).unwrap());
+
+        assert_eq!(
+            do_zk_verify_groth16(&env, vk_ptr, proof_ptr, input_ptr).unwrap(),
+            9 // mapped Groth16Err
+        );
+    }
+
+    // A tree with the single leaf foo -> bar using the Tendermint proof spec
+    const ICS23_ROOT_HEX: &str = "2d6e9a3e3928b84ea41ebc047c06d8b416d5855983a04921bd48adde9c4aa714";
+    const ICS23_EXIST_PROOF_HEX: &str = "0a150a03666f6f12036261721a090801180120012a0100";
+    const ICS23_NONEXIST_PROOF_HEX: &str =
+        "121c0a03676f6f12150a03666f6f12036261721a090801180120012a0100";
+
+    #[test]
+    fn do_ics23_verify_membership_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let root_ptr = write_data(&env, &hex::decode(ICS23_ROOT_HEX).unwrap());
+        let proof = hex::decode(ICS23_EXIST_PROOF_HEX).unwrap();
+        let proof_ptr = write_data(&env, &proof);
+        let key_ptr = write_data(&env, b"foo");
+        let value_ptr = write_data(&env, b"bar");
+
+        let gas_before = env.get_gas_left();
+        assert_eq!(
+            do_ics23_verify_membership(
+                &env,
+                ICS23_SPEC_TENDERMINT,
+                root_ptr,
+                proof_ptr,
+                key_ptr,
+                value_ptr
+            )
+            .unwrap(),
+            0
+        );
+        let gas_used = gas_before - env.get_gas_left();
+        let gas_config = GasConfig::default();
+        assert_eq!(
+            gas_used,
+            gas_config.ics23_verify_cost
+                + gas_config.ics23_verify_per_byte_cost * (proof.len() as u64 + 6)
+        );
+    }
+
+    #[test]
+    fn do_ics23_verify_membership_wrong_value_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let root_ptr = write_data(&env, &hex::decode(ICS23_ROOT_HEX).unwrap());
+        let proof_ptr = write_data(&env, &hex::decode(ICS23_EXIST_PROOF_HEX).unwrap());
+        let key_ptr = write_data(&env, b"foo");
+        let value_ptr = write_data(&env, b"baz");
+
+        assert_eq!(
+            do_ics23_verify_membership(
+                &env,
+                ICS23_SPEC_TENDERMINT,
+                root_ptr,
+                proof_ptr,
+                key_ptr,
+                value_ptr
+            )
+            .unwrap(),
+            1 // verification failure
+        );
+    }
+
+    #[test]
+    fn do_ics23_verify_membership_invalid_proof_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let root_ptr = write_data(&env, &hex::decode(ICS23_ROOT_HEX).unwrap());
+        let proof_ptr = write_data(&env, &[0x0a, 0xff]);
+        let key_ptr = write_data(&env, b"foo");
+        let value_ptr = write_data(&env, b"bar");
+
+        assert_eq!(
+            do_ics23_verify_membership(
+                &env,
+                ICS23_SPEC_TENDERMINT,
+                root_ptr,
+                proof_ptr,
+                key_ptr,
+                value_ptr
+            )
+            .unwrap(),
+            12 // mapped Ics23Err
+        );
+    }
+
+    #[test]
+    fn do_ics23_verify_non_membership_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+
+        let root_ptr = write_data(&env, &hex::decode(ICS23_ROOT_HEX).unwrap());
+        let proof_ptr = write_data(&env, &hex::decode(ICS23_NONEXIST_PROOF_HEX).unwrap());
+        let key_ptr = write_data(&env, b"goo");
+        assert_eq!(
+            do_ics23_verify_non_membership(
+                &env,
+                ICS23_SPEC_TENDERMINT,
+                root_ptr,
+                proof_ptr,
+                key_ptr
+            )
+            .unwrap(),
+            0
+        );
+
+        // the proof only covers keys after foo
+        let root_ptr = write_data(&env, &hex::decode(ICS23_ROOT_HEX).unwrap());
+        let proof_ptr = write_data(&env, &hex::decode(ICS23_NONEXIST_PROOF_HEX).unwrap());
+        let key_ptr = write_data(&env, b"eoo");
+        assert_eq!(
+            do_ics23_verify_non_membership(
+                &env,
+                ICS23_SPEC_TENDERMINT,
+                root_ptr,
+                proof_ptr,
+                key_ptr
+            )
+            .unwrap(),
+            1
+        );
+    }
+
+    // A header at height 11 of a chain with a single validator, which follows the trusted
+    // header at height 10
+    const TENDERMINT_TRUSTED_HEADER_HEX: &str = "0a02080b120974657374696e672d31180a2206088ae2cfaa064220a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4974a20a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4975a156170702068617368206f662074686520626c6f636b";
+    const TENDERMINT_VALIDATORS_HEX: &str = "0a3c0a1434750f98bd59fcfc946da45aaabe933be154a4b512220a208a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c180a";
+    const TENDERMINT_UNTRUSTED_HEADER_HEX: &str = "0a740a02080b120974657374696e672d31180b2206088be2cfaa064220a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4974a20a8da5bcd937a7538b5a3e9a67ba734655705ab2a805e3b97255e90644747b4975a156170702068617368206f662074686520626c6f636b12b201080b10011a480a200011289ad8eb5f811e66cf0d9bb5c04897d970310ec979c8a794c012e69ec1ed122408011220d887db09649dab0d83951d8d5d69b2e7d8bb70e79daa2a3a279b4fd6b8346cea22620802121434750f98bd59fcfc946da45aaabe933be154a4b51a06088be2cfaa06224075c59ecb3457e109c1a17bf80b48662e82af79c65b40cfcb4f646862b0585b9436c84e3fd57b6c7b10668ac70e557959edf819ef6dc9fbedd2aedb4c96eafe05";
+
+    /// A verification costs more than TESTING_GAS_LIMIT
+    const TENDERMINT_GAS_LIMIT: u64 = 10_000_000_000_000; // ~10ms
+
+    fn tendermint_input(untrusted_header: &[u8]) -> Vec<u8> {
+        let validators = hex::decode(TENDERMINT_VALIDATORS_HEX).unwrap();
+        encode_sections(&[
+            hex::decode(TENDERMINT_TRUSTED_HEADER_HEX).unwrap(),
+            validators.clone(),
+            untrusted_header.to_vec(),
+            validators,
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn do_tendermint_verify_header_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(TENDERMINT_GAS_LIMIT);
+
+        let input = tendermint_input(&hex::decode(TENDERMINT_UNTRUSTED_HEADER_HEX).unwrap());
+        let input_ptr = write_data(&env, &input);
+
+        let gas_before = env.get_gas_left();
+        assert_eq!(do_tendermint_verify_header(&env, input_ptr).unwrap(), 0);
+        let gas_used = gas_before - env.get_gas_left();
+        let gas_config = GasConfig::default();
+        assert_eq!(
+            gas_used,
+            gas_config.tendermint_verify_header_cost
+                + gas_config.tendermint_verify_header_per_byte_cost * input.len() as u64
+        );
+    }
+
+    #[test]
+    fn do_tendermint_verify_header_modified_header_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(TENDERMINT_GAS_LIMIT);
+
+        let mut untrusted_header = hex::decode(TENDERMINT_UNTRUSTED_HEADER_HEX).unwrap();
+        // modify the app hash
+        untrusted_header[100] ^= 1;
+        let input_ptr = write_data(&env, &tendermint_input(&untrusted_header));
+        assert_eq!(
+            do_tendermint_verify_header(&env, input_ptr).unwrap(),
+            1 // verification failure
+        );
+    }
+
+    #[test]
+    fn do_tendermint_verify_header_invalid_input_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(TENDERMINT_GAS_LIMIT);
+
+        let input_ptr = write_data(&env, &tendermint_input(&[0x0a, 0xff]));
+        assert_eq!(
+            do_tendermint_verify_header(&env, input_ptr).unwrap(),
+            13 // mapped TendermintErr
+        );
+
+        let input = encode_sections(&[b"foo".to_vec()]).unwrap();
+        let input_ptr = write_data(&env, &input);
+        assert_eq!(do_tendermint_verify_header(&env, input_ptr).unwrap(), 13);
+    }
+
+    // Two keys signing the message "hello bls"
+    const BLS12_381_PUBKEY1_HEX: &str = "86d4e01c75c1331328bda6bee6724fd4b25f25725e7b55d27ae17f257cfba31c75715ff595399b194cb0fa68b890a8d2";
+    const BLS12_381_PUBKEY2_HEX: &str = "aabf0ec575019e6c8280db3c675e7981bb55275716154a9c74a84739ccc6b539397f88627915d24da0536a52547efcac";
+    const BLS12_381_AGGREGATE_SIG_HEX: &str = "986228fe79837c7d35ddf628345cae60689eb300d3bd7038cbbf7fc304ae4d92ed23b57408816f9bdda5a6e224a7b27214086d80275564fc8ae531a6f664f112b8663f2d3e1c798253ba1fc26be71d35646a1bb0fa1ec73c32380df8b1676c4e";
+    const BLS12_381_MSG: &[u8] = b"hello bls";
+    const BLS12_381_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+    const BLS12_381_G1_GENERATOR_HEX: &str = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+    const BLS12_381_G2_GENERATOR_HEX: &str = "93e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8";
+    /// Enough for several verifications in one test, which cost more than TESTING_GAS_LIMIT each
+    const BLS12_381_GAS_LIMIT: u64 = 100_000_000_000_000; // ~100ms
+
+    fn bls12_381_pubkeys() -> Vec<u8> {
+        [
+            hex::decode(BLS12_381_PUBKEY1_HEX).unwrap(),
+            hex::decode(BLS12_381_PUBKEY2_HEX).unwrap(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn do_bls12_381_aggregate_verify_works() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(BLS12_381_GAS_LIMIT);
+
+        let pubkeys_ptr = write_data(&env, &bls12_381_pubkeys());
+        let messages_ptr = write_data(&env, &encode_sections(&[BLS12_381_MSG.to_vec()]).unwrap());
+        let sig_ptr = write_data(&env, &hex::decode(BLS12_381_AGGREGATE_SIG_HEX).unwrap());
+        let dst_ptr = write_data(&env, BLS12_381_DST);
+
+        let gas_before = env.get_gas_left();
+        assert_eq!(
+            do_bls12_381_aggregate_verify(&env, pubkeys_ptr, messages_ptr, sig_ptr, dst_ptr)
+                .unwrap(),
+            0
+        );
+        let gas_used = gas_before - env.get_gas_left();
+        let gas_config = GasConfig::default();
+        assert_eq!(
+            gas_used,
+            gas_config.bls12_381_aggregate_verify_cost
+                + gas_config.bls12_381_aggregate_verify_per_pubkey_cost * 2
+                + gas_config.bls12_381_aggregate_verify_per_message_cost
+        );
+    }
+
+    #[test]
+    fn do_bls12_381_aggregate_verify_wrong_message_fails() {
+        let api = MockApi::default();
+        let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(BLS12_381_GAS_LIMIT);
+
+        let pubkeys_ptr = write_data(&env, &bls12_381_pubkeys());
+        let messages_ptr = write_data(&env, &encode_sections(&[b"hello BLS".to_vec()]).unwrap());
+        let sig_ptr = write_data(&env, &hex::decode(BLS12_381_AGGREGATE_SIG_HEX).unwrap());
+        let dst_ptr = write_data(&env, BLS12_381_DST);
+
+        assert_eq!(
+            do_bls12_381_aggregate_verify(&env, pubkeys_ptr, messages_ptr, sig_ptr, dst_ptr)
+                .unwrap(),
+            1 // verification failure
+        );
+    }
+
+    #[test]
+    fn do_bls12_381_aggregate_verify_invalid_input_fails() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(BLS12_381_GAS_LIMIT);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
+        let messages = encode_sections(&[BLS12_381_MSG.to_vec()]).unwrap();
+        let sig = hex::decode(BLS12_381_AGGREGATE_SIG_HEX).unwrap();
+
+        // truncated pubkeys
+        let pubkeys_ptr = write_data(&env, &bls12_381_pubkeys()[1..]);
+        let messages_ptr = write_data(&env, &messages);
         let sig_ptr = write_data(&env, &sig);
-        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
-        // alter pubkey
-        pubkey[1] ^= 0x01;
-        let pubkey_ptr = write_data(&env, &pubkey);
+        let dst_ptr = write_data(&env, BLS12_381_DST);
+        assert_eq!(
+            do_bls12_381_aggregate_verify(&env, pubkeys_ptr, messages_ptr, sig_ptr, dst_ptr)
+                .unwrap(),
+            5 // mapped InvalidPubkeyFormat
+        );
 
+        // truncated signature
+        let pubkeys_ptr = write_data(&env, &bls12_381_pubkeys());
+        let messages_ptr = write_data(&env, &messages);
+        let sig_ptr = write_data(&env, &sig[1..]);
+        let dst_ptr = write_data(&env, BLS12_381_DST);
         assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
-            1
+            do_bls12_381_aggregate_verify(&env, pubkeys_ptr, messages_ptr, sig_ptr, dst_ptr)
+                .unwrap(),
+            4 // mapped InvalidSignatureFormat
+        );
+
+        // one message too many
+        let pubkeys_ptr = write_data(&env, &bls12_381_pubkeys());
+        let messages_ptr = write_data(
+            &env,
+            &encode_sections(&[BLS12_381_MSG.to_vec(), b"a".to_vec(), b"b".to_vec()]).unwrap(),
+        );
+        let sig_ptr = write_data(&env, &sig);
+        let dst_ptr = write_data(&env, BLS12_381_DST);
+        assert_eq!(
+            do_bls12_381_aggregate_verify(&env, pubkeys_ptr, messages_ptr, sig_ptr, dst_ptr)
+                .unwrap(),
+            14 // mapped Bls12381Err
         );
     }
 
     #[test]
-    fn do_ed25519_verify_larger_pubkey_fails() {
+    fn do_bls12_381_pairing_equality_works() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(BLS12_381_GAS_LIMIT);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
-        let sig_ptr = write_data(&env, &sig);
-        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
-        // extend / break pubkey
-        pubkey.push(0x00);
-        let pubkey_ptr = write_data(&env, &pubkey);
+        let g1 = hex::decode(BLS12_381_G1_GENERATOR_HEX).unwrap();
+        let g2 = hex::decode(BLS12_381_G2_GENERATOR_HEX).unwrap();
+        let ps_ptr = write_data(&env, &g1);
+        let qs_ptr = write_data(&env, &g2);
+        let r_ptr = write_data(&env, &g1);
+        let s_ptr = write_data(&env, &g2);
 
-        let result = do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr);
-        match result.unwrap_err() {
-            VmError::CommunicationErr {
-                source: CommunicationError::RegionLengthTooBig { length, .. },
-                ..
-            } => assert_eq!(length, EDDSA_PUBKEY_LEN + 1),
-            e => panic!("Unexpected error: {:?}", e),
-        }
+        let gas_before = env.get_gas_left();
+        assert_eq!(
+            do_bls12_381_pairing_equality(&env, ps_ptr, qs_ptr, r_ptr, s_ptr).unwrap(),
+            0
+        );
+        let gas_used = gas_before - env.get_gas_left();
+        let gas_config = GasConfig::default();
+        assert_eq!(
+            gas_used,
+            gas_config.bls12_381_pairing_equality_cost
+                + gas_config.bls12_381_pairing_equality_per_pair_cost
+        );
+
+        let ps_ptr = write_data(&env, &hex::decode(BLS12_381_PUBKEY1_HEX).unwrap());
+        let qs_ptr = write_data(&env, &g2);
+        let r_ptr = write_data(&env, &g1);
+        let s_ptr = write_data(&env, &g2);
+        assert_eq!(
+            do_bls12_381_pairing_equality(&env, ps_ptr, qs_ptr, r_ptr, s_ptr).unwrap(),
+            1 // not equal
+        );
+
+        let ps_ptr = write_data(&env, &g1);
+        let qs_ptr = write_data(&env, &g2[1..]);
+        let r_ptr = write_data(&env, &g1);
+        let s_ptr = write_data(&env, &g2);
+        assert_eq!(
+            do_bls12_381_pairing_equality(&env, ps_ptr, qs_ptr, r_ptr, s_ptr).unwrap(),
+            14 // mapped Bls12381Err
+        );
     }
 
     #[test]
-    fn do_ed25519_verify_shorter_pubkey_fails() {
+    fn do_bls12_381_hash_to_curve_works() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
+        env.set_gas_left(BLS12_381_GAS_LIMIT);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
-        let sig_ptr = write_data(&env, &sig);
-        let mut pubkey = hex::decode(EDDSA_PUBKEY_HEX).unwrap();
-        // reduce / break pubkey
-        pubkey.pop();
-        let pubkey_ptr = write_data(&env, &pubkey);
+        // test vectors from RFC 9380, appendix J.9.1 and J.10.1
+        let message_ptr = write_data(&env, b"abc");
+        let dst_ptr = write_data(&env, b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_");
+        let result = do_bls12_381_hash_to_g1(&env, message_ptr, dst_ptr).unwrap();
+        let error = result >> 32;
+        let point_ptr: u32 = (result & 0xFFFFFFFF).try_into().unwrap();
+        assert_eq!(error, 0);
+        assert_eq!(
+            force_read(&env, point_ptr),
+            hex!("83567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903")
+        );
 
+        let message_ptr = write_data(&env, b"abc");
+        let dst_ptr = write_data(&env, b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_");
+        let result = do_bls12_381_hash_to_g2(&env, message_ptr, dst_ptr).unwrap();
+        let error = result >> 32;
+        let point_ptr: u32 = (result & 0xFFFFFFFF).try_into().unwrap();
+        assert_eq!(error, 0);
         assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
-            5 // mapped InvalidPubkeyFormat
-        )
+            force_read(&env, point_ptr),
+            hex!("939cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6")
+        );
+
+        // empty domain separation tag
+        let message_ptr = write_data(&env, b"abc");
+        let dst_ptr = write_data(&env, b"");
+        let result = do_bls12_381_hash_to_g1(&env, message_ptr, dst_ptr).unwrap();
+        assert_eq!(result >> 32, 14); // mapped Bls12381Err
     }
 
     #[test]
-    fn do_ed25519_verify_empty_pubkey_fails() {
+    fn do_ct_eq_works() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let msg = hex::decode(EDDSA_MSG_HEX).unwrap();
-        let msg_ptr = write_data(&env, &msg);
-        let sig = hex::decode(EDDSA_SIG_HEX).unwrap();
-        let sig_ptr = write_data(&env, &sig);
-        let pubkey = vec![];
-        let pubkey_ptr = write_data(&env, &pubkey);
+        let a_ptr = write_data(&env, b"secret");
+        let b_ptr = write_data(&env, b"secret");
+        assert_eq!(do_ct_eq(&env, a_ptr, b_ptr).unwrap(), 0);
 
-        assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
-            5 // mapped InvalidPubkeyFormat
-        )
+        let a_ptr = write_data(&env, b"secret");
+        let b_ptr = write_data(&env, b"secreT");
+        assert_eq!(do_ct_eq(&env, a_ptr, b_ptr).unwrap(), 1);
+
+        let a_ptr = write_data(&env, b"secret");
+        let b_ptr = write_data(&env, b"secre");
+        assert_eq!(do_ct_eq(&env, a_ptr, b_ptr).unwrap(), 1);
     }
 
     #[test]
-    fn do_ed25519_verify_wrong_data_fails() {
+    fn do_ct_eq_fails_for_large_inputs() {
         let api = MockApi::default();
         let (env, mut _instance) = make_instance(api);
 
-        let msg = vec![0x22; MESSAGE_HASH_MAX_LEN];
-        let msg_ptr = write_data(&env, &msg);
-        let sig = vec![0x22; MAX_LENGTH_ED25519_SIGNATURE];
-        let sig_ptr = write_data(&env, &sig);
-        let pubkey = vec![0x04; EDDSA_PUBKEY_LEN];
-        let pubkey_ptr = write_data(&env, &pubkey);
-
-        assert_eq!(
-            do_ed25519_verify(&env, msg_ptr, sig_ptr, pubkey_ptr).unwrap(),
-            1 // verification failure
-        )
+        let a_ptr = write_data(&env, &vec![0x61; MAX_LENGTH_CT_EQ + 1]);
+        let b_ptr = write_data(&env, b"secret");
+        let result = do_ct_eq(&env, a_ptr, b_ptr);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source:
+                    CommunicationError::RegionLengthTooBig {
+                        length, max_length, ..
+                    },
+                ..
+            } => {
+                assert_eq!(length, MAX_LENGTH_CT_EQ + 1);
+                assert_eq!(max_length, MAX_LENGTH_CT_EQ);
+            }
+            err => panic!("Incorrect error returned: {:?}", err),
+        }
     }
 
     #[test]
@@ -1694,6 +3704,51 @@ mod tests {
         assert_eq!(parsed_again.amount, coins(INIT_AMOUNT, INIT_DENOM));
     }
 
+    #[test]
+    fn do_query_chain_applies_query_multipliers() {
+        let request: QueryRequest<Empty> = QueryRequest::Bank(BankQuery::AllBalances {
+            address: INIT_ADDR.to_string(),
+        });
+        let request_data = cosmwasm_std::to_vec(&request).unwrap();
+
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api.clone());
+        let request_ptr = write_data(&env, &request_data);
+        leave_default_data(&env);
+        do_query_chain(&env, request_ptr).unwrap();
+        let default_used = env.with_gas_state(|gas_state| gas_state.externally_used_gas);
+        let default_left = env.get_gas_left();
+
+        // bank queries are not affected by the stargate multiplier
+        let (mut env, _instance) = make_instance(api.clone());
+        env.gas_config.stargate_query_multiplier = GasMultiplier::new(10, 1).unwrap();
+        let request_ptr = write_data(&env, &request_data);
+        leave_default_data(&env);
+        do_query_chain(&env, request_ptr).unwrap();
+        assert_eq!(env.get_gas_left(), default_left);
+
+        let (mut env, _instance) = make_instance(api);
+        env.gas_config.query_multiplier = GasMultiplier::new(2, 1).unwrap();
+        let request_ptr = write_data(&env, &request_data);
+        leave_default_data(&env);
+        do_query_chain(&env, request_ptr).unwrap();
+        assert_eq!(env.get_gas_left(), default_left - default_used);
+    }
+
+    #[test]
+    fn is_stargate_query_works() {
+        assert!(is_stargate_query(
+            br#"{"stargate":{"path":"/foo","data":""}}"#
+        ));
+        assert!(is_stargate_query(br#"{"ibc":{"port_id":{}}}"#));
+        assert!(!is_stargate_query(
+            br#"{"bank":{"all_balances":{"address":"foo"}}}"#
+        ));
+        assert!(!is_stargate_query(br#"{"custom":{"stargate":{}}}"#));
+        assert!(!is_stargate_query(b"Not valid JSON"));
+        assert!(!is_stargate_query(b"[]"));
+    }
+
     #[test]
     fn do_query_chain_fails_for_broken_request() {
         let api = MockApi::default();
@@ -1746,6 +3801,117 @@ mod tests {
         }
     }
 
+    #[test]
+    fn do_query_chain_rejects_reentrant_queries() {
+        let request: QueryRequest<Empty> = QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: String::from("myself"),
+            msg: Binary::from(b"{}" as &[u8]),
+        });
+        let request_data = cosmwasm_std::to_vec(&request).unwrap();
+        let query = |env: &Environment<MockApi, MockStorage, MockQuerier>| {
+            let request_ptr = write_data(env, &request_data);
+            leave_default_data(env);
+            let response_ptr = do_query_chain(env, request_ptr).unwrap();
+            let response = force_read(env, response_ptr);
+            cosmwasm_std::from_slice::<cosmwasm_std::QuerierResult>(&response)
+                .unwrap()
+                .unwrap_err()
+        };
+
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api.clone());
+        env.allow_reentrant_queries = false;
+        env.set_contract_address(Some("myself".to_string()));
+        match query(&env) {
+            SystemError::Reentrancy { contract_addr } => assert_eq!(contract_addr, "myself"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // queries of other contracts are forwarded to the querier
+        let (mut env, _instance) = make_instance(api.clone());
+        env.allow_reentrant_queries = false;
+        env.set_contract_address(Some("someone else".to_string()));
+        match query(&env) {
+            SystemError::NoSuchContract { addr } => assert_eq!(addr, "myself"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // allowed by default
+        let (env, _instance) = make_instance(api);
+        env.set_contract_address(Some("myself".to_string()));
+        match query(&env) {
+            SystemError::NoSuchContract { addr } => assert_eq!(addr, "myself"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn do_query_chain_passes_query_depth_to_querier() {
+        /// Answers all queries with an empty response and records their query depths
+        #[derive(Default)]
+        struct DepthRecordingQuerier {
+            depths: Mutex<Vec<u32>>,
+        }
+
+        impl Querier for DepthRecordingQuerier {
+            fn query_raw(
+                &self,
+                _request: &[u8],
+                _gas_limit: u64,
+            ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
+                panic!("query_raw_at_depth must be called")
+            }
+
+            fn query_raw_at_depth(
+                &self,
+                _request: &[u8],
+                _gas_limit: u64,
+                query_depth: u32,
+            ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
+                self.depths.lock().unwrap().push(query_depth);
+                let response = SystemResult::Ok(ContractResult::Ok(Binary::default()));
+                (Ok(response), GasInfo::free())
+            }
+        }
+
+        let api = MockApi::default();
+        let (env, instance) = make_instance(api.clone());
+        let request: QueryRequest<Empty> = QueryRequest::Bank(BankQuery::AllBalances {
+            address: INIT_ADDR.to_string(),
+        });
+        let request_ptr = write_data(&env, &cosmwasm_std::to_vec(&request).unwrap());
+
+        // Same Wasm memory, different querier
+        let mut recording_env: Environment<MockApi, MockStorage, DepthRecordingQuerier> =
+            Environment::new(api, TESTING_GAS_LIMIT, false);
+        recording_env.query_depth = 2;
+        recording_env.set_wasmer_instance(Some(NonNull::from(instance.as_ref())));
+        recording_env.set_gas_left(TESTING_GAS_LIMIT);
+        recording_env.move_in(MockStorage::new(), DepthRecordingQuerier::default());
+
+        do_query_chain(&recording_env, request_ptr).unwrap();
+        let (_, querier) = recording_env.move_out();
+        assert_eq!(*querier.unwrap().depths.lock().unwrap(), [3]);
+    }
+
+    #[test]
+    fn smart_query_target_works() {
+        assert_eq!(
+            smart_query_target(br#"{"wasm":{"smart":{"contract_addr":"foo","msg":"e30="}}}"#),
+            Some("foo".to_string())
+        );
+        assert_eq!(
+            smart_query_target(br#"{"wasm":{"raw":{"contract_addr":"foo","key":""}}}"#),
+            None
+        );
+        assert_eq!(
+            smart_query_target(br#"{"wasm":{"smart":{"msg":"e30=","contract_addr":"f\u006fo"}}}"#),
+            Some("foo".to_string())
+        );
+        assert_eq!(smart_query_target(br#"{"bank":{"balance":{}}}"#), None);
+        assert_eq!(smart_query_target(b"Not valid JSON"), None);
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn do_db_scan_unbound_works() {
@@ -1773,6 +3939,43 @@ mod tests {
         assert!(item.0.unwrap().is_none());
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_applies_iterator_multiplier() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.gas_config.storage_multiplier = GasMultiplier::new(5, 1).unwrap();
+        env.gas_config.iterator_multiplier = GasMultiplier::new(2, 1).unwrap();
+        leave_default_data(&env);
+
+        let gas_before = env.get_gas_left();
+        do_db_scan(&env, 0, 0, Order::Ascending.into()).unwrap();
+        // MockStorage charges 11 gas for creating an iterator
+        assert_eq!(gas_before - env.get_gas_left(), 22);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_enforces_key_length_limit() {
+        let api = MockApi::default();
+        let (mut env, _instance) = make_instance(api);
+        env.storage_limits.max_key_length = 3;
+        let start_ptr = write_data(&env, b"ant");
+        let end_ptr = write_data(&env, b"tree");
+        leave_default_data(&env);
+
+        let result = do_db_scan(&env, start_ptr, end_ptr, Order::Ascending.into());
+        match result.unwrap_err() {
+            VmError::StorageKeyTooLong {
+                length, max_length, ..
+            } => {
+                assert_eq!(length, 4);
+                assert_eq!(max_length, 3);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn do_db_scan_unbound_descending_works() {
@@ -1916,6 +4119,156 @@ mod tests {
         // API makes no guarantees for value_ptr in this case
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_with_limit_works() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+
+        leave_default_data(&env);
+
+        let id = do_db_scan_with_limit(&env, 0, 0, Order::Descending.into(), 1).unwrap();
+
+        // Entry 2
+        let kv_region_ptr = do_db_next(&env, id).unwrap();
+        assert_eq!(
+            force_read(&env, kv_region_ptr),
+            [KEY2, b"\0\0\0\x04", VALUE2, b"\0\0\0\x05"].concat()
+        );
+
+        // End
+        let kv_region_ptr = do_db_next(&env, id).unwrap();
+        assert_eq!(force_read(&env, kv_region_ptr), b"\0\0\0\0\0\0\0\0");
+
+        // Zero limit
+        let id = do_db_scan_with_limit(&env, 0, 0, Order::Ascending.into(), 0).unwrap();
+        let kv_region_ptr = do_db_next(&env, id).unwrap();
+        assert_eq!(force_read(&env, kv_region_ptr), b"\0\0\0\0\0\0\0\0");
+
+        // Unlimited iterators are not affected
+        let id = do_db_scan(&env, 0, 0, Order::Ascending.into()).unwrap();
+        let kv_region_ptr = do_db_next(&env, id).unwrap();
+        assert_eq!(
+            force_read(&env, kv_region_ptr),
+            [KEY1, b"\0\0\0\x03", VALUE1, b"\0\0\0\x06"].concat()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_works_for_empty_ranges() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        leave_data(&env, SORTED_KEYS);
+
+        for order in [Order::Ascending, Order::Descending] {
+            // start equals end
+            let keys = scan_keys_via_imports(&env, Some(b"a/1"), Some(b"a/1"), order);
+            assert_eq!(keys, Vec::<Vec<u8>>::new());
+            // no keys between the bounds
+            let keys = scan_keys_via_imports(&env, Some(b"a/4"), Some(b"a0"), order);
+            assert_eq!(keys, Vec::<Vec<u8>>::new());
+            // before the first key
+            let keys = scan_keys_via_imports(&env, None, Some(b"a"), order);
+            assert_eq!(keys, Vec::<Vec<u8>>::new());
+        }
+
+        // empty storage
+        leave_data(&env, &[]);
+        let keys = scan_keys_via_imports(&env, None, None, Order::Descending);
+        assert_eq!(keys, Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_works_for_reversed_bounds() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        leave_data(&env, SORTED_KEYS);
+
+        for order in [Order::Ascending, Order::Descending] {
+            let keys = scan_keys_via_imports(&env, Some(b"b"), Some(b"a"), order);
+            assert_eq!(keys, Vec::<Vec<u8>>::new());
+            assert_eq!(
+                expected_keys(SORTED_KEYS, Some(b"b"), Some(b"a"), order),
+                keys
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_works_for_single_key_ranges() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        leave_data(&env, SORTED_KEYS);
+
+        for key in SORTED_KEYS {
+            // the smallest key greater than `key` is `key` followed by a zero byte
+            let end = [*key, b"\0"].concat();
+            for order in [Order::Ascending, Order::Descending] {
+                let keys = scan_keys_via_imports(&env, Some(key), Some(&end), order);
+                assert_eq!(keys, vec![key.to_vec()]);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_works_for_prefixes() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        leave_data(&env, SORTED_KEYS);
+
+        let (start, end) = prefix_range(b"a/");
+        let keys = scan_keys_via_imports(&env, Some(&start), end.as_deref(), Order::Descending);
+        assert_eq!(
+            keys,
+            vec![b"a/3".to_vec(), b"a/2".to_vec(), b"a/1".to_vec()]
+        );
+
+        for prefix in [&b"a"[..], b"a/", b"b", b"c", &[0xFF], &[0xFF, 0xFF]] {
+            let (start, end) = prefix_range(prefix);
+            for order in [Order::Ascending, Order::Descending] {
+                assert_eq!(
+                    scan_keys_via_imports(&env, Some(&start), end.as_deref(), order),
+                    expected_keys(SORTED_KEYS, Some(&start), end.as_deref(), order),
+                    "prefix {:?}, order {}",
+                    prefix,
+                    order as i32
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn do_db_scan_matches_expected_keys_for_all_bounds() {
+        let api = MockApi::default();
+        let (env, _instance) = make_instance(api);
+        leave_data(&env, SORTED_KEYS);
+
+        let bounds: Vec<Option<&[u8]>> = [None]
+            .into_iter()
+            .chain(SORTED_KEYS.iter().map(|key| Some(*key)))
+            .chain([Some(&b"a/"[..]), Some(b"c")])
+            .collect();
+        for start in &bounds {
+            for end in &bounds {
+                for order in [Order::Ascending, Order::Descending] {
+                    assert_eq!(
+                        scan_keys_via_imports(&env, *start, *end, order),
+                        expected_keys(SORTED_KEYS, *start, *end, order),
+                        "start {:?}, end {:?}, order {}",
+                        start,
+                        end,
+                        order as i32
+                    );
+                }
+            }
+        }
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn do_db_next_fails_for_non_existent_id() {
@@ -1,26 +1,55 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::ptr::NonNull;
 use std::sync::Mutex;
 
 use wasmer::{Exports, Function, ImportObject, Instance as WasmerInstance, Module, Val};
 
-use crate::backend::{Backend, BackendApi, Querier, Storage};
-use crate::capabilities::required_capabilities_from_module;
+use cosmwasm_std::Binary;
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, Record};
+
+use crate::backend::{Backend, BackendApi, BackendError, GasInfo, Querier, Storage};
+use crate::capabilities::{import_capability, required_capabilities_from_module};
+use crate::compatibility::DEFAULT_IMPORT_NAMESPACE;
 use crate::conversion::{ref_to_u32, to_u32};
-use crate::environment::Environment;
+use crate::environment::{
+    process_gas_info, CallLimits, DebugLimits, Environment, GasConfig, GasMultiplier, SessionEntry,
+    SessionReport, StorageLimits, StorageReadCache,
+};
 use crate::errors::{CommunicationError, VmError, VmResult};
+use crate::extra_imports::ExtraImports;
 use crate::imports::{
-    do_abort, do_addr_canonicalize, do_addr_humanize, do_addr_validate, do_db_read, do_db_remove,
-    do_db_write, do_debug, do_ed25519_batch_verify, do_ed25519_verify, do_query_chain,
-    do_secp256k1_recover_pubkey, do_secp256k1_verify,
+    do_abort, do_addr_canonicalize, do_addr_humanize, do_addr_validate,
+    do_bls12_381_aggregate_verify, do_bls12_381_hash_to_g1, do_bls12_381_hash_to_g2,
+    do_bls12_381_pairing_equality, do_ct_eq, do_db_read, do_db_remove, do_db_write, do_debug,
+    do_ed25519_batch_verify, do_ed25519_batch_verify_packed, do_ed25519_verify,
+    do_ics23_verify_membership, do_ics23_verify_non_membership, do_poseidon_hash, do_query_chain,
+    do_secp256k1_recover_pubkey, do_secp256k1_verify, do_secp256k1_verify_prehashed,
+    do_secp256r1_recover_pubkey, do_secp256r1_verify, do_tendermint_verify_header,
+    do_zk_verify_groth16,
 };
 #[cfg(feature = "iterator")]
-use crate::imports::{do_db_next, do_db_scan};
+use crate::imports::{do_db_next, do_db_scan, do_db_scan_with_limit};
+use crate::libraries::Library;
 use crate::memory::{read_region, write_region};
+#[cfg(feature = "iterator")]
+use crate::prefixed_storage::namespace_upper_bound;
 use crate::size::Size;
 use crate::wasm_backend::compile;
 
-#[derive(Copy, Clone, Debug)]
+/// Wraps an import implementation into a closure that adds a "during <import>" breadcrumb
+/// to the errors it returns (see [`VmError::context`]) and counts the call in the session report.
+macro_rules! with_import_context {
+    ($name:literal, $func:ident($($arg:ident: $ty:ty),* $(,)?)) => {
+        |env: &Environment<A, S, Q>, $($arg: $ty),*| {
+            env.update_session(|session| session.host_calls += 1);
+            $func(env, $($arg),*).map_err(|err| err.context(concat!("during ", $name)))
+        }
+    };
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct GasReport {
     /// The original limit the instance was created with
     pub limit: u64,
@@ -33,11 +62,85 @@ pub struct GasReport {
     pub used_internally: u64,
 }
 
+/// The components of an instance that are left after [`Instance::recycle`]
+pub struct RecycledInstance<A: BackendApi, S: Storage, Q: Querier> {
+    /// The external dependencies for reuse
+    pub backend: Backend<A, S, Q>,
+    /// The final gas report, i.e. the gas state at the time the instance was recycled
+    pub gas_report: GasReport,
+    /// The session report if a session was active, for continuing it on a new instance
+    /// (see [`Instance::resume_session`])
+    pub session: Option<SessionReport>,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct InstanceOptions {
     /// Gas limit measured in [CosmWasm gas](https://github.com/CosmWasm/cosmwasm/blob/main/docs/GAS.md).
     pub gas_limit: u64,
     pub print_debug: bool,
+    /// Gas costs and multipliers for host functionality (crypto APIs, storage, queries)
+    pub gas_config: GasConfig,
+    /// Length limits for storage keys and values written or read by the contract
+    pub storage_limits: StorageLimits,
+    /// Caching of repeated storage reads within a single call
+    pub storage_read_cache: StorageReadCache,
+    pub debug_limits: DebugLimits,
+    /// Limits for the message passed to a call and the depth of nested queries
+    pub call_limits: CallLimits,
+    /// Allows smart queries of the executing contract, which are otherwise answered with
+    /// [`SystemError::Reentrancy`](cosmwasm_std::SystemError::Reentrancy). Chains should
+    /// keep this enabled unless they migrate to rejecting reentrant queries, since this
+    /// changes the results of existing contracts. The mocks in [`testing`](crate::testing)
+    /// reject them by default.
+    pub allow_reentrant_queries: bool,
+    /// The number of contract queries this instance is nested in, i.e. 0 for an instance
+    /// called by the chain directly and 1 for a contract queried by such an instance. The VM
+    /// passes this plus one to [`Querier::query_raw_at_depth`], which the querier executing a
+    /// smart query must use for the queried instance.
+    pub query_depth: u32,
+    /// Validates the addresses in bank and wasm messages returned by the contract via the
+    /// backend API. This helps catching broken addresses in integration tests.
+    pub validate_msg_addresses: bool,
+    /// Multiplier for all gas used by the contract except for gas used externally, which has
+    /// been charged by the chain already. This allows chains to discount gas for
+    /// system contracts. The gas limit and [`GasReport`] are not affected by the multiplier,
+    /// i.e. they remain in CosmWasm gas, and the contract runs out of gas once the
+    /// multiplied usage exceeds the limit. Instantiation fails for a zero multiplier.
+    pub gas_multiplier: GasMultiplier,
+}
+
+/// Optional parameters for creating an instance from a compiled module, which are set by the
+/// [`Cache`](crate::Cache) or in tests.
+#[derive(Default)]
+pub(crate) struct InstanceParams<'a> {
+    /// Additional imports provided to the module in their own namespaces
+    pub extra_imports: Option<ExtraImports<'a>>,
+    /// Guards the creation of the Wasmer instance, see [`Cache`](crate::Cache)
+    pub instantiation_lock: Option<&'a Mutex<()>>,
+    /// If set, imports that require a capability (see
+    /// [`CAPABILITY_GATED_IMPORTS`](crate::capabilities::CAPABILITY_GATED_IMPORTS)) are only
+    /// provided if the capability is available and instantiating a module that uses them fails
+    /// otherwise. If it is `None`, all imports are provided.
+    pub available_capabilities: Option<&'a HashSet<String>>,
+    /// The functions of these libraries are provided in the namespace they are registered
+    /// under, if the module imports from it.
+    pub libraries: Option<&'a HashMap<String, Library>>,
+}
+
+/// A failed instantiation. This contains the backend that was passed in, such that it
+/// can be reused by the caller.
+pub struct InstantiationFailure<A: BackendApi, S: Storage, Q: Querier> {
+    pub error: VmError,
+    pub backend: Backend<A, S, Q>,
+}
+
+impl<A: BackendApi, S: Storage, Q: Querier> fmt::Debug for InstantiationFailure<A, S, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the backend does not implement Debug
+        f.debug_struct("InstantiationFailure")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
 }
 
 pub struct Instance<A: BackendApi, S: Storage, Q: Querier> {
@@ -50,6 +153,12 @@ pub struct Instance<A: BackendApi, S: Storage, Q: Querier> {
     env: Environment<A, S, Q>,
 }
 
+impl<A: BackendApi, S: Storage, Q: Querier> fmt::Debug for Instance<A, S, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Instance").finish_non_exhaustive()
+    }
+}
+
 impl<A, S, Q> Instance<A, S, Q>
 where
     A: BackendApi + 'static, // 'static is needed here to allow copying API instances into closures
@@ -65,27 +174,94 @@ where
         memory_limit: Option<Size>,
     ) -> VmResult<Self> {
         let module = compile(code, memory_limit, &[])?;
-        Instance::from_module(
-            &module,
-            backend,
-            options.gas_limit,
-            options.print_debug,
-            None,
-            None,
-        )
+        Instance::from_module(&module, backend, options, InstanceParams::default())
     }
 
+    /// Creates an instance from a compiled module. See [`InstanceParams`] for the optional
+    /// parameters.
     pub(crate) fn from_module(
         module: &Module,
         backend: Backend<A, S, Q>,
-        gas_limit: u64,
-        print_debug: bool,
-        extra_imports: Option<HashMap<&str, Exports>>,
-        instantiation_lock: Option<&Mutex<()>>,
+        options: InstanceOptions,
+        params: InstanceParams,
     ) -> VmResult<Self> {
-        let store = module.store();
+        Self::try_from_module(module, backend, options, params).map_err(|failure| failure.error)
+    }
+
+    /// Like [`Instance::from_module`] but returns the backend along with the error if
+    /// instantiation fails. The storage and querier are only moved into the instance
+    /// after the Wasmer instance was created successfully.
+    pub(crate) fn try_from_module(
+        module: &Module,
+        backend: Backend<A, S, Q>,
+        options: InstanceOptions,
+        params: InstanceParams,
+    ) -> Result<Self, InstantiationFailure<A, S, Q>> {
+        let instantiated = Self::instantiate(module, backend.api.clone(), options, params);
+        match instantiated {
+            Ok((wasmer_instance, env)) => {
+                env.move_in(backend.storage, backend.querier);
+                Ok(Instance {
+                    _inner: wasmer_instance,
+                    env,
+                })
+            }
+            Err(error) => Err(InstantiationFailure { error, backend }),
+        }
+    }
+
+    /// Creates the Wasmer instance and its environment without storage and querier
+    fn instantiate(
+        module: &Module,
+        api: A,
+        options: InstanceOptions,
+        params: InstanceParams,
+    ) -> VmResult<(Box<WasmerInstance>, Environment<A, S, Q>)> {
+        let InstanceParams {
+            extra_imports,
+            instantiation_lock,
+            available_capabilities,
+            libraries,
+        } = params;
+        if let Some(available_capabilities) = available_capabilities {
+            check_import_capabilities(module, available_capabilities)?;
+        }
+        let import_available = |name: &str| match (available_capabilities, import_capability(name))
+        {
+            (Some(available), Some(capability)) => available.contains(capability),
+            _ => true,
+        };
 
-        let env = Environment::new(backend.api, gas_limit, print_debug);
+        if options.gas_multiplier.is_zero() {
+            // this would give the contract unlimited gas
+            return Err(VmError::instantiation_err(
+                "Gas multiplier must not be zero",
+            ));
+        }
+
+        if options.query_depth > options.call_limits.max_query_depth {
+            return Err(VmError::max_query_depth_exceeded(
+                options.query_depth,
+                options.call_limits.max_query_depth,
+            ));
+        }
+
+        let store = module.store();
+        let gas_limit = options.gas_limit;
+
+        let mut env = Environment::new(api, gas_limit, options.print_debug);
+        env.gas_config = options.gas_config;
+        env.storage_limits = options.storage_limits;
+        env.storage_read_cache = options.storage_read_cache;
+        env.debug_limits = options.debug_limits;
+        env.call_limits = options.call_limits;
+        env.allow_reentrant_queries = options.allow_reentrant_queries;
+        env.query_depth = options.query_depth;
+        env.validate_msg_addresses = options.validate_msg_addresses;
+        env.gas_multiplier = options.gas_multiplier;
+        if let Some(extra_imports) = &extra_imports {
+            env.metering_exempt_imports = extra_imports.metering_exempt().clone();
+        }
 
         let mut import_obj = ImportObject::new();
         let mut env_imports = Exports::new();
@@ -96,14 +272,22 @@ where
         // Ownership of the value pointer is transferred to the contract.
         env_imports.insert(
             "db_read",
-            Function::new_native_with_env(store, env.clone(), do_db_read),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("db_read", do_db_read(key_ptr: u32)),
+            ),
         );
 
         // Writes the given value into the database entry at the given key.
         // Ownership of both input and output pointer is not transferred to the host.
         env_imports.insert(
             "db_write",
-            Function::new_native_with_env(store, env.clone(), do_db_write),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("db_write", do_db_write(key_ptr: u32, value_ptr: u32)),
+            ),
         );
 
         // Removes the value at the given key. Different than writing &[] as future
@@ -112,7 +296,11 @@ where
         // Ownership of both key pointer is not transferred to the host.
         env_imports.insert(
             "db_remove",
-            Function::new_native_with_env(store, env.clone(), do_db_remove),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("db_remove", do_db_remove(key_ptr: u32)),
+            ),
         );
 
         // Reads human address from source_ptr and checks if it is valid.
@@ -120,7 +308,11 @@ where
         // Ownership of the input pointer is not transferred to the host.
         env_imports.insert(
             "addr_validate",
-            Function::new_native_with_env(store, env.clone(), do_addr_validate),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("addr_validate", do_addr_validate(source_ptr: u32)),
+            ),
         );
 
         // Reads human address from source_ptr and writes canonicalized representation to destination_ptr.
@@ -129,7 +321,14 @@ where
         // Ownership of both input and output pointer is not transferred to the host.
         env_imports.insert(
             "addr_canonicalize",
-            Function::new_native_with_env(store, env.clone(), do_addr_canonicalize),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "addr_canonicalize",
+                    do_addr_canonicalize(source_ptr: u32, destination_ptr: u32)
+                ),
+            ),
         );
 
         // Reads canonical address from source_ptr and writes humanized representation to destination_ptr.
@@ -138,7 +337,14 @@ where
         // Ownership of both input and output pointer is not transferred to the host.
         env_imports.insert(
             "addr_humanize",
-            Function::new_native_with_env(store, env.clone(), do_addr_humanize),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "addr_humanize",
+                    do_addr_humanize(source_ptr: u32, destination_ptr: u32)
+                ),
+            ),
         );
 
         // Verifies message hashes against a signature with a public key, using the secp256k1 ECDSA parametrization.
@@ -146,20 +352,106 @@ where
         // Ownership of input pointers is not transferred to the host.
         env_imports.insert(
             "secp256k1_verify",
-            Function::new_native_with_env(store, env.clone(), do_secp256k1_verify),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "secp256k1_verify",
+                    do_secp256k1_verify(hash_ptr: u32, signature_ptr: u32, pubkey_ptr: u32)
+                ),
+            ),
         );
 
+        // Same as secp256k1_verify, but with an explicit identifier of the hash function that produced the digest.
+        // Returns 0 on verification success, 1 on verification failure, and values greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `cosmwasm_1_2` capability.
+        if import_available("env.secp256k1_verify_prehashed") {
+            env_imports.insert(
+                "secp256k1_verify_prehashed",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "secp256k1_verify_prehashed",
+                        do_secp256k1_verify_prehashed(
+                            digest_ptr: u32,
+                            hash_function: u32,
+                            signature_ptr: u32,
+                            pubkey_ptr: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
         env_imports.insert(
             "secp256k1_recover_pubkey",
-            Function::new_native_with_env(store, env.clone(), do_secp256k1_recover_pubkey),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "secp256k1_recover_pubkey",
+                    do_secp256k1_recover_pubkey(
+                        hash_ptr: u32,
+                        signature_ptr: u32,
+                        recover_param: u32,
+                    )
+                ),
+            ),
         );
 
+        // Verifies message hashes against a signature with a public key, using the secp256r1 (P-256) ECDSA parametrization.
+        // Returns 0 on verification success, 1 on verification failure, and values greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `secp256r1` capability.
+        if import_available("env.secp256r1_verify") {
+            env_imports.insert(
+                "secp256r1_verify",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "secp256r1_verify",
+                        do_secp256r1_verify(hash_ptr: u32, signature_ptr: u32, pubkey_ptr: u32)
+                    ),
+                ),
+            );
+        }
+
+        // Same as secp256k1_recover_pubkey, but for the secp256r1 (P-256) curve.
+        // Contracts can only use this import on chains with the `secp256r1` capability.
+        if import_available("env.secp256r1_recover_pubkey") {
+            env_imports.insert(
+                "secp256r1_recover_pubkey",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "secp256r1_recover_pubkey",
+                        do_secp256r1_recover_pubkey(
+                            hash_ptr: u32,
+                            signature_ptr: u32,
+                            recover_param: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
         // Verifies a message against a signature with a public key, using the ed25519 EdDSA scheme.
         // Returns 0 on verification success, 1 on verification failure, and values greater than 1 in case of error.
         // Ownership of input pointers is not transferred to the host.
         env_imports.insert(
             "ed25519_verify",
-            Function::new_native_with_env(store, env.clone(), do_ed25519_verify),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "ed25519_verify",
+                    do_ed25519_verify(message_ptr: u32, signature_ptr: u32, pubkey_ptr: u32)
+                ),
+            ),
         );
 
         // Verifies a batch of messages against a batch of signatures with a batch of public keys,
@@ -169,16 +461,255 @@ where
         // Ownership of input pointers is not transferred to the host.
         env_imports.insert(
             "ed25519_batch_verify",
-            Function::new_native_with_env(store, env.clone(), do_ed25519_batch_verify),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!(
+                    "ed25519_batch_verify",
+                    do_ed25519_batch_verify(
+                        messages_ptr: u32,
+                        signatures_ptr: u32,
+                        public_keys_ptr: u32,
+                    )
+                ),
+            ),
         );
 
+        // Same as ed25519_batch_verify, but with messages, signatures and public keys packed
+        // into a single region. See `cosmwasm_crypto::ed25519_batch_decode_packed` for the format.
+        // Ownership of the input pointer is not transferred to the host.
+        // Contracts can only use this import on chains with the `cosmwasm_1_2` capability.
+        if import_available("env.ed25519_batch_verify_packed") {
+            env_imports.insert(
+                "ed25519_batch_verify_packed",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "ed25519_batch_verify_packed",
+                        do_ed25519_batch_verify_packed(input_ptr: u32)
+                    ),
+                ),
+            );
+        }
+
+        // Verifies an aggregate BLS signature over the BLS12-381 curve with public keys in G1
+        // and signatures in G2. Takes pointers to the concatenated compressed public keys, the
+        // sections encoded messages, the compressed signature and the domain separation tag.
+        // Returns 0 on verification success, 1 on verification failure, and values
+        // greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this and the other BLS12-381 imports on chains with the
+        // `bls12_381` capability.
+        if import_available("env.bls12_381_aggregate_verify") {
+            env_imports.insert(
+                "bls12_381_aggregate_verify",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "bls12_381_aggregate_verify",
+                        do_bls12_381_aggregate_verify(
+                            pubkeys_ptr: u32,
+                            messages_ptr: u32,
+                            signature_ptr: u32,
+                            dst_ptr: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
+        // Checks the pairing equality e(p_1, q_1) * ... * e(p_n, q_n) == e(r, s) over the BLS12-381 curve.
+        // Takes pointers to the concatenated compressed G1 points p, the concatenated compressed
+        // G2 points q and the compressed points r (G1) and s (G2).
+        // Returns 0 if the equality holds, 1 if it does not hold, and values greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        if import_available("env.bls12_381_pairing_equality") {
+            env_imports.insert(
+                "bls12_381_pairing_equality",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "bls12_381_pairing_equality",
+                        do_bls12_381_pairing_equality(
+                            ps_ptr: u32,
+                            qs_ptr: u32,
+                            r_ptr: u32,
+                            s_ptr: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
+        // Hashes a message to a BLS12-381 G1 or G2 point with a domain separation tag.
+        // Returns a u64 with the error code in the high half (0 on success) and a pointer
+        // to the compressed point in the low half.
+        // Ownership of input pointers is not transferred to the host.
+        if import_available("env.bls12_381_hash_to_g1") {
+            env_imports.insert(
+                "bls12_381_hash_to_g1",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "bls12_381_hash_to_g1",
+                        do_bls12_381_hash_to_g1(message_ptr: u32, dst_ptr: u32)
+                    ),
+                ),
+            );
+        }
+        if import_available("env.bls12_381_hash_to_g2") {
+            env_imports.insert(
+                "bls12_381_hash_to_g2",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "bls12_381_hash_to_g2",
+                        do_bls12_381_hash_to_g2(message_ptr: u32, dst_ptr: u32)
+                    ),
+                ),
+            );
+        }
+
+        // Calculates the Poseidon hash of 1 to 16 BN254 scalar field elements.
+        // Takes a pointer to a sections encoded list of 32 byte big-endian field elements.
+        // Returns a u64 with the error code in the high half (0 on success) and a pointer
+        // to the 32 byte hash in the low half.
+        // Ownership of the input pointer is not transferred to the host.
+        // Contracts can only use this import on chains with the `zk` capability.
+        if import_available("env.poseidon_hash") {
+            env_imports.insert(
+                "poseidon_hash",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!("poseidon_hash", do_poseidon_hash(inputs_ptr: u32)),
+                ),
+            );
+        }
+
+        // Verifies a Groth16 proof over the BN254 curve against a verification key and
+        // the concatenated public inputs, using the encoding of the Ethereum precompiles.
+        // Returns 0 on verification success, 1 on verification failure, and values
+        // greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `zk` capability.
+        if import_available("env.zk_verify_groth16") {
+            env_imports.insert(
+                "zk_verify_groth16",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "zk_verify_groth16",
+                        do_zk_verify_groth16(vk_ptr: u32, proof_ptr: u32, public_inputs_ptr: u32)
+                    ),
+                ),
+            );
+        }
+
+        // Verifies an untrusted Tendermint header against a trusted one, like a light client.
+        // Takes a pointer to the sections encoded trusted header, trusted next validators,
+        // untrusted signed header and untrusted validators.
+        // Returns 0 on verification success, 1 on verification failure, and values
+        // greater than 1 in case of error.
+        // Ownership of the input pointer is not transferred to the host.
+        // Contracts can only use this import on chains with the `tendermint` capability.
+        if import_available("env.tendermint_verify_header") {
+            env_imports.insert(
+                "tendermint_verify_header",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "tendermint_verify_header",
+                        do_tendermint_verify_header(input_ptr: u32)
+                    ),
+                ),
+            );
+        }
+
+        // Verifies an ICS-23 existence proof of a key and value against a commitment root, using
+        // the proof spec with the given identifier.
+        // Returns 0 on verification success, 1 on verification failure, and values
+        // greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `cosmwasm_1_2` capability.
+        if import_available("env.ics23_verify_membership") {
+            env_imports.insert(
+                "ics23_verify_membership",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "ics23_verify_membership",
+                        do_ics23_verify_membership(
+                            spec_id: u32,
+                            root_ptr: u32,
+                            proof_ptr: u32,
+                            key_ptr: u32,
+                            value_ptr: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
+        // Verifies an ICS-23 non-existence proof of a key against a commitment root, using
+        // the proof spec with the given identifier.
+        // Returns 0 on verification success, 1 on verification failure, and values
+        // greater than 1 in case of error.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `cosmwasm_1_2` capability.
+        if import_available("env.ics23_verify_non_membership") {
+            env_imports.insert(
+                "ics23_verify_non_membership",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "ics23_verify_non_membership",
+                        do_ics23_verify_non_membership(
+                            spec_id: u32,
+                            root_ptr: u32,
+                            proof_ptr: u32,
+                            key_ptr: u32,
+                        )
+                    ),
+                ),
+            );
+        }
+
+        // Compares two byte strings in constant time, such that secrets can be compared without
+        // data-dependent timing. Returns 0 if the inputs are equal and 1 otherwise.
+        // Ownership of input pointers is not transferred to the host.
+        // Contracts can only use this import on chains with the `cosmwasm_1_2` capability.
+        if import_available("env.ct_eq") {
+            env_imports.insert(
+                "ct_eq",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!("ct_eq", do_ct_eq(a_ptr: u32, b_ptr: u32)),
+                ),
+            );
+        }
+
         // Allows the contract to emit debug logs that the host can either process or ignore.
         // This is never written to chain.
         // Takes a pointer argument of a memory region that must contain an UTF-8 encoded string.
         // Ownership of both input and output pointer is not transferred to the host.
         env_imports.insert(
             "debug",
-            Function::new_native_with_env(store, env.clone(), do_debug),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("debug", do_debug(message_ptr: u32)),
+            ),
         );
 
         // Aborts the contract execution with an error message provided by the contract.
@@ -186,12 +717,20 @@ where
         // Ownership of both input and output pointer is not transferred to the host.
         env_imports.insert(
             "abort",
-            Function::new_native_with_env(store, env.clone(), do_abort),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("abort", do_abort(message_ptr: u32)),
+            ),
         );
 
         env_imports.insert(
             "query_chain",
-            Function::new_native_with_env(store, env.clone(), do_query_chain),
+            Function::new_native_with_env(
+                store,
+                env.clone(),
+                with_import_context!("query_chain", do_query_chain(request_ptr: u32)),
+            ),
         );
 
         // Creates an iterator that will go from start to end.
@@ -201,10 +740,37 @@ where
         // Ownership of both start and end pointer is not transferred to the host.
         // Returns an iterator ID.
         #[cfg(feature = "iterator")]
-        env_imports.insert(
-            "db_scan",
-            Function::new_native_with_env(store, env.clone(), do_db_scan),
-        );
+        if import_available("env.db_scan") {
+            env_imports.insert(
+                "db_scan",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "db_scan",
+                        do_db_scan(start_ptr: u32, end_ptr: u32, order: i32)
+                    ),
+                ),
+            );
+        }
+
+        // Like db_scan but the iterator returns at most `limit` elements.
+        // The limit is passed to the backend, such that it does not need to load more elements.
+        // Returns an iterator ID.
+        #[cfg(feature = "iterator")]
+        if import_available("env.db_scan_with_limit") {
+            env_imports.insert(
+                "db_scan_with_limit",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!(
+                        "db_scan_with_limit",
+                        do_db_scan_with_limit(start_ptr: u32, end_ptr: u32, order: i32, limit: u32)
+                    ),
+                ),
+            );
+        }
 
         // Get next element of iterator with ID `iterator_id`.
         // Creates a region containing both key and value and returns its address.
@@ -212,17 +778,40 @@ where
         // The KV region uses the format value || key || keylen, where keylen is a fixed size big endian u32 value.
         // An empty key (i.e. KV region ends with \0\0\0\0) means no more element, no matter what the value is.
         #[cfg(feature = "iterator")]
-        env_imports.insert(
-            "db_next",
-            Function::new_native_with_env(store, env.clone(), do_db_next),
-        );
+        if import_available("env.db_next") {
+            env_imports.insert(
+                "db_next",
+                Function::new_native_with_env(
+                    store,
+                    env.clone(),
+                    with_import_context!("db_next", do_db_next(iterator_id: u32)),
+                ),
+            );
+        }
 
-        import_obj.register("env", env_imports);
+        import_obj.register(DEFAULT_IMPORT_NAMESPACE, env_imports);
+
+        if let Some(libraries) = libraries {
+            for (namespace, library) in libraries {
+                if module.imports().any(|import| import.module() == namespace) {
+                    import_obj.register(namespace, library.instantiate(store, &env)?);
+                }
+            }
+        }
 
         if let Some(extra_imports) = extra_imports {
-            for (namespace, exports_obj) in extra_imports {
+            let (namespaces, host_env) = extra_imports.into_parts();
+            for (namespace, exports_obj) in namespaces {
+                let is_library = matches!(libraries, Some(l) if l.contains_key(namespace));
+                if namespace == DEFAULT_IMPORT_NAMESPACE || is_library {
+                    return Err(VmError::instantiation_err(format!(
+                        "Extra imports must not use namespace \"{}\", which is already in use",
+                        namespace
+                    )));
+                }
                 import_obj.register(namespace, exports_obj);
             }
+            host_env.bind(&env);
         }
 
         let wasmer_instance = Box::from(
@@ -231,19 +820,14 @@ where
                 WasmerInstance::new(module, &import_obj)
             }
             .map_err(|original| {
-                VmError::instantiation_err(format!("Error instantiating module: {original}"))
+                VmError::instantiation_err(format!("Error instantiating module: {}", original))
             })?,
         );
 
         let instance_ptr = NonNull::from(wasmer_instance.as_ref());
         env.set_wasmer_instance(Some(instance_ptr));
-        env.set_gas_left(gas_limit);
-        env.move_in(backend.storage, backend.querier);
-        let instance = Instance {
-            _inner: wasmer_instance,
-            env,
-        };
-        Ok(instance)
+        env.set_gas_left(env.gas_multiplier.apply_inverse(gas_limit));
+        Ok((wasmer_instance, env))
     }
 
     pub fn api(&self) -> &A {
@@ -251,27 +835,49 @@ where
     }
 
     /// Decomposes this instance into its components.
-    /// External dependencies are returned for reuse, the rest is dropped.
-    pub fn recycle(self) -> Option<Backend<A, S, Q>> {
-        if let (Some(storage), Some(querier)) = self.env.move_out() {
-            let api = self.env.api;
-            Some(Backend {
-                api,
+    /// External dependencies are returned for reuse together with a final gas report,
+    /// the rest is dropped.
+    pub fn recycle(self) -> Option<RecycledInstance<A, S, Q>> {
+        let (gas_report, session, backend) = self.into_parts();
+        backend.map(|backend| RecycledInstance {
+            backend,
+            gas_report,
+            session,
+        })
+    }
+
+    /// Like [`Instance::recycle`], but returns the final gas report and session report
+    /// even if the external dependencies cannot be recovered
+    pub(crate) fn into_parts(self) -> (GasReport, Option<SessionReport>, Option<Backend<A, S, Q>>) {
+        let gas_report = self.create_gas_report();
+        let session = self.env.session();
+        let backend = match self.env.move_out() {
+            (Some(storage), Some(querier)) => Some(Backend {
+                api: self.env.api,
                 storage,
                 querier,
-            })
-        } else {
-            None
-        }
+            }),
+            _ => None,
+        };
+        (gas_report, session, backend)
     }
 
-    /// Returns the features required by this contract.
+    /// Returns the features required by this contract, i.e. the ones it requires via
+    /// `requires_*` exports and the ones needed by the capability gated imports it uses.
     ///
     /// This is not needed for production because we can do static analysis
     /// on the Wasm file before instatiation to obtain this information. It's
     /// only kept because it can be handy for integration testing.
     pub fn required_capabilities(&self) -> HashSet<String> {
-        required_capabilities_from_module(self._inner.module())
+        let module = self._inner.module();
+        let mut out = required_capabilities_from_module(module);
+        for import in module.imports() {
+            let full_name = format!("{}.{}", import.module(), import.name());
+            if let Some(capability) = import_capability(&full_name) {
+                out.insert(capability.to_string());
+            }
+        }
+        out
     }
 
     /// Returns the size of the default memory in pages.
@@ -282,7 +888,22 @@ where
         self.env.memory().size().0 as _
     }
 
+    /// Returns the maximum size of the default memory, i.e. the memory limit the module
+    /// was compiled with (rounded down to whole pages) or the maximum declared by the
+    /// contract if that is lower. Returns `None` if the memory is unlimited.
+    pub fn memory_limit(&self) -> Option<Size> {
+        self.env
+            .memory()
+            .ty()
+            .maximum
+            .map(|pages| Size(pages.bytes().0))
+    }
+
     /// Returns the currently remaining gas.
+    ///
+    /// This is the gas available to the Wasm execution, i.e. it is divided by
+    /// [`InstanceOptions::gas_multiplier`]. Use [`Instance::create_gas_report`] for
+    /// values in CosmWasm gas.
     pub fn get_gas_left(&self) -> u64 {
         self.env.get_gas_left()
     }
@@ -293,18 +914,157 @@ where
     pub fn create_gas_report(&self) -> GasReport {
         let state = self.env.with_gas_state(|gas_state| gas_state.clone());
         let gas_left = self.env.get_gas_left();
+        let multiplier = self.env.gas_multiplier;
+        // The gas left and the externally used gas deducted from it are divided by the
+        // multiplier. Both are converted back to get values consistent with the limit.
+        let used_internally = multiplier.apply(
+            multiplier
+                .apply_inverse(state.gas_limit)
+                .saturating_sub(state.externally_used_wasm_gas)
+                .saturating_sub(gas_left),
+        );
         GasReport {
             limit: state.gas_limit,
-            remaining: gas_left,
-            used_externally: state.externally_used_gas,
             // If externally_used_gas exceeds the gas limit, this will return 0.
             // no matter how much gas was used internally. But then we error with out of gas
             // anyways, and it does not matter much anymore where gas was spend.
-            used_internally: state
+            remaining: state
                 .gas_limit
                 .saturating_sub(state.externally_used_gas)
-                .saturating_sub(gas_left),
+                .saturating_sub(used_internally),
+            used_externally: state.externally_used_gas,
+            used_internally,
+        }
+    }
+
+    /// Starts accumulating statistics across all following calls of this instance, e.g. for
+    /// an integration test session or a pooled instance. A running session is restarted.
+    pub fn start_session(&mut self) {
+        self.env.set_session(Some(SessionReport::default()));
+    }
+
+    /// Returns the statistics of the current session or `None` if no session is active
+    pub fn session_report(&self) -> Option<SessionReport> {
+        self.env.session()
+    }
+
+    /// Stops accumulating statistics and returns the report of the session that ended
+    pub fn end_session(&mut self) -> Option<SessionReport> {
+        let report = self.env.session();
+        self.env.set_session(None);
+        report
+    }
+
+    /// Continues accumulating into an existing session report, e.g. the one of a
+    /// [`RecycledInstance`] this instance was created from
+    pub fn resume_session(&mut self, session: SessionReport) {
+        self.env.set_session(Some(session));
+    }
+
+    /// Adds a call of the given entry point to the session report,
+    /// given the gas report from before the call
+    pub(crate) fn record_call(&self, entry_point: &str, gas_before: &GasReport) {
+        let gas_after = self.create_gas_report();
+        let gas_used = gas_before.remaining.saturating_sub(gas_after.remaining);
+        let gas_used_externally = gas_after
+            .used_externally
+            .saturating_sub(gas_before.used_externally);
+        self.env.update_session(|session| {
+            session.calls += 1;
+            session.gas_used += gas_used;
+            session.gas_used_externally += gas_used_externally;
+            session.entries.push(SessionEntry {
+                entry_point: entry_point.to_string(),
+                gas_used,
+                gas_used_externally,
+            });
+        });
+    }
+
+    /// Lets the backend validate a custom message returned by the contract.
+    /// Gas used by the backend is charged to this instance.
+    pub(crate) fn validate_custom_msg(&self, msg: Binary) -> VmResult<()> {
+        let (result, gas_info) = self.env.api.validate_custom_msg(msg);
+        process_gas_info(&self.env, gas_info)?;
+        result.map_err(|err| match err {
+            BackendError::UserErr { msg } => VmError::custom_msg_rejected(msg),
+            err => VmError::from(err),
+        })
+    }
+
+    /// Charges the gas for JSON serialization or deserialization of the given number of bytes
+    /// performed by the host (see [`GasConfig::serialization_per_byte_cost`]).
+    pub(crate) fn charge_serialization(&self, bytes: usize) -> VmResult<()> {
+        let cost = self
+            .env
+            .gas_config
+            .serialization_per_byte_cost
+            .saturating_mul(bytes as u64);
+        self.env.update_session(|session| session.gas_used += cost);
+        process_gas_info(&self.env, GasInfo::with_cost(cost))
+    }
+
+    /// Returns true if queries of the executing contract are allowed, see
+    /// [`InstanceOptions::allow_reentrant_queries`]
+    pub(crate) fn allows_reentrant_queries(&self) -> bool {
+        self.env.allow_reentrant_queries
+    }
+
+    /// Sets the address of the contract executing the following call, which is needed
+    /// to detect reentrant queries
+    pub(crate) fn set_contract_address(&self, contract_address: Option<String>) {
+        self.env.set_contract_address(contract_address);
+    }
+
+    /// Rejects messages larger than [`CallLimits::max_msg_size`]
+    pub(crate) fn check_msg_size(&self, size: usize) -> VmResult<()> {
+        let max = self.env.call_limits.max_msg_size.0;
+        if size > max {
+            return Err(VmError::input_too_large(size, max));
+        }
+        Ok(())
+    }
+
+    /// Returns true if addresses in returned messages should be validated
+    /// (see [`InstanceOptions::validate_msg_addresses`])
+    pub(crate) fn validates_msg_addresses(&self) -> bool {
+        self.env.validate_msg_addresses
+    }
+
+    /// Checks that the given address is valid and normalized, like `addr_validate` does.
+    /// Gas used by the backend is charged to this instance.
+    pub(crate) fn validate_address(&self, addr: &str) -> VmResult<()> {
+        let invalid = |err| match err {
+            BackendError::UserErr { msg } => VmError::invalid_msg_address(addr, msg),
+            err => VmError::from(err),
+        };
+        let (result, gas_info) = self.env.api.canonical_address(addr);
+        process_gas_info(&self.env, gas_info)?;
+        let canonical = result.map_err(invalid)?;
+        let (result, gas_info) = self.env.api.human_address(&canonical);
+        process_gas_info(&self.env, gas_info)?;
+        if result.map_err(invalid)? != addr {
+            return Err(VmError::invalid_msg_address(
+                addr,
+                "Address is not normalized",
+            ));
         }
+        Ok(())
+    }
+
+    /// Drops all entries of the storage read cache (see [`StorageReadCache`])
+    pub(crate) fn clear_storage_read_cache(&self) {
+        self.env.clear_read_cache();
+    }
+
+    /// Resets the number of debug messages (see [`DebugLimits`])
+    pub(crate) fn reset_debug_messages(&self) {
+        self.env.reset_debug_messages();
+    }
+
+    /// Resets the number of storage writes (see [`StorageLimits::max_writes_per_call`])
+    pub(crate) fn reset_storage_writes(&self) {
+        self.env.reset_storage_writes();
     }
 
     /// Sets the readonly storage flag on this instance. Since one instance can be used
@@ -314,6 +1074,19 @@ where
         self.env.set_storage_readonly(new_value);
     }
 
+    /// Runs `func` with debug printing enabled or disabled, independent of the `print_debug`
+    /// option this instance was created with. The previous setting is restored afterwards.
+    ///
+    /// This allows e.g. printing the debug messages of a single query for diagnostics
+    /// without affecting other calls of this instance.
+    pub fn with_print_debug<F: FnOnce(&mut Self) -> T, T>(&mut self, enabled: bool, func: F) -> T {
+        let previous = self.env.print_debug();
+        self.env.set_print_debug(enabled);
+        let result = func(self);
+        self.env.set_print_debug(previous);
+        result
+    }
+
     pub fn with_storage<F: FnOnce(&mut S) -> VmResult<T>, T>(&mut self, func: F) -> VmResult<T> {
         self.env.with_storage_from_context::<F, T>(func)
     }
@@ -322,6 +1095,42 @@ where
         self.env.with_querier_from_context::<F, T>(func)
     }
 
+    /// Returns a clone of the environment, which shares the storage and querier with
+    /// this instance
+    pub(crate) fn shared_env(&self) -> Environment<A, S, Q> {
+        self.env.clone()
+    }
+
+    /// Reads the value of the given key from the storage without charging gas, e.g. for tools
+    /// that inspect contract state offline.
+    pub fn read_raw(&self, key: &[u8]) -> VmResult<Option<Vec<u8>>> {
+        self.env
+            .with_storage_from_context(|storage| Ok(storage.get(key).0?))
+    }
+
+    /// Returns all storage entries with keys starting with the given prefix in ascending
+    /// order without charging gas. This only requires iterator support of the storage and
+    /// also works for contracts that do not use iterators themselves.
+    #[cfg(feature = "iterator")]
+    pub fn scan_raw(&self, prefix: &[u8]) -> VmResult<Vec<Record>> {
+        let end = namespace_upper_bound(prefix);
+        self.env.with_storage_from_context(|storage| {
+            let iterator_id = storage
+                .scan(Some(prefix), end.as_deref(), Order::Ascending)
+                .0?;
+            let mut records = vec![];
+            let result = loop {
+                match storage.next(iterator_id).0 {
+                    Ok(Some(record)) => records.push(record),
+                    Ok(None) => break Ok(records),
+                    Err(err) => break Err(err),
+                }
+            };
+            storage.close_iterator(iterator_id).0?;
+            Ok(result?)
+        })
+    }
+
     /// Requests memory allocation by the instance and returns a pointer
     /// in the Wasm address space to the created Region object.
     pub(crate) fn allocate(&mut self, size: usize) -> VmResult<u32> {
@@ -365,6 +1174,28 @@ where
     }
 }
 
+/// Ensures the module does not import functions which require a capability that is not available.
+///
+/// Contracts are checked for this on upload already. Doing it again upon instantiation
+/// results in a clear error when a chain drops a capability after the upload.
+fn check_import_capabilities(
+    module: &Module,
+    available_capabilities: &HashSet<String>,
+) -> VmResult<()> {
+    for import in module.imports() {
+        let full_name = format!("{}.{}", import.module(), import.name());
+        if let Some(capability) = import_capability(&full_name) {
+            if !available_capabilities.contains(capability) {
+                return Err(VmError::instantiation_err(format!(
+                    "Error instantiating module: capability \"{}\" not enabled (required by import {})",
+                    capability, full_name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 /// This exists only to be exported through `internals` for use by crates that are
 /// part of Cosmwasm.
 pub fn instance_from_module<A, S, Q>(
@@ -372,14 +1203,35 @@ pub fn instance_from_module<A, S, Q>(
     backend: Backend<A, S, Q>,
     gas_limit: u64,
     print_debug: bool,
-    extra_imports: Option<HashMap<&str, Exports>>,
+    extra_imports: Option<ExtraImports>,
 ) -> VmResult<Instance<A, S, Q>>
 where
     A: BackendApi + 'static, // 'static is needed here to allow copying API instances into closures
     S: Storage + 'static, // 'static is needed here to allow using this in an Environment that is cloned into closures
     Q: Querier + 'static,
 {
-    Instance::from_module(module, backend, gas_limit, print_debug, extra_imports, None)
+    let options = InstanceOptions {
+        gas_limit,
+        print_debug,
+        gas_config: GasConfig::default(),
+        storage_limits: StorageLimits::default(),
+        storage_read_cache: StorageReadCache::default(),
+        debug_limits: DebugLimits::default(),
+        call_limits: CallLimits::default(),
+        allow_reentrant_queries: true,
+        query_depth: 0,
+        validate_msg_addresses: false,
+        gas_multiplier: GasMultiplier::ONE,
+    };
+    Instance::from_module(
+        module,
+        backend,
+        options,
+        InstanceParams {
+            extra_imports,
+            ..Default::default()
+        },
+    )
 }
 
 #[cfg(test)]
@@ -390,15 +1242,16 @@ mod tests {
     use super::*;
     use crate::backend::Storage;
     use crate::calls::{call_execute, call_instantiate, call_query};
+    use crate::capabilities::capabilities_from_csv;
     use crate::errors::VmError;
     use crate::testing::{
         mock_backend, mock_env, mock_info, mock_instance, mock_instance_options,
-        mock_instance_with_balances, mock_instance_with_failing_api, mock_instance_with_gas_limit,
-        mock_instance_with_options, MockInstanceOptions,
+        register_own_storage, MockApi, MockInstanceBuilder, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
     };
     use cosmwasm_std::{
         coin, coins, from_binary, AllBalanceResponse, BalanceResponse, BankQuery, Empty,
-        QueryRequest,
+        QueryRequest, WasmQuery,
     };
 
     const KIB: usize = 1024;
@@ -415,6 +1268,51 @@ mod tests {
         assert_eq!(instance.required_capabilities().len(), 0);
     }
 
+    #[test]
+    fn required_capabilities_works_for_gated_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "ics23_verify_membership" (func (param i32 i32 i32 i32 i32) (result i32)))
+            (import "env" "ics23_verify_non_membership" (func (param i32 i32 i32 i32) (result i32)))
+            (type (func))
+            (func (type 0) nop)
+            (export "requires_water" (func 2))
+            )"#,
+        )
+        .unwrap();
+
+        let backend = mock_backend(&[]);
+        let (instance_options, memory_limit) = mock_instance_options();
+        let instance = Instance::from_code(&wasm, backend, instance_options, memory_limit).unwrap();
+        assert_eq!(
+            instance.required_capabilities(),
+            HashSet::from(["cosmwasm_1_2".to_string(), "water".to_string()])
+        );
+    }
+
+    #[test]
+    fn from_code_works_with_boxed_backend() {
+        let backend = Backend {
+            api: MockApi::default(),
+            storage: Box::new(MockStorage::new()) as Box<dyn Storage>,
+            querier: Box::new(MockQuerier::<Empty>::new(&[])) as Box<dyn Querier>,
+        };
+        let (instance_options, memory_limit) = mock_instance_options();
+        let mut instance =
+            Instance::from_code(CONTRACT, backend, instance_options, memory_limit).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+
+        let response = call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.as_slice(), br#"{"verifier":"verifies"}"#);
+    }
+
     #[test]
     fn required_capabilities_works_for_many_exports() {
         let wasm = wat::parse_str(
@@ -470,15 +1368,16 @@ mod tests {
         });
         let mut exports = Exports::new();
         exports.insert("bar", fun);
-        let mut extra_imports = HashMap::new();
-        extra_imports.insert("foo", exports);
+        let mut extra_imports = ExtraImports::new();
+        extra_imports.insert("foo", exports).unwrap();
         let instance = Instance::from_module(
             &module,
             backend,
-            instance_options.gas_limit,
-            false,
-            Some(extra_imports),
-            None,
+            instance_options,
+            InstanceParams {
+                extra_imports: Some(extra_imports),
+                ..Default::default()
+            },
         )
         .unwrap();
 
@@ -489,26 +1388,207 @@ mod tests {
     }
 
     #[test]
-    fn call_function0_works() {
-        let instance = mock_instance(CONTRACT, &[]);
+    fn extra_imports_must_not_shadow_library() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "math" "one" (func $one (result i32)))
+            )"#,
+        )
+        .unwrap();
+        let (instance_options, memory_limit) = mock_instance_options();
+        let module = compile(&wasm, memory_limit, &[]).unwrap();
+        let library_wasm =
+            wat::parse_str(r#"(module (func (export "one") (result i32) i32.const 1))"#).unwrap();
+        let mut libraries = HashMap::new();
+        libraries.insert(
+            "math".to_string(),
+            Library::new(&library_wasm, Size::mebi(1)).unwrap(),
+        );
 
-        instance
-            .call_function0("interface_version_8", &[])
-            .expect("error calling function");
+        let mut extra_imports = ExtraImports::new();
+        extra_imports.insert("math", Exports::new()).unwrap();
+        let host_env = extra_imports.host_env();
+        let result = Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams {
+                extra_imports: Some(extra_imports),
+                libraries: Some(&libraries),
+                ..Default::default()
+            },
+        );
+        match result.err().unwrap() {
+            VmError::InstantiationErr { msg, .. } => assert_eq!(
+                msg,
+                "Extra imports must not use namespace \"math\", which is already in use"
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        // the handle is not bound to the failed instance
+        match host_env.memory().unwrap_err() {
+            VmError::UninitializedContextData { kind, .. } => assert_eq!(kind, "host env"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
     }
 
     #[test]
-    fn call_function1_works() {
-        let instance = mock_instance(CONTRACT, &[]);
+    fn try_from_module_returns_backend_on_failure() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "foo" "bar" (func $bar))
+            (memory 1)
+            (export "memory" (memory 0))
+            )"#,
+        )
+        .unwrap();
+        let (instance_options, memory_limit) = mock_instance_options();
+        let module = compile(&wasm, memory_limit, &[]).unwrap();
 
-        // can call function few times
-        let result = instance
-            .call_function1("allocate", &[0u32.into()])
-            .expect("error calling allocate");
-        assert_ne!(result.unwrap_i32(), 0);
+        let mut backend = mock_backend(&[]);
+        backend.storage.set(b"foo", b"bar").0.unwrap();
+        let failure = Instance::try_from_module(
+            &module,
+            backend,
+            instance_options,
+            InstanceParams::default(),
+        )
+        .unwrap_err();
+        assert!(
+            format!("{:?}", failure).starts_with("InstantiationFailure { error: InstantiationErr"),
+            "{:?}",
+            failure
+        );
+        match failure.error {
+            VmError::InstantiationErr { msg, .. } => {
+                assert!(msg.contains("Error instantiating module"), "{}", msg)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(
+            failure.backend.storage.get(b"foo").0.unwrap(),
+            Some(b"bar".to_vec())
+        );
+    }
 
-        let result = instance
-            .call_function1("allocate", &[1u32.into()])
+    #[test]
+    fn from_module_gates_imports_by_capability() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "poseidon_hash" (func $poseidon_hash (param i32) (result i64)))
+            (memory 1)
+            (export "memory" (memory 0))
+            )"#,
+        )
+        .unwrap();
+        let (instance_options, memory_limit) = mock_instance_options();
+        let module = compile(&wasm, memory_limit, &[]).unwrap();
+
+        // Without capabilities, all imports are provided
+        Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams::default(),
+        )
+        .unwrap();
+
+        let available = capabilities_from_csv("zk");
+        Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams {
+                available_capabilities: Some(&available),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let available = capabilities_from_csv("iterator,staking");
+        let result = Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams {
+                available_capabilities: Some(&available),
+                ..Default::default()
+            },
+        );
+        match result.err().unwrap() {
+            VmError::InstantiationErr { msg, .. } => assert_eq!(
+                msg,
+                "Error instantiating module: capability \"zk\" not enabled (required by import env.poseidon_hash)"
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn from_module_gates_iterator_imports() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "db_scan" (func $db_scan (param i32 i32 i32) (result i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            )"#,
+        )
+        .unwrap();
+        let (instance_options, memory_limit) = mock_instance_options();
+        let module = compile(&wasm, memory_limit, &[]).unwrap();
+
+        let available = capabilities_from_csv("iterator");
+        Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams {
+                available_capabilities: Some(&available),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let available = HashSet::new();
+        let result = Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            instance_options,
+            InstanceParams {
+                available_capabilities: Some(&available),
+                ..Default::default()
+            },
+        );
+        match result.err().unwrap() {
+            VmError::InstantiationErr { msg, .. } => {
+                assert!(msg.contains("capability \"iterator\" not enabled"))
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn call_function0_works() {
+        let instance = mock_instance(CONTRACT, &[]);
+
+        instance
+            .call_function0("interface_version_8", &[])
+            .expect("error calling function");
+    }
+
+    #[test]
+    fn call_function1_works() {
+        let instance = mock_instance(CONTRACT, &[]);
+
+        // can call function few times
+        let result = instance
+            .call_function1("allocate", &[0u32.into()])
+            .expect("error calling allocate");
+        assert_ne!(result.unwrap_i32(), 0);
+
+        let result = instance
+            .call_function1("allocate", &[1u32.into()])
             .expect("error calling allocate");
         assert_ne!(result.unwrap_i32(), 0);
 
@@ -520,13 +1600,9 @@ mod tests {
 
     #[test]
     fn allocate_deallocate_works() {
-        let mut instance = mock_instance_with_options(
-            CONTRACT,
-            MockInstanceOptions {
-                memory_limit: Some(Size::mebi(500)),
-                ..Default::default()
-            },
-        );
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .memory_limit(Some(Size::mebi(500)))
+            .build();
 
         let sizes: Vec<usize> = vec![
             0,
@@ -581,7 +1657,9 @@ mod tests {
     fn errors_in_imports() {
         // set up an instance that will experience an error in an import
         let error_message = "Api failed intentionally";
-        let mut instance = mock_instance_with_failing_api(CONTRACT, &[], error_message);
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .backend_error(error_message)
+            .build();
         let init_result = call_instantiate::<_, _, _, Empty>(
             &mut instance,
             &mock_env(),
@@ -589,10 +1667,18 @@ mod tests {
             b"{\"verifier\": \"some1\", \"beneficiary\": \"some2\"}",
         );
 
-        match init_result.unwrap_err() {
-            VmError::RuntimeErr { msg, .. } => assert!(msg.contains(error_message)),
+        let err = init_result.unwrap_err();
+        match &err {
+            VmError::BackendErr {
+                source: BackendError::Unknown { msg },
+                ..
+            } => assert_eq!(msg, error_message),
             err => panic!("Unexpected error: {:?}", err),
         }
+        assert_eq!(
+            err.breadcrumbs(),
+            ["during addr_validate", "in call_instantiate"]
+        );
     }
 
     #[test]
@@ -634,12 +1720,14 @@ mod tests {
                 (memory 0)
                 (export "memory" (memory 0))
 
-                (type (func))
-                (func (type 0) nop)
+                (func nop)
+                (func (param i32 i32 i32) (result i32) i32.const 0)
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32))
                 (export "interface_version_8" (func 0))
-                (export "instantiate" (func 0))
-                (export "allocate" (func 0))
-                (export "deallocate" (func 0))
+                (export "instantiate" (func 1))
+                (export "allocate" (func 2))
+                (export "deallocate" (func 3))
             )"#,
         )
         .unwrap();
@@ -652,12 +1740,14 @@ mod tests {
                 (memory 3)
                 (export "memory" (memory 0))
 
-                (type (func))
-                (func (type 0) nop)
+                (func nop)
+                (func (param i32 i32 i32) (result i32) i32.const 0)
+                (func (param i32) (result i32) i32.const 0)
+                (func (param i32))
                 (export "interface_version_8" (func 0))
-                (export "instantiate" (func 0))
-                (export "allocate" (func 0))
-                (export "deallocate" (func 0))
+                (export "instantiate" (func 1))
+                (export "allocate" (func 2))
+                (export "deallocate" (func 3))
             )"#,
         )
         .unwrap();
@@ -665,6 +1755,30 @@ mod tests {
         assert_eq!(instance.memory_pages(), 3);
     }
 
+    #[test]
+    fn memory_limit_works() {
+        let backend = mock_backend(&[]);
+        let (instance_options, _) = mock_instance_options();
+        let instance =
+            Instance::from_code(CONTRACT, backend, instance_options, Some(Size::mebi(16))).unwrap();
+        assert_eq!(instance.memory_limit(), Some(Size::mebi(16)));
+
+        // rounded down to whole pages
+        let backend = mock_backend(&[]);
+        let instance = Instance::from_code(
+            CONTRACT,
+            backend,
+            instance_options,
+            Some(Size::kibi(64 * 20 + 1)),
+        )
+        .unwrap();
+        assert_eq!(instance.memory_limit(), Some(Size::kibi(64 * 20)));
+
+        let backend = mock_backend(&[]);
+        let instance = Instance::from_code(CONTRACT, backend, instance_options, None).unwrap();
+        assert_eq!(instance.memory_limit(), None);
+    }
+
     #[test]
     fn memory_pages_grows_with_usage() {
         let mut instance = mock_instance(CONTRACT, &[]);
@@ -683,15 +1797,40 @@ mod tests {
 
     #[test]
     fn get_gas_left_works() {
-        let instance = mock_instance_with_gas_limit(CONTRACT, 123321);
+        let instance = MockInstanceBuilder::new(CONTRACT).gas_limit(123321).build();
         let orig_gas = instance.get_gas_left();
         assert_eq!(orig_gas, 123321);
     }
 
+    #[test]
+    fn with_print_debug_works() {
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .print_debug(false)
+            .build();
+        assert!(!instance.env.print_debug());
+
+        let enabled = instance.with_print_debug(true, |instance| instance.env.print_debug());
+        assert!(enabled);
+        assert!(!instance.env.print_debug());
+
+        let mut instance = MockInstanceBuilder::new(CONTRACT).print_debug(true).build();
+        let enabled = instance.with_print_debug(false, |instance| {
+            // calls work with a changed setting
+            let info = mock_info("creator", &coins(1000, "earth"));
+            let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+            call_instantiate::<_, _, _, Empty>(instance, &mock_env(), &info, msg)
+                .unwrap()
+                .unwrap();
+            instance.env.print_debug()
+        });
+        assert!(!enabled);
+        assert!(instance.env.print_debug());
+    }
+
     #[test]
     fn create_gas_report_works() {
         const LIMIT: u64 = 700_000_000_000;
-        let mut instance = mock_instance_with_gas_limit(CONTRACT, LIMIT);
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
 
         let report1 = instance.create_gas_report();
         assert_eq!(report1.used_externally, 0);
@@ -716,6 +1855,258 @@ mod tests {
         );
     }
 
+    #[test]
+    fn create_gas_report_applies_gas_multiplier() {
+        // enough for instantiation with half of the gas but not with all of it
+        const LIMIT: u64 = 5_000_000_000;
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
+        let err =
+            call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg).unwrap_err();
+        assert!(matches!(err, VmError::GasDepletion { .. }), "{:?}", err);
+
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .gas_limit(LIMIT)
+            .gas_multiplier(GasMultiplier::new(1, 2).unwrap())
+            .build();
+        let report1 = instance.create_gas_report();
+        assert_eq!(report1.used_externally, 0);
+        assert_eq!(report1.used_internally, 0);
+        assert_eq!(report1.limit, LIMIT);
+        assert_eq!(report1.remaining, LIMIT);
+
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+
+        // externally used gas is not discounted, internally used gas is half of the
+        // 5775750198 used without multiplier
+        let report2 = instance.create_gas_report();
+        assert_eq!(report2.used_externally, 73);
+        assert_eq!(report2.used_internally, 2887875099);
+        assert_eq!(report2.limit, LIMIT);
+        assert_eq!(
+            report2.remaining,
+            LIMIT - report2.used_externally - report2.used_internally
+        );
+    }
+
+    #[test]
+    fn instantiation_fails_for_zero_gas_multiplier() {
+        let (options, memory_limit) = mock_instance_options();
+        let options = InstanceOptions {
+            gas_multiplier: GasMultiplier::new(0, 1).unwrap(),
+            ..options
+        };
+        let err = Instance::from_code(CONTRACT, mock_backend(&[]), options, memory_limit)
+            .err()
+            .unwrap();
+        match err {
+            VmError::InstantiationErr { msg, .. } => {
+                assert_eq!(msg, "Gas multiplier must not be zero")
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn instantiation_fails_beyond_max_query_depth() {
+        let (options, memory_limit) = mock_instance_options();
+        let options = InstanceOptions {
+            query_depth: 3,
+            call_limits: CallLimits {
+                max_query_depth: 2,
+                ..options.call_limits
+            },
+            ..options
+        };
+        let err = Instance::from_code(CONTRACT, mock_backend(&[]), options, memory_limit)
+            .err()
+            .unwrap();
+        match err {
+            VmError::MaxQueryDepthExceeded { depth, max, .. } => {
+                assert_eq!(depth, 3);
+                assert_eq!(max, 2);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // the maximum depth itself works
+        let options = InstanceOptions {
+            query_depth: 2,
+            ..options
+        };
+        Instance::from_code(CONTRACT, mock_backend(&[]), options, memory_limit).unwrap();
+    }
+
+    #[test]
+    fn session_report_works() {
+        const LIMIT: u64 = 700_000_000_000;
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
+        assert_eq!(instance.session_report(), None);
+
+        instance.start_session();
+        assert_eq!(instance.session_report(), Some(SessionReport::default()));
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+
+        let config_len = instance
+            .with_storage(|store| Ok(store.get(b"config").0.unwrap().unwrap().len() as u64))
+            .unwrap();
+        let gas_report = instance.create_gas_report();
+        let report = instance.session_report().unwrap();
+        assert_eq!(report.calls, 2);
+        assert_eq!(report.gas_used, LIMIT - gas_report.remaining);
+        assert_eq!(report.gas_used_externally, gas_report.used_externally);
+        assert!(report.host_calls > 0);
+        assert_eq!(report.storage_bytes_read, config_len);
+        assert_eq!(
+            report.storage_bytes_written,
+            b"config".len() as u64 + config_len
+        );
+
+        // failed calls are counted as well
+        call_query(&mut instance, &mock_env(), br#"{"no_such_query":{}}"#)
+            .unwrap()
+            .unwrap_err();
+        let report2 = instance.end_session().unwrap();
+        assert_eq!(report2.calls, 3);
+        assert!(report2.gas_used > report.gas_used);
+
+        // no accumulation after the session ended
+        assert_eq!(instance.session_report(), None);
+        call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(instance.session_report(), None);
+
+        // restarting resets the report
+        instance.start_session();
+        assert_eq!(instance.session_report(), Some(SessionReport::default()));
+    }
+
+    #[test]
+    fn recycle_works() {
+        const LIMIT: u64 = 700_000_000_000;
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        let expected_report = instance.create_gas_report();
+
+        let recycled = instance.recycle().unwrap();
+        assert_eq!(recycled.gas_report, expected_report);
+        assert_eq!(
+            recycled.gas_report.remaining,
+            LIMIT - expected_report.used_externally - expected_report.used_internally
+        );
+
+        // backend contains the state written by the contract
+        let (value, _) = recycled.backend.storage.get(b"config");
+        assert!(value.unwrap().is_some());
+    }
+
+    #[test]
+    fn session_report_records_entries() {
+        const LIMIT: u64 = 700_000_000_000;
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
+        instance.start_session();
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+        call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+
+        let gas_report = instance.create_gas_report();
+        let report = instance.end_session().unwrap();
+        let entry_points: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| entry.entry_point.as_str())
+            .collect();
+        assert_eq!(entry_points, ["instantiate", "query", "query"]);
+        assert!(report.entries.iter().all(|entry| entry.gas_used > 0));
+        assert_eq!(report.entries[1], report.entries[2]);
+        assert!(
+            report
+                .entries
+                .iter()
+                .map(|entry| entry.gas_used)
+                .sum::<u64>()
+                <= report.gas_used
+        );
+        assert_eq!(
+            report
+                .entries
+                .iter()
+                .map(|entry| entry.gas_used_externally)
+                .sum::<u64>(),
+            gas_report.used_externally
+        );
+        assert_eq!(report.total_for("query"), 2 * report.entries[1].gas_used);
+        assert_eq!(report.total_for("execute"), 0);
+        let totals = report.totals_by_entry_point();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals["instantiate"], report.entries[0].gas_used);
+        assert_eq!(totals["query"], report.total_for("query"));
+    }
+
+    #[test]
+    fn session_can_be_resumed_after_recycling() {
+        const LIMIT: u64 = 700_000_000_000;
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(LIMIT).build();
+        instance.start_session();
+
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        let recycled = instance.recycle().unwrap();
+        let session = recycled.session.unwrap();
+        assert_eq!(session.gas_used, LIMIT - recycled.gas_report.remaining);
+
+        let (mut instance_options, memory_limit) = mock_instance_options();
+        instance_options.gas_limit = LIMIT;
+        let mut instance =
+            Instance::from_code(CONTRACT, recycled.backend, instance_options, memory_limit)
+                .unwrap();
+        instance.resume_session(session.clone());
+        call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#)
+            .unwrap()
+            .unwrap();
+
+        let gas_report = instance.create_gas_report();
+        let resumed = instance.session_report().unwrap();
+        assert_eq!(resumed.calls, 2);
+        assert_eq!(resumed.entries.len(), 2);
+        assert_eq!(resumed.entries[0], session.entries[0]);
+        assert_eq!(resumed.entries[1].entry_point, "query");
+        assert_eq!(
+            resumed.gas_used,
+            session.gas_used + LIMIT - gas_report.remaining
+        );
+    }
+
     #[test]
     fn set_storage_readonly_works() {
         let mut instance = mock_instance(CONTRACT, &[]);
@@ -761,6 +2152,112 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn read_raw_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        assert_eq!(instance.read_raw(b"foo").unwrap(), None);
+
+        instance
+            .with_storage(|store| {
+                store.set(b"foo", b"bar").0.unwrap();
+                Ok(())
+            })
+            .unwrap();
+        let gas_before = instance.get_gas_left();
+        assert_eq!(instance.read_raw(b"foo").unwrap(), Some(b"bar".to_vec()));
+        assert_eq!(instance.get_gas_left(), gas_before);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_raw_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        assert_eq!(instance.scan_raw(b"").unwrap(), vec![]);
+
+        instance
+            .with_storage(|store| {
+                store.set(b"a", b"1").0.unwrap();
+                store.set(b"ab", b"2").0.unwrap();
+                store.set(b"b", b"3").0.unwrap();
+                store.set(&[0xFF], b"4").0.unwrap();
+                store.set(&[0xFF, 0xFF], b"5").0.unwrap();
+                Ok(())
+            })
+            .unwrap();
+        let entries = |prefix: &[u8]| {
+            instance
+                .scan_raw(prefix)
+                .unwrap()
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entries(b"a"), vec![b"1".to_vec(), b"2".to_vec()]);
+        assert_eq!(entries(b"ab"), vec![b"2".to_vec()]);
+        assert_eq!(entries(b"c"), Vec::<Vec<u8>>::new());
+        assert_eq!(entries(&[0xFF]), vec![b"4".to_vec(), b"5".to_vec()]);
+        assert_eq!(entries(b"").len(), 5);
+    }
+
+    #[test]
+    fn with_storage_works_for_prepopulated_backend() {
+        let mut backend = mock_backend(&[]);
+        backend.storage.set(b"foo", b"bar").0.unwrap();
+        let mut instance = MockInstanceBuilder::new(CONTRACT).backend(backend).build();
+
+        instance
+            .with_storage(|store| {
+                assert_eq!(store.get(b"foo").0.unwrap(), Some(b"bar".to_vec()));
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn register_own_storage_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let raw_query = |instance: &mut Instance<MockApi, MockStorage, MockQuerier>| {
+            instance
+                .with_querier(|querier| {
+                    let response = querier
+                        .query::<Empty>(
+                            &WasmQuery::Raw {
+                                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                                key: b"foo".into(),
+                            }
+                            .into(),
+                            DEFAULT_QUERY_GAS_LIMIT,
+                        )
+                        .0
+                        .unwrap()
+                        .unwrap()
+                        .unwrap();
+                    Ok(response)
+                })
+                .unwrap()
+        };
+
+        instance
+            .with_storage(|store| {
+                store.set(b"foo", b"bar").0.unwrap();
+                Ok(())
+            })
+            .unwrap();
+        register_own_storage(&mut instance, MOCK_CONTRACT_ADDR);
+        assert_eq!(raw_query(&mut instance).as_slice(), b"bar");
+
+        // the querier holds a snapshot until registering again
+        instance
+            .with_storage(|store| {
+                store.set(b"foo", b"baz").0.unwrap();
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(raw_query(&mut instance).as_slice(), b"bar");
+        register_own_storage(&mut instance, MOCK_CONTRACT_ADDR);
+        assert_eq!(raw_query(&mut instance).as_slice(), b"baz");
+    }
+
     #[test]
     #[should_panic]
     fn with_storage_safe_for_panic() {
@@ -771,11 +2268,51 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn with_storage_converts_panic_to_foreign_panic() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let err = instance
+            .with_storage::<_, ()>(|_store| panic!("trigger failure"))
+            .unwrap_err();
+        match err {
+            VmError::BackendErr {
+                source: BackendError::ForeignPanic { msg },
+                ..
+            } => assert_eq!(msg.as_deref(), Some("trigger failure")),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // the instance remains usable
+        instance
+            .with_storage(|store| {
+                store.set(b"foo", b"bar").0.unwrap();
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn with_querier_converts_panic_to_foreign_panic() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let err = instance
+            .with_querier::<_, ()>(|_querier| panic!("trigger failure"))
+            .unwrap_err();
+        match err {
+            VmError::BackendErr {
+                source: BackendError::ForeignPanic { msg },
+                ..
+            } => assert_eq!(msg.as_deref(), Some("trigger failure")),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
     #[test]
     fn with_querier_works_readonly() {
         let rich_addr = String::from("foobar");
         let rich_balance = vec![coin(10000, "gold"), coin(8000, "silver")];
-        let mut instance = mock_instance_with_balances(CONTRACT, &[(&rich_addr, &rich_balance)]);
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .balances(&[(&rich_addr, &rich_balance)])
+            .build();
 
         // query one
         instance
@@ -831,7 +2368,9 @@ mod tests {
         let rich_addr = String::from("foobar");
         let rich_balance1 = vec![coin(10000, "gold"), coin(500, "silver")];
         let rich_balance2 = vec![coin(10000, "gold"), coin(8000, "silver")];
-        let mut instance = mock_instance_with_balances(CONTRACT, &[(&rich_addr, &rich_balance1)]);
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .balances(&[(&rich_addr, &rich_balance1)])
+            .build();
 
         // Get initial state
         instance
@@ -925,7 +2464,7 @@ mod tests {
 
     #[test]
     fn contract_enforces_gas_limit() {
-        let mut instance = mock_instance_with_gas_limit(CONTRACT, 20_000);
+        let mut instance = MockInstanceBuilder::new(CONTRACT).gas_limit(20_000).build();
 
         // init contract
         let info = mock_info("creator", &coins(1000, "earth"));
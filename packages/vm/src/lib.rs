@@ -6,26 +6,33 @@ mod calls;
 mod capabilities;
 mod checksum;
 mod compatibility;
+mod compression;
 mod conversion;
 mod environment;
 mod errors;
+mod extra_imports;
 mod filesystem;
 mod imports;
 mod instance;
+mod libraries;
 mod limited;
 mod memory;
 mod modules;
+mod prefixed_storage;
 mod sections;
 mod serde;
 mod size;
 mod static_analysis;
 pub mod testing;
+mod versioned_storage;
 mod wasm_backend;
 
 pub use crate::backend::{
-    Backend, BackendApi, BackendError, BackendResult, GasInfo, Querier, Storage,
+    Backend, BackendApi, BackendError, BackendResult, GasInfo, Querier, Storage, VersionedStorage,
+};
+pub use crate::cache::{
+    AnalysisReport, Cache, CacheOptions, CallOutcome, Metrics, PrecompileProgress, Stats,
 };
-pub use crate::cache::{AnalysisReport, Cache, CacheOptions, Metrics, Stats};
 pub use crate::calls::{
     call_execute, call_execute_raw, call_instantiate, call_instantiate_raw, call_migrate,
     call_migrate_raw, call_query, call_query_raw, call_reply, call_reply_raw, call_sudo,
@@ -35,18 +42,34 @@ pub use crate::calls::{
 pub use crate::calls::{
     call_ibc_channel_close, call_ibc_channel_close_raw, call_ibc_channel_connect,
     call_ibc_channel_connect_raw, call_ibc_channel_open, call_ibc_channel_open_raw,
-    call_ibc_packet_ack, call_ibc_packet_ack_raw, call_ibc_packet_receive,
-    call_ibc_packet_receive_raw, call_ibc_packet_timeout, call_ibc_packet_timeout_raw,
+    call_ibc_destination_callback, call_ibc_destination_callback_raw, call_ibc_packet_ack,
+    call_ibc_packet_ack_raw, call_ibc_packet_receive, call_ibc_packet_receive_raw,
+    call_ibc_packet_timeout, call_ibc_packet_timeout_raw, call_ibc_source_callback,
+    call_ibc_source_callback_raw,
+};
+pub use crate::capabilities::{
+    capabilities_from_csv, Capabilities, CapabilitiesConfigError, InvalidCapabilityError,
 };
-pub use crate::capabilities::capabilities_from_csv;
 pub use crate::checksum::Checksum;
+pub use crate::compatibility::AllowedEntryPoints;
+pub use crate::environment::{
+    CallLimits, DebugLimits, GasConfig, GasMultiplier, SessionEntry, SessionReport, StorageLimits,
+    StorageReadCache,
+};
 pub use crate::errors::{
-    CommunicationError, CommunicationResult, RegionValidationError, RegionValidationResult,
-    VmError, VmResult,
+    Breadcrumbs, CommunicationError, CommunicationResult, RegionValidationError,
+    RegionValidationResult, VmError, VmResult,
+};
+pub use crate::extra_imports::{make_host_function, ExtraImports, HostEnv};
+pub use crate::instance::{
+    GasReport, Instance, InstanceOptions, InstantiationFailure, RecycledInstance,
 };
-pub use crate::instance::{GasReport, Instance, InstanceOptions};
+pub use crate::libraries::Library;
+pub use crate::prefixed_storage::PrefixedStorage;
 pub use crate::serde::{from_slice, to_vec};
 pub use crate::size::Size;
+pub use crate::static_analysis::{IbcEntryPoints, NonDeterminismSource};
+pub use crate::versioned_storage::HeightPinnedStorage;
 
 #[doc(hidden)]
 pub mod internals {
@@ -57,5 +80,6 @@ pub mod internals {
 
     pub use crate::compatibility::check_wasm;
     pub use crate::instance::instance_from_module;
+    pub use crate::memory::{read_region, read_region_truncated, write_region};
     pub use crate::wasm_backend::{compile, make_runtime_store};
 }
@@ -0,0 +1,243 @@
+use wasmer::{
+    Exports, Function, ImportObject, Instance as WasmerInstance, Module, RuntimeError, Store, Type,
+    Val,
+};
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+
+use crate::backend::{BackendApi, Querier, Storage};
+use crate::environment::Environment;
+use crate::errors::{VmError, VmResult};
+use crate::size::Size;
+use crate::wasm_backend::compile;
+
+/// A Wasm module provided by the host (e.g. a math library of the chain) whose exported
+/// functions can be imported by contracts. This allows sharing heavy code between contracts
+/// instead of compiling it into every one of them.
+///
+/// Contract and library do not share memory, such that the exported functions may only
+/// take and return integers. Libraries must not have any imports.
+///
+/// Every contract instance that imports from a library gets its own instance of the library.
+/// Library code is metered like contract code and the gas used is deducted from the calling
+/// contract.
+#[derive(Clone)]
+pub struct Library {
+    module: Module,
+}
+
+impl Library {
+    /// Compiles the given library. The memory limit (in bytes) applies to the memory
+    /// of the library instances.
+    pub fn new(wasm: &[u8], memory_limit: Size) -> VmResult<Self> {
+        let module = compile(wasm, Some(memory_limit), &[])?;
+
+        if let Some(import) = module.imports().next() {
+            return Err(VmError::static_validation_err(format!(
+                "Library must not have imports, found: \"{}.{}\"",
+                import.module(),
+                import.name()
+            )));
+        }
+        for export in module.exports().functions() {
+            let ty = export.ty();
+            let is_integer = |t: &Type| matches!(t, Type::I32 | Type::I64);
+            if !ty.params().iter().all(is_integer) || !ty.results().iter().all(is_integer) {
+                return Err(VmError::static_validation_err(format!(
+                    "Library export \"{}\" must only use integer parameters and results",
+                    export.name()
+                )));
+            }
+        }
+
+        Ok(Library { module })
+    }
+
+    /// The names of the functions exported by the library
+    pub fn function_names(&self) -> Vec<String> {
+        self.module
+            .exports()
+            .functions()
+            .map(|export| export.name().to_string())
+            .collect()
+    }
+
+    /// Creates an instance of the library and returns its functions as imports for the
+    /// contract instance that owns `env`.
+    pub(crate) fn instantiate<A, S, Q>(
+        &self,
+        store: &Store,
+        env: &Environment<A, S, Q>,
+    ) -> VmResult<Exports>
+    where
+        A: BackendApi + 'static,
+        S: Storage + 'static,
+        Q: Querier + 'static,
+    {
+        let instance =
+            WasmerInstance::new(&self.module, &ImportObject::new()).map_err(|original| {
+                VmError::instantiation_err(format!("Error instantiating library: {}", original))
+            })?;
+
+        let mut exports = Exports::new();
+        for export in self.module.exports().functions() {
+            let function = instance.exports.get_function(export.name())?.clone();
+            let library_instance = instance.clone();
+            exports.insert(
+                export.name(),
+                Function::new_with_env(store, export.ty(), env.clone(), move |env, args| {
+                    call_library_function(env, &library_instance, &function, args)
+                }),
+            );
+        }
+        Ok(exports)
+    }
+}
+
+/// Calls a library function with the gas left of the contract and deducts the
+/// gas used by the library from the contract.
+fn call_library_function<A: BackendApi, S: Storage, Q: Querier>(
+    env: &Environment<A, S, Q>,
+    library_instance: &WasmerInstance,
+    function: &Function,
+    args: &[Val],
+) -> Result<Vec<Val>, RuntimeError> {
+    set_remaining_points(library_instance, env.get_gas_left());
+    let result = function.call(args);
+    match get_remaining_points(library_instance) {
+        MeteringPoints::Remaining(gas_left) => {
+            env.set_gas_left(gas_left);
+            Ok(result?.into_vec())
+        }
+        MeteringPoints::Exhausted => {
+            env.set_gas_left(0);
+            Err(VmError::gas_depletion().into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::instance::{Instance, InstanceParams};
+    use crate::testing::{mock_backend, mock_instance_options, MockApi, MockQuerier, MockStorage};
+
+    const MEMORY_LIMIT: Size = Size::mebi(16);
+
+    fn library() -> Library {
+        let wasm = wat::parse_str(
+            r#"(module
+            (func (export "add") (param i64 i64) (result i64)
+                local.get 0
+                local.get 1
+                i64.add)
+            (func (export "spin") (param i32)
+                (loop
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.tee 0
+                    br_if 0))
+            )"#,
+        )
+        .unwrap();
+        Library::new(&wasm, MEMORY_LIMIT).unwrap()
+    }
+
+    fn contract_instance(gas_limit: u64) -> Instance<MockApi, MockStorage, MockQuerier> {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "math" "add" (func $add (param i64 i64) (result i64)))
+            (import "math" "spin" (func $spin (param i32)))
+            (memory 1)
+            (export "memory" (memory 0))
+            (func (export "add") (param i64 i64) (result i64)
+                local.get 0
+                local.get 1
+                call $add)
+            (func (export "spin") (param i32) (result i32)
+                local.get 0
+                call $spin
+                i32.const 0)
+            )"#,
+        )
+        .unwrap();
+        let (mut options, memory_limit) = mock_instance_options();
+        options.gas_limit = gas_limit;
+        let module = compile(&wasm, memory_limit, &[]).unwrap();
+        let mut libraries = HashMap::new();
+        libraries.insert("math".to_string(), library());
+        Instance::from_module(
+            &module,
+            mock_backend(&[]),
+            options,
+            InstanceParams {
+                libraries: Some(&libraries),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new_works() {
+        let mut names = library().function_names();
+        names.sort();
+        assert_eq!(names, ["add", "spin"]);
+    }
+
+    #[test]
+    fn new_fails_for_imports() {
+        let wasm = wat::parse_str(r#"(module (import "env" "debug" (func (param i32))))"#).unwrap();
+        match Library::new(&wasm, MEMORY_LIMIT).err().unwrap() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert_eq!(msg, "Library must not have imports, found: \"env.debug\"")
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn new_fails_for_non_integer_types() {
+        let wasm = wat::parse_str(r#"(module (func (export "get") (param externref)))"#).unwrap();
+        match Library::new(&wasm, MEMORY_LIMIT).err().unwrap() {
+            VmError::StaticValidationErr { msg, .. } => assert_eq!(
+                msg,
+                "Library export \"get\" must only use integer parameters and results"
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn library_functions_can_be_called() {
+        let instance = contract_instance(1_000_000_000_000);
+        let result = instance
+            .call_function1("add", &[Val::I64(40), Val::I64(2)])
+            .unwrap();
+        assert_eq!(result.unwrap_i64(), 42);
+    }
+
+    #[test]
+    fn library_calls_use_gas_of_the_contract() {
+        let instance = contract_instance(1_000_000_000_000);
+        instance.call_function1("spin", &[Val::I32(1)]).unwrap();
+        let used_short = instance.create_gas_report().used_internally;
+
+        let instance = contract_instance(1_000_000_000_000);
+        instance.call_function1("spin", &[Val::I32(1000)]).unwrap();
+        let used_long = instance.create_gas_report().used_internally;
+        assert!(used_long > used_short + 999 * 150_000);
+    }
+
+    #[test]
+    fn library_calls_can_run_out_of_gas() {
+        let instance = contract_instance(100_000_000);
+        let err = instance
+            .call_function1("spin", &[Val::I32(1_000_000)])
+            .unwrap_err();
+        assert!(err.to_string().contains("Ran out of gas"), "{}", err);
+        assert_eq!(instance.create_gas_report().remaining, 0);
+    }
+}
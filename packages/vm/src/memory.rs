@@ -38,11 +38,29 @@ pub fn read_region(memory: &wasmer::Memory, ptr: u32, max_length: usize) -> VmRe
         );
     }
 
-    match WasmPtr::<u8, Array>::new(region.offset).deref(memory, 0, region.length) {
+    read_region_data(memory, &region, region.length)
+}
+
+/// Like [`read_region`] but reads only the first `max_length` bytes of longer regions
+/// instead of erroring. Returns the data along with the full length of the region.
+pub fn read_region_truncated(
+    memory: &wasmer::Memory,
+    ptr: u32,
+    max_length: usize,
+) -> VmResult<(Vec<u8>, usize)> {
+    let region = get_region(memory, ptr)?;
+    let length = region.length.min(to_u32(max_length).unwrap_or(u32::MAX));
+    let data = read_region_data(memory, &region, length)?;
+    Ok((data, region.length as usize))
+}
+
+/// Copies the first `length` bytes of the region's data
+fn read_region_data(memory: &wasmer::Memory, region: &Region, length: u32) -> VmResult<Vec<u8>> {
+    match WasmPtr::<u8, Array>::new(region.offset).deref(memory, 0, length) {
         Some(cells) => {
             // In case you want to do some premature optimization, this shows how to cast a `&'mut [Cell<u8>]` to `&mut [u8]`:
             // https://github.com/wasmerio/wasmer/blob/0.13.1/lib/wasi/src/syscalls/mod.rs#L79-L81
-            let len = region.length as usize;
+            let len = length as usize;
             let mut result = vec![0u8; len];
             for i in 0..len {
                 result[i] = cells[i].get();
@@ -57,26 +75,13 @@ pub fn read_region(memory: &wasmer::Memory, ptr: u32, max_length: usize) -> VmRe
     }
 }
 
-/// maybe_read_region is like read_region, but gracefully handles null pointer (0) by returning None
-/// meant to be used where the argument is optional (like scan)
-#[cfg(feature = "iterator")]
-pub fn maybe_read_region(
-    memory: &wasmer::Memory,
-    ptr: u32,
-    max_length: usize,
-) -> VmResult<Option<Vec<u8>>> {
-    if ptr == 0 {
-        Ok(None)
-    } else {
-        read_region(memory, ptr, max_length).map(Some)
-    }
-}
-
 /// A prepared and sufficiently large memory Region is expected at ptr that points to pre-allocated memory.
 ///
 /// Returns number of bytes written on success.
 pub fn write_region(memory: &wasmer::Memory, ptr: u32, data: &[u8]) -> VmResult<()> {
     let mut region = get_region(memory, ptr)?;
+    validate_region_location(&region, ptr, memory.size().bytes().0)
+        .map_err(CommunicationError::from)?;
 
     let region_capacity = region.capacity as usize;
     if data.len() > region_capacity {
@@ -138,6 +143,40 @@ fn validate_region(region: &Region) -> RegionValidationResult<()> {
     Ok(())
 }
 
+/// Checks the location of a Region the host is going to write to against the Wasm memory it
+/// lives in. The data must be fully contained in the memory and must not overlap with the
+/// Region struct stored at `region_ptr`. Otherwise writing the data would corrupt the
+/// Region itself. Regions that are only read are not checked since only their `length`
+/// bytes are accessed.
+///
+/// This expects a Region that passed `validate_region` already.
+fn validate_region_location(
+    region: &Region,
+    region_ptr: u32,
+    memory_size: usize,
+) -> RegionValidationResult<()> {
+    let data_start = region.offset as u64;
+    let data_end = data_start + region.capacity as u64;
+    if data_end > memory_size as u64 {
+        return Err(RegionValidationError::out_of_memory_bounds(
+            region.offset,
+            region.capacity,
+            memory_size,
+        ));
+    }
+
+    let struct_start = region_ptr as u64;
+    let struct_end = struct_start + std::mem::size_of::<Region>() as u64;
+    if region.capacity != 0 && data_start < struct_end && struct_start < data_end {
+        return Err(RegionValidationError::overlaps_region_struct(
+            region_ptr,
+            region.offset,
+            region.capacity,
+        ));
+    }
+    Ok(())
+}
+
 /// Overrides a Region at ptr in wasm memory with data
 fn set_region(memory: &wasmer::Memory, ptr: u32, data: Region) -> CommunicationResult<()> {
     let wptr = WasmPtr::<Region>::new(ptr);
@@ -157,6 +196,41 @@ fn set_region(memory: &wasmer::Memory, ptr: u32, data: Region) -> CommunicationR
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::errors::VmError;
+    use crate::wasm_backend::make_runtime_store;
+    use std::fmt::Debug;
+    use wasmer::{Memory, MemoryType, Pages};
+
+    const PAGE_SIZE: usize = 65536;
+
+    /// Creates a standalone Wasm memory of one page
+    fn make_memory() -> Memory {
+        let store = make_runtime_store(None);
+        Memory::new(&store, MemoryType::new(Pages(1), None, false)).unwrap()
+    }
+
+    /// Writes a Region struct to the given address without any validation
+    fn put_region(memory: &Memory, ptr: u32, offset: u32, capacity: u32, length: u32) {
+        let region = Region {
+            offset,
+            capacity,
+            length,
+        };
+        WasmPtr::<Region>::new(ptr)
+            .deref(memory)
+            .unwrap()
+            .set(region);
+    }
+
+    fn expect_invalid_region(result: VmResult<impl Debug>) -> RegionValidationError {
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source: CommunicationError::InvalidRegion { source, .. },
+                ..
+            } => source,
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
 
     #[test]
     fn validate_region_passes_for_valid_region() {
@@ -234,6 +308,214 @@ mod tests {
         }
     }
 
+    #[test]
+    fn validate_region_location_passes_for_valid_location() {
+        // data before struct
+        let region = Region {
+            offset: 100,
+            capacity: 50,
+            length: 50,
+        };
+        validate_region_location(&region, 150, PAGE_SIZE).unwrap();
+
+        // data after struct
+        let region = Region {
+            offset: 112,
+            capacity: 50,
+            length: 0,
+        };
+        validate_region_location(&region, 100, PAGE_SIZE).unwrap();
+
+        // data at end of memory
+        let region = Region {
+            offset: PAGE_SIZE as u32 - 10,
+            capacity: 10,
+            length: 10,
+        };
+        validate_region_location(&region, 8, PAGE_SIZE).unwrap();
+
+        // empty data never overlaps
+        let region = Region {
+            offset: 104,
+            capacity: 0,
+            length: 0,
+        };
+        validate_region_location(&region, 100, PAGE_SIZE).unwrap();
+    }
+
+    #[test]
+    fn validate_region_location_fails_when_exceeding_memory() {
+        let region = Region {
+            offset: PAGE_SIZE as u32 - 10,
+            capacity: 11,
+            length: 0,
+        };
+        match validate_region_location(&region, 8, PAGE_SIZE).unwrap_err() {
+            RegionValidationError::OutOfMemoryBounds {
+                offset,
+                capacity,
+                memory_size,
+                ..
+            } => {
+                assert_eq!(offset, PAGE_SIZE as u32 - 10);
+                assert_eq!(capacity, 11);
+                assert_eq!(memory_size, PAGE_SIZE);
+            }
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+
+        let region = Region {
+            offset: PAGE_SIZE as u32,
+            capacity: 1,
+            length: 1,
+        };
+        match validate_region_location(&region, 8, PAGE_SIZE).unwrap_err() {
+            RegionValidationError::OutOfMemoryBounds { .. } => {}
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn validate_region_location_fails_when_overlapping_struct() {
+        let cases = [
+            // data contains struct
+            (90, 40),
+            // data starts inside of struct
+            (104, 40),
+            // data ends inside of struct
+            (80, 21),
+            // data is struct
+            (100, 12),
+            // data inside of struct
+            (104, 4),
+        ];
+        for (offset, capacity) in cases {
+            let region = Region {
+                offset,
+                capacity,
+                length: 0,
+            };
+            match validate_region_location(&region, 100, PAGE_SIZE).unwrap_err() {
+                RegionValidationError::OverlapsRegionStruct {
+                    region_ptr,
+                    offset: err_offset,
+                    capacity: err_capacity,
+                    ..
+                } => {
+                    assert_eq!(region_ptr, 100);
+                    assert_eq!(err_offset, offset);
+                    assert_eq!(err_capacity, capacity);
+                }
+                e => panic!("Got unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn read_region_works() {
+        let memory = make_memory();
+        put_region(&memory, 100, 200, 10, 3);
+        write_region(&memory, 100, b"abc").unwrap();
+        assert_eq!(read_region(&memory, 100, 10).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn read_region_truncated_works() {
+        let memory = make_memory();
+        put_region(&memory, 100, 200, 10, 3);
+        write_region(&memory, 100, b"abc").unwrap();
+        assert_eq!(
+            read_region_truncated(&memory, 100, 10).unwrap(),
+            (b"abc".to_vec(), 3)
+        );
+        assert_eq!(
+            read_region_truncated(&memory, 100, 3).unwrap(),
+            (b"abc".to_vec(), 3)
+        );
+        assert_eq!(
+            read_region_truncated(&memory, 100, 2).unwrap(),
+            (b"ab".to_vec(), 3)
+        );
+        assert_eq!(
+            read_region_truncated(&memory, 100, 0).unwrap(),
+            (b"".to_vec(), 3)
+        );
+
+        // invalid regions still error
+        put_region(&memory, 100, 0, 10, 5);
+        match expect_invalid_region(read_region_truncated(&memory, 100, 2)) {
+            RegionValidationError::ZeroOffset { .. } => {}
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_region_ignores_capacity_exceeding_memory() {
+        let memory = make_memory();
+        // length is in bounds but capacity is not
+        put_region(&memory, 100, PAGE_SIZE as u32 - 10, 20, 5);
+        assert_eq!(read_region(&memory, 100, 10).unwrap(), [0; 5]);
+
+        // data overlapping with the Region struct can be read
+        put_region(&memory, 100, 96, 32, 8);
+        assert_eq!(read_region(&memory, 100, 10).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn read_region_fails_for_region_ptr_exceeding_memory() {
+        let memory = make_memory();
+        let result = read_region(&memory, PAGE_SIZE as u32 - 4, 10);
+        match result.unwrap_err() {
+            VmError::CommunicationErr {
+                source: CommunicationError::DerefErr { offset, .. },
+                ..
+            } => assert_eq!(offset, PAGE_SIZE as u32 - 4),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_region_fails_for_invalid_region() {
+        let memory = make_memory();
+        put_region(&memory, 100, 200, 10, 11);
+        match expect_invalid_region(read_region(&memory, 100, 20)) {
+            RegionValidationError::LengthExceedsCapacity { .. } => {}
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+
+        put_region(&memory, 100, 0, 10, 5);
+        match expect_invalid_region(read_region(&memory, 100, 20)) {
+            RegionValidationError::ZeroOffset { .. } => {}
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn write_region_fails_for_region_overlapping_struct() {
+        let memory = make_memory();
+        put_region(&memory, 100, 96, 32, 0);
+        match expect_invalid_region(write_region(&memory, 100, b"overwrite the region")) {
+            RegionValidationError::OverlapsRegionStruct { region_ptr, .. } => {
+                assert_eq!(region_ptr, 100)
+            }
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+        // Region is unchanged
+        let cell = WasmPtr::<Region>::new(100).deref(&memory).unwrap();
+        assert_eq!(cell.get().offset, 96);
+        assert_eq!(cell.get().length, 0);
+    }
+
+    #[test]
+    fn write_region_fails_for_region_exceeding_memory() {
+        let memory = make_memory();
+        put_region(&memory, 100, PAGE_SIZE as u32 - 2, 8, 0);
+        match expect_invalid_region(write_region(&memory, 100, b"ab")) {
+            RegionValidationError::OutOfMemoryBounds { .. } => {}
+            e => panic!("Got unexpected error: {:?}", e),
+        }
+    }
+
     #[test]
     fn validate_region_fails_when_exceeding_address_space() {
         let region = Region {
@@ -1,8 +1,11 @@
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use thiserror::Error;
 
-use wasmer::{DeserializeError, Module, Store};
+use wasmer::{Module, Store};
 
 use crate::checksum::Checksum;
 use crate::errors::{VmError, VmResult};
@@ -38,16 +41,27 @@ use crate::modules::current_wasmer_module_version;
 ///   the module header version (<https://github.com/wasmerio/wasmer/issues/3193>). In cosmwasm-vm 1.1.0-1.1.1
 ///   the old value "v3" is still used along with Wasmer 2.3.0 (bug). From cosmwasm 1.1.2 onwards, this is
 ///   fixed by bumping to "v4".
-const MODULE_SERIALIZATION_VERSION: &str = "v4";
+/// - **v5**:<br>
+///   The serialized module is prefixed with its SHA-256 hash, which is verified when loading.
+const MODULE_SERIALIZATION_VERSION: &str = "v5";
+
+/// The length of the hash in front of the serialized module
+const HASH_LENGTH: usize = 32;
 
 /// Representation of a directory that contains compiled Wasm artifacts.
 pub struct FileSystemCache {
     /// The base path this cache operates in. Within this path, versioned directories are created.
     /// A sophisticated version of this cache might be able to read multiple input versions in the future.
     base_path: PathBuf,
+    /// A second directory with the same layout as `base_path` which is used for modules that
+    /// are not found in `base_path`. This is never written to.
+    fallback_path: Option<PathBuf>,
     wasmer_module_version: u32,
 }
 
+/// Makes the names of temporary files unique within the process
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 /// An error type that hides system specific error information
 /// to ensure deterministic errors across operating systems.
 #[derive(Error, Debug)]
@@ -70,6 +84,8 @@ impl FileSystemCache {
     ///
     /// This method is unsafe because there's no way to ensure the artifacts
     /// stored in this cache haven't been corrupted or tampered with.
+    /// The hashes stored along with the artifacts detect accidental corruption,
+    /// but anyone who can write to this directory can replace both.
     pub unsafe fn new(path: impl Into<PathBuf>) -> Result<Self, NewFileSystemCacheError> {
         let wasmer_module_version = current_wasmer_module_version();
 
@@ -82,6 +98,7 @@ impl FileSystemCache {
                 if !metadata.permissions().readonly() {
                     Ok(Self {
                         base_path: path,
+                        fallback_path: None,
                         wasmer_module_version,
                     })
                 } else {
@@ -95,58 +112,136 @@ impl FileSystemCache {
             mkdir_p(&path).map_err(|_e| NewFileSystemCacheError::CouldntCreatePath)?;
             Ok(Self {
                 base_path: path,
+                fallback_path: None,
                 wasmer_module_version,
             })
         }
     }
 
-    /// Loads a serialized module from the file system and returns a module (i.e. artifact + store),
-    /// along with the size of the serialized module.
+    /// Uses the given directory as a read-only fallback for modules that are not found in this cache,
+    /// e.g. the modules directory of another node on a shared file system. The fallback directory
+    /// uses the same layout as this cache and does not need to exist.
+    ///
+    /// Modules loaded from the fallback directory are copied into this cache, such that subsequent
+    /// loads do not need the fallback directory anymore. New modules are only stored in this cache.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe for the same reason as [`FileSystemCache::new`]. The artifacts in
+    /// the fallback directory are loaded without further checks.
+    pub unsafe fn with_fallback(mut self, fallback_path: impl Into<PathBuf>) -> Self {
+        self.fallback_path = Some(fallback_path.into());
+        self
+    }
+
+    /// Loads a serialized module from the file system and returns a module (i.e. artifact + store).
+    ///
+    /// The hash stored along with the serialized module is verified before deserialization.
+    /// If it does not match, an integrity error is returned.
     pub fn load(&self, checksum: &Checksum, store: &Store) -> VmResult<Option<Module>> {
         let filename = checksum.to_hex();
-        let file_path = self.latest_modules_path().join(filename);
+        let modules_dir = self.latest_modules_path();
 
-        let result = unsafe { Module::deserialize_from_file(store, &file_path) };
-        match result {
-            Ok(module) => Ok(Some(module)),
-            Err(DeserializeError::Io(err)) => match err.kind() {
-                io::ErrorKind::NotFound => Ok(None),
-                _ => Err(VmError::cache_err(format!(
-                    "Error opening module file: {}",
-                    err
-                ))),
-            },
-            Err(err) => Err(VmError::cache_err(format!(
-                "Error deserializing module: {}",
-                err
-            ))),
+        if let Some(content) = read_module_file(&modules_dir.join(&filename))? {
+            return deserialize_module(store, &content[HASH_LENGTH..]).map(Some);
+        }
+
+        if let Some(fallback_path) = &self.fallback_path {
+            let fallback_file_path = self.modules_path(fallback_path).join(&filename);
+            if let Some(content) = read_module_file(&fallback_file_path)? {
+                // Copying is only an optimization, so errors are ignored here. The module is
+                // then loaded from the fallback directory again next time.
+                let _ = write_file_atomically(&modules_dir, &filename, &content);
+                return deserialize_module(store, &content[HASH_LENGTH..]).map(Some);
+            }
         }
+
+        Ok(None)
+    }
+
+    /// Returns true if and only if a module for the given checksum is stored in this cache.
+    /// This does neither check the fallback directory nor the integrity of the module.
+    pub fn has(&self, checksum: &Checksum) -> bool {
+        self.latest_modules_path().join(checksum.to_hex()).is_file()
     }
 
-    /// Stores a serialized module to the file system. Returns the size of the serialized module.
+    /// Stores a serialized module to the file system, prefixed with the hash of the serialization.
     pub fn store(&mut self, checksum: &Checksum, module: &Module) -> VmResult<()> {
-        let modules_dir = self.latest_modules_path();
-        mkdir_p(&modules_dir)
-            .map_err(|_e| VmError::cache_err("Error creating modules directory"))?;
+        let serialized = module
+            .serialize()
+            .map_err(|e| VmError::cache_err(format!("Error serializing module: {}", e)))?;
+        let mut content: Vec<u8> = Checksum::generate(&serialized).into();
+        content.extend_from_slice(&serialized);
 
-        let filename = checksum.to_hex();
-        let path = modules_dir.join(filename);
-        module
-            .serialize_to_file(path)
+        let modules_dir = self.latest_modules_path();
+        write_file_atomically(&modules_dir, &checksum.to_hex(), &content)
             .map_err(|e| VmError::cache_err(format!("Error writing module to disk: {}", e)))?;
         Ok(())
     }
 
     /// The path to the latest version of the modules.
     fn latest_modules_path(&self) -> PathBuf {
+        self.modules_path(&self.base_path)
+    }
+
+    /// The path to the latest version of the modules within the given base path.
+    fn modules_path(&self, base_path: &Path) -> PathBuf {
         let version = format!(
             "{}-wasmer{}",
             MODULE_SERIALIZATION_VERSION, self.wasmer_module_version
         );
-        self.base_path.join(version)
+        base_path.join(version)
     }
 }
 
+/// Reads a module file and verifies the hash of the serialized module it contains.
+/// Returns `None` if the file does not exist.
+fn read_module_file(file_path: &Path) -> VmResult<Option<Vec<u8>>> {
+    let content = match fs::read(file_path) {
+        Ok(content) => content,
+        Err(err) => {
+            return match err.kind() {
+                io::ErrorKind::NotFound => Ok(None),
+                _ => Err(VmError::cache_err(format!(
+                    "Error opening module file: {}",
+                    err
+                ))),
+            }
+        }
+    };
+
+    if content.len() < HASH_LENGTH {
+        return Err(VmError::integrity_err());
+    }
+    let (hash, serialized) = content.split_at(HASH_LENGTH);
+    if Checksum::generate(serialized) != Checksum::try_from(hash)? {
+        return Err(VmError::integrity_err());
+    }
+    Ok(Some(content))
+}
+
+fn deserialize_module(store: &Store, serialized: &[u8]) -> VmResult<Module> {
+    unsafe { Module::deserialize(store, serialized) }
+        .map_err(|e| VmError::cache_err(format!("Error deserializing module: {}", e)))
+}
+
+/// Writes a file via a temporary file in the destination directory, such that concurrent
+/// readers never see a partially written file.
+fn write_file_atomically(dir: &Path, filename: &str, content: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let tmp_path = dir.join(format!(
+        ".tmp-{}-{}",
+        process::id(),
+        TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result =
+        fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, dir.join(filename)));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -205,6 +300,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn file_system_cache_has_works() {
+        let tmp_dir = TempDir::new().unwrap();
+        let mut cache = unsafe { FileSystemCache::new(tmp_dir.path()).unwrap() };
+
+        let wasm = wat::parse_str(SOME_WAT).unwrap();
+        let checksum = Checksum::generate(&wasm);
+        assert!(!cache.has(&checksum));
+
+        let module = compile(&wasm, None, &[]).unwrap();
+        cache.store(&checksum, &module).unwrap();
+        assert!(cache.has(&checksum));
+    }
+
     #[test]
     fn file_system_cache_store_uses_expected_path() {
         let tmp_dir = TempDir::new().unwrap();
@@ -219,10 +328,108 @@ mod tests {
         cache.store(&checksum, &module).unwrap();
 
         let file_path = format!(
-            "{}/v4-wasmer1/{}",
+            "{}/v5-wasmer1/{}",
             tmp_dir.path().to_string_lossy(),
             checksum
         );
-        let _serialized_module = fs::read(file_path).unwrap();
+        let content = fs::read(file_path).unwrap();
+
+        // The serialized module is prefixed with its hash
+        let (hash, serialized_module) = content.split_at(HASH_LENGTH);
+        assert_eq!(
+            Checksum::generate(serialized_module).to_hex(),
+            hex::encode(hash)
+        );
+    }
+
+    #[test]
+    fn file_system_cache_load_errors_for_corrupted_module() {
+        let tmp_dir = TempDir::new().unwrap();
+        let mut cache = unsafe { FileSystemCache::new(tmp_dir.path()).unwrap() };
+
+        // Create module
+        let wasm = wat::parse_str(SOME_WAT).unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        // Store module
+        let module = compile(&wasm, None, &[]).unwrap();
+        cache.store(&checksum, &module).unwrap();
+
+        // Corrupt the serialized module
+        let file_path = cache.latest_modules_path().join(checksum.to_hex());
+        let mut content = fs::read(&file_path).unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 0x01;
+        fs::write(&file_path, &content).unwrap();
+
+        let store = make_runtime_store(TESTING_MEMORY_LIMIT);
+        match cache.load(&checksum, &store).unwrap_err() {
+            VmError::IntegrityErr { .. } => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // Truncate the file to less than the hash
+        fs::write(&file_path, &content[..10]).unwrap();
+        match cache.load(&checksum, &store).unwrap_err() {
+            VmError::IntegrityErr { .. } => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn file_system_cache_loads_from_fallback() {
+        let tmp_dir = TempDir::new().unwrap();
+        let fallback_dir = TempDir::new().unwrap();
+
+        // Create module
+        let wasm = wat::parse_str(SOME_WAT).unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        // Store module in fallback
+        let mut fallback = unsafe { FileSystemCache::new(fallback_dir.path()).unwrap() };
+        let module = compile(&wasm, None, &[]).unwrap();
+        fallback.store(&checksum, &module).unwrap();
+
+        let cache = unsafe {
+            FileSystemCache::new(tmp_dir.path())
+                .unwrap()
+                .with_fallback(fallback_dir.path())
+        };
+        let local_file_path = cache.latest_modules_path().join(checksum.to_hex());
+        assert!(!local_file_path.exists());
+
+        // Load module from fallback, which creates a local copy
+        let store = make_runtime_store(TESTING_MEMORY_LIMIT);
+        let cached = cache.load(&checksum, &store).unwrap();
+        assert!(cached.is_some());
+        assert!(local_file_path.is_file());
+
+        // Load module from local copy
+        fs::remove_dir_all(fallback_dir.path()).unwrap();
+        let store = make_runtime_store(TESTING_MEMORY_LIMIT);
+        let cached = cache.load(&checksum, &store).unwrap();
+        assert!(cached.is_some());
+
+        // Only the module remains in the local directory
+        let entries = fs::read_dir(cache.latest_modules_path()).unwrap().count();
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn file_system_cache_returns_none_if_not_in_fallback() {
+        let tmp_dir = TempDir::new().unwrap();
+        let fallback_dir = TempDir::new().unwrap();
+        let cache = unsafe {
+            FileSystemCache::new(tmp_dir.path())
+                .unwrap()
+                .with_fallback(fallback_dir.path().join("non-existent"))
+        };
+
+        let wasm = wat::parse_str(SOME_WAT).unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        let store = make_runtime_store(TESTING_MEMORY_LIMIT);
+        let cached = cache.load(&checksum, &store).unwrap();
+        assert!(cached.is_none());
     }
 }
@@ -49,8 +49,13 @@ impl InMemoryCache {
         }
     }
 
+    /// Stores a module with the given size (in bytes) in the cache, which evicts the least recently
+    /// used modules if needed. Modules that do not fit into the cache at all are not stored.
     pub fn store(&mut self, checksum: &Checksum, module: Module, size: usize) -> VmResult<()> {
         if let Some(modules) = &mut self.modules {
+            if size >= modules.capacity() {
+                return Ok(());
+            }
             modules
                 .put_with_weight(*checksum, SizedModule { module, size })
                 .map_err(|e| VmError::cache_err(format!("{:?}", e)))?;
@@ -289,4 +294,29 @@ mod tests {
             .unwrap();
         assert_eq!(cache.size(), 1_500_000);
     }
+
+    #[test]
+    fn store_skips_modules_bigger_than_the_cache() {
+        let mut cache = InMemoryCache::new(Size::mebi(2));
+
+        // Create module
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t0 (func (param i32) (result i32)))
+            (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+                get_local $p0
+                i32.const 1
+                i32.add)
+            )"#,
+        )
+        .unwrap();
+        let checksum = Checksum::generate(&wasm);
+
+        cache
+            .store(&checksum, compile(&wasm, None, &[]).unwrap(), 3_000_000)
+            .unwrap();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.size(), 0);
+        assert!(cache.load(&checksum).unwrap().is_none());
+    }
 }
@@ -30,7 +30,7 @@ impl PinnedMemoryCache {
     }
 
     /// Looks up a module in the cache and creates a new module
-    pub fn load(&mut self, checksum: &Checksum) -> VmResult<Option<Module>> {
+    pub fn load(&self, checksum: &Checksum) -> VmResult<Option<Module>> {
         match self.modules.get(checksum) {
             Some(module) => Ok(Some(module.module.clone())),
             None => Ok(None),
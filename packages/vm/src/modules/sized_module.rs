@@ -1,7 +1,34 @@
 use wasmer::Module;
 
+/// The in-memory size of a compiled module relative to the size of its Wasm bytecode.
+///
+/// This is slightly above the ratios of 26–30 that `examples/module_size.rs` reports for the
+/// contracts in `testdata`.
+const MODULE_SIZE_FACTOR: usize = 30;
+
 #[derive(Debug, Clone)]
 pub struct SizedModule {
     pub module: Module,
     pub size: usize,
 }
+
+/// Estimates the memory usage of a compiled module in bytes from the size of its Wasm bytecode.
+///
+/// In contrast to measuring the module, this is cheap and returns the same value on every
+/// machine, such that the memory caches hold the same modules across nodes.
+pub fn module_size_estimate(wasm_size: usize) -> usize {
+    wasm_size.saturating_mul(MODULE_SIZE_FACTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_size_estimate_works() {
+        assert_eq!(module_size_estimate(0), 0);
+        assert_eq!(module_size_estimate(1), 30);
+        assert_eq!(module_size_estimate(200_000), 6_000_000);
+        assert_eq!(module_size_estimate(usize::MAX), usize::MAX);
+    }
+}
@@ -0,0 +1,301 @@
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, Record};
+
+#[cfg(feature = "iterator")]
+use crate::backend::BackendError;
+use crate::backend::{BackendResult, Storage};
+
+/// A [`Storage`] adapter that prepends a fixed prefix to all keys before passing them to
+/// the inner storage and strips it from keys returned by iterators.
+///
+/// This allows embedders to store the state of multiple contracts in a single key-value
+/// database. Range bounds are translated such that a contract only ever iterates over its
+/// own namespace.
+///
+/// Isolation requires that no prefix in use is a prefix of another one, e.g. because all
+/// prefixes have the same length or are length-prefixed themselves.
+#[derive(Debug, Clone)]
+pub struct PrefixedStorage<S: Storage> {
+    inner: S,
+    prefix: Vec<u8>,
+}
+
+impl<S: Storage> PrefixedStorage<S> {
+    pub fn new(inner: S, prefix: &[u8]) -> Self {
+        PrefixedStorage {
+            inner,
+            prefix: prefix.to_vec(),
+        }
+    }
+
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the inner storage, e.g. to wrap it with a different prefix.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn prefixed(&self, key: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.prefix.len() + key.len());
+        out.extend_from_slice(&self.prefix);
+        out.extend_from_slice(key);
+        out
+    }
+
+    /// The bounds of the given range within the prefixed namespace. Unbounded ends are
+    /// limited to the namespace. Since both bounds get the same prefix, empty ranges
+    /// remain empty.
+    #[cfg(feature = "iterator")]
+    fn prefixed_bounds(
+        &self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> (Vec<u8>, Option<Vec<u8>>) {
+        let start = self.prefixed(start.unwrap_or_default());
+        let end = match end {
+            Some(end) => Some(self.prefixed(end)),
+            None => namespace_upper_bound(&self.prefix),
+        };
+        (start, end)
+    }
+}
+
+/// Returns the smallest key that is greater than all keys starting with `prefix`, or
+/// `None` if there is no such key (i.e. the prefix consists of 0xFF bytes only).
+#[cfg(feature = "iterator")]
+pub(crate) fn namespace_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut bound = prefix.to_vec();
+    while let Some(last) = bound.pop() {
+        if last < 0xFF {
+            bound.push(last + 1);
+            return Some(bound);
+        }
+    }
+    None
+}
+
+impl<S: Storage> Storage for PrefixedStorage<S> {
+    fn get(&self, key: &[u8]) -> BackendResult<Option<Vec<u8>>> {
+        self.inner.get(&self.prefixed(key))
+    }
+
+    #[cfg(feature = "iterator")]
+    fn scan(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> BackendResult<u32> {
+        let (start, end) = self.prefixed_bounds(start, end);
+        self.inner.scan(Some(&start), end.as_deref(), order)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>> {
+        let (result, gas_info) = self.inner.next(iterator_id);
+        let result = match result {
+            Ok(Some((key, value))) => match key.strip_prefix(self.prefix.as_slice()) {
+                Some(key) => Ok(Some((key.to_vec(), value))),
+                None => Err(BackendError::unknown(
+                    "Iterator returned a key outside of the storage prefix",
+                )),
+            },
+            other => other,
+        };
+        (result, gas_info)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn close_iterator(&mut self, iterator_id: u32) -> BackendResult<()> {
+        self.inner.close_iterator(iterator_id)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range_with_limit(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        limit: u32,
+    ) -> BackendResult<u32> {
+        let (start, end) = self.prefixed_bounds(start, end);
+        self.inner
+            .range_with_limit(Some(&start), end.as_deref(), order, limit)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()> {
+        let key = self.prefixed(key);
+        self.inner.set(&key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> BackendResult<()> {
+        let key = self.prefixed(key);
+        self.inner.remove(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockStorage;
+
+    #[test]
+    fn get_set_and_remove_use_prefix() {
+        let mut store = PrefixedStorage::new(MockStorage::new(), b"c1");
+        store.set(b"foo", b"bar").0.unwrap();
+        assert_eq!(store.get(b"foo").0.unwrap(), Some(b"bar".to_vec()));
+
+        let inner = store.into_inner();
+        assert_eq!(inner.get(b"c1foo").0.unwrap(), Some(b"bar".to_vec()));
+        assert_eq!(inner.get(b"foo").0.unwrap(), None);
+
+        let mut store = PrefixedStorage::new(inner, b"c1");
+        store.remove(b"foo").0.unwrap();
+        assert_eq!(store.get(b"foo").0.unwrap(), None);
+        assert_eq!(store.inner().get(b"c1foo").0.unwrap(), None);
+    }
+
+    #[test]
+    fn namespaces_are_isolated() {
+        let mut store = PrefixedStorage::new(MockStorage::new(), b"c1");
+        store.set(b"foo", b"one").0.unwrap();
+
+        let mut store = PrefixedStorage::new(store.into_inner(), b"c2");
+        assert_eq!(store.get(b"foo").0.unwrap(), None);
+        store.set(b"foo", b"two").0.unwrap();
+
+        let store = PrefixedStorage::new(store.into_inner(), b"c1");
+        assert_eq!(store.get(b"foo").0.unwrap(), Some(b"one".to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn namespace_upper_bound_works() {
+        assert_eq!(namespace_upper_bound(b"c1"), Some(b"c2".to_vec()));
+        assert_eq!(namespace_upper_bound(&[0x01, 0xFF]), Some(vec![0x02]));
+        assert_eq!(namespace_upper_bound(&[0xFF, 0xFF]), None);
+        assert_eq!(namespace_upper_bound(b""), None);
+    }
+
+    #[cfg(feature = "iterator")]
+    fn collect<S: Storage>(store: &mut PrefixedStorage<S>, iterator_id: u32) -> Vec<Record> {
+        let mut out = Vec::new();
+        while let Some(record) = store.next(iterator_id).0.unwrap() {
+            out.push(record);
+        }
+        out
+    }
+
+    #[cfg(feature = "iterator")]
+    fn setup_namespaces() -> PrefixedStorage<MockStorage> {
+        let mut inner = MockStorage::new();
+        inner.set(b"c0zzz", b"before").0.unwrap();
+        inner.set(b"c1", b"empty key").0.unwrap();
+        inner.set(b"c1ant", b"hill").0.unwrap();
+        inner.set(b"c1foo", b"bar").0.unwrap();
+        inner.set(b"c1ze", b"bra").0.unwrap();
+        inner.set(b"c2aaa", b"after").0.unwrap();
+        PrefixedStorage::new(inner, b"c1")
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_is_limited_to_namespace() {
+        let mut store = setup_namespaces();
+
+        let id = store.scan(None, None, Order::Ascending).0.unwrap();
+        assert_eq!(
+            collect(&mut store, id),
+            vec![
+                (b"".to_vec(), b"empty key".to_vec()),
+                (b"ant".to_vec(), b"hill".to_vec()),
+                (b"foo".to_vec(), b"bar".to_vec()),
+                (b"ze".to_vec(), b"bra".to_vec()),
+            ]
+        );
+
+        let id = store.scan(None, None, Order::Descending).0.unwrap();
+        let keys: Vec<Vec<u8>> = collect(&mut store, id).into_iter().map(|r| r.0).collect();
+        assert_eq!(
+            keys,
+            vec![
+                b"ze".to_vec(),
+                b"foo".to_vec(),
+                b"ant".to_vec(),
+                b"".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_translates_bounds() {
+        let mut store = setup_namespaces();
+
+        let id = store
+            .scan(Some(b"b"), Some(b"zz"), Order::Ascending)
+            .0
+            .unwrap();
+        let keys: Vec<Vec<u8>> = collect(&mut store, id).into_iter().map(|r| r.0).collect();
+        assert_eq!(keys, vec![b"foo".to_vec(), b"ze".to_vec()]);
+
+        let id = store.scan(Some(b"foo"), None, Order::Ascending).0.unwrap();
+        let keys: Vec<Vec<u8>> = collect(&mut store, id).into_iter().map(|r| r.0).collect();
+        assert_eq!(keys, vec![b"foo".to_vec(), b"ze".to_vec()]);
+
+        let id = store.scan(None, Some(b"foo"), Order::Descending).0.unwrap();
+        let keys: Vec<Vec<u8>> = collect(&mut store, id).into_iter().map(|r| r.0).collect();
+        assert_eq!(keys, vec![b"ant".to_vec(), b"".to_vec()]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_with_limit_translates_bounds() {
+        let mut store = setup_namespaces();
+
+        let id = store
+            .range_with_limit(Some(b"a"), None, Order::Ascending, 2)
+            .0
+            .unwrap();
+        let keys: Vec<Vec<u8>> = collect(&mut store, id).into_iter().map(|r| r.0).collect();
+        assert_eq!(keys, vec![b"ant".to_vec(), b"foo".to_vec()]);
+
+        // empty ranges do not leak into other namespaces
+        let id = store
+            .range_with_limit(Some(b"zz"), Some(b"a"), Order::Ascending, 10)
+            .0
+            .unwrap();
+        assert_eq!(collect(&mut store, id), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_works_for_max_prefix() {
+        let mut inner = MockStorage::new();
+        inner.set(&[0xFE, 0x01], b"other").0.unwrap();
+        inner.set(&[0xFF, 0x01], b"mine").0.unwrap();
+        let mut store = PrefixedStorage::new(inner, &[0xFF]);
+
+        let id = store.scan(None, None, Order::Ascending).0.unwrap();
+        assert_eq!(
+            collect(&mut store, id),
+            vec![(vec![0x01], b"mine".to_vec())]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn next_fails_for_unknown_iterator() {
+        let mut store = setup_namespaces();
+        match store.next(42).0.unwrap_err() {
+            BackendError::IteratorDoesNotExist { id } => assert_eq!(id, 42),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+}
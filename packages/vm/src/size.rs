@@ -1,4 +1,8 @@
-#[derive(Copy, Clone, Debug)]
+use std::str::FromStr;
+
+use crate::errors::VmError;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Size(pub usize);
 
 impl Size {
@@ -33,6 +37,43 @@ impl Size {
     }
 }
 
+/// Parses sizes like "512Mi" or "2G", such that limits can be expressed naturally in
+/// config files. Supported units are k, Ki, M, Mi, G and Gi. Without a unit, the number
+/// is a number of bytes.
+impl FromStr for Size {
+    type Err = VmError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let unit_start = input
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len());
+        let (number, unit) = input.split_at(unit_start);
+        let number: usize = number
+            .parse()
+            .map_err(|_| VmError::generic_err(format!("Invalid size: \"{}\"", input)))?;
+        let factor = match unit.trim_start() {
+            "" => 1,
+            "k" => Size::kilo(1).0,
+            "Ki" => Size::kibi(1).0,
+            "M" => Size::mega(1).0,
+            "Mi" => Size::mebi(1).0,
+            "G" => Size::giga(1).0,
+            "Gi" => Size::gibi(1).0,
+            unit => {
+                return Err(VmError::generic_err(format!(
+                    "Invalid size unit \"{}\" in \"{}\"",
+                    unit, input
+                )))
+            }
+        };
+        number
+            .checked_mul(factor)
+            .map(Size)
+            .ok_or_else(|| VmError::generic_err(format!("Size \"{}\" is too large", input)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,4 +109,27 @@ mod tests {
         assert_eq!(format!("{:?}", Size::kibi(2)), "Size(2048)");
         assert_eq!(format!("{:?}", Size::mebi(1)), "Size(1048576)");
     }
+
+    #[test]
+    fn from_str_works() {
+        assert_eq!("0".parse::<Size>().unwrap(), Size(0));
+        assert_eq!("1234".parse::<Size>().unwrap(), Size(1234));
+        assert_eq!("3k".parse::<Size>().unwrap(), Size::kilo(3));
+        assert_eq!("3Ki".parse::<Size>().unwrap(), Size::kibi(3));
+        assert_eq!("512M".parse::<Size>().unwrap(), Size::mega(512));
+        assert_eq!("512Mi".parse::<Size>().unwrap(), Size::mebi(512));
+        assert_eq!("2G".parse::<Size>().unwrap(), Size::giga(2));
+        assert_eq!("2Gi".parse::<Size>().unwrap(), Size::gibi(2));
+        // whitespace is ignored
+        assert_eq!(" 16 Mi ".parse::<Size>().unwrap(), Size::mebi(16));
+    }
+
+    #[test]
+    fn from_str_fails_for_invalid_input() {
+        for input in ["", "Mi", "-1", "1.5Mi", "16 MiB", "16mi", "16 Mi Mi"] {
+            assert!(input.parse::<Size>().is_err(), "{}", input);
+        }
+        let err = format!("{}Gi", usize::MAX).parse::<Size>().unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
 }
@@ -1,6 +1,11 @@
-use parity_wasm::elements::{deserialize_buffer, Internal, Module};
-use std::collections::HashSet;
+use parity_wasm::elements::{
+    deserialize_buffer, BlockType, External, FunctionType, ImportCountType, Instruction, Internal,
+    Module, Type, ValueType,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
+use crate::capabilities::REQUIRES_PREFIX;
+use crate::compatibility::INTERFACE_VERSION_PREFIX;
 use crate::errors::{VmError, VmResult};
 
 pub const REQUIRED_IBC_EXPORTS: &[&str] = &[
@@ -12,6 +17,10 @@ pub const REQUIRED_IBC_EXPORTS: &[&str] = &[
     "ibc_packet_timeout",
 ];
 
+/// The entry points called by the IBC callbacks middleware. Unlike the [`REQUIRED_IBC_EXPORTS`],
+/// those can be exported independently of each other.
+pub const IBC_CALLBACK_EXPORTS: &[&str] = &["ibc_source_callback", "ibc_destination_callback"];
+
 pub fn deserialize_wasm(wasm_code: &[u8]) -> VmResult<Module> {
     deserialize_buffer(wasm_code).map_err(|err| {
         VmError::static_validation_err(format!(
@@ -72,14 +81,222 @@ impl ExportInfo for wasmer::Module {
     }
 }
 
-/// Returns true if and only if all IBC entry points ([`REQUIRED_IBC_EXPORTS`])
-/// exist as exported functions. This does not guarantee the entry points
-/// are functional and for simplicity does not even check their signatures.
-pub fn has_ibc_entry_points(module: &impl ExportInfo) -> bool {
+/// The IBC entry points ([`REQUIRED_IBC_EXPORTS`]) exported by a contract
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum IbcEntryPoints {
+    /// All IBC entry points are exported
+    Complete,
+    /// Some but not all IBC entry points are exported. Contains the missing ones.
+    Partial(Vec<String>),
+    /// No IBC entry point is exported
+    None,
+}
+
+/// Checks which IBC entry points ([`REQUIRED_IBC_EXPORTS`]) exist as exported functions.
+/// This does not guarantee the entry points are functional and does not check their signatures.
+pub fn ibc_entry_points(module: &impl ExportInfo) -> IbcEntryPoints {
     let available_exports = module.exported_function_names(None);
-    REQUIRED_IBC_EXPORTS
+    let missing: Vec<String> = REQUIRED_IBC_EXPORTS
+        .iter()
+        .filter(|required| !available_exports.contains(**required))
+        .map(|required| required.to_string())
+        .collect();
+    if missing.is_empty() {
+        IbcEntryPoints::Complete
+    } else if missing.len() == REQUIRED_IBC_EXPORTS.len() {
+        IbcEntryPoints::None
+    } else {
+        IbcEntryPoints::Partial(missing)
+    }
+}
+
+/// Looks up the type of an exported function. The function index space starts
+/// with imported functions, followed by the ones defined in the module.
+pub(crate) fn exported_function_type<'a>(
+    module: &'a Module,
+    name: &str,
+) -> Option<&'a FunctionType> {
+    let function_index = module
+        .export_section()?
+        .entries()
         .iter()
-        .all(|required| available_exports.contains(*required))
+        .find_map(|entry| match entry.internal() {
+            Internal::Function(index) if entry.field() == name => Some(*index as usize),
+            _ => None,
+        })?;
+
+    let imported_count = module.import_count(ImportCountType::Function);
+    let type_index = if function_index < imported_count {
+        module
+            .import_section()?
+            .entries()
+            .iter()
+            .filter_map(|entry| match entry.external() {
+                External::Function(type_index) => Some(*type_index),
+                _ => None,
+            })
+            .nth(function_index)?
+    } else {
+        module
+            .function_section()?
+            .entries()
+            .get(function_index - imported_count)?
+            .type_ref()
+    };
+
+    match module.type_section()?.types().get(type_index as usize)? {
+        Type::Function(function_type) => Some(function_type),
+    }
+}
+
+/// Returns the full names (`module.field`) of all functions imported by the contract
+pub fn imported_functions(module: &Module) -> BTreeSet<String> {
+    module
+        .import_section()
+        .map_or(BTreeSet::new(), |import_section| {
+            import_section
+                .entries()
+                .iter()
+                .filter(|entry| matches!(entry.external(), External::Function(_)))
+                .map(|entry| format!("{}.{}", entry.module(), entry.field()))
+                .collect()
+        })
+}
+
+/// Returns the exported entry points along with their number of parameters.
+///
+/// This contains all exported functions apart from memory management (`allocate`,
+/// `deallocate`) and marker exports (`interface_version_*`, `requires_*`).
+pub fn entry_points(module: &Module) -> BTreeMap<String, usize> {
+    module
+        .exported_function_names(None)
+        .into_iter()
+        .filter(|name| {
+            name != "allocate"
+                && name != "deallocate"
+                && !name.starts_with(INTERFACE_VERSION_PREFIX)
+                && !name.starts_with(REQUIRES_PREFIX)
+        })
+        .filter_map(|name| {
+            let arity = exported_function_type(module, &name)?.params().len();
+            Some((name, arity))
+        })
+        .collect()
+}
+
+/// Returns true if the contract uses floats anywhere, i.e. in a function signature,
+/// local, global, block type or instruction.
+///
+/// Contracts using floats are rejected when they are compiled, so this is false for
+/// all stored contracts unless the VM is configured to allow floats.
+pub fn uses_floats(module: &Module) -> bool {
+    let in_types = module.type_section().map_or(false, |section| {
+        section.types().iter().any(|Type::Function(function_type)| {
+            function_type.params().iter().any(is_float)
+                || function_type.results().iter().any(is_float)
+        })
+    });
+    let in_globals = module.global_section().map_or(false, |section| {
+        section
+            .entries()
+            .iter()
+            .any(|global| is_float(&global.global_type().content_type()))
+    });
+    let in_code = module.code_section().map_or(false, |section| {
+        section.bodies().iter().any(|body| {
+            body.locals()
+                .iter()
+                .any(|local| is_float(&local.value_type()))
+                || body.code().elements().iter().any(is_float_instruction)
+        })
+    });
+    in_types || in_globals || in_code
+}
+
+/// A potential source of non-determinism in a contract, see [`non_determinism_sources`]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NonDeterminismSource {
+    /// Float types or instructions (see [`uses_floats`]), whose results can differ
+    /// between platforms, e.g. for NaN values
+    Floats,
+    /// A shared memory, which can be modified concurrently by other threads
+    SharedMemory,
+    /// An import (`module.field`) that is neither provided by the VM nor a library
+    UnknownImport(String),
+    /// An import (`module.field`) from a WASI module, which provides access to the system,
+    /// e.g. clocks and random numbers
+    WasiImport(String),
+}
+
+/// Lists potential sources of non-determinism in the contract, e.g. for auditing contracts
+/// before allowing them on a chain.
+///
+/// `known_imports` are the full names (`module.field`) of the functions provided to the
+/// contract. All other imports are reported, including imports of memories, tables and globals.
+/// Contracts with any of these sources are rejected when they are stored unless the VM is
+/// configured to allow floats.
+pub fn non_determinism_sources(
+    module: &Module,
+    known_imports: &[&str],
+) -> BTreeSet<NonDeterminismSource> {
+    let mut sources = BTreeSet::new();
+    if uses_floats(module) {
+        sources.insert(NonDeterminismSource::Floats);
+    }
+    let has_shared_memory = module.memory_section().map_or(false, |section| {
+        section
+            .entries()
+            .iter()
+            .any(|memory| memory.limits().shared())
+    });
+    if has_shared_memory {
+        sources.insert(NonDeterminismSource::SharedMemory);
+    }
+    for entry in module
+        .import_section()
+        .map_or(&[][..], |section| section.entries())
+    {
+        let full_name = format!("{}.{}", entry.module(), entry.field());
+        if let External::Memory(memory) = entry.external() {
+            if memory.limits().shared() {
+                sources.insert(NonDeterminismSource::SharedMemory);
+            }
+        }
+        if entry.module().starts_with("wasi") {
+            sources.insert(NonDeterminismSource::WasiImport(full_name));
+        } else if !matches!(entry.external(), External::Function(_))
+            || !known_imports.contains(&full_name.as_str())
+        {
+            sources.insert(NonDeterminismSource::UnknownImport(full_name));
+        }
+    }
+    sources
+}
+
+fn is_float(value_type: &ValueType) -> bool {
+    matches!(value_type, ValueType::F32 | ValueType::F64)
+}
+
+fn is_float_instruction(instruction: &Instruction) -> bool {
+    use Instruction::*;
+    match instruction {
+        Block(BlockType::Value(value_type))
+        | Loop(BlockType::Value(value_type))
+        | If(BlockType::Value(value_type)) => is_float(value_type),
+        F32Load(..) | F64Load(..) | F32Store(..) | F64Store(..) | F32Const(_) | F64Const(_) => true,
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => true,
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F32Add
+        | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => true,
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt | F64Add
+        | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => true,
+        I32TruncSF32 | I32TruncUF32 | I32TruncSF64 | I32TruncUF64 | I64TruncSF32 | I64TruncUF32
+        | I64TruncSF64 | I64TruncUF64 => true,
+        F32ConvertSI32 | F32ConvertUI32 | F32ConvertSI64 | F32ConvertUI64 | F32DemoteF64
+        | F64ConvertSI32 | F64ConvertUI32 | F64ConvertSI64 | F64ConvertUI64 | F64PromoteF32 => true,
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => true,
+        _ => false,
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +307,7 @@ mod tests {
 
     static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
     static CORRUPTED: &[u8] = include_bytes!("../testdata/corrupted.wasm");
+    static FLOATY: &[u8] = include_bytes!("../testdata/floaty.wasm");
 
     #[test]
     fn deserialize_wasm_works() {
@@ -238,7 +456,7 @@ mod tests {
     }
 
     #[test]
-    fn has_ibc_entry_points_works() {
+    fn ibc_entry_points_works() {
         // Non-IBC contract
         let wasm = wat::parse_str(
             r#"(module
@@ -255,7 +473,7 @@ mod tests {
         )
         .unwrap();
         let module = deserialize_wasm(&wasm).unwrap();
-        assert!(!has_ibc_entry_points(&module));
+        assert_eq!(ibc_entry_points(&module), IbcEntryPoints::None);
 
         // IBC contract
         let wasm = wat::parse_str(
@@ -280,7 +498,7 @@ mod tests {
         )
         .unwrap();
         let module = deserialize_wasm(&wasm).unwrap();
-        assert!(has_ibc_entry_points(&module));
+        assert_eq!(ibc_entry_points(&module), IbcEntryPoints::Complete);
 
         // Missing packet ack
         let wasm = wat::parse_str(
@@ -304,6 +522,177 @@ mod tests {
         )
         .unwrap();
         let module = deserialize_wasm(&wasm).unwrap();
-        assert!(!has_ibc_entry_points(&module));
+        assert_eq!(
+            ibc_entry_points(&module),
+            IbcEntryPoints::Partial(vec!["ibc_packet_ack".to_string()])
+        );
+    }
+
+    #[test]
+    fn ibc_entry_points_lists_missing() {
+        let module_with_exports = |names: &[&str]| {
+            let exports: String = names
+                .iter()
+                .map(|name| format!(r#"(export "{}" (func 0))"#, name))
+                .collect();
+            let wasm = wat::parse_str(format!(
+                r#"(module
+                    (type (func))
+                    (func (type 0) nop)
+                    (export "instantiate" (func 0))
+                    {}
+                )"#,
+                exports
+            ))
+            .unwrap();
+            deserialize_wasm(&wasm).unwrap()
+        };
+
+        let module = module_with_exports(&[]);
+        assert_eq!(ibc_entry_points(&module), IbcEntryPoints::None);
+
+        let module = module_with_exports(REQUIRED_IBC_EXPORTS);
+        assert_eq!(ibc_entry_points(&module), IbcEntryPoints::Complete);
+
+        let module = module_with_exports(&["ibc_channel_open", "ibc_packet_receive"]);
+        assert_eq!(
+            ibc_entry_points(&module),
+            IbcEntryPoints::Partial(vec![
+                "ibc_channel_connect".to_string(),
+                "ibc_channel_close".to_string(),
+                "ibc_packet_ack".to_string(),
+                "ibc_packet_timeout".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn imported_functions_works() {
+        let module = deserialize_wasm(CONTRACT).unwrap();
+        let imports = imported_functions(&module);
+        assert!(imports.contains("env.db_read"));
+        assert!(imports.contains("env.addr_validate"));
+        assert!(imports.iter().all(|name| name.starts_with("env.")));
+
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "foo" (func (param i32)))
+                (import "env" "table" (table 1 funcref))
+                (import "math" "add" (func (param i64 i64) (result i64)))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        assert_eq!(
+            imported_functions(&module),
+            BTreeSet::from(["env.foo".to_string(), "math.add".to_string()])
+        );
+    }
+
+    #[test]
+    fn entry_points_works() {
+        let module = deserialize_wasm(CONTRACT).unwrap();
+        assert_eq!(
+            entry_points(&module),
+            BTreeMap::from([
+                ("execute".to_string(), 3),
+                ("instantiate".to_string(), 3),
+                ("migrate".to_string(), 2),
+                ("query".to_string(), 2),
+                ("sudo".to_string(), 2),
+            ])
+        );
+
+        // arities are taken from the function type, also for re-exported imports
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "foo" (func (param i32 i32 i32 i32)))
+                (func (param i32) (result i32) i32.const 0)
+                (export "allocate" (func 1))
+                (export "requires_iterator" (func 1))
+                (export "interface_version_8" (func 1))
+                (export "instantiate" (func 1))
+                (export "foo" (func 0))
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        assert_eq!(
+            entry_points(&module),
+            BTreeMap::from([("foo".to_string(), 4), ("instantiate".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn uses_floats_works() {
+        let module = deserialize_wasm(CONTRACT).unwrap();
+        assert!(!uses_floats(&module));
+        let module = deserialize_wasm(FLOATY).unwrap();
+        assert!(uses_floats(&module));
+
+        let floaty_modules = [
+            // signature
+            r#"(module (func (param f32)))"#,
+            // local
+            r#"(module (func (local f64)))"#,
+            // global
+            r#"(module (global f32 (f32.const 0)))"#,
+            // block type
+            r#"(module (func (result i32) (block (result f64) unreachable) drop i32.const 0))"#,
+            // instruction
+            r#"(module (func (param i32) (result i32) local.get 0 f32.convert_i32_s i32.reinterpret_f32))"#,
+        ];
+        for wat in floaty_modules {
+            let module = deserialize_wasm(&wat::parse_str(wat).unwrap()).unwrap();
+            assert!(uses_floats(&module), "{}", wat);
+        }
+    }
+
+    #[test]
+    fn non_determinism_sources_works() {
+        let module = deserialize_wasm(CONTRACT).unwrap();
+        let known_imports: Vec<String> = imported_functions(&module).into_iter().collect();
+        let known_imports: Vec<&str> = known_imports.iter().map(String::as_str).collect();
+        assert_eq!(
+            non_determinism_sources(&module, &known_imports),
+            BTreeSet::new()
+        );
+
+        let module = deserialize_wasm(FLOATY).unwrap();
+        assert!(non_determinism_sources(&module, &known_imports)
+            .contains(&NonDeterminismSource::Floats));
+
+        let wasm = wat::parse_str(
+            r#"(module
+                (import "env" "db_read" (func (param i32) (result i32)))
+                (import "env" "foo" (func (param i32)))
+                (import "env" "global" (global i32))
+                (import "wasi_snapshot_preview1" "random_get" (func (param i32 i32) (result i32)))
+                (memory 1 1 shared)
+            )"#,
+        )
+        .unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        assert_eq!(
+            non_determinism_sources(&module, &["env.db_read", "env.global"]),
+            BTreeSet::from([
+                NonDeterminismSource::SharedMemory,
+                NonDeterminismSource::UnknownImport("env.foo".to_string()),
+                NonDeterminismSource::UnknownImport("env.global".to_string()),
+                NonDeterminismSource::WasiImport("wasi_snapshot_preview1.random_get".to_string()),
+            ])
+        );
+
+        // imported shared memory
+        let wasm =
+            wat::parse_str(r#"(module (import "env" "memory" (memory 1 1 shared)))"#).unwrap();
+        let module = deserialize_wasm(&wasm).unwrap();
+        assert_eq!(
+            non_determinism_sources(&module, &[]),
+            BTreeSet::from([
+                NonDeterminismSource::SharedMemory,
+                NonDeterminismSource::UnknownImport("env.memory".to_string()),
+            ])
+        );
     }
 }
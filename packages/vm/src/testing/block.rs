@@ -0,0 +1,244 @@
+//! Simulation of a block executing a sequence of messages against one contract.
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Serialize};
+
+use cosmwasm_std::{ContractResult, CustomMsg, Env, MessageInfo, Response};
+
+use crate::calls::{call_execute, call_migrate, call_sudo};
+use crate::errors::{VmError, VmResult};
+use crate::instance::Instance;
+use crate::serde::to_vec;
+use crate::{BackendApi, Querier};
+
+use super::storage::MockStorage;
+
+/// An entry point called with a message in a [`Block`]
+#[derive(Clone, Debug)]
+pub enum BlockEntryPoint {
+    Execute(MessageInfo),
+    Sudo,
+    Migrate,
+}
+
+/// The result of a message that fit into the block
+#[derive(Debug)]
+pub struct BlockMsgOutcome<U> {
+    pub result: ContractResult<Response<U>>,
+    /// Gas used by this message, internally and externally
+    pub gas_used: u64,
+}
+
+/// The result of [`Block::run`]
+#[derive(Debug)]
+pub struct BlockOutcome<U> {
+    /// The outcomes of the messages that fit into the block, in order
+    pub included: Vec<BlockMsgOutcome<U>>,
+    /// The number of messages that were not executed because the block gas limit was reached.
+    /// This includes the message that exceeded the limit.
+    pub excluded: usize,
+    /// Total gas used by the included messages
+    pub gas_used: u64,
+}
+
+/// Runs a sequence of messages against one instance under a shared block gas limit,
+/// which is useful for testing contracts that batch user operations.
+///
+/// Messages are executed in order until one of them exceeds the remaining block gas.
+/// That message and all messages after it are excluded from the block. Like on chain,
+/// the state changes of excluded and failed messages are reverted.
+///
+/// The gas limit of the instance must be at least the block gas limit, otherwise the
+/// block stops when the instance runs out of gas.
+#[derive(Clone, Debug)]
+pub struct Block {
+    env: Env,
+    gas_limit: u64,
+    msgs: Vec<(BlockEntryPoint, Vec<u8>)>,
+}
+
+impl Block {
+    pub fn new(env: Env, gas_limit: u64) -> Self {
+        Block {
+            env,
+            gas_limit,
+            msgs: vec![],
+        }
+    }
+
+    /// Adds a message for the given entry point
+    pub fn msg(mut self, entry_point: BlockEntryPoint, msg: impl Serialize + JsonSchema) -> Self {
+        let msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
+        self.msgs.push((entry_point, msg));
+        self
+    }
+
+    /// Adds an execute message
+    pub fn execute(self, info: MessageInfo, msg: impl Serialize + JsonSchema) -> Self {
+        self.msg(BlockEntryPoint::Execute(info), msg)
+    }
+
+    pub fn run<A, Q, U>(self, instance: &mut Instance<A, MockStorage, Q>) -> BlockOutcome<U>
+    where
+        A: BackendApi + 'static,
+        Q: Querier + 'static,
+        U: DeserializeOwned + CustomMsg,
+    {
+        let mut included = Vec::with_capacity(self.msgs.len());
+        let mut gas_used = 0u64;
+        for (entry_point, msg) in &self.msgs {
+            let snapshot = instance
+                .with_storage(|storage| Ok(storage.clone()))
+                .expect("Testing error: Could not access storage");
+            let gas_before = instance.get_gas_left();
+            let result = self.call(instance, entry_point, msg);
+            let msg_gas_used = gas_before - instance.get_gas_left();
+
+            let fits = gas_used.saturating_add(msg_gas_used) <= self.gas_limit;
+            let succeeded = matches!(result, Ok(ContractResult::Ok(_)));
+            if !fits || !succeeded {
+                instance
+                    .with_storage(|storage| {
+                        *storage = snapshot;
+                        Ok(())
+                    })
+                    .expect("Testing error: Could not access storage");
+            }
+
+            let result = match result {
+                Ok(result) if fits => result,
+                Ok(_) | Err(VmError::GasDepletion { .. }) => {
+                    return BlockOutcome {
+                        excluded: self.msgs.len() - included.len(),
+                        included,
+                        gas_used,
+                    };
+                }
+                Err(err) => panic!("VM error: {}", err),
+            };
+            gas_used += msg_gas_used;
+            included.push(BlockMsgOutcome {
+                result,
+                gas_used: msg_gas_used,
+            });
+        }
+        BlockOutcome {
+            included,
+            excluded: 0,
+            gas_used,
+        }
+    }
+
+    fn call<A, Q, U>(
+        &self,
+        instance: &mut Instance<A, MockStorage, Q>,
+        entry_point: &BlockEntryPoint,
+        msg: &[u8],
+    ) -> VmResult<ContractResult<Response<U>>>
+    where
+        A: BackendApi + 'static,
+        Q: Querier + 'static,
+        U: DeserializeOwned + CustomMsg,
+    {
+        match entry_point {
+            BlockEntryPoint::Execute(info) => call_execute(instance, &self.env, info, msg),
+            BlockEntryPoint::Sudo => call_sudo(instance, &self.env, msg),
+            BlockEntryPoint::Migrate => call_migrate(instance, &self.env, msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        instantiate, mock_env, mock_info, MockApi, MockInstanceBuilder, MockQuerier,
+    };
+    use crate::Storage;
+    use cosmwasm_std::Empty;
+    use serde_json::json;
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+
+    fn setup() -> Instance<MockApi, MockStorage, MockQuerier> {
+        let mut instance = MockInstanceBuilder::new(CONTRACT).build();
+        let msg = json!({"verifier": "verifies", "beneficiary": "benefits"});
+        let _: ContractResult<Response<Empty>> =
+            instantiate(&mut instance, mock_env(), mock_info("creator", &[]), msg);
+        instance
+    }
+
+    fn gas_of_one_msg() -> u64 {
+        let mut instance = setup();
+        let outcome: BlockOutcome<Empty> = Block::new(mock_env(), u64::MAX)
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .run(&mut instance);
+        outcome.included[0].gas_used
+    }
+
+    #[test]
+    fn run_includes_all_messages_within_limit() {
+        let mut instance = setup();
+        let outcome: BlockOutcome<Empty> = Block::new(mock_env(), u64::MAX)
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .run(&mut instance);
+        assert_eq!(outcome.included.len(), 2);
+        assert_eq!(outcome.excluded, 0);
+        assert_eq!(
+            outcome.gas_used,
+            outcome.included[0].gas_used + outcome.included[1].gas_used
+        );
+        for msg in outcome.included {
+            msg.result.unwrap();
+        }
+    }
+
+    #[test]
+    fn run_stops_at_block_gas_limit() {
+        let per_msg = gas_of_one_msg();
+
+        let mut instance = setup();
+        let outcome: BlockOutcome<Empty> = Block::new(mock_env(), per_msg * 2 + per_msg / 2)
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .execute(mock_info("verifies", &[]), json!({"release": {}}))
+            .run(&mut instance);
+        assert_eq!(outcome.included.len(), 2);
+        assert_eq!(outcome.excluded, 2);
+        assert!(outcome.gas_used <= per_msg * 2 + per_msg / 2);
+    }
+
+    #[test]
+    fn run_reverts_state_of_excluded_messages() {
+        let mut instance = setup();
+        let config = |instance: &mut Instance<_, MockStorage, _>| {
+            instance
+                .with_storage(|storage| Ok(storage.get(b"config").0.unwrap()))
+                .unwrap()
+        };
+        let state_before = config(&mut instance);
+
+        // migrate changes the verifier but does not fit into the block
+        let outcome: BlockOutcome<Empty> = Block::new(mock_env(), 1)
+            .msg(
+                BlockEntryPoint::Migrate,
+                json!({"verifier": "someone else"}),
+            )
+            .run(&mut instance);
+        assert_eq!(outcome.included.len(), 0);
+        assert_eq!(outcome.excluded, 1);
+        assert_eq!(outcome.gas_used, 0);
+        assert_eq!(config(&mut instance), state_before);
+
+        // with enough gas, the migration is applied
+        let outcome: BlockOutcome<Empty> = Block::new(mock_env(), u64::MAX)
+            .msg(
+                BlockEntryPoint::Migrate,
+                json!({"verifier": "someone else"}),
+            )
+            .run(&mut instance);
+        outcome.included[0].result.clone().unwrap();
+        assert_ne!(config(&mut instance), state_before);
+    }
+}
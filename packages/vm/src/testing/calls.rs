@@ -3,13 +3,16 @@
 //! use cosmwasm_vm::testing::X
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Serialize};
+use std::time::{Duration, Instant};
 
-use cosmwasm_std::{ContractResult, CustomMsg, Env, MessageInfo, QueryResponse, Reply, Response};
+use cosmwasm_std::{
+    Addr, ContractResult, CustomMsg, Env, MessageInfo, QueryResponse, Reply, Response, WasmMsg,
+};
 #[cfg(feature = "stargate")]
 use cosmwasm_std::{
     Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannelCloseMsg, IbcChannelConnectMsg,
-    IbcChannelOpenMsg, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
-    IbcReceiveResponse,
+    IbcChannelOpenMsg, IbcDestinationCallbackMsg, IbcPacketAckMsg, IbcPacketReceiveMsg,
+    IbcPacketTimeoutMsg, IbcReceiveResponse, IbcSourceCallbackMsg,
 };
 
 use crate::calls::{
@@ -17,13 +20,17 @@ use crate::calls::{
 };
 #[cfg(feature = "stargate")]
 use crate::calls::{
-    call_ibc_channel_close, call_ibc_channel_connect, call_ibc_channel_open, call_ibc_packet_ack,
-    call_ibc_packet_receive, call_ibc_packet_timeout,
+    call_ibc_channel_close, call_ibc_channel_connect, call_ibc_channel_open,
+    call_ibc_destination_callback, call_ibc_packet_ack, call_ibc_packet_receive,
+    call_ibc_packet_timeout, call_ibc_source_callback,
 };
-use crate::instance::Instance;
+use crate::errors::{VmError, VmResult};
+use crate::instance::{GasReport, Instance};
 use crate::serde::to_vec;
 use crate::{BackendApi, Querier, Storage};
 
+use super::storage::MockStorage;
+
 /// Mimicks the call signature of the smart contracts.
 /// Thus it moves env and msg rather than take them as reference.
 /// This is inefficient here, but only used in test code.
@@ -40,10 +47,116 @@ where
     M: Serialize + JsonSchema,
     U: DeserializeOwned + CustomMsg,
 {
-    let serialized_msg = to_vec(&msg).expect("Testing error: Could not seralize request message");
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
     call_instantiate(instance, &env, &info, &serialized_msg).expect("VM error")
 }
 
+/// The admin and label the chain stores along with a contract on instantiation.
+/// Those are not visible to the contract itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstantiateOptions {
+    pub admin: Option<String>,
+    pub label: String,
+}
+
+/// The result of [`instantiate_with_options`] and [`instantiate_from_wasm_msg`]
+#[derive(Debug)]
+pub struct InstantiateOutcome<U> {
+    pub result: ContractResult<Response<U>>,
+    pub admin: Option<String>,
+    pub label: String,
+}
+
+/// Like [`instantiate`] but with the admin and label of the contract, which are returned
+/// along with the result for assertions. Like the chain, this requires a non-empty label
+/// and a valid admin address.
+pub fn instantiate_with_options<A, S, Q, M, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    info: MessageInfo,
+    msg: M,
+    options: InstantiateOptions,
+) -> InstantiateOutcome<U>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema,
+    U: DeserializeOwned + CustomMsg,
+{
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
+    instantiate_raw_with_options(instance, &env, &info, &serialized_msg, options)
+}
+
+/// Instantiates the contract with a `WasmMsg::Instantiate` sent by `sender`, e.g. a message
+/// emitted by a factory contract. The funds, admin and label are taken from the message.
+/// The code ID is ignored.
+pub fn instantiate_from_wasm_msg<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    sender: &str,
+    wasm_msg: &WasmMsg,
+) -> InstantiateOutcome<U>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    match wasm_msg {
+        WasmMsg::Instantiate {
+            admin,
+            msg,
+            funds,
+            label,
+            ..
+        } => {
+            let info = MessageInfo {
+                sender: Addr::unchecked(sender),
+                funds: funds.clone(),
+            };
+            let options = InstantiateOptions {
+                admin: admin.clone(),
+                label: label.clone(),
+            };
+            instantiate_raw_with_options(instance, &env, &info, msg, options)
+        }
+        _ => panic!(
+            "Testing error: Expected WasmMsg::Instantiate, got {:?}",
+            wasm_msg
+        ),
+    }
+}
+
+fn instantiate_raw_with_options<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: &Env,
+    info: &MessageInfo,
+    msg: &[u8],
+    options: InstantiateOptions,
+) -> InstantiateOutcome<U>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    if options.label.trim().is_empty() {
+        panic!("Testing error: The label of a contract must not be empty");
+    }
+    if let Some(admin) = &options.admin {
+        if let Err(err) = instance.api().canonical_address(admin).0 {
+            panic!("Testing error: Invalid admin address {}: {}", admin, err);
+        }
+    }
+    let result = call_instantiate(instance, env, info, msg).expect("VM error");
+    InstantiateOutcome {
+        result,
+        admin: options.admin,
+        label: options.label,
+    }
+}
+
 // execute mimicks the call signature of the smart contracts.
 // thus it moves env and msg rather than take them as reference.
 // this is inefficient here, but only used in test code
@@ -60,7 +173,7 @@ where
     M: Serialize + JsonSchema,
     U: DeserializeOwned + CustomMsg,
 {
-    let serialized_msg = to_vec(&msg).expect("Testing error: Could not seralize request message");
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
     call_execute(instance, &env, &info, &serialized_msg).expect("VM error")
 }
 
@@ -79,7 +192,7 @@ where
     M: Serialize + JsonSchema,
     U: DeserializeOwned + CustomMsg,
 {
-    let serialized_msg = to_vec(&msg).expect("Testing error: Could not seralize request message");
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
     call_migrate(instance, &env, &serialized_msg).expect("VM error")
 }
 
@@ -98,7 +211,7 @@ where
     M: Serialize + JsonSchema,
     U: DeserializeOwned + CustomMsg,
 {
-    let serialized_msg = to_vec(&msg).expect("Testing error: Could not seralize request message");
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
     call_sudo(instance, &env, &serialized_msg).expect("VM error")
 }
 
@@ -133,10 +246,164 @@ where
     Q: Querier + 'static,
     M: Serialize + JsonSchema,
 {
-    let serialized_msg = to_vec(&msg).expect("Testing error: Could not seralize request message");
+    let serialized_msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
     call_query(instance, &env, &serialized_msg).expect("VM error")
 }
 
+/// The result of a call along with the resources it consumed, as returned by
+/// [`instantiate_with_report`], [`execute_with_report`] and [`query_with_report`]
+#[derive(Debug)]
+pub struct CallReport<T> {
+    pub response: T,
+    /// The gas used by this call only. `limit` is the gas that was available
+    /// at the start of the call.
+    pub gas_report: GasReport,
+    /// The wall time of the call. This is not deterministic and should only be
+    /// used for benchmarking.
+    pub duration: Duration,
+}
+
+fn with_report<A, S, Q, T>(
+    instance: &mut Instance<A, S, Q>,
+    call: impl FnOnce(&mut Instance<A, S, Q>) -> T,
+) -> CallReport<T>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+{
+    let before = instance.create_gas_report();
+    let start = Instant::now();
+    let response = call(instance);
+    let duration = start.elapsed();
+    let after = instance.create_gas_report();
+    CallReport {
+        response,
+        gas_report: GasReport {
+            limit: before.remaining,
+            remaining: after.remaining,
+            used_externally: after.used_externally - before.used_externally,
+            used_internally: after.used_internally - before.used_internally,
+        },
+        duration,
+    }
+}
+
+/// Like [`instantiate`] but also reports the gas usage and duration of the call
+pub fn instantiate_with_report<A, S, Q, M, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    info: MessageInfo,
+    msg: M,
+) -> CallReport<ContractResult<Response<U>>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema,
+    U: DeserializeOwned + CustomMsg,
+{
+    with_report(instance, |instance| instantiate(instance, env, info, msg))
+}
+
+/// Like [`execute`] but also reports the gas usage and duration of the call
+pub fn execute_with_report<A, S, Q, M, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    info: MessageInfo,
+    msg: M,
+) -> CallReport<ContractResult<Response<U>>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema,
+    U: DeserializeOwned + CustomMsg,
+{
+    with_report(instance, |instance| execute(instance, env, info, msg))
+}
+
+/// The first message of an [`execute_many`] transaction that failed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecuteManyError {
+    /// The position of the message in the transaction
+    pub index: usize,
+    pub error: String,
+}
+
+/// Executes the given messages in order, like a chain executes a transaction with
+/// multiple messages: either all of them succeed or the state changes of all of them
+/// are reverted using a [`MockStorage::snapshot`]. Running out of gas fails the
+/// transaction like a contract error. Other VM errors revert the state changes as well
+/// and are returned.
+///
+/// The report contains the responses of all messages if the transaction succeeded and
+/// the gas used by the whole transaction.
+pub fn execute_many<A, Q, M, U>(
+    instance: &mut Instance<A, MockStorage, Q>,
+    env: Env,
+    info: MessageInfo,
+    msgs: Vec<M>,
+) -> VmResult<CallReport<Result<Vec<Response<U>>, ExecuteManyError>>>
+where
+    A: BackendApi + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema,
+    U: DeserializeOwned + CustomMsg,
+{
+    let snapshot = instance.with_storage(|storage| Ok(storage.snapshot()))?;
+    let CallReport {
+        response,
+        gas_report,
+        duration,
+    } = with_report(instance, |instance| {
+        let mut responses = Vec::with_capacity(msgs.len());
+        for (index, msg) in msgs.into_iter().enumerate() {
+            let msg = to_vec(&msg).expect("Testing error: Could not serialize request message");
+            let error = match call_execute(instance, &env, &info, &msg) {
+                Ok(ContractResult::Ok(response)) => {
+                    responses.push(response);
+                    continue;
+                }
+                Ok(ContractResult::Err(error)) => error,
+                Err(err @ VmError::GasDepletion { .. }) => err.to_string(),
+                Err(err) => return Err(err),
+            };
+            return Ok(Err(ExecuteManyError { index, error }));
+        }
+        Ok(Ok(responses))
+    });
+    let succeeded = matches!(response, Ok(Ok(_)));
+    instance.with_storage(|storage| {
+        if succeeded {
+            storage.commit();
+        } else {
+            storage.rollback_to(snapshot);
+        }
+        Ok(())
+    })?;
+    Ok(CallReport {
+        response: response?,
+        gas_report,
+        duration,
+    })
+}
+
+/// Like [`query`] but also reports the gas usage and duration of the call
+pub fn query_with_report<A, S, Q, M>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    msg: M,
+) -> CallReport<ContractResult<QueryResponse>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema,
+{
+    with_report(instance, |instance| query(instance, env, msg))
+}
+
 // ibc_channel_open mimicks the call signature of the smart contracts.
 // thus it moves env and channel rather than take them as reference.
 // this is inefficient here, but only used in test code
@@ -243,3 +510,285 @@ where
 {
     call_ibc_packet_timeout(instance, &env, &msg).expect("VM error")
 }
+
+// ibc_source_callback mimicks the call signature of the smart contracts.
+// thus it moves env and msg rather than take them as reference.
+// this is inefficient here, but only used in test code
+#[cfg(feature = "stargate")]
+pub fn ibc_source_callback<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    msg: IbcSourceCallbackMsg,
+) -> ContractResult<IbcBasicResponse<U>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    call_ibc_source_callback(instance, &env, &msg).expect("VM error")
+}
+
+// ibc_destination_callback mimicks the call signature of the smart contracts.
+// thus it moves env and msg rather than take them as reference.
+// this is inefficient here, but only used in test code
+#[cfg(feature = "stargate")]
+pub fn ibc_destination_callback<A, S, Q, U>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    msg: IbcDestinationCallbackMsg,
+) -> ContractResult<IbcBasicResponse<U>>
+where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    U: DeserializeOwned + CustomMsg,
+{
+    call_ibc_destination_callback(instance, &env, &msg).expect("VM error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{mock_env, mock_info, mock_instance, MockInstanceBuilder};
+    use cosmwasm_std::{coins, from_slice, to_binary, AllBalanceResponse, Empty};
+    use serde_json::json;
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+
+    #[derive(Serialize, JsonSchema)]
+    struct InitMsg {
+        verifier: String,
+        beneficiary: String,
+    }
+
+    fn init_msg() -> InitMsg {
+        InitMsg {
+            verifier: "verifies".to_string(),
+            beneficiary: "benefits".to_string(),
+        }
+    }
+
+    #[test]
+    fn execute_many_works() {
+        let mut instance = mock_instance(CONTRACT, &coins(30, "earth"));
+        let _: ContractResult<Response> = instantiate(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+        );
+
+        let report: CallReport<Result<Vec<Response>, _>> = execute_many(
+            &mut instance,
+            mock_env(),
+            mock_info("verifies", &[]),
+            vec![json!({"release": {}}), json!({"release": {}})],
+        )
+        .unwrap();
+        let responses = report.response.unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].messages.len(), 1);
+        assert!(report.gas_report.used_internally > 0);
+        let open_snapshots = instance
+            .with_storage(|storage| Ok(storage.open_snapshots()))
+            .unwrap();
+        assert_eq!(open_snapshots, 0);
+    }
+
+    #[test]
+    fn execute_many_reverts_all_messages_on_failure() {
+        let mut instance = MockInstanceBuilder::new(CONTRACT)
+            .gas_limit(500_000_000_000)
+            .build();
+        let _: ContractResult<Response> = instantiate(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+        );
+
+        // a contract error
+        let report: CallReport<Result<Vec<Response>, _>> = execute_many(
+            &mut instance,
+            mock_env(),
+            mock_info("someone else", &[]),
+            vec![json!({"release": {}})],
+        )
+        .unwrap();
+        assert_eq!(report.response.unwrap_err().index, 0);
+
+        // the storage loop writes until the gas runs out
+        let report: CallReport<Result<Vec<Response>, _>> = execute_many(
+            &mut instance,
+            mock_env(),
+            mock_info("verifies", &[]),
+            vec![json!({"release": {}}), json!({"storage_loop": {}})],
+        )
+        .unwrap();
+        assert_eq!(
+            report.response.unwrap_err(),
+            ExecuteManyError {
+                index: 1,
+                error: "Ran out of gas during contract execution (in call_execute)".to_string(),
+            }
+        );
+        assert_eq!(report.gas_report.remaining, 0);
+        let test_key = instance
+            .with_storage(|storage| Ok(storage.get(b"test.key").0.unwrap()))
+            .unwrap();
+        assert_eq!(test_key, None);
+    }
+
+    #[test]
+    fn execute_many_returns_vm_errors() {
+        let mut instance = mock_instance(CONTRACT, &coins(30, "earth"));
+        let _: ContractResult<Response> = instantiate(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+        );
+        let before = instance.with_storage(|storage| Ok(storage.dump())).unwrap();
+
+        let err = execute_many::<_, _, _, Empty>(
+            &mut instance,
+            mock_env(),
+            mock_info("verifies", &[]),
+            vec![json!({"release": {}}), json!({"panic": {}})],
+        )
+        .unwrap_err();
+        assert!(matches!(err, VmError::RuntimeErr { .. }), "{:?}", err);
+        let (after, open_snapshots) = instance
+            .with_storage(|storage| Ok((storage.dump(), storage.open_snapshots())))
+            .unwrap();
+        assert_eq!(after, before);
+        assert_eq!(open_snapshots, 0);
+    }
+
+    #[test]
+    fn mock_instance_builder_returns_handles() {
+        let (mut instance, storage, querier) =
+            MockInstanceBuilder::new(CONTRACT).build_with_handles();
+
+        storage.with(|storage| storage.set(b"foo", b"bar").0.unwrap());
+        assert_eq!(instance.read_raw(b"foo").unwrap(), Some(b"bar".to_vec()));
+
+        querier.with(|querier| querier.update_balance("rich", coins(123, "earth")));
+        let response = query(
+            &mut instance,
+            mock_env(),
+            json!({"other_balance": {"address": "rich"}}),
+        )
+        .unwrap();
+        assert_eq!(
+            from_slice::<AllBalanceResponse>(&response).unwrap().amount,
+            coins(123, "earth")
+        );
+
+        // handles remain usable while the instance is in use
+        let _: ContractResult<Response> = instantiate(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+        );
+        let keys = storage.with(|storage| storage.dump().len());
+        assert_eq!(keys, 2);
+    }
+
+    #[test]
+    fn instantiate_with_options_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let options = InstantiateOptions {
+            admin: Some("admin".to_string()),
+            label: "hackatom".to_string(),
+        };
+        let outcome: InstantiateOutcome<Empty> = instantiate_with_options(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+            options,
+        );
+        assert_eq!(outcome.result.unwrap().messages.len(), 0);
+        assert_eq!(outcome.admin.as_deref(), Some("admin"));
+        assert_eq!(outcome.label, "hackatom");
+    }
+
+    #[test]
+    #[should_panic(expected = "The label of a contract must not be empty")]
+    fn instantiate_with_options_requires_label() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let _: InstantiateOutcome<Empty> = instantiate_with_options(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+            InstantiateOptions::default(),
+        );
+    }
+
+    #[test]
+    fn instantiate_from_wasm_msg_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let wasm_msg = WasmMsg::Instantiate {
+            admin: None,
+            code_id: 17,
+            msg: to_binary(&init_msg()).unwrap(),
+            funds: vec![],
+            label: "child".to_string(),
+        };
+        let outcome: InstantiateOutcome<Empty> =
+            instantiate_from_wasm_msg(&mut instance, mock_env(), "factory", &wasm_msg);
+        outcome.result.unwrap();
+        assert_eq!(outcome.admin, None);
+        assert_eq!(outcome.label, "child");
+    }
+
+    #[test]
+    fn with_report_variants_report_gas_per_call() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+
+        let report = instantiate_with_report::<_, _, _, _, Empty>(
+            &mut instance,
+            mock_env(),
+            mock_info("creator", &[]),
+            init_msg(),
+        );
+        report.response.unwrap();
+        let instantiate_gas = report.gas_report;
+        assert!(instantiate_gas.used_internally > 0);
+        assert!(instantiate_gas.used_externally > 0);
+        assert_eq!(
+            instantiate_gas.limit - instantiate_gas.remaining,
+            instantiate_gas.used_internally + instantiate_gas.used_externally
+        );
+
+        let report = query_with_report(
+            &mut instance,
+            mock_env(),
+            serde_json::json!({ "verifier": {} }),
+        );
+        assert_eq!(
+            report.response.unwrap().as_slice(),
+            br#"{"verifier":"verifies"}"#
+        );
+        // the report only covers the query
+        assert_eq!(report.gas_report.limit, instantiate_gas.remaining);
+        assert!(report.gas_report.used_internally > 0);
+        assert_eq!(
+            report.gas_report.limit - report.gas_report.remaining,
+            report.gas_report.used_internally + report.gas_report.used_externally
+        );
+
+        let report = execute_with_report::<_, _, _, _, Empty>(
+            &mut instance,
+            mock_env(),
+            mock_info("verifies", &[]),
+            serde_json::json!({ "release": {} }),
+        );
+        report.response.unwrap();
+        assert!(report.gas_report.used_internally > 0);
+    }
+}
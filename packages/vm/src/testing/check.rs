@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use crate::capabilities::required_capabilities_from_module;
+use crate::checksum::Checksum;
+use crate::compatibility::check_wasm;
+use crate::errors::VmResult;
+use crate::instance::{Instance, InstanceParams};
+use crate::static_analysis::{deserialize_wasm, ibc_entry_points, IbcEntryPoints};
+use crate::wasm_backend::compile;
+
+use super::instance::mock_instance_options;
+use super::mock::mock_backend;
+
+/// The result of a successful [`check_contract`]
+#[derive(PartialEq, Eq, Debug)]
+pub struct ContractCheckReport {
+    pub checksum: Checksum,
+    /// The size of the Wasm bytecode in bytes
+    pub wasm_size: usize,
+    pub required_capabilities: HashSet<String>,
+    pub ibc_entry_points: IbcEntryPoints,
+    /// The size of the contract's memory after instantiation in pages
+    pub memory_pages: usize,
+}
+
+/// Checks if the given Wasm is a contract that can be stored on a chain with the given
+/// capabilities. This runs the static checks performed when storing a contract (see
+/// [`check_wasm`](crate::internals::check_wasm)), compiles it and instantiates it against
+/// the mock backend. No entry point is called.
+///
+/// This is meant as a sanity check before uploading a contract.
+pub fn check_contract(
+    wasm: &[u8],
+    available_capabilities: &HashSet<String>,
+) -> VmResult<ContractCheckReport> {
+    check_wasm(wasm, available_capabilities)?;
+    let deserialized = deserialize_wasm(wasm)?;
+
+    let (options, memory_limit) = mock_instance_options();
+    let module = compile(wasm, memory_limit, &[])?;
+    let instance = Instance::from_module(
+        &module,
+        mock_backend(&[]),
+        options,
+        InstanceParams {
+            available_capabilities: Some(available_capabilities),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(ContractCheckReport {
+        checksum: Checksum::generate(wasm),
+        wasm_size: wasm.len(),
+        required_capabilities: required_capabilities_from_module(&deserialized),
+        ibc_entry_points: ibc_entry_points(&deserialized),
+        memory_pages: instance.memory_pages(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::capabilities_from_csv;
+    use crate::errors::VmError;
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+    static IBC_CONTRACT: &[u8] = include_bytes!("../../testdata/ibc_reflect.wasm");
+
+    #[test]
+    fn check_contract_works() {
+        let report = check_contract(CONTRACT, &capabilities_from_csv("iterator,staking")).unwrap();
+        assert_eq!(report.checksum, Checksum::generate(CONTRACT));
+        assert_eq!(report.wasm_size, CONTRACT.len());
+        assert_eq!(report.required_capabilities, HashSet::new());
+        assert_eq!(report.ibc_entry_points, IbcEntryPoints::None);
+        assert_eq!(report.memory_pages, 17);
+
+        let report = check_contract(
+            IBC_CONTRACT,
+            &capabilities_from_csv("iterator,staking,stargate"),
+        )
+        .unwrap();
+        assert_eq!(
+            report.required_capabilities,
+            capabilities_from_csv("iterator,staking,stargate")
+        );
+        assert_eq!(report.ibc_entry_points, IbcEntryPoints::Complete);
+    }
+
+    #[test]
+    fn check_contract_fails_for_missing_capabilities() {
+        match check_contract(IBC_CONTRACT, &capabilities_from_csv("iterator")).unwrap_err() {
+            VmError::StaticValidationErr { msg, .. } => {
+                assert!(msg.contains("requires unavailable capabilities"), "{}", msg)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn check_contract_fails_for_invalid_wasm() {
+        let err = check_contract(b"not wasm", &HashSet::new()).unwrap_err();
+        assert!(matches!(err, VmError::StaticValidationErr { .. }));
+    }
+}
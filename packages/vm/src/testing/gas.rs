@@ -0,0 +1,191 @@
+//! Gas assertions for integration tests.
+//!
+//! Instead of asserting exact gas values, which change with every compiler or VM update,
+//! the gas used can be compared to an expected value or a recorded baseline with a tolerance.
+//! Running the tests with the environment variable `UPDATE_GAS_BASELINE=1` (re-)creates the
+//! baseline files instead of comparing against them. Review and check in the resulting changes.
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::instance::GasReport;
+
+/// Set this environment variable to `1` or `true` to write gas baseline files instead of
+/// comparing against them
+pub const UPDATE_GAS_BASELINE_ENV: &str = "UPDATE_GAS_BASELINE";
+
+/// Asserts that the total gas used according to the report (internally and externally) is
+/// within `tolerance_percent` percent of `expected`.
+pub fn assert_gas_within(report: &GasReport, expected: u64, tolerance_percent: u64) {
+    if let Err(err) = check_gas_within(gas_used(report), expected, tolerance_percent) {
+        panic!("{}", err);
+    }
+}
+
+/// Asserts that the total gas used according to the report is within `tolerance_percent`
+/// percent of the baseline stored in `<dir>/<name>.json`.
+///
+/// When the environment variable [`UPDATE_GAS_BASELINE_ENV`] is set, the baseline file is
+/// written instead.
+pub fn assert_gas_baseline(
+    report: &GasReport,
+    dir: impl AsRef<Path>,
+    name: &str,
+    tolerance_percent: u64,
+) {
+    if let Err(err) = check_gas_baseline(
+        dir.as_ref(),
+        name,
+        gas_used(report),
+        tolerance_percent,
+        update_mode(),
+    ) {
+        panic!("{}", err);
+    }
+}
+
+fn gas_used(report: &GasReport) -> u64 {
+    report.used_internally + report.used_externally
+}
+
+fn update_mode() -> bool {
+    matches!(
+        std::env::var(UPDATE_GAS_BASELINE_ENV).as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+fn check_gas_within(used: u64, expected: u64, tolerance_percent: u64) -> Result<(), String> {
+    let tolerance = (expected as u128 * tolerance_percent as u128 / 100) as u64;
+    let min = expected.saturating_sub(tolerance);
+    let max = expected.saturating_add(tolerance);
+    if used < min || used > max {
+        return Err(format!(
+            "Gas used ({}) is not within {}% of the expected value ({}). Allowed range: {}..={}",
+            used, tolerance_percent, expected, min, max
+        ));
+    }
+    Ok(())
+}
+
+/// Compares `used` to the baseline of the given name, or writes it in update mode.
+/// Returns a human readable description of the problem on failure.
+fn check_gas_baseline(
+    dir: &Path,
+    name: &str,
+    used: u64,
+    tolerance_percent: u64,
+    update: bool,
+) -> Result<(), String> {
+    let path = dir.join(format!("{}.json", name));
+
+    if update {
+        let content = serde_json::to_string_pretty(&json!({ "gas_used": used })).unwrap() + "\n";
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, content))
+            .map_err(|e| format!("{}: could not write gas baseline: {}", name, e))?;
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "{}: could not read {}: {}. Run with {}=1 to create it.",
+            name,
+            path.display(),
+            e,
+            UPDATE_GAS_BASELINE_ENV
+        )
+    })?;
+    let baseline = serde_json::from_str::<Value>(&content)
+        .ok()
+        .and_then(|value| value["gas_used"].as_u64())
+        .ok_or_else(|| format!("{}: invalid gas baseline in {}", name, path.display()))?;
+    check_gas_within(used, baseline, tolerance_percent).map_err(|err| {
+        format!(
+            "{}: {} (baseline {}). Run with {}=1 to update the baseline.",
+            name,
+            err,
+            path.display(),
+            UPDATE_GAS_BASELINE_ENV
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn report(used_internally: u64, used_externally: u64) -> GasReport {
+        GasReport {
+            limit: 1_000_000,
+            remaining: 1_000_000 - used_internally - used_externally,
+            used_externally,
+            used_internally,
+        }
+    }
+
+    #[test]
+    fn check_gas_within_works() {
+        check_gas_within(100, 100, 0).unwrap();
+        check_gas_within(90, 100, 10).unwrap();
+        check_gas_within(110, 100, 10).unwrap();
+        check_gas_within(u64::MAX, u64::MAX, 10).unwrap();
+
+        let err = check_gas_within(89, 100, 10).unwrap_err();
+        assert_eq!(
+            err,
+            "Gas used (89) is not within 10% of the expected value (100). Allowed range: 90..=110"
+        );
+        check_gas_within(111, 100, 10).unwrap_err();
+        check_gas_within(101, 100, 0).unwrap_err();
+    }
+
+    #[test]
+    fn assert_gas_within_works() {
+        assert_gas_within(&report(900, 150), 1000, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Gas used (1200) is not within 5% of the expected value (1000)")]
+    fn assert_gas_within_panics_for_mismatch() {
+        assert_gas_within(&report(1000, 200), 1000, 5);
+    }
+
+    #[test]
+    fn check_gas_baseline_works() {
+        let dir = TempDir::new().unwrap();
+        let baseline_dir = dir.path().join("gas");
+
+        // missing file
+        let err = check_gas_baseline(&baseline_dir, "execute", 1000, 5, false).unwrap_err();
+        assert!(err.starts_with("execute: could not read"), "{}", err);
+
+        // update creates directory and file
+        check_gas_baseline(&baseline_dir, "execute", 1000, 5, true).unwrap();
+        let content = fs::read_to_string(baseline_dir.join("execute.json")).unwrap();
+        assert_eq!(content, "{\n  \"gas_used\": 1000\n}\n");
+
+        check_gas_baseline(&baseline_dir, "execute", 1000, 5, false).unwrap();
+        check_gas_baseline(&baseline_dir, "execute", 1049, 5, false).unwrap();
+        let err = check_gas_baseline(&baseline_dir, "execute", 1051, 5, false).unwrap_err();
+        assert!(
+            err.starts_with("execute: Gas used (1051) is not within 5%"),
+            "{}",
+            err
+        );
+
+        // invalid file
+        fs::write(baseline_dir.join("execute.json"), r#"{"gas":1000}"#).unwrap();
+        let err = check_gas_baseline(&baseline_dir, "execute", 1000, 5, false).unwrap_err();
+        assert!(err.starts_with("execute: invalid gas baseline"), "{}", err);
+    }
+
+    #[test]
+    fn assert_gas_baseline_works() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("execute.json"), r#"{"gas_used":1000}"#).unwrap();
+        assert_gas_baseline(&report(800, 200), dir.path(), "execute", 1);
+    }
+}
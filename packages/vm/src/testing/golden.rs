@@ -0,0 +1,214 @@
+//! Golden file testing for query responses.
+//!
+//! Query responses are stored as pretty-printed JSON files next to the integration tests.
+//! Running the tests with the environment variable `UPDATE_GOLDEN=1` (re-)creates those
+//! files instead of comparing against them. Review and check in the resulting changes.
+use std::fs;
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use cosmwasm_std::{ContractResult, Env, QueryResponse};
+
+use crate::instance::Instance;
+use crate::{BackendApi, Querier, Storage};
+
+use super::calls::query;
+
+/// Set this environment variable to `1` or `true` to write golden files instead of
+/// comparing against them
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// Runs all `queries` against the instance and compares the responses to the golden files
+/// `<dir>/<name>.json`.
+///
+/// Panics with a report of all mismatching or missing golden files.
+/// When the environment variable [`UPDATE_GOLDEN_ENV`] is set, the golden files are
+/// written instead.
+pub fn assert_query_responses_golden<A, S, Q, M>(
+    instance: &mut Instance<A, S, Q>,
+    env: Env,
+    dir: impl AsRef<Path>,
+    queries: &[(&str, M)],
+) where
+    A: BackendApi + 'static,
+    S: Storage + 'static,
+    Q: Querier + 'static,
+    M: Serialize + JsonSchema + Clone,
+{
+    let update = update_mode();
+    let failures: Vec<String> = queries
+        .iter()
+        .filter_map(|(name, msg)| {
+            let result = query(instance, env.clone(), msg.clone());
+            check_golden(dir.as_ref(), name, &golden_value(result), update).err()
+        })
+        .collect();
+    if !failures.is_empty() {
+        panic!(
+            "{} golden file(s) do not match. Run with {}=1 to update them.\n\n{}",
+            failures.len(),
+            UPDATE_GOLDEN_ENV,
+            failures.join("\n\n")
+        );
+    }
+}
+
+fn update_mode() -> bool {
+    matches!(
+        std::env::var(UPDATE_GOLDEN_ENV).as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+/// Converts a query result into the JSON stored in golden files.
+///
+/// Successful responses are embedded as JSON if possible, so the files stay readable.
+/// Errors are stored as their message.
+fn golden_value(result: ContractResult<QueryResponse>) -> Value {
+    match result {
+        ContractResult::Ok(data) => match serde_json::from_slice::<Value>(data.as_slice()) {
+            Ok(value) => json!({ "ok": value }),
+            Err(_) => json!({ "ok_binary": data.to_base64() }),
+        },
+        ContractResult::Err(msg) => json!({ "error": msg }),
+    }
+}
+
+/// Compares `actual` to the golden file of the given name, or writes it in update mode.
+/// Returns a human readable description of the problem on failure.
+fn check_golden(dir: &Path, name: &str, actual: &Value, update: bool) -> Result<(), String> {
+    let path = dir.join(format!("{}.json", name));
+    let actual_str = serde_json::to_string_pretty(actual).unwrap() + "\n";
+
+    if update {
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, actual_str))
+            .map_err(|e| format!("{}: could not write golden file: {}", name, e))?;
+        return Ok(());
+    }
+
+    let expected_str = fs::read_to_string(&path)
+        .map_err(|e| format!("{}: could not read {}: {}", name, path.display(), e))?;
+    let expected: Value = serde_json::from_str(&expected_str)
+        .map_err(|e| format!("{}: invalid JSON in {}: {}", name, path.display(), e))?;
+    if &expected != actual {
+        return Err(format!(
+            "{}: response differs from {}\nexpected:\n{}\nactual:\n{}",
+            name,
+            path.display(),
+            expected_str.trim_end(),
+            actual_str.trim_end()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{instantiate, mock_env, mock_info, mock_instance};
+    use cosmwasm_std::{Binary, Empty};
+    use tempfile::TempDir;
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+
+    #[test]
+    fn golden_value_works() {
+        let value = golden_value(ContractResult::Ok(Binary::from(br#"{"a":1}"#)));
+        assert_eq!(value, json!({ "ok": { "a": 1 } }));
+
+        let value = golden_value(ContractResult::Ok(Binary::from(b"\xff\x00")));
+        assert_eq!(value, json!({ "ok_binary": "/wA=" }));
+
+        let value = golden_value(ContractResult::Err("not found".to_string()));
+        assert_eq!(value, json!({ "error": "not found" }));
+    }
+
+    #[test]
+    fn check_golden_works() {
+        let dir = TempDir::new().unwrap();
+        let golden_dir = dir.path().join("golden");
+        let value = json!({ "ok": { "count": 3 } });
+
+        // missing file
+        let err = check_golden(&golden_dir, "count", &value, false).unwrap_err();
+        assert!(err.starts_with("count: could not read"), "{}", err);
+
+        // update creates directory and file
+        check_golden(&golden_dir, "count", &value, true).unwrap();
+        let content = fs::read_to_string(golden_dir.join("count.json")).unwrap();
+        assert_eq!(content, "{\n  \"ok\": {\n    \"count\": 3\n  }\n}\n");
+        check_golden(&golden_dir, "count", &value, false).unwrap();
+
+        // formatting of the golden file does not matter
+        fs::write(golden_dir.join("count.json"), r#"{"ok":{"count":3}}"#).unwrap();
+        check_golden(&golden_dir, "count", &value, false).unwrap();
+
+        // mismatch
+        let changed = json!({ "ok": { "count": 4 } });
+        let err = check_golden(&golden_dir, "count", &changed, false).unwrap_err();
+        assert!(err.starts_with("count: response differs from"), "{}", err);
+    }
+
+    #[derive(Serialize, JsonSchema, Clone)]
+    #[serde(rename_all = "snake_case")]
+    enum QueryMsg {
+        Verifier {},
+        Recurse { depth: u32, work: u32 },
+    }
+
+    #[test]
+    fn assert_query_responses_golden_works() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let info = mock_info("creator", &[]);
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        instantiate::<_, _, _, _, Empty>(
+            &mut instance,
+            mock_env(),
+            info,
+            serde_json::from_slice::<Value>(msg).unwrap(),
+        )
+        .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("verifier.json"),
+            r#"{"ok":{"verifier":"verifies"}}"#,
+        )
+        .unwrap();
+
+        let queries = [("verifier", QueryMsg::Verifier {})];
+        assert_query_responses_golden(&mut instance, mock_env(), dir.path(), &queries);
+    }
+
+    #[test]
+    #[should_panic(expected = "2 golden file(s) do not match")]
+    fn assert_query_responses_golden_panics_for_mismatch() {
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let info = mock_info("creator", &[]);
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        instantiate::<_, _, _, _, Empty>(
+            &mut instance,
+            mock_env(),
+            info,
+            serde_json::from_slice::<Value>(msg).unwrap(),
+        )
+        .unwrap();
+
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("verifier.json"),
+            r#"{"ok":{"verifier":"someone else"}}"#,
+        )
+        .unwrap();
+
+        let queries = [
+            ("verifier", QueryMsg::Verifier {}),
+            ("recurse", QueryMsg::Recurse { depth: 0, work: 0 }),
+        ];
+        assert_query_responses_golden(&mut instance, mock_env(), dir.path(), &queries);
+    }
+}
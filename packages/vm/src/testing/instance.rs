@@ -1,11 +1,15 @@
 //! This file has some helpers for integration tests.
 //! They should be imported via full path to ensure there is no confusion
 //! use cosmwasm_vm::testing::X
-use cosmwasm_std::Coin;
+use cosmwasm_std::{Coin, CustomQuery};
+use serde::de::DeserializeOwned;
 use std::collections::HashSet;
 
 use crate::capabilities::capabilities_from_csv;
 use crate::compatibility::check_wasm;
+use crate::environment::{
+    CallLimits, DebugLimits, Environment, GasConfig, GasMultiplier, StorageLimits, StorageReadCache,
+};
 use crate::instance::{Instance, InstanceOptions};
 use crate::size::Size;
 use crate::{Backend, BackendApi, Querier, Storage};
@@ -25,59 +29,45 @@ pub fn mock_instance(
     wasm: &[u8],
     contract_balance: &[Coin],
 ) -> Instance<MockApi, MockStorage, MockQuerier> {
-    mock_instance_with_options(
-        wasm,
-        MockInstanceOptions {
-            contract_balance: Some(contract_balance),
-            ..Default::default()
-        },
-    )
+    MockInstanceBuilder::new(wasm)
+        .contract_balance(contract_balance)
+        .build()
 }
 
+#[deprecated(
+    note = "Use MockInstanceBuilder::new(wasm).contract_balance(..).backend_error(..) instead"
+)]
 pub fn mock_instance_with_failing_api(
     wasm: &[u8],
     contract_balance: &[Coin],
     backend_error: &'static str,
 ) -> Instance<MockApi, MockStorage, MockQuerier> {
-    mock_instance_with_options(
-        wasm,
-        MockInstanceOptions {
-            contract_balance: Some(contract_balance),
-            backend_error: Some(backend_error),
-            ..Default::default()
-        },
-    )
+    MockInstanceBuilder::new(wasm)
+        .contract_balance(contract_balance)
+        .backend_error(backend_error)
+        .build()
 }
 
+#[deprecated(note = "Use MockInstanceBuilder::new(wasm).balances(..) instead")]
 pub fn mock_instance_with_balances(
     wasm: &[u8],
     balances: &[(&str, &[Coin])],
 ) -> Instance<MockApi, MockStorage, MockQuerier> {
-    mock_instance_with_options(
-        wasm,
-        MockInstanceOptions {
-            balances,
-            ..Default::default()
-        },
-    )
+    MockInstanceBuilder::new(wasm).balances(balances).build()
 }
 
 /// Creates an instance from the given Wasm bytecode.
 /// The gas limit is measured in [CosmWasm gas](https://github.com/CosmWasm/cosmwasm/blob/main/docs/GAS.md).
+#[deprecated(note = "Use MockInstanceBuilder::new(wasm).gas_limit(..) instead")]
 pub fn mock_instance_with_gas_limit(
     wasm: &[u8],
     gas_limit: u64,
 ) -> Instance<MockApi, MockStorage, MockQuerier> {
-    mock_instance_with_options(
-        wasm,
-        MockInstanceOptions {
-            gas_limit,
-            ..Default::default()
-        },
-    )
+    MockInstanceBuilder::new(wasm).gas_limit(gas_limit).build()
 }
 
 #[derive(Debug)]
+#[deprecated(note = "Use MockInstanceBuilder instead")]
 pub struct MockInstanceOptions<'a> {
     // dependencies
     pub balances: &'a [(&'a str, &'a [Coin])],
@@ -91,20 +81,15 @@ pub struct MockInstanceOptions<'a> {
     /// Gas limit measured in [CosmWasm gas](https://github.com/CosmWasm/cosmwasm/blob/main/docs/GAS.md).
     pub gas_limit: u64,
     pub print_debug: bool,
+    pub gas_config: GasConfig,
+    pub storage_limits: StorageLimits,
+    pub storage_read_cache: StorageReadCache,
+    pub debug_limits: DebugLimits,
     /// Memory limit in bytes. Use a value that is divisible by the Wasm page size 65536, e.g. full MiBs.
     pub memory_limit: Option<Size>,
 }
 
-impl MockInstanceOptions<'_> {
-    fn default_capabilities() -> HashSet<String> {
-        #[allow(unused_mut)]
-        let mut out = capabilities_from_csv("iterator,staking,cosmwasm_1_1");
-        #[cfg(feature = "stargate")]
-        out.insert("stargate".to_string());
-        out
-    }
-}
-
+#[allow(deprecated)]
 impl Default for MockInstanceOptions<'_> {
     fn default() -> Self {
         Self {
@@ -114,48 +99,321 @@ impl Default for MockInstanceOptions<'_> {
             backend_error: None,
 
             // instance
-            available_capabilities: Self::default_capabilities(),
+            available_capabilities: default_capabilities(),
             gas_limit: DEFAULT_GAS_LIMIT,
             print_debug: DEFAULT_PRINT_DEBUG,
+            gas_config: GasConfig::default(),
+            storage_limits: StorageLimits::default(),
+            storage_read_cache: StorageReadCache::default(),
+            debug_limits: DebugLimits::default(),
             memory_limit: DEFAULT_MEMORY_LIMIT,
         }
     }
 }
 
+#[deprecated(note = "Use MockInstanceBuilder instead")]
+#[allow(deprecated)]
 pub fn mock_instance_with_options(
     wasm: &[u8],
     options: MockInstanceOptions,
 ) -> Instance<MockApi, MockStorage, MockQuerier> {
-    check_wasm(wasm, &options.available_capabilities).unwrap();
-    let contract_address = MOCK_CONTRACT_ADDR;
-
-    // merge balances
-    let mut balances = options.balances.to_vec();
+    let mut builder = MockInstanceBuilder::new(wasm)
+        .balances(options.balances)
+        .capabilities(options.available_capabilities)
+        .gas_limit(options.gas_limit)
+        .print_debug(options.print_debug)
+        .gas_config(options.gas_config)
+        .storage_limits(options.storage_limits)
+        .storage_read_cache(options.storage_read_cache)
+        .debug_limits(options.debug_limits)
+        .memory_limit(options.memory_limit);
     if let Some(contract_balance) = options.contract_balance {
-        // Remove old entry if exists
-        if let Some(pos) = balances.iter().position(|item| item.0 == contract_address) {
-            balances.remove(pos);
+        builder = builder.contract_balance(contract_balance);
+    }
+    if let Some(backend_error) = options.backend_error {
+        builder = builder.backend_error(backend_error);
+    }
+    builder.build()
+}
+
+fn default_capabilities() -> HashSet<String> {
+    #[allow(unused_mut)]
+    let mut out = capabilities_from_csv("iterator,staking,cosmwasm_1_1");
+    #[cfg(feature = "stargate")]
+    out.insert("stargate".to_string());
+    out
+}
+
+/// Builds an instance for testing, backed by mock dependencies.
+///
+/// All settings are optional and default to values suitable for integration tests:
+///
+/// ```
+/// # use cosmwasm_std::coins;
+/// # use cosmwasm_vm::testing::MockInstanceBuilder;
+/// # let wasm = include_bytes!("../../testdata/hackatom.wasm");
+/// let instance = MockInstanceBuilder::new(wasm)
+///     .gas_limit(5_000_000_000)
+///     .contract_balance(&coins(123, "ucosm"))
+///     .build();
+/// ```
+///
+/// Storage and querier are owned by the instance once built. Use
+/// [`MockInstanceBuilder::build_with_handles`] to get handles to them along with the instance,
+/// or provide pre-populated dependencies via [`MockInstanceBuilder::backend`].
+pub struct MockInstanceBuilder<'a> {
+    wasm: &'a [u8],
+
+    // dependencies
+    backend: Option<Backend<MockApi, MockStorage, MockQuerier>>,
+    balances: Vec<(&'a str, &'a [Coin])>,
+    contract_balance: Option<&'a [Coin]>,
+    backend_error: Option<&'static str>,
+
+    // instance
+    available_capabilities: HashSet<String>,
+    gas_limit: u64,
+    print_debug: bool,
+    gas_config: GasConfig,
+    storage_limits: StorageLimits,
+    storage_read_cache: StorageReadCache,
+    debug_limits: DebugLimits,
+    call_limits: CallLimits,
+    allow_reentrant_queries: bool,
+    query_depth: u32,
+    validate_msg_addresses: bool,
+    gas_multiplier: GasMultiplier,
+    memory_limit: Option<Size>,
+}
+
+impl<'a> MockInstanceBuilder<'a> {
+    pub fn new(wasm: &'a [u8]) -> Self {
+        MockInstanceBuilder {
+            wasm,
+            backend: None,
+            balances: vec![],
+            contract_balance: None,
+            backend_error: None,
+            available_capabilities: default_capabilities(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            print_debug: DEFAULT_PRINT_DEBUG,
+            gas_config: GasConfig::default(),
+            storage_limits: StorageLimits::default(),
+            storage_read_cache: StorageReadCache::default(),
+            debug_limits: DebugLimits::default(),
+            call_limits: CallLimits::default(),
+            allow_reentrant_queries: false,
+            query_depth: 0,
+            validate_msg_addresses: false,
+            gas_multiplier: GasMultiplier::ONE,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
         }
-        balances.push((contract_address, contract_balance));
     }
 
-    let api = if let Some(backend_error) = options.backend_error {
-        MockApi::new_failing(backend_error)
-    } else {
-        MockApi::default()
-    };
+    /// Uses the given backend instead of creating a new one.
+    ///
+    /// This allows starting with pre-populated storage or a custom querier.
+    /// `balances`, `contract_balance` and `backend_error` are ignored in this case.
+    pub fn backend(mut self, backend: Backend<MockApi, MockStorage, MockQuerier>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Bank balances known to the querier
+    pub fn balances(mut self, balances: &[(&'a str, &'a [Coin])]) -> Self {
+        self.balances = balances.to_vec();
+        self
+    }
+
+    /// Bank balance of the contract. This is merged into balances and might override an existing value.
+    pub fn contract_balance(mut self, contract_balance: &'a [Coin]) -> Self {
+        self.contract_balance = Some(contract_balance);
+        self
+    }
+
+    /// When set, all calls to the API fail with BackendError::Unknown containing this message
+    pub fn backend_error(mut self, backend_error: &'static str) -> Self {
+        self.backend_error = Some(backend_error);
+        self
+    }
+
+    /// Capabilities available to the contract. The contract is checked against those.
+    pub fn capabilities(mut self, capabilities: HashSet<String>) -> Self {
+        self.available_capabilities = capabilities;
+        self
+    }
+
+    /// Gas limit measured in [CosmWasm gas](https://github.com/CosmWasm/cosmwasm/blob/main/docs/GAS.md).
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn print_debug(mut self, print_debug: bool) -> Self {
+        self.print_debug = print_debug;
+        self
+    }
+
+    pub fn gas_config(mut self, gas_config: GasConfig) -> Self {
+        self.gas_config = gas_config;
+        self
+    }
+
+    pub fn storage_limits(mut self, storage_limits: StorageLimits) -> Self {
+        self.storage_limits = storage_limits;
+        self
+    }
+
+    pub fn storage_read_cache(mut self, storage_read_cache: StorageReadCache) -> Self {
+        self.storage_read_cache = storage_read_cache;
+        self
+    }
+
+    pub fn debug_limits(mut self, debug_limits: DebugLimits) -> Self {
+        self.debug_limits = debug_limits;
+        self
+    }
+
+    pub fn max_msg_size(mut self, max_msg_size: Size) -> Self {
+        self.call_limits.max_msg_size = max_msg_size;
+        self
+    }
+
+    pub fn allow_reentrant_queries(mut self, allow_reentrant_queries: bool) -> Self {
+        self.allow_reentrant_queries = allow_reentrant_queries;
+        self
+    }
+
+    /// Sets the query depth, e.g. to simulate an instance created for a nested contract query
+    pub fn query_depth(mut self, query_depth: u32) -> Self {
+        self.query_depth = query_depth;
+        self
+    }
+
+    pub fn max_query_depth(mut self, max_query_depth: u32) -> Self {
+        self.call_limits.max_query_depth = max_query_depth;
+        self
+    }
+
+    pub fn validate_msg_addresses(mut self, validate_msg_addresses: bool) -> Self {
+        self.validate_msg_addresses = validate_msg_addresses;
+        self
+    }
+
+    pub fn gas_multiplier(mut self, gas_multiplier: GasMultiplier) -> Self {
+        self.gas_multiplier = gas_multiplier;
+        self
+    }
+
+    /// Memory limit in bytes. Use a value that is divisible by the Wasm page size 65536, e.g. full MiBs.
+    pub fn memory_limit(mut self, memory_limit: Option<Size>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    pub fn build(self) -> Instance<MockApi, MockStorage, MockQuerier> {
+        check_wasm(self.wasm, &self.available_capabilities).unwrap();
+
+        let backend = match self.backend {
+            Some(backend) => backend,
+            None => {
+                let contract_address = MOCK_CONTRACT_ADDR;
+
+                // merge balances
+                let mut balances = self.balances;
+                if let Some(contract_balance) = self.contract_balance {
+                    // Remove old entry if exists
+                    if let Some(pos) = balances.iter().position(|item| item.0 == contract_address) {
+                        balances.remove(pos);
+                    }
+                    balances.push((contract_address, contract_balance));
+                }
+
+                let api = if let Some(backend_error) = self.backend_error {
+                    MockApi::new_failing(backend_error)
+                } else {
+                    MockApi::default()
+                };
+
+                Backend {
+                    api,
+                    storage: MockStorage::default(),
+                    querier: MockQuerier::new(&balances),
+                }
+            }
+        };
+        let options = InstanceOptions {
+            gas_limit: self.gas_limit,
+            print_debug: self.print_debug,
+            gas_config: self.gas_config,
+            storage_limits: self.storage_limits,
+            storage_read_cache: self.storage_read_cache,
+            debug_limits: self.debug_limits,
+            call_limits: self.call_limits,
+            allow_reentrant_queries: self.allow_reentrant_queries,
+            query_depth: self.query_depth,
+            validate_msg_addresses: self.validate_msg_addresses,
+            gas_multiplier: self.gas_multiplier,
+        };
+        Instance::from_code(self.wasm, backend, options, self.memory_limit).unwrap()
+    }
+
+    /// Like [`MockInstanceBuilder::build`] but also returns handles to the storage and
+    /// querier of the instance
+    pub fn build_with_handles(
+        self,
+    ) -> (
+        Instance<MockApi, MockStorage, MockQuerier>,
+        MockStorageHandle,
+        MockQuerierHandle,
+    ) {
+        let instance = self.build();
+        let storage = MockStorageHandle {
+            env: instance.shared_env(),
+        };
+        let querier = MockQuerierHandle {
+            env: instance.shared_env(),
+        };
+        (instance, storage, querier)
+    }
+}
+
+/// A handle to the storage of an instance created by
+/// [`MockInstanceBuilder::build_with_handles`]. It accesses the same storage as the instance
+/// and can be cloned.
+#[derive(Clone)]
+pub struct MockStorageHandle {
+    env: Environment<MockApi, MockStorage, MockQuerier>,
+}
+
+impl MockStorageHandle {
+    /// Runs `func` with the storage, e.g. to set up or inspect the contract's state.
+    ///
+    /// Panics if the storage was moved out of the instance, e.g. by [`Instance::recycle`].
+    pub fn with<T>(&self, func: impl FnOnce(&mut MockStorage) -> T) -> T {
+        self.env
+            .with_storage_from_context(|storage| Ok(func(storage)))
+            .expect("Testing error: Could not access storage")
+    }
+}
+
+/// A handle to the querier of an instance created by
+/// [`MockInstanceBuilder::build_with_handles`]. It accesses the same querier as the instance
+/// and can be cloned.
+#[derive(Clone)]
+pub struct MockQuerierHandle {
+    env: Environment<MockApi, MockStorage, MockQuerier>,
+}
 
-    let backend = Backend {
-        api,
-        storage: MockStorage::default(),
-        querier: MockQuerier::new(&balances),
-    };
-    let memory_limit = options.memory_limit;
-    let options = InstanceOptions {
-        gas_limit: options.gas_limit,
-        print_debug: options.print_debug,
-    };
-    Instance::from_code(wasm, backend, options, memory_limit).unwrap()
+impl MockQuerierHandle {
+    /// Runs `func` with the querier, e.g. to update balances or register contracts.
+    ///
+    /// Panics if the querier was moved out of the instance, e.g. by [`Instance::recycle`].
+    pub fn with<T>(&self, func: impl FnOnce(&mut MockQuerier) -> T) -> T {
+        self.env
+            .with_querier_from_context(|querier| Ok(func(querier)))
+            .expect("Testing error: Could not access querier")
+    }
 }
 
 /// Creates InstanceOptions for testing
@@ -164,11 +422,42 @@ pub fn mock_instance_options() -> (InstanceOptions, Option<Size>) {
         InstanceOptions {
             gas_limit: DEFAULT_GAS_LIMIT,
             print_debug: DEFAULT_PRINT_DEBUG,
+            gas_config: GasConfig::default(),
+            storage_limits: StorageLimits::default(),
+            storage_read_cache: StorageReadCache::default(),
+            debug_limits: DebugLimits::default(),
+            call_limits: CallLimits::default(),
+            allow_reentrant_queries: false,
+            query_depth: 0,
+            validate_msg_addresses: false,
+            gas_multiplier: GasMultiplier::ONE,
         },
         DEFAULT_MEMORY_LIMIT,
     )
 }
 
+/// Registers a snapshot of the instance's storage in its [`MockQuerier`], such that
+/// `WasmQuery::Raw` requests to `contract_addr` (usually [`MOCK_CONTRACT_ADDR`]) can read it.
+///
+/// Call this again after executions which change the contract's state.
+pub fn register_own_storage<A, C>(
+    instance: &mut Instance<A, MockStorage, MockQuerier<C>>,
+    contract_addr: &str,
+) where
+    A: BackendApi + 'static,
+    C: CustomQuery + DeserializeOwned + 'static,
+{
+    let storage = instance
+        .with_storage(|storage| Ok(storage.clone()))
+        .unwrap();
+    instance
+        .with_querier(|querier| {
+            querier.register_contract_storage(contract_addr, storage);
+            Ok(())
+        })
+        .unwrap();
+}
+
 /// Runs a series of IO tests, hammering especially on allocate and deallocate.
 /// This could be especially useful when run with some kind of leak detector.
 pub fn test_io<A, S, Q>(instance: &mut Instance<A, S, Q>)
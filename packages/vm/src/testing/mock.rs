@@ -1,6 +1,10 @@
-use cosmwasm_std::testing::{digit_sum, riffle_shuffle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use cosmwasm_std::testing::{digit_sum, riffle_shuffle, AddressCodec};
 use cosmwasm_std::{
-    Addr, BlockInfo, Coin, ContractInfo, Env, MessageInfo, Timestamp, TransactionInfo,
+    Addr, BlockInfo, CanonicalAddr, Coin, ContractInfo, Env, MessageInfo, Timestamp,
+    TransactionInfo,
 };
 
 use super::querier::MockQuerier;
@@ -41,52 +45,269 @@ const CANONICAL_LENGTH: usize = 54;
 const SHUFFLES_ENCODE: usize = 18;
 const SHUFFLES_DECODE: usize = 2;
 
+/// How [`MockApi`] treats the casing of human addresses in the mock format.
+/// Addresses in the format of an [`AddressCodec`] are handled by the codec.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaseNormalization {
+    /// Converts addresses to lowercase, such that all casings of an address are valid.
+    /// This mimicks formats like hex or bech32 and is the default.
+    Lowercase,
+    /// Treats addresses as case sensitive
+    Preserve,
+    /// Like `Lowercase` but rejects addresses that contain both upper and lower case
+    /// letters, like bech32 does.
+    RejectMixed,
+}
+
+/// A failure injected via [`MockApiBuilder::fail_on_call`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InjectedFailure {
+    /// The 1-based number of the API call that fails. Calls of `canonical_address`
+    /// and `human_address` are counted together.
+    pub call: usize,
+    /// The message of the BackendError::Unknown returned by the failing call
+    pub message: &'static str,
+}
+
 /// Zero-pads all human addresses to make them fit the canonical_length and
 /// trims off zeros for the reverse operation.
 /// This is not really smart, but allows us to see a difference (and consistent length for canonical adddresses).
-#[derive(Copy, Clone)]
+///
+/// Clones of a `MockApi` share the counter of API calls used for [`MockApiBuilder::fail_on_call`].
+#[derive(Clone)]
 pub struct MockApi {
     /// Length of canonical addresses created with this API. Contracts should not make any assumtions
     /// what this value is.
     canonical_length: usize,
+    /// The number of in-shuffles that restore the original after `SHUFFLES_ENCODE` in-shuffles
+    /// for canonical addresses of `canonical_length`
+    shuffles_decode: usize,
     /// When set, all calls to the API fail with BackendError::Unknown containing this message
     backend_error: Option<&'static str>,
+    /// When set, addresses are encoded in this format instead of the mock format
+    address_codec: Option<AddressCodec>,
+    case_normalization: CaseNormalization,
+    injected_failure: Option<InjectedFailure>,
+    /// The number of API calls, which is only counted if a failure is injected
+    calls: Option<Arc<AtomicUsize>>,
 }
 
 impl MockApi {
+    pub fn builder() -> MockApiBuilder {
+        MockApiBuilder::default()
+    }
+
     /// Read-only getter for `canonical_length`, which must not be changed by the caller.
     pub fn canonical_length(&self) -> usize {
         self.canonical_length
     }
 
     pub fn new_failing(backend_error: &'static str) -> Self {
+        Self::builder().backend_error(backend_error).build()
+    }
+
+    /// Uses the given address format instead of the default mock format, e.g.
+    /// bech32 addresses with a chain specific prefix or EVM hex addresses.
+    pub fn with_address_codec(self, address_codec: AddressCodec) -> Self {
         MockApi {
-            backend_error: Some(backend_error),
-            ..MockApi::default()
+            address_codec: Some(address_codec),
+            ..self
+        }
+    }
+
+    /// The message all calls fail with, if set
+    pub fn backend_error(&self) -> Option<&'static str> {
+        self.backend_error
+    }
+
+    pub fn address_codec(&self) -> Option<AddressCodec> {
+        self.address_codec
+    }
+
+    pub fn case_normalization(&self) -> CaseNormalization {
+        self.case_normalization
+    }
+
+    pub fn injected_failure(&self) -> Option<InjectedFailure> {
+        self.injected_failure
+    }
+
+    /// The number of calls of `canonical_address` and `human_address` on this API and its clones.
+    /// Calls are only counted if a failure is injected via [`MockApiBuilder::fail_on_call`].
+    pub fn calls(&self) -> Option<usize> {
+        self.calls
+            .as_ref()
+            .map(|calls| calls.load(Ordering::SeqCst))
+    }
+
+    /// Counts the call and returns the error this call should fail with, if any
+    fn start_call(&self) -> Option<BackendError> {
+        let call = self
+            .calls
+            .as_ref()
+            .map(|calls| calls.fetch_add(1, Ordering::SeqCst) + 1);
+        if let Some(backend_error) = self.backend_error {
+            return Some(BackendError::unknown(backend_error));
+        }
+        match (self.injected_failure, call) {
+            (Some(failure), Some(call)) if failure.call == call => {
+                Some(BackendError::unknown(failure.message))
+            }
+            _ => None,
+        }
+    }
+
+    /// Normalizes the casing of a human address in the mock format
+    fn normalize(&self, input: &str) -> Result<String, BackendError> {
+        match self.case_normalization {
+            CaseNormalization::Lowercase => Ok(input.to_lowercase()),
+            CaseNormalization::Preserve => Ok(input.to_string()),
+            CaseNormalization::RejectMixed => {
+                let has_upper = input.chars().any(char::is_uppercase);
+                let has_lower = input.chars().any(char::is_lowercase);
+                if has_upper && has_lower {
+                    Err(BackendError::user_err(
+                        "Invalid input: human address must not be mixed-case",
+                    ))
+                } else {
+                    Ok(input.to_lowercase())
+                }
+            }
         }
     }
 }
 
 impl Default for MockApi {
     fn default() -> Self {
-        MockApi {
+        Self::builder().build()
+    }
+}
+
+/// Creates a [`MockApi`] with custom behaviour, e.g. to test address handling edge cases.
+///
+/// ## Examples
+///
+/// ```
+/// # use cosmwasm_vm::BackendApi;
+/// # use cosmwasm_vm::testing::{CaseNormalization, MockApi};
+/// let api = MockApi::builder()
+///     .canonical_length(8)
+///     .case_normalization(CaseNormalization::RejectMixed)
+///     .fail_on_call(3, "Temporarily unavailable")
+///     .build();
+/// assert_eq!(api.canonical_address("addr1").0.unwrap().len(), 8);
+/// api.canonical_address("ADDR1").0.unwrap();
+/// api.canonical_address("addr1").0.unwrap_err(); // third call
+/// api.canonical_address("Addr1").0.unwrap_err(); // mixed-case
+/// api.canonical_address("overlong1").0.unwrap_err(); // longer than canonical length
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct MockApiBuilder {
+    canonical_length: usize,
+    backend_error: Option<&'static str>,
+    address_codec: Option<AddressCodec>,
+    case_normalization: CaseNormalization,
+    injected_failure: Option<InjectedFailure>,
+}
+
+impl Default for MockApiBuilder {
+    fn default() -> Self {
+        MockApiBuilder {
             canonical_length: CANONICAL_LENGTH,
             backend_error: None,
+            address_codec: None,
+            case_normalization: CaseNormalization::Lowercase,
+            injected_failure: None,
+        }
+    }
+}
+
+impl MockApiBuilder {
+    /// Sets the length of canonical addresses in the mock format, which is also the maximum
+    /// length of human addresses. Must be even and non-zero.
+    pub fn canonical_length(mut self, canonical_length: usize) -> Self {
+        self.canonical_length = canonical_length;
+        self
+    }
+
+    /// Lets all calls to the API fail with BackendError::Unknown containing this message
+    pub fn backend_error(mut self, backend_error: &'static str) -> Self {
+        self.backend_error = Some(backend_error);
+        self
+    }
+
+    /// Uses the given address format instead of the default mock format
+    pub fn address_codec(mut self, address_codec: AddressCodec) -> Self {
+        self.address_codec = Some(address_codec);
+        self
+    }
+
+    pub fn case_normalization(mut self, case_normalization: CaseNormalization) -> Self {
+        self.case_normalization = case_normalization;
+        self
+    }
+
+    /// Lets the `call`-th API call (1-based) fail with BackendError::Unknown containing
+    /// the message. All other calls behave normally.
+    pub fn fail_on_call(mut self, call: usize, message: &'static str) -> Self {
+        self.injected_failure = Some(InjectedFailure { call, message });
+        self
+    }
+
+    pub fn build(self) -> MockApi {
+        assert!(
+            self.canonical_length != 0 && self.canonical_length % 2 == 0,
+            "Canonical length must be even and non-zero"
+        );
+        MockApi {
+            canonical_length: self.canonical_length,
+            shuffles_decode: shuffles_decode(self.canonical_length),
+            backend_error: self.backend_error,
+            address_codec: self.address_codec,
+            case_normalization: self.case_normalization,
+            injected_failure: self.injected_failure,
+            calls: self.injected_failure.map(|_| Arc::new(AtomicUsize::new(0))),
         }
     }
 }
 
+/// The number of in-shuffles needed after `SHUFFLES_ENCODE` in-shuffles to restore the
+/// original order of `length` elements
+fn shuffles_decode(length: usize) -> usize {
+    if length == CANONICAL_LENGTH {
+        return SHUFFLES_DECODE;
+    }
+    let original: Vec<usize> = (0..length).collect();
+    let mut shuffled = riffle_shuffle(&original);
+    let mut period = 1;
+    while shuffled != original {
+        shuffled = riffle_shuffle(&shuffled);
+        period += 1;
+    }
+    (period - SHUFFLES_ENCODE % period) % period
+}
+
 impl BackendApi for MockApi {
     fn canonical_address(&self, input: &str) -> BackendResult<Vec<u8>> {
-        // mimicks formats like hex or bech32 where different casings are valid for one address
-        let normalized = input.to_lowercase();
-
         let gas_info = GasInfo::with_cost(GAS_COST_CANONICALIZE);
 
-        if let Some(backend_error) = self.backend_error {
-            return (Err(BackendError::unknown(backend_error)), gas_info);
+        if let Some(error) = self.start_call() {
+            return (Err(error), gas_info);
+        }
+
+        if let Some(address_codec) = self.address_codec {
+            let result = address_codec
+                .canonicalize(input)
+                .map(Vec::from)
+                .map_err(|e| BackendError::user_err(e.to_string()));
+            return (result, gas_info);
         }
 
+        let normalized = match self.normalize(input) {
+            Ok(normalized) => normalized,
+            Err(error) => return (Err(error), gas_info),
+        };
+
         // Dummy input validation. This is more sophisticated for formats like bech32, where format and checksum are validated.
         if normalized.len() < 3 {
             return (
@@ -121,8 +342,16 @@ impl BackendApi for MockApi {
     fn human_address(&self, canonical: &[u8]) -> BackendResult<String> {
         let gas_info = GasInfo::with_cost(GAS_COST_HUMANIZE);
 
-        if let Some(backend_error) = self.backend_error {
-            return (Err(BackendError::unknown(backend_error)), gas_info);
+        if let Some(error) = self.start_call() {
+            return (Err(error), gas_info);
+        }
+
+        if let Some(address_codec) = self.address_codec {
+            let result = address_codec
+                .humanize(&CanonicalAddr::from(canonical))
+                .map(String::from)
+                .map_err(|e| BackendError::user_err(e.to_string()));
+            return (result, gas_info);
         }
 
         if canonical.len() != self.canonical_length {
@@ -136,7 +365,7 @@ impl BackendApi for MockApi {
 
         let mut tmp: Vec<u8> = canonical.into();
         // Shuffle two more times which restored the original value (24 elements are back to original after 20 rounds)
-        for _ in 0..SHUFFLES_DECODE {
+        for _ in 0..self.shuffles_decode {
             tmp = riffle_shuffle(&tmp);
         }
         // Rotate back
@@ -261,4 +490,134 @@ mod tests {
             err => panic!("Unexpected error: {:?}", err),
         }
     }
+
+    #[test]
+    fn address_codec_bech32_works() {
+        let api = MockApi::default().with_address_codec(AddressCodec::Bech32 { prefix: "juno" });
+        let canonical = vec![0x12; 20];
+        let human = api.human_address(&canonical).0.unwrap();
+        assert!(human.starts_with("juno1"));
+        assert_eq!(api.canonical_address(&human).0.unwrap(), canonical);
+
+        match api.canonical_address("foobar123").0.unwrap_err() {
+            BackendError::UserErr { .. } => {}
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn address_codec_evm_hex_works() {
+        let api = MockApi::default().with_address_codec(AddressCodec::EvmHex);
+        let human = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let canonical = api.canonical_address(&human.to_lowercase()).0.unwrap();
+        assert_eq!(canonical.len(), 20);
+        assert_eq!(api.human_address(&canonical).0.unwrap(), human);
+
+        match api.human_address(&[0x12; 32]).0.unwrap_err() {
+            BackendError::UserErr { .. } => {}
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn builder_canonical_length_works() {
+        for length in [4, 8, 20, 32, 54, 64, 100] {
+            let api = MockApi::builder().canonical_length(length).build();
+            assert_eq!(api.canonical_length(), length);
+            for original_length in [3, length - 1, length] {
+                let original = &"cosmwasm".repeat(20)[..original_length];
+                let canonical = api.canonical_address(original).0.unwrap();
+                assert_eq!(canonical.len(), length);
+                assert_eq!(api.human_address(&canonical).0.unwrap(), original);
+            }
+
+            // overlong address
+            let overlong = "a".repeat(length + 1);
+            match api.canonical_address(&overlong).0.unwrap_err() {
+                BackendError::UserErr { msg, .. } => assert!(msg.contains("too long")),
+                err => panic!("Unexpected error: {:?}", err),
+            }
+        }
+
+        // shuffling is still effective for custom lengths
+        let api = MockApi::builder().canonical_length(20).build();
+        let original = "foobar123";
+        let canonical = api.canonical_address(original).0.unwrap();
+        assert_ne!(&canonical[..original.len()], original.as_bytes());
+        assert_eq!(api.human_address(&canonical).0.unwrap(), original);
+    }
+
+    #[test]
+    #[should_panic(expected = "Canonical length must be even and non-zero")]
+    fn builder_canonical_length_must_be_even() {
+        MockApi::builder().canonical_length(21).build();
+    }
+
+    #[test]
+    fn builder_case_normalization_works() {
+        let api = MockApi::default();
+        assert_eq!(api.case_normalization(), CaseNormalization::Lowercase);
+
+        let api = MockApi::builder()
+            .case_normalization(CaseNormalization::Preserve)
+            .build();
+        let canonical = api.canonical_address("CosmWasmChef").0.unwrap();
+        assert_eq!(api.human_address(&canonical).0.unwrap(), "CosmWasmChef");
+        assert_ne!(canonical, api.canonical_address("cosmwasmchef").0.unwrap());
+
+        let api = MockApi::builder()
+            .case_normalization(CaseNormalization::RejectMixed)
+            .build();
+        let canonical = api.canonical_address("COSMWASMCHEF").0.unwrap();
+        assert_eq!(api.human_address(&canonical).0.unwrap(), "cosmwasmchef");
+        assert_eq!(canonical, api.canonical_address("cosmwasmchef").0.unwrap());
+        match api.canonical_address("CosmWasmChef").0.unwrap_err() {
+            BackendError::UserErr { msg, .. } => assert!(msg.contains("mixed-case")),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn builder_fail_on_call_works() {
+        let api = MockApi::builder()
+            .fail_on_call(2, "Temporarily unavailable")
+            .build();
+        assert_eq!(
+            api.injected_failure(),
+            Some(InjectedFailure {
+                call: 2,
+                message: "Temporarily unavailable"
+            })
+        );
+        assert_eq!(api.backend_error(), None);
+
+        let canonical = api.canonical_address("foobar123").0.unwrap();
+        // clones share the call counter
+        let copy = api.clone();
+        match copy.human_address(&canonical).0.unwrap_err() {
+            BackendError::Unknown { msg, .. } => assert_eq!(msg, "Temporarily unavailable"),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(api.human_address(&canonical).0.unwrap(), "foobar123");
+        assert_eq!(api.calls(), Some(3));
+
+        // different APIs count separately
+        let other = MockApi::builder().fail_on_call(1, "Unavailable").build();
+        assert_eq!(other.calls(), Some(0));
+        // calls are only counted with an injected failure
+        assert_eq!(MockApi::default().calls(), None);
+    }
+
+    #[test]
+    fn new_failing_works() {
+        let api = MockApi::new_failing("Temporarily unavailable");
+        assert_eq!(api.backend_error(), Some("Temporarily unavailable"));
+        for _ in 0..2 {
+            match api.canonical_address("foobar123").0.unwrap_err() {
+                BackendError::Unknown { msg, .. } => assert_eq!(msg, "Temporarily unavailable"),
+                err => panic!("Unexpected error: {:?}", err),
+            }
+        }
+        assert_eq!(api.calls(), None);
+    }
 }
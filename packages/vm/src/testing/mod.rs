@@ -1,24 +1,45 @@
 // The external interface is `use cosmwasm_vm::testing::X` for all integration testing symbols, no matter where they live internally.
 
+mod block;
 mod calls;
+mod check;
+mod gas;
+mod golden;
 mod instance;
 mod mock;
 mod querier;
 mod storage;
+#[cfg(feature = "stress")]
+mod stress;
 
-pub use calls::{execute, instantiate, migrate, query, reply, sudo};
+pub use block::{Block, BlockEntryPoint, BlockMsgOutcome, BlockOutcome};
+pub use calls::{
+    execute, execute_many, execute_with_report, instantiate, instantiate_from_wasm_msg,
+    instantiate_with_options, instantiate_with_report, migrate, query, query_with_report, reply,
+    sudo, CallReport, ExecuteManyError, InstantiateOptions, InstantiateOutcome,
+};
 #[cfg(feature = "stargate")]
 pub use calls::{
-    ibc_channel_close, ibc_channel_connect, ibc_channel_open, ibc_packet_ack, ibc_packet_receive,
-    ibc_packet_timeout,
+    ibc_channel_close, ibc_channel_connect, ibc_channel_open, ibc_destination_callback,
+    ibc_packet_ack, ibc_packet_receive, ibc_packet_timeout, ibc_source_callback,
 };
+pub use check::{check_contract, ContractCheckReport};
+pub use gas::{assert_gas_baseline, assert_gas_within, UPDATE_GAS_BASELINE_ENV};
+pub use golden::{assert_query_responses_golden, UPDATE_GOLDEN_ENV};
+#[allow(deprecated)]
 pub use instance::{
     mock_instance, mock_instance_options, mock_instance_with_balances,
     mock_instance_with_failing_api, mock_instance_with_gas_limit, mock_instance_with_options,
-    test_io, MockInstanceOptions,
+    register_own_storage, test_io, MockInstanceBuilder, MockInstanceOptions, MockQuerierHandle,
+    MockStorageHandle,
 };
 pub use mock::{
-    mock_backend, mock_backend_with_balances, mock_env, mock_info, MockApi, MOCK_CONTRACT_ADDR,
+    mock_backend, mock_backend_with_balances, mock_env, mock_info, CaseNormalization,
+    InjectedFailure, MockApi, MockApiBuilder, MOCK_CONTRACT_ADDR,
 };
 pub use querier::MockQuerier;
-pub use storage::MockStorage;
+#[cfg(feature = "iterator")]
+pub use storage::{expected_keys, prefix_range, scan_keys};
+pub use storage::{with_storage_diff, MockStorage, StorageDiff, StorageSnapshot};
+#[cfg(feature = "stress")]
+pub use stress::{stress, StressOptions, StressOutcome, StressReport};
@@ -1,12 +1,15 @@
 use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 
 use cosmwasm_std::testing::{MockQuerier as StdMockQuerier, MockQuerierCustomHandlerResult};
 use cosmwasm_std::{
-    to_binary, to_vec, Binary, Coin, ContractResult, CustomQuery, Empty, Querier as _,
-    QueryRequest, SystemError, SystemResult,
+    from_slice, to_binary, to_vec, Binary, Coin, ContractResult, CustomQuery, Empty, Querier as _,
+    QueryRequest, SystemError, SystemResult, WasmQuery,
 };
 
-use crate::{BackendError, BackendResult, GasInfo, Querier};
+use crate::{BackendError, BackendResult, GasInfo, Querier, Storage};
+
+use super::storage::MockStorage;
 
 const GAS_COST_QUERY_FLAT: u64 = 100_000;
 /// Gas per request byte
@@ -17,12 +20,15 @@ const GAS_COST_QUERY_RESPONSE_MULTIPLIER: u64 = 100;
 /// MockQuerier holds an immutable table of bank balances
 pub struct MockQuerier<C: CustomQuery + DeserializeOwned = Empty> {
     querier: StdMockQuerier<C>,
+    /// Storages of other contracts used to answer `WasmQuery::Raw`, by contract address
+    contract_storages: HashMap<String, MockStorage>,
 }
 
 impl<C: CustomQuery + DeserializeOwned> MockQuerier<C> {
     pub fn new(balances: &[(&str, &[Coin])]) -> Self {
         MockQuerier {
             querier: StdMockQuerier::new(balances),
+            contract_storages: HashMap::new(),
         }
     }
 
@@ -52,6 +58,48 @@ impl<C: CustomQuery + DeserializeOwned> MockQuerier<C> {
         self.querier.update_wasm(handler)
     }
 
+    /// Registers the storage of the contract at the given address and returns the previously
+    /// registered one. `WasmQuery::Raw` requests to this contract are then answered from that
+    /// storage. All other Wasm queries are still handled by the handler set in `update_wasm`.
+    ///
+    /// The querier owns the storage, so register it again after changing the contract's state.
+    /// Use [`register_own_storage`](super::register_own_storage) to make the storage of
+    /// the instance under test available.
+    pub fn register_contract_storage(
+        &mut self,
+        contract_addr: impl Into<String>,
+        storage: MockStorage,
+    ) -> Option<MockStorage> {
+        self.contract_storages.insert(contract_addr.into(), storage)
+    }
+
+    /// Answers `WasmQuery::Raw` requests to contracts with a registered storage.
+    /// Returns None for all other requests.
+    fn query_contract_storage(
+        &self,
+        bin_request: &[u8],
+    ) -> Option<SystemResult<ContractResult<Binary>>> {
+        match from_slice::<QueryRequest<C>>(bin_request).ok()? {
+            QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
+                let storage = self.contract_storages.get(&contract_addr)?;
+                let value = match storage.get(&key).0 {
+                    Ok(value) => value,
+                    Err(err) => {
+                        return Some(SystemResult::Err(SystemError::InvalidRequest {
+                            error: err.to_string(),
+                            request: Binary::from(bin_request),
+                        }))
+                    }
+                };
+                // Like the chain, respond with empty data for missing keys
+                Some(SystemResult::Ok(ContractResult::Ok(
+                    value.unwrap_or_default().into(),
+                )))
+            }
+            _ => None,
+        }
+    }
+
     pub fn with_custom_handler<CH: 'static>(mut self, handler: CH) -> Self
     where
         CH: Fn(&C) -> MockQuerierCustomHandlerResult,
@@ -67,7 +115,9 @@ impl<C: CustomQuery + DeserializeOwned> Querier for MockQuerier<C> {
         bin_request: &[u8],
         gas_limit: u64,
     ) -> BackendResult<SystemResult<ContractResult<Binary>>> {
-        let response = self.querier.raw_query(bin_request);
+        let response = self
+            .query_contract_storage(bin_request)
+            .unwrap_or_else(|| self.querier.raw_query(bin_request));
         let gas_info = GasInfo::with_externally_used(
             GAS_COST_QUERY_FLAT
                 + (GAS_COST_QUERY_REQUEST_MULTIPLIER * (bin_request.len() as u64))
@@ -131,6 +181,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn wasm_querier_raw_uses_registered_storage() {
+        let mut storage = MockStorage::new();
+        storage
+            .set(b"config", b"{\"owner\":\"creator\"}")
+            .0
+            .unwrap();
+        let mut querier: MockQuerier<Empty> = MockQuerier::new(&[]);
+        assert!(querier.register_contract_storage("peer", storage).is_none());
+
+        let raw = |contract_addr: &str, key: &[u8]| {
+            querier
+                .query::<Empty>(
+                    &WasmQuery::Raw {
+                        contract_addr: contract_addr.to_string(),
+                        key: key.into(),
+                    }
+                    .into(),
+                    DEFAULT_QUERY_GAS_LIMIT,
+                )
+                .0
+                .unwrap()
+        };
+
+        // existing key
+        let value = raw("peer", b"config").unwrap().unwrap();
+        assert_eq!(value.as_slice(), b"{\"owner\":\"creator\"}");
+
+        // missing key results in empty response
+        let value = raw("peer", b"foo").unwrap().unwrap();
+        assert_eq!(value, Binary::default());
+
+        // unregistered contracts are handled by the wasm handler
+        match raw("other", b"config") {
+            SystemResult::Err(SystemError::NoSuchContract { addr }) => assert_eq!(addr, "other"),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+
     #[test]
     fn bank_querier_all_balances() {
         let addr = String::from("foobar");
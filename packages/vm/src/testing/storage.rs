@@ -1,15 +1,19 @@
-use std::collections::BTreeMap;
 #[cfg(feature = "iterator")]
 use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 #[cfg(feature = "iterator")]
 use std::ops::{Bound, RangeBounds};
 
 #[cfg(feature = "iterator")]
 use cosmwasm_std::{Order, Record};
 
+use crate::instance::Instance;
+#[cfg(feature = "iterator")]
+use crate::prefixed_storage::namespace_upper_bound;
 #[cfg(feature = "iterator")]
 use crate::BackendError;
-use crate::{BackendResult, GasInfo, Storage};
+use crate::{BackendApi, BackendResult, GasInfo, Querier, Storage};
 
 #[cfg(feature = "iterator")]
 const GAS_COST_LAST_ITERATION: u64 = 37;
@@ -18,19 +22,34 @@ const GAS_COST_LAST_ITERATION: u64 = 37;
 const GAS_COST_RANGE: u64 = 11;
 
 #[cfg(feature = "iterator")]
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 struct Iter {
     data: Vec<Record>,
     position: usize,
 }
 
-#[derive(Default, Debug)]
+/// The entries of a [`MockStorage`]
+type Data = BTreeMap<Vec<u8>, Vec<u8>>;
+
+#[derive(Default, Debug, Clone)]
 pub struct MockStorage {
-    data: BTreeMap<Vec<u8>, Vec<u8>>,
+    data: Data,
     #[cfg(feature = "iterator")]
     iterators: HashMap<u32, Iter>,
+    #[cfg(feature = "iterator")]
+    last_iterator_id: u32,
+    /// Open snapshots from oldest to newest along with their IDs
+    snapshots: Vec<(u64, Data)>,
+    next_snapshot_id: u64,
 }
 
+/// A handle to a state of a [`MockStorage`], created by [`MockStorage::snapshot`].
+///
+/// Snapshots can be nested like the cache contexts a chain creates for each message
+/// and submessage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageSnapshot(u64);
+
 impl MockStorage {
     pub fn new() -> Self {
         MockStorage::default()
@@ -56,6 +75,148 @@ impl MockStorage {
         }
         (Ok(out), total)
     }
+
+    /// Remembers the current state such that it can be restored using [`MockStorage::rollback_to`],
+    /// e.g. to revert the changes of a failed message the way a chain does.
+    pub fn snapshot(&mut self) -> StorageSnapshot {
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+        self.snapshots.push((id, self.data.clone()));
+        StorageSnapshot(id)
+    }
+
+    /// Restores the state at the time the snapshot was taken. The snapshot and all
+    /// snapshots taken after it are closed.
+    ///
+    /// Panics if the snapshot was already closed.
+    pub fn rollback_to(&mut self, snapshot: StorageSnapshot) {
+        let position = self.snapshot_position(snapshot);
+        let (_, data) = self.snapshots.drain(position..).next().unwrap();
+        self.data = data;
+    }
+
+    /// Keeps all changes made since the most recent open snapshot and closes it.
+    /// Changes are then reverted if an older snapshot is rolled back.
+    ///
+    /// Panics if there is no open snapshot.
+    pub fn commit(&mut self) {
+        self.snapshots
+            .pop()
+            .expect("Testing error: No open snapshot to commit");
+    }
+
+    /// The number of snapshots that were neither rolled back nor committed
+    pub fn open_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    fn snapshot_position(&self, snapshot: StorageSnapshot) -> usize {
+        self.snapshots
+            .iter()
+            .position(|(id, _)| *id == snapshot.0)
+            .expect("Testing error: Snapshot was already rolled back or committed")
+    }
+
+    /// Returns all entries sorted by key
+    pub fn dump(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.data
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// The changes between two states of a [`MockStorage`], e.g. to assert exactly which
+/// keys a call touched. The `Display` implementation lists all changes with hex encoded
+/// keys and values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageDiff {
+    /// Keys that only exist after, with their values
+    pub added: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Keys with different values, with the values before and after
+    pub changed: BTreeMap<Vec<u8>, (Vec<u8>, Vec<u8>)>,
+    /// Keys that only exist before, with their values
+    pub removed: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl StorageDiff {
+    pub fn new(before: &MockStorage, after: &MockStorage) -> Self {
+        let mut diff = StorageDiff::default();
+        for (key, value) in &before.data {
+            match after.data.get(key) {
+                None => {
+                    diff.removed.insert(key.clone(), value.clone());
+                }
+                Some(new_value) if new_value != value => {
+                    diff.changed
+                        .insert(key.clone(), (value.clone(), new_value.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, value) in &after.data {
+            if !before.data.contains_key(key) {
+                diff.added.insert(key.clone(), value.clone());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// All added, changed and removed keys
+    pub fn keys(&self) -> BTreeSet<Vec<u8>> {
+        self.added
+            .keys()
+            .chain(self.changed.keys())
+            .chain(self.removed.keys())
+            .cloned()
+            .collect()
+    }
+}
+
+impl fmt::Display for StorageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.added {
+            writeln!(f, "+ {}: {}", hex::encode(key), hex::encode(value))?;
+        }
+        for (key, (before, after)) in &self.changed {
+            writeln!(
+                f,
+                "~ {}: {} -> {}",
+                hex::encode(key),
+                hex::encode(before),
+                hex::encode(after)
+            )?;
+        }
+        for (key, value) in &self.removed {
+            writeln!(f, "- {}: {}", hex::encode(key), hex::encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the given call and returns its result along with the changes it made to
+/// the storage of the instance.
+pub fn with_storage_diff<A, Q, T>(
+    instance: &mut Instance<A, MockStorage, Q>,
+    call: impl FnOnce(&mut Instance<A, MockStorage, Q>) -> T,
+) -> (T, StorageDiff)
+where
+    A: BackendApi + 'static,
+    Q: Querier + 'static,
+{
+    let snapshot = |instance: &mut Instance<A, MockStorage, Q>| {
+        instance
+            .with_storage(|storage| Ok(storage.clone()))
+            .expect("Testing error: Could not access storage")
+    };
+    let before = snapshot(instance);
+    let result = call(instance);
+    let after = snapshot(instance);
+    (result, StorageDiff::new(&before, &after))
 }
 
 impl Storage for MockStorage {
@@ -70,26 +231,48 @@ impl Storage for MockStorage {
         start: Option<&[u8]>,
         end: Option<&[u8]>,
         order: Order,
+    ) -> BackendResult<u32> {
+        self.range_with_limit(start, end, order, u32::MAX)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn range_with_limit(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+        limit: u32,
     ) -> BackendResult<u32> {
         let gas_info = GasInfo::with_externally_used(GAS_COST_RANGE);
         let bounds = range_bounds(start, end);
+        let limit = limit as usize;
 
         let values: Vec<Record> = match (bounds.start_bound(), bounds.end_bound()) {
             // BTreeMap.range panics if range is start > end.
             // However, this cases represent just empty range and we treat it as such.
             (Bound::Included(start), Bound::Excluded(end)) if start > end => Vec::new(),
             _ => match order {
-                Order::Ascending => self.data.range(bounds).map(clone_item).collect(),
-                Order::Descending => self.data.range(bounds).rev().map(clone_item).collect(),
+                Order::Ascending => self
+                    .data
+                    .range(bounds)
+                    .take(limit)
+                    .map(clone_item)
+                    .collect(),
+                Order::Descending => self
+                    .data
+                    .range(bounds)
+                    .rev()
+                    .take(limit)
+                    .map(clone_item)
+                    .collect(),
             },
         };
 
-        let last_id: u32 = self
-            .iterators
-            .len()
-            .try_into()
+        let new_id = self
+            .last_iterator_id
+            .checked_add(1)
             .expect("Found more iterator IDs than supported");
-        let new_id = last_id + 1;
+        self.last_iterator_id = new_id;
         let iter = Iter {
             data: values,
             position: 0,
@@ -124,6 +307,17 @@ impl Storage for MockStorage {
         (Ok(value), gas_info)
     }
 
+    #[cfg(feature = "iterator")]
+    fn close_iterator(&mut self, iterator_id: u32) -> BackendResult<()> {
+        match self.iterators.remove(&iterator_id) {
+            Some(_) => (Ok(()), GasInfo::free()),
+            None => (
+                Err(BackendError::iterator_does_not_exist(iterator_id)),
+                GasInfo::free(),
+            ),
+        }
+    }
+
     fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()> {
         self.data.insert(key.to_vec(), value.to_vec());
         let gas_info = GasInfo::with_externally_used((key.len() + value.len()) as u64);
@@ -156,6 +350,70 @@ fn clone_item(item_ref: BTreeMapRecordRef) -> Record {
     (key.clone(), value.clone())
 }
 
+/// Reads the keys of all entries in the given range of the storage, using the same bounds as
+/// [`Storage::scan`], and releases the iterator afterwards.
+///
+/// Panics if the storage returns an error.
+#[cfg(feature = "iterator")]
+pub fn scan_keys<S: Storage + ?Sized>(
+    storage: &mut S,
+    start: Option<&[u8]>,
+    end: Option<&[u8]>,
+    order: Order,
+) -> Vec<Vec<u8>> {
+    let iterator_id = storage
+        .scan(start, end, order)
+        .0
+        .expect("Testing error: Could not create iterator");
+    let mut keys = vec![];
+    while let Some((key, _)) = storage
+        .next(iterator_id)
+        .0
+        .expect("Testing error: Could not read from iterator")
+    {
+        keys.push(key);
+    }
+    storage
+        .close_iterator(iterator_id)
+        .0
+        .expect("Testing error: Could not close iterator");
+    keys
+}
+
+/// Returns the keys of `sorted` that are in the given range in the given order, using the
+/// same bounds as [`Storage::scan`]. The keys are found by binary search, so they must be
+/// sorted in ascending order.
+///
+/// This serves as the expected result when testing scans over a storage containing these keys.
+#[cfg(feature = "iterator")]
+pub fn expected_keys(
+    sorted: &[&[u8]],
+    start: Option<&[u8]>,
+    end: Option<&[u8]>,
+    order: Order,
+) -> Vec<Vec<u8>> {
+    let from = start.map_or(0, |start| sorted.partition_point(|key| *key < start));
+    let to = end.map_or(sorted.len(), |end| sorted.partition_point(|key| *key < end));
+    let mut keys: Vec<Vec<u8>> = sorted
+        .get(from..to.max(from))
+        .unwrap_or_default()
+        .iter()
+        .map(|key| key.to_vec())
+        .collect();
+    if matches!(order, Order::Descending) {
+        keys.reverse();
+    }
+    keys
+}
+
+/// Returns the start and end of the range of all keys starting with the given prefix,
+/// which can be passed to [`Storage::scan`]. The end is `None` if the prefix is empty or
+/// consists of 0xFF bytes only.
+#[cfg(feature = "iterator")]
+pub fn prefix_range(prefix: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+    (prefix.to_vec(), namespace_upper_bound(prefix))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,6 +427,145 @@ mod tests {
         assert_eq!(None, store.get(b"food").0.unwrap());
     }
 
+    #[test]
+    fn dump_works() {
+        let mut store = MockStorage::new();
+        assert_eq!(store.dump(), vec![]);
+        store.set(b"foo", b"bar").0.unwrap();
+        store.set(b"bar", b"baz").0.unwrap();
+        assert_eq!(
+            store.dump(),
+            vec![
+                (b"bar".to_vec(), b"baz".to_vec()),
+                (b"foo".to_vec(), b"bar".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn rollback_to_restores_snapshot() {
+        let mut store = MockStorage::new();
+        store.set(b"foo", b"bar").0.unwrap();
+
+        let snapshot = store.snapshot();
+        store.set(b"foo", b"changed").0.unwrap();
+        store.set(b"new", b"value").0.unwrap();
+        store.remove(b"foo").0.unwrap();
+        assert_eq!(store.open_snapshots(), 1);
+
+        store.rollback_to(snapshot);
+        assert_eq!(store.dump(), vec![(b"foo".to_vec(), b"bar".to_vec())]);
+        assert_eq!(store.open_snapshots(), 0);
+    }
+
+    #[test]
+    fn nested_snapshots_work() {
+        let mut store = MockStorage::new();
+        let outer = store.snapshot();
+        store.set(b"outer", b"1").0.unwrap();
+
+        // committed inner changes are kept
+        store.snapshot();
+        store.set(b"inner", b"2").0.unwrap();
+        store.commit();
+        assert_eq!(store.get(b"inner").0.unwrap(), Some(b"2".to_vec()));
+
+        // rolled back inner changes are dropped
+        let inner = store.snapshot();
+        store.set(b"failed", b"3").0.unwrap();
+        store.rollback_to(inner);
+        assert_eq!(store.get(b"failed").0.unwrap(), None);
+        assert_eq!(store.open_snapshots(), 1);
+
+        // rolling back the outer snapshot reverts committed inner changes too
+        store.snapshot();
+        store.set(b"open", b"4").0.unwrap();
+        store.rollback_to(outer);
+        assert_eq!(store.dump(), vec![]);
+        assert_eq!(store.open_snapshots(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Snapshot was already rolled back or committed")]
+    fn rollback_to_panics_for_closed_snapshot() {
+        let mut store = MockStorage::new();
+        let snapshot = store.snapshot();
+        store.rollback_to(snapshot);
+        store.snapshot();
+        store.rollback_to(snapshot);
+    }
+
+    #[test]
+    #[should_panic(expected = "No open snapshot to commit")]
+    fn commit_panics_without_snapshot() {
+        let mut store = MockStorage::new();
+        store.commit();
+    }
+
+    #[test]
+    fn storage_diff_works() {
+        let mut before = MockStorage::new();
+        before.set(b"same", b"value").0.unwrap();
+        before.set(b"changed", b"old").0.unwrap();
+        before.set(b"removed", b"gone").0.unwrap();
+        let mut after = before.clone();
+        after.set(b"changed", b"new").0.unwrap();
+        after.remove(b"removed").0.unwrap();
+        after.set(b"added", b"\x01").0.unwrap();
+
+        let diff = StorageDiff::new(&before, &after);
+        assert_eq!(
+            diff.added,
+            BTreeMap::from([(b"added".to_vec(), b"\x01".to_vec())])
+        );
+        assert_eq!(
+            diff.changed,
+            BTreeMap::from([(b"changed".to_vec(), (b"old".to_vec(), b"new".to_vec()))])
+        );
+        assert_eq!(
+            diff.removed,
+            BTreeMap::from([(b"removed".to_vec(), b"gone".to_vec())])
+        );
+        assert_eq!(
+            diff.keys(),
+            BTreeSet::from([b"added".to_vec(), b"changed".to_vec(), b"removed".to_vec()])
+        );
+        assert_eq!(
+            diff.to_string(),
+            "+ 6164646564: 01\n~ 6368616e676564: 6f6c64 -> 6e6577\n- 72656d6f766564: 676f6e65\n"
+        );
+
+        let diff = StorageDiff::new(&after, &after);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+    }
+
+    #[test]
+    fn with_storage_diff_works() {
+        use crate::testing::{instantiate, migrate, mock_env, mock_info, mock_instance};
+        use cosmwasm_std::{ContractResult, Empty, Response};
+        use serde_json::json;
+
+        static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+        let mut instance = mock_instance(CONTRACT, &[]);
+
+        let msg = json!({"verifier": "verifies", "beneficiary": "benefits"});
+        let (result, diff) = with_storage_diff(&mut instance, |instance| {
+            instantiate(instance, mock_env(), mock_info("creator", &[]), msg)
+        });
+        let _: Response<Empty> = result.unwrap();
+        assert_eq!(diff.added.keys().collect::<Vec<_>>(), vec![b"config"]);
+        assert!(diff.changed.is_empty());
+
+        let (result, diff): (ContractResult<Response<Empty>>, _) =
+            with_storage_diff(&mut instance, |instance| {
+                migrate(instance, mock_env(), json!({"verifier": "someone else"}))
+            });
+        result.unwrap();
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.keys(), BTreeSet::from([b"config".to_vec()]));
+    }
+
     #[test]
     fn delete() {
         let mut store = MockStorage::new();
@@ -180,6 +577,129 @@ mod tests {
         assert_eq!(Some(b"bank".to_vec()), store.get(b"food").0.unwrap());
     }
 
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_with_limit_works() {
+        let mut store = MockStorage::new();
+        store.set(b"ant", b"hill").0.unwrap();
+        store.set(b"foo", b"bar").0.unwrap();
+        store.set(b"ze", b"bra").0.unwrap();
+
+        let id = store
+            .range_with_limit(None, None, Order::Descending, 2)
+            .0
+            .unwrap();
+        let elements = store.all(id).0.unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                (b"ze".to_vec(), b"bra".to_vec()),
+                (b"foo".to_vec(), b"bar".to_vec()),
+            ]
+        );
+
+        let id = store
+            .range_with_limit(Some(b"b"), None, Order::Ascending, 0)
+            .0
+            .unwrap();
+        assert_eq!(store.all(id).0.unwrap(), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn range_count_works() {
+        let mut store = MockStorage::new();
+        assert_eq!(store.range_count(None, None).0.unwrap(), 0);
+
+        store.set(b"ant", b"hill").0.unwrap();
+        store.set(b"foo", b"bar").0.unwrap();
+        store.set(b"ze", b"bra").0.unwrap();
+
+        assert_eq!(store.range_count(None, None).0.unwrap(), 3);
+        assert_eq!(store.range_count(Some(b"b"), None).0.unwrap(), 2);
+        assert_eq!(store.range_count(None, Some(b"foo")).0.unwrap(), 1);
+        assert_eq!(
+            store.range_count(Some(b"foo"), Some(b"foo\0")).0.unwrap(),
+            1
+        );
+        assert_eq!(store.range_count(Some(b"zz"), Some(b"a")).0.unwrap(), 0);
+        // the iterators are released
+        assert!(store.iterators.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_keys_works() {
+        let mut store = MockStorage::new();
+        for key in [b"a".as_slice(), b"a/1", b"a/2", b"b"] {
+            store.set(key, b"value").0.unwrap();
+        }
+
+        let (start, end) = prefix_range(b"a/");
+        assert_eq!(
+            scan_keys(&mut store, Some(&start), end.as_deref(), Order::Descending),
+            vec![b"a/2".to_vec(), b"a/1".to_vec()]
+        );
+        assert_eq!(
+            scan_keys(&mut store, None, None, Order::Ascending),
+            expected_keys(&[b"a", b"a/1", b"a/2", b"b"], None, None, Order::Ascending)
+        );
+        assert!(store.iterators.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn expected_keys_works() {
+        let sorted: &[&[u8]] = &[b"a", b"b", b"c"];
+        assert_eq!(
+            expected_keys(sorted, Some(b"b"), None, Order::Ascending),
+            vec![b"b".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(
+            expected_keys(sorted, None, Some(b"b"), Order::Descending),
+            vec![b"a".to_vec()]
+        );
+        assert_eq!(
+            expected_keys(sorted, Some(b"a0"), Some(b"c"), Order::Descending),
+            vec![b"b".to_vec()]
+        );
+        // reversed bounds
+        assert_eq!(
+            expected_keys(sorted, Some(b"c"), Some(b"a"), Order::Ascending),
+            Vec::<Vec<u8>>::new()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn prefix_range_works() {
+        assert_eq!(prefix_range(b"a/"), (b"a/".to_vec(), Some(b"a0".to_vec())));
+        assert_eq!(prefix_range(&[0xFF]), (vec![0xFF], None));
+        assert_eq!(prefix_range(b""), (vec![], None));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn close_iterator_works() {
+        let mut store = MockStorage::new();
+        store.set(b"foo", b"bar").0.unwrap();
+
+        let id1 = store.scan(None, None, Order::Ascending).0.unwrap();
+        let id2 = store.scan(None, None, Order::Ascending).0.unwrap();
+        store.close_iterator(id1).0.unwrap();
+        match store.next(id1).0.unwrap_err() {
+            BackendError::IteratorDoesNotExist { id, .. } => assert_eq!(id, id1),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        store.close_iterator(id1).0.unwrap_err();
+        assert_eq!(store.next(id2).0.unwrap().unwrap().0, b"foo".to_vec());
+
+        // IDs are not reused
+        let id3 = store.scan(None, None, Order::Ascending).0.unwrap();
+        assert_ne!(id3, id1);
+        assert_ne!(id3, id2);
+    }
+
     #[test]
     #[cfg(feature = "iterator")]
     fn iterator() {
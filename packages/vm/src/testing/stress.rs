@@ -0,0 +1,238 @@
+//! Concurrent execution of many instances of one contract.
+//!
+//! This helps validating that a contract and the VM behave deterministically when many
+//! instances of the same module run in parallel, and gives a rough idea of the throughput
+//! of a machine for capacity planning.
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::environment::panic_payload_message;
+use crate::instance::{Instance, InstanceOptions, InstanceParams};
+use crate::size::Size;
+use crate::wasm_backend::compile;
+
+use super::instance::mock_instance_options;
+use super::mock::{mock_backend, MockApi};
+use super::querier::MockQuerier;
+use super::storage::MockStorage;
+
+/// Configuration of [`stress`]
+#[derive(Copy, Clone, Debug)]
+pub struct StressOptions {
+    /// The number of instances, each of which runs on its own thread with its own backend
+    pub instances: usize,
+    pub instance_options: InstanceOptions,
+    pub memory_limit: Option<Size>,
+}
+
+impl Default for StressOptions {
+    fn default() -> Self {
+        let (instance_options, memory_limit) = mock_instance_options();
+        StressOptions {
+            instances: 8,
+            instance_options,
+            memory_limit,
+        }
+    }
+}
+
+/// The observable outcome of the scenario on one instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StressOutcome<R> {
+    /// The value returned by the scenario
+    pub result: R,
+    /// Gas used by all calls of the scenario
+    pub gas_used: u64,
+    /// The storage of the instance after the scenario, sorted by key
+    pub storage: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// The result of [`stress`]
+#[derive(Debug)]
+pub struct StressReport<R> {
+    pub instances: usize,
+    /// The number of contract calls made by all instances, including failed ones
+    pub calls: u64,
+    /// The wall time from starting the instances until all of them finished the scenario.
+    /// This is not deterministic and should only be used for capacity planning.
+    pub duration: Duration,
+    /// The total time the instances waited for the instantiation lock of the module, which
+    /// is shared like in [`Cache`](crate::Cache). This is not deterministic either.
+    pub lock_wait: Duration,
+    /// The outcome of the first instance, which the other outcomes are compared to
+    pub expected: StressOutcome<R>,
+    /// The index and outcome of every instance whose outcome differs from `expected`
+    pub nondeterministic: Vec<(usize, StressOutcome<R>)>,
+}
+
+impl<R> StressReport<R> {
+    /// Returns true if all instances produced the same outcome
+    pub fn is_deterministic(&self) -> bool {
+        self.nondeterministic.is_empty()
+    }
+
+    /// The number of contract calls per second of wall time across all instances
+    pub fn calls_per_second(&self) -> f64 {
+        self.calls as f64 / self.duration.as_secs_f64()
+    }
+}
+
+/// Runs `scenario` on [`StressOptions::instances`] instances of the given contract
+/// concurrently and compares their outcomes.
+///
+/// The contract is compiled once and every instance gets its own [`mock_backend`], such that
+/// all instances start from the same state. A deterministic contract must then return the same
+/// result, use the same gas and write the same storage on every instance.
+/// Panics if the scenario panics on any instance.
+pub fn stress<R, F>(wasm: &[u8], options: StressOptions, scenario: F) -> StressReport<R>
+where
+    R: PartialEq + Send + 'static,
+    F: Fn(&mut Instance<MockApi, MockStorage, MockQuerier>) -> R + Send + Sync + 'static,
+{
+    assert!(
+        options.instances > 0,
+        "Testing error: At least one instance is required"
+    );
+    let module =
+        compile(wasm, options.memory_limit, &[]).expect("Testing error: Could not compile Wasm");
+    let scenario = Arc::new(scenario);
+    let instantiation_lock = Arc::new(Mutex::new(()));
+    let barrier = Arc::new(Barrier::new(options.instances));
+
+    let start = Instant::now();
+    let threads: Vec<_> = (0..options.instances)
+        .map(|_| {
+            let module = module.clone();
+            let scenario = Arc::clone(&scenario);
+            let instantiation_lock = Arc::clone(&instantiation_lock);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                let wait_start = Instant::now();
+                let guard = instantiation_lock.lock().unwrap();
+                let lock_wait = wait_start.elapsed();
+                let mut instance = Instance::from_module(
+                    &module,
+                    mock_backend(&[]),
+                    options.instance_options,
+                    InstanceParams::default(),
+                )
+                .expect("Testing error: Could not create instance");
+                drop(guard);
+
+                instance.start_session();
+                let result = scenario(&mut instance);
+                let session = instance.end_session().unwrap();
+                let storage = instance
+                    .with_storage(|storage| Ok(storage.dump()))
+                    .expect("Testing error: Could not access storage");
+                let outcome = StressOutcome {
+                    result,
+                    gas_used: session.gas_used,
+                    storage,
+                };
+                (outcome, session.calls, lock_wait)
+            })
+        })
+        .collect();
+    let results: Vec<_> = threads
+        .into_iter()
+        .enumerate()
+        .map(|(index, thread)| {
+            thread.join().unwrap_or_else(|panic| {
+                panic!(
+                    "Testing error: Scenario panicked on instance {}: {}",
+                    index,
+                    panic_payload_message(&*panic)
+                )
+            })
+        })
+        .collect();
+    let duration = start.elapsed();
+
+    let calls = results.iter().map(|(_, calls, _)| calls).sum();
+    let lock_wait = results.iter().map(|(_, _, lock_wait)| *lock_wait).sum();
+    let mut outcomes = results.into_iter().map(|(outcome, _, _)| outcome);
+    let expected = outcomes.next().unwrap();
+    let nondeterministic = outcomes
+        .enumerate()
+        .map(|(index, outcome)| (index + 1, outcome))
+        .filter(|(_, outcome)| *outcome != expected)
+        .collect();
+    StressReport {
+        instances: options.instances,
+        calls,
+        duration,
+        lock_wait,
+        expected,
+        nondeterministic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{instantiate, mock_env, mock_info, query};
+    use cosmwasm_std::{ContractResult, Empty, Response};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+
+    fn instantiate_and_query(instance: &mut Instance<MockApi, MockStorage, MockQuerier>) -> bool {
+        let msg = json!({"verifier": "verifies", "beneficiary": "benefits"});
+        let res: ContractResult<Response<Empty>> =
+            instantiate(instance, mock_env(), mock_info("creator", &[]), msg);
+        res.unwrap();
+        query(instance, mock_env(), json!({"verifier": {}})).is_ok()
+    }
+
+    #[test]
+    fn stress_works() {
+        let options = StressOptions {
+            instances: 4,
+            ..StressOptions::default()
+        };
+        let report = stress(CONTRACT, options, instantiate_and_query);
+        assert_eq!(report.instances, 4);
+        assert_eq!(report.calls, 8);
+        assert!(report.is_deterministic(), "{:?}", report.nondeterministic);
+        assert!(report.expected.result);
+        assert!(report.expected.gas_used > 0);
+        assert_eq!(report.expected.storage.len(), 1);
+        assert!(report.calls_per_second() > 0.0);
+    }
+
+    #[test]
+    fn stress_detects_nondeterministic_results() {
+        let options = StressOptions {
+            instances: 3,
+            ..StressOptions::default()
+        };
+        let counter = Arc::new(AtomicUsize::new(0));
+        let report = stress(CONTRACT, options, move |instance| {
+            instantiate_and_query(instance);
+            counter.fetch_add(1, Ordering::SeqCst) == 0
+        });
+        assert!(!report.is_deterministic());
+        // either the first instance differs from both others or one of the others differs
+        let differing = report.nondeterministic.len();
+        assert!(differing == 1 || differing == 2, "{}", differing);
+        for (index, outcome) in &report.nondeterministic {
+            assert!(*index > 0 && *index < 3);
+            assert_ne!(outcome.result, report.expected.result);
+            assert_eq!(outcome.storage, report.expected.storage);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Scenario panicked on instance")]
+    fn stress_names_panicking_instance() {
+        let options = StressOptions {
+            instances: 2,
+            ..StressOptions::default()
+        };
+        stress(CONTRACT, options, |_| panic!("boom"));
+    }
+}
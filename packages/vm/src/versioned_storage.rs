@@ -0,0 +1,290 @@
+#[cfg(feature = "iterator")]
+use cosmwasm_std::{Order, Record};
+
+use crate::backend::{BackendError, BackendResult, GasInfo, Storage, VersionedStorage};
+
+/// A [`Storage`] that reads the state of a [`VersionedStorage`] at a fixed block height.
+///
+/// Historical state cannot be changed, so all writes fail. Since queries are executed with
+/// read-only storage, this never affects contract queries. Create this via
+/// [`Backend::at_height`](crate::Backend::at_height).
+#[derive(Debug, Clone)]
+pub struct HeightPinnedStorage<S: VersionedStorage> {
+    inner: S,
+    height: u64,
+}
+
+impl<S: VersionedStorage> HeightPinnedStorage<S> {
+    pub fn new(inner: S, height: u64) -> Self {
+        HeightPinnedStorage { inner, height }
+    }
+
+    /// The block height the state is read at
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: VersionedStorage> Storage for HeightPinnedStorage<S> {
+    fn get(&self, key: &[u8]) -> BackendResult<Option<Vec<u8>>> {
+        self.inner.get_at_height(key, self.height)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn scan(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> BackendResult<u32> {
+        self.inner.range_at_height(start, end, order, self.height)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>> {
+        self.inner.next(iterator_id)
+    }
+
+    #[cfg(feature = "iterator")]
+    fn close_iterator(&mut self, iterator_id: u32) -> BackendResult<()> {
+        self.inner.close_iterator(iterator_id)
+    }
+
+    fn set(&mut self, _key: &[u8], _value: &[u8]) -> BackendResult<()> {
+        (Err(historical_write_error(self.height)), GasInfo::free())
+    }
+
+    fn remove(&mut self, _key: &[u8]) -> BackendResult<()> {
+        (Err(historical_write_error(self.height)), GasInfo::free())
+    }
+}
+
+fn historical_write_error(height: u64) -> BackendError {
+    BackendError::unknown(format!(
+        "Cannot write to the historical state at height {}",
+        height
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    #[cfg(feature = "iterator")]
+    use std::collections::HashMap;
+
+    use cosmwasm_std::{coins, Empty};
+
+    use crate::calls::{call_instantiate, call_query};
+    use crate::testing::{
+        mock_env, mock_info, mock_instance, mock_instance_options, MockQuerier, MockStorage,
+    };
+    use crate::{Backend, Instance};
+
+    static CONTRACT: &[u8] = include_bytes!("../testdata/hackatom.wasm");
+
+    /// A versioned storage keeping a full snapshot for every height that was committed
+    #[derive(Default)]
+    struct SnapshotStorage {
+        head: MockStorage,
+        snapshots: BTreeMap<u64, MockStorage>,
+        /// Maps iterator IDs to the snapshot height and the ID within that snapshot
+        #[cfg(feature = "iterator")]
+        iterators: HashMap<u32, (u64, u32)>,
+    }
+
+    impl SnapshotStorage {
+        fn commit(&mut self, height: u64) {
+            self.snapshots.insert(height, self.head.clone());
+        }
+
+        fn snapshot(&self, height: u64) -> Option<&MockStorage> {
+            self.snapshots.range(..=height).next_back().map(|(_, s)| s)
+        }
+    }
+
+    impl Storage for SnapshotStorage {
+        fn get(&self, key: &[u8]) -> BackendResult<Option<Vec<u8>>> {
+            self.head.get(key)
+        }
+
+        #[cfg(feature = "iterator")]
+        fn scan(
+            &mut self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+        ) -> BackendResult<u32> {
+            self.head.scan(start, end, order)
+        }
+
+        #[cfg(feature = "iterator")]
+        fn next(&mut self, iterator_id: u32) -> BackendResult<Option<Record>> {
+            match self.iterators.get(&iterator_id) {
+                Some((height, inner_id)) => self
+                    .snapshots
+                    .range_mut(..=*height)
+                    .next_back()
+                    .unwrap()
+                    .1
+                    .next(*inner_id),
+                None => self.head.next(iterator_id),
+            }
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) -> BackendResult<()> {
+            self.head.set(key, value)
+        }
+
+        fn remove(&mut self, key: &[u8]) -> BackendResult<()> {
+            self.head.remove(key)
+        }
+    }
+
+    impl VersionedStorage for SnapshotStorage {
+        fn get_at_height(&self, key: &[u8], height: u64) -> BackendResult<Option<Vec<u8>>> {
+            match self.snapshot(height) {
+                Some(snapshot) => snapshot.get(key),
+                None => (Ok(None), GasInfo::free()),
+            }
+        }
+
+        #[cfg(feature = "iterator")]
+        fn range_at_height(
+            &mut self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+            height: u64,
+        ) -> BackendResult<u32> {
+            let (snapshot_height, snapshot) = self
+                .snapshots
+                .range_mut(..=height)
+                .next_back()
+                .expect("No snapshot at this height");
+            let snapshot_height = *snapshot_height;
+            let (result, gas_info) = snapshot.scan(start, end, order);
+            let result = result.map(|inner_id| {
+                // IDs start at 1000 to avoid collisions with the head's iterators
+                let id = 1000 + self.iterators.len() as u32;
+                self.iterators.insert(id, (snapshot_height, inner_id));
+                id
+            });
+            (result, gas_info)
+        }
+    }
+
+    fn make_storage() -> SnapshotStorage {
+        let mut storage = SnapshotStorage::default();
+        storage.set(b"foo", b"one").0.unwrap();
+        storage.set(b"gar", b"one").0.unwrap();
+        storage.commit(10);
+        storage.set(b"foo", b"two").0.unwrap();
+        storage.remove(b"gar").0.unwrap();
+        storage.commit(20);
+        storage
+    }
+
+    #[test]
+    fn get_reads_at_pinned_height() {
+        let storage = make_storage();
+        let pinned = HeightPinnedStorage::new(storage, 15);
+        assert_eq!(pinned.height(), 15);
+        assert_eq!(pinned.get(b"foo").0.unwrap(), Some(b"one".to_vec()));
+        assert_eq!(pinned.get(b"gar").0.unwrap(), Some(b"one".to_vec()));
+
+        let pinned = HeightPinnedStorage::new(pinned.into_inner(), 20);
+        assert_eq!(pinned.get(b"foo").0.unwrap(), Some(b"two".to_vec()));
+        assert_eq!(pinned.get(b"gar").0.unwrap(), None);
+
+        let pinned = HeightPinnedStorage::new(pinned.into_inner(), 5);
+        assert_eq!(pinned.get(b"foo").0.unwrap(), None);
+    }
+
+    #[test]
+    fn writes_fail() {
+        let mut pinned = HeightPinnedStorage::new(make_storage(), 15);
+        match pinned.set(b"foo", b"three").0.unwrap_err() {
+            BackendError::Unknown { msg } => {
+                assert_eq!(msg, "Cannot write to the historical state at height 15")
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        match pinned.remove(b"foo").0.unwrap_err() {
+            BackendError::Unknown { .. } => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+        assert_eq!(pinned.get(b"foo").0.unwrap(), Some(b"one".to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "iterator")]
+    fn scan_reads_at_pinned_height() {
+        let mut pinned = HeightPinnedStorage::new(make_storage(), 15);
+        let id = pinned.scan(None, None, Order::Ascending).0.unwrap();
+        assert_eq!(
+            pinned.next(id).0.unwrap(),
+            Some((b"foo".to_vec(), b"one".to_vec()))
+        );
+        assert_eq!(
+            pinned.next(id).0.unwrap(),
+            Some((b"gar".to_vec(), b"one".to_vec()))
+        );
+        assert_eq!(pinned.next(id).0.unwrap(), None);
+
+        let mut pinned = HeightPinnedStorage::new(pinned.into_inner(), 25);
+        let id = pinned.scan(None, None, Order::Descending).0.unwrap();
+        assert_eq!(
+            pinned.next(id).0.unwrap(),
+            Some((b"foo".to_vec(), b"two".to_vec()))
+        );
+        assert_eq!(pinned.next(id).0.unwrap(), None);
+    }
+
+    #[test]
+    fn instance_queries_at_pinned_height() {
+        // instantiate at height 10
+        let mut instance = mock_instance(CONTRACT, &[]);
+        let info = mock_info("creator", &coins(1000, "earth"));
+        let msg = br#"{"verifier": "verifies", "beneficiary": "benefits"}"#;
+        call_instantiate::<_, _, _, Empty>(&mut instance, &mock_env(), &info, msg)
+            .unwrap()
+            .unwrap();
+        let backend = instance.recycle().unwrap().backend;
+        let mut storage = SnapshotStorage {
+            head: backend.storage,
+            ..SnapshotStorage::default()
+        };
+        storage.commit(10);
+
+        // state is gone at height 20
+        storage.head = MockStorage::new();
+        storage.commit(20);
+
+        let (options, memory_limit) = mock_instance_options();
+        let query_at = |storage: SnapshotStorage, height: u64| {
+            let backend = Backend {
+                api: backend.api.clone(),
+                storage,
+                querier: MockQuerier::<Empty>::new(&[]),
+            };
+            let mut instance =
+                Instance::from_code(CONTRACT, backend.at_height(height), options, memory_limit)
+                    .unwrap();
+            let result = call_query(&mut instance, &mock_env(), br#"{"verifier":{}}"#).unwrap();
+            let storage = instance.recycle().unwrap().backend.storage.into_inner();
+            (result, storage)
+        };
+
+        let (result, storage) = query_at(storage, 15);
+        let data = result.unwrap();
+        assert_eq!(data.as_slice(), br#"{"verifier":"verifies"}"#);
+
+        let (result, _) = query_at(storage, 20);
+        assert!(result.is_err());
+    }
+}
@@ -2,10 +2,12 @@ use std::sync::Arc;
 
 use wasmer::{Module, ModuleMiddleware};
 
+use crate::checksum::Checksum;
 use crate::errors::VmResult;
 use crate::size::Size;
 
-use super::store::make_compile_time_store;
+use super::fingerprint::Fingerprinter;
+use super::store::{make_compile_time_store, make_compile_time_store_with_observers};
 
 /// Compiles a given Wasm bytecode into a module.
 /// The given memory limit (in bytes) is used when memories are created.
@@ -21,6 +23,26 @@ pub fn compile(
     Ok(module)
 }
 
+/// Compiles a given Wasm bytecode into a module like [`compile`] and computes a fingerprint
+/// of the instrumented code, i.e. of the operators after all middlewares were applied.
+///
+/// The fingerprint is deterministic for a given Wasm bytecode and VM version, such that
+/// nodes can compare it to ensure they run identical instrumented code.
+pub fn compile_with_fingerprint(
+    code: &[u8],
+    memory_limit: Option<Size>,
+) -> VmResult<(Module, Checksum)> {
+    let fingerprinter = Arc::new(Fingerprinter::new());
+    let store = make_compile_time_store_with_observers(
+        memory_limit,
+        &[],
+        &[fingerprinter.clone() as Arc<dyn ModuleMiddleware>],
+    );
+    let module = Module::new(&store, code)?;
+    let fingerprint = fingerprinter.fingerprint(&module);
+    Ok((module, fingerprint))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -32,4 +54,59 @@ mod tests {
         let err = compile(CONTRACT, None, &[]).unwrap_err();
         assert!(err.to_string().contains("Float operator detected:"));
     }
+
+    #[test]
+    fn compile_with_fingerprint_is_deterministic() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (func (export "add") (param i32 i32) (result i32)
+                    local.get 0
+                    local.get 1
+                    i32.add)
+            )"#,
+        )
+        .unwrap();
+        let (_module, fingerprint) = compile_with_fingerprint(&wasm, None).unwrap();
+        let (_module, again) = compile_with_fingerprint(&wasm, None).unwrap();
+        assert_eq!(fingerprint, again);
+        assert_ne!(fingerprint, Checksum::generate(&wasm));
+
+        let other = wat::parse_str(
+            r#"(module
+                (func (export "add") (param i32 i32) (result i32)
+                    local.get 1
+                    local.get 0
+                    i32.add)
+            )"#,
+        )
+        .unwrap();
+        let (_module, other_fingerprint) = compile_with_fingerprint(&other, None).unwrap();
+        assert_ne!(fingerprint, other_fingerprint);
+    }
+
+    #[test]
+    fn compile_with_fingerprint_covers_instrumentation() {
+        let wasm = wat::parse_str(r#"(module (func (export "nop") nop))"#).unwrap();
+        let (module, _fingerprint) = compile_with_fingerprint(&wasm, None).unwrap();
+        // the globals added by the metering middleware are part of the hashed module info
+        assert!(module
+            .exports()
+            .any(|export| export.name() == "wasmer_metering_remaining_points"));
+
+        // the operator stream seen by the fingerprinter includes the metering code
+        let fingerprinter = Arc::new(Fingerprinter::new());
+        let store = make_compile_time_store_with_observers(
+            None,
+            &[],
+            &[fingerprinter.clone() as Arc<dyn ModuleMiddleware>],
+        );
+        let module = Module::new(&store, &wasm).unwrap();
+        let instrumented = fingerprinter.fingerprint(&module);
+
+        let fingerprinter = Arc::new(Fingerprinter::new());
+        let store =
+            make_compile_time_store(None, &[fingerprinter.clone() as Arc<dyn ModuleMiddleware>]);
+        let module = Module::new(&store, &wasm).unwrap();
+        assert_ne!(fingerprinter.fingerprint(&module), instrumented);
+    }
 }
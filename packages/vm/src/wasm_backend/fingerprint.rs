@@ -0,0 +1,685 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use loupe::MemoryUsage;
+use sha2::{Digest, Sha256};
+use wasmer::wasmparser::{MemoryImmediate, Operator, Type as OperatorType, TypeOrFuncType};
+use wasmer::{
+    ExportIndex, ExternType, FunctionMiddleware, FunctionType, GlobalInit, GlobalType,
+    LocalFunctionIndex, MemoryType, MiddlewareError, MiddlewareReaderState, Module,
+    ModuleMiddleware, Mutability, TableType, Type,
+};
+
+use crate::checksum::Checksum;
+
+/// The name used in errors
+const MIDDLEWARE_NAME: &str = "Fingerprinter";
+
+/// A middleware that hashes the operators of all functions as they are passed to the compiler.
+///
+/// It must be added after all middlewares that instrument the code (see
+/// [`make_compile_time_store_with_observers`](super::store::make_compile_time_store_with_observers)).
+/// A `Fingerprinter` must only be used for compiling a single module.
+#[derive(Debug, Default, MemoryUsage)]
+pub struct Fingerprinter {
+    /// The digests of the operators of each local function by function index.
+    /// Those are collected in a map since functions may be compiled in parallel.
+    #[loupe(skip)]
+    functions: Arc<Mutex<BTreeMap<u32, [u8; 32]>>>,
+}
+
+impl Fingerprinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Combines the digests of all functions with the instrumented module info of the given
+    /// module, which must be the one compiled with this middleware.
+    pub fn fingerprint(&self, module: &Module) -> Checksum {
+        let mut hasher = Sha256::new();
+        update_section(&mut hasher, &encode_module_info(module));
+        for (index, digest) in self.functions.lock().unwrap().iter() {
+            hasher.update(index.to_be_bytes());
+            hasher.update(digest);
+        }
+        Checksum::from(<[u8; 32]>::from(hasher.finalize()))
+    }
+}
+
+/// Adds length prefixed data to the hash such that sections cannot be shifted into each other
+fn update_section(hasher: &mut Sha256, data: &[u8]) {
+    hasher.update((data.len() as u64).to_be_bytes());
+    hasher.update(data);
+}
+
+/// Encodes the module fields that are deterministic for a given Wasm blob,
+/// i.e. no module ID and no hash maps.
+///
+/// The encoding follows the Wasm binary format where the field has a counterpart there.
+/// All lists are prefixed with their length.
+fn encode_module_info(module: &Module) -> Vec<u8> {
+    let info = module.info();
+    let mut out = Vec::new();
+
+    write_u32(&mut out, info.imports.len() as u32);
+    for import in module.imports() {
+        write_name(&mut out, import.module());
+        write_name(&mut out, import.name());
+        match import.ty() {
+            ExternType::Function(ty) => {
+                out.push(0x00);
+                write_function_type(&mut out, ty);
+            }
+            ExternType::Table(ty) => {
+                out.push(0x01);
+                write_table_type(&mut out, ty);
+            }
+            ExternType::Memory(ty) => {
+                out.push(0x02);
+                write_memory_type(&mut out, ty);
+            }
+            ExternType::Global(ty) => {
+                out.push(0x03);
+                write_global_type(&mut out, ty);
+            }
+        }
+    }
+
+    write_u32(&mut out, info.exports.len() as u32);
+    for (name, export) in info.exports.iter() {
+        write_name(&mut out, name);
+        let (kind, index) = match export {
+            ExportIndex::Function(index) => (0x00, index.as_u32()),
+            ExportIndex::Table(index) => (0x01, index.as_u32()),
+            ExportIndex::Memory(index) => (0x02, index.as_u32()),
+            ExportIndex::Global(index) => (0x03, index.as_u32()),
+        };
+        out.push(kind);
+        write_u32(&mut out, index);
+    }
+
+    match info.start_function {
+        Some(index) => {
+            out.push(0x01);
+            write_u32(&mut out, index.as_u32());
+        }
+        None => out.push(0x00),
+    }
+
+    write_u32(&mut out, info.signatures.len() as u32);
+    for signature in info.signatures.values() {
+        write_function_type(&mut out, signature);
+    }
+
+    write_u32(&mut out, info.functions.len() as u32);
+    for signature_index in info.functions.values() {
+        write_u32(&mut out, signature_index.as_u32());
+    }
+
+    write_u32(&mut out, info.tables.len() as u32);
+    for table in info.tables.values() {
+        write_table_type(&mut out, table);
+    }
+
+    write_u32(&mut out, info.memories.len() as u32);
+    for memory in info.memories.values() {
+        write_memory_type(&mut out, memory);
+    }
+
+    write_u32(&mut out, info.globals.len() as u32);
+    for global in info.globals.values() {
+        write_global_type(&mut out, global);
+    }
+
+    write_u32(&mut out, info.global_initializers.len() as u32);
+    for initializer in info.global_initializers.values() {
+        match initializer {
+            GlobalInit::I32Const(value) => {
+                out.push(0x41);
+                write_i64(&mut out, (*value).into());
+            }
+            GlobalInit::I64Const(value) => {
+                out.push(0x42);
+                write_i64(&mut out, *value);
+            }
+            GlobalInit::F32Const(value) => {
+                out.push(0x43);
+                out.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            GlobalInit::F64Const(value) => {
+                out.push(0x44);
+                out.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            GlobalInit::V128Const(value) => {
+                out.extend_from_slice(&[0xfd, 0x0c]);
+                out.extend_from_slice(value.bytes());
+            }
+            GlobalInit::GetGlobal(index) => {
+                out.push(0x23);
+                write_u32(&mut out, index.as_u32());
+            }
+            GlobalInit::RefNullConst => out.push(0xd0),
+            GlobalInit::RefFunc(index) => {
+                out.push(0xd2);
+                write_u32(&mut out, index.as_u32());
+            }
+        }
+    }
+
+    out
+}
+
+fn value_type_code(ty: Type) -> u8 {
+    match ty {
+        Type::I32 => 0x7f,
+        Type::I64 => 0x7e,
+        Type::F32 => 0x7d,
+        Type::F64 => 0x7c,
+        Type::V128 => 0x7b,
+        Type::FuncRef => 0x70,
+        Type::ExternRef => 0x6f,
+    }
+}
+
+fn write_function_type(out: &mut Vec<u8>, ty: &FunctionType) {
+    out.push(0x60);
+    write_u32(out, ty.params().len() as u32);
+    for param in ty.params() {
+        out.push(value_type_code(*param));
+    }
+    write_u32(out, ty.results().len() as u32);
+    for result in ty.results() {
+        out.push(value_type_code(*result));
+    }
+}
+
+fn write_table_type(out: &mut Vec<u8>, ty: &TableType) {
+    out.push(value_type_code(ty.ty));
+    write_limits(out, ty.minimum, ty.maximum, false);
+}
+
+fn write_memory_type(out: &mut Vec<u8>, ty: &MemoryType) {
+    let maximum = ty.maximum.map(|pages| pages.0);
+    write_limits(out, ty.minimum.0, maximum, ty.shared);
+}
+
+fn write_global_type(out: &mut Vec<u8>, ty: &GlobalType) {
+    out.push(value_type_code(ty.ty));
+    out.push(match ty.mutability {
+        Mutability::Const => 0x00,
+        Mutability::Var => 0x01,
+    });
+}
+
+fn write_limits(out: &mut Vec<u8>, minimum: u32, maximum: Option<u32>, shared: bool) {
+    let flags = u8::from(maximum.is_some()) | u8::from(shared) << 1;
+    out.push(flags);
+    write_u32(out, minimum);
+    if let Some(maximum) = maximum {
+        write_u32(out, maximum);
+    }
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_u32(out, name.len() as u32);
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Writes an unsigned LEB128 value
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    write_u64(out, value.into())
+}
+
+/// Writes an unsigned LEB128 value
+fn write_u64(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Writes a signed LEB128 value
+fn write_i64(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_block_type(out: &mut Vec<u8>, ty: TypeOrFuncType) {
+    match ty {
+        TypeOrFuncType::Type(ty) => out.push(match ty {
+            OperatorType::I32 => 0x7f,
+            OperatorType::I64 => 0x7e,
+            OperatorType::F32 => 0x7d,
+            OperatorType::F64 => 0x7c,
+            OperatorType::V128 => 0x7b,
+            OperatorType::FuncRef => 0x70,
+            OperatorType::ExternRef => 0x6f,
+            OperatorType::ExnRef => 0x68,
+            OperatorType::Func => 0x60,
+            OperatorType::EmptyBlockType => 0x40,
+        }),
+        TypeOrFuncType::FuncType(index) => write_i64(out, index.into()),
+    }
+}
+
+fn write_memarg(out: &mut Vec<u8>, memarg: MemoryImmediate) {
+    if memarg.memory == 0 {
+        write_u32(out, memarg.align.into());
+    } else {
+        // multi-memory encoding
+        write_u32(out, u32::from(memarg.align) | 1 << 6);
+        write_u32(out, memarg.memory);
+    }
+    write_u64(out, memarg.offset);
+}
+
+/// Encodes an operator in the Wasm binary format.
+///
+/// Only operators accepted by the [`Gatekeeper`](super::gatekeeper::Gatekeeper) are supported,
+/// since no others reach the compiler.
+fn encode_operator(out: &mut Vec<u8>, operator: &Operator) -> Result<(), MiddlewareError> {
+    let opcode: u8 = match operator {
+        Operator::Unreachable => 0x00,
+        Operator::Nop => 0x01,
+        Operator::Block { ty } => {
+            out.push(0x02);
+            write_block_type(out, *ty);
+            return Ok(());
+        }
+        Operator::Loop { ty } => {
+            out.push(0x03);
+            write_block_type(out, *ty);
+            return Ok(());
+        }
+        Operator::If { ty } => {
+            out.push(0x04);
+            write_block_type(out, *ty);
+            return Ok(());
+        }
+        Operator::Else => 0x05,
+        Operator::End => 0x0b,
+        Operator::Br { relative_depth } => {
+            out.push(0x0c);
+            write_u32(out, *relative_depth);
+            return Ok(());
+        }
+        Operator::BrIf { relative_depth } => {
+            out.push(0x0d);
+            write_u32(out, *relative_depth);
+            return Ok(());
+        }
+        Operator::BrTable { table } => {
+            out.push(0x0e);
+            write_u32(out, table.len());
+            for target in table.targets() {
+                let target = target.map_err(|err| {
+                    MiddlewareError::new(MIDDLEWARE_NAME, format!("Invalid br_table: {}", err))
+                })?;
+                write_u32(out, target);
+            }
+            write_u32(out, table.default());
+            return Ok(());
+        }
+        Operator::Return => 0x0f,
+        Operator::Call { function_index } => {
+            out.push(0x10);
+            write_u32(out, *function_index);
+            return Ok(());
+        }
+        Operator::CallIndirect { index, table_index } => {
+            out.push(0x11);
+            write_u32(out, *index);
+            write_u32(out, *table_index);
+            return Ok(());
+        }
+        Operator::Drop => 0x1a,
+        Operator::Select => 0x1b,
+        Operator::LocalGet { local_index }
+        | Operator::LocalSet { local_index }
+        | Operator::LocalTee { local_index } => {
+            out.push(match operator {
+                Operator::LocalGet { .. } => 0x20,
+                Operator::LocalSet { .. } => 0x21,
+                _ => 0x22,
+            });
+            write_u32(out, *local_index);
+            return Ok(());
+        }
+        Operator::GlobalGet { global_index } | Operator::GlobalSet { global_index } => {
+            out.push(match operator {
+                Operator::GlobalGet { .. } => 0x23,
+                _ => 0x24,
+            });
+            write_u32(out, *global_index);
+            return Ok(());
+        }
+        Operator::I32Load { memarg }
+        | Operator::I64Load { memarg }
+        | Operator::I32Load8S { memarg }
+        | Operator::I32Load8U { memarg }
+        | Operator::I32Load16S { memarg }
+        | Operator::I32Load16U { memarg }
+        | Operator::I64Load8S { memarg }
+        | Operator::I64Load8U { memarg }
+        | Operator::I64Load16S { memarg }
+        | Operator::I64Load16U { memarg }
+        | Operator::I64Load32S { memarg }
+        | Operator::I64Load32U { memarg }
+        | Operator::I32Store { memarg }
+        | Operator::I64Store { memarg }
+        | Operator::I32Store8 { memarg }
+        | Operator::I32Store16 { memarg }
+        | Operator::I64Store8 { memarg }
+        | Operator::I64Store16 { memarg }
+        | Operator::I64Store32 { memarg } => {
+            out.push(match operator {
+                Operator::I32Load { .. } => 0x28,
+                Operator::I64Load { .. } => 0x29,
+                Operator::I32Load8S { .. } => 0x2c,
+                Operator::I32Load8U { .. } => 0x2d,
+                Operator::I32Load16S { .. } => 0x2e,
+                Operator::I32Load16U { .. } => 0x2f,
+                Operator::I64Load8S { .. } => 0x30,
+                Operator::I64Load8U { .. } => 0x31,
+                Operator::I64Load16S { .. } => 0x32,
+                Operator::I64Load16U { .. } => 0x33,
+                Operator::I64Load32S { .. } => 0x34,
+                Operator::I64Load32U { .. } => 0x35,
+                Operator::I32Store { .. } => 0x36,
+                Operator::I64Store { .. } => 0x37,
+                Operator::I32Store8 { .. } => 0x3a,
+                Operator::I32Store16 { .. } => 0x3b,
+                Operator::I64Store8 { .. } => 0x3c,
+                Operator::I64Store16 { .. } => 0x3d,
+                _ => 0x3e,
+            });
+            write_memarg(out, *memarg);
+            return Ok(());
+        }
+        Operator::MemorySize { mem, .. } | Operator::MemoryGrow { mem, .. } => {
+            out.push(match operator {
+                Operator::MemorySize { .. } => 0x3f,
+                _ => 0x40,
+            });
+            write_u32(out, *mem);
+            return Ok(());
+        }
+        Operator::I32Const { value } => {
+            out.push(0x41);
+            write_i64(out, (*value).into());
+            return Ok(());
+        }
+        Operator::I64Const { value } => {
+            out.push(0x42);
+            write_i64(out, *value);
+            return Ok(());
+        }
+        Operator::I32Eqz => 0x45,
+        Operator::I32Eq => 0x46,
+        Operator::I32Ne => 0x47,
+        Operator::I32LtS => 0x48,
+        Operator::I32LtU => 0x49,
+        Operator::I32GtS => 0x4a,
+        Operator::I32GtU => 0x4b,
+        Operator::I32LeS => 0x4c,
+        Operator::I32LeU => 0x4d,
+        Operator::I32GeS => 0x4e,
+        Operator::I32GeU => 0x4f,
+        Operator::I64Eqz => 0x50,
+        Operator::I64Eq => 0x51,
+        Operator::I64Ne => 0x52,
+        Operator::I64LtS => 0x53,
+        Operator::I64LtU => 0x54,
+        Operator::I64GtS => 0x55,
+        Operator::I64GtU => 0x56,
+        Operator::I64LeS => 0x57,
+        Operator::I64LeU => 0x58,
+        Operator::I64GeS => 0x59,
+        Operator::I64GeU => 0x5a,
+        Operator::I32Clz => 0x67,
+        Operator::I32Ctz => 0x68,
+        Operator::I32Popcnt => 0x69,
+        Operator::I32Add => 0x6a,
+        Operator::I32Sub => 0x6b,
+        Operator::I32Mul => 0x6c,
+        Operator::I32DivS => 0x6d,
+        Operator::I32DivU => 0x6e,
+        Operator::I32RemS => 0x6f,
+        Operator::I32RemU => 0x70,
+        Operator::I32And => 0x71,
+        Operator::I32Or => 0x72,
+        Operator::I32Xor => 0x73,
+        Operator::I32Shl => 0x74,
+        Operator::I32ShrS => 0x75,
+        Operator::I32ShrU => 0x76,
+        Operator::I32Rotl => 0x77,
+        Operator::I32Rotr => 0x78,
+        Operator::I64Clz => 0x79,
+        Operator::I64Ctz => 0x7a,
+        Operator::I64Popcnt => 0x7b,
+        Operator::I64Add => 0x7c,
+        Operator::I64Sub => 0x7d,
+        Operator::I64Mul => 0x7e,
+        Operator::I64DivS => 0x7f,
+        Operator::I64DivU => 0x80,
+        Operator::I64RemS => 0x81,
+        Operator::I64RemU => 0x82,
+        Operator::I64And => 0x83,
+        Operator::I64Or => 0x84,
+        Operator::I64Xor => 0x85,
+        Operator::I64Shl => 0x86,
+        Operator::I64ShrS => 0x87,
+        Operator::I64ShrU => 0x88,
+        Operator::I64Rotl => 0x89,
+        Operator::I64Rotr => 0x8a,
+        Operator::I32WrapI64 => 0xa7,
+        Operator::I64ExtendI32S => 0xac,
+        Operator::I64ExtendI32U => 0xad,
+        Operator::I32Extend8S => 0xc0,
+        Operator::I32Extend16S => 0xc1,
+        Operator::I64Extend8S => 0xc2,
+        Operator::I64Extend16S => 0xc3,
+        Operator::I64Extend32S => 0xc4,
+        _ => {
+            let msg = format!("Operator not supported by the fingerprint: {:?}", operator);
+            return Err(MiddlewareError::new(MIDDLEWARE_NAME, msg));
+        }
+    };
+    out.push(opcode);
+    Ok(())
+}
+
+impl ModuleMiddleware for Fingerprinter {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionFingerprinter {
+            index: local_function_index.as_u32(),
+            hasher: Sha256::new(),
+            buffer: Vec::new(),
+            functions: self.functions.clone(),
+        })
+    }
+}
+
+struct FunctionFingerprinter {
+    index: u32,
+    hasher: Sha256,
+    /// Reused for encoding operators
+    buffer: Vec<u8>,
+    functions: Arc<Mutex<BTreeMap<u32, [u8; 32]>>>,
+}
+
+impl fmt::Debug for FunctionFingerprinter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionFingerprinter")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionFingerprinter {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // The Wasm binary encoding is self-delimiting, so no length prefix is needed
+        self.buffer.clear();
+        encode_operator(&mut self.buffer, &operator)?;
+        self.hasher.update(&self.buffer);
+        state.push_operator(operator);
+        Ok(())
+    }
+}
+
+/// There is no callback for the end of a function, so the digest is stored when the
+/// function middleware is dropped after the function was compiled.
+impl Drop for FunctionFingerprinter {
+    fn drop(&mut self) {
+        let digest = std::mem::take(&mut self.hasher).finalize();
+        self.functions
+            .lock()
+            .unwrap()
+            .insert(self.index, digest.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmer::wasmparser::{Parser, Payload};
+
+    use crate::wasm_backend::compile;
+
+    static CONTRACT: &[u8] = include_bytes!("../../testdata/hackatom.wasm");
+
+    /// Checks that every operator of the given Wasm encodes to its original bytes
+    fn assert_operators_roundtrip(wasm: &[u8]) {
+        let mut count = 0;
+        for payload in Parser::new(0).parse_all(wasm) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut reader = body.get_operators_reader().unwrap();
+                let mut operators = vec![];
+                while !reader.eof() {
+                    operators.push(reader.read_with_offset().unwrap());
+                }
+                let ends = operators
+                    .iter()
+                    .skip(1)
+                    .map(|(_, offset)| *offset)
+                    .chain([body.range().end]);
+                for ((operator, start), end) in operators.iter().zip(ends) {
+                    let mut encoded = vec![];
+                    encode_operator(&mut encoded, operator).unwrap();
+                    assert_eq!(encoded, &wasm[*start..end], "{:?}", operator);
+                    count += 1;
+                }
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn encode_operator_matches_wasm_binary_format() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (type $t (func (param i32) (result i32)))
+            (memory 1)
+            (table 2 funcref)
+            (global $g (mut i64) (i64.const 0))
+            (func $f (type $t) (param $a i32) (result i32) (local $b i64)
+                (block $outer (result i32)
+                    (loop $inner
+                        (br_table $outer $inner $outer (local.get $a) (local.get $a))
+                    )
+                    (i32.const 0)
+                )
+                (if (result i32) (i32.eqz)
+                    (then (i32.load8_u offset=300 (i32.const -2147483648)))
+                    (else (i32.load align=1 (i32.const 2147483647)))
+                )
+                (block (param i32) (result i32) (i32.extend8_s))
+                (local.set $b (i64.extend_i32_u))
+                (global.set $g (i64.rotl (global.get $g) (i64.const -9223372036854775808)))
+                (i64.store32 offset=4294967295 (i32.const 64) (local.get $b))
+                (drop (memory.grow (memory.size)))
+                (call_indirect (type $t) (i32.const 1) (i32.const 0))
+                (return (call $f (i32.wrap_i64 (i64.const 9223372036854775807))))
+            )
+        )"#,
+        )
+        .unwrap();
+        assert_operators_roundtrip(&wasm);
+        assert_operators_roundtrip(CONTRACT);
+    }
+
+    #[test]
+    fn encode_operator_rejects_operators_not_accepted_by_the_gatekeeper() {
+        let mut encoded = vec![];
+        let err = encode_operator(&mut encoded, &Operator::F32Add).unwrap_err();
+        assert!(err.to_string().contains("not supported by the fingerprint"));
+    }
+
+    #[test]
+    fn encode_module_info_works() {
+        let wasm = wat::parse_str(
+            r#"(module
+            (import "env" "abort" (func (param i32)))
+            (memory 3 17)
+            (export "memory" (memory 0))
+        )"#,
+        )
+        .unwrap();
+        let encoded = encode_module_info(&compile(&wasm, None, &[]).unwrap());
+        let mut expected = vec![];
+        // imports
+        expected.extend([1, 3, b'e', b'n', b'v', 5, b'a', b'b', b'o', b'r', b't']);
+        expected.extend([0x00, 0x60, 1, 0x7f, 0]);
+        // exports, including the globals added by the metering middleware
+        expected.extend([3, 6, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0]);
+        expected.push(32);
+        expected.extend(b"wasmer_metering_remaining_points");
+        expected.extend([0x03, 0]);
+        expected.push(32);
+        expected.extend(b"wasmer_metering_points_exhausted");
+        expected.extend([0x03, 1]);
+        // start function, signatures and functions
+        expected.extend([0x00, 1, 0x60, 1, 0x7f, 0, 1, 0]);
+        // tables and memories
+        expected.extend([0, 1, 0x01, 3, 17]);
+        // globals and their initializers
+        expected.extend([2, 0x7e, 0x01, 0x7f, 0x01, 2, 0x42, 0, 0x41, 0]);
+        assert_eq!(encoded, expected);
+
+        // the export name is part of the encoding
+        let renamed = wat::parse_str(
+            r#"(module
+            (import "env" "abort" (func (param i32)))
+            (memory 3 17)
+            (export "memorx" (memory 0))
+        )"#,
+        )
+        .unwrap();
+        assert_ne!(
+            encode_module_info(&compile(&renamed, None, &[]).unwrap()),
+            encoded
+        );
+    }
+}
@@ -1,8 +1,9 @@
 mod compile;
+mod fingerprint;
 mod gatekeeper;
 mod limiting_tunables;
 mod store;
 
-pub use compile::compile;
+pub use compile::{compile, compile_with_fingerprint};
 pub use limiting_tunables::LimitingTunables;
-pub use store::make_runtime_store;
+pub use store::{make_runtime_store, GAS_PER_OPERATION};
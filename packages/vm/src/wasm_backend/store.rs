@@ -20,6 +20,9 @@ use super::limiting_tunables::LimitingTunables;
 /// https://github.com/WebAssembly/memory64/blob/master/proposals/memory64/Overview.md
 const MAX_WASM_PAGES: u32 = 65536;
 
+/// The gas charged by the metering middleware for each Wasm operation
+pub const GAS_PER_OPERATION: u64 = 150_000;
+
 fn cost(_operator: &Operator) -> u64 {
     // A flat fee for each operation
     // The target is 1 Teragas per millisecond (see GAS.md).
@@ -27,7 +30,7 @@ fn cost(_operator: &Operator) -> u64 {
     // In https://github.com/CosmWasm/cosmwasm/pull/1042 a profiler is developed to
     // identify runtime differences between different Wasm operation, but this is not yet
     // precise enough to derive insights from it.
-    150_000
+    GAS_PER_OPERATION
 }
 
 /// Created a store with the default compiler and the given memory limit (in bytes).
@@ -35,6 +38,16 @@ fn cost(_operator: &Operator) -> u64 {
 pub fn make_compile_time_store(
     memory_limit: Option<Size>,
     middlewares: &[Arc<dyn ModuleMiddleware>],
+) -> Store {
+    make_compile_time_store_with_observers(memory_limit, middlewares, &[])
+}
+
+/// Like [`make_compile_time_store`] but adds `observers` after all instrumentation
+/// middlewares, such that they see the operators that are actually compiled.
+pub fn make_compile_time_store_with_observers(
+    memory_limit: Option<Size>,
+    middlewares: &[Arc<dyn ModuleMiddleware>],
+    observers: &[Arc<dyn ModuleMiddleware>],
 ) -> Store {
     let gas_limit = 0;
     let deterministic = Arc::new(Gatekeeper::default());
@@ -48,6 +61,9 @@ pub fn make_compile_time_store(
         }
         config.push_middleware(deterministic);
         config.push_middleware(metering);
+        for observer in observers {
+            config.push_middleware(observer.clone());
+        }
         let engine = Universal::new(config).engine();
         make_store_with_engine(&engine, memory_limit)
     }
@@ -60,6 +76,9 @@ pub fn make_compile_time_store(
         }
         config.push_middleware(deterministic);
         config.push_middleware(metering);
+        for observer in observers {
+            config.push_middleware(observer.clone());
+        }
         let engine = Universal::new(config).engine();
         make_store_with_engine(&engine, memory_limit)
     }